@@ -0,0 +1,261 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use std::fmt;
+use std::str::FromStr;
+
+use tracelogging::_internal::MetaReader;
+use tracelogging::InType;
+use tracelogging::Level;
+use tracelogging::Opcode;
+use tracelogging::OutType;
+
+/// The providers and events of a schema artifact written by
+/// `tracelogging::write_schema_artifact`, sorted by name.
+#[derive(Debug, Default)]
+pub struct Artifact {
+    pub providers: Vec<ProviderInfo>,
+    pub events: Vec<EventInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ProviderInfo {
+    pub name: String,
+    pub id: String,
+    pub symbol: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EventInfo {
+    pub provider: String,
+    pub provider_id: String,
+    pub name: String,
+    pub id: u16,
+    pub version: u8,
+    pub channel: u8,
+    pub level: u8,
+    pub opcode: u8,
+    pub task: u16,
+    pub keyword: u64,
+    pub tag: u32,
+    pub fields: Vec<FieldInfo>,
+}
+
+/// A field definition. Struct fields are followed by their members, which have a
+/// greater `depth`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FieldInfo {
+    pub name: String,
+    pub depth: usize,
+    pub in_type: u8,
+    pub out_type: u8,
+    pub flags: u8,
+    pub tag: u32,
+    pub constant_count: u16,
+}
+
+/// The error returned when an artifact cannot be parsed: the line number (starting at
+/// 1) and a description.
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+impl Artifact {
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, "tracelogging-schema 1")) => {}
+            _ => {
+                return Err(ParseError {
+                    line: 1,
+                    message: "expected \"tracelogging-schema 1\"",
+                })
+            }
+        }
+
+        let mut artifact = Artifact::default();
+        for (index, line) in lines {
+            let error = |message| ParseError {
+                line: index + 1,
+                message,
+            };
+            let values: Vec<&str> = line.split('\t').collect();
+            match values[0] {
+                "" => {}
+                "provider" if values.len() == 4 => artifact.providers.push(ProviderInfo {
+                    name: values[1].to_string(),
+                    id: values[2].to_string(),
+                    symbol: values[3].to_string(),
+                }),
+                "event" if values.len() == 11 => {
+                    let keyword = values[9]
+                        .strip_prefix("0x")
+                        .and_then(|hex| u64::from_str_radix(hex, 16).ok())
+                        .ok_or_else(|| error("invalid keyword"))?;
+                    let meta = from_hex(values[10]).ok_or_else(|| error("invalid metadata"))?;
+                    let (tag, name, fields) =
+                        parse_meta(&meta).ok_or_else(|| error("invalid metadata"))?;
+                    let event = EventInfo {
+                        provider: values[1].to_string(),
+                        provider_id: values[2].to_string(),
+                        name,
+                        id: number(values[3], index)?,
+                        version: number(values[4], index)?,
+                        channel: number(values[5], index)?,
+                        level: number(values[6], index)?,
+                        opcode: number(values[7], index)?,
+                        task: number(values[8], index)?,
+                        keyword,
+                        tag,
+                        fields,
+                    };
+
+                    // An event written from several places is listed once per place.
+                    if !artifact.events.contains(&event) {
+                        artifact.events.push(event);
+                    }
+                }
+                _ => return Err(error("unrecognized line")),
+            }
+        }
+
+        artifact.providers.sort();
+        artifact.providers.dedup();
+        artifact.events.sort();
+        return Ok(artifact);
+    }
+
+    /// Returns the names and ids of the providers that have events or definitions, in
+    /// name order.
+    pub fn provider_names(&self) -> Vec<(&str, &str)> {
+        let mut names: Vec<(&str, &str)> = self
+            .providers
+            .iter()
+            .map(|p| (p.name.as_str(), p.id.as_str()))
+            .chain(
+                self.events
+                    .iter()
+                    .map(|e| (e.provider.as_str(), e.provider_id.as_str())),
+            )
+            .collect();
+        names.sort();
+        names.dedup();
+        return names;
+    }
+}
+
+impl EventInfo {
+    /// Returns the event's attributes, e.g. `id=0 version=0 level=Verbose ...`.
+    pub fn attributes(&self) -> String {
+        let mut text = format!(
+            "id={} version={} level={} opcode={} task={} keyword=0x{:x}",
+            self.id,
+            self.version,
+            Level::from_int(self.level),
+            Opcode::from_int(self.opcode),
+            self.task,
+            self.keyword
+        );
+        if self.channel != 11 {
+            text.push_str(&format!(" channel={}", self.channel));
+        }
+        if self.tag != 0 {
+            text.push_str(&format!(" tag=0x{:x}", self.tag));
+        }
+        return text;
+    }
+}
+
+impl FieldInfo {
+    pub fn in_type(&self) -> InType {
+        return InType::from_int(self.in_type);
+    }
+
+    pub fn is_struct(&self) -> bool {
+        return self.in_type() == InType::Struct && self.flags != InType::CustomFlag;
+    }
+
+    /// Returns the field's type, e.g. `U32`, `Str8[]`, `U8[4] (Hex)`, or `Struct(2)`.
+    pub fn type_name(&self) -> String {
+        let mut text = if self.flags == InType::CustomFlag {
+            "Custom".to_string()
+        } else if self.is_struct() {
+            format!("Struct({})", self.out_type)
+        } else {
+            self.in_type().to_string()
+        };
+
+        match self.flags {
+            InType::VariableCountFlag => text.push_str("[]"),
+            InType::ConstantCountFlag => text.push_str(&format!("[{}]", self.constant_count)),
+            _ => {}
+        }
+
+        if !self.is_struct() && self.out_type != 0 {
+            text.push_str(&format!(" ({})", OutType::from_int(self.out_type)));
+        }
+        if self.tag != 0 {
+            text.push_str(&format!(" tag=0x{:x}", self.tag));
+        }
+        return text;
+    }
+}
+
+fn parse_meta(meta: &[u8]) -> Option<(u32, String, Vec<FieldInfo>)> {
+    let mut reader = MetaReader::new(meta);
+    let (tag, name) = reader.read_event().ok()?;
+
+    // Remaining member counts of the enclosing structs.
+    let mut remaining: Vec<u8> = Vec::new();
+    let mut fields = Vec::new();
+    while !reader.at_end() {
+        let field = reader.read_field().ok()?;
+        while remaining.last() == Some(&0) {
+            remaining.pop();
+        }
+        if let Some(count) = remaining.last_mut() {
+            *count -= 1;
+        }
+
+        fields.push(FieldInfo {
+            name: field.name.to_string(),
+            depth: remaining.len(),
+            in_type: field.in_type.as_int(),
+            out_type: field.out_type,
+            flags: field.flags,
+            tag: field.tag,
+            constant_count: field.constant_count,
+        });
+        if field.is_struct() {
+            remaining.push(field.out_type);
+        }
+    }
+
+    return Some((tag, name.to_string(), fields));
+}
+
+fn number<T: FromStr>(value: &str, index: usize) -> Result<T, ParseError> {
+    return value.parse().map_err(|_| ParseError {
+        line: index + 1,
+        message: "invalid number",
+    });
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    return (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect();
+}
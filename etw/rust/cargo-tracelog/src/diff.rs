@@ -0,0 +1,91 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::artifact::Artifact;
+use crate::artifact::EventInfo;
+
+/// Returns the differences between the events of two artifacts, or an empty string
+/// if they have the same events.
+///
+/// Events are matched by provider name and event name. For each event that was
+/// added or removed, writes a `+` or `-` line with the event's attributes. For each
+/// event whose attributes or fields changed, writes a `~` line followed by the
+/// attribute and field lines that differ.
+pub fn diff(old: &Artifact, new: &Artifact) -> String {
+    let old_events = by_name(old);
+    let new_events = by_name(new);
+    let mut out = String::new();
+
+    let mut keys: Vec<&(&str, &str)> = old_events.keys().chain(new_events.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        let (provider, name) = *key;
+        match (old_events.get(key), new_events.get(key)) {
+            (Some(old), None) => {
+                for event in old {
+                    let _ = writeln!(out, "- {}/{}: {}", provider, name, event.attributes());
+                }
+            }
+            (None, Some(new)) => {
+                for event in new {
+                    let _ = writeln!(out, "+ {}/{}: {}", provider, name, event.attributes());
+                }
+            }
+            (Some(old), Some(new)) if old != new => {
+                let _ = writeln!(out, "~ {}/{}", provider, name);
+                let old_lines = describe(old);
+                let new_lines = describe(new);
+                for line in old_lines.iter().filter(|line| !new_lines.contains(line)) {
+                    let _ = writeln!(out, "  - {}", line);
+                }
+                for line in new_lines.iter().filter(|line| !old_lines.contains(line)) {
+                    let _ = writeln!(out, "  + {}", line);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    return out;
+}
+
+fn by_name(artifact: &Artifact) -> BTreeMap<(&str, &str), Vec<&EventInfo>> {
+    let mut events: BTreeMap<(&str, &str), Vec<&EventInfo>> = BTreeMap::new();
+    for event in &artifact.events {
+        events
+            .entry((event.provider.as_str(), event.name.as_str()))
+            .or_default()
+            .push(event);
+    }
+    return events;
+}
+
+/// Returns one line for the attributes of each schema of an event, and one line per
+/// field (prefixed by the schema's attributes if the event has several schemas).
+fn describe(events: &[&EventInfo]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for event in events {
+        let attributes = event.attributes();
+        let prefix = if events.len() == 1 {
+            String::new()
+        } else {
+            format!("[{}] ", attributes)
+        };
+        lines.push(attributes.clone());
+        for (index, field) in event.fields.iter().enumerate() {
+            lines.push(format!(
+                "{}field {}: {}{}: {}",
+                prefix,
+                index,
+                "  ".repeat(field.depth),
+                field.name,
+                field.type_name()
+            ));
+        }
+    }
+    return lines;
+}
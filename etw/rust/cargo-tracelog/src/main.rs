@@ -0,0 +1,92 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! `cargo tracelog`: reads the schema artifact written by
+//! `tracelogging::write_schema_artifact` and lists the events, generates a WPR
+//! profile or an instrumentation manifest, or diffs the events of two builds.
+
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::fs;
+use std::process::exit;
+
+mod artifact;
+mod diff;
+mod profile;
+
+use artifact::Artifact;
+
+const USAGE: &str = "\
+Usage:
+    cargo tracelog list <artifact>
+    cargo tracelog wprp <artifact>
+    cargo tracelog manifest <artifact>
+    cargo tracelog diff <old-artifact> <new-artifact>
+
+<artifact> is a file written by tracelogging::write_schema_artifact (requires
+feature export_schema of the tracelogging crate) from the module that defines the
+events.
+
+diff exits with status 1 if the artifacts have different events.";
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+
+    // cargo runs `cargo-tracelog tracelog <args>` for `cargo tracelog <args>`.
+    if args.first().map(String::as_str) == Some("tracelog") {
+        args.remove(0);
+    }
+
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        ["list", path] => load(path).map(|artifact| print!("{}", list(&artifact))),
+        ["wprp", path] => load(path).map(|artifact| print!("{}", profile::wprp(&artifact))),
+        ["manifest", path] => load(path).map(|artifact| print!("{}", profile::manifest(&artifact))),
+        ["diff", old, new] => load(old).and_then(|old| {
+            let new = load(new)?;
+            let text = diff::diff(&old, &new);
+            print!("{}", text);
+            if !text.is_empty() {
+                exit(1);
+            }
+            return Ok(());
+        }),
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    };
+
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        exit(2);
+    }
+}
+
+fn load(path: &str) -> Result<Artifact, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    return Artifact::parse(&text).map_err(|e| format!("{}: {}", path, e));
+}
+
+/// Returns one line per provider, followed by one line per event of the provider
+/// and one indented line per field of the event.
+fn list(artifact: &Artifact) -> String {
+    let mut out = String::new();
+    for (name, id) in artifact.provider_names() {
+        out.push_str(&format!("{} {{{}}}\n", name, id));
+        for event in artifact.events.iter().filter(|e| e.provider == name) {
+            out.push_str(&format!("  {}: {}\n", event.name, event.attributes()));
+            for field in &event.fields {
+                out.push_str(&format!(
+                    "    {}{}: {}\n",
+                    "  ".repeat(field.depth),
+                    field.name,
+                    field.type_name()
+                ));
+            }
+        }
+    }
+    return out;
+}
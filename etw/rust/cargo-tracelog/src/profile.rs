@@ -0,0 +1,417 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use tracelogging::InType;
+use tracelogging::OutType;
+
+use crate::artifact::Artifact;
+use crate::artifact::EventInfo;
+use crate::artifact::FieldInfo;
+
+/// Returns a WPR recording profile that collects the events of every provider in the
+/// artifact, with the union of the providers' event keywords and their highest level.
+pub fn wprp(artifact: &Artifact) -> String {
+    let providers = artifact.provider_names();
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<WindowsPerformanceRecorder Version=\"1.0\">\n");
+    out.push_str("  <Profiles>\n");
+    out.push_str("    <EventCollector Id=\"EventCollector_Tracelog\" Name=\"Tracelog\">\n");
+    out.push_str("      <BufferSize Value=\"64\" />\n");
+    out.push_str("      <Buffers Value=\"32\" />\n");
+    out.push_str("    </EventCollector>\n");
+
+    for (index, (name, id)) in providers.iter().enumerate() {
+        let events = artifact.events.iter().filter(|e| e.provider == *name);
+        let keyword = events.clone().fold(0, |keyword, e| keyword | e.keyword);
+        let level = events.map(|e| e.level).max().unwrap_or(5).max(1);
+        let _ = writeln!(
+            out,
+            "    <!-- {} -->\n    <EventProvider Id=\"EventProvider_{}\" Name=\"{}\" Level=\"{}\">",
+            escape(name),
+            index,
+            id,
+            level
+        );
+        if keyword != 0 {
+            let _ = writeln!(
+                out,
+                "      <Keywords>\n        <Keyword Value=\"0x{:x}\" />\n      </Keywords>",
+                keyword
+            );
+        }
+        out.push_str("    </EventProvider>\n");
+    }
+
+    for (mode, detail) in [("File", "Verbose"), ("Memory", "Verbose")] {
+        let _ = writeln!(
+            out,
+            "    <Profile Id=\"Tracelog.{1}.{0}\" Name=\"Tracelog\" Description=\"TraceLogging providers\" LoggingMode=\"{0}\" DetailLevel=\"{1}\">",
+            mode, detail
+        );
+        out.push_str("      <Collectors>\n");
+        out.push_str("        <EventCollectorId Value=\"EventCollector_Tracelog\">\n");
+        out.push_str("          <EventProviders>\n");
+        for index in 0..providers.len() {
+            let _ = writeln!(
+                out,
+                "            <EventProviderId Value=\"EventProvider_{}\" />",
+                index
+            );
+        }
+        out.push_str("          </EventProviders>\n");
+        out.push_str("        </EventCollectorId>\n");
+        out.push_str("      </Collectors>\n");
+        out.push_str("    </Profile>\n");
+    }
+
+    out.push_str("  </Profiles>\n");
+    out.push_str("</WindowsPerformanceRecorder>\n");
+    return out;
+}
+
+/// Returns an instrumentation manifest that describes the events of every provider
+/// in the artifact.
+///
+/// A manifest identifies events by id and version, so events with id 0 are listed
+/// in a comment instead. Custom-serialized fields cannot be described and are also
+/// listed in a comment. Counted strings and arrays are described with the count or
+/// length fields that TraceLogging stores before their values. The providers'
+/// `resourceFileName` and `messageFileName` must be added before the manifest is
+/// installed.
+pub fn manifest(artifact: &Artifact) -> String {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    out.push_str("<instrumentationManifest xmlns=\"http://schemas.microsoft.com/win/2004/08/events\" xmlns:win=\"http://manifests.microsoft.com/win/2004/08/windows/events\" xmlns:xs=\"http://www.w3.org/2001/XMLSchema\">\n");
+    out.push_str("  <instrumentation>\n");
+    out.push_str("    <events>\n");
+
+    for (name, id) in artifact.provider_names() {
+        let all_events: Vec<&EventInfo> = artifact
+            .events
+            .iter()
+            .filter(|e| e.provider == name)
+            .collect();
+        let (events, unnamed): (Vec<&EventInfo>, Vec<&EventInfo>) =
+            all_events.iter().partition(|e| e.id != 0);
+
+        let _ = writeln!(
+            out,
+            "      <provider name=\"{}\" guid=\"{{{}}}\" symbol=\"{}\">",
+            escape(name),
+            id,
+            symbol(name)
+        );
+        if !unnamed.is_empty() {
+            out.push_str("        <!-- Events without an id:");
+            for event in &unnamed {
+                let _ = write!(out, " {}", escape_comment(&event.name));
+            }
+            out.push_str(" -->\n");
+        }
+
+        let keywords: BTreeSet<u64> = events
+            .iter()
+            .flat_map(|e| (0..64).map(move |bit| e.keyword & (1 << bit)))
+            .filter(|&bit| bit != 0)
+            .collect();
+        if !keywords.is_empty() {
+            out.push_str("        <keywords>\n");
+            for keyword in &keywords {
+                let _ = writeln!(
+                    out,
+                    "          <keyword name=\"Keyword_0x{:x}\" mask=\"0x{:x}\" />",
+                    keyword, keyword
+                );
+            }
+            out.push_str("        </keywords>\n");
+        }
+
+        let tasks: BTreeSet<u16> = events.iter().map(|e| e.task).filter(|&t| t != 0).collect();
+        if !tasks.is_empty() {
+            out.push_str("        <tasks>\n");
+            for task in &tasks {
+                let _ = writeln!(
+                    out,
+                    "          <task name=\"Task_{}\" value=\"{}\" />",
+                    task, task
+                );
+            }
+            out.push_str("        </tasks>\n");
+        }
+
+        let opcodes: BTreeSet<u8> = events
+            .iter()
+            .map(|e| e.opcode)
+            .filter(|&o| standard_opcode(o).is_none())
+            .collect();
+        if !opcodes.is_empty() {
+            out.push_str("        <opcodes>\n");
+            for opcode in &opcodes {
+                let _ = writeln!(
+                    out,
+                    "          <opcode name=\"Opcode_{}\" value=\"{}\" />",
+                    opcode, opcode
+                );
+            }
+            out.push_str("        </opcodes>\n");
+        }
+
+        out.push_str("        <templates>\n");
+        for event in &events {
+            let _ = writeln!(out, "          <template tid=\"{}\">", template_id(event));
+            write_template_fields(&mut out, &event.fields, 12);
+            out.push_str("          </template>\n");
+        }
+        out.push_str("        </templates>\n");
+
+        out.push_str("        <events>\n");
+        for event in &events {
+            let _ = write!(
+                out,
+                "          <event value=\"{}\" version=\"{}\" level=\"{}\" symbol=\"{}\" template=\"{}\"",
+                event.id,
+                event.version,
+                level_name(event.level),
+                event_symbol(event, &events),
+                template_id(event)
+            );
+            if event.keyword != 0 {
+                out.push_str(" keywords=\"");
+                let names: Vec<String> = (0..64)
+                    .map(|bit| event.keyword & (1 << bit))
+                    .filter(|&bit| bit != 0)
+                    .map(|bit| format!("Keyword_0x{:x}", bit))
+                    .collect();
+                out.push_str(&names.join(" "));
+                out.push('"');
+            }
+            if event.task != 0 {
+                let _ = write!(out, " task=\"Task_{}\"", event.task);
+            }
+            match standard_opcode(event.opcode) {
+                Some(opcode) => {
+                    let _ = write!(out, " opcode=\"{}\"", opcode);
+                }
+                None => {
+                    let _ = write!(out, " opcode=\"Opcode_{}\"", event.opcode);
+                }
+            }
+            out.push_str(" />\n");
+        }
+        out.push_str("        </events>\n");
+        out.push_str("      </provider>\n");
+    }
+
+    out.push_str("    </events>\n");
+    out.push_str("  </instrumentation>\n");
+    out.push_str("</instrumentationManifest>\n");
+    return out;
+}
+
+/// Writes the `<data>` and `<struct>` elements for `fields`, starting at the fields
+/// with the smallest depth.
+fn write_template_fields(out: &mut String, fields: &[FieldInfo], indent: usize) {
+    let pad = " ".repeat(indent);
+    let mut index = 0;
+    while index < fields.len() {
+        let field = &fields[index];
+        let name = escape(&field.name);
+        let members_end = index
+            + 1
+            + fields[index + 1..]
+                .iter()
+                .take_while(|member| member.depth > field.depth)
+                .count();
+
+        let count = match field.flags {
+            InType::ConstantCountFlag => format!(" count=\"{}\"", field.constant_count),
+            InType::VariableCountFlag => {
+                let _ = writeln!(
+                    out,
+                    "{}<data name=\"{}.Count\" inType=\"win:UInt16\" />",
+                    pad, name
+                );
+                format!(" count=\"{}.Count\"", name)
+            }
+            _ => String::new(),
+        };
+
+        if field.flags == InType::CustomFlag {
+            let _ = writeln!(
+                out,
+                "{}<!-- Custom-serialized field: {} -->",
+                pad,
+                escape_comment(&field.name)
+            );
+        } else if field.is_struct() {
+            let _ = writeln!(out, "{}<struct name=\"{}\"{}>", pad, name, count);
+            write_template_fields(out, &fields[index + 1..members_end], indent + 2);
+            let _ = writeln!(out, "{}</struct>", pad);
+        } else {
+            let in_type = field.in_type();
+            let mut length = String::new();
+            if in_type == InType::Binary {
+                let _ = writeln!(
+                    out,
+                    "{}<data name=\"{}.Length\" inType=\"win:UInt16\" />",
+                    pad, name
+                );
+                length = format!(" length=\"{}.Length\"", name);
+            }
+
+            let _ = write!(
+                out,
+                "{}<data name=\"{}\" inType=\"{}\"{}{}",
+                pad,
+                name,
+                in_type_name(in_type),
+                count,
+                length
+            );
+            if let Some(out_type) = out_type_name(field) {
+                let _ = write!(out, " outType=\"{}\"", out_type);
+            }
+            out.push_str(" />\n");
+        }
+
+        index = members_end;
+    }
+}
+
+/// Returns the event's symbol, with the event's version if the provider has other
+/// events with the same name, since symbols must be unique.
+fn event_symbol(event: &EventInfo, events: &[&EventInfo]) -> String {
+    if events.iter().filter(|e| e.name == event.name).count() == 1 {
+        return symbol(&event.name);
+    } else {
+        return format!("{}_V{}", symbol(&event.name), event.version);
+    }
+}
+
+fn template_id(event: &EventInfo) -> String {
+    return format!("T_{}_{}_{}", symbol(&event.name), event.id, event.version);
+}
+
+fn in_type_name(in_type: InType) -> &'static str {
+    return match in_type {
+        InType::CStr16 => "win:UnicodeString",
+        InType::CStr8 => "win:AnsiString",
+        InType::I8 => "win:Int8",
+        InType::U8 => "win:UInt8",
+        InType::I16 => "win:Int16",
+        InType::U16 => "win:UInt16",
+        InType::I32 => "win:Int32",
+        InType::U32 => "win:UInt32",
+        InType::I64 => "win:Int64",
+        InType::U64 => "win:UInt64",
+        InType::F32 => "win:Float",
+        InType::F64 => "win:Double",
+        InType::Bool32 => "win:Boolean",
+        InType::Binary => "win:Binary",
+        InType::Guid => "win:GUID",
+        InType::_HexSize_PlatformSpecific => "win:Pointer",
+        InType::FileTime => "win:FILETIME",
+        InType::SystemTime => "win:SYSTEMTIME",
+        InType::Sid => "win:SID",
+        InType::Hex32 => "win:HexInt32",
+        InType::Hex64 => "win:HexInt64",
+        InType::Str16 => "win:CountedUnicodeString",
+        InType::Str8 => "win:CountedAnsiString",
+        InType::BinaryC => "win:CountedBinary",
+        _ => "win:Binary",
+    };
+}
+
+fn out_type_name(field: &FieldInfo) -> Option<&'static str> {
+    let in_type = field.in_type();
+    return Some(match OutType::from_int(field.out_type) {
+        OutType::String => "xs:string",
+        OutType::Boolean => "xs:boolean",
+        OutType::Hex => match in_type {
+            InType::I8 | InType::U8 => "win:HexInt8",
+            InType::I16 | InType::U16 => "win:HexInt16",
+            InType::I32 | InType::U32 => "win:HexInt32",
+            InType::I64 | InType::U64 => "win:HexInt64",
+            _ => return None,
+        },
+        OutType::Pid => "win:PID",
+        OutType::Tid => "win:TID",
+        OutType::Port => "win:Port",
+        OutType::IPv4 => "win:IPv4",
+        OutType::IPv6 => "win:IPv6",
+        OutType::SocketAddress => "win:SocketAddress",
+        OutType::Xml => "win:Xml",
+        OutType::Json => "win:Json",
+        OutType::Win32Error => "win:Win32Error",
+        OutType::NtStatus => "win:NTSTATUS",
+        OutType::HResult => "win:HResult",
+        OutType::DateTime => "xs:dateTime",
+        OutType::DateTimeCultureInsensitive => "win:DateTimeCultureInsensitive",
+        OutType::Utf8 => "win:Utf8",
+        OutType::Pkcs7WithTypeInfo => "win:Pkcs7WithTypeInfo",
+        OutType::CodePointer => "win:CodePointer",
+        OutType::DateTimeUtc => "win:DateTimeUtc",
+        _ => return None,
+    });
+}
+
+fn level_name(level: u8) -> String {
+    return match level {
+        0 => "win:LogAlways".to_string(),
+        1 => "win:Critical".to_string(),
+        2 => "win:Error".to_string(),
+        3 => "win:Warning".to_string(),
+        4 => "win:Informational".to_string(),
+        5 => "win:Verbose".to_string(),
+        _ => format!("{}", level),
+    };
+}
+
+fn standard_opcode(opcode: u8) -> Option<&'static str> {
+    return match opcode {
+        0 => Some("win:Info"),
+        1 => Some("win:Start"),
+        2 => Some("win:Stop"),
+        3 => Some("win:DC_Start"),
+        4 => Some("win:DC_Stop"),
+        5 => Some("win:Extension"),
+        6 => Some("win:Reply"),
+        7 => Some("win:Resume"),
+        8 => Some("win:Suspend"),
+        9 => Some("win:Send"),
+        240 => Some("win:Receive"),
+        _ => None,
+    };
+}
+
+/// Converts a name to a C identifier, e.g. `MyCompany.MyComponent` to
+/// `MyCompany_MyComponent`.
+fn symbol(name: &str) -> String {
+    return name
+        .chars()
+        .map(|ch| if ch.is_ascii_alphanumeric() { ch } else { '_' })
+        .collect();
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(ch),
+        }
+    }
+    return out;
+}
+
+fn escape_comment(value: &str) -> String {
+    return value.replace("--", "- -");
+}
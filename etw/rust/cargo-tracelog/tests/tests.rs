@@ -0,0 +1,194 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Output;
+
+use tracelogging as tlg;
+
+tlg::define_provider!(PROV, "TracelogCliTest");
+
+/// Writes the events that the artifact describes. The events are never written
+/// (the provider is not registered); write_event! adds them to the schema table.
+fn write_events() {
+    tlg::write_event!(
+        PROV,
+        "Start",
+        id_version(1, 0),
+        level(Informational),
+        opcode(Start),
+        keyword(0x3),
+        u32("Pid", &1),
+        str8("Path", "/"),
+    );
+    tlg::write_event!(
+        PROV,
+        "Stop",
+        id_version(2, 0),
+        level(Warning),
+        opcode(Stop),
+        task(7),
+        keyword(0x4),
+        struct("Result", {
+            u32("Code", &0, format(Win32Error)),
+            u16_slice("Ports", &[80u16, 443]),
+        }),
+    );
+    tlg::write_event!(
+        PROV,
+        "Changed",
+        id_version(3, 1),
+        level(Verbose),
+        u32("Value", &1),
+    );
+    tlg::write_event!(
+        PROV,
+        "Changed",
+        id_version(3, 2),
+        level(Verbose),
+        u64("Value", &1),
+        binary("Data", &[1u8, 2]),
+    );
+}
+
+/// Returns the artifact, without the events that `keep` rejects.
+fn artifact(keep: impl Fn(&[&str]) -> bool) -> String {
+    let mut text = String::new();
+    tlg::write_schema_artifact(&mut text).unwrap();
+    text.lines()
+        .filter(|line| {
+            let values: Vec<&str> = line.split('\t').collect();
+            values[0] != "event" || (values[1] == "TracelogCliTest" && keep(&values))
+        })
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn write_artifact(name: &str, text: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("cargo-tracelog-{}-{}", std::process::id(), name));
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn run(args: &[&str], paths: &[&PathBuf]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_cargo-tracelog"))
+        .args(args)
+        .args(paths)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn list() {
+    write_events();
+    let path = write_artifact("list", &artifact(|_| true));
+
+    // As run by `cargo tracelog list`.
+    let output = run(&["tracelog", "list"], &[&path]);
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.starts_with(&format!("TracelogCliTest {{{:?}}}\n", PROV.id())));
+    assert!(text.contains(
+        "  Start: id=1 version=0 level=Informational opcode=ActivityStart task=0 keyword=0x3\n    Pid: U32\n    Path: Str8 (Utf8)\n"
+    ));
+    assert!(text.contains(
+        "  Stop: id=2 version=0 level=Warning opcode=ActivityStop task=7 keyword=0x4\n    Result: Struct(2)\n      Code: U32 (Win32Error)\n      Ports: U16[]\n"
+    ));
+
+    let output = run(&["list"], &[&path]);
+    assert_eq!(text, stdout(&output));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn wprp() {
+    write_events();
+    let path = write_artifact("wprp", &artifact(|_| true));
+    let output = run(&["wprp"], &[&path]);
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains(&format!(
+        "<EventProvider Id=\"EventProvider_0\" Name=\"{:?}\" Level=\"5\">",
+        PROV.id()
+    )));
+    assert!(text.contains("<Keyword Value=\"0x7\" />"));
+    assert!(text.contains("<Profile Id=\"Tracelog.Verbose.File\""));
+    assert!(text.contains("<EventProviderId Value=\"EventProvider_0\" />"));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn manifest() {
+    write_events();
+    let path = write_artifact("manifest", &artifact(|_| true));
+    let output = run(&["manifest"], &[&path]);
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains(&format!(
+        "<provider name=\"TracelogCliTest\" guid=\"{{{:?}}}\" symbol=\"TracelogCliTest\">",
+        PROV.id()
+    )));
+    assert!(text.contains("<keyword name=\"Keyword_0x4\" mask=\"0x4\" />"));
+    assert!(text.contains("<task name=\"Task_7\" value=\"7\" />"));
+    assert!(text
+        .contains("<data name=\"Path\" inType=\"win:CountedAnsiString\" outType=\"win:Utf8\" />"));
+    assert!(text.contains(
+        "<struct name=\"Result\">\n              <data name=\"Code\" inType=\"win:UInt32\" outType=\"win:Win32Error\" />\n              <data name=\"Ports.Count\" inType=\"win:UInt16\" />\n              <data name=\"Ports\" inType=\"win:UInt16\" count=\"Ports.Count\" />\n            </struct>"
+    ));
+    assert!(text.contains(
+        "<data name=\"Data.Length\" inType=\"win:UInt16\" />\n            <data name=\"Data\" inType=\"win:Binary\" length=\"Data.Length\" />"
+    ));
+    assert!(text.contains("symbol=\"Changed_V2\" template=\"T_Changed_3_2\""));
+    assert!(text.contains(
+        "<event value=\"2\" version=\"0\" level=\"win:Warning\" symbol=\"Stop\" template=\"T_Stop_2_0\" keywords=\"Keyword_0x4\" task=\"Task_7\" opcode=\"win:Stop\" />"
+    ));
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn diff() {
+    write_events();
+    let old = write_artifact("diff-old", &artifact(|e| e[3] != "2" && e[4] != "2"));
+    let new = write_artifact("diff-new", &artifact(|e| e[4] != "1"));
+
+    let output = run(&["diff"], &[&old, &old]);
+    assert_eq!(Some(0), output.status.code());
+    assert_eq!("", stdout(&output));
+
+    let output = run(&["diff"], &[&old, &new]);
+    assert_eq!(Some(1), output.status.code());
+    assert_eq!(
+        "~ TracelogCliTest/Changed\n\
+        \x20 - id=3 version=1 level=Verbose opcode=Info task=0 keyword=0x1\n\
+        \x20 - field 0: Value: U32\n\
+        \x20 + id=3 version=2 level=Verbose opcode=Info task=0 keyword=0x1\n\
+        \x20 + field 0: Value: U64\n\
+        \x20 + field 1: Data: Binary\n\
+        + TracelogCliTest/Stop: id=2 version=0 level=Warning opcode=ActivityStop task=7 keyword=0x4\n",
+        stdout(&output)
+    );
+
+    fs::remove_file(old).unwrap();
+    fs::remove_file(new).unwrap();
+}
+
+#[test]
+fn errors() {
+    let path = write_artifact("errors", "tracelogging-schema 1\nevent\tx\n");
+    let output = run(&["list"], &[&path]);
+    assert_eq!(Some(2), output.status.code());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .ends_with(": line 2: unrecognized line\n"));
+    fs::remove_file(path).unwrap();
+
+    let output = run(&["unknown"], &[]);
+    assert_eq!(Some(2), output.status.code());
+}
@@ -0,0 +1,140 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Stress test for provider registration, enable notifications, and writes.
+//!
+//! One thread repeatedly registers and unregisters the provider, one thread
+//! repeatedly installs and removes an enable-changed callback, and the remaining
+//! threads write events as fast as they can. While it runs, start and stop ETW
+//! sessions that collect the `TraceLogging.Stress` provider (e.g. with
+//! `tracelog -start Stress -f stress.etl -guid *TraceLogging.Stress` and
+//! `tracelog -stop Stress`) so that enable callbacks race with the other threads.
+//!
+//! Run with `cargo run --release -p tracelogging --example stress -- [seconds] [writers]`.
+//! Defaults are 10 seconds and 4 writer threads.
+
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use tracelogging as tlg;
+
+tlg::define_provider!(PROVIDER, "TraceLogging.Stress");
+
+static IN_CALLBACK: AtomicBool = AtomicBool::new(false);
+static NOTIFICATIONS: AtomicU64 = AtomicU64::new(0);
+
+fn enable_changed(_state: tlg::ProviderEnableState) {
+    // Callbacks for a provider are serialized.
+    assert!(!IN_CALLBACK.swap(true, Ordering::Acquire));
+    NOTIFICATIONS.fetch_add(1, Ordering::Relaxed);
+    IN_CALLBACK.store(false, Ordering::Release);
+}
+
+fn arg(index: usize, default: u64) -> u64 {
+    return env::args()
+        .nth(index)
+        .map(|value| value.parse().expect("arguments must be integers"))
+        .unwrap_or(default);
+}
+
+fn main() {
+    let duration = Duration::from_secs(arg(1, 10));
+    let writers = arg(2, 4);
+    let done = Arc::new(AtomicBool::new(false));
+    let start = Instant::now();
+
+    let registrar = {
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut cycles = 0u64;
+            let mut failures = 0u64;
+            while !done.load(Ordering::Relaxed) {
+                // Safety: unregistered before the thread exits.
+                if unsafe { PROVIDER.register() } != 0 {
+                    failures += 1;
+                }
+                thread::yield_now();
+                PROVIDER.unregister();
+                cycles += 1;
+            }
+            return (cycles, failures);
+        })
+    };
+
+    let toggler = {
+        let done = done.clone();
+        thread::spawn(move || {
+            let mut toggles = 0u64;
+            while !done.load(Ordering::Relaxed) {
+                PROVIDER.on_enable_changed(Some(enable_changed));
+                thread::yield_now();
+                PROVIDER.on_enable_changed(None);
+                toggles += 1;
+            }
+            return toggles;
+        })
+    };
+
+    let writer_threads: Vec<_> = (0..writers)
+        .map(|writer| {
+            let done = done.clone();
+            thread::spawn(move || {
+                let mut writes = 0u64;
+                let mut enabled = 0u64;
+                while !done.load(Ordering::Relaxed) {
+                    if PROVIDER.enabled(tlg::Level::Verbose, 0x1) {
+                        enabled += 1;
+                    }
+                    let generation = PROVIDER.enable_generation();
+                    tlg::write_event!(
+                        PROVIDER,
+                        "Stress",
+                        level(Verbose),
+                        keyword(0x1),
+                        u64("Writer", &writer),
+                        u64("Write", &writes),
+                        u32("Generation", &generation),
+                    );
+                    writes += 1;
+                }
+                return (writes, enabled);
+            })
+        })
+        .collect();
+
+    thread::sleep(duration);
+    done.store(true, Ordering::Relaxed);
+
+    let (cycles, failures) = registrar.join().unwrap();
+    let toggles = toggler.join().unwrap();
+    let (writes, enabled) = writer_threads
+        .into_iter()
+        .map(|thread| thread.join().unwrap())
+        .fold((0, 0), |(writes, enabled), (w, e)| {
+            (writes + w, enabled + e)
+        });
+
+    // After the last unregister, the provider must report that it is disabled.
+    assert!(!PROVIDER.is_registered());
+    assert!(!PROVIDER.enabled(tlg::Level::LogAlways, 0));
+    assert!(!PROVIDER.enable_state().is_enabled());
+
+    println!(
+        "{:.1}s: {} register cycles ({} failed), {} callback toggles, {} notifications, {} writes ({} enabled)",
+        start.elapsed().as_secs_f64(),
+        cycles,
+        failures,
+        toggles,
+        NOTIFICATIONS.load(Ordering::Relaxed),
+        writes,
+        enabled
+    );
+}
@@ -5,17 +5,265 @@
 //! Internal implementation details for tracelogging macros and tracelogging_dynamic.
 //! Contents subject to change without notice.
 
+use core::fmt;
 use core::mem;
 use core::slice;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
 use core::time::Duration;
 
+pub use crate::adoption::Adopter;
+pub use crate::adoption::AdopterLink;
+pub use crate::adoption::AdoptionTable;
+pub use crate::adoption::ADOPTION_SLOTS;
+pub use crate::descriptors::capped_length;
+pub use crate::descriptors::capped_slice;
 pub use crate::descriptors::counted_size;
+pub use crate::descriptors::option_as_slice;
+pub use crate::descriptors::ptr_len_size;
+pub use crate::descriptors::sid_length;
 pub use crate::descriptors::slice_count;
 pub use crate::descriptors::EventDataDescriptor;
 pub use crate::descriptors::EventDescriptor;
+pub use crate::descriptors::NULL_SID;
+pub use crate::enable_state::EnableSnapshot;
+#[cfg(target_has_atomic = "64")]
+pub use crate::enable_state::EnableState;
+#[cfg(feature = "export_schema")]
+pub use crate::event_schema::assert_unique_providers;
+pub use crate::meta_reader::skip_meta_fields;
+pub use crate::meta_reader::MetaField;
+pub use crate::meta_reader::MetaReader;
 pub use crate::native::ProviderContext;
+pub use crate::native::SET_INFORMATION_NOT_SUPPORTED;
+pub use crate::provider::fnv1a_hash;
+pub use crate::provider::provider_event_filter;
+pub use crate::provider::provider_inject_register_error;
 pub use crate::provider::provider_new;
+pub use crate::provider::provider_note_write;
 pub use crate::provider::provider_write_transfer;
+pub use crate::provider::register_result;
+pub use crate::provider::EventTarget;
+pub use crate::spin_lock::SpinLock;
+pub use crate::spin_lock::SpinLockGuard;
+
+/// For use by the write_event macro: If feature `export_schema` is enabled, adds the
+/// event's schema to the table returned by `event_schemas()`. Otherwise, does nothing.
+#[cfg(feature = "export_schema")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tlg_export_event {
+    ($provider:path, $desc:ident, $meta:ident, $compiled_in:ident) => {
+        // References the metadata constant (not a separate static) so that the schema
+        // shares the metadata bytes used by the event.
+        static _TLG_SCHEMA: $crate::EventSchema =
+            $crate::EventSchema::new($provider.primary(), $desc, &$meta);
+        // Events removed by compiled_in() get a None entry so that their schema (and
+        // metadata) can be discarded by the linker.
+        #[cfg(any(windows, target_os = "linux", target_os = "android"))]
+        #[used]
+        #[cfg_attr(windows, link_section = ".rdata$zTLGb")]
+        #[cfg_attr(not(windows), link_section = "tlg_event_schema")]
+        static _TLG_SCHEMA_ENTRY: ::core::option::Option<&$crate::EventSchema> = if $compiled_in {
+            ::core::option::Option::Some(&_TLG_SCHEMA)
+        } else {
+            ::core::option::Option::None
+        };
+    };
+}
+
+/// For use by the write_event macro: If feature `export_schema` is enabled, adds the
+/// event's schema to the table returned by `event_schemas()`. Otherwise, does nothing.
+#[cfg(not(feature = "export_schema"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tlg_export_event {
+    ($($tokens:tt)*) => {};
+}
+
+/// For use by the write_event macro: Returns false if the event should be removed at
+/// compile time because of the `TRACELOGGING_MIN_LEVEL` or `TRACELOGGING_KEYWORD_MASK`
+/// environment variables (passed as the values of `option_env!`).
+///
+/// An event is removed if its level is more verbose than `min_level`, or if its
+/// keyword is nonzero and has no bits in common with `keyword_mask`. Empty values are
+/// ignored. Invalid values cause a compile-time error.
+pub const fn compiled_in(
+    level: crate::Level,
+    keyword: u64,
+    min_level: Option<&str>,
+    keyword_mask: Option<&str>,
+) -> bool {
+    if let Some(min_level) = min_level {
+        if !min_level.is_empty() && level.as_int() > parse_min_level(min_level.as_bytes()) {
+            return false;
+        }
+    }
+
+    if let Some(keyword_mask) = keyword_mask {
+        if !keyword_mask.is_empty()
+            && keyword != 0
+            && keyword & parse_keyword_mask(keyword_mask.as_bytes()) == 0
+        {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+const fn parse_min_level(value: &[u8]) -> u8 {
+    const NAMES: [&str; 6] = [
+        "LogAlways",
+        "Critical",
+        "Error",
+        "Warning",
+        "Informational",
+        "Verbose",
+    ];
+    let mut i = 0;
+    while i < NAMES.len() {
+        if ascii_eq_ignore_case(value, NAMES[i].as_bytes()) {
+            return i as u8;
+        }
+        i += 1;
+    }
+
+    return match parse_u64(value) {
+        Some(n) if n <= 255 => n as u8,
+        _ => panic!("TRACELOGGING_MIN_LEVEL must be a level name or an integer from 0 to 255"),
+    };
+}
+
+const fn parse_keyword_mask(value: &[u8]) -> u64 {
+    return match parse_u64(value) {
+        Some(n) => n,
+        None => panic!("TRACELOGGING_KEYWORD_MASK must be a decimal or 0x-prefixed hex u64"),
+    };
+}
+
+// <[u8]>::eq_ignore_ascii_case is not const.
+#[allow(clippy::manual_ignore_case_cmp)]
+const fn ascii_eq_ignore_case(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i].to_ascii_lowercase() != b[i].to_ascii_lowercase() {
+            return false;
+        }
+        i += 1;
+    }
+
+    return true;
+}
+
+/// Parses a decimal or `0x`-prefixed hexadecimal u64. Returns None if invalid.
+const fn parse_u64(value: &[u8]) -> Option<u64> {
+    let (radix, mut i) = if value.len() > 2 && value[0] == b'0' && (value[1] | 0x20) == b'x' {
+        (16, 2)
+    } else {
+        (10, 0)
+    };
+
+    if i == value.len() {
+        return None;
+    }
+
+    let mut n: u64 = 0;
+    while i < value.len() {
+        let ch = value[i];
+        let digit = match ch {
+            b'0'..=b'9' => ch - b'0',
+            b'a'..=b'f' if radix == 16 => ch - b'a' + 10,
+            b'A'..=b'F' if radix == 16 => ch - b'A' + 10,
+            _ => return None,
+        };
+        n = match n.checked_mul(radix) {
+            Some(n) => match n.checked_add(digit as u64) {
+                Some(n) => n,
+                None => return None,
+            },
+            None => return None,
+        };
+        i += 1;
+    }
+
+    return Some(n);
+}
+
+/// For use by the define_provider macro: If feature `export_schema` is enabled, adds
+/// the provider to the table returned by `provider_schemas()`. Otherwise, does nothing.
+#[cfg(feature = "export_schema")]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tlg_export_provider {
+    ($provider:ident) => {
+        const _: () = {
+            static _TLG_PROVIDER_SCHEMA: $crate::ProviderSchema = $crate::ProviderSchema::new(
+                &$provider,
+                ::core::stringify!($provider),
+                ::core::module_path!(),
+            );
+            #[cfg(any(windows, target_os = "linux", target_os = "android"))]
+            #[used]
+            #[cfg_attr(windows, link_section = ".rdata$zTLPb")]
+            #[cfg_attr(not(windows), link_section = "tlg_provider_schema")]
+            static _TLG_PROVIDER_ENTRY: ::core::option::Option<&$crate::ProviderSchema> =
+                ::core::option::Option::Some(&_TLG_PROVIDER_SCHEMA);
+        };
+    };
+}
+
+/// For use by the define_provider macro: If feature `export_schema` is enabled, adds
+/// the provider to the table returned by `provider_schemas()`. Otherwise, does nothing.
+#[cfg(not(feature = "export_schema"))]
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tlg_export_provider {
+    ($($tokens:tt)*) => {};
+}
+
+/// For use by the write_event macro: Returns a `Vec<u8>` with the data for an `error`
+/// field, i.e. the `Message` string (the error's `Display` text) followed by the
+/// `Sources` string array (the `Display` text of each error in the `source()` chain).
+/// Strings are truncated to 65535 bytes. Requires `std`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tlg_error_chain_data {
+    ($err:expr) => {{
+        fn push_str(data: &mut ::std::vec::Vec<u8>, value: &dyn ::core::fmt::Display) {
+            let value = ::std::string::ToString::to_string(value);
+            let mut len = value.len().min(65535);
+            while !value.is_char_boundary(len) {
+                len -= 1;
+            }
+            data.extend_from_slice(&(len as ::core::primitive::u16).to_le_bytes());
+            data.extend_from_slice(&value.as_bytes()[..len]);
+        }
+
+        let err: &dyn ::std::error::Error = $err;
+        let mut data = ::std::vec::Vec::new();
+        push_str(&mut data, &err);
+
+        let count_pos = data.len();
+        data.extend_from_slice(&[0, 0]);
+        let mut count: ::core::primitive::u16 = 0;
+        let mut source = err.source();
+        while let ::core::option::Option::Some(source_err) = source {
+            if count == ::core::primitive::u16::MAX {
+                break;
+            }
+            push_str(&mut data, &source_err);
+            count += 1;
+            source = source_err.source();
+        }
+        data[count_pos..count_pos + 2].copy_from_slice(&count.to_le_bytes());
+        data
+    }};
+}
 
 const UNIX_EPOCH_FILETIME: u64 = 0x19DB1DED53E8000;
 const FILETIME_PER_SECOND: u64 = 10000000;
@@ -31,6 +279,30 @@ pub fn meta_as_bytes<T>(meta: &T) -> &[u8] {
     }
 }
 
+/// For use by the write_event macro: Converts the event's metadata structure into a
+/// byte array. `N` must be `size_of::<T>()`.
+///
+/// The macro stores the event's metadata as a `[u8; N]` constant instead of a
+/// call-site-specific structure so that call sites with identical schemas produce
+/// identical constants, which the compiler and linker can merge.
+pub const fn meta_to_bytes<T, const N: usize>(meta: T) -> [u8; N] {
+    union MetaBytes<T, const N: usize> {
+        meta: mem::ManuallyDrop<T>,
+        bytes: [u8; N],
+    }
+
+    assert!(mem::size_of::<T>() == N, "metadata size mismatch");
+
+    // Safety: T is a packed structure of integer and byte array fields (no padding)
+    // with the same size as [u8; N], so every byte is initialized.
+    unsafe {
+        return MetaBytes {
+            meta: mem::ManuallyDrop::new(meta),
+        }
+        .bytes;
+    }
+}
+
 /// Returns the number of bytes needed to encode the specified tag.
 pub const fn tag_size(tag: u32) -> usize {
     return if 0 == (tag & 0x001FFFFF) {
@@ -65,6 +337,163 @@ pub const fn tag_encode<const SIZE: usize>(tag: u32) -> [u8; SIZE] {
     return result;
 }
 
+/// For use by the write_event macro: Returns the schema of a `raw_custom` field as a
+/// byte array. `N` must be `schema.len()`.
+pub const fn custom_schema<const N: usize>(schema: &[u8]) -> [u8; N] {
+    assert!(schema.len() == N, "schema size mismatch");
+    let mut result = [0; N];
+
+    let mut i = 0;
+    while i != N {
+        result[i] = schema[i];
+        i += 1;
+    }
+
+    return result;
+}
+
+/// Invokes a closure given as a field value. Used instead of `(CLOSURE)()` so that the
+/// expansion does not trigger clippy's `redundant_closure_call` lint.
+pub fn call_field_closure<T>(closure: impl FnOnce() -> T) -> T {
+    return closure();
+}
+
+/// For use by the fmt field type: A fixed-size buffer holding the text produced by a
+/// `fmt::Arguments` value, truncated at a character boundary if it does not fit.
+pub struct FmtBuffer {
+    len: usize,
+    buf: [u8; FmtBuffer::CAPACITY],
+}
+
+impl FmtBuffer {
+    /// Maximum size (in bytes) of the formatted text.
+    pub const CAPACITY: usize = 512;
+
+    /// Formats `args` into a new buffer.
+    pub fn new(args: fmt::Arguments) -> Self {
+        let mut buffer = Self {
+            len: 0,
+            buf: [0; Self::CAPACITY],
+        };
+
+        // An error means the text was truncated.
+        let _ = fmt::write(&mut buffer, args);
+        return buffer;
+    }
+}
+
+impl AsRef<[u8]> for FmtBuffer {
+    fn as_ref(&self) -> &[u8] {
+        return &self.buf[..self.len];
+    }
+}
+
+impl fmt::Write for FmtBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let remaining = Self::CAPACITY - self.len;
+        let mut len = s.len().min(remaining);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        return if len == s.len() {
+            Ok(())
+        } else {
+            Err(fmt::Error)
+        };
+    }
+}
+
+/// For use by the str8_slice and str16_slice field types: A fixed-size buffer holding
+/// the data for a variable-length array of counted strings, i.e. a u16 element count
+/// followed by a u16 byte count and the bytes of each element. Elements are included
+/// in order until one does not fit; that element and the rest are dropped.
+pub struct StrSliceBuffer {
+    len: usize,
+    buf: [u8; StrSliceBuffer::CAPACITY],
+}
+
+impl StrSliceBuffer {
+    /// Maximum size (in bytes) of the encoded array, including the count prefix.
+    pub const CAPACITY: usize = 2048;
+
+    /// Encodes a slice of 8-bit strings, e.g. `&[&str]`, into a new buffer.
+    pub fn new8<S: AsRef<[u8]>>(values: &[S]) -> Self {
+        let mut buffer = Self::empty();
+        for value in values {
+            let value = value.as_ref();
+            if !buffer.reserve(value.len()) {
+                break;
+            }
+
+            buffer.push_bytes(value);
+        }
+        return buffer;
+    }
+
+    /// Encodes a slice of 16-bit strings, e.g. `&[&[u16]]`, into a new buffer.
+    pub fn new16<S: AsRef<[u16]>>(values: &[S]) -> Self {
+        let mut buffer = Self::empty();
+        for value in values {
+            let value = value.as_ref();
+            if !buffer.reserve(value.len() * 2) {
+                break;
+            }
+
+            for ch in value {
+                buffer.push_bytes(&ch.to_le_bytes());
+            }
+        }
+        return buffer;
+    }
+
+    fn empty() -> Self {
+        return Self {
+            len: 2,
+            buf: [0; Self::CAPACITY],
+        };
+    }
+
+    /// If an element of `size` bytes fits, increments the element count, writes the
+    /// element's byte count, and returns true. Otherwise returns false.
+    fn reserve(&mut self, size: usize) -> bool {
+        let count = u16::from_le_bytes([self.buf[0], self.buf[1]]);
+        if count == u16::MAX || Self::CAPACITY - self.len < 2 + size {
+            return false;
+        }
+
+        self.buf[0..2].copy_from_slice(&(count + 1).to_le_bytes());
+        self.push_bytes(&(size as u16).to_le_bytes());
+        return true;
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+impl AsRef<[u8]> for StrSliceBuffer {
+    fn as_ref(&self) -> &[u8] {
+        return &self.buf[..self.len];
+    }
+}
+
+/// For use by the mark macro: Returns the next marker sequence number. Sequence
+/// numbers are process-wide and start at 1.
+pub fn next_mark_sequence() -> u64 {
+    static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+    return SEQUENCE.fetch_add(1, Ordering::Relaxed) as u64 + 1;
+}
+
+/// For use by the mark macro: Returns the current performance counter (QPC) value, or
+/// 0 if not Windows/etw.
+pub fn performance_counter() -> u64 {
+    return ProviderContext::performance_counter();
+}
+
 /// Returns the filetime corresponding to an i32 count of seconds since 1970 (time32_t).
 pub const fn filetime_from_time32(time32: &i32) -> i64 {
     let time = *time32;
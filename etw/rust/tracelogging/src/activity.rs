@@ -0,0 +1,81 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::str::from_utf8;
+
+use crate::guid::Guid;
+use crate::provider::Provider;
+
+#[allow(unused_imports)] // For docs
+#[cfg(feature = "macros")]
+use crate::activity_scope;
+
+/// RAII guard for an activity: writes an activity-stop event when dropped.
+///
+/// Use [`activity_scope!`] to create an `ActivityScope`. The macro generates a new
+/// activity id, writes an activity-start event, and returns an `ActivityScope` that
+/// writes the matching activity-stop event when it goes out of scope, including on
+/// early return.
+///
+/// While the scope is alive, the current thread's activity id is set to the scope's
+/// activity id, so events written by [`write_event!`](crate::write_event) without an
+/// `activity_id` option (and nested scopes) become part of the activity. The
+/// previous thread activity id is recorded as the scope's related (parent) id and is
+/// restored when the scope is dropped. Scopes must therefore be dropped in the reverse
+/// order of creation, on the thread that created them (`ActivityScope` is not `Send`).
+#[must_use = "the activity stops when the ActivityScope is dropped"]
+pub struct ActivityScope {
+    activity_id: Guid,
+    related_id: Guid,
+    stop: fn(activity_id: &Guid) -> u32,
+    not_send: PhantomData<*const ()>,
+}
+
+impl ActivityScope {
+    /// For use by the activity_scope macro: creates a new activity id, makes it the
+    /// current thread's activity id, and records the previous thread activity id as
+    /// the related id. `stop` will be called with the activity id when the scope is
+    /// dropped.
+    pub fn new(stop: fn(activity_id: &Guid) -> u32) -> Self {
+        let activity_id = Provider::create_activity_id();
+        let related_id = Provider::set_current_thread_activity_id(&activity_id);
+        return Self {
+            activity_id,
+            related_id,
+            stop,
+            not_send: PhantomData,
+        };
+    }
+
+    /// Returns the id of this activity.
+    pub const fn activity_id(&self) -> &Guid {
+        return &self.activity_id;
+    }
+
+    /// Returns the id of the parent activity, i.e. the thread's activity id at the
+    /// time this scope was created.
+    pub const fn related_id(&self) -> &Guid {
+        return &self.related_id;
+    }
+}
+
+impl Drop for ActivityScope {
+    /// Writes the activity-stop event and restores the thread's previous activity id.
+    fn drop(&mut self) {
+        (self.stop)(&self.activity_id);
+        Provider::set_current_thread_activity_id(&self.related_id);
+    }
+}
+
+impl fmt::Debug for ActivityScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "ActivityScope {{ activity_id: {}, related_id: {} }}",
+            from_utf8(&self.activity_id.to_utf8_bytes()).unwrap(),
+            from_utf8(&self.related_id.to_utf8_bytes()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::ptr;
+
+#[cfg(not(loom))]
+use core::hint;
+#[cfg(not(loom))]
+use core::sync::atomic;
+
+// Under `--cfg loom`, use loom's atomics so that tests/loom.rs can explore the
+// interleavings of adopting, unadopting, and forwarding enable callbacks.
+#[cfg(loom)]
+use loom::hint;
+#[cfg(loom)]
+use loom::sync::atomic;
+
+use crate::spin_lock::SpinLock;
+use crate::spin_lock::SpinLockGuard;
+
+/// Maximum number of C/C++ providers that can be adopted by a process.
+pub const ADOPTION_SLOTS: usize = 8;
+
+/// Maximum number of providers that can adopt the same C/C++ provider at the same
+/// time.
+pub const ADOPTERS_PER_SLOT: usize = 8;
+
+/// Implemented by the contexts stored in an [`AdoptionTable`].
+pub trait Adopter {
+    /// Returns the adopter's link, which counts the dispatches using the adopter.
+    fn adopter_link(&self) -> &AdopterLink;
+}
+
+/// Per-adopter state used by an [`AdoptionTable`]: the number of
+/// [`AdoptionTable::dispatch`] calls that have copied the adopter and have not yet
+/// finished invoking it.
+pub struct AdopterLink {
+    dispatches: atomic::AtomicUsize,
+}
+
+impl AdopterLink {
+    /// Creates a link with no dispatches in progress.
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        return Self {
+            dispatches: atomic::AtomicUsize::new(0),
+        };
+    }
+
+    /// Creates a link with no dispatches in progress.
+    /// (Not `const` because loom's atomics cannot be created in a const context.)
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        return Self {
+            dispatches: atomic::AtomicUsize::new(0),
+        };
+    }
+}
+
+impl Default for AdopterLink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A C/C++ provider and the contexts that have adopted it.
+struct Slot<A> {
+    cpp: usize,     // Address of the C/C++ provider, or 0 if the slot is unused.
+    chained: usize, // The C/C++ provider's callback before it was adopted (0 = none).
+    adopters: [*const A; ADOPTERS_PER_SLOT], // The first `count` entries are valid.
+    count: usize,
+}
+
+impl<A> Clone for Slot<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A> Copy for Slot<A> {}
+
+unsafe impl<A> Send for Slot<A> {}
+
+impl<A> Slot<A> {
+    const UNUSED: Self = Self {
+        cpp: 0,
+        chained: 0,
+        adopters: [ptr::null(); ADOPTERS_PER_SLOT],
+        count: 0,
+    };
+}
+
+#[cfg(not(loom))]
+#[allow(clippy::declare_interior_mutable_const)]
+const UNLOCKED: SpinLock<()> = SpinLock::new(());
+
+/// The C/C++ providers whose registrations have been adopted, and the contexts that
+/// adopted each one. Used by `ProviderContext::adopt`. Kept separate from the ETW
+/// code so that tests/loom.rs can model it.
+///
+/// - A slot is bound to a C/C++ provider the first time the provider is adopted and
+///   is never reused for a different C/C++ provider. ETW may deliver an enable
+///   callback through a slot after the C/C++ provider's original callback has been
+///   restored, and such a late callback must only reach the adopters and chained
+///   callback of the provider that it was delivered for.
+/// - The table lock is only held while a slot is bound or while its adopters are
+///   added, removed, or copied. It is never held while callbacks run, so callbacks
+///   may adopt or unadopt providers in other slots.
+/// - Each slot's dispatch lock is held while an enable callback is forwarded to the
+///   slot's adopters and chained callback, and while a new adopter is added and
+///   receives the C/C++ provider's current state. Callbacks for the same C/C++
+///   provider are serialized; callbacks for different C/C++ providers are not.
+/// - [`AdoptionTable::remove`] does not take the dispatch lock. It waits until no
+///   dispatch is using the removed adopter (see [`AdopterLink`]).
+///
+/// Lock order: a slot's dispatch lock, then the table lock.
+pub struct AdoptionTable<A> {
+    slots: SpinLock<[Slot<A>; ADOPTION_SLOTS]>,
+    dispatch: [SpinLock<()>; ADOPTION_SLOTS],
+}
+
+impl<A> AdoptionTable<A> {
+    /// Creates a table with no adopted providers.
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        return Self {
+            slots: SpinLock::new([Slot::UNUSED; ADOPTION_SLOTS]),
+            dispatch: [UNLOCKED; ADOPTION_SLOTS],
+        };
+    }
+
+    /// Creates a table with no adopted providers.
+    /// (Not `const` because loom's atomics cannot be created in a const context.)
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        return Self {
+            slots: SpinLock::new([Slot::UNUSED; ADOPTION_SLOTS]),
+            dispatch: [(); ADOPTION_SLOTS].map(|_| SpinLock::new(())),
+        };
+    }
+}
+
+impl<A: Adopter> AdoptionTable<A> {
+    /// Returns the slot that is bound to the C/C++ provider at address `cpp`, binding
+    /// an unused slot if the provider has not been adopted before. Returns None if
+    /// all slots are bound to other C/C++ providers.
+    pub fn bind(&self, cpp: usize) -> Option<usize> {
+        debug_assert!(cpp != 0);
+        let mut slots = self.slots.lock();
+        let slot = slots
+            .iter()
+            .position(|slot| slot.cpp == cpp)
+            .or_else(|| slots.iter().position(|slot| slot.cpp == 0));
+        if let Some(slot) = slot {
+            slots[slot].cpp = cpp;
+        }
+        return slot;
+    }
+
+    /// Returns the slot that is bound to the C/C++ provider at address `cpp`, or None
+    /// if the provider has never been adopted.
+    pub fn find(&self, cpp: usize) -> Option<usize> {
+        return self.slots.lock().iter().position(|slot| slot.cpp == cpp);
+    }
+
+    /// Locks the slot's dispatch lock. Hold the returned guard while calling
+    /// [`AdoptionTable::add`] and while delivering the C/C++ provider's current state
+    /// to the new adopter.
+    pub fn lock_dispatch(&self, slot: usize) -> SpinLockGuard<'_, ()> {
+        return self.dispatch[slot].lock();
+    }
+
+    /// Adds `adopter` to the slot. If the slot had no adopters, calls `chain` with the
+    /// table lock held: `chain` installs the slot's callback in the C/C++ provider and
+    /// returns the callback that it replaced. Returns false (and does not add the
+    /// adopter) if the slot already has [`ADOPTERS_PER_SLOT`] adopters.
+    ///
+    /// Must be called with the slot's dispatch lock held (see
+    /// [`AdoptionTable::lock_dispatch`]).
+    pub fn add(&self, slot: usize, adopter: &A, chain: impl FnOnce() -> usize) -> bool {
+        let mut slots = self.slots.lock();
+        let slot = &mut slots[slot];
+        if slot.count == ADOPTERS_PER_SLOT {
+            return false;
+        }
+
+        if slot.count == 0 {
+            slot.chained = chain();
+        }
+
+        slot.adopters[slot.count] = adopter;
+        slot.count += 1;
+        return true;
+    }
+
+    /// Removes `adopter` from the slot. If it was the slot's last adopter, calls
+    /// `unchain` with the table lock held and the callback that `chain` returned
+    /// when the adopter was added. Then waits until no dispatch is using `adopter`.
+    ///
+    /// Must not be called from a dispatch through the same slot (e.g. from an enable
+    /// callback of a provider that adopted the same C/C++ provider): that dispatch
+    /// may have counted `adopter`, so this would spin forever.
+    pub fn remove(&self, slot: usize, adopter: &A, unchain: impl FnOnce(usize)) {
+        {
+            let mut slots = self.slots.lock();
+            let slot = &mut slots[slot];
+            if let Some(index) = slot.adopters[..slot.count]
+                .iter()
+                .position(|&other| ptr::eq(other, adopter))
+            {
+                slot.count -= 1;
+                slot.adopters[index] = slot.adopters[slot.count];
+                slot.adopters[slot.count] = ptr::null();
+                if slot.count == 0 {
+                    unchain(slot.chained);
+                }
+            }
+        }
+
+        // Dispatches copy their adopters while holding the table lock, so any
+        // dispatch that can still use the adopter has already counted itself.
+        let link = adopter.adopter_link();
+        while link.dispatches.load(atomic::Ordering::Acquire) != 0 {
+            hint::spin_loop();
+        }
+    }
+
+    /// Forwards an enable callback that ETW delivered through the slot: with the
+    /// slot's dispatch lock held, calls `each` for each adopter, then calls `chained`
+    /// with the C/C++ provider's original callback (0 if none). The table lock is not
+    /// held while `each` or `chained` run.
+    ///
+    /// # Safety
+    ///
+    /// Each adopter must remain valid until it has been removed.
+    pub unsafe fn dispatch(
+        &self,
+        slot: usize,
+        mut each: impl FnMut(&A),
+        chained: impl FnOnce(usize),
+    ) {
+        let _dispatch = self.dispatch[slot].lock();
+
+        let mut adopters = [ptr::null(); ADOPTERS_PER_SLOT];
+        let count;
+        let chained_callback;
+        {
+            let slots = self.slots.lock();
+            let slot = &slots[slot];
+            count = slot.count;
+            adopters[..count].copy_from_slice(&slot.adopters[..count]);
+            for &adopter in &adopters[..count] {
+                let link = (*adopter).adopter_link();
+                link.dispatches.fetch_add(1, atomic::Ordering::Relaxed);
+            }
+            chained_callback = slot.chained;
+        }
+
+        for &adopter in &adopters[..count] {
+            let adopter: &A = &*adopter;
+            each(adopter);
+            adopter
+                .adopter_link()
+                .dispatches
+                .fetch_sub(1, atomic::Ordering::Release);
+        }
+
+        chained(chained_callback);
+    }
+}
+
+impl<A> Default for AdoptionTable<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
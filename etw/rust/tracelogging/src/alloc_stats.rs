@@ -0,0 +1,291 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::alloc::GlobalAlloc;
+use core::alloc::Layout;
+use core::fmt;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+use crate::descriptors::EventDataDescriptor;
+use crate::descriptors::EventDescriptor;
+use crate::enums::InType;
+use crate::enums::Level;
+use crate::provider::provider_event_filter;
+use crate::provider::provider_write_transfer;
+use crate::provider::Provider;
+
+const SIZE_CLASSES: usize = 8;
+const U64_ARRAY: u8 = InType::U64.as_int() | InType::VariableCountFlag;
+
+/// Metadata for the "AllocStats" event.
+static EVENT_META: [u8; meta_len(EVENT_META_PARTS)] = meta_concat(EVENT_META_PARTS);
+const EVENT_META_PARTS: &[&[u8]] = &[
+    &[0, 0, 0], // u16 size (filled in by meta_concat), u8 event tag = 0.
+    b"AllocStats\0",
+    b"SizeClassMax\0",
+    &[U64_ARRAY],
+    b"AllocCount\0",
+    &[U64_ARRAY],
+    b"AllocBytes\0",
+    &[U64_ARRAY],
+    b"FreeCount\0",
+    &[InType::U64.as_int()],
+    b"FreeBytes\0",
+    &[InType::U64.as_int()],
+];
+
+const fn meta_len(parts: &[&[u8]]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i != parts.len() {
+        len += parts[i].len();
+        i += 1;
+    }
+    return len;
+}
+
+const fn meta_concat<const N: usize>(parts: &[&[u8]]) -> [u8; N] {
+    let mut result = [0; N];
+    let mut pos = 0;
+    let mut i = 0;
+    while i != parts.len() {
+        let mut j = 0;
+        while j != parts[i].len() {
+            result[pos] = parts[i][j];
+            pos += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    result[0] = N as u8;
+    result[1] = (N >> 8) as u8;
+    return result;
+}
+
+/// A snapshot of the statistics collected by a [`StatsAllocator`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AllocStats {
+    /// Number of allocations in each size class. Allocations of size `n` are counted
+    /// in the first size class `i` for which `n <= AllocStats::SIZE_CLASS_MAX[i]`.
+    pub alloc_count: [u64; SIZE_CLASSES],
+    /// Number of bytes allocated in each size class.
+    pub alloc_bytes: [u64; SIZE_CLASSES],
+    /// Number of deallocations (all size classes).
+    pub free_count: u64,
+    /// Number of bytes deallocated (all size classes).
+    pub free_bytes: u64,
+}
+
+impl AllocStats {
+    /// The largest allocation size (in bytes) counted in each size class.
+    pub const SIZE_CLASS_MAX: [u64; SIZE_CLASSES] =
+        [16, 64, 256, 1024, 4096, 16384, 65536, u64::MAX];
+
+    /// Returns the index of the size class for an allocation of the specified size.
+    pub const fn size_class(size: usize) -> usize {
+        let mut i = 0;
+        while i != SIZE_CLASSES - 1 && (size as u64) > Self::SIZE_CLASS_MAX[i] {
+            i += 1;
+        }
+        return i;
+    }
+}
+
+/// A [`GlobalAlloc`] wrapper that counts allocations by size class and periodically
+/// writes the counts to ETW as an `"AllocStats"` event (requires feature
+/// `alloc_stats`).
+///
+/// `StatsAllocator` forwards each request to the wrapped allocator and updates a few
+/// relaxed atomic counters. Every `threshold` allocations (or whenever
+/// [`StatsAllocator::write_stats`] is called, e.g. from a timer), it writes an event
+/// with the following fields to the specified provider, using the specified level and
+/// keyword:
+///
+/// - `SizeClassMax`: `u64[]`, the value of [`AllocStats::SIZE_CLASS_MAX`].
+/// - `AllocCount`: `u64[]`, number of allocations in each size class.
+/// - `AllocBytes`: `u64[]`, number of bytes allocated in each size class.
+/// - `FreeCount`: `u64`, number of deallocations.
+/// - `FreeBytes`: `u64`, number of bytes deallocated.
+///
+/// Counts are cumulative since the allocator was created. A reallocation is counted
+/// as a deallocation of the old size followed by an allocation of the new size.
+///
+/// Writing the event does not allocate. The provider should be registered early
+/// (events are not written while it is unregistered or not enabled).
+///
+/// ```no_run
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(ALLOC_PROVIDER, "MyCompany.MyComponent.Alloc");
+///
+/// #[global_allocator]
+/// static GLOBAL: tlg::StatsAllocator<std::alloc::System> = tlg::StatsAllocator::new(
+///     std::alloc::System,
+///     &ALLOC_PROVIDER,
+///     tlg::Level::Informational,
+///     0x1,
+///     100000, // Write statistics every 100000 allocations.
+/// );
+///
+/// fn main() {
+///     unsafe { ALLOC_PROVIDER.register(); }
+///     // ...
+///     GLOBAL.write_stats(); // Write final statistics.
+///     ALLOC_PROVIDER.unregister();
+/// }
+/// ```
+pub struct StatsAllocator<A> {
+    inner: A,
+    provider: &'static Provider,
+    descriptor: EventDescriptor,
+    threshold: usize,
+    alloc_total: AtomicUsize,
+    alloc_count: [AtomicUsize; SIZE_CLASSES],
+    alloc_bytes: [AtomicUsize; SIZE_CLASSES],
+    free_count: AtomicUsize,
+    free_bytes: AtomicUsize,
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicUsize = AtomicUsize::new(0);
+
+impl<A> StatsAllocator<A> {
+    /// Returns an allocator that forwards to `inner` and writes an `"AllocStats"`
+    /// event to `provider` with the specified level and keyword every `threshold`
+    /// allocations. If `threshold` is 0, events are only written by
+    /// [`StatsAllocator::write_stats`].
+    pub const fn new(
+        inner: A,
+        provider: &'static Provider,
+        level: Level,
+        keyword: u64,
+        threshold: usize,
+    ) -> Self {
+        return Self {
+            inner,
+            provider,
+            descriptor: EventDescriptor::new(level, keyword),
+            threshold,
+            alloc_total: ZERO,
+            alloc_count: [ZERO; SIZE_CLASSES],
+            alloc_bytes: [ZERO; SIZE_CLASSES],
+            free_count: ZERO,
+            free_bytes: ZERO,
+        };
+    }
+
+    /// Returns the wrapped allocator.
+    pub const fn inner(&self) -> &A {
+        return &self.inner;
+    }
+
+    /// Returns a snapshot of the current statistics.
+    pub fn stats(&self) -> AllocStats {
+        let mut stats = AllocStats {
+            free_count: self.free_count.load(Ordering::Relaxed) as u64,
+            free_bytes: self.free_bytes.load(Ordering::Relaxed) as u64,
+            ..AllocStats::default()
+        };
+        for i in 0..SIZE_CLASSES {
+            stats.alloc_count[i] = self.alloc_count[i].load(Ordering::Relaxed) as u64;
+            stats.alloc_bytes[i] = self.alloc_bytes[i].load(Ordering::Relaxed) as u64;
+        }
+        return stats;
+    }
+
+    /// Writes an `"AllocStats"` event with the current statistics if the provider is
+    /// enabled for the allocator's level and keyword.
+    ///
+    /// Returns 0 for success or a Win32 error from `EventWrite` for failure. The return
+    /// value is for diagnostic purposes only and should generally be ignored in retail
+    /// builds.
+    pub fn write_stats(&self) -> u32 {
+        let provider = self.provider;
+        let descriptor = &self.descriptor;
+        if !provider.enabled(descriptor.level, descriptor.keyword)
+            || !provider_event_filter(provider, descriptor)
+        {
+            return 0;
+        }
+
+        let stats = self.stats();
+        let count = SIZE_CLASSES as u16;
+        let mut dd = [
+            EventDataDescriptor::from_raw_bytes(provider.raw_meta(), 2), // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
+            EventDataDescriptor::from_raw_bytes(&EVENT_META, 1), // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
+            EventDataDescriptor::from_value(&count),
+            EventDataDescriptor::from_slice(&AllocStats::SIZE_CLASS_MAX),
+            EventDataDescriptor::from_value(&count),
+            EventDataDescriptor::from_slice(&stats.alloc_count),
+            EventDataDescriptor::from_value(&count),
+            EventDataDescriptor::from_slice(&stats.alloc_bytes),
+            EventDataDescriptor::from_value(&stats.free_count),
+            EventDataDescriptor::from_value(&stats.free_bytes),
+        ];
+        return provider_write_transfer(provider, descriptor, None, None, &mut dd);
+    }
+
+    fn record_alloc(&self, size: usize) {
+        let class = AllocStats::size_class(size);
+        self.alloc_count[class].fetch_add(1, Ordering::Relaxed);
+        self.alloc_bytes[class].fetch_add(size, Ordering::Relaxed);
+
+        let total = self
+            .alloc_total
+            .fetch_add(1, Ordering::Relaxed)
+            .wrapping_add(1);
+        if self.threshold != 0 && total % self.threshold == 0 {
+            self.write_stats();
+        }
+    }
+
+    fn record_free(&self, size: usize) {
+        self.free_count.fetch_add(1, Ordering::Relaxed);
+        self.free_bytes.fetch_add(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for StatsAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        return ptr;
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            self.record_alloc(layout.size());
+        }
+        return ptr;
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        self.record_free(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            self.record_free(layout.size());
+            self.record_alloc(new_size);
+        }
+        return new_ptr;
+    }
+}
+
+impl<A> fmt::Debug for StatsAllocator<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "StatsAllocator {{ provider: \"{}\", threshold: {}, stats: {:?} }}",
+            self.provider.name(),
+            self.threshold,
+            self.stats()
+        );
+    }
+}
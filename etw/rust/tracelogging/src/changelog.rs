@@ -1,46 +1,256 @@
-//! Release history
-
-#[allow(unused_imports)]
-use crate::*; // For docs
-
-/// # v1.2.2 (2024-05-20)
-/// - tracelogging crate supports use in kernel mode via feature
-///   `kernel_mode`.
-pub mod v1_2_2 {}
-
-/// # v1.2.1 (2023-06-29)
-/// - Guid: Fix `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
-pub mod v1_2_1 {}
-
-/// # v1.2.0 (2023-05-15)
-/// - In event macros, `activity_id` and `related_id` values can now be either
-///   `&Guid` or `&[u8; 16]`.
-/// - New event macro field types `errno`, `errno_slice`, `time32`, and `time64`
-///   (for compatibility with eventheader).
-/// - New provider macro option `group_name` (for compatibility with eventheader).
-/// - Guid: Added `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
-/// - Better macro parse error reporting.
-/// - Opcode enum names clarified.
-pub mod v1_2_0 {}
-
-/// # v1.1.0 (2023-03-24)
-/// - Relax multithreading precondition on [`Provider::unregister`]. Now,
-///   only [`Provider::register`] has special preconditions.
-pub mod v1_1_0 {}
-
-/// # v1.0.2 (2023-03-13, Breaking)
-/// - **Breaking:** Rename the `filetime_from_systemtime` macro to
-///   [`win_filetime_from_systemtime`].
-/// - Improve the doc comments for [`win_filetime_from_systemtime`].
-/// - Remove "For testing" macros from `tracelogging_macros` crate.
-pub mod v1_0_2 {}
-
-/// # v1.0.1 (2023-03-13)
-/// - Add `filetime_from_systemtime` macro.
-/// - Optimization: Split internal `filetime_from_duration` function into separate
-///   before-1970 and after-1970 functions.
-pub mod v1_0_1 {}
-
-/// # v0.1.0 (2022-08-13)
-/// - Initial release.
-pub mod v0_1_0 {}
+//! Release history
+
+#[allow(unused_imports)]
+use crate::*; // For docs
+
+/// # v2.0.0 (Unreleased)
+/// - **Breaking change:** `Provider::enabled` is no longer a `const fn`. ETW enable
+///   callbacks are serialized and update the provider's level and keywords
+///   atomically, and [`Provider::enabled`] always evaluates a consistent snapshot,
+///   which requires atomic loads that cannot be done in a `const fn`. Enable
+///   callbacks must not unregister the provider (see [`ProviderEnableCallback`]).
+/// - New [`Provider::enable_generation`] method.
+/// - New [`try_write_event!`] macro and [`Provider::is_registered`] method for
+///   logging during early process initialization.
+/// - [`Channel`], [`Level`], [`Opcode`], [`InType`], and [`OutType`] have new
+///   `as_str()` and `from_name()` methods, implement `FromStr`, and `Display` now
+///   formats the canonical name (e.g. `Verbose`) instead of the integer value.
+/// - New feature `write_retries` with `Provider::set_write_retries` method for
+///   retrying writes that fail because a session's buffers are full. (Retries may
+///   duplicate events in sessions that recorded the first attempt.)
+/// - `define_provider!` accepts a const `&str` expression for the provider id, e.g.
+///   `id(env!("MY_PROVIDER_GUID"))`. New [`Guid::parse_const`] method.
+/// - New `auto_id(hash)` option for [`write_event!`] and new
+///   [`Provider::event_id_from_name`] method for generating a stable event id from
+///   the event name. With feature `export_schema`, new `duplicate_event_ids()`
+///   function, and `test_unique_providers!` also fails if two events of a provider
+///   have the same id but different names.
+/// - New `opt_TYPE` field types (e.g. `opt_u32`) for [`write_event!`] that log an
+///   `Option<T>` value as an array with 0 or 1 elements.
+/// - New feature `keyword_levels` with `Provider::set_keyword_level` and
+///   `Provider::set_default_keyword_level` methods for restricting the levels allowed
+///   by [`Provider::enabled`] per keyword.
+/// - New [`Provider::set_event_filter`] method for suppressing specific events at
+///   runtime. [`EventDescriptor`] is now exported from the crate root.
+/// - New feature `write_hook` with `Provider::set_pre_write_hook` method for
+///   observing events (descriptor and sizes) before they are written.
+/// - New [`activity_scope!`] macro and [`ActivityScope`] type for writing matching
+///   activity start and stop events.
+/// - [`Channel`], [`Level`], [`Opcode`], [`InType`], and [`OutType`] implement
+///   `TryFrom<&str>`. New feature `serde` implements `Serialize` and `Deserialize`
+///   for these types (as the canonical name or the integer value).
+/// - New feature `alloc_stats` with `StatsAllocator`, a `GlobalAlloc` wrapper that
+///   writes allocation statistics (counts and bytes by size class) as events.
+/// - New `trait(type, b"data")` option for `define_provider!` to add custom provider
+///   traits to the provider metadata.
+/// - New event macro field types `TYPE_array` (e.g. `u32_array("Name", N, &values)`)
+///   for fixed-length arrays. The element count is stored in the event metadata
+///   instead of in the event data.
+/// - New feature `export_schema` with `event_schemas()` and
+///   `Provider::event_schemas()` for enumerating the events (name, descriptor, and
+///   fields) defined by `write_event!` at runtime.
+/// - `Guid` and `EventDescriptor` are guaranteed (and statically verified) to match
+///   the layouts of the Windows `GUID` and `EVENT_DESCRIPTOR` structures. New
+///   `as_win32_ptr()` methods return pointers for passing them to C code.
+/// - New [`Provider::on_enable_changed`] sets a typed callback that receives a
+///   [`ProviderEnableState`] (enabled, max level, any/all keywords) each time the
+///   sessions listening to the provider change. New [`Provider::enable_state`] returns
+///   the current state.
+/// - New [`write_state_event!`] macro and [`StateSource`] type for writing state
+///   (rundown) events when a session sends a capture-state request. New
+///   [`Provider::add_state_source`], [`Provider::state_sources`], and
+///   [`Provider::capture_state`] methods, and [`Provider::STATE_KEYWORD`] constant.
+/// - New `error` field type for [`write_event!`] (requires `std`) that logs a
+///   `std::error::Error` and its `source()` chain as a struct with `Message` and
+///   `Sources` strings.
+/// - New [`write_event_cpp_style!`] macro that accepts the C++ `TraceLoggingWrite`
+///   field syntax (e.g. `TraceLoggingInt32(value, "Name")`) for porting C++ code.
+/// - Event and field names may contain `\"` and `\'` escape sequences.
+/// - New `keywords(NAME = VALUE, ...)` option for [`define_provider!`] that declares
+///   named keywords as constants (e.g. `MY_PROVIDER::KEYWORD_NETWORK`) and reports
+///   overlapping keyword values as compile-time errors.
+/// - New [`include_manifest!`] macro that generates a provider and typed
+///   event-writing functions from an ETW instrumentation manifest (`.man` file).
+/// - New [`EventFilterDescriptor`] type for accessing the filter data passed to a
+///   provider enable callback, and new [`Provider::enable_filter_type`] and
+///   [`Provider::payload_filter_active`] methods for checking whether a session is
+///   using ETW payload filtering.
+/// - New [`InType::fixed_size`], [`InType::is_counted`], and
+///   [`InType::is_nul_terminated`] methods for determining the size of a field value.
+/// - New feature `log-adapter` with `LogAdapter`, an implementation of `log::Log` that
+///   writes `log` crate records (message, target, module, file, and line) as events.
+/// - New `keyword_none()` option for [`write_event!`] for events with keyword 0, and new
+///   `default_keyword(VALUE)` and `warn_default_keyword()` options for
+///   [`define_provider!`] for changing the keyword used by events that do not specify
+///   one and for warning about such events.
+/// - New [`define_field_tags!`] macro that declares named field tag constants (for use
+///   in `tag(...)` options) with validated values, and new [`FieldTag`] and
+///   [`FieldTags`] types for looking up a tag's name and description by value.
+/// - New [`Provider::write_raw`] method for writing an event with pre-encoded
+///   TraceLogging metadata and data, e.g. to relog captured events under another
+///   provider.
+/// - New [`Provider::adopt_registration`] method for sharing the ETW registration of
+///   a C/C++ `TraceLoggingHProvider` with the same provider id instead of registering
+///   the provider a second time.
+/// - New `activity(start)` option for [`write_event!`] that generates a new activity
+///   id, uses it for the event (with opcode `ActivityStart`), and returns it along with
+///   the result code as a `(u32, Guid)` tuple.
+/// - New [`emit_policy!`] macro and [`EmitPolicy`] type for limiting how often a call
+///   site emits (first N occurrences, then every Mth occurrence), e.g. during error
+///   storms.
+/// - [`write_event!`] stores event metadata as a byte array constant instead of a
+///   call-site-specific structure, so identical metadata from different call sites can
+///   be merged by the compiler and linker. With `export_schema`, the schema table no
+///   longer keeps a separate copy of each event's metadata.
+/// - New `debug_ir()` option for [`write_event!`] that adds a JSON description of the
+///   event's schema to the macro expansion for use by IDE plugins and other tools.
+/// - New `binary_capped("Name", &bytes, MAX_LEN)` field type for [`write_event!`]
+///   that logs at most `MAX_LEN` bytes of a value followed by a `NameLength` field
+///   with the value's original length.
+/// - New `id_from_name_v5("NAMESPACE_GUID")` option for [`define_provider!`] that
+///   generates the provider id at compile time as an RFC 4122 version 5 (name-based)
+///   UUID. `define_provider!` now rejects an empty provider name.
+/// - New `sample_rate(N)` option for [`write_event!`] that writes only 1 of every N
+///   occurrences of the event, using a per-call-site atomic counter.
+/// - With feature `export_schema`, [`define_provider!`] records each provider in a
+///   table returned by `provider_schemas()`. New `duplicate_providers()` function and
+///   `test_unique_providers!` macro for detecting a crate that defines the same
+///   provider (same name or id) more than once.
+/// - New [`ProviderMetadata`] type and [`Provider::metadata`] method for parsing a
+///   provider's encoded metadata into its name, group id, and traits.
+/// - New [`Provider::try_register`] and [`Provider::try_register_with_callback`]
+///   methods that return a [`RegisterError`] indicating whether `EventRegister` or
+///   setting the provider traits failed. If registration fails at either step, the
+///   provider is now left unregistered (previously a failure to set the traits was
+///   ignored). A failure because the operating system does not support provider
+///   traits (`ERROR_NOT_SUPPORTED`, or `STATUS_NOT_SUPPORTED` in kernel mode) is
+///   still not an error.
+/// - `write_event!` field values may be given as a closure with no parameters, e.g.
+///   `str8("Detail", || render_detail())`. The closure is invoked only if the event
+///   is enabled.
+/// - New [`Provider::enabled_info`] method that queries ETW for the sessions that
+///   have the provider enabled in this process, reporting each session's logger id,
+///   level, and keywords ([`ProviderEnabledInfo`], [`SessionEnableInfo`]). Uses
+///   `EnumerateTraceGuidsEx`, so it is only supported on user-mode Windows.
+/// - New [`mark!`] macro for writing marker events (navigation anchors for WPA or
+///   TTD) with a standard keyword ([`Provider::MARKER_KEYWORD`]), a process-wide
+///   sequence number, and a QPC timestamp.
+/// - New `fmt` field type for [`write_event!`] that logs a `format_args!` value as
+///   a UTF-8 string without allocating a `String`. Text longer than 512 bytes is
+///   truncated.
+/// - New `caller_codepointer` field type for [`write_event!`] that logs the return
+///   address of a caller N frames up the stack, so that library events can identify
+///   the code that called the library without capturing a full stack.
+/// - `win_sid` fields no longer panic if the value is not a valid SID (e.g. if it is
+///   shorter than its sub-authority count requires). The NULL SID (`S-1-0-0`) is
+///   logged instead.
+/// - New `#[derive(TraceLoggingEvent)]` macro ([`TraceLoggingEvent`]) that generates
+///   a `write(&self, provider)` method for a struct, writing the struct's fields as
+///   an event. Field types are inferred from the Rust types or specified with
+///   `#[event(...)]` attributes.
+/// - New loom model checks (`tests/loom.rs`, run with `RUSTFLAGS="--cfg loom"`) for
+///   the provider enable state, covering races between enable callbacks, the disable
+///   done by unregistration, and writers, and for the table of adopted C/C++
+///   providers, covering races between adopting, unadopting, and forwarded enable
+///   callbacks. The ETW registration itself is not modeled; a new `stress` example
+///   races registration, enable-changed callbacks, and writes on multiple threads.
+/// - `define_provider!` reports an error for `trait(1, ...)` (the provider group
+///   trait). Use `group_id("GUID")` instead.
+/// - New `unregistered_writes` feature: [`Provider::unregistered_writes`] counts
+///   events that were written while the provider was not registered, e.g. writes
+///   from a component that starts logging before its provider is registered. The
+///   `unregistered_writes_panic` feature also fails a `debug_assert!` on each such
+///   write.
+/// - New [`EventControlCode`] type for interpreting the `event_control_code` parameter
+///   of a [`ProviderEnableCallback`] (`DisableProvider`, `EnableProvider`,
+///   `CaptureState`).
+/// - New `define_provider!` option `keywords_mask(MASK)` reserves the keyword bits
+///   that the provider's events may use. `write_event!` fails to build if an event's
+///   keyword has bits outside the mask (the state and marker keyword bits are always
+///   allowed). The effective mask is available as `PROVIDER::KEYWORDS_MASK`.
+/// - New `stats` feature: [`Provider::stats`] returns counts of the events that were
+///   written, filtered (not enabled or rejected by the event filter), and failed,
+///   along with the most recent error code, e.g. to detect sessions that drop events
+///   because their buffers are full.
+/// - New `write_event!` field types `char32`, `char32_slice`, `char32_array`,
+///   `opt_char32`, and `str32` for logging Rust `char` values as UTF-32 characters
+///   (U32 + String). `#[derive(TraceLoggingEvent)]` maps `char` fields to `char32`.
+/// - New `write_event!` raw field type `raw_custom("Name", PROTOCOL, SCHEMA, VALUE)`
+///   for adding custom-serialized fields (e.g. Bond or protobuf values). The macro
+///   writes the `u16` size prefixes for the schema and the value.
+/// - New [`include_event_catalog!`] macro that generates a provider and typed
+///   event-writing functions from a JSON event catalog, so a team can enforce a
+///   reviewed list of events instead of free-form `write_event!` calls.
+/// - New [`Provider::register_with_options`] method and [`RegisterOptions`] type for
+///   registering with a custom enable callback and context and for retrying
+///   `EventRegister` after transient failures such as `ERROR_BUSY`. Returns a
+///   [`RegisterError`] with the Win32 error code.
+/// - Events can be removed at compile time (not just disabled at runtime) by setting
+///   the `TRACELOGGING_MIN_LEVEL` and/or `TRACELOGGING_KEYWORD_MASK` environment
+///   variables when building. See [`write_event!`](crate::write_event).
+/// - New `binary_ptr_len` field type for logging a buffer given as a raw pointer and
+///   length (e.g. from a C caller) without creating a slice. Requires `unsafe`.
+/// - Guid: Added `from_name_with_namespace()` for RFC 4122 name-based (version 5)
+///   UUIDs, and the standard `NAMESPACE_DNS`, `NAMESPACE_URL`, `NAMESPACE_OID`, and
+///   `NAMESPACE_X500` namespaces.
+/// - New [`WriteFlags`] type (`EVENT_WRITE_FLAG_*` values) for use with
+///   `EventWriteEx`.
+/// - New [`WriteError`] type for handling the result of writing an event without
+///   hard-coding Win32 error codes, e.g. `WriteError::check(write_event!(...))`.
+///   [`RegisterError`] can be converted to its Win32 error code with `u32::from`.
+///   Both types are `#[non_exhaustive]` (so that new failure kinds can be added
+///   without a breaking change) and implement `Hash`.
+/// - New `str8_slice` and `str16_slice` field types for logging an array of
+///   strings, e.g. `str8_slice("Args", &["a", "b"])`, without raw fields.
+/// - New [`define_provider_set!`] macro and [`ProviderSet`] type for writing each
+///   event to several providers, e.g. a telemetry provider and a debugging
+///   provider. The event is built once and written to each enabled provider.
+/// - Requires `tracelogging_macros` 1.3.0, which generates code for the new
+///   internal event-writing interface used by `ProviderSet`.
+/// - With feature `export_schema`, new `write_schema_artifact()` function that
+///   writes the module's provider and event schemas as text for the new
+///   `cargo-tracelog` tool, which lists the events, generates a WPR profile or an
+///   instrumentation manifest, and diffs the events of two builds.
+pub mod v2_0_0 {}
+
+/// # v1.2.2 (2024-05-20)
+/// - tracelogging crate supports use in kernel mode via feature
+///   `kernel_mode`.
+pub mod v1_2_2 {}
+
+/// # v1.2.1 (2023-06-29)
+/// - Guid: Fix `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
+pub mod v1_2_1 {}
+
+/// # v1.2.0 (2023-05-15)
+/// - In event macros, `activity_id` and `related_id` values can now be either
+///   `&Guid` or `&[u8; 16]`.
+/// - New event macro field types `errno`, `errno_slice`, `time32`, and `time64`
+///   (for compatibility with eventheader).
+/// - New provider macro option `group_name` (for compatibility with eventheader).
+/// - Guid: Added `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
+/// - Better macro parse error reporting.
+/// - Opcode enum names clarified.
+pub mod v1_2_0 {}
+
+/// # v1.1.0 (2023-03-24)
+/// - Relax multithreading precondition on [`Provider::unregister`]. Now,
+///   only [`Provider::register`] has special preconditions.
+pub mod v1_1_0 {}
+
+/// # v1.0.2 (2023-03-13, Breaking)
+/// - **Breaking:** Rename the `filetime_from_systemtime` macro to
+///   [`win_filetime_from_systemtime`].
+/// - Improve the doc comments for [`win_filetime_from_systemtime`].
+/// - Remove "For testing" macros from `tracelogging_macros` crate.
+pub mod v1_0_2 {}
+
+/// # v1.0.1 (2023-03-13)
+/// - Add `filetime_from_systemtime` macro.
+/// - Optimization: Split internal `filetime_from_duration` function into separate
+///   before-1970 and after-1970 functions.
+pub mod v1_0_1 {}
+
+/// # v0.1.0 (2022-08-13)
+/// - Initial release.
+pub mod v0_1_0 {}
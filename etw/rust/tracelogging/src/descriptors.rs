@@ -1,14 +1,24 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+use core::ffi::c_void;
 use core::marker::PhantomData;
+use core::mem::align_of;
 use core::mem::size_of;
+use core::mem::transmute;
+use core::slice;
 
 use crate::enums::Channel;
 use crate::enums::Level;
 use crate::enums::Opcode;
 
 /// Characteristics of an ETW event: severity level, category bits, etc.
+///
+/// The layout of `EventDescriptor` is guaranteed to match the Windows
+/// [EVENT_DESCRIPTOR](https://learn.microsoft.com/windows/win32/api/evntprov/ns-evntprov-event_descriptor)
+/// structure (16 bytes, same alignment as `u64`, same field order and offsets), so a reference
+/// to an `EventDescriptor` can be passed to C code that expects a
+/// `const EVENT_DESCRIPTOR*` (see [`EventDescriptor::as_win32_ptr`]).
 #[repr(C)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct EventDescriptor {
@@ -105,6 +115,109 @@ impl EventDescriptor {
             keyword,
         };
     }
+
+    /// Returns a pointer to this descriptor for use as a `PCEVENT_DESCRIPTOR`
+    /// (`const EVENT_DESCRIPTOR*`) when calling C code.
+    pub const fn as_win32_ptr(&self) -> *const c_void {
+        return self as *const Self as *const c_void;
+    }
+}
+
+// Verify that EventDescriptor matches the EVENT_DESCRIPTOR layout.
+const _: () = {
+    assert!(size_of::<EventDescriptor>() == 16);
+    assert!(align_of::<EventDescriptor>() == align_of::<u64>());
+
+    let descriptor = EventDescriptor::from_parts(
+        0x0201,
+        0x03,
+        Channel::from_int(0x04),
+        Level::from_int(0x05),
+        Opcode::from_int(0x06),
+        0x0807,
+        0x100F0E0D0C0B0A09,
+    );
+    let bytes: [u8; 16] = unsafe { transmute(descriptor) };
+    let id = 0x0201u16.to_ne_bytes();
+    let task = 0x0807u16.to_ne_bytes();
+    let keyword = 0x100F0E0D0C0B0A09u64.to_ne_bytes();
+    let expected: [u8; 16] = [
+        id[0], id[1], // Id
+        0x03, 0x04, 0x05, 0x06, // Version, Channel, Level, Opcode
+        task[0], task[1], // Task
+        keyword[0], keyword[1], keyword[2], keyword[3], keyword[4], keyword[5], keyword[6],
+        keyword[7],
+    ];
+    let mut i = 0;
+    while i != 16 {
+        assert!(
+            bytes[i] == expected[i],
+            "EventDescriptor must match EVENT_DESCRIPTOR"
+        );
+        i += 1;
+    }
+};
+
+/// Describes a filter that an ETW session specified when it enabled a provider. The
+/// layout matches the Windows
+/// [EVENT_FILTER_DESCRIPTOR](https://learn.microsoft.com/windows/win32/api/evntprov/ns-evntprov-event_filter_descriptor)
+/// structure.
+///
+/// A custom [`ProviderEnableCallback`](crate::ProviderEnableCallback) receives the
+/// filter as a `filter_data: usize` parameter. Use
+/// [`EventFilterDescriptor::from_filter_data`] to access it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventFilterDescriptor {
+    /// Address of the filter data.
+    pub ptr: u64,
+
+    /// Size of the filter data, in bytes.
+    pub size: u32,
+
+    /// Type of the filter, e.g. [`EventFilterDescriptor::TYPE_PAYLOAD`].
+    pub filter_type: u32,
+}
+
+impl EventFilterDescriptor {
+    /// `EVENT_FILTER_TYPE_SCHEMATIZED`: provider-defined filter data.
+    pub const TYPE_SCHEMATIZED: u32 = 0x80000000;
+    /// `EVENT_FILTER_TYPE_PID`: filter by process id (handled by ETW).
+    pub const TYPE_PID: u32 = 0x80000004;
+    /// `EVENT_FILTER_TYPE_EXECUTABLE_NAME`: filter by executable name (handled by ETW).
+    pub const TYPE_EXECUTABLE_NAME: u32 = 0x80000008;
+    /// `EVENT_FILTER_TYPE_PAYLOAD`: filter by field values (handled by ETW).
+    pub const TYPE_PAYLOAD: u32 = 0x80000100;
+    /// `EVENT_FILTER_TYPE_EVENT_ID`: filter by event id (handled by ETW).
+    pub const TYPE_EVENT_ID: u32 = 0x80000200;
+    /// `EVENT_FILTER_TYPE_EVENT_NAME`: filter by event name (handled by ETW).
+    pub const TYPE_EVENT_NAME: u32 = 0x80000400;
+
+    /// Returns the descriptor referenced by the `filter_data` parameter of a
+    /// [`ProviderEnableCallback`](crate::ProviderEnableCallback), or `None` if
+    /// `filter_data` is 0 (no filter).
+    ///
+    /// # Safety
+    ///
+    /// `filter_data` must be 0 or the `filter_data` parameter of an enable callback
+    /// that is still running.
+    pub unsafe fn from_filter_data<'a>(filter_data: usize) -> Option<&'a Self> {
+        return (filter_data as *const Self).as_ref();
+    }
+
+    /// Returns the filter data.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` and `size` must describe valid memory, e.g. this descriptor was returned
+    /// by [`EventFilterDescriptor::from_filter_data`] during an enable callback.
+    pub unsafe fn data(&self) -> &[u8] {
+        return if self.size == 0 {
+            &[]
+        } else {
+            slice::from_raw_parts(self.ptr as usize as *const u8, self.size as usize)
+        };
+    }
 }
 
 /// Describes a block of data to be sent to ETW via EventWrite.
@@ -129,6 +242,18 @@ impl<'a> EventDataDescriptor<'a> {
         };
     }
 
+    /// Returns the size of the referenced data, in bytes.
+    #[cfg(feature = "write_hook")]
+    pub(crate) const fn size(&self) -> u32 {
+        return self.size;
+    }
+
+    /// Returns the value of the reserved field (the descriptor type).
+    #[cfg(feature = "write_hook")]
+    pub(crate) const fn reserved(&self) -> u32 {
+        return self.reserved;
+    }
+
     /// Returns an EventDataDescriptor initialized with the specified value's bytes.
     /// Sets the reserved field to 0.
     pub fn from_value<T: Copy>(value: &'a T) -> Self {
@@ -142,20 +267,12 @@ impl<'a> EventDataDescriptor<'a> {
 
     /// Returns an EventDataDescriptor for a sid.
     /// Sets the reserved field to 0.
-    /// Requires value.len() >= sid_length(value).
-    pub fn from_sid(mut value: &'a [u8]) -> Self {
-        let value_len = value.len();
-
-        debug_assert!(4 <= value_len, "add_sid(value) requires value.len() >= 4");
-        let len = 8 + 4 * (value[1] as usize); // May panic if precondition not met.
-
-        if len != value_len {
-            debug_assert!(
-                len <= value_len,
-                "add_sid(value) requires value.len() >= sid_length(value)"
-            );
-            value = &value[..len]; // May panic if precondition not met.
-        }
+    /// If `sid_length(value)` is `None`, returns a descriptor for [`NULL_SID`].
+    pub fn from_sid(value: &'a [u8]) -> Self {
+        let value = match sid_length(value) {
+            Some(len) => &value[..len],
+            None => &NULL_SID,
+        };
 
         return Self {
             ptr: value.as_ptr() as usize as u64,
@@ -214,6 +331,23 @@ impl<'a> EventDataDescriptor<'a> {
         };
     }
 
+    /// Returns an EventDataDescriptor for a counted field (binary) from a pointer and a
+    /// byte length, e.g. a buffer received from C code. The length is capped to 65535.
+    /// Sets the reserved field to 0.
+    ///
+    /// # Safety
+    ///
+    /// Unless `len` is 0, `ptr` must be valid for reads of `min(len, 65535)` bytes for
+    /// the lifetime `'a`.
+    pub unsafe fn from_ptr_len(ptr: *const u8, len: usize) -> Self {
+        return Self {
+            ptr: ptr as usize as u64,
+            size: safe_len(65535, len) as u32,
+            reserved: 0,
+            lifetime: PhantomData,
+        };
+    }
+
     /// Returns an EventDataDescriptor for variable-length array field.
     /// Sets the reserved field to 0.
     pub fn from_slice<T: Copy>(mut value: &'a [T]) -> Self {
@@ -241,11 +375,55 @@ pub fn counted_size<T>(value: &[T]) -> u16 {
     return (size_of::<T>() as u16) * len;
 }
 
+/// Returns the size for a counted field created by
+/// [`EventDataDescriptor::from_ptr_len`].
+pub fn ptr_len_size(value: &EventDataDescriptor) -> u16 {
+    return value.size as u16;
+}
+
+/// The NULL SID (`S-1-0-0`), logged in place of a malformed sid value.
+pub static NULL_SID: [u8; 12] = [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+/// Returns the size of the sid at the start of `value`, i.e.
+/// `8 + 4 * SubAuthorityCount`, or `None` if `value` does not start with a valid sid
+/// (revision 1, at most 15 sub-authorities, and `value.len()` at least the size of
+/// the sid).
+pub fn sid_length(value: &[u8]) -> Option<usize> {
+    const SID_REVISION: u8 = 1;
+    const SID_MAX_SUB_AUTHORITIES: u8 = 15;
+
+    if value.len() < 8 || value[0] != SID_REVISION || value[1] > SID_MAX_SUB_AUTHORITIES {
+        return None;
+    }
+
+    let len = 8 + 4 * (value[1] as usize);
+    return if len <= value.len() { Some(len) } else { None };
+}
+
 /// Returns the count for a variable-length array field.
 pub fn slice_count<T>(value: &[T]) -> u16 {
     return safe_len(65535, value.len());
 }
 
+/// Returns the first `max_len` bytes of the value of a capped binary field.
+pub fn capped_slice(value: &[u8], max_len: u16) -> &[u8] {
+    return &value[..value.len().min(max_len as usize)];
+}
+
+/// Returns the original length of the value of a capped binary field, saturated to
+/// `u32::MAX`.
+pub fn capped_length(value: &[u8]) -> u32 {
+    return u32::try_from(value.len()).unwrap_or(u32::MAX);
+}
+
+/// Returns a 0-length or 1-length slice for an optional field.
+pub fn option_as_slice<T>(value: &Option<T>) -> &[T] {
+    return match value {
+        Some(value) => slice::from_ref(value),
+        None => &[],
+    };
+}
+
 const fn safe_len(max_len: u16, len: usize) -> u16 {
     return if (max_len as usize) < len {
         max_len
@@ -0,0 +1,83 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+
+#[allow(unused_imports)] // For docs
+use crate::emit_policy;
+
+/// Emission policy for a call site that may run many times in quick succession, e.g.
+/// an error path during an error storm: emit the first `first` occurrences, then
+/// emit only every `then_every`th occurrence.
+///
+/// Normally used via [`emit_policy!`], which creates a `static` policy for the call
+/// site. The policy is implemented with a single atomic counter, so it may be used
+/// from any number of threads.
+///
+/// For example, with `EmitPolicy::new(3, 10)`, occurrences 1, 2, 3, 13, 23, 33, ...
+/// are emitted. Each emitted occurrence after the first 3 summarizes the 9 suppressed
+/// occurrences before it. With `then_every == 0`, only the first `first` occurrences
+/// are emitted.
+pub struct EmitPolicy {
+    first: u64,
+    then_every: u64,
+    count: AtomicU64,
+}
+
+impl EmitPolicy {
+    /// Creates a policy that emits the first `first` occurrences, then every
+    /// `then_every`th occurrence (never, if `then_every == 0`).
+    pub const fn new(first: u64, then_every: u64) -> Self {
+        return Self {
+            first,
+            then_every,
+            count: AtomicU64::new(0),
+        };
+    }
+
+    /// Returns the number of occurrences that are always emitted.
+    pub const fn first(&self) -> u64 {
+        return self.first;
+    }
+
+    /// Returns the interval at which occurrences after the first `first` are
+    /// emitted, or 0 if they are never emitted.
+    pub const fn then_every(&self) -> u64 {
+        return self.then_every;
+    }
+
+    /// Returns the number of occurrences recorded by [`EmitPolicy::check`].
+    pub fn count(&self) -> u64 {
+        return self.count.load(Ordering::Relaxed);
+    }
+
+    /// Sets the number of occurrences to 0, so the next `first` occurrences are
+    /// emitted.
+    pub fn reset(&self) {
+        self.count.store(0, Ordering::Relaxed);
+    }
+
+    /// Records an occurrence. Returns `Some(n)` if the occurrence should be emitted,
+    /// where `n` is the total number of occurrences so far (including this one), or
+    /// `None` if the occurrence should be suppressed.
+    pub fn check(&self) -> Option<u64> {
+        let n = self.count.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+        let emit =
+            n <= self.first || (self.then_every != 0 && (n - self.first) % self.then_every == 0);
+        return if emit { Some(n) } else { None };
+    }
+}
+
+impl fmt::Debug for EmitPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "EmitPolicy {{ first: {}, then_every: {}, count: {} }}",
+            self.first,
+            self.then_every,
+            self.count()
+        );
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+#[cfg(all(target_has_atomic = "64", not(loom)))]
+use core::hint;
+#[cfg(all(target_has_atomic = "64", not(loom)))]
+use core::sync::atomic;
+
+// Under `--cfg loom`, use loom's atomics so that tests/loom.rs can explore the
+// interleavings of the sequence lock.
+#[cfg(all(target_has_atomic = "64", loom))]
+use loom::hint;
+#[cfg(all(target_has_atomic = "64", loom))]
+use loom::sync::atomic;
+
+use crate::enums::Level;
+#[cfg(target_has_atomic = "64")]
+use crate::spin_lock::SpinLock;
+
+/// The level/keyword state of a provider, as last set by an ETW enable callback.
+///
+/// ETW may invoke a provider's enable callback on any thread at any time, including
+/// while other threads are checking whether the provider is enabled. The state is
+/// protected by a sequence lock so that readers always observe a consistent
+/// level + keyword_any + keyword_all snapshot:
+///
+/// - Updates are serialized by the `updating` lock. Only one enable callback at a
+///   time can modify the state (and run the user's callback).
+/// - Each state change increments `sequence` to an odd value, stores the new
+///   values, then increments `sequence` to an even value (Release).
+/// - Readers load `sequence` (Acquire), load the values, then re-check `sequence`.
+///   If `sequence` was odd or changed, the read raced with an update and is retried.
+///
+/// Enable notifications that do not change the level or keywords are coalesced: they
+/// do not modify the state and do not increment the generation.
+#[cfg(target_has_atomic = "64")]
+pub struct EnableState {
+    sequence: atomic::AtomicU32, // Odd while an update is in progress.
+    updating: SpinLock<()>,      // Held while an enable callback is running.
+    level: atomic::AtomicI32,    // -1 means not enabled by anybody.
+    keyword_any: atomic::AtomicU64,
+    keyword_all: atomic::AtomicU64,
+}
+
+/// A consistent snapshot of an [`EnableState`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct EnableSnapshot {
+    /// Number of state changes that have been applied (wraps on overflow).
+    pub generation: u32,
+    /// Enabled level, or -1 if not enabled.
+    pub level: i32,
+    /// Enabled keyword_any bits.
+    pub keyword_any: u64,
+    /// Enabled keyword_all bits.
+    pub keyword_all: u64,
+}
+
+impl EnableSnapshot {
+    /// A snapshot with generation 0 and level -1 (not enabled).
+    pub const DISABLED: Self = Self {
+        generation: 0,
+        level: -1,
+        keyword_any: 0,
+        keyword_all: 0,
+    };
+
+    /// Returns true if the snapshot is enabled at the specified level and keyword.
+    #[inline(always)]
+    pub const fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return (level.0 as i32) <= self.level
+            && (keyword == 0
+                || ((keyword & self.keyword_any) != 0
+                    && (keyword & self.keyword_all) == self.keyword_all));
+    }
+}
+
+/// The level and keywords with which ETW sessions have enabled a provider, as passed
+/// to an [`EnableChangedCallback`](crate::EnableChangedCallback) or returned by
+/// [`Provider::enable_state`](crate::Provider::enable_state).
+///
+/// This reflects the combined state of all sessions that are listening to the
+/// provider. It does not include the provider's own keyword-level overrides (see
+/// `Provider::set_keyword_level`, feature `keyword_levels`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProviderEnableState {
+    level: i32, // -1 means not enabled by anybody.
+    any_keyword: u64,
+    all_keyword: u64,
+}
+
+impl ProviderEnableState {
+    /// The state of a provider that is not enabled by any session.
+    pub const DISABLED: Self = Self {
+        level: -1,
+        any_keyword: 0,
+        all_keyword: 0,
+    };
+
+    pub(crate) const fn from_snapshot(snapshot: &EnableSnapshot) -> Self {
+        return Self {
+            level: snapshot.level,
+            any_keyword: snapshot.keyword_any,
+            all_keyword: snapshot.keyword_all,
+        };
+    }
+
+    /// Returns true if at least one session has enabled the provider.
+    pub const fn is_enabled(&self) -> bool {
+        return self.level >= 0;
+    }
+
+    /// Returns the most verbose level enabled by any session, or
+    /// [`Level::LogAlways`] (0) if the provider is not enabled.
+    pub const fn max_level(&self) -> Level {
+        return if self.level >= 0 {
+            Level(self.level as u8)
+        } else {
+            Level::LogAlways
+        };
+    }
+
+    /// Returns the keyword bits of which an event must have at least one to be
+    /// enabled (`MatchAnyKeyword`). An event with keyword 0 matches any value.
+    pub const fn any_keyword(&self) -> u64 {
+        return self.any_keyword;
+    }
+
+    /// Returns the keyword bits that an event must have all of to be enabled
+    /// (`MatchAllKeyword`).
+    pub const fn all_keyword(&self) -> u64 {
+        return self.all_keyword;
+    }
+
+    /// Returns true if an event with the specified level and keyword would be enabled
+    /// by this state.
+    pub const fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return EnableSnapshot {
+            generation: 0,
+            level: self.level,
+            keyword_any: self.any_keyword,
+            keyword_all: self.all_keyword,
+        }
+        .enabled(level, keyword);
+    }
+}
+
+impl Default for ProviderEnableState {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl EnableState {
+    /// Creates a new state with level = -1 (not enabled) and generation = 0.
+    #[cfg(not(loom))]
+    pub const fn new() -> Self {
+        return Self {
+            sequence: atomic::AtomicU32::new(0),
+            updating: SpinLock::new(()),
+            level: atomic::AtomicI32::new(-1),
+            keyword_any: atomic::AtomicU64::new(0),
+            keyword_all: atomic::AtomicU64::new(0),
+        };
+    }
+
+    /// Creates a new state with level = -1 (not enabled) and generation = 0.
+    /// (Not `const` because loom's atomics cannot be created in a const context.)
+    #[cfg(loom)]
+    pub fn new() -> Self {
+        return Self {
+            sequence: atomic::AtomicU32::new(0),
+            updating: SpinLock::new(()),
+            level: atomic::AtomicI32::new(-1),
+            keyword_any: atomic::AtomicU64::new(0),
+            keyword_all: atomic::AtomicU64::new(0),
+        };
+    }
+
+    /// Returns the number of state changes that have been applied (wraps on
+    /// overflow). This increments each time an enable callback changes the level or
+    /// keywords, so callers can cheaply detect that cached decisions may be stale.
+    pub fn generation(&self) -> u32 {
+        return self.snapshot().generation;
+    }
+
+    /// Returns true if enabled at the specified level and keyword.
+    #[inline(always)]
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
+        // Fast path: level is a single atomic, so a disabled result can be
+        // returned without taking a full snapshot.
+        if (level.0 as i32) > self.level.load(atomic::Ordering::Relaxed) {
+            return false;
+        }
+
+        return self.snapshot().enabled(level, keyword);
+    }
+
+    /// Returns a consistent snapshot of the current state.
+    pub fn snapshot(&self) -> EnableSnapshot {
+        loop {
+            let seq1 = self.sequence.load(atomic::Ordering::Acquire);
+            if seq1 & 1 == 0 {
+                let level = self.level.load(atomic::Ordering::Relaxed);
+                let keyword_any = self.keyword_any.load(atomic::Ordering::Relaxed);
+                let keyword_all = self.keyword_all.load(atomic::Ordering::Relaxed);
+                atomic::fence(atomic::Ordering::Acquire);
+                let seq2 = self.sequence.load(atomic::Ordering::Relaxed);
+                if seq1 == seq2 {
+                    return EnableSnapshot {
+                        generation: seq1 >> 1,
+                        level,
+                        keyword_any,
+                        keyword_all,
+                    };
+                }
+            }
+
+            hint::spin_loop();
+        }
+    }
+
+    /// Applies an enable notification, then invokes `then` while updates are still
+    /// serialized (i.e. no other call to `update` will run until `then` returns).
+    ///
+    /// `then` runs while the `updating` lock is held. The lock is not reentrant, so
+    /// `then` must not call `update` (directly, or by unregistering the provider)
+    /// and must not wait for a thread that calls `update`, or it will spin forever.
+    ///
+    /// - event_control_code 0 (disable): level = -1.
+    /// - event_control_code 1 (enable): level, keyword_any, keyword_all updated.
+    /// - Other control codes do not change the state.
+    pub fn update<F: FnOnce()>(
+        &self,
+        event_control_code: u32,
+        level: u8,
+        keyword_any: u64,
+        keyword_all: u64,
+        then: F,
+    ) {
+        let _updating = self.updating.lock();
+
+        // We hold the update lock, so Relaxed loads see our own latest values.
+        let old_level = self.level.load(atomic::Ordering::Relaxed);
+        let old_any = self.keyword_any.load(atomic::Ordering::Relaxed);
+        let old_all = self.keyword_all.load(atomic::Ordering::Relaxed);
+        let (new_level, new_any, new_all) = match event_control_code {
+            0 => (-1, old_any, old_all),
+            1 => (level as i32, keyword_any, keyword_all),
+            _ => (old_level, old_any, old_all),
+        };
+
+        if new_level != old_level || new_any != old_any || new_all != old_all {
+            let seq = self.sequence.load(atomic::Ordering::Relaxed);
+            self.sequence
+                .store(seq.wrapping_add(1), atomic::Ordering::Relaxed);
+            atomic::fence(atomic::Ordering::Release);
+            self.level.store(new_level, atomic::Ordering::Relaxed);
+            self.keyword_any.store(new_any, atomic::Ordering::Relaxed);
+            self.keyword_all.store(new_all, atomic::Ordering::Relaxed);
+            self.sequence
+                .store(seq.wrapping_add(2), atomic::Ordering::Release);
+        }
+
+        then();
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl Default for EnableState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -5,6 +5,19 @@
 
 use core::fmt;
 use core::mem::size_of;
+use core::ops;
+use core::str;
+
+/// The error returned when parsing a [Channel], [EventControlCode], [Level], [Opcode],
+/// [InType], or [OutType] from a string that is neither a recognized name nor an integer.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ParseEnumError;
+
+impl fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.write_str("unrecognized name");
+    }
+}
 
 /// *Advanced:* Indicates routing and decoding for an event.
 ///
@@ -30,6 +43,28 @@ impl Channel {
         return self.0;
     }
 
+    /// Returns the canonical name of this channel (e.g. `"TraceLogging"`), or `None`
+    /// if the value does not have a name.
+    pub const fn as_str(self) -> Option<&'static str> {
+        return match self.0 {
+            0 => Some("TraceClassic"),
+            11 => Some("TraceLogging"),
+            12 => Some("ProviderMetadata"),
+            _ => None,
+        };
+    }
+
+    /// Returns the channel with the specified canonical name (case-sensitive), or
+    /// `None` if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<Channel> {
+        return match name {
+            "TraceClassic" => Some(Self::TraceClassic),
+            "TraceLogging" => Some(Self::TraceLogging),
+            "ProviderMetadata" => Some(Self::ProviderMetadata),
+            _ => None,
+        };
+    }
+
     /// Channel for non-TraceLogging events.
     pub const TraceClassic: Channel = Channel(0);
 
@@ -45,8 +80,36 @@ impl Channel {
 }
 
 impl fmt::Display for Channel {
+    /// Formats the channel's canonical name if it has one, otherwise its integer value.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        return self.0.fmt(f);
+        return match self.as_str() {
+            Some(name) => f.write_str(name),
+            None => self.0.fmt(f),
+        };
+    }
+}
+
+impl str::FromStr for Channel {
+    type Err = ParseEnumError;
+
+    /// Parses a canonical name (see [`Channel::from_name`]) or an integer value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match Self::from_name(s) {
+            Some(value) => Ok(value),
+            None => match s.parse::<u8>() {
+                Ok(value) => Ok(Self::from(value)),
+                Err(_) => Err(ParseEnumError),
+            },
+        };
+    }
+}
+
+impl TryFrom<&str> for Channel {
+    type Error = ParseEnumError;
+
+    /// Parses a canonical name (see [`Channel::from_name`]) or an integer value.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return s.parse();
     }
 }
 
@@ -62,6 +125,168 @@ impl From<Channel> for u8 {
     }
 }
 
+/// The reason for an invocation of a provider's enable callback, i.e. the
+/// `event_control_code` parameter of a [`ProviderEnableCallback`](crate::ProviderEnableCallback).
+///
+/// Use `EventControlCode::from(event_control_code)` in a custom enable callback to
+/// tell an enable notification from a disable notification or from a capture-state
+/// request. Values other than the named constants are passed through unchanged.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EventControlCode(u32);
+
+impl EventControlCode {
+    /// Returns a control code with the specified value.
+    #[inline(always)]
+    pub const fn from_int(value: u32) -> EventControlCode {
+        return EventControlCode(value);
+    }
+
+    /// Returns the integer value of this control code.
+    #[inline(always)]
+    pub const fn as_int(self) -> u32 {
+        return self.0;
+    }
+
+    /// Returns the canonical name of this control code (e.g. `"CaptureState"`), or
+    /// `None` if the value does not have a name.
+    pub const fn as_str(self) -> Option<&'static str> {
+        return match self.0 {
+            0 => Some("DisableProvider"),
+            1 => Some("EnableProvider"),
+            2 => Some("CaptureState"),
+            _ => None,
+        };
+    }
+
+    /// Returns the control code with the specified canonical name (case-sensitive),
+    /// or `None` if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<EventControlCode> {
+        return match name {
+            "DisableProvider" => Some(Self::DisableProvider),
+            "EnableProvider" => Some(Self::EnableProvider),
+            "CaptureState" => Some(Self::CaptureState),
+            _ => None,
+        };
+    }
+
+    /// A session stopped collecting events from the provider
+    /// (`EVENT_CONTROL_CODE_DISABLE_PROVIDER`). The callback's level and keywords
+    /// are those of the sessions that are still collecting events, if any.
+    pub const DisableProvider: EventControlCode = EventControlCode(0);
+
+    /// A session started collecting events from the provider or changed its level or
+    /// keywords (`EVENT_CONTROL_CODE_ENABLE_PROVIDER`).
+    pub const EnableProvider: EventControlCode = EventControlCode(1);
+
+    /// A session requested that the provider write its current state, i.e. rundown
+    /// events (`EVENT_CONTROL_CODE_CAPTURE_STATE`). This does not change the level
+    /// or keywords with which the provider is enabled.
+    pub const CaptureState: EventControlCode = EventControlCode(2);
+}
+
+impl fmt::Display for EventControlCode {
+    /// Formats the control code's canonical name if it has one, otherwise its integer
+    /// value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self.as_str() {
+            Some(name) => f.write_str(name),
+            None => self.0.fmt(f),
+        };
+    }
+}
+
+impl str::FromStr for EventControlCode {
+    type Err = ParseEnumError;
+
+    /// Parses a canonical name (see [`EventControlCode::from_name`]) or an integer
+    /// value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match Self::from_name(s) {
+            Some(value) => Ok(value),
+            None => match s.parse::<u32>() {
+                Ok(value) => Ok(Self::from(value)),
+                Err(_) => Err(ParseEnumError),
+            },
+        };
+    }
+}
+
+impl TryFrom<&str> for EventControlCode {
+    type Error = ParseEnumError;
+
+    /// Parses a canonical name (see [`EventControlCode::from_name`]) or an integer
+    /// value.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return s.parse();
+    }
+}
+
+impl From<u32> for EventControlCode {
+    fn from(val: u32) -> Self {
+        return Self(val);
+    }
+}
+
+impl From<EventControlCode> for u32 {
+    fn from(val: EventControlCode) -> Self {
+        return val.0;
+    }
+}
+
+/// *Advanced:* Flags for writing an event with `EventWriteEx`, i.e. the `Flags`
+/// parameter of `EventWriteEx` (`EVENT_WRITE_FLAG_*` values). Combine flags with `|`.
+///
+/// Most events should use [WriteFlags::None].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WriteFlags(u32);
+
+impl WriteFlags {
+    /// Returns flags with the specified value.
+    #[inline(always)]
+    pub const fn from_int(value: u32) -> WriteFlags {
+        return WriteFlags(value);
+    }
+
+    /// Returns the integer value of these flags.
+    #[inline(always)]
+    pub const fn as_int(self) -> u32 {
+        return self.0;
+    }
+
+    /// No flags. The event is written the same way as by `EventWriteTransfer`.
+    pub const None: WriteFlags = WriteFlags(0);
+
+    /// Do not raise a fault if the event data cannot be read, e.g. when writing
+    /// from kernel mode with user-mode data (`EVENT_WRITE_FLAG_NO_FAULTING`).
+    pub const NoFaulting: WriteFlags = WriteFlags(1);
+
+    /// The event contains data from an in-private (e.g. private browsing) context.
+    /// Sessions that enabled the provider with `EVENT_ENABLE_PROPERTY_EXCLUDE_INPRIVATE`
+    /// will not receive the event (`EVENT_WRITE_FLAG_INPRIVATE`).
+    pub const InPrivate: WriteFlags = WriteFlags(2);
+}
+
+impl ops::BitOr for WriteFlags {
+    type Output = WriteFlags;
+    fn bitor(self, rhs: WriteFlags) -> WriteFlags {
+        return WriteFlags(self.0 | rhs.0);
+    }
+}
+
+impl From<u32> for WriteFlags {
+    fn from(val: u32) -> Self {
+        return WriteFlags(val);
+    }
+}
+
+impl From<WriteFlags> for u32 {
+    fn from(val: WriteFlags) -> Self {
+        return val.0;
+    }
+}
+
 /// Indicates the severity of an event. Use Verbose if unsure.
 #[repr(C)]
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -80,6 +305,34 @@ impl Level {
         return self.0;
     }
 
+    /// Returns the canonical name of this level (e.g. `"Verbose"`), or `None`
+    /// if the value does not have a name.
+    pub const fn as_str(self) -> Option<&'static str> {
+        return match self.0 {
+            0 => Some("LogAlways"),
+            1 => Some("Critical"),
+            2 => Some("Error"),
+            3 => Some("Warning"),
+            4 => Some("Informational"),
+            5 => Some("Verbose"),
+            _ => None,
+        };
+    }
+
+    /// Returns the level with the specified canonical name (case-sensitive), or
+    /// `None` if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<Level> {
+        return match name {
+            "LogAlways" => Some(Self::LogAlways),
+            "Critical" => Some(Self::Critical),
+            "Error" => Some(Self::Error),
+            "Warning" => Some(Self::Warning),
+            "Informational" => Some(Self::Informational),
+            "Verbose" => Some(Self::Verbose),
+            _ => None,
+        };
+    }
+
     /// Event ignores level-based filtering. This level should almost never be used.
     pub const LogAlways: Level = Level(0);
     /// Critical error event.
@@ -95,8 +348,36 @@ impl Level {
 }
 
 impl fmt::Display for Level {
+    /// Formats the level's canonical name if it has one, otherwise its integer value.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        return self.0.fmt(f);
+        return match self.as_str() {
+            Some(name) => f.write_str(name),
+            None => self.0.fmt(f),
+        };
+    }
+}
+
+impl str::FromStr for Level {
+    type Err = ParseEnumError;
+
+    /// Parses a canonical name (see [`Level::from_name`]) or an integer value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match Self::from_name(s) {
+            Some(value) => Ok(value),
+            None => match s.parse::<u8>() {
+                Ok(value) => Ok(Self::from(value)),
+                Err(_) => Err(ParseEnumError),
+            },
+        };
+    }
+}
+
+impl TryFrom<&str> for Level {
+    type Error = ParseEnumError;
+
+    /// Parses a canonical name (see [`Level::from_name`]) or an integer value.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return s.parse();
     }
 }
 
@@ -135,6 +416,80 @@ impl Opcode {
         return self.0;
     }
 
+    /// Returns the canonical name of this opcode (e.g. `"Info"`), or `None`
+    /// if the value does not have a name.
+    pub const fn as_str(self) -> Option<&'static str> {
+        return match self.0 {
+            0 => Some("Info"),
+            1 => Some("ActivityStart"),
+            2 => Some("ActivityStop"),
+            3 => Some("CollectionStart"),
+            4 => Some("CollectionStop"),
+            5 => Some("Extension"),
+            6 => Some("Reply"),
+            7 => Some("Resume"),
+            8 => Some("Suspend"),
+            9 => Some("Send"),
+            240 => Some("Receive"),
+            241 => Some("ReservedOpcode241"),
+            242 => Some("ReservedOpcode242"),
+            243 => Some("ReservedOpcode243"),
+            244 => Some("ReservedOpcode244"),
+            245 => Some("ReservedOpcode245"),
+            246 => Some("ReservedOpcode246"),
+            247 => Some("ReservedOpcode247"),
+            248 => Some("ReservedOpcode248"),
+            249 => Some("ReservedOpcode249"),
+            250 => Some("ReservedOpcode250"),
+            251 => Some("ReservedOpcode251"),
+            252 => Some("ReservedOpcode252"),
+            253 => Some("ReservedOpcode253"),
+            254 => Some("ReservedOpcode254"),
+            255 => Some("ReservedOpcode255"),
+            _ => None,
+        };
+    }
+
+    /// Returns the opcode with the specified canonical name (case-sensitive), or
+    /// `None` if the name is not recognized.
+    ///
+    /// Also accepts the alias names `Start`, `Stop`, `DC_Start`, `DC_Stop`.
+    pub fn from_name(name: &str) -> Option<Opcode> {
+        return match name {
+            "Info" => Some(Self::Info),
+            "ActivityStart" => Some(Self::ActivityStart),
+            "ActivityStop" => Some(Self::ActivityStop),
+            "CollectionStart" => Some(Self::CollectionStart),
+            "CollectionStop" => Some(Self::CollectionStop),
+            "Extension" => Some(Self::Extension),
+            "Reply" => Some(Self::Reply),
+            "Resume" => Some(Self::Resume),
+            "Suspend" => Some(Self::Suspend),
+            "Send" => Some(Self::Send),
+            "Receive" => Some(Self::Receive),
+            "ReservedOpcode241" => Some(Self::ReservedOpcode241),
+            "ReservedOpcode242" => Some(Self::ReservedOpcode242),
+            "ReservedOpcode243" => Some(Self::ReservedOpcode243),
+            "ReservedOpcode244" => Some(Self::ReservedOpcode244),
+            "ReservedOpcode245" => Some(Self::ReservedOpcode245),
+            "ReservedOpcode246" => Some(Self::ReservedOpcode246),
+            "ReservedOpcode247" => Some(Self::ReservedOpcode247),
+            "ReservedOpcode248" => Some(Self::ReservedOpcode248),
+            "ReservedOpcode249" => Some(Self::ReservedOpcode249),
+            "ReservedOpcode250" => Some(Self::ReservedOpcode250),
+            "ReservedOpcode251" => Some(Self::ReservedOpcode251),
+            "ReservedOpcode252" => Some(Self::ReservedOpcode252),
+            "ReservedOpcode253" => Some(Self::ReservedOpcode253),
+            "ReservedOpcode254" => Some(Self::ReservedOpcode254),
+            "ReservedOpcode255" => Some(Self::ReservedOpcode255),
+            "Start" => Some(Self::Start),
+            "Stop" => Some(Self::Stop),
+            "DC_Start" => Some(Self::DC_Start),
+            "DC_Stop" => Some(Self::DC_Stop),
+            _ => None,
+        };
+    }
+
     /// Normal event. The event may set activity_id if it is part of an activity.
     pub const Info: Opcode = Opcode(0);
 
@@ -238,8 +593,36 @@ impl Opcode {
 }
 
 impl fmt::Display for Opcode {
+    /// Formats the opcode's canonical name if it has one, otherwise its integer value.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        return self.0.fmt(f);
+        return match self.as_str() {
+            Some(name) => f.write_str(name),
+            None => self.0.fmt(f),
+        };
+    }
+}
+
+impl str::FromStr for Opcode {
+    type Err = ParseEnumError;
+
+    /// Parses a canonical name (see [`Opcode::from_name`]) or an integer value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match Self::from_name(s) {
+            Some(value) => Ok(value),
+            None => match s.parse::<u8>() {
+                Ok(value) => Ok(Self::from(value)),
+                Err(_) => Err(ParseEnumError),
+            },
+        };
+    }
+}
+
+impl TryFrom<&str> for Opcode {
+    type Error = ParseEnumError;
+
+    /// Parses a canonical name (see [`Opcode::from_name`]) or an integer value.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return s.parse();
     }
 }
 
@@ -281,6 +664,111 @@ impl InType {
         return self.0;
     }
 
+    /// Returns the canonical name of this intype (e.g. `"U32"`), or `None`
+    /// if the value does not have a name.
+    pub const fn as_str(self) -> Option<&'static str> {
+        return match self.0 {
+            0 => Some("Invalid"),
+            1 => Some("CStr16"),
+            2 => Some("CStr8"),
+            3 => Some("I8"),
+            4 => Some("U8"),
+            5 => Some("I16"),
+            6 => Some("U16"),
+            7 => Some("I32"),
+            8 => Some("U32"),
+            9 => Some("I64"),
+            10 => Some("U64"),
+            11 => Some("F32"),
+            12 => Some("F64"),
+            13 => Some("Bool32"),
+            14 => Some("Binary"),
+            15 => Some("Guid"),
+            16 => Some("_HexSize_PlatformSpecific"),
+            17 => Some("FileTime"),
+            18 => Some("SystemTime"),
+            19 => Some("Sid"),
+            20 => Some("Hex32"),
+            21 => Some("Hex64"),
+            22 => Some("Str16"),
+            23 => Some("Str8"),
+            24 => Some("Struct"),
+            25 => Some("BinaryC"),
+            _ => None,
+        };
+    }
+
+    /// Returns the intype with the specified canonical name (case-sensitive), or
+    /// `None` if the name is not recognized.
+    ///
+    /// Also accepts the alias names `ISize`, `USize`, `HexSize`.
+    pub fn from_name(name: &str) -> Option<InType> {
+        return match name {
+            "Invalid" => Some(Self::Invalid),
+            "CStr16" => Some(Self::CStr16),
+            "CStr8" => Some(Self::CStr8),
+            "I8" => Some(Self::I8),
+            "U8" => Some(Self::U8),
+            "I16" => Some(Self::I16),
+            "U16" => Some(Self::U16),
+            "I32" => Some(Self::I32),
+            "U32" => Some(Self::U32),
+            "I64" => Some(Self::I64),
+            "U64" => Some(Self::U64),
+            "F32" => Some(Self::F32),
+            "F64" => Some(Self::F64),
+            "Bool32" => Some(Self::Bool32),
+            "Binary" => Some(Self::Binary),
+            "Guid" => Some(Self::Guid),
+            "_HexSize_PlatformSpecific" => Some(Self::_HexSize_PlatformSpecific),
+            "FileTime" => Some(Self::FileTime),
+            "SystemTime" => Some(Self::SystemTime),
+            "Sid" => Some(Self::Sid),
+            "Hex32" => Some(Self::Hex32),
+            "Hex64" => Some(Self::Hex64),
+            "Str16" => Some(Self::Str16),
+            "Str8" => Some(Self::Str8),
+            "Struct" => Some(Self::Struct),
+            "BinaryC" => Some(Self::BinaryC),
+            "ISize" => Some(Self::ISize),
+            "USize" => Some(Self::USize),
+            "HexSize" => Some(Self::HexSize),
+            _ => None,
+        };
+    }
+
+    /// Returns the size (in bytes) of a field value with this intype, or `None` if the
+    /// size is not fixed.
+    ///
+    /// Returns `None` for variable-size intypes (see [`InType::is_counted`],
+    /// [`InType::is_nul_terminated`], and [InType::Sid]), for [InType::Struct] (the
+    /// struct's size is determined by its fields), and for unrecognized intypes.
+    /// Flags (e.g. [`InType::VariableCountFlag`]) are ignored, so the result is the
+    /// size of one array element.
+    pub const fn fixed_size(self) -> Option<usize> {
+        return match self.0 & Self::TypeMask {
+            3 | 4 => Some(1),                 // I8, U8
+            5 | 6 => Some(2),                 // I16, U16
+            7 | 8 | 11 | 13 | 20 => Some(4),  // I32, U32, F32, Bool32, Hex32
+            9 | 10 | 12 | 17 | 21 => Some(8), // I64, U64, F64, FileTime, Hex64
+            15 | 18 => Some(16),              // Guid, SystemTime
+            _ => None,
+        };
+    }
+
+    /// Returns true if a field value with this intype begins with a `u16` byte count
+    /// ([InType::Str16], [InType::Str8], [InType::Binary], [InType::BinaryC]).
+    /// Flags are ignored.
+    pub const fn is_counted(self) -> bool {
+        return matches!(self.0 & Self::TypeMask, 14 | 22 | 23 | 25);
+    }
+
+    /// Returns true if a field value with this intype ends with a NUL terminator
+    /// ([InType::CStr16], [InType::CStr8]). Flags are ignored.
+    pub const fn is_nul_terminated(self) -> bool {
+        return matches!(self.0 & Self::TypeMask, 1 | 2);
+    }
+
     /// TlgInNULL = Invalid type.
     pub const Invalid: InType = InType(0);
 
@@ -514,8 +1002,36 @@ impl InType {
 }
 
 impl fmt::Display for InType {
+    /// Formats the intype's canonical name if it has one, otherwise its integer value.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        return self.0.fmt(f);
+        return match self.as_str() {
+            Some(name) => f.write_str(name),
+            None => self.0.fmt(f),
+        };
+    }
+}
+
+impl str::FromStr for InType {
+    type Err = ParseEnumError;
+
+    /// Parses a canonical name (see [`InType::from_name`]) or an integer value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match Self::from_name(s) {
+            Some(value) => Ok(value),
+            None => match s.parse::<u8>() {
+                Ok(value) => Ok(Self::from(value)),
+                Err(_) => Err(ParseEnumError),
+            },
+        };
+    }
+}
+
+impl TryFrom<&str> for InType {
+    type Error = ParseEnumError;
+
+    /// Parses a canonical name (see [`InType::from_name`]) or an integer value.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return s.parse();
     }
 }
 
@@ -569,6 +1085,70 @@ impl OutType {
         return self.0;
     }
 
+    /// Returns the canonical name of this outtype (e.g. `"Win32Error"`), or `None`
+    /// if the value does not have a name.
+    pub const fn as_str(self) -> Option<&'static str> {
+        return match self.0 {
+            0 => Some("Default"),
+            1 => Some("NoPrint"),
+            2 => Some("String"),
+            3 => Some("Boolean"),
+            4 => Some("Hex"),
+            5 => Some("Pid"),
+            6 => Some("Tid"),
+            7 => Some("Port"),
+            8 => Some("IPv4"),
+            9 => Some("IPv6"),
+            10 => Some("SocketAddress"),
+            11 => Some("Xml"),
+            12 => Some("Json"),
+            13 => Some("Win32Error"),
+            14 => Some("NtStatus"),
+            15 => Some("HResult"),
+            16 => Some("DateTime"),
+            17 => Some("Signed"),
+            18 => Some("Unsigned"),
+            33 => Some("DateTimeCultureInsensitive"),
+            35 => Some("Utf8"),
+            36 => Some("Pkcs7WithTypeInfo"),
+            37 => Some("CodePointer"),
+            38 => Some("DateTimeUtc"),
+            _ => None,
+        };
+    }
+
+    /// Returns the outtype with the specified canonical name (case-sensitive), or
+    /// `None` if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<OutType> {
+        return match name {
+            "Default" => Some(Self::Default),
+            "NoPrint" => Some(Self::NoPrint),
+            "String" => Some(Self::String),
+            "Boolean" => Some(Self::Boolean),
+            "Hex" => Some(Self::Hex),
+            "Pid" => Some(Self::Pid),
+            "Tid" => Some(Self::Tid),
+            "Port" => Some(Self::Port),
+            "IPv4" => Some(Self::IPv4),
+            "IPv6" => Some(Self::IPv6),
+            "SocketAddress" => Some(Self::SocketAddress),
+            "Xml" => Some(Self::Xml),
+            "Json" => Some(Self::Json),
+            "Win32Error" => Some(Self::Win32Error),
+            "NtStatus" => Some(Self::NtStatus),
+            "HResult" => Some(Self::HResult),
+            "DateTime" => Some(Self::DateTime),
+            "Signed" => Some(Self::Signed),
+            "Unsigned" => Some(Self::Unsigned),
+            "DateTimeCultureInsensitive" => Some(Self::DateTimeCultureInsensitive),
+            "Utf8" => Some(Self::Utf8),
+            "Pkcs7WithTypeInfo" => Some(Self::Pkcs7WithTypeInfo),
+            "CodePointer" => Some(Self::CodePointer),
+            "DateTimeUtc" => Some(Self::DateTimeUtc),
+            _ => None,
+        };
+    }
+
     /// TlgOutNULL = default formatting will be applied based on the field's InType.
     pub const Default: OutType = OutType(0);
     /// TlgOutNOPRINT = field should be hidden. (Most decoders show it anyway.)
@@ -642,8 +1222,36 @@ impl OutType {
 }
 
 impl fmt::Display for OutType {
+    /// Formats the outtype's canonical name if it has one, otherwise its integer value.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        return self.0.fmt(f);
+        return match self.as_str() {
+            Some(name) => f.write_str(name),
+            None => self.0.fmt(f),
+        };
+    }
+}
+
+impl str::FromStr for OutType {
+    type Err = ParseEnumError;
+
+    /// Parses a canonical name (see [`OutType::from_name`]) or an integer value.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        return match Self::from_name(s) {
+            Some(value) => Ok(value),
+            None => match s.parse::<u8>() {
+                Ok(value) => Ok(Self::from(value)),
+                Err(_) => Err(ParseEnumError),
+            },
+        };
+    }
+}
+
+impl TryFrom<&str> for OutType {
+    type Error = ParseEnumError;
+
+    /// Parses a canonical name (see [`OutType::from_name`]) or an integer value.
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        return s.parse();
     }
 }
 
@@ -658,3 +1266,70 @@ impl From<OutType> for u8 {
         return val.0;
     }
 }
+
+/// Implements Serialize (canonical name if the value has one, otherwise the integer
+/// value) and Deserialize (canonical name, integer string, or integer value).
+#[cfg(feature = "serde")]
+macro_rules! impl_serde {
+    ($t:ident) => {
+        impl serde::Serialize for $t {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                return match self.as_str() {
+                    Some(name) => serializer.serialize_str(name),
+                    None => serializer.serialize_u8(self.0),
+                };
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $t {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct Visitor;
+
+                impl<'de> serde::de::Visitor<'de> for Visitor {
+                    type Value = $t;
+
+                    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                        return f.write_str(concat!("a ", stringify!($t), " name or integer"));
+                    }
+
+                    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<$t, E> {
+                        return v
+                            .parse()
+                            .map_err(|_| E::invalid_value(serde::de::Unexpected::Str(v), &self));
+                    }
+
+                    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<$t, E> {
+                        return match u8::try_from(v) {
+                            Ok(value) => Ok($t::from(value)),
+                            Err(_) => {
+                                Err(E::invalid_value(serde::de::Unexpected::Unsigned(v), &self))
+                            }
+                        };
+                    }
+
+                    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<$t, E> {
+                        return match u8::try_from(v) {
+                            Ok(value) => Ok($t::from(value)),
+                            Err(_) => {
+                                Err(E::invalid_value(serde::de::Unexpected::Signed(v), &self))
+                            }
+                        };
+                    }
+                }
+
+                return deserializer.deserialize_any(Visitor);
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde")]
+impl_serde!(Channel);
+#[cfg(feature = "serde")]
+impl_serde!(Level);
+#[cfg(feature = "serde")]
+impl_serde!(Opcode);
+#[cfg(feature = "serde")]
+impl_serde!(InType);
+#[cfg(feature = "serde")]
+impl_serde!(OutType);
@@ -0,0 +1,534 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::iter::FusedIterator;
+
+use crate::descriptors::EventDescriptor;
+use crate::enums::InType;
+use crate::enums::OutType;
+use crate::meta_reader::MetaReader;
+use crate::provider::Provider;
+
+/// Compile-time information about an event defined by
+/// [`write_event!`](crate::write_event) (requires feature `export_schema`).
+///
+/// When the `export_schema` feature is enabled, each `write_event!` (and
+/// [`activity_scope!`](crate::activity_scope)) event is recorded in a static table
+/// that can be enumerated at runtime via [`event_schemas`] or
+/// [`Provider::event_schemas`]. This allows tools to generate documentation or
+/// decoding manifests for a provider's events without scraping source code.
+///
+/// The table is built by the linker, so it includes every event that was linked into
+/// the current module, regardless of whether the event has ever been written. The table
+/// is currently supported on Windows and Linux targets. On other targets it is empty.
+pub struct EventSchema {
+    provider: &'static Provider,
+    descriptor: EventDescriptor,
+    meta: &'static [u8],
+}
+
+impl EventSchema {
+    /// For use by the `write_event` macro: Creates a new event schema.
+    #[doc(hidden)]
+    pub const fn new(
+        provider: &'static Provider,
+        descriptor: EventDescriptor,
+        meta: &'static [u8],
+    ) -> Self {
+        return Self {
+            provider,
+            descriptor,
+            meta,
+        };
+    }
+
+    /// Returns the provider that writes this event.
+    pub const fn provider(&self) -> &'static Provider {
+        return self.provider;
+    }
+
+    /// Returns the event's descriptor (id, version, channel, level, opcode, task, and
+    /// keyword).
+    pub const fn descriptor(&self) -> &EventDescriptor {
+        return &self.descriptor;
+    }
+
+    /// *Advanced:* Returns the event's encoded TraceLogging metadata bytes.
+    pub const fn raw_meta(&self) -> &'static [u8] {
+        return self.meta;
+    }
+
+    /// Returns the event's name.
+    pub fn name(&self) -> &'static str {
+        return MetaReader::new(self.meta)
+            .read_event()
+            .map_or("", |(_, name)| name);
+    }
+
+    /// Returns the event's tag (0 if the event has no tag).
+    pub fn tag(&self) -> u32 {
+        return MetaReader::new(self.meta).read_tag().unwrap_or(0);
+    }
+
+    /// Returns an iterator over the event's fields, in the order they appear in the
+    /// event. Struct fields are followed by their member fields.
+    pub fn fields(&self) -> EventSchemaFields {
+        let mut reader = MetaReader::new(self.meta);
+        let _ = reader.read_event();
+        return EventSchemaFields { reader };
+    }
+}
+
+impl fmt::Debug for EventSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "EventSchema {{ provider: \"{}\", name: \"{}\", id: {}, level: {}, keyword: 0x{:x} }}",
+            self.provider.name(),
+            self.name(),
+            self.descriptor.id,
+            self.descriptor.level,
+            self.descriptor.keyword
+        );
+    }
+}
+
+/// Information about a field of an event, as returned by [`EventSchema::fields`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EventSchemaField {
+    /// The field's name.
+    pub name: &'static str,
+
+    /// The field's encoding, without array flags. For a struct, this is
+    /// [InType::Struct].
+    pub in_type: InType,
+
+    /// The field's format. For a struct, this is the number of member fields that
+    /// follow the struct, i.e. `OutType::from_int(field_count)`.
+    pub out_type: OutType,
+
+    /// The field's tag (0 if the field has no tag).
+    pub tag: u32,
+
+    /// True if the field is an array (variable-length or fixed-length).
+    pub is_array: bool,
+
+    /// For a fixed-length array field, the number of elements in the array.
+    pub constant_count: Option<u16>,
+}
+
+/// Iterator over the fields of an [EventSchema].
+#[derive(Clone, Debug)]
+pub struct EventSchemaFields {
+    reader: MetaReader<'static>,
+}
+
+impl Iterator for EventSchemaFields {
+    type Item = EventSchemaField;
+
+    fn next(&mut self) -> Option<EventSchemaField> {
+        if self.reader.at_end() {
+            return None;
+        }
+
+        let field = self.reader.read_field().ok()?;
+        return Some(EventSchemaField {
+            name: field.name,
+            in_type: field.in_type,
+            out_type: OutType::from_int(field.out_type),
+            tag: field.tag,
+            is_array: field.is_array(),
+            constant_count: if field.flags == InType::ConstantCountFlag {
+                Some(field.constant_count)
+            } else {
+                None
+            },
+        });
+    }
+}
+
+impl FusedIterator for EventSchemaFields {}
+
+/// Returns an iterator over the schemas of all events defined by
+/// [`write_event!`](crate::write_event) in the current module (requires feature
+/// `export_schema`). The order of the events is unspecified.
+///
+/// Use [`Provider::event_schemas`] to enumerate the events of a specific provider.
+pub fn event_schemas() -> impl Iterator<Item = &'static EventSchema> + Clone {
+    return section::events().iter().filter_map(|entry| *entry);
+}
+
+impl Provider {
+    /// Returns an iterator over the schemas of the events defined by
+    /// [`write_event!`](crate::write_event) for this provider (requires feature
+    /// `export_schema`). The order of the events is unspecified.
+    ///
+    /// ```
+    /// use tracelogging as tlg;
+    ///
+    /// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+    ///
+    /// fn log_start() {
+    ///     tlg::write_event!(MY_PROVIDER, "Start", level(Informational), u32("Pid", &1234));
+    /// }
+    ///
+    /// for event in MY_PROVIDER.event_schemas() {
+    ///     println!("{} level={}", event.name(), event.descriptor().level);
+    ///     for field in event.fields() {
+    ///         println!("  {}: {:?}", field.name, field.in_type);
+    ///     }
+    /// }
+    /// # log_start();
+    /// ```
+    pub fn event_schemas(&'static self) -> impl Iterator<Item = &'static EventSchema> + Clone {
+        return event_schemas().filter(move |schema| core::ptr::eq(schema.provider, self));
+    }
+}
+
+/// Compile-time information about a provider defined by
+/// [`define_provider!`](crate::define_provider) (requires feature `export_schema`).
+///
+/// When the `export_schema` feature is enabled, each `define_provider!` is recorded in
+/// a static table that can be enumerated at runtime via [`provider_schemas`]. This is
+/// used by [`duplicate_providers`] and [`test_unique_providers!`](crate::test_unique_providers)
+/// to detect crates that define the same provider more than once.
+///
+/// As with [EventSchema], the table is built by the linker and is currently supported
+/// on Windows and Linux targets. On other targets it is empty.
+pub struct ProviderSchema {
+    provider: &'static Provider,
+    symbol: &'static str,
+    module_path: &'static str,
+}
+
+impl ProviderSchema {
+    /// For use by the `define_provider` macro: Creates a new provider schema.
+    #[doc(hidden)]
+    pub const fn new(
+        provider: &'static Provider,
+        symbol: &'static str,
+        module_path: &'static str,
+    ) -> Self {
+        return Self {
+            provider,
+            symbol,
+            module_path,
+        };
+    }
+
+    /// Returns the provider.
+    pub const fn provider(&self) -> &'static Provider {
+        return self.provider;
+    }
+
+    /// Returns the provider's symbol, e.g. `"MY_PROVIDER"`.
+    pub const fn symbol(&self) -> &'static str {
+        return self.symbol;
+    }
+
+    /// Returns the path of the module that contains the provider's definition, e.g.
+    /// `"my_crate::telemetry"`.
+    pub const fn module_path(&self) -> &'static str {
+        return self.module_path;
+    }
+
+    /// Returns the name of the crate that contains the provider's definition, i.e. the
+    /// first segment of [`ProviderSchema::module_path`].
+    pub fn crate_name(&self) -> &'static str {
+        return crate_name(self.module_path);
+    }
+}
+
+impl fmt::Debug for ProviderSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "ProviderSchema {{ symbol: \"{}::{}\", name: \"{}\", id: {:?} }}",
+            self.module_path,
+            self.symbol,
+            self.provider.name(),
+            self.provider.id()
+        );
+    }
+}
+
+/// Returns an iterator over the schemas of all providers defined by
+/// [`define_provider!`](crate::define_provider) in the current module (requires
+/// feature `export_schema`). The order of the providers is unspecified.
+pub fn provider_schemas() -> impl Iterator<Item = &'static ProviderSchema> + Clone {
+    return section::providers().iter().filter_map(|entry| *entry);
+}
+
+/// Writes the schemas of all providers and events defined in the current module
+/// (see [`provider_schemas`] and [`event_schemas`]) as a text schema artifact
+/// (requires feature `export_schema`).
+///
+/// The artifact is read by the `cargo-tracelog` tool, which lists the events,
+/// generates a WPR profile or an instrumentation manifest, and diffs the schemas of
+/// two builds. Since the schema table is only available inside the module that
+/// contains the events, the module writes the artifact itself, e.g. from a unit test
+/// or from a command-line option used by the release build:
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// fn log_start() {
+///     tlg::write_event!(MY_PROVIDER, "Start", level(Informational), u32("Pid", &1234));
+/// }
+///
+/// let mut artifact = String::new();
+/// tlg::write_schema_artifact(&mut artifact).unwrap();
+/// // std::fs::write("target/my_component.tlgschema", &artifact).unwrap();
+/// # log_start();
+/// ```
+///
+/// The artifact starts with the line `tracelogging-schema 1`, followed by one line
+/// per provider and one line per event, with tab-separated values:
+///
+/// - `provider`, name, id, and `module_path::symbol`.
+/// - `event`, provider name, provider id, event id, version, channel, level, opcode,
+///   task, keyword (hex), and the event's TraceLogging metadata (hex, see
+///   [`EventSchema::raw_meta`]).
+///
+/// Channel, level, and opcode are written as integers. The order of the lines is
+/// unspecified.
+pub fn write_schema_artifact(out: &mut dyn fmt::Write) -> fmt::Result {
+    out.write_str("tracelogging-schema 1\n")?;
+    for schema in provider_schemas() {
+        writeln!(
+            out,
+            "provider\t{}\t{:?}\t{}::{}",
+            schema.provider.name(),
+            schema.provider.id(),
+            schema.module_path,
+            schema.symbol
+        )?;
+    }
+
+    for schema in event_schemas() {
+        let descriptor = &schema.descriptor;
+        write!(
+            out,
+            "event\t{}\t{:?}\t{}\t{}\t{}\t{}\t{}\t{}\t0x{:x}\t",
+            schema.provider.name(),
+            schema.provider.id(),
+            descriptor.id,
+            descriptor.version,
+            descriptor.channel.as_int(),
+            descriptor.level.as_int(),
+            descriptor.opcode.as_int(),
+            descriptor.task,
+            descriptor.keyword
+        )?;
+        for byte in schema.meta {
+            write!(out, "{:02x}", byte)?;
+        }
+        out.write_str("\n")?;
+    }
+
+    return Ok(());
+}
+
+/// Returns an iterator over pairs of providers defined in the specified crate that
+/// have the same name (compared case-insensitively, as ETW does) or the same id
+/// (requires feature `export_schema`).
+///
+/// Defining the same provider twice in one crate results in two registrations of the
+/// provider, each with its own enable callback and enable state, which is usually a
+/// mistake. Use [`test_unique_providers!`](crate::test_unique_providers) to generate a
+/// unit test that fails if a crate has duplicate providers.
+///
+/// `crate_name` is the crate's name as it appears in `module_path!()`, e.g.
+/// `"my_crate"`. Providers defined in other crates are ignored, since it is normal
+/// for several crates to write events using the same provider.
+pub fn duplicate_providers(
+    crate_name: &str,
+) -> impl Iterator<Item = (&'static ProviderSchema, &'static ProviderSchema)> + '_ {
+    let in_crate = move |schema: &&'static ProviderSchema| schema.crate_name() == crate_name;
+    return provider_schemas()
+        .filter(in_crate)
+        .enumerate()
+        .flat_map(move |(index, first)| {
+            return provider_schemas()
+                .filter(in_crate)
+                .skip(index + 1)
+                .filter(move |second| is_duplicate(first.provider, second.provider))
+                .map(move |second| (first, second));
+        });
+}
+
+/// Returns an iterator over pairs of events that have the same nonzero id and version
+/// but different names, written to the same provider, where the provider is defined
+/// in the specified crate (requires feature `export_schema`).
+///
+/// Event ids generated by the `auto_id(hash)` option of
+/// [`write_event!`](crate::write_event) depend only on the event name, so two
+/// different names may generate the same id. Use
+/// [`test_unique_providers!`](crate::test_unique_providers) to generate a unit test
+/// that fails if a crate has colliding event ids.
+///
+/// `crate_name` is the crate's name as it appears in `module_path!()`. Events written
+/// by other crates are included if the provider is defined in the specified crate.
+pub fn duplicate_event_ids(
+    crate_name: &str,
+) -> impl Iterator<Item = (&'static EventSchema, &'static EventSchema)> + '_ {
+    let in_crate = move |schema: &&'static EventSchema| {
+        return schema.descriptor.id != 0
+            && provider_schemas().any(|provider| {
+                return core::ptr::eq(provider.provider, schema.provider)
+                    && provider.crate_name() == crate_name;
+            });
+    };
+    return event_schemas()
+        .filter(in_crate)
+        .enumerate()
+        .flat_map(move |(index, first)| {
+            return event_schemas()
+                .filter(in_crate)
+                .skip(index + 1)
+                .filter(move |second| {
+                    return core::ptr::eq(first.provider, second.provider)
+                        && first.descriptor.id == second.descriptor.id
+                        && first.descriptor.version == second.descriptor.version
+                        && first.name() != second.name();
+                })
+                .map(move |second| (first, second));
+        });
+}
+
+fn is_duplicate(first: &Provider, second: &Provider) -> bool {
+    return first.id() == second.id() || first.name().eq_ignore_ascii_case(second.name());
+}
+
+/// Returns the first segment of a module path.
+fn crate_name(module_path: &str) -> &str {
+    return match module_path.find("::") {
+        Some(end) => &module_path[..end],
+        None => module_path,
+    };
+}
+
+/// For use by the test_unique_providers macro: Panics if the crate that contains
+/// `module_path` has duplicate providers or colliding event ids.
+pub fn assert_unique_providers(module_path: &str) {
+    if let Some((first, second)) = duplicate_providers(crate_name(module_path)).next() {
+        panic!(
+            "providers have the same name or id: {:?} and {:?}",
+            first, second
+        );
+    }
+
+    if let Some((first, second)) = duplicate_event_ids(crate_name(module_path)).next() {
+        panic!(
+            "events have the same id and version but different names: {:?} and {:?}",
+            first, second
+        );
+    }
+}
+
+// Each event's `&'static EventSchema` is placed in a dedicated linker section by the
+// `_tlg_export_event` macro, and each provider's `&'static ProviderSchema` is placed
+// in another section by the `_tlg_export_provider` macro. Each section is enumerated
+// as an array of `Option<&T>`. Entries may be separated by zero padding, so `None`
+// entries are skipped.
+
+#[cfg(windows)]
+mod section {
+    use super::EventSchema;
+    use super::ProviderSchema;
+
+    // The linker sorts ".rdata$zTLGx" sections by the text after '$', so the event
+    // entries (".rdata$zTLGb") are placed between these two markers.
+    #[used]
+    #[link_section = ".rdata$zTLGa"]
+    static START: [Option<&EventSchema>; 1] = [None];
+    #[used]
+    #[link_section = ".rdata$zTLGc"]
+    static STOP: [Option<&EventSchema>; 1] = [None];
+
+    // Same for the provider entries (".rdata$zTLPb").
+    #[used]
+    #[link_section = ".rdata$zTLPa"]
+    static PROVIDERS_START: [Option<&ProviderSchema>; 1] = [None];
+    #[used]
+    #[link_section = ".rdata$zTLPc"]
+    static PROVIDERS_STOP: [Option<&ProviderSchema>; 1] = [None];
+
+    pub fn events() -> &'static [Option<&'static EventSchema>] {
+        let start = START.as_ptr().wrapping_add(1);
+        let stop = STOP.as_ptr();
+        let len = (stop as usize - start as usize) / core::mem::size_of::<Option<&EventSchema>>();
+        // Safety: the linker places all entries contiguously between START and STOP.
+        return unsafe { core::slice::from_raw_parts(start, len) };
+    }
+
+    pub fn providers() -> &'static [Option<&'static ProviderSchema>] {
+        let start = PROVIDERS_START.as_ptr().wrapping_add(1);
+        let stop = PROVIDERS_STOP.as_ptr();
+        let len =
+            (stop as usize - start as usize) / core::mem::size_of::<Option<&ProviderSchema>>();
+        // Safety: the linker places all entries contiguously between START and STOP.
+        return unsafe { core::slice::from_raw_parts(start, len) };
+    }
+}
+
+#[cfg(all(not(windows), any(target_os = "linux", target_os = "android")))]
+mod section {
+    use super::EventSchema;
+    use super::ProviderSchema;
+
+    // Ensures that the sections exist even if there are no events or providers.
+    #[used]
+    #[link_section = "tlg_event_schema"]
+    static EMPTY: Option<&EventSchema> = None;
+    #[used]
+    #[link_section = "tlg_provider_schema"]
+    static EMPTY_PROVIDERS: Option<&ProviderSchema> = None;
+
+    extern "C" {
+        // Defined by the linker for sections whose names are C identifiers.
+        static __start_tlg_event_schema: u8;
+        static __stop_tlg_event_schema: u8;
+        static __start_tlg_provider_schema: u8;
+        static __stop_tlg_provider_schema: u8;
+    }
+
+    pub fn providers() -> &'static [Option<&'static ProviderSchema>] {
+        // Safety: the linker places all entries contiguously between start and stop.
+        unsafe {
+            let start = &__start_tlg_provider_schema as *const u8;
+            let stop = &__stop_tlg_provider_schema as *const u8;
+            let len =
+                (stop as usize - start as usize) / core::mem::size_of::<Option<&ProviderSchema>>();
+            return core::slice::from_raw_parts(start as *const Option<&ProviderSchema>, len);
+        }
+    }
+
+    pub fn events() -> &'static [Option<&'static EventSchema>] {
+        // Safety: the linker places all entries contiguously between start and stop.
+        unsafe {
+            let start = &__start_tlg_event_schema as *const u8;
+            let stop = &__stop_tlg_event_schema as *const u8;
+            let len =
+                (stop as usize - start as usize) / core::mem::size_of::<Option<&EventSchema>>();
+            return core::slice::from_raw_parts(start as *const Option<&EventSchema>, len);
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "android")))]
+mod section {
+    use super::EventSchema;
+    use super::ProviderSchema;
+
+    pub fn events() -> &'static [Option<&'static EventSchema>] {
+        return &[];
+    }
+
+    pub fn providers() -> &'static [Option<&'static ProviderSchema>] {
+        return &[];
+    }
+}
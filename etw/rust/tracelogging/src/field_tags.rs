@@ -0,0 +1,90 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::slice;
+
+/// A named field tag, as declared by
+/// [`define_field_tags!`](crate::define_field_tags).
+///
+/// A field tag is a 28-bit value (`0x1..=0x0FFFFFFF`) attached to a field with the
+/// `tag(...)` field option. The meaning of a tag is defined by the provider, e.g. a
+/// provider might use tag `0x01000000` to mark fields that contain personal data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldTag {
+    name: &'static str,
+    value: u32,
+    doc: &'static str,
+}
+
+impl FieldTag {
+    /// Creates a field tag with the specified name, value, and description.
+    pub const fn new(name: &'static str, value: u32, doc: &'static str) -> Self {
+        return Self { name, value, doc };
+    }
+
+    /// Returns the tag's name, e.g. `"PII"`.
+    pub const fn name(&self) -> &'static str {
+        return self.name;
+    }
+
+    /// Returns the tag's value, e.g. `0x01000000`.
+    pub const fn value(&self) -> u32 {
+        return self.value;
+    }
+
+    /// Returns the tag's description (from its doc comment), or `""` if none.
+    pub const fn doc(&self) -> &'static str {
+        return self.doc;
+    }
+}
+
+impl fmt::Display for FieldTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} (0x{:X})", self.name, self.value);
+    }
+}
+
+/// The set of field tags declared by a
+/// [`define_field_tags!`](crate::define_field_tags) invocation, available as the
+/// `TAGS` constant of the generated module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FieldTags {
+    tags: &'static [FieldTag],
+}
+
+impl FieldTags {
+    /// Creates a set of field tags. Normally called by `define_field_tags!`, which
+    /// verifies that names and values are unique.
+    pub const fn new(tags: &'static [FieldTag]) -> Self {
+        return Self { tags };
+    }
+
+    /// Returns the tags, in declaration order.
+    pub const fn as_slice(&self) -> &'static [FieldTag] {
+        return self.tags;
+    }
+
+    /// Returns an iterator over the tags, in declaration order.
+    pub fn iter(&self) -> slice::Iter<'static, FieldTag> {
+        return self.tags.iter();
+    }
+
+    /// Returns the tag with the specified value, or `None` if no tag has the value.
+    pub fn lookup(&self, value: u32) -> Option<&'static FieldTag> {
+        return self.tags.iter().find(|tag| tag.value == value);
+    }
+
+    /// Returns the tag with the specified name, or `None` if no tag has the name.
+    pub fn lookup_name(&self, name: &str) -> Option<&'static FieldTag> {
+        return self.tags.iter().find(|tag| tag.name == name);
+    }
+}
+
+impl IntoIterator for FieldTags {
+    type Item = &'static FieldTag;
+    type IntoIter = slice::Iter<'static, FieldTag>;
+    fn into_iter(self) -> Self::IntoIter {
+        return self.tags.iter();
+    }
+}
@@ -3,6 +3,7 @@
 
 use core::borrow;
 use core::convert::TryInto;
+use core::ffi::c_void;
 use core::fmt;
 use core::mem;
 use core::str::from_utf8;
@@ -10,6 +11,11 @@ use core::str::from_utf8;
 /// [GUID](https://docs.microsoft.com/windows/win32/api/guiddef/ns-guiddef-guid)
 /// ([UUID](https://en.wikipedia.org/wiki/Universally_unique_identifier)).
 /// with host-endian in-memory representation (as expected by the ETW APIs).
+///
+/// The layout of `Guid` is guaranteed to match the Windows `GUID` structure (16
+/// bytes, 4-byte aligned, host-endian `Data1`, `Data2`, and `Data3`), so a reference to a
+/// `Guid` can be passed to C code that expects a `const GUID*` (see
+/// [`Guid::as_win32_ptr`]).
 #[repr(C)]
 #[derive(Clone, Copy, Default, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct Guid {
@@ -20,6 +26,21 @@ pub struct Guid {
 }
 
 impl Guid {
+    /// The RFC 4122 namespace for fully-qualified domain names, for use with
+    /// [`Guid::from_name_with_namespace`].
+    pub const NAMESPACE_DNS: Guid = Guid::from_u128(&0x6ba7b810_9dad_11d1_80b4_00c04fd430c8);
+
+    /// The RFC 4122 namespace for URLs, for use with [`Guid::from_name_with_namespace`].
+    pub const NAMESPACE_URL: Guid = Guid::from_u128(&0x6ba7b811_9dad_11d1_80b4_00c04fd430c8);
+
+    /// The RFC 4122 namespace for ISO OIDs, for use with
+    /// [`Guid::from_name_with_namespace`].
+    pub const NAMESPACE_OID: Guid = Guid::from_u128(&0x6ba7b812_9dad_11d1_80b4_00c04fd430c8);
+
+    /// The RFC 4122 namespace for X.500 DNs, for use with
+    /// [`Guid::from_name_with_namespace`].
+    pub const NAMESPACE_X500: Guid = Guid::from_u128(&0x6ba7b814_9dad_11d1_80b4_00c04fd430c8);
+
     /// Returns a zeroed GUID, i.e. GUID_NULL.
     pub const fn zero() -> Self {
         return Self {
@@ -81,6 +102,33 @@ impl Guid {
         return Guid::from_bytes_le(v[0..16].try_into().unwrap());
     }
 
+    /// Returns a name-based (version 5) UUID as defined by
+    /// [RFC 4122](https://www.rfc-editor.org/rfc/rfc4122#section-4.3), i.e. a SHA-1
+    /// hash of the namespace GUID followed by the UTF-8 bytes of the name. Given the same
+    /// namespace and name, it will always generate the same GUID, and the result
+    /// matches other standard UUIDv5 implementations. Unlike [`Guid::from_name`], the
+    /// name is case-sensitive.
+    ///
+    /// The namespace can be one of the standard namespaces (e.g. [`Guid::NAMESPACE_DNS`])
+    /// or any GUID chosen by the caller, e.g. to derive activity ids or object ids
+    /// from stable strings.
+    /// ```
+    /// # use tracelogging::Guid;
+    /// assert_eq!(
+    ///    Guid::from_name_with_namespace(&Guid::NAMESPACE_DNS, "python.org"),
+    ///    Guid::from_u128(&0x886313e1_3b8a_5372_9b90_0c9aee199e5d));
+    /// ```
+    pub fn from_name_with_namespace(namespace: &Guid, name: &str) -> Self {
+        let mut hasher = Sha1NonSecret::new();
+        hasher.write(&namespace.to_bytes_be());
+        hasher.write(name.as_bytes());
+
+        let mut v = hasher.finish();
+        v[6] = (v[6] & 0x0F) | 0x50; // Version 5.
+        v[8] = (v[8] & 0x3F) | 0x80; // Variant 1 (RFC 4122).
+        return Guid::from_bytes_be(v[0..16].try_into().unwrap());
+    }
+
     /// Creates a GUID from field values.
     /// ```
     /// # use tracelogging::Guid;
@@ -232,6 +280,52 @@ impl Guid {
         return Some(Self::from_bytes_be(&b));
     }
 
+    /// Creates a GUID from a string with optional {} and optional '-'. Same as
+    /// [`Guid::try_parse`], but usable in a `const` context, e.g. to parse a GUID
+    /// string from `env!(...)` at compile time.
+    ///
+    /// Panics if the GUID could not be parsed from the input. When evaluated in a
+    /// `const` or `static` initializer, this is a compile-time error.
+    /// ```
+    /// # use tracelogging::Guid;
+    /// const MY_GUID: Guid = Guid::parse_const("a3a2a1a0-b1b0-c1c0-d7d6-d5d4d3d2d1d0");
+    /// assert_eq!(
+    ///     Guid::from_fields(0xa3a2a1a0, 0xb1b0, 0xc1c0, [0xd7, 0xd6, 0xd5, 0xd4, 0xd3, 0xd2, 0xd1, 0xd0]),
+    ///     MY_GUID);
+    /// ```
+    pub const fn parse_const(value: &str) -> Self {
+        let input = value.as_bytes();
+        let mut start = 0;
+        let mut end = input.len();
+        if end >= 2 && input[0] == b'{' && input[end - 1] == b'}' {
+            start += 1;
+            end -= 1;
+        }
+
+        let dash = end - start == 36
+            && input[start + 8] == b'-'
+            && input[start + 13] == b'-'
+            && input[start + 18] == b'-'
+            && input[start + 23] == b'-';
+        if !dash && end - start != 32 {
+            panic!("GUID string must be formatted like \"a3a2a1a0-b1b0-c1c0-d7d6-d5d4d3d2d1d0\"");
+        }
+
+        let mut b = [0u8; 16];
+        let mut pos = start;
+        let mut i = 0;
+        while i < 16 {
+            if dash && (i == 4 || i == 6 || i == 8 || i == 10) {
+                pos += 1;
+            }
+            b[i] = (const_hex_to_u4(input[pos]) << 4) | const_hex_to_u4(input[pos + 1]);
+            pos += 2;
+            i += 1;
+        }
+
+        return Self::from_bytes_be(&b);
+    }
+
     /// Returns the field values of the GUID as a tuple.
     /// ```
     /// # use tracelogging::Guid;
@@ -249,6 +343,12 @@ impl Guid {
         return unsafe { mem::transmute(self) };
     }
 
+    /// Returns a pointer to this GUID for use as an `LPCGUID` (`const GUID*`) when
+    /// calling C code.
+    pub const fn as_win32_ptr(&self) -> *const c_void {
+        return self as *const Self as *const c_void;
+    }
+
     /// Returns the bytes of the GUID in big-endian (RFC) byte order.
     /// ```
     /// # use tracelogging::Guid;
@@ -374,6 +474,34 @@ impl Guid {
     }
 }
 
+// Verify that Guid matches the GUID layout.
+const _: () = {
+    assert!(mem::size_of::<Guid>() == 16);
+    assert!(mem::align_of::<Guid>() == 4);
+
+    let guid = Guid::from_fields(
+        0x03020100,
+        0x0504,
+        0x0706,
+        [0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F],
+    );
+    let bytes = guid.as_bytes_raw();
+    let data1 = 0x03020100u32.to_ne_bytes();
+    let data2 = 0x0504u16.to_ne_bytes();
+    let data3 = 0x0706u16.to_ne_bytes();
+    let expected: [u8; 16] = [
+        data1[0], data1[1], data1[2], data1[3], // Data1
+        data2[0], data2[1], // Data2
+        data3[0], data3[1], // Data3
+        0x08, 0x09, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, // Data4
+    ];
+    let mut i = 0;
+    while i != 16 {
+        assert!(bytes[i] == expected[i], "Guid must match GUID");
+        i += 1;
+    }
+};
+
 impl fmt::Debug for Guid {
     /// Format the GUID, e.g. "a3a2a1a0-b1b0-c1c0-d7d6-d5d4d3d2d1d0".
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -388,6 +516,15 @@ impl borrow::Borrow<[u8; 16]> for Guid {
     }
 }
 
+const fn const_hex_to_u4(ch: u8) -> u8 {
+    return match ch {
+        b'0'..=b'9' => ch - b'0',
+        b'a'..=b'f' => ch - b'a' + 10,
+        b'A'..=b'F' => ch - b'A' + 10,
+        _ => panic!("GUID string contains a character that is not a hex digit"),
+    };
+}
+
 struct GuidParseState<'a> {
     input: &'a [u8],
     pos: usize,
@@ -0,0 +1,122 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::Ordering;
+
+use crate::enums::Level;
+use crate::spin_lock::SpinLock;
+
+/// Level 255 allows every level, so it doubles as "no override".
+const NO_LIMIT: u8 = 255;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NO_LIMIT_ATOMIC: AtomicU8 = AtomicU8::new(NO_LIMIT);
+
+/// Per-keyword level overrides, applied in addition to the level set by ETW.
+///
+/// Each of the 64 keyword bits may have a level. An event whose keyword has one or
+/// more bits with a level is allowed up to the most-verbose of those levels. Any other
+/// event (including events with keyword 0) is allowed up to the default level.
+///
+/// Updates are serialized by a spin lock. Readers do not take the lock, so a reader
+/// that races with an update may see some entries from before the update and some
+/// from after.
+pub(crate) struct KeywordLevels {
+    configured: AtomicBool, // false if all entries are NO_LIMIT.
+    updating: SpinLock<()>, // Held while an update is in progress.
+    default_level: AtomicU8,
+    levels: [AtomicU8; 64],
+}
+
+impl KeywordLevels {
+    pub const fn new() -> Self {
+        return Self {
+            configured: AtomicBool::new(false),
+            updating: SpinLock::new(()),
+            default_level: AtomicU8::new(NO_LIMIT),
+            levels: [NO_LIMIT_ATOMIC; 64],
+        };
+    }
+
+    /// Returns true if an event with the specified level and keyword is allowed.
+    #[inline(always)]
+    pub fn allows(&self, level: Level, keyword: u64) -> bool {
+        return !self.configured.load(Ordering::Relaxed) || level.0 <= self.limit(keyword);
+    }
+
+    /// Returns the override for an event with the specified keyword, or `None` if
+    /// events with the keyword are not restricted.
+    pub fn get(&self, keyword: u64) -> Option<Level> {
+        let limit = self.limit(keyword);
+        return if limit == NO_LIMIT {
+            None
+        } else {
+            Some(Level(limit))
+        };
+    }
+
+    /// Sets (or with `None`, removes) the override for each bit in `keywords`.
+    pub fn set(&self, keywords: u64, level: Option<Level>) {
+        let value = level.map_or(NO_LIMIT, |level| level.0);
+        self.update(|| {
+            let mut bits = keywords;
+            while bits != 0 {
+                let bit = bits.trailing_zeros() as usize;
+                self.levels[bit].store(value, Ordering::Relaxed);
+                bits &= bits - 1;
+            }
+        });
+    }
+
+    /// Sets (or with `None`, removes) the override for events whose keyword has no
+    /// bits with an override.
+    pub fn set_default(&self, level: Option<Level>) {
+        let value = level.map_or(NO_LIMIT, |level| level.0);
+        self.update(|| self.default_level.store(value, Ordering::Relaxed));
+    }
+
+    /// Removes all overrides.
+    pub fn clear(&self) {
+        self.update(|| {
+            for entry in &self.levels {
+                entry.store(NO_LIMIT, Ordering::Relaxed);
+            }
+            self.default_level.store(NO_LIMIT, Ordering::Relaxed);
+        });
+    }
+
+    fn limit(&self, keyword: u64) -> u8 {
+        let mut found = false;
+        let mut limit = 0;
+        let mut bits = keyword;
+        while bits != 0 {
+            let value = self.levels[bits.trailing_zeros() as usize].load(Ordering::Relaxed);
+            if value != NO_LIMIT {
+                found = true;
+                limit = limit.max(value);
+            }
+            bits &= bits - 1;
+        }
+
+        return if found {
+            limit
+        } else {
+            self.default_level.load(Ordering::Relaxed)
+        };
+    }
+
+    fn update<F: FnOnce()>(&self, f: F) {
+        let _updating = self.updating.lock();
+
+        f();
+
+        let configured = self.default_level.load(Ordering::Relaxed) != NO_LIMIT
+            || self
+                .levels
+                .iter()
+                .any(|entry| entry.load(Ordering::Relaxed) != NO_LIMIT);
+        self.configured.store(configured, Ordering::Relaxed);
+    }
+}
@@ -114,7 +114,13 @@
 /// [Options:](#options)
 ///
 /// - `id("ProviderGuid")`
+/// - `id_from_name_v5("NamespaceGuid")`
 /// - `group_id("ProviderGroupGuid")`
+/// - `trait(TraitType, b"TraitData")`
+/// - `keywords(NAME = 0x1, ...)`
+/// - `default_keyword(0x1)`
+/// - `warn_default_keyword()`
+/// - `keywords_mask(0xFF)`
 ///
 /// # Overview
 ///
@@ -181,7 +187,8 @@
 ///   the provider. This string will be included in the events and will be a primary
 ///   attribute for event identification. It needs to be unique so that it does not
 ///   conflict with names used by other providers. It should follow a namespace
-///   convention like "CompanyName.ComponentName".
+///   convention like "CompanyName.ComponentName". The name must not be empty, must
+///   not contain `'\0'`, and must be less than 32KB.
 ///
 /// ## Options
 ///
@@ -195,6 +202,35 @@
 ///
 ///   Example: `id("80c257fb-c6bc-4538-a4c4-c7b863d46a8c")`
 ///
+///   The GUID may also be given as a const `&str` expression instead of a string
+///   literal, e.g. `id(env!("MY_PROVIDER_GUID"))` or `id(my_build_info::PROVIDER_GUID)`.
+///   This allows the provider id to be injected at build time (e.g. from an
+///   environment variable or from a constant generated by a build script). The string
+///   is parsed using [`Guid::parse_const`], so an invalid GUID string is reported as a
+///   compile-time error when the provider's initializer is evaluated.
+///
+///   The expression is evaluated outside of the provider's `unsafe` initializer, so
+///   it cannot call unsafe functions unless it has its own `unsafe` block:
+///
+///   ```compile_fail,E0133
+///   tracelogging::define_provider!(
+///       MY_PROVIDER,
+///       "MyCompany.MyComponent",
+///       id(core::str::from_utf8_unchecked(b"80c257fb-c6bc-4538-a4c4-c7b863d46a8c")));
+///   ```
+///
+/// - `id_from_name_v5("NAMESPACE_GUID")`
+///
+///   Generates the provider id at compile time as a standard name-based
+///   [RFC 4122](https://www.rfc-editor.org/rfc/rfc4122#section-4.3) version 5 UUID: a
+///   SHA-1 hash of the namespace GUID followed by the UTF-8 provider name. Use this
+///   instead of `id` when your organization derives provider ids from names using its
+///   own namespace. Unlike `Guid::from_name`, the hash is case-sensitive. Tools that
+///   locate providers by name (e.g. `tracelog -guid *ProviderName`) assume the default
+///   `Guid::from_name` id, so they will not find a provider that uses this option.
+///
+///   Example: `id_from_name_v5("6ba7b810-9dad-11d1-80b4-00c04fd430c8")`
+///
 /// - `group_id("GUID")`
 ///
 ///   Specifies the ETW
@@ -204,12 +240,103 @@
 ///
 ///   Example: `group_id("f73b8292-f610-4fa7-ba62-708353d162c4")`
 ///
+/// - `trait(type, b"data")`
+///
+///   Adds a [provider trait](https://docs.microsoft.com/windows/win32/etw/provider-traits)
+///   with the specified type (an integer literal in the range 0..255) and data (a byte
+///   string literal) to the provider's metadata. Traits are included after the
+///   provider group trait (if any), in the order they are specified. The option may be
+///   used more than once. Most providers do not need custom traits. Use `group_id`
+///   (not `trait`) to join a provider group: trait type 1 (the provider group trait)
+///   is a compile error.
+///
+///   Example: `trait(128, b"\x01\x02VendorData")`
+///
+/// - `keywords(NAME = VALUE, ...)`
+///
+///   Declares named keywords for the provider. Each VALUE must be a non-zero `u64`
+///   integer literal, and the values must not overlap (no bit may be used by more
+///   than one keyword), so keyword assignments are centralized and bit collisions are
+///   compile-time errors. The option may be used more than once.
+///
+///   For each keyword, `define_provider!` generates a constant `KEYWORD_NAME` in a
+///   module with the same name as the provider symbol, along with a `KEYWORDS`
+///   constant listing all of the names and values (e.g. for documentation or tooling):
+///
+///   ```
+///   use tracelogging as tlg;
+///
+///   tlg::define_provider!(
+///       MY_PROVIDER,
+///       "MyCompany.MyComponent",
+///       keywords(NETWORK = 0x2, STORAGE = 0x4),
+///   );
+///
+///   tlg::write_event!(MY_PROVIDER, "Connected", keyword(MY_PROVIDER::KEYWORD_NETWORK));
+///   assert_eq!(MY_PROVIDER::KEYWORDS, &[("NETWORK", 0x2), ("STORAGE", 0x4)]);
+///   ```
+///
+/// - `default_keyword(VALUE)`
+///
+///   Sets the keyword used by [`write_event!`] for events that do not specify a
+///   `keyword` option. VALUE must be a `u64` integer literal and may be 0. If not
+///   specified, the default keyword is `0x1`. The value is available as the constant
+///   `DEFAULT_KEYWORD` in the module with the same name as the provider symbol, e.g.
+///   `MY_PROVIDER::DEFAULT_KEYWORD`.
+///
+///   Use this for providers whose consumers treat keyword bit `0x1` as a meaningful
+///   category, where the implicit `0x1` would misclassify events.
+///
+/// - `warn_default_keyword()`
+///
+///   Generates a compile-time warning (a `deprecated` warning) for each event that
+///   does not specify a `keyword` or `keyword_none` option, i.e. for each event that
+///   relies on the provider's default keyword.
+///
+/// - `keywords_mask(MASK)`
+///
+///   Reserves the keyword bits that the provider's events may use. MASK must be a
+///   non-zero `u64` integer literal. Each [`write_event!`] for the provider checks its
+///   keyword at compile time and fails to build if the keyword has bits outside the
+///   mask. The [`Provider::STATE_KEYWORD`] and [`Provider::MARKER_KEYWORD`] bits are
+///   always allowed. The named `keywords` and the default keyword must be within the
+///   mask, so a provider with a mask that excludes `0x1` must also specify
+///   `default_keyword`. The effective mask is available as the constant
+///   `KEYWORDS_MASK` in the module with the same name as the provider symbol (if no
+///   mask is specified, `KEYWORDS_MASK` is `u64::MAX`).
+///
+///   Use this when a range of keyword bits is owned by another component (e.g. a
+///   shared keyword scheme) so that events cannot accidentally claim those bits.
+///
+///   ```
+///   use tracelogging as tlg;
+///
+///   tlg::define_provider!(
+///       MY_PROVIDER,
+///       "MyCompany.MyComponent",
+///       keywords(NETWORK = 0x2, STORAGE = 0x4),
+///       keywords_mask(0x00FF),
+///   );
+///
+///   tlg::write_event!(MY_PROVIDER, "Connected", keyword(MY_PROVIDER::KEYWORD_NETWORK));
+///   // tlg::write_event!(MY_PROVIDER, "Bad", keyword(0x100)); // Compile error.
+///   ```
+///
 /// - `debug()`
 ///
 ///   For non-production diagnostics: prints the expanded macro during compilation.
 ///
 /// - For compability with the `eventheader` crate, certain other options may be
 ///   accepted and ignored.
+///
+/// ## Duplicate providers
+///
+/// Defining the same provider (same name or same id) more than once in a crate
+/// results in separate registrations with separate enable callbacks, so events may be
+/// split unpredictably between them. If feature `export_schema` is enabled, each
+/// `define_provider!` is recorded in the table returned by `provider_schemas()`, and
+/// `test_unique_providers!()` generates a unit test that fails if the crate defines
+/// the same provider more than once.
 #[cfg(feature = "macros")]
 pub use tracelogging_macros::define_provider;
 
@@ -221,19 +348,25 @@ pub use tracelogging_macros::define_provider;
 ///
 /// - `level(Verbose)`
 /// - `keyword(0x123)`
+/// - `keyword_none()`
 /// - `opcode(Info)`
 /// - `activity_id(&guid)`
+/// - `activity(start)`
 /// - `related_id(&guid)`
 /// - `task(24)`
 /// - `tag(0x123)`
 /// - `id_version(23, 0)`
+/// - `auto_id(hash)`
 /// - `channel(TraceLogging)`
+/// - `sample_rate(100)`
 /// - `debug()`
+/// - `debug_ir()`
 ///
 /// [Fields:](#fields-1)
 ///
 /// - `u32("FieldName", &int_val)`
 /// - `u32_slice("FieldName", &int_vals[..])`
+/// - `opt_u32("FieldName", &option_int_val)`
 /// - `str8("FieldName", str_val)`
 /// - `str8_json("FieldName", json_str_val)`
 /// - `struct("FieldName", { str8("NestedField", str_val), ... })`
@@ -251,6 +384,8 @@ pub use tracelogging_macros::define_provider;
 /// ```ignore
 /// if !MY_PROVIDER.enabled(event_level, event_keyword) {
 ///     0
+/// } else if !MY_PROVIDER.event_filter().map_or(true, |filter| filter(&event_descriptor)) {
+///     0
 /// } else {
 ///     EventWriteTransfer(MY_PROVIDER, options and fields...)
 /// }
@@ -262,7 +397,9 @@ pub use tracelogging_macros::define_provider;
 ///
 /// **Note:** The field value expressions are evaluated and the event is sent to ETW only
 /// if the event is enabled, i.e. only if one or more ETW logging sessions are listening
-/// to the provider with filters that include the level and keyword of the event.
+/// to the provider with filters that include the level and keyword of the event, and
+/// only if the provider's event filter (if any) accepts the event. See
+/// [`Provider::set_event_filter`].
 ///
 /// The `write_event!` macro returns a `u32` value with a Win32 result code. If no ETW
 /// logging sessions are listening for the event, `write_event!` immediately returns 0
@@ -270,7 +407,28 @@ pub use tracelogging_macros::define_provider;
 /// [EventWriteTransfer](https://docs.microsoft.com/windows/win32/api/evntprov/nf-evntprov-eventwritetransfer)
 /// API. Since most components treat logging APIs as fire-and-forget, this value should
 /// normally be ignored in production code. It is generally used only for debugging and
-/// troubleshooting.
+/// troubleshooting. (With the `activity(start)` option, the macro returns a
+/// `(u32, Guid)` tuple with the result code and the new activity id.)
+///
+/// # Compile-time event removal
+///
+/// Shipping binaries may want verbose or diagnostic events removed entirely (for size or
+/// to avoid exposing event names and field names), not just disabled at runtime. Set
+/// these environment variables when building the crates that write events:
+///
+/// - `TRACELOGGING_MIN_LEVEL` - a [Level] name (e.g. `Informational`) or an integer
+///   from 0 to 255. Events with a level greater (more verbose) than this are removed.
+/// - `TRACELOGGING_KEYWORD_MASK` - a decimal or `0x`-prefixed hexadecimal `u64`. Events
+///   with a nonzero keyword that has no bits in common with this mask are removed.
+///
+/// A removed event does not evaluate its field values, returns 0, and is not included
+/// in `event_schemas()` (feature `export_schema`). Optimized builds contain no code or
+/// metadata for removed events. The event's level and keyword must be constants, so
+/// the check is performed at compile time; the variables are read with `option_env!`,
+/// so changing them causes the affected crates to be rebuilt. Empty values are ignored
+/// and invalid values cause a compile error. Events written via [`activity_scope!`] are
+/// removed the same way (the scope's stop event is checked separately from its start
+/// event).
 ///
 /// # Limitations
 ///
@@ -334,6 +492,9 @@ pub use tracelogging_macros::define_provider;
 ///   This should be the original symbol name created by [`define_provider!`], not a
 ///   reference or alias.
 ///
+///   This may also be a symbol created by [`define_provider_set!`], in which case the
+///   event is written to each provider in the set.
+///
 /// - `"EventName"`
 ///
 ///   A **string literal** that specifies a short human-readable name for the event. The
@@ -366,11 +527,17 @@ pub use tracelogging_macros::define_provider;
 ///   Keyword is important for event filtering so all events should specify a meaningful
 ///   non-zero keyword.
 ///
-///   If no `keyword` options are specified then the event's keyword will be `0x1` to
-///   flag the event as not having any assigned keyword. If the `keyword` option is
-///   specified it must be a constant `u64` value. The `keyword` option may be specified
-///   more than once, in which case all provided keyword values will be OR'ed together in
-///   the event's keyword.
+///   If no `keyword` options are specified then the event's keyword will be the
+///   provider's default keyword, normally `0x1` to flag the event as not having any
+///   assigned keyword (see the `default_keyword` option of [`define_provider!`]). If
+///   the `keyword` option is specified it must be a constant `u64` value. The `keyword`
+///   option may be specified more than once, in which case all provided keyword values
+///   will be OR'ed together in the event's keyword.
+///
+/// - `keyword_none()`
+///
+///   Sets the event's keyword to 0 instead of the provider's default keyword. May not
+///   be combined with the `keyword` option.
 ///
 /// - `opcode(event_opcode)`
 ///
@@ -391,6 +558,27 @@ pub use tracelogging_macros::define_provider;
 ///   If specified, the value must be a reference to a [Guid] or a reference to a
 ///   `[u8; 16]`.
 ///
+/// - `activity(start)`
+///
+///   Starts a new activity: the macro generates a new activity id (via
+///   [`Provider::create_activity_id`]), uses it as the event's activity id, and
+///   returns it so that it can be passed to the `activity_id` option of subsequent
+///   events in the activity. The opcode defaults to
+///   [`ActivityStart`](Opcode::ActivityStart). May not be combined with the
+///   `activity_id` option.
+///
+///   With this option, the macro returns a `(u32, Guid)` tuple with the Win32 result
+///   code and the new activity id. The activity id is generated even if the event is
+///   not enabled.
+///
+///   ```
+///   # use tracelogging as tlg;
+///   # tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///   let (_, activity_id) = tlg::write_event!(MY_PROVIDER, "Download", activity(start));
+///   tlg::write_event!(MY_PROVIDER, "Progress", activity_id(&activity_id));
+///   tlg::write_event!(MY_PROVIDER, "Download", opcode(Stop), activity_id(&activity_id));
+///   ```
+///
 /// - `related_id(&guid)`
 ///
 ///   Specifies the related activity id to use for the event.
@@ -445,6 +633,19 @@ pub use tracelogging_macros::define_provider;
 ///   specified, the id must be a constant `u16` value and the version must be a constant
 ///   `u8` value.
 ///
+/// - `auto_id(hash)`
+///
+///   Assigns the event a stable nonzero id generated at compile time from a hash of
+///   the event name (see [`Provider::event_id_from_name`]). The version will be 0.
+///   This is useful for event processing pipelines that require an id but where a
+///   manually-maintained table of ids would be a burden.
+///
+///   The id only depends on the event name, so it does not change when the event's
+///   fields change. Different event names may generate the same id. If feature
+///   `export_schema` is enabled, `duplicate_event_ids()` and `test_unique_providers!()`
+///   detect collisions between the events of a provider. The `auto_id` option cannot
+///   be combined with `id_version`.
+///
 /// - `channel(event_channel)`
 ///
 ///   Specifies the channel attribute for the event.
@@ -456,10 +657,37 @@ pub use tracelogging_macros::define_provider;
 ///   [Channel::TraceLogging]. If the channel is specified it must be a constant
 ///   [Channel] value.
 ///
+/// - `sample_rate(N)`
+///
+///   Writes only 1 of every N occurrences of the event: the 1st, (N+1)th, (2N+1)th,
+///   etc. Use this for events that fire so frequently that writing every occurrence
+///   would be too expensive.
+///
+///   Occurrences are counted per call site (across all threads) using an atomic
+///   counter (an [`EmitPolicy`]). Only occurrences that would otherwise be written are
+///   counted, i.e. occurrences where the provider is enabled for the event's level and
+///   keyword and the provider's event filter (if any) accepts the event. Field values
+///   are not evaluated for occurrences that are skipped. A skipped occurrence returns 0.
+///
+///   N must be a nonzero constant `u64` value. The `sample_rate` option is not
+///   supported by [`activity_scope!`]. To log the first occurrences of an event before
+///   sampling, or to include the occurrence count in the event, use [`emit_policy!`].
+///
 /// - `debug()`
 ///
 ///   For non-production diagnostics: prints the expanded macro during compilation.
 ///
+/// - `debug_ir()`
+///
+///   For tooling: adds a `_TLG_EVENT_IR` string constant to the expanded macro with a
+///   JSON description of the event's schema (provider symbol, event name, descriptor
+///   values, keywords, tag, and each field's name, field type, `InType`, `OutType`,
+///   array length, and tag). Tools such as IDE plugins can read the schema from the
+///   macro expansion without depending on the macro's internals. Values that are
+///   expressions are given as the expression's tokens. The JSON includes an
+///   `ir_version` member that changes only if the format changes incompatibly. Does
+///   not affect the event.
+///
 /// ## Fields
 ///
 /// Event content is provided in fields. Each field is added to the event with a field
@@ -502,12 +730,18 @@ pub use tracelogging_macros::define_provider;
 ///   only if at least one logging session is listening to the provider and has filtering
 ///   that includes this event's level and keyword.
 ///
+///   `VALUE_REF` may also be a closure with no parameters, e.g.
+///   `str8("Detail", || render_detail())`. The closure returns the field's value (or a
+///   reference to the value) and is invoked only if the event is enabled. Use this when
+///   the value is expensive to compute or when computing it needs its own borrows.
+///
 /// - `tag(TAG)` specifies a 28-bit "field tag" with provider-defined semantics.
 ///
 ///   This is usually omitted because most providers do not use field tags.
 ///
 ///   If not present, the field tag is `0`. If present, the TAG must be a 28-bit constant
-///   `u32` value in the range `0` to `0x0FFFFFFF`.
+///   `u32` value in the range `0` to `0x0FFFFFFF`, e.g. a constant declared with
+///   [`define_field_tags!`] such as `tag(MY_TAGS::PII)`.
 ///
 /// - `format(FORMAT)` specifies an [OutType] that overrides the format that would
 ///   normally apply for the given `TYPE`.
@@ -534,6 +768,7 @@ pub use tracelogging_macros::define_provider;
 ///     str8("MyField2", message, format(Json)), // Using the format option
 ///     str8("MyField3", message, tag(0x1234)),  // Using the tag option
 ///     str8("MyField4", message, format(Json), tag(0x1234)), // Both options
+///     str8("MyField5", || message.to_uppercase()), // Value computed by a closure
 /// );
 /// ```
 ///
@@ -542,17 +777,28 @@ pub use tracelogging_macros::define_provider;
 /// | Field Type | Rust Type | ETW Type
 /// |------------|-----------|---------
 /// | `binary` | `&[u8]` | [`Binary`](InType::Binary)
+/// | `binary_capped` [^capped] | `&[u8]` | [`Binary`](InType::Binary)
+/// | `binary_ptr_len` [^ptr_len] | `*const u8, usize` | [`Binary`](InType::Binary)
 /// | `binaryc` [^binaryc] | `&[u8]` | [`BinaryC`](InType::BinaryC)
 /// | `bool8` | `&bool` | [`U8`](InType::U8) + [`Boolean`](OutType::Boolean)
 /// | `bool8_slice` | `&[bool]` | [`U8`](InType::U8) + [`Boolean`](OutType::Boolean)
+/// | `bool8_array` [^array] | `&[bool; N]` | [`U8`](InType::U8) + [`Boolean`](OutType::Boolean)
 /// | `bool32` | `&i32` | [`Bool32`](InType::Bool32)
 /// | `bool32_slice` | `&[i32]` | [`Bool32`](InType::Bool32)
+/// | `bool32_array` [^array] | `&[i32; N]` | [`Bool32`](InType::Bool32)
+/// | `caller_codepointer` [^caller] | `u32` | [`HexSize`](InType::HexSize) + [`CodePointer`](OutType::CodePointer)
 /// | `char8_cp1252` | `&u8` | [`U8`](InType::U8) + [`String`](OutType::String)
 /// | `char8_cp1252_slice` | `&[u8]` | [`U8`](InType::U8) + [`String`](OutType::String)
+/// | `char8_cp1252_array` [^array] | `&[u8; N]` | [`U8`](InType::U8) + [`String`](OutType::String)
 /// | `char16` | `&u16` | [`U16`](InType::U16) + [`String`](OutType::String)
 /// | `char16_slice` | `&[u16]` | [`U16`](InType::U16) + [`String`](OutType::String)
+/// | `char16_array` [^array] | `&[u16; N]` | [`U16`](InType::U16) + [`String`](OutType::String)
+/// | `char32` [^char32] | `&char` | [`U32`](InType::U32) + [`String`](OutType::String)
+/// | `char32_slice` [^char32] | `&[char]` | [`U32`](InType::U32) + [`String`](OutType::String)
+/// | `char32_array` [^array] | `&[char; N]` | [`U32`](InType::U32) + [`String`](OutType::String)
 /// | `codepointer` | `&usize` | [`HexSize`](InType::HexSize) + [`CodePointer`](OutType::CodePointer)
 /// | `codepointer_slice` | `&[usize]` | [`HexSize`](InType::HexSize) + [`CodePointer`](OutType::CodePointer)
+/// | `codepointer_array` [^array] | `&[usize; N]` | [`HexSize`](InType::HexSize) + [`CodePointer`](OutType::CodePointer)
 /// | `cstr8` [^cstr] | `&[u8]` | [`CStr8`](InType::CStr8) + [`Utf8`](OutType::Utf8)
 /// | `cstr8_cp1252` [^cstr] | `&[u8]` | [`CStr8`](InType::CStr8)
 /// | `cstr8_json` [^cstr] | `&[u8]` | [`CStr8`](InType::CStr8) + [`Json`](OutType::Json)
@@ -562,89 +808,179 @@ pub use tracelogging_macros::define_provider;
 /// | `cstr16_xml` [^cstr] | `&[u16]` | [`CStr16`](InType::CStr16) + [`Xml`](OutType::Xml)
 /// | `errno` [^errno] | `&i32` | [`I32`](InType::I32)
 /// | `errno_slice` [^errno] | `&[i32]` | [`I32`](InType::I32)
+/// | `errno_array` [^array] | `&[i32; N]` | [`I32`](InType::I32)
+/// | `error` [^error] | `&dyn std::error::Error` | [`Struct`](InType::Struct)
 /// | `f32` | `&f32` | [`F32`](InType::F32)
 /// | `f32_slice` | `&[f32]` | [`F32`](InType::F32)
+/// | `f32_array` [^array] | `&[f32; N]` | [`F32`](InType::F32)
 /// | `f64` | `&f64` | [`F64`](InType::F64)
 /// | `f64_slice` | `&[f64]` | [`F64`](InType::F64)
+/// | `f64_array` [^array] | `&[f64; N]` | [`F64`](InType::F64)
+/// | `fmt` [^fmt] | `std::fmt::Arguments` | [`Str8`](InType::Str8) + [`Utf8`](OutType::Utf8)
 /// | `guid` | `&tracelogging::Guid` | [`Guid`](InType::Guid)
 /// | `guid_slice` | `&[tracelogging::Guid]` | [`Guid`](InType::Guid)
+/// | `guid_array` [^array] | `&[tracelogging::Guid; N]` | [`Guid`](InType::Guid)
 /// | `hresult` | `&i32` | [`I32`](InType::I32) + [`HResult`](OutType::HResult)
 /// | `hresult_slice` | `&[i32]` | [`I32`](InType::I32) + [`HResult`](OutType::HResult)
+/// | `hresult_array` [^array] | `&[i32; N]` | [`I32`](InType::I32) + [`HResult`](OutType::HResult)
 /// | `i8` | `&i8` | [`I8`](InType::I8)
 /// | `i8_slice` | `&[i8]` | [`I8`](InType::I8)
+/// | `i8_array` [^array] | `&[i8; N]` | [`I8`](InType::I8)
 /// | `i8_hex` | `&i8` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
 /// | `i8_hex_slice` | `&[i8]` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
+/// | `i8_hex_array` [^array] | `&[i8; N]` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
 /// | `i16` | `&i16` | [`I16`](InType::I16)
 /// | `i16_slice` | `&[i16]` | [`I16`](InType::I16)
+/// | `i16_array` [^array] | `&[i16; N]` | [`I16`](InType::I16)
 /// | `i16_hex` | `&i16` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
 /// | `i16_hex_slice` | `&[i16]` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
+/// | `i16_hex_array` [^array] | `&[i16; N]` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
 /// | `i32` | `&i32` | [`I32`](InType::I32)
 /// | `i32_slice` | `&[i32]` | [`I32`](InType::I32)
+/// | `i32_array` [^array] | `&[i32; N]` | [`I32`](InType::I32)
 /// | `i32_hex` | `&i32` | [`Hex32`](InType::Hex32)
 /// | `i32_hex_slice` | `&[i32]` | [`Hex32`](InType::Hex32)
+/// | `i32_hex_array` [^array] | `&[i32; N]` | [`Hex32`](InType::Hex32)
 /// | `i64` | `&i64` | [`I64`](InType::I64)
 /// | `i64_slice` | `&[i64]` | [`I64`](InType::I64)
+/// | `i64_array` [^array] | `&[i64; N]` | [`I64`](InType::I64)
 /// | `i64_hex` | `&i64` | [`Hex64`](InType::Hex64)
 /// | `i64_hex_slice` | `&[i64]` | [`Hex64`](InType::Hex64)
+/// | `i64_hex_array` [^array] | `&[i64; N]` | [`Hex64`](InType::Hex64)
 /// | `ipv4` | `&[u8; 4]` | [`U32`](InType::U32) + [`IPv4`](OutType::IPv4)
 /// | `ipv4_slice` | `&[[u8; 4]]` | [`U32`](InType::U32) + [`IPv4`](OutType::IPv4)
+/// | `ipv4_array` [^array] | `&[[u8; 4]; N]` | [`U32`](InType::U32) + [`IPv4`](OutType::IPv4)
 /// | `ipv6` | `&[u8; 16]` | [`Binary`](InType::Binary) + [`IPv6`](OutType::IPv6)
 /// | `ipv6c` [^binaryc] | `&[u8; 16]` | [`BinaryC`](InType::BinaryC) + [`IPv6`](OutType::IPv6)
 /// | `isize` | `&isize` | [`ISize`](InType::ISize)
 /// | `isize_slice` | `&[isize]` | [`ISize`](InType::ISize)
+/// | `isize_array` [^array] | `&[isize; N]` | [`ISize`](InType::ISize)
 /// | `isize_hex` | `&isize` | [`HexSize`](InType::HexSize)
 /// | `isize_hex_slice` | `&[isize]` | [`HexSize`](InType::HexSize)
+/// | `isize_hex_array` [^array] | `&[isize; N]` | [`HexSize`](InType::HexSize)
+/// | `opt_bool8` [^opt] | `&Option<bool>` | [`U8`](InType::U8) + [`Boolean`](OutType::Boolean)
+/// | `opt_bool32` [^opt] | `&Option<i32>` | [`Bool32`](InType::Bool32)
+/// | `opt_char8_cp1252` [^opt] | `&Option<u8>` | [`U8`](InType::U8) + [`String`](OutType::String)
+/// | `opt_char16` [^opt] | `&Option<u16>` | [`U16`](InType::U16) + [`String`](OutType::String)
+/// | `opt_char32` [^opt] | `&Option<char>` | [`U32`](InType::U32) + [`String`](OutType::String)
+/// | `opt_codepointer` [^opt] | `&Option<usize>` | [`HexSize`](InType::HexSize) + [`CodePointer`](OutType::CodePointer)
+/// | `opt_errno` [^opt] | `&Option<i32>` | [`I32`](InType::I32)
+/// | `opt_f32` [^opt] | `&Option<f32>` | [`F32`](InType::F32)
+/// | `opt_f64` [^opt] | `&Option<f64>` | [`F64`](InType::F64)
+/// | `opt_guid` [^opt] | `&Option<tracelogging::Guid>` | [`Guid`](InType::Guid)
+/// | `opt_hresult` [^opt] | `&Option<i32>` | [`I32`](InType::I32) + [`HResult`](OutType::HResult)
+/// | `opt_i8` [^opt] | `&Option<i8>` | [`I8`](InType::I8)
+/// | `opt_i8_hex` [^opt] | `&Option<i8>` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
+/// | `opt_i16` [^opt] | `&Option<i16>` | [`I16`](InType::I16)
+/// | `opt_i16_hex` [^opt] | `&Option<i16>` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
+/// | `opt_i32` [^opt] | `&Option<i32>` | [`I32`](InType::I32)
+/// | `opt_i32_hex` [^opt] | `&Option<i32>` | [`Hex32`](InType::Hex32)
+/// | `opt_i64` [^opt] | `&Option<i64>` | [`I64`](InType::I64)
+/// | `opt_i64_hex` [^opt] | `&Option<i64>` | [`Hex64`](InType::Hex64)
+/// | `opt_ipv4` [^opt] | `&Option<[u8; 4]>` | [`U32`](InType::U32) + [`IPv4`](OutType::IPv4)
+/// | `opt_isize` [^opt] | `&Option<isize>` | [`ISize`](InType::ISize)
+/// | `opt_isize_hex` [^opt] | `&Option<isize>` | [`HexSize`](InType::HexSize)
+/// | `opt_pid` [^opt] | `&Option<u32>` | [`U32`](InType::U32) + [`Pid`](OutType::Pid)
+/// | `opt_pointer` [^opt] | `&Option<usize>` | [`HexSize`](InType::HexSize)
+/// | `opt_port` [^opt] | `&Option<u16>` | [`U16`](InType::U16) + [`Port`](OutType::Port)
+/// | `opt_tid` [^opt] | `&Option<u32>` | [`U32`](InType::U32) + [`Tid`](OutType::Tid)
+/// | `opt_u8` [^opt] | `&Option<u8>` | [`U8`](InType::U8)
+/// | `opt_u8_hex` [^opt] | `&Option<u8>` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
+/// | `opt_u16` [^opt] | `&Option<u16>` | [`U16`](InType::U16)
+/// | `opt_u16_hex` [^opt] | `&Option<u16>` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
+/// | `opt_u32` [^opt] | `&Option<u32>` | [`U32`](InType::U32)
+/// | `opt_u32_hex` [^opt] | `&Option<u32>` | [`Hex32`](InType::Hex32)
+/// | `opt_u64` [^opt] | `&Option<u64>` | [`U64`](InType::U64)
+/// | `opt_u64_hex` [^opt] | `&Option<u64>` | [`Hex64`](InType::Hex64)
+/// | `opt_usize` [^opt] | `&Option<usize>` | [`USize`](InType::USize)
+/// | `opt_usize_hex` [^opt] | `&Option<usize>` | [`HexSize`](InType::HexSize)
+/// | `opt_win_error` [^opt] | `&Option<u32>` | [`U32`](InType::U32) + [`Win32Error`](OutType::Win32Error)
+/// | `opt_win_filetime` [^opt] | `&Option<i64>` | [`FileTime`](InType::FileTime)
+/// | `opt_win_ntstatus` [^opt] | `&Option<i32>` | [`Hex32`](InType::Hex32) + [`NtStatus`](OutType::NtStatus)
+/// | `opt_win_systemtime` [^opt] | `&Option<[u16; 8]>` | [`SystemTime`](InType::SystemTime)
+/// | `opt_win_systemtime_utc` [^opt] | `&Option<[u16; 8]>` | [`SystemTime`](InType::SystemTime) + [`DateTimeUtc`](OutType::DateTimeUtc)
 /// | `pid` | `&u32` | [`U32`](InType::U32) + [`Pid`](OutType::Pid)
 /// | `pid_slice` | `&[u32]` | [`U32`](InType::U32) + [`Pid`](OutType::Pid)
+/// | `pid_array` [^array] | `&[u32; N]` | [`U32`](InType::U32) + [`Pid`](OutType::Pid)
 /// | `pointer` | `&usize` | [`HexSize`](InType::HexSize)
 /// | `pointer_slice` | `&[usize]` | [`HexSize`](InType::HexSize)
+/// | `pointer_array` [^array] | `&[usize; N]` | [`HexSize`](InType::HexSize)
 /// | `port` | `&u16` | [`U16`](InType::U16) + [`Port`](OutType::Port)
 /// | `port_slice` | `&[u16]` | [`U16`](InType::U16) + [`Port`](OutType::Port)
+/// | `port_array` [^array] | `&[u16; N]` | [`U16`](InType::U16) + [`Port`](OutType::Port)
 /// | `socketaddress` | `&[u8]` | [`Binary`](InType::Binary) + [`SocketAddress`](OutType::SocketAddress)
 /// | `socketaddressc` [^binaryc] | `&[u8]` | [`BinaryC`](InType::BinaryC) + [`SocketAddress`](OutType::SocketAddress)
 /// | `str8` | `&[u8]` | [`Str8`](InType::Str8) + [`Utf8`](OutType::Utf8)
+/// | `str8_slice` [^str_slice] | `&[&str]` | [`Str8`](InType::Str8) + [`Utf8`](OutType::Utf8)
 /// | `str8_cp1252` | `&[u8]` | [`Str8`](InType::Str8)
 /// | `str8_json` | `&[u8]` | [`Str8`](InType::Str8) + [`Json`](OutType::Json)
 /// | `str8_xml` | `&[u8]` | [`Str8`](InType::Str8) + [`Xml`](OutType::Xml)
 /// | `str16` | `&[u16]` | [`Str16`](InType::Str16)
+/// | `str16_slice` [^str_slice] | `&[&[u16]]` | [`Str16`](InType::Str16)
 /// | `str16_json` | `&[u16]` | [`Str16`](InType::Str16) + [`Json`](OutType::Json)
 /// | `str16_xml` | `&[u16]` | [`Str16`](InType::Str16) + [`Xml`](OutType::Xml)
+/// | `str32` [^char32] | `&[char]` | [`U32`](InType::U32) + [`String`](OutType::String)
 /// | `systemtime` [^systemtime] | `&std::time::SystemTime` | [`FileTime`](InType::FileTime)
 /// | `tid` | `&u32` | [`U32`](InType::U32) + [`Tid`](OutType::Tid)
 /// | `tid_slice` | `&[u32]` | [`U32`](InType::U32) + [`Tid`](OutType::Tid)
+/// | `tid_array` [^array] | `&[u32; N]` | [`U32`](InType::U32) + [`Tid`](OutType::Tid)
 /// | `time32` [^time] | `&i32` | [`FileTime`](InType::FileTime)
 /// | `time64` [^time] | `&i64` | [`FileTime`](InType::FileTime)
 /// | `u8` | `&u8` | [`U8`](InType::U8)
 /// | `u8_slice` | `&[u8]` | [`U8`](InType::U8)
+/// | `u8_array` [^array] | `&[u8; N]` | [`U8`](InType::U8)
 /// | `u8_hex` | `&u8` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
 /// | `u8_hex_slice` | `&[u8]` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
+/// | `u8_hex_array` [^array] | `&[u8; N]` | [`U8`](InType::U8) + [`Hex`](OutType::Hex)
 /// | `u16` | `&u16` | [`U16`](InType::U16)
 /// | `u16_slice` | `&[u16]` | [`U16`](InType::U16)
+/// | `u16_array` [^array] | `&[u16; N]` | [`U16`](InType::U16)
 /// | `u16_hex` | `&u16` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
 /// | `u16_hex_slice` | `&[u16]` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
+/// | `u16_hex_array` [^array] | `&[u16; N]` | [`U16`](InType::U16) + [`Hex`](OutType::Hex)
 /// | `u32` | `&u32` | [`U32`](InType::U32)
 /// | `u32_slice` | `&[u32]` | [`U32`](InType::U32)
+/// | `u32_array` [^array] | `&[u32; N]` | [`U32`](InType::U32)
 /// | `u32_hex` | `&u32` | [`Hex32`](InType::Hex32)
 /// | `u32_hex_slice` | `&[u32]` | [`Hex32`](InType::Hex32)
+/// | `u32_hex_array` [^array] | `&[u32; N]` | [`Hex32`](InType::Hex32)
 /// | `u64` | `&u64` | [`U64`](InType::U64)
 /// | `u64_slice` | `&[u64]` | [`U64`](InType::U64)
+/// | `u64_array` [^array] | `&[u64; N]` | [`U64`](InType::U64)
 /// | `u64_hex` | `&u64` | [`Hex64`](InType::Hex64)
 /// | `u64_hex_slice` | `&[u64]` | [`Hex64`](InType::Hex64)
+/// | `u64_hex_array` [^array] | `&[u64; N]` | [`Hex64`](InType::Hex64)
 /// | `usize` | `&usize` | [`USize`](InType::USize)
 /// | `usize_slice` | `&[usize]` | [`USize`](InType::USize)
+/// | `usize_array` [^array] | `&[usize; N]` | [`USize`](InType::USize)
 /// | `usize_hex` | `&usize` | [`HexSize`](InType::HexSize)
 /// | `usize_hex_slice` | `&[usize]` | [`HexSize`](InType::HexSize)
+/// | `usize_hex_array` [^array] | `&[usize; N]` | [`HexSize`](InType::HexSize)
 /// | `win_error` | `&u32` | [`U32`](InType::U32) + [`Win32Error`](OutType::Win32Error)
 /// | `win_error_slice` | `&[u32]` | [`U32`](InType::U32) + [`Win32Error`](OutType::Win32Error)
+/// | `win_error_array` [^array] | `&[u32; N]` | [`U32`](InType::U32) + [`Win32Error`](OutType::Win32Error)
 /// | `win_filetime` | `&i64` | [`FileTime`](InType::FileTime)
 /// | `win_filetime_slice` | `&[i64]` | [`FileTime`](InType::FileTime)
+/// | `win_filetime_array` [^array] | `&[i64; N]` | [`FileTime`](InType::FileTime)
 /// | `win_ntstatus` | `&i32` | [`Hex32`](InType::Hex32) + [`NtStatus`](OutType::NtStatus)
 /// | `win_ntstatus_slice` | `&[i32]` | [`Hex32`](InType::Hex32) + [`NtStatus`](OutType::NtStatus)
+/// | `win_ntstatus_array` [^array] | `&[i32; N]` | [`Hex32`](InType::Hex32) + [`NtStatus`](OutType::NtStatus)
 /// | `win_sid` [^sid] | `&[u8]` | [`Sid`](InType::Sid)
 /// | `win_systemtime` | `&[u16; 8]` | [`SystemTime`](InType::SystemTime)
 /// | `win_systemtime_slice` | `&[[u16; 8]]` | [`SystemTime`](InType::SystemTime)
+/// | `win_systemtime_array` [^array] | `&[[u16; 8]; N]` | [`SystemTime`](InType::SystemTime)
 /// | `win_systemtime_utc` | `&[u16; 8]` | [`SystemTime`](InType::SystemTime) + [`DateTimeUtc`](OutType::DateTimeUtc)
 /// | `win_systemtime_utc_slice` | `&[[u16; 8]]` | [`SystemTime`](InType::SystemTime) + [`DateTimeUtc`](OutType::DateTimeUtc)
+/// | `win_systemtime_utc_array` [^array] | `&[[u16; 8]; N]` | [`SystemTime`](InType::SystemTime) + [`DateTimeUtc`](OutType::DateTimeUtc)
+///
+/// [^array]: The `TYPE_array` types (e.g. `u32_array`, `guid_array`, `ipv4_array`) are
+/// available for each `TYPE` that has a corresponding `TYPE_slice` type. They use the
+/// syntax `TYPE_array("NAME", N, VALUE_REF)`, where `N` is a constant `usize`
+/// expression in the range `0` to `65535` and `VALUE_REF` must be a reference to an
+/// array of exactly `N` elements, e.g. `u32_array("Values", 4, &[1, 2, 3, 4])`. The
+/// field is encoded as a fixed-length array: the element count is stored in the event's
+/// metadata instead of in the event's data, saving 2 bytes per event compared to
+/// `TYPE_slice`.
 ///
 /// [^binaryc]: The `...` and `...c` types are the same except that the `...c` types use
 /// a newer `InType::BinaryC` ETW encoding. The `BinaryC` encoding avoids the extra
@@ -652,6 +988,42 @@ pub use tracelogging_macros::define_provider;
 /// new encoding requires updated decoder support so it may not work with older ETW
 /// decoding tools.
 ///
+/// [^capped]: The `binary_capped` type logs at most the first `MAX_LEN` bytes of a
+/// potentially-large value, e.g. a packet or buffer dump. It uses the syntax
+/// `binary_capped("NAME", VALUE_REF, MAX_LEN)`, where `MAX_LEN` is a constant `u16`
+/// expression, e.g. `binary_capped("Packet", &packet, 64)`. The field is followed by a
+/// `U32` field named `"NAMELength"` (e.g. `PacketLength`) that contains the original
+/// length of the value (saturated to `u32::MAX`), so decoders can tell whether the
+/// value was truncated. The `tag` and `format` options apply only to the binary field.
+///
+/// [^ptr_len]: The `binary_ptr_len` type logs a buffer given as a raw pointer and a
+/// byte length instead of a slice, e.g. a buffer received from a C caller. It uses the
+/// syntax `binary_ptr_len("NAME", PTR, LEN)`, where `PTR` is a `*const u8` and `LEN` is
+/// a `usize`. No slice is created, so the buffer does not need to satisfy the
+/// requirements of [`core::slice::from_raw_parts`]. Since `LEN` bytes are read
+/// (at most 65535) from `PTR`, the `write_event!` must be inside an `unsafe` block and
+/// the caller must ensure that `PTR` is valid for reads of that many bytes (`PTR` may
+/// be null if `LEN` is 0). The pointer and length are evaluated only if the event is
+/// enabled.
+///
+/// [^caller]: The `caller_codepointer` type logs a return address from the stack
+/// instead of a value, so that an event written by a library can identify the code
+/// that called into the library. It uses the syntax `caller_codepointer("NAME", DEPTH)`,
+/// where `DEPTH` is a `u32`: `0` logs an address in the function that contains the
+/// `write_event!`, `1` logs the address in that function's caller to which the
+/// function will return, `2` logs the next caller, and so on. The address is captured
+/// with `RtlCaptureStackBackTrace`, which is much cheaper than capturing a full stack
+/// but is not free, so it is only captured if the event is enabled. Frames of inlined
+/// functions do not appear on the stack, so mark the function `#[inline(never)]` if
+/// it might be inlined. The field is `0` if the stack is not that deep or if not
+/// running on Windows.
+///
+/// [^char32]: The `char32` types log UTF-32 (UCS-4) characters from Rust `char`
+/// values, with no conversion. ETW has no UTF-32 string encoding, so `str32` (the same
+/// as `char32_slice`) is encoded as a variable-length array of characters, which
+/// decoders may display as an array rather than as a string. Prefer `str8` for
+/// strings that are available as `&str`.
+///
 /// [^cstr]: The `cstrN` types use a `0`-terminated `InType::CStrN` string encoding in
 /// the event. If the provided field value contains any `'\0'` characters then the event
 /// will include the value up to the first `'\0'`; otherwise the event will include the
@@ -663,6 +1035,39 @@ pub use tracelogging_macros::define_provider;
 /// [^errno]: The `errno` type is intended for use with C-style `errno` error codes. On
 /// Windows, the `errno` type behaves exactly like the `i32` type.
 ///
+/// [^error]: The `error` type logs an error and its
+/// [`source()`](https://doc.rust-lang.org/std/error/trait.Error.html#method.source)
+/// chain as a struct with two members: `Message` (a UTF-8 string with the error's
+/// `Display` text) and `Sources` (a variable-length array of UTF-8 strings with the
+/// `Display` text of each error in the source chain, outermost first). For example,
+/// `error("Error", &err)` where `err` is a `std::io::Error`. The strings are formatted
+/// into a temporary `Vec` when the event is written, so the `error` type requires
+/// `std`. Each string is truncated to 65535 bytes. For a `Box<dyn Error>`, pass
+/// `err.as_ref()`. The `error` type accepts a `tag` but not a `format`.
+///
+/// [^fmt]: The `fmt` type formats a [`format_args!`] value into a stack buffer, e.g.
+/// `fmt("Message", format_args!("{} of {}", index, count))`, so the text can be logged
+/// without allocating a `String`. As with other field values, formatting only happens
+/// if the event is enabled. Text longer than 512 bytes is truncated at a character
+/// boundary.
+///
+/// [^str_slice]: The `str8_slice` and `str16_slice` types log a variable-length array
+/// of counted strings, e.g. `str8_slice("Args", &["a", "b"])`. The value may be a slice
+/// of any type that implements `AsRef<[u8]>` (`str8_slice`) or `AsRef<[u16]>`
+/// (`str16_slice`), e.g. `&[&str]`, `&[String]`, or `&[&[u16]]`. The strings are
+/// encoded into a 2048-byte stack buffer when the event is written. Each string uses 2
+/// bytes plus its length, and the element count uses 2 bytes. If a string does not
+/// fit, the array ends with the previous string.
+///
+/// [^opt]: The `opt_TYPE` types (e.g. `opt_u32`, `opt_guid`, `opt_win_filetime`) are
+/// available for each `TYPE` that has a corresponding `TYPE_slice` type. The value
+/// must be a reference to an `Option` of the value type that `TYPE` expects, e.g.
+/// `opt_u32` expects `&Option<u32>` and `opt_ipv4` expects `&Option<[u8; 4]>`. The
+/// field is encoded as a variable-length array (the same as `TYPE_slice`) that has 0
+/// elements for `None` or 1 element for `Some(value)`, so the event's metadata is the
+/// same in both cases and decoders can distinguish a missing value from a default
+/// value.
+///
 /// [^systemtime]: When logging `systemtime` types, `write_event!` will convert the
 /// provided `std::time::SystemTime` value into a Win32
 /// [`FILETIME`](https://docs.microsoft.com/windows/win32/api/minwinbase/ns-minwinbase-filetime),
@@ -674,8 +1079,9 @@ pub use tracelogging_macros::define_provider;
 ///
 /// [^sid]: The `win_sid` type requires an input byte-slice value that is at least
 /// [`GetSidLength(value_bytes)`](https://docs.microsoft.com/windows/win32/api/securitybaseapi/nf-securitybaseapi-getlengthsid)
-/// =  `value_bytes[1] * 4 + 8` bytes long. `write_event!` will panic if the value is
-/// smaller than that size.
+/// =  `value_bytes[1] * 4 + 8` bytes long. If the value is not a valid SID (e.g. it is
+/// too short, as might happen with a value received from an untrusted source),
+/// `write_event!` logs the NULL SID (`S-1-0-0`) instead.
 ///
 /// [^time]: When logging `time32` and `time64` types, `write_event!` assumes that the
 /// provided `i32` or `i64` value is the number of seconds since 1970 (i.e. a `time_t`)
@@ -739,8 +1145,8 @@ pub use tracelogging_macros::define_provider;
 /// *Advanced:* In certain cases, you may need capabilities not directly exposed by the
 /// normal field types. For example,
 ///
-/// - You might need to log an array of a variable-sized type, such as an array of
-///   string.
+/// - You might need to log an array of a variable-sized type other than string (for
+///   arrays of string, use `str8_slice` or `str16_slice`).
 /// - You might need to log an array of struct.
 /// - You might want to log several fields in one block of data to reduce overhead.
 ///
@@ -817,6 +1223,20 @@ pub use tracelogging_macros::define_provider;
 ///   part of the struct. In cases of nested structs, a struct and its fields count as a
 ///   single logical field.
 ///
+/// - `raw_custom("NAME", PROTOCOL, SCHEMA_BYTES, VALUE_BYTES, tag(TAG))`
+///
+///   The `raw_custom` type allows you to add a field that uses a custom serialization
+///   protocol (e.g. a value serialized by a Bond or protobuf encoder). PROTOCOL must be
+///   a constant `u8` value in the range 0 to 31 that identifies the serialization
+///   protocol. SCHEMA_BYTES must be a constant `&[u8]` (at most 65535 bytes) that is
+///   stored in the event's metadata. VALUE_BYTES is specified as `&[u8]` and is stored
+///   in the event's data.
+///
+///   Unlike the other raw field types, VALUE_BYTES must not include a size prefix:
+///   `write_event!` adds the `u16` size prefix to both the schema and the value (values
+///   longer than 65535 bytes are truncated). TraceLogging decoders do not decode custom
+///   fields, so the event consumer must understand the specified protocol.
+///
 /// - `raw_data(VALUE_BYTES)`
 ///
 ///   The `raw_data` type allows you to add data to the event without specifying any
@@ -889,21 +1309,447 @@ pub use tracelogging_macros::define_provider;
 ///         2, 0,       // RawStructSlice[1].RawChar8s.Length = 2
 ///         48, 49,     // RawStructSlice[1].RawChar8s content
 ///     ]),
+///
+///     // Make a custom-serialized field using protocol 1 with a 2-byte schema.
+///     raw_custom("RawCustom", 1, &[0x10, 0x20], &[1, 2, 3]),
 /// );
 /// ```
 #[cfg(feature = "macros")]
 pub use tracelogging_macros::write_event;
 
+/// Sends an event to ETW via the specified provider if the provider is registered.
+/// Intended for use during early process initialization or late process shutdown.
+///
+/// `try_write_event!(PROVIDER_SYMBOL, "EventName", options and fields...);`
+///
+/// The syntax and behavior are the same as [`write_event!`] except that the generated
+/// code first checks whether the provider is registered. If the provider is not
+/// registered, `try_write_event!` returns 6 (`ERROR_INVALID_HANDLE`) without
+/// evaluating any field value expressions.
+///
+/// You can think of `try_write_event!(MY_PROVIDER, "EventName", options and fields...)`
+/// as expanding to code that is something like the following:
+///
+/// ```ignore
+/// if !MY_PROVIDER.is_registered() {
+///     6 // ERROR_INVALID_HANDLE
+/// } else if !MY_PROVIDER.enabled(event_level, event_keyword) {
+///     0
+/// } else if !MY_PROVIDER.event_filter().map_or(true, |filter| filter(&event_descriptor)) {
+///     0
+/// } else {
+///     EventWriteTransfer(MY_PROVIDER, options and fields...)
+/// }
+/// ```
+///
+/// Code generated by `try_write_event!` (like code generated by `write_event!`):
+///
+/// - Never allocates. Event metadata is a compile-time constant and event data is
+///   referenced in place.
+/// - Does not use Rust thread-local storage and takes no locks. The registration and
+///   enablement checks are simple reads of the provider's state.
+/// - Does not call into ETW unless the provider is registered and enabled.
+///
+/// This makes it safe to use in contexts where the process is not fully initialized,
+/// e.g. static constructors that run before `main` or before the provider has been
+/// registered, and in contexts that run after the provider has been unregistered. The
+/// non-zero return value can be used to detect that the event was dropped because the
+/// provider was not registered.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// // Provider is not registered yet: event is dropped, field values not evaluated.
+/// let result = tlg::try_write_event!(MY_PROVIDER, "EarlyEvent", u32("Value", &1));
+/// assert_eq!(result, 6);
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::try_write_event;
+
+/// Sends an event to ETW via the specified provider, using the field syntax of the C++
+/// `TraceLoggingWrite` macro. Intended for porting C++ code to Rust.
+///
+/// `write_event_cpp_style!(PROVIDER_SYMBOL, "EventName", TraceLoggingLevel(...), TraceLoggingInt32(...), ...);`
+///
+/// Each `TraceLoggingX(...)` argument is translated into the corresponding
+/// [`write_event!`] option or field, so C++ event definitions can be ported with few
+/// changes. The generated code is the same as for [`write_event!`].
+///
+/// - Field macros use the C++ argument order: `TraceLoggingInt32(value, "Name")`
+///   becomes `i32("Name", &(value))`. Values are passed by value (the macro adds the
+///   `&`), and must have the Rust type expected by the corresponding [`write_event!`]
+///   field type (e.g. `TraceLoggingGuid` needs a [`Guid`] and `TraceLoggingString`
+///   needs a `&str` or `&[u8]`).
+/// - The name is optional. If not specified, the text of the value expression is used
+///   as the field name (same as C++).
+/// - The optional description argument is ignored. The optional tag argument becomes a
+///   `tag(...)` option.
+/// - Counted and array macros take a count argument after the value:
+///   `TraceLoggingCountedUtf8String(value, count, "Name")` logs `&value[..count]`, and
+///   `TraceLoggingInt32Array(values, count, "Name")` becomes a `i32_slice` field.
+///   `TraceLoggingInt32FixedArray(values, N, "Name")` becomes an `i32_array` field.
+/// - `TraceLoggingStruct(fieldCount, "Name")` groups the next `fieldCount` fields into
+///   a struct (same as `raw_struct`).
+/// - `TraceLoggingLevel`, `TraceLoggingKeyword`, `TraceLoggingOpcode`,
+///   `TraceLoggingChannel`, `TraceLoggingEventTag`, and `TraceLoggingIdVersion` set the
+///   corresponding event options. The `WINEVENT_LEVEL_*` and `WINEVENT_OPCODE_*`
+///   constant names are accepted. `TraceLoggingDescription` is ignored.
+/// - `TraceLoggingValue` is supported only for literal values (e.g. `"text"`, `5`,
+///   `5u64`, `1.5`, `true`) because Rust macros cannot determine the type of an
+///   expression. Use a typed macro (e.g. `TraceLoggingInt32`) for other values.
+/// - Other options (e.g. `activity_id(...)` or `u32("Name", &value)`) are passed to
+///   [`write_event!`] unchanged, so ported code can be converted incrementally.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// let request_id = 42u32;
+/// let url = "/index.html";
+/// let status = [200u16, 304];
+/// tlg::write_event_cpp_style!(
+///     MY_PROVIDER,
+///     "RequestComplete",
+///     TraceLoggingLevel(WINEVENT_LEVEL_INFO),
+///     TraceLoggingKeyword(0x10),
+///     TraceLoggingUInt32(request_id), // Field name is "request_id".
+///     TraceLoggingUtf8String(url, "Url", "The request URL"),
+///     TraceLoggingUInt16Array(status, status.len(), "Status"),
+///     TraceLoggingValue("v1", "Version"),
+/// );
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::write_event_cpp_style;
+
+/// Generates a provider and typed event-writing functions from an ETW instrumentation
+/// manifest (`.man` file).
+///
+/// `include_manifest!("path/to/manifest.man");`
+///
+/// The path is relative to the directory containing the crate's `Cargo.toml`. The
+/// manifest is read at compile time, and the crate is rebuilt when it changes. For each
+/// `<provider>` element in the manifest, the macro generates:
+///
+/// - A [`define_provider!`] with the provider's name and guid. The provider symbol is
+///   the manifest's `symbol` attribute (or the provider name) converted to
+///   `UPPER_SNAKE_CASE`. The provider's `<keyword>` elements become
+///   `keywords(NAME = MASK, ...)` constants, e.g. `MY_PROVIDER::KEYWORD_NETWORK`.
+/// - For each `<event>`, a `pub fn` named after the event's `symbol` attribute (or its
+///   task and opcode names) converted to `snake_case`. The function has one parameter
+///   for each `<data>` element in the event's template and returns the result of
+///   [`write_event!`] (a Win32 error code, for diagnostic purposes only). The event
+///   uses the manifest's id, version, level, keywords, opcode, and task.
+///
+/// The field type for each `<data>` element is selected based on the `inType` (and for
+/// some types, the `outType`), e.g. `win:UInt32` becomes `u32`, `win:UInt32` with
+/// `win:Win32Error` becomes `win_error`, and `win:UnicodeString` becomes `str8` (the
+/// parameter is a `&str`). Data with a `count` attribute becomes a `TYPE_slice` field.
+/// Structs, maps, and `length` attributes are not supported.
+///
+/// Note that events written by the generated functions use the TraceLogging encoding,
+/// so decoders will use the TraceLogging metadata in the event instead of the
+/// manifest. Channels and messages from the manifest are not used.
+///
+/// ```ignore
+/// use tracelogging as tlg;
+///
+/// // Generates MY_PROVIDER and a request_start(url: &str, request_id: &u32) function.
+/// tlg::include_manifest!("src/my_provider.man");
+///
+/// unsafe { MY_PROVIDER.register(); }
+/// request_start("/index.html", &42);
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::include_manifest;
+
+/// Generates a provider and typed event-writing functions from a JSON event catalog.
+///
+/// `include_event_catalog!("path/to/events.json");`
+///
+/// Use this to enforce a reviewed list of events: the catalog file lists the
+/// provider's events and their fields, and code writes events by calling the
+/// generated functions instead of using [`write_event!`] directly. The path is
+/// relative to the directory containing the crate's `Cargo.toml`. The catalog is read
+/// at compile time, and the crate is rebuilt when it changes.
+///
+/// The catalog is a JSON object with the following members:
+///
+/// - `"provider"`: the provider name (required).
+/// - `"symbol"`: the provider symbol. Defaults to the provider name. The symbol is
+///   converted to `UPPER_SNAKE_CASE`.
+/// - `"id"`, `"group_id"`: the provider's id and group id, as for [`define_provider!`].
+/// - `"keywords"`: an object mapping keyword names to masks, e.g.
+///   `{ "Network": "0x1" }`. These become `keywords(NAME = MASK, ...)` constants, e.g.
+///   `MY_PROVIDER::KEYWORD_NETWORK`.
+/// - `"events"`: an array of event objects.
+///
+/// Each event object has the following members:
+///
+/// - `"name"`: the event name (required).
+/// - `"function"`: the name of the generated function. Defaults to the event name
+///   converted to `snake_case`.
+/// - `"description"`: text for the generated function's doc comment.
+/// - `"level"`, `"opcode"`: a name (e.g. `"Warning"`, `"Start"`) or an integer.
+/// - `"keywords"`: an array of keyword names from the catalog's `"keywords"`.
+/// - `"id"`, `"version"`, `"task"`, `"tag"`: integers.
+/// - `"fields"`: an array of field objects.
+///
+/// Each field object has a `"name"` (required), a `"type"` (required), and optional
+/// `"format"`, `"tag"`, and `"description"` members. The type is a [`write_event!`]
+/// field type, e.g. `"u32"`, `"str8"`, or `"guid_slice"`, and the format is an
+/// [`OutType`] name, e.g. `"Hex"`. Struct, raw, array, and other special field types
+/// are not supported.
+///
+/// Integers may be JSON numbers or strings (decimal or `0x` hex). Unknown members
+/// are reported as errors.
+///
+/// For each event, the macro generates a `pub fn` with one parameter for each field
+/// and returns the result of [`write_event!`] (a Win32 error code, for diagnostic
+/// purposes only). The parameter name is the field name converted to `snake_case`.
+/// The parameter type is a reference to the field type's value type, e.g. `&u32` for
+/// `u32`, `&str` for `str8`, and `&[u16]` for `u16_slice` or `str16`.
+///
+/// ```ignore
+/// use tracelogging as tlg;
+///
+/// // events.json:
+/// // {
+/// //   "provider": "MyCompany.MyComponent",
+/// //   "symbol": "MY_PROVIDER",
+/// //   "keywords": { "Network": 1 },
+/// //   "events": [
+/// //     { "name": "RequestStart", "level": "Informational", "keywords": ["Network"],
+/// //       "fields": [
+/// //         { "name": "Url", "type": "str8" },
+/// //         { "name": "RequestId", "type": "u32", "format": "Hex" } ] }
+/// //   ]
+/// // }
+///
+/// // Generates MY_PROVIDER and a request_start(url: &str, request_id: &u32) function.
+/// tlg::include_event_catalog!("src/events.json");
+///
+/// unsafe { MY_PROVIDER.register(); }
+/// request_start("/index.html", &42);
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::include_event_catalog;
+
+/// Declares named field tags with validated values and a lookup registry.
+///
+/// `define_field_tags!(TAGS_SYMBOL, NAME = VALUE, ...);`
+///
+/// A field tag is a 28-bit value with provider-defined semantics that is attached to a
+/// field with the `tag(...)` field option (see [`write_event!`]). Declaring the tags
+/// in one place (instead of repeating magic numbers at each `write_event!`) documents
+/// their meaning and lets tools map tag values back to names.
+///
+/// Each tag may have a doc comment. Each VALUE must be an integer literal in the range
+/// `0x1` to `0x0FFFFFFF`. Repeated names and repeated values are compile-time errors.
+///
+/// The macro generates a module named `TAGS_SYMBOL` containing:
+///
+/// - A `u32` constant for each tag, e.g. `MY_TAGS::PII`, for use in `tag(...)` options.
+/// - A `TAGS` constant of type [`FieldTags`] with each tag's name, value, and doc
+///   comment, with [`FieldTags::lookup`] and [`FieldTags::lookup_name`] methods.
+///
+/// Like the provider symbol from [`define_provider!`], the module is not `pub`. To
+/// share the tags with multiple modules, declare them in the parent module.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_field_tags!(
+///     MY_TAGS,
+///     /// Field contains personal data.
+///     PII = 0x0100_0000,
+///     /// Field contains a hashed value.
+///     HASHED = 0x0200_0000,
+/// );
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// let user = "user@example.com";
+/// tlg::write_event!(MY_PROVIDER, "Login", str8("User", user, tag(MY_TAGS::PII)));
+///
+/// let pii = MY_TAGS::TAGS.lookup(0x0100_0000).unwrap();
+/// assert_eq!(pii.name(), "PII");
+/// assert_eq!(pii.doc(), "Field contains personal data.");
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::define_field_tags;
+
+/// Writes an activity-start event and returns an [`ActivityScope`] that writes the
+/// matching activity-stop event when it is dropped.
+///
+/// `activity_scope!(PROVIDER_SYMBOL, "ActivityName", options and fields...)`
+///
+/// The syntax is the same as [`write_event!`] except that the `opcode`, `activity`,
+/// `activity_id`, and `related_id` options are not allowed. The macro:
+///
+/// - Generates a new activity id (via [`Provider::create_activity_id`]) and makes it
+///   the current thread's activity id. The previous thread activity id becomes the
+///   activity's related (parent) id.
+/// - Writes an event with opcode [`Opcode::ActivityStart`], the new activity id, the
+///   parent id as the related id, and the specified options and fields.
+/// - Returns an [`ActivityScope`]. When the scope is dropped, it writes an event with
+///   the same name and options, opcode [`Opcode::ActivityStop`], and no fields, then
+///   restores the previous thread activity id.
+///
+/// Events written on the same thread while the scope is alive (without an
+/// `activity_id` option) are part of the activity, so nested activities are recorded
+/// with the correct parent.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// fn process(item: u32) -> Result<(), ()> {
+///     let _activity = tlg::activity_scope!(
+///         MY_PROVIDER,
+///         "ProcessItem",
+///         level(Informational),
+///         u32("Item", &item),
+///     );
+///     if item == 0 {
+///         return Err(()); // ProcessItem stop event is written here...
+///     }
+///     tlg::write_event!(MY_PROVIDER, "Step", level(Verbose)); // Part of ProcessItem.
+///     return Ok(()); // ...or here.
+/// }
+/// # let _ = process(1);
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::activity_scope;
+
+/// Derives a `write(&self, provider: &Provider) -> u32` method that writes the
+/// struct as an event.
+///
+/// This is useful when the same structured record is logged from many places. The
+/// event's name is the struct's name, and each field of the struct becomes a field of
+/// the event, with the same name. The `write` method uses the same code generation as
+/// [`write_event!`], so fields are only evaluated if the provider is enabled for the
+/// event's level and keyword, and the return value is the same as for `write_event!`.
+/// Since the provider is a parameter rather than a `static`, derived events are not
+/// included in `event_schemas()` (feature `export_schema`).
+///
+/// The field type is chosen based on the field's Rust type:
+///
+/// | Rust type | Field type
+/// |-----------|-----------
+/// | `bool`, `char`, `i8`..`i64`, `isize`, `u8`..`u64`, `usize`, `f32`, `f64`, `Guid` | `bool8`, `char32`, `i8`..`i64`, `isize`, `u8`..`u64`, `usize`, `f32`, `f64`, `guid`
+/// | `String`, `&str` | `str8`
+/// | `SystemTime` | `systemtime`
+/// | `Vec<T>`, `[T; N]`, `&[T]` where `T` is a scalar type above | `T_slice` (`binary` if `T` is `u8`)
+/// | `Option<T>` where `T` is a scalar type above | `opt_T`
+///
+/// Use attributes to customize the event:
+///
+/// - `#[event(OPTIONS...)]` on the struct specifies [`write_event!`] options for the
+///   event, e.g. `#[event(level(Warning), keyword(0x10))]`. Option values may refer
+///   to `self`, e.g. `#[event(activity_id(&self.activity))]`. If no `keyword` option
+///   is specified, the event uses keyword `0x1` (the provider's `default_keyword` is
+///   not available because the provider is a parameter).
+/// - `#[event(TYPE)]` on a field specifies the field type, e.g. `#[event(u32_hex)]`
+///   or `#[event(str8_json)]`. This is required for field types that are not listed
+///   above.
+/// - `#[event(OPTIONS...)]` on a field specifies field options, e.g.
+///   `#[event(format(Hex))]` or `#[event(u32, tag(0x1234))]`.
+/// - `#[event(skip)]` on a field excludes the field from the event.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// #[derive(tlg::TraceLoggingEvent)]
+/// #[event(level(Informational), keyword(0x1))]
+/// struct PacketSent<'a> {
+///     bytes: u32,
+///     dest: &'a str,
+///     #[event(format(Hex))]
+///     flags: u16,
+///     #[event(skip)]
+///     _internal_state: Vec<String>,
+/// }
+///
+/// let packet_sent = PacketSent {
+///     bytes: 1500,
+///     dest: "192.168.0.1",
+///     flags: 0x12,
+///     _internal_state: Vec::new(),
+/// };
+/// packet_sent.write(&MY_PROVIDER);
+/// ```
+#[cfg(feature = "macros")]
+pub use tracelogging_macros::TraceLoggingEvent;
+
+pub use activity::ActivityScope;
+#[cfg(feature = "alloc_stats")]
+pub use alloc_stats::AllocStats;
+#[cfg(feature = "alloc_stats")]
+pub use alloc_stats::StatsAllocator;
+pub use descriptors::EventDescriptor;
+pub use descriptors::EventFilterDescriptor;
+pub use emit_policy::EmitPolicy;
+pub use enable_state::ProviderEnableState;
+#[cfg(feature = "export_schema")]
+pub use event_schema::duplicate_event_ids;
+#[cfg(feature = "export_schema")]
+pub use event_schema::duplicate_providers;
+#[cfg(feature = "export_schema")]
+pub use event_schema::event_schemas;
+#[cfg(feature = "export_schema")]
+pub use event_schema::provider_schemas;
+#[cfg(feature = "export_schema")]
+pub use event_schema::write_schema_artifact;
+#[cfg(feature = "export_schema")]
+pub use event_schema::EventSchema;
+#[cfg(feature = "export_schema")]
+pub use event_schema::EventSchemaField;
+#[cfg(feature = "export_schema")]
+pub use event_schema::EventSchemaFields;
+#[cfg(feature = "export_schema")]
+pub use event_schema::ProviderSchema;
 pub use enums::Channel;
+pub use enums::EventControlCode;
 pub use enums::InType;
 pub use enums::Level;
 pub use enums::Opcode;
 pub use enums::OutType;
+pub use enums::ParseEnumError;
+pub use enums::WriteFlags;
+pub use field_tags::FieldTag;
+pub use field_tags::FieldTags;
 pub use guid::Guid;
+#[cfg(feature = "log-adapter")]
+pub use log_adapter::level_from_log;
+#[cfg(feature = "log-adapter")]
+pub use log_adapter::LogAdapter;
+pub use native::EnableChangedCallback;
 pub use native::NativeImplementation;
+#[cfg(feature = "write_hook")]
+pub use native::PreWriteHook;
 pub use native::ProviderEnableCallback;
 pub use native::NATIVE_IMPLEMENTATION;
+pub use provider::EventFilter;
 pub use provider::Provider;
+pub use provider::RegisterError;
+pub use provider::RegisterOptions;
+pub use provider::WriteError;
+pub use provider_meta::ProviderMetadata;
+pub use provider_meta::ProviderTrait;
+pub use provider_meta::ProviderTraits;
+pub use provider_set::ProviderSet;
+#[cfg(feature = "stats")]
+pub use provider_stats::ProviderStats;
+pub use session_info::ProviderEnabledInfo;
+pub use session_info::SessionEnableInfo;
+pub use state_source::StateSource;
+pub use state_source::StateSources;
 pub mod _internal;
 pub mod changelog;
 
@@ -973,8 +1819,298 @@ macro_rules! provider_enabled {
     }};
 }
 
+/// Creates a static [`ProviderSet`] variable, i.e. a symbol that can be used in place
+/// of a provider symbol to write each event to several providers.
+///
+/// `define_provider_set!(SET_SYMBOL, [PROVIDER_SYMBOL1, PROVIDER_SYMBOL2, ...]);`
+///
+/// Each `PROVIDER_SYMBOL` must be the original symbol name created by
+/// [`define_provider!`] in the same module (or imported into it), not a reference or
+/// alias. The providers are registered and unregistered individually; the set has
+/// no registration of its own.
+///
+/// Use `SET_SYMBOL` as the provider symbol of [`write_event!`] (or the other event
+/// macros). The event's metadata and data are built once, then the event is written to
+/// each provider in the set that is enabled for the event's level and keyword and
+/// whose event filter accepts the event. The macro returns 0 for success or the first
+/// nonzero Win32 error code from a provider.
+///
+/// Like the module created by [`define_provider!`], this also creates a type named
+/// `SET_SYMBOL` with the constants used by [`write_event!`]:
+///
+/// - `DEFAULT_KEYWORD` is the first provider's `DEFAULT_KEYWORD`.
+/// - `KEYWORDS_MASK` is the intersection of the providers' `KEYWORDS_MASK` values, so
+///   an event's keyword must be allowed by every provider in the set.
+///
+/// If feature `export_schema` is enabled, the schemas of events written to the set are
+/// exported as events of the first provider (see [`ProviderSet::primary`]).
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(TELEMETRY_PROVIDER, "MyCompany.MyComponent.Telemetry");
+/// tlg::define_provider!(DEBUG_PROVIDER, "MyCompany.MyComponent.Debug");
+/// tlg::define_provider_set!(ALL_PROVIDERS, [TELEMETRY_PROVIDER, DEBUG_PROVIDER]);
+///
+/// unsafe {
+///     TELEMETRY_PROVIDER.register();
+///     DEBUG_PROVIDER.register();
+/// }
+///
+/// tlg::write_event!(ALL_PROVIDERS, "Started", level(Informational), u32("Port", &443));
+///
+/// DEBUG_PROVIDER.unregister();
+/// TELEMETRY_PROVIDER.unregister();
+/// ```
+#[macro_export]
+macro_rules! define_provider_set {
+    ($set_symbol:ident, [$first_symbol:ident $(, $provider_symbol:ident)* $(,)?]) => {
+        static $set_symbol: ::tracelogging::ProviderSet =
+            ::tracelogging::ProviderSet::new(&[&$first_symbol $(, &$provider_symbol)*]);
+
+        // A type (not a module) so that the constants can refer to providers defined
+        // in a function body.
+        #[allow(non_camel_case_types, dead_code)]
+        enum $set_symbol {}
+
+        #[allow(dead_code)]
+        impl $set_symbol {
+            #[allow(deprecated)]
+            pub const DEFAULT_KEYWORD: u64 = $first_symbol::DEFAULT_KEYWORD;
+            pub const KEYWORDS_MASK: u64 =
+                $first_symbol::KEYWORDS_MASK $(& $provider_symbol::KEYWORDS_MASK)*;
+        }
+    };
+}
+
+/// Sends a state (rundown) event to ETW via the specified provider. Intended for use
+/// in a [`StateSource`] function.
+///
+/// `write_state_event!(PROVIDER_SYMBOL, "EventName", options and fields...);`
+///
+/// This is the same as [`write_event!`] except that the event automatically uses
+/// `opcode(DC_Start)` ([`Opcode::CollectionStart`], the conventional opcode for
+/// rundown events) and includes [`Provider::STATE_KEYWORD`] in its keyword. Do not
+/// specify an `opcode` option. Other options and fields are the same as for
+/// [`write_event!`]. The event's keyword will not include the default keyword (`0x1`)
+/// unless specified by a `keyword` option.
+///
+/// State events are written by the provider's [`StateSource`] functions when an ETW
+/// session sends a capture-state request, so that a trace includes the state that
+/// existed before the trace started, e.g. configuration settings or the list of open
+/// connections. Use [`Provider::add_state_source`] to add a source to a provider.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// static CONNECTIONS: tlg::StateSource = tlg::StateSource::new(|| {
+///     for (id, peer) in [(1u32, "10.0.0.1"), (2, "10.0.0.2")] {
+///         tlg::write_state_event!(
+///             MY_PROVIDER,
+///             "Connection",
+///             level(Informational),
+///             u32("Id", &id),
+///             str8("Peer", peer),
+///         );
+///     }
+/// });
+///
+/// MY_PROVIDER.add_state_source(&CONNECTIONS);
+/// unsafe { MY_PROVIDER.register(); }
+/// MY_PROVIDER.capture_state(); // Write the initial state (optional).
+/// ```
+#[cfg(feature = "macros")]
+#[macro_export]
+macro_rules! write_state_event {
+    ($provider_symbol:ident, $event_name:tt $(, $($args:tt)*)?) => {
+        ::tracelogging::write_event!(
+            $provider_symbol,
+            $event_name,
+            opcode(DC_Start),
+            keyword(::tracelogging::Provider::STATE_KEYWORD)
+            $(, $($args)*)?
+        )
+    };
+}
+
+/// Limits how often a call site emits, e.g. to tame error storms: emits the first `N`
+/// occurrences, then every `M`th occurrence.
+///
+/// `emit_policy!(first(N), then_every(M))`
+///
+/// `emit_policy!(first(N), then_every(M), PROVIDER_SYMBOL, "EventName", options and fields...)`
+///
+/// Each invocation creates a `static` [`EmitPolicy`] for the call site, i.e. the
+/// occurrences are counted per call site, across all threads. `N` and `M` must be
+/// compile-time constant `u64` expressions. If `M` is 0, only the first `N`
+/// occurrences are emitted.
+///
+/// - With only the policy, records an occurrence and returns an `Option<u64>`: `Some`
+///   with the total number of occurrences so far (including this one) if the
+///   occurrence should be emitted, or `None` if it should be suppressed.
+/// - With a provider and event, records an occurrence and, if the occurrence should be
+///   emitted, writes the event with [`write_event!`] and returns its result. The
+///   event gets an additional `u64` field named `Occurrences` (the total number of
+///   occurrences so far) before the other fields, so the decoder can tell how many
+///   occurrences were suppressed. Returns 0 if the occurrence is suppressed.
+///
+/// Occurrences are counted even if the provider is not enabled.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// fn on_error(code: u32) {
+///     // Occurrences 1..=10, 110, 210, ...
+///     tlg::emit_policy!(
+///         first(10),
+///         then_every(100),
+///         MY_PROVIDER,
+///         "RequestFailed",
+///         level(Warning),
+///         u32("Code", &code),
+///     );
+/// }
+///
+/// fn on_retry() {
+///     if let Some(count) = tlg::emit_policy!(first(1), then_every(1000)) {
+///         println!("retried {} times", count);
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! emit_policy {
+    (first($first:expr), then_every($then_every:expr) $(,)?) => {{
+        static _TLG_POLICY: ::tracelogging::EmitPolicy =
+            ::tracelogging::EmitPolicy::new($first, $then_every);
+        _TLG_POLICY.check()
+    }};
+    (first($first:expr), then_every($then_every:expr), $provider_symbol:ident, $event_name:tt $(, $($args:tt)*)?) => {{
+        static _TLG_POLICY: ::tracelogging::EmitPolicy =
+            ::tracelogging::EmitPolicy::new($first, $then_every);
+        match _TLG_POLICY.check() {
+            ::core::option::Option::None => 0u32,
+            ::core::option::Option::Some(_tlg_occurrences) => ::tracelogging::write_event!(
+                $provider_symbol,
+                $event_name,
+                u64("Occurrences", &_tlg_occurrences)
+                $(, $($args)*)?
+            ),
+        }
+    }};
+}
+
+/// Writes a marker event, i.e. a navigation anchor for tools like WPA or a
+/// time-travel-debugging (TTD) trace, e.g. to mark the start of a phase of work.
+///
+/// `mark!(PROVIDER_SYMBOL, "MarkerName");`
+///
+/// `mark!(PROVIDER_SYMBOL, "MarkerName", fields...);`
+///
+/// Marker events follow a fixed convention so that tools (and people) can find them
+/// without knowing the provider's other events:
+///
+/// - The event name is the marker name.
+/// - The keyword is [`Provider::MARKER_KEYWORD`] and the level is
+///   [`LogAlways`](Level::LogAlways), so a session can collect a provider's markers
+///   by enabling the provider with that keyword, regardless of level.
+/// - The first field is a `u64` named `MarkSequence`: a process-wide sequence
+///   number (starting at 1) that orders the markers written by all providers.
+/// - The second field is a `u64` named `MarkQpc`: the value of
+///   `QueryPerformanceCounter` when the marker was written, for correlating with
+///   other QPC-based timestamps such as TTD positions (0 if not Windows/etw).
+/// - Any additional fields follow, using the same syntax as [`write_event!`].
+///
+/// As with [`write_event!`], the marker is written (and consumes a sequence number)
+/// only if the provider is enabled for the marker keyword. Returns 0 for success or a
+/// Win32 error code.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// fn run_phase(phase: u32) {
+///     tlg::mark!(MY_PROVIDER, "PhaseStart", u32("Phase", &phase));
+///     // ...
+///     tlg::mark!(MY_PROVIDER, "PhaseStop");
+/// }
+/// # run_phase(2);
+/// ```
+#[cfg(feature = "macros")]
+#[macro_export]
+macro_rules! mark {
+    ($provider_symbol:ident, $marker_name:tt $(, $($args:tt)*)?) => {
+        ::tracelogging::write_event!(
+            $provider_symbol,
+            $marker_name,
+            level(LogAlways),
+            keyword(::tracelogging::Provider::MARKER_KEYWORD),
+            u64("MarkSequence", &::tracelogging::_internal::next_mark_sequence()),
+            u64("MarkQpc", &::tracelogging::_internal::performance_counter())
+            $(, $($args)*)?
+        )
+    };
+}
+
+/// Generates a unit test that fails if the current crate defines the same provider
+/// (same name or same id) more than once, or if two events of a provider defined in
+/// the current crate have the same id and version but different names (requires
+/// feature `export_schema`).
+///
+/// `test_unique_providers!();`
+///
+/// Invoke once, e.g. in the crate's `lib.rs`. Expands to nothing unless the crate is
+/// compiled with `cfg(test)`, where it expands to a `#[test]` function named
+/// `tracelogging_unique_providers` that calls [`duplicate_providers`] and
+/// [`duplicate_event_ids`] and panics with the duplicates (if any). Providers defined
+/// in other crates are ignored.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// tlg::test_unique_providers!();
+/// ```
+#[cfg(feature = "export_schema")]
+#[macro_export]
+macro_rules! test_unique_providers {
+    () => {
+        #[cfg(test)]
+        #[test]
+        fn tracelogging_unique_providers() {
+            $crate::_internal::assert_unique_providers(::core::module_path!());
+        }
+    };
+}
+
+mod activity;
+mod adoption;
+#[cfg(feature = "alloc_stats")]
+mod alloc_stats;
 mod descriptors;
+mod emit_policy;
+mod enable_state;
+#[cfg(feature = "export_schema")]
+mod event_schema;
 mod enums;
+mod field_tags;
 mod guid;
+#[cfg(feature = "keyword_levels")]
+mod keyword_levels;
+#[cfg(feature = "log-adapter")]
+mod log_adapter;
+mod meta_reader;
 mod native;
 mod provider;
+mod provider_meta;
+mod provider_set;
+#[cfg(feature = "stats")]
+mod provider_stats;
+mod session_info;
+mod spin_lock;
+mod state_source;
@@ -0,0 +1,196 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::str::from_utf8;
+
+use crate::descriptors::counted_size;
+use crate::descriptors::EventDataDescriptor;
+use crate::descriptors::EventDescriptor;
+use crate::enums::InType;
+use crate::enums::Level;
+use crate::enums::OutType;
+use crate::provider::provider_event_filter;
+use crate::provider::provider_write_transfer;
+use crate::provider::Provider;
+
+/// Metadata for the "Log" event.
+static EVENT_META: [u8; 52] = *b"\x34\x00\x00Log\0\
+    Message\0\x97\x23\
+    Target\0\x97\x23\
+    ModulePath\0\x97\x23\
+    File\0\x97\x23\
+    Line\0\x08";
+
+const _: () = {
+    // Verify the hand-encoded metadata.
+    assert!(EVENT_META[0] as usize == EVENT_META.len());
+    assert!(EVENT_META[15] == InType::Str8.as_int() | 0x80); // Message: Str8 + OutType
+    assert!(EVENT_META[16] == OutType::Utf8.as_int());
+    assert!(EVENT_META[EVENT_META.len() - 1] == InType::U32.as_int());
+};
+
+/// Maximum size (in bytes) of the `Message` field. Longer messages are truncated.
+const MESSAGE_MAX: usize = 1024;
+
+/// Returns the tracelogging level that corresponds to a `log` level: `Error` →
+/// [`Level::Error`](const@Level::Error), `Warn` → [`Level::Warning`], `Info` →
+/// [`Level::Informational`], `Debug` and `Trace` → [`Level::Verbose`].
+pub const fn level_from_log(level: log::Level) -> Level {
+    return match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn => Level::Warning,
+        log::Level::Info => Level::Informational,
+        log::Level::Debug | log::Level::Trace => Level::Verbose,
+    };
+}
+
+/// An implementation of [`log::Log`] that writes each log record as a TraceLogging
+/// event (requires feature `log-adapter`).
+///
+/// This allows existing `log::info!(...)` call sites to produce ETW events without
+/// being rewritten. Each record is written to the specified provider as a `"Log"`
+/// event with the level from [`level_from_log`], the adapter's keyword, and the
+/// following fields:
+///
+/// - `Message`: UTF-8 string, the formatted message (truncated to 1024 bytes).
+/// - `Target`: UTF-8 string, the record's target (usually the module path).
+/// - `ModulePath`: UTF-8 string, the module that logged the record, or empty.
+/// - `File`: UTF-8 string, the source file that logged the record, or empty.
+/// - `Line`: `u32`, the source line that logged the record, or 0.
+///
+/// Records are only formatted if the provider is enabled for the record's level and
+/// the adapter's keyword, so disabled `log` calls are cheap. Writing an event does not
+/// allocate. Use `log::set_max_level` to filter records before they reach the adapter.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(LOG_PROVIDER, "MyCompany.MyComponent.Log");
+/// static LOGGER: tlg::LogAdapter = tlg::LogAdapter::new(&LOG_PROVIDER, 0x1);
+///
+/// unsafe { LOG_PROVIDER.register(); }
+/// log::set_logger(&LOGGER).unwrap();
+/// log::set_max_level(log::LevelFilter::Trace);
+///
+/// log::info!("Request {} complete", 42);
+/// ```
+pub struct LogAdapter {
+    provider: &'static Provider,
+    keyword: u64,
+}
+
+impl LogAdapter {
+    /// Returns an adapter that writes log records to `provider` with the specified
+    /// keyword.
+    pub const fn new(provider: &'static Provider, keyword: u64) -> Self {
+        return Self { provider, keyword };
+    }
+
+    /// Returns the adapter's provider.
+    pub const fn provider(&self) -> &'static Provider {
+        return self.provider;
+    }
+
+    /// Returns the adapter's keyword.
+    pub const fn keyword(&self) -> u64 {
+        return self.keyword;
+    }
+}
+
+impl log::Log for LogAdapter {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        return self
+            .provider
+            .enabled(level_from_log(metadata.level()), self.keyword);
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let provider = self.provider;
+        let descriptor = EventDescriptor::new(level_from_log(record.level()), self.keyword);
+        if !provider.enabled(descriptor.level, descriptor.keyword)
+            || !provider_event_filter(provider, &descriptor)
+        {
+            return;
+        }
+
+        let mut buffer = MessageBuffer {
+            buf: [0; MESSAGE_MAX],
+            len: 0,
+        };
+        let message = match record.args().as_str() {
+            Some(message) => message,
+            None => {
+                let _ = fmt::write(&mut buffer, *record.args());
+                buffer.as_str()
+            }
+        };
+
+        let target = record.target();
+        let module_path = record.module_path().unwrap_or_default();
+        let file = record.file().unwrap_or_default();
+        let line = record.line().unwrap_or_default();
+
+        let message_size = counted_size(message.as_bytes());
+        let target_size = counted_size(target.as_bytes());
+        let module_path_size = counted_size(module_path.as_bytes());
+        let file_size = counted_size(file.as_bytes());
+        let mut dd = [
+            EventDataDescriptor::from_raw_bytes(provider.raw_meta(), 2), // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
+            EventDataDescriptor::from_raw_bytes(&EVENT_META, 1), // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
+            EventDataDescriptor::from_value(&message_size),
+            EventDataDescriptor::from_counted(message.as_bytes()),
+            EventDataDescriptor::from_value(&target_size),
+            EventDataDescriptor::from_counted(target.as_bytes()),
+            EventDataDescriptor::from_value(&module_path_size),
+            EventDataDescriptor::from_counted(module_path.as_bytes()),
+            EventDataDescriptor::from_value(&file_size),
+            EventDataDescriptor::from_counted(file.as_bytes()),
+            EventDataDescriptor::from_value(&line),
+        ];
+        provider_write_transfer(provider, &descriptor, None, None, &mut dd);
+    }
+
+    fn flush(&self) {}
+}
+
+impl fmt::Debug for LogAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "LogAdapter {{ provider: \"{}\", keyword: 0x{:X} }}",
+            self.provider.name(),
+            self.keyword
+        );
+    }
+}
+
+/// Fixed-size buffer for formatting a message. Output beyond the buffer's capacity
+/// is discarded (at a char boundary).
+struct MessageBuffer {
+    buf: [u8; MESSAGE_MAX],
+    len: usize,
+}
+
+impl MessageBuffer {
+    fn as_str(&self) -> &str {
+        return from_utf8(&self.buf[..self.len]).unwrap_or_default();
+    }
+}
+
+impl fmt::Write for MessageBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut len = s.len().min(MESSAGE_MAX - self.len);
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.buf[self.len..self.len + len].copy_from_slice(&s.as_bytes()[..len]);
+        self.len += len;
+        return if len == s.len() {
+            Ok(())
+        } else {
+            Err(fmt::Error)
+        };
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::str::from_utf8;
+
+use crate::enums::InType;
+use crate::enums::OutType;
+
+/// A field definition from TraceLogging event metadata, as returned by
+/// [`MetaReader::read_field`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MetaField<'a> {
+    /// The field's name.
+    pub name: &'a str,
+    /// The field's encoding, without flags.
+    pub in_type: InType,
+    /// The field's flags: 0, [`InType::VariableCountFlag`],
+    /// [`InType::ConstantCountFlag`], or [`InType::CustomFlag`].
+    pub flags: u8,
+    /// The field's format, without the chain flag. For a struct, the number of
+    /// member fields that follow the struct.
+    pub out_type: u8,
+    /// The field's tag (0 if the field has no tag).
+    pub tag: u32,
+    /// For a fixed-length array, the number of elements. Otherwise 0.
+    pub constant_count: u16,
+    /// For a custom-serialized field, the field's schema. Otherwise empty.
+    pub schema: &'a [u8],
+}
+
+impl<'a> MetaField<'a> {
+    /// Returns true if the field is an array (variable-length or fixed-length).
+    pub const fn is_array(&self) -> bool {
+        return self.flags == InType::VariableCountFlag || self.flags == InType::ConstantCountFlag;
+    }
+
+    /// Returns true if the field is a struct (not a custom-serialized field), i.e. if
+    /// its member fields follow it.
+    pub fn is_struct(&self) -> bool {
+        return self.in_type == InType::Struct && self.flags != InType::CustomFlag;
+    }
+}
+
+/// Parser for TraceLogging event metadata: u16 size, tag, NUL-terminated event name,
+/// then field definitions. Shared by the event schema, decoding, validation, and
+/// sanitizing code so that the metadata format is parsed in one place.
+///
+/// Each method returns `Err(offset)` if the metadata is malformed at `offset`. After
+/// an error, the reader is at the end of the metadata.
+#[derive(Clone, Debug)]
+pub struct MetaReader<'a> {
+    meta: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> MetaReader<'a> {
+    /// Returns a reader positioned at the event tag, i.e. after the u16 metadata size.
+    /// The size is not checked: the metadata ends at the end of `meta`.
+    pub const fn new(meta: &'a [u8]) -> Self {
+        return Self { meta, pos: 2 }; // Skip u16 metadata size.
+    }
+
+    /// Returns a reader positioned at the event tag after checking that the u16
+    /// metadata size at the start of `meta` is valid. The metadata ends at that size.
+    pub fn with_size(meta: &'a [u8]) -> Result<Self, usize> {
+        if meta.len() < 2 {
+            return Err(0);
+        }
+
+        let size = u16::from_le_bytes([meta[0], meta[1]]) as usize;
+        if size < 2 || size > meta.len() {
+            return Err(0);
+        }
+
+        return Ok(Self::new(&meta[..size]));
+    }
+
+    /// Returns the offset of the next byte to be read.
+    pub const fn pos(&self) -> usize {
+        return self.pos;
+    }
+
+    /// Returns true if there are no more field definitions.
+    pub const fn at_end(&self) -> bool {
+        return self.pos >= self.meta.len();
+    }
+
+    /// Reads a tag: up to 4 bytes of 7 bits each (most-significant first), with the
+    /// high bit set on each byte except the last.
+    pub fn read_tag(&mut self) -> Result<u32, usize> {
+        let start = self.pos;
+        let mut tag = 0;
+        let mut shift = 21;
+        loop {
+            let byte = self.read_u8()?;
+            tag |= ((byte & 0x7F) as u32) << shift;
+            if (byte & 0x80) == 0 {
+                return Ok(tag);
+            } else if shift == 0 {
+                return self.fail(start);
+            }
+            shift -= 7;
+        }
+    }
+
+    /// Reads a NUL-terminated UTF-8 name (the event name or a field name).
+    pub fn read_name(&mut self) -> Result<&'a str, usize> {
+        let start = self.pos.min(self.meta.len());
+        let len = match self.meta[start..].iter().position(|&b| b == 0) {
+            Some(len) => len,
+            None => return self.fail(start),
+        };
+        return match from_utf8(&self.meta[start..start + len]) {
+            Ok(name) => {
+                self.pos = start + len + 1;
+                Ok(name)
+            }
+            Err(_) => self.fail(start),
+        };
+    }
+
+    /// Reads the event's tag and name. Must be called before the first
+    /// [`MetaReader::read_field`].
+    pub fn read_event(&mut self) -> Result<(u32, &'a str), usize> {
+        let tag = self.read_tag()?;
+        let name = self.read_name()?;
+        return Ok((tag, name));
+    }
+
+    /// Reads the next field definition.
+    pub fn read_field(&mut self) -> Result<MetaField<'a>, usize> {
+        let name = self.read_name()?;
+        let in_byte = self.read_u8()?;
+        let mut out_byte = 0;
+        let mut tag = 0;
+        if (in_byte & 0x80) != 0 {
+            out_byte = self.read_u8()?;
+            if (out_byte & 0x80) != 0 {
+                tag = self.read_tag()?;
+            }
+        }
+
+        let flags = in_byte & InType::FlagMask;
+        let mut constant_count = 0;
+        let mut schema: &[u8] = &[];
+        if flags == InType::CustomFlag {
+            let schema_len = self.read_u16()? as usize;
+            let start = self.pos;
+            schema = match self.meta.get(start..start + schema_len) {
+                Some(schema) => schema,
+                None => return self.fail(start),
+            };
+            self.pos += schema_len;
+        } else if flags == InType::ConstantCountFlag {
+            constant_count = self.read_u16()?;
+        }
+
+        return Ok(MetaField {
+            name,
+            in_type: InType::from_int(in_byte & InType::TypeMask),
+            flags,
+            out_type: out_byte & OutType::TypeMask,
+            tag,
+            constant_count,
+            schema,
+        });
+    }
+
+    fn read_u8(&mut self) -> Result<u8, usize> {
+        return match self.meta.get(self.pos) {
+            Some(&value) => {
+                self.pos += 1;
+                Ok(value)
+            }
+            None => self.fail(self.pos),
+        };
+    }
+
+    fn read_u16(&mut self) -> Result<u16, usize> {
+        let lo = self.read_u8()?;
+        let hi = self.read_u8()?;
+        return Ok(u16::from_le_bytes([lo, hi]));
+    }
+
+    fn fail<T>(&mut self, offset: usize) -> Result<T, usize> {
+        self.pos = self.meta.len();
+        return Err(offset);
+    }
+}
+
+/// Returns the index after the `count` fields that start at `fields[index]`
+/// (including the members of structs), or `None` if the fields end too soon.
+pub fn skip_meta_fields(fields: &[MetaField], mut index: usize, count: u8) -> Option<usize> {
+    for _ in 0..count {
+        let field = fields.get(index)?;
+        index += 1;
+        if field.is_struct() {
+            index = skip_meta_fields(fields, index, field.out_type)?;
+        }
+    }
+    return Some(index);
+}
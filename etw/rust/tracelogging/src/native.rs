@@ -1,17 +1,57 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+use core::ffi::c_void;
 use core::marker::PhantomPinned;
+#[cfg(any(feature = "write_hook", all(windows, feature = "etw")))]
+use core::mem;
+#[cfg(any(feature = "write_hook", all(windows, feature = "etw")))]
+use core::ptr;
+#[cfg(any(feature = "write_hook", all(windows, feature = "etw")))]
+use core::sync::atomic::AtomicPtr;
+#[cfg(feature = "unregistered_writes")]
+use core::sync::atomic::AtomicU32;
+#[cfg(not(all(windows, feature = "etw")))]
+use core::sync::atomic::AtomicU64;
+#[cfg(feature = "write_retries")]
+use core::sync::atomic::AtomicU8;
+use core::sync::atomic::Ordering;
 
 #[cfg(all(windows, feature = "etw"))]
 use core::cell::UnsafeCell;
 #[cfg(all(windows, feature = "etw"))]
 use core::sync::atomic;
 
+#[cfg(all(windows, feature = "etw"))]
+use crate::adoption::Adopter;
+#[cfg(all(windows, feature = "etw"))]
+use crate::adoption::AdopterLink;
+#[cfg(all(windows, feature = "etw"))]
+use crate::adoption::AdoptionTable;
+#[cfg(all(windows, feature = "etw"))]
+use crate::adoption::ADOPTION_SLOTS;
 use crate::descriptors::EventDataDescriptor;
 use crate::descriptors::EventDescriptor;
+#[cfg(all(windows, feature = "etw"))]
+use crate::descriptors::EventFilterDescriptor;
+use crate::enable_state::EnableSnapshot;
+#[cfg(all(windows, feature = "etw"))]
+use crate::enable_state::EnableState;
+use crate::enable_state::ProviderEnableState;
+#[cfg(all(windows, feature = "etw"))]
+use crate::enums::EventControlCode;
 use crate::enums::Level;
+use crate::enums::WriteFlags;
 use crate::guid::Guid;
+#[cfg(feature = "keyword_levels")]
+use crate::keyword_levels::KeywordLevels;
+use crate::provider::RegisterError;
+#[cfg(feature = "stats")]
+use crate::provider_stats::ProviderStats;
+#[cfg(feature = "stats")]
+use crate::provider_stats::StatsCounters;
+use crate::session_info::ProviderEnabledInfo;
+use crate::state_source::StateSourceList;
 
 /// Possible configurations under which this crate can be compiled: `Windows` or `Other`.
 pub enum NativeImplementation {
@@ -36,8 +76,47 @@ else {
     NativeImplementation::Other
 };
 
+/// Error returned by EventWriteTransfer (ERROR_NOT_ENOUGH_MEMORY) or by
+/// EtwWriteTransfer (STATUS_NO_MEMORY) when the session's buffers are full.
+#[cfg(not(feature = "kernel_mode"))]
+pub(crate) const WRITE_BUFFERS_FULL: u32 = 8;
+#[cfg(feature = "kernel_mode")]
+pub(crate) const WRITE_BUFFERS_FULL: u32 = 0xC0000017;
+
+/// Error returned by EventSetInformation (ERROR_NOT_SUPPORTED) or by
+/// EtwSetInformation (STATUS_NOT_SUPPORTED) when the operating system does not
+/// support the requested information class, e.g. provider traits.
+pub const SET_INFORMATION_NOT_SUPPORTED: u32 = if cfg!(feature = "kernel_mode") {
+    0xC00000BB
+} else {
+    50
+};
+
+/// Number of spin_loop hints between write retries.
+#[cfg(feature = "write_retries")]
+const WRITE_RETRY_SPINS: u32 = 64;
+
+/// Errors returned by EventRegister (ERROR_BUSY, ERROR_NOT_ENOUGH_MEMORY) or by
+/// EtwRegister (STATUS_DEVICE_BUSY, STATUS_INSUFFICIENT_RESOURCES) that may not occur
+/// if the registration is retried.
+#[cfg(not(feature = "kernel_mode"))]
+const REGISTER_TRANSIENT_ERRORS: [u32; 2] = [170, 8];
+#[cfg(feature = "kernel_mode")]
+const REGISTER_TRANSIENT_ERRORS: [u32; 2] = [0x80000011, 0xC000009A];
+
+/// Number of spin_loop hints between register retries.
+const REGISTER_RETRY_SPINS: u32 = 4096;
+
 /// Signature for a custom
 /// [provider enable callback](https://docs.microsoft.com/windows/win32/api/evntprov/nc-evntprov-penablecallback).
+/// Use [`EventControlCode::from`](crate::EventControlCode) to interpret
+/// `event_control_code`.
+///
+/// Enable callbacks for a provider are serialized: the callback runs while the
+/// provider holds a lock that prevents other enable notifications from updating the
+/// provider's state. The callback must not unregister the provider and must not do
+/// anything that waits for another enable notification for the same provider (e.g.
+/// synchronously enabling the provider in a session), or it will deadlock.
 pub type ProviderEnableCallback = fn(
     source_id: &Guid,
     event_control_code: u32,
@@ -48,6 +127,20 @@ pub type ProviderEnableCallback = fn(
     callback_context: usize,
 );
 
+/// Signature for a callback that is invoked when the level or keywords with which ETW
+/// sessions have enabled a provider change. See
+/// [`Provider::on_enable_changed`](crate::Provider::on_enable_changed).
+///
+/// Same restrictions as [`ProviderEnableCallback`]: the callback must not unregister
+/// the provider or wait for another enable notification for the same provider.
+pub type EnableChangedCallback = fn(state: ProviderEnableState);
+
+/// Signature for a hook that is invoked before each event is written (requires
+/// feature `write_hook`). Receives the event's descriptor, the size of the event's
+/// metadata, and the total size of the event's data, in bytes.
+#[cfg(feature = "write_hook")]
+pub type PreWriteHook = fn(descriptor: &EventDescriptor, meta_len: usize, data_len: usize);
+
 #[cfg(all(windows, feature = "etw"))]
 type OuterEnableCallback = unsafe extern "system" fn(
     source_id: &Guid,
@@ -59,10 +152,47 @@ type OuterEnableCallback = unsafe extern "system" fn(
     outer_context: usize, // = &ProviderContextInner
 );
 
+/// The layout of the C/C++ `_tlgProvider_t` structure (the target of a
+/// `TraceLoggingHProvider`) from `TraceLoggingProvider.h`. The fields that the C/C++
+/// code updates while the provider is registered are accessed atomically.
+#[cfg(all(windows, feature = "etw"))]
+#[repr(C)]
+struct TlgProvider {
+    level_plus1: atomic::AtomicU32,
+    provider_metadata_ptr: *const u8, // Points at the RemainingSize field, after the GUID.
+    keyword_any: atomic::AtomicU64,
+    keyword_all: atomic::AtomicU64,
+    reg_handle: atomic::AtomicU64,
+    enable_callback: atomic::AtomicUsize, // 0 or OuterEnableCallback
+    callback_context: usize,
+}
+
+/// The adopted C/C++ providers. An adopted C/C++ provider's enable callback is
+/// replaced by its slot's `adopted_callback`, which forwards to each adopter and then
+/// to the original callback. The C/C++ provider's `callback_context` is never
+/// modified, and a slot is never reused for a different C/C++ provider, so the C/C++
+/// code never sees a callback paired with the wrong context.
+#[cfg(all(windows, feature = "etw"))]
+static ADOPTIONS: AdoptionTable<ProviderContextInner> = AdoptionTable::new();
+
 /// Data needed to manage an ETW registration with callback.
 pub struct ProviderContext {
     _pinned: PhantomPinned,
+    #[cfg(feature = "write_retries")]
+    write_retries: AtomicU8,
+    #[cfg(feature = "keyword_levels")]
+    keyword_levels: KeywordLevels,
+    #[cfg(feature = "write_hook")]
+    pre_write_hook: AtomicPtr<()>, // null or PreWriteHook
+    #[cfg(feature = "unregistered_writes")]
+    unregistered_writes: AtomicU32,
+    #[cfg(feature = "stats")]
+    stats: StatsCounters,
 
+    #[cfg(not(all(windows, feature = "etw")))]
+    state_sources: StateSourceList,
+    #[cfg(not(all(windows, feature = "etw")))]
+    injected_error: AtomicU64, // 0 or (step << 32) | error, see inject_register_error.
     #[cfg(all(windows, feature = "etw"))]
     cell: UnsafeCell<ProviderContextInner>,
 }
@@ -88,16 +218,157 @@ impl ProviderContext {
         return result;
     }
 
+    /// Windows: return QueryPerformanceCounter(...);
+    /// WindowsKernelMode: return KeQueryPerformanceCounter(NULL);
+    /// Other: return 0;
+    pub fn performance_counter() -> u64 {
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            result = 0;
+        }
+        #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
+        {
+            let mut counter = 0i64;
+            unsafe { QueryPerformanceCounter(&mut counter) };
+            result = counter as u64;
+        }
+        #[cfg(all(windows, feature = "etw", feature = "kernel_mode"))]
+        {
+            result = unsafe { KeQueryPerformanceCounter(ptr::null_mut()) } as u64;
+        }
+        return result;
+    }
+
+    /// Windows: return RtlCaptureStackBackTrace(depth + 1, 1, ...);
+    /// WindowsKernelMode: return RtlCaptureStackBackTrace(depth + 1, 1, ...);
+    /// Other: return 0;
+    ///
+    /// Returns a code address from the caller's stack: with `depth = 0`, the address
+    /// in the caller to which this function returns; with `depth = 1`, the address in
+    /// the caller's caller to which the caller returns; and so on. Returns 0 if the
+    /// stack is not that deep.
+    #[inline(never)]
+    pub fn caller_address(_depth: u32) -> usize {
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            result = 0;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            // Skip this function's frame.
+            let mut address = 0usize;
+            unsafe {
+                RtlCaptureStackBackTrace(_depth.saturating_add(1), 1, &mut address, ptr::null_mut())
+            };
+            result = address;
+        }
+        return result;
+    }
+
+    /// Windows: return EnumerateTraceGuidsEx(TraceGuidQueryInfo, ...) for this process;
+    /// WindowsKernelMode: return ERROR_NOT_SUPPORTED;
+    /// Other: return ERROR_NOT_SUPPORTED;
+    pub fn enabled_info(_provider_id: &Guid) -> Result<ProviderEnabledInfo, u32> {
+        let result;
+        #[cfg(not(all(windows, feature = "etw", not(feature = "kernel_mode"))))]
+        {
+            result = Err(50); // ERROR_NOT_SUPPORTED
+        }
+        #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
+        {
+            const TRACE_GUID_QUERY_INFO: u32 = 2;
+            const ERROR_INVALID_DATA: u32 = 13;
+            const ERROR_WMI_GUID_NOT_FOUND: u32 = 4200;
+
+            // 8 KB holds the provider's instances in about 30 processes, even if
+            // every instance is enabled by the maximum number of sessions.
+            let mut buffer = [0u64; 1024];
+            let mut return_length = 0u32;
+            let status = unsafe {
+                EnumerateTraceGuidsEx(
+                    TRACE_GUID_QUERY_INFO,
+                    _provider_id as *const Guid as *const c_void,
+                    mem::size_of::<Guid>() as u32,
+                    buffer.as_mut_ptr() as *mut c_void,
+                    mem::size_of_val(&buffer) as u32,
+                    &mut return_length,
+                )
+            };
+            result = match status {
+                0 => {
+                    let bytes = unsafe {
+                        core::slice::from_raw_parts(
+                            buffer.as_ptr() as *const u8,
+                            (return_length as usize).min(mem::size_of_val(&buffer)),
+                        )
+                    };
+                    let pid = unsafe { GetCurrentProcessId() };
+                    ProviderEnabledInfo::from_trace_guid_info(bytes, pid).ok_or(ERROR_INVALID_DATA)
+                }
+                ERROR_WMI_GUID_NOT_FOUND => Ok(ProviderEnabledInfo::EMPTY),
+                _ => Err(status),
+            };
+        }
+        return result;
+    }
+
     /// Creates a new provider context.
     pub const fn new() -> ProviderContext {
         return ProviderContext {
             _pinned: PhantomPinned,
+            #[cfg(feature = "write_retries")]
+            write_retries: AtomicU8::new(0),
+            #[cfg(feature = "keyword_levels")]
+            keyword_levels: KeywordLevels::new(),
+            #[cfg(feature = "write_hook")]
+            pre_write_hook: AtomicPtr::new(ptr::null_mut()),
+            #[cfg(feature = "unregistered_writes")]
+            unregistered_writes: AtomicU32::new(0),
+            #[cfg(feature = "stats")]
+            stats: StatsCounters::new(),
 
+            #[cfg(not(all(windows, feature = "etw")))]
+            state_sources: StateSourceList::new(),
+            #[cfg(not(all(windows, feature = "etw")))]
+            injected_error: AtomicU64::new(0),
             #[cfg(all(windows, feature = "etw"))]
             cell: UnsafeCell::new(ProviderContextInner::new()),
         };
     }
 
+    /// For tests: makes the next call to `register` (for `EventRegister`) or to
+    /// `set_information` with class 2 (for `SetTraits`) fail with the specified error.
+    /// `None` removes a pending injected error. Has no effect on Windows/etw.
+    pub fn inject_register_error(&self, _error: Option<RegisterError>) {
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            let value = match _error {
+                None => 0,
+                Some(RegisterError::EventRegister(error)) => (1u64 << 32) | error as u64,
+                Some(RegisterError::SetTraits(error)) => (2u64 << 32) | error as u64,
+            };
+            self.injected_error.store(value, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns and clears the injected error if it is for the specified step, or
+    /// returns 0.
+    #[cfg(not(all(windows, feature = "etw")))]
+    fn take_injected_error(&self, step: u64) -> u32 {
+        let value = self.injected_error.load(Ordering::Relaxed);
+        if value >> 32 != step
+            || self
+                .injected_error
+                .compare_exchange(value, 0, Ordering::Relaxed, Ordering::Relaxed)
+                .is_err()
+        {
+            return 0;
+        }
+        return value as u32;
+    }
+
     /// Returns the registration handle. For diagnostic purposes only.
     pub const fn reg_handle(&self) -> u64 {
         let result;
@@ -114,9 +385,28 @@ impl ProviderContext {
         return result;
     }
 
-    /// Returns true if the provider is enabled at the specified level and keyword.
+    /// Returns the handle for writing events: the adopted C/C++ provider's current
+    /// handle (0 once the C/C++ code unregisters it) or the provider's own handle.
+    #[cfg(all(windows, feature = "etw"))]
+    fn write_handle(&self) -> u64 {
+        let inner_ptr: *const ProviderContextInner = self.cell.get();
+        let inner = unsafe { &*inner_ptr };
+        let adopted = inner.adopted.load(Ordering::Relaxed);
+        return if adopted.is_null() {
+            inner.reg_handle
+        } else {
+            unsafe { &*adopted }.reg_handle.load(Ordering::Relaxed)
+        };
+    }
+
+    /// Returns true if the provider is enabled at the specified level and keyword and
+    /// (if feature `keyword_levels` is enabled) the level is allowed by the provider's
+    /// keyword levels.
+    ///
+    /// The level and keywords are read as a consistent snapshot, i.e. the result
+    /// never mixes the level from one enable callback with the keywords from another.
     #[inline(always)]
-    pub const fn enabled(&self, _level: Level, _keyword: u64) -> bool {
+    pub fn enabled(&self, _level: Level, _keyword: u64) -> bool {
         let result;
         #[cfg(not(all(windows, feature = "etw")))]
         {
@@ -126,12 +416,151 @@ impl ProviderContext {
         {
             let inner_ptr: *const ProviderContextInner = self.cell.get();
             let inner = unsafe { &*inner_ptr };
-            result = (_level.0 as i32) <= inner.level && inner.enabled_keyword(_keyword);
+            result = inner.enable.enabled(_level, _keyword)
+                && self.keyword_level_allows(_level, _keyword);
+        }
+        return result;
+    }
+
+    /// Returns true if the specified level is allowed by the provider's keyword
+    /// levels, i.e. if [`ProviderContext::keyword_level`] is `None` or is at least as
+    /// verbose as `level`. Does not check whether ETW has enabled the provider.
+    /// Always true if feature `keyword_levels` is not enabled.
+    #[inline(always)]
+    pub fn keyword_level_allows(&self, _level: Level, _keyword: u64) -> bool {
+        let result;
+        #[cfg(not(feature = "keyword_levels"))]
+        {
+            result = true;
+        }
+        #[cfg(feature = "keyword_levels")]
+        {
+            result = self.keyword_levels.allows(_level, _keyword);
+        }
+        return result;
+    }
+
+    /// Returns the most-verbose level allowed for events with the specified keyword,
+    /// or `None` if such events are not restricted by keyword levels.
+    #[cfg(feature = "keyword_levels")]
+    pub fn keyword_level(&self, keyword: u64) -> Option<Level> {
+        return self.keyword_levels.get(keyword);
+    }
+
+    /// Sets (or with `None`, removes) the most-verbose level allowed for events with
+    /// any of the bits in `keywords`.
+    #[cfg(feature = "keyword_levels")]
+    pub fn set_keyword_level(&self, keywords: u64, level: Option<Level>) {
+        self.keyword_levels.set(keywords, level);
+    }
+
+    /// Sets (or with `None`, removes) the most-verbose level allowed for events whose
+    /// keyword has no bits with a keyword level.
+    #[cfg(feature = "keyword_levels")]
+    pub fn set_default_keyword_level(&self, level: Option<Level>) {
+        self.keyword_levels.set_default(level);
+    }
+
+    /// Removes all keyword levels.
+    #[cfg(feature = "keyword_levels")]
+    pub fn clear_keyword_levels(&self) {
+        self.keyword_levels.clear();
+    }
+
+    /// Returns the number of times the provider's enabled level or keywords have
+    /// changed (wraps on overflow). Always 0 if not Windows/etw.
+    pub fn enable_generation(&self) -> u32 {
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            result = 0;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let inner_ptr: *const ProviderContextInner = self.cell.get();
+            let inner = unsafe { &*inner_ptr };
+            result = inner.enable.generation();
+        }
+        return result;
+    }
+
+    /// Returns a consistent snapshot of the provider's enabled level and keywords.
+    /// Always [`EnableSnapshot::DISABLED`] if not Windows/etw.
+    pub fn enable_snapshot(&self) -> EnableSnapshot {
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            result = EnableSnapshot::DISABLED;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let inner_ptr: *const ProviderContextInner = self.cell.get();
+            let inner = unsafe { &*inner_ptr };
+            result = inner.enable.snapshot();
+        }
+        return result;
+    }
+
+    /// Returns the type of the filter that was passed to the most recent enable
+    /// callback, or 0 if it had no filter or the provider is disabled. Always 0 if not
+    /// Windows/etw.
+    pub fn enable_filter_type(&self) -> u32 {
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            result = 0;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let inner_ptr: *const ProviderContextInner = self.cell.get();
+            let inner = unsafe { &*inner_ptr };
+            result = inner.filter_type.load(Ordering::Relaxed);
+        }
+        return result;
+    }
+
+    /// Returns the provider's current enable state.
+    /// Always [`ProviderEnableState::DISABLED`] if not Windows/etw.
+    pub fn enable_state(&self) -> ProviderEnableState {
+        return ProviderEnableState::from_snapshot(&self.enable_snapshot());
+    }
+
+    /// Sets (or with `None`, removes) the callback to be invoked after each enable
+    /// callback that changes the provider's enable state. Has no effect if not
+    /// Windows/etw.
+    pub fn set_enable_changed_callback(&self, _callback: Option<EnableChangedCallback>) {
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let callback_ptr = match _callback {
+                None => ptr::null_mut(),
+                Some(callback) => callback as *mut (),
+            };
+            let inner_ptr: *const ProviderContextInner = self.cell.get();
+            let inner = unsafe { &*inner_ptr };
+            inner.enable_changed.store(callback_ptr, Ordering::Release);
+        }
+    }
+
+    /// Returns the provider's state sources. On Windows/etw, the sources are invoked
+    /// when ETW sends a capture-state request.
+    pub fn state_sources(&self) -> &StateSourceList {
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            result = &self.state_sources;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let inner_ptr: *const ProviderContextInner = self.cell.get();
+            let inner = unsafe { &*inner_ptr };
+            result = &inner.state_sources;
         }
         return result;
     }
 
     /// Calls EventUnregister (EtwUnregister for kernel_mode) and sets reg_handle = 0.
+    /// If the registration was adopted, unchains from the `TraceLoggingHProvider`'s
+    /// enable callback instead of calling EventUnregister.
     ///
     /// # Preconditions
     /// - This will panic if it overlaps with another thread simultaneously calling
@@ -151,7 +580,8 @@ impl ProviderContext {
         return result;
     }
 
-    /// Calls EventRegister (EtwRegister for kernel_mode).
+    /// Calls EventRegister (EtwRegister for kernel_mode). If EventRegister fails, the
+    /// context is left unregistered (reg_handle = 0, no callback).
     ///
     /// # Preconditions
     /// - This will panic if provider is currently registered.
@@ -171,7 +601,7 @@ impl ProviderContext {
         let result;
         #[cfg(not(all(windows, feature = "etw")))]
         {
-            result = 0;
+            result = self.take_injected_error(1);
         }
         #[cfg(all(windows, feature = "etw"))]
         {
@@ -183,12 +613,87 @@ impl ProviderContext {
         return result;
     }
 
+    /// Same as [`ProviderContext::register`], but if EventRegister fails with a
+    /// transient error (e.g. ERROR_BUSY), spins briefly and retries up to `retries`
+    /// times.
+    ///
+    /// # Preconditions
+    /// - Same as [`ProviderContext::register`].
+    ///
+    /// # Safety
+    /// - Same as [`ProviderContext::register`].
+    pub unsafe fn register_with_retries(
+        &self,
+        provider_id: &Guid,
+        callback_fn: Option<ProviderEnableCallback>,
+        callback_context: usize,
+        retries: u8,
+    ) -> u32 {
+        let mut result = self.register(provider_id, callback_fn, callback_context);
+        let mut retries = retries;
+        while retries != 0 && REGISTER_TRANSIENT_ERRORS.contains(&result) {
+            for _ in 0..REGISTER_RETRY_SPINS {
+                core::hint::spin_loop();
+            }
+            result = self.register(provider_id, callback_fn, callback_context);
+            retries -= 1;
+        }
+        return result;
+    }
+
+    /// Uses the registration of a C/C++ `TraceLoggingHProvider` instead of calling
+    /// EventRegister: writes with its reg_handle and chains into its enable callback.
+    /// Returns ERROR_INVALID_PARAMETER if `cpp_provider` is null or has a different
+    /// provider id, ERROR_INVALID_HANDLE if `cpp_provider` is not registered,
+    /// ERROR_NO_SYSTEM_RESOURCES if too many C/C++ providers have been adopted or too
+    /// many contexts have adopted `cpp_provider`.
+    ///
+    /// # Preconditions
+    /// - This will panic if provider is currently registered.
+    /// - This will panic if it overlaps with another thread simultaneously calling
+    ///   register or unregister.
+    ///
+    /// # Safety
+    /// 1. Pinning: Context must not be moved-from as long as provider is registered.
+    /// 2. `cpp_provider` must be null or point at a `_tlgProvider_t` that stays valid
+    ///    (is not freed) until this context is unregistered.
+    pub unsafe fn adopt(
+        &self,
+        _provider_id: &Guid,
+        cpp_provider: *mut c_void,
+        _callback_fn: Option<ProviderEnableCallback>,
+        _callback_context: usize,
+    ) -> u32 {
+        let result;
+        if cpp_provider.is_null() {
+            result = 87; // ERROR_INVALID_PARAMETER
+        } else {
+            #[cfg(not(all(windows, feature = "etw")))]
+            {
+                result = 0;
+            }
+            #[cfg(all(windows, feature = "etw"))]
+            {
+                result = /* unsafe */ { &mut *self.cell.get() }.adopt(
+                    _provider_id,
+                    cpp_provider as *const TlgProvider,
+                    _callback_fn,
+                    _callback_context);
+            }
+        }
+        return result;
+    }
+
     /// Calls EventSetInformation (EtwSetInformation for kernel_mode).
     pub fn set_information(&self, _information_class: u32, _information: &[u8]) -> u32 {
         let result;
         #[cfg(not(all(windows, feature = "etw")))]
         {
-            result = 0;
+            result = if _information_class == 2 {
+                self.take_injected_error(2)
+            } else {
+                0
+            };
         }
         #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
         {
@@ -215,10 +720,173 @@ impl ProviderContext {
         return result;
     }
 
+    /// The maximum value accepted by [`ProviderContext::set_write_retries`].
+    #[cfg(feature = "write_retries")]
+    pub const MAX_WRITE_RETRIES: u8 = 3;
+
+    /// Returns the number of times a write will be retried after a transient
+    /// buffer-full failure.
+    #[cfg(feature = "write_retries")]
+    pub fn write_retries(&self) -> u8 {
+        return self.write_retries.load(Ordering::Relaxed);
+    }
+
+    /// Sets the number of times a write will be retried after a transient
+    /// buffer-full failure (clamped to [`ProviderContext::MAX_WRITE_RETRIES`]).
+    #[cfg(feature = "write_retries")]
+    pub fn set_write_retries(&self, retries: u8) {
+        self.write_retries
+            .store(retries.min(Self::MAX_WRITE_RETRIES), Ordering::Relaxed);
+    }
+
+    /// Sets (or with `None`, removes) the hook to be invoked by
+    /// [`ProviderContext::write_transfer`] before each write.
+    #[cfg(feature = "write_hook")]
+    pub fn set_pre_write_hook(&self, hook: Option<PreWriteHook>) {
+        let hook_ptr = match hook {
+            None => ptr::null_mut(),
+            Some(hook) => hook as *mut (),
+        };
+        self.pre_write_hook.store(hook_ptr, Ordering::Release);
+    }
+
+    /// Returns the hook set by [`ProviderContext::set_pre_write_hook`], if any.
+    #[cfg(feature = "write_hook")]
+    pub fn pre_write_hook(&self) -> Option<PreWriteHook> {
+        let hook_ptr = self.pre_write_hook.load(Ordering::Acquire);
+        return if hook_ptr.is_null() {
+            None
+        } else {
+            // Safety: non-null values are only stored by set_pre_write_hook.
+            Some(unsafe { mem::transmute::<*mut (), PreWriteHook>(hook_ptr) })
+        };
+    }
+
+    /// Notes an attempt to write an event. If the provider is not registered, counts
+    /// the write (requires feature `unregistered_writes`) and, if feature
+    /// `unregistered_writes_panic` is enabled, fails a debug assertion (ETW builds
+    /// only). Otherwise does nothing.
+    #[inline(always)]
+    pub fn note_write(&self) {
+        #[cfg(feature = "unregistered_writes")]
+        if self.reg_handle() == 0 {
+            self.count_unregistered_write();
+        }
+    }
+
+    #[cfg(feature = "unregistered_writes")]
+    #[cold]
+    #[inline(never)]
+    fn count_unregistered_write(&self) {
+        self.unregistered_writes.fetch_add(1, Ordering::Relaxed);
+        #[cfg(all(windows, feature = "etw", feature = "unregistered_writes_panic"))]
+        debug_assert!(false, "event written to a provider that is not registered");
+    }
+
+    /// Returns the number of writes counted by [`ProviderContext::note_write`].
+    #[cfg(feature = "unregistered_writes")]
+    pub fn unregistered_writes(&self) -> u32 {
+        return self.unregistered_writes.load(Ordering::Relaxed);
+    }
+
+    /// Counts an event that was skipped because the provider was not enabled or
+    /// because the event was rejected by a filter (requires feature `stats`).
+    /// Otherwise does nothing.
+    #[inline(always)]
+    pub fn note_filtered(&self) {
+        #[cfg(feature = "stats")]
+        self.stats.note_filtered();
+    }
+
+    /// Returns the counts collected by [`ProviderContext::note_filtered`] and
+    /// [`ProviderContext::write_transfer`].
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> ProviderStats {
+        return self.stats.snapshot();
+    }
+
     /// Calls EventWriteTransfer (EtwWriteTransfer for kernel_mode).
+    ///
+    /// With feature `write_retries`, if the write fails because a session's buffers
+    /// are full, spins briefly and retries up to `write_retries` times.
+    ///
+    /// With feature `stats`, counts the result as written or failed.
+    ///
+    /// With feature `write_hook`, first invokes the pre-write hook (if any). Data
+    /// descriptors with type 1 (event metadata) are counted as metadata, descriptors
+    /// with type 0 (none) are counted as data, and other descriptors are not counted.
+    #[inline]
     pub fn write_transfer(
+        &self,
+        descriptor: &EventDescriptor,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        data: &[EventDataDescriptor],
+    ) -> u32 {
+        return self.write_ex(
+            descriptor,
+            0,
+            WriteFlags::None,
+            activity_id,
+            related_id,
+            data,
+        );
+    }
+
+    /// Same as [`ProviderContext::write_transfer`], but calls EventWriteEx (EtwWriteEx
+    /// for kernel_mode) if `filter` or `flags` is nonzero.
+    ///
+    /// `filter` is a bitmask of the ids of sessions that should not receive the event
+    /// (see the `FilterData` parameter of the enable callback). `flags` is a
+    /// combination of `EVENT_WRITE_FLAG` values.
+    pub fn write_ex(
+        &self,
+        descriptor: &EventDescriptor,
+        filter: u64,
+        flags: WriteFlags,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        data: &[EventDataDescriptor],
+    ) -> u32 {
+        #[cfg(feature = "write_hook")]
+        if let Some(hook) = self.pre_write_hook() {
+            let mut meta_len = 0;
+            let mut data_len = 0;
+            for dd in data {
+                match dd.reserved() {
+                    0 => data_len += dd.size() as usize, // EVENT_DATA_DESCRIPTOR_TYPE_NONE
+                    1 => meta_len += dd.size() as usize, // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
+                    _ => {}
+                }
+            }
+            hook(descriptor, meta_len, data_len);
+        }
+
+        let flags = flags.as_int();
+        #[allow(unused_mut)]
+        let mut result = self.write_once(descriptor, filter, flags, activity_id, related_id, data);
+        #[cfg(feature = "write_retries")]
+        if result == WRITE_BUFFERS_FULL {
+            let mut retries = self.write_retries.load(Ordering::Relaxed);
+            while retries != 0 && result == WRITE_BUFFERS_FULL {
+                for _ in 0..WRITE_RETRY_SPINS {
+                    core::hint::spin_loop();
+                }
+                result = self.write_once(descriptor, filter, flags, activity_id, related_id, data);
+                retries -= 1;
+            }
+        }
+
+        #[cfg(feature = "stats")]
+        self.stats.note_result(result);
+        return result;
+    }
+
+    fn write_once(
         &self,
         _descriptor: &EventDescriptor,
+        _filter: u64,
+        _flags: u32,
         _activity_id: Option<&[u8; 16]>,
         _related_id: Option<&[u8; 16]>,
         _data: &[EventDataDescriptor],
@@ -231,27 +899,53 @@ impl ProviderContext {
         #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
         {
             result = unsafe {
-                EventWriteTransfer(
-                    self.reg_handle(),
-                    _descriptor,
-                    _activity_id,
-                    _related_id,
-                    _data.len() as u32,
-                    _data.as_ptr(),
-                )
+                if _filter == 0 && _flags == 0 {
+                    EventWriteTransfer(
+                        self.write_handle(),
+                        _descriptor,
+                        _activity_id,
+                        _related_id,
+                        _data.len() as u32,
+                        _data.as_ptr(),
+                    )
+                } else {
+                    EventWriteEx(
+                        self.write_handle(),
+                        _descriptor,
+                        _filter,
+                        _flags,
+                        _activity_id,
+                        _related_id,
+                        _data.len() as u32,
+                        _data.as_ptr(),
+                    )
+                }
             };
         }
         #[cfg(all(windows, feature = "etw", feature = "kernel_mode"))]
         {
             result = unsafe {
-                EtwWriteTransfer(
-                    self.reg_handle(),
-                    _descriptor,
-                    _activity_id,
-                    _related_id,
-                    _data.len() as u32,
-                    _data.as_ptr(),
-                )
+                if _filter == 0 && _flags == 0 {
+                    EtwWriteTransfer(
+                        self.write_handle(),
+                        _descriptor,
+                        _activity_id,
+                        _related_id,
+                        _data.len() as u32,
+                        _data.as_ptr(),
+                    )
+                } else {
+                    EtwWriteEx(
+                        self.write_handle(),
+                        _descriptor,
+                        _filter,
+                        _flags,
+                        _activity_id,
+                        _related_id,
+                        _data.len() as u32,
+                        _data.as_ptr(),
+                    )
+                }
             };
         }
         return result;
@@ -275,36 +969,37 @@ impl Drop for ProviderContext {
 
 #[cfg(all(windows, feature = "etw"))]
 struct ProviderContextInner {
-    level: i32, // -1 means not enabled by anybody.
+    enable: EnableState,
     busy: atomic::AtomicBool,
     reg_handle: u64,
-    keyword_any: u64,
-    keyword_all: u64,
     callback_fn: Option<ProviderEnableCallback>,
     callback_context: usize,
+    enable_changed: AtomicPtr<()>,      // null or EnableChangedCallback
+    enable_notified: atomic::AtomicU32, // Generation last reported to enable_changed.
+    filter_type: atomic::AtomicU32,     // Filter type from the last enable callback.
+    state_sources: StateSourceList,
+    adopted: AtomicPtr<TlgProvider>, // null or the adopted C/C++ provider.
+    adopter_link: AdopterLink,
 }
 
 #[cfg(all(windows, feature = "etw"))]
 impl ProviderContextInner {
     const fn new() -> Self {
         return Self {
-            level: -1,
+            enable: EnableState::new(),
             busy: atomic::AtomicBool::new(false),
             reg_handle: 0,
-            keyword_any: 0,
-            keyword_all: 0,
             callback_fn: None,
             callback_context: 0,
+            enable_changed: AtomicPtr::new(ptr::null_mut()),
+            enable_notified: atomic::AtomicU32::new(0),
+            filter_type: atomic::AtomicU32::new(0),
+            state_sources: StateSourceList::new(),
+            adopted: AtomicPtr::new(ptr::null_mut()),
+            adopter_link: AdopterLink::new(),
         };
     }
 
-    /// Returns true if the provider is enabled at the specified keyword.
-    const fn enabled_keyword(&self, keyword: u64) -> bool {
-        return keyword == 0
-            || ((keyword & self.keyword_any) != 0
-                && (keyword & self.keyword_all) == self.keyword_all);
-    }
-
     fn unregister(&mut self) -> u32 {
         let result;
 
@@ -315,15 +1010,22 @@ impl ProviderContextInner {
             if self.reg_handle == 0 {
                 result = 0;
             } else {
-                #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
-                {
-                    result = unsafe { EventUnregister(self.reg_handle) };
+                if !self.adopted.load(Ordering::Relaxed).is_null() {
+                    result = 0;
+                    self.unadopt();
+                } else {
+                    #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
+                    {
+                        result = unsafe { EventUnregister(self.reg_handle) };
+                    }
+                    #[cfg(all(windows, feature = "etw", feature = "kernel_mode"))]
+                    {
+                        result = unsafe { EtwUnregister(self.reg_handle) };
+                    }
                 }
-                #[cfg(all(windows, feature = "etw", feature = "kernel_mode"))]
-                {
-                    result = unsafe { EtwUnregister(self.reg_handle) };
-                }
-                self.level = -1;
+                self.enable
+                    .update(0, 0, 0, 0, || self.notify_enable_changed());
+                self.filter_type.store(0, Ordering::Relaxed);
                 self.reg_handle = 0;
             }
 
@@ -372,14 +1074,139 @@ impl ProviderContextInner {
             )
         };
 
+        if result != 0 {
+            // Leave the context in the same state as before the call.
+            self.reg_handle = 0;
+            self.callback_fn = None;
+            self.callback_context = 0;
+        }
+
         self.busy.swap(false, atomic::Ordering::Release);
 
         return result;
     }
 
-    #[cfg(all(windows, feature = "etw"))]
-    fn outer_callback_impl(
+    fn adopt(
         &mut self,
+        provider_id: &Guid,
+        cpp_provider: *const TlgProvider,
+        callback_fn: Option<ProviderEnableCallback>,
+        callback_context: usize,
+    ) -> u32 {
+        let was_busy = self.busy.swap(true, atomic::Ordering::Acquire);
+        if was_busy {
+            panic!("provider.register called simultaneously with another call to register or unregister.");
+        }
+
+        if self.reg_handle != 0 {
+            self.busy.swap(false, atomic::Ordering::Relaxed);
+            panic!("provider.register called when provider is already registered");
+        }
+
+        let result;
+        let cpp = unsafe { &*cpp_provider };
+        let reg_handle = cpp.reg_handle.load(Ordering::Relaxed);
+        if reg_handle == 0 || cpp.provider_metadata_ptr.is_null() {
+            result = 6; // ERROR_INVALID_HANDLE
+        } else if *provider_id
+            != unsafe { ptr::read_unaligned(cpp.provider_metadata_ptr.sub(16) as *const Guid) }
+        {
+            result = 87; // ERROR_INVALID_PARAMETER
+        } else {
+            result = match ADOPTIONS.bind(cpp_provider as usize) {
+                None => 1450, // ERROR_NO_SYSTEM_RESOURCES
+                Some(slot) => self.adopt_slot(
+                    provider_id,
+                    cpp,
+                    slot,
+                    reg_handle,
+                    callback_fn,
+                    callback_context,
+                ),
+            };
+        }
+
+        self.busy.swap(false, atomic::Ordering::Release);
+
+        return result;
+    }
+
+    /// Adds this context to the adopters of the C/C++ provider bound to `slot`, then
+    /// delivers the C/C++ provider's current state (from sessions that enabled it
+    /// before it was adopted). Both are done while holding the slot's dispatch lock so
+    /// that the current state cannot overwrite the state from a newer enable callback.
+    /// Returns ERROR_NO_SYSTEM_RESOURCES if the slot has too many adopters.
+    fn adopt_slot(
+        &mut self,
+        provider_id: &Guid,
+        cpp: &TlgProvider,
+        slot: usize,
+        reg_handle: u64,
+        callback_fn: Option<ProviderEnableCallback>,
+        callback_context: usize,
+    ) -> u32 {
+        let _dispatch = ADOPTIONS.lock_dispatch(slot);
+
+        self.callback_fn = callback_fn;
+        self.callback_context = callback_context;
+        let added = ADOPTIONS.add(slot, self, || {
+            let chained = cpp.enable_callback.load(Ordering::Acquire);
+            cpp.enable_callback
+                .store(Self::ADOPTED_CALLBACKS[slot] as usize, Ordering::Release);
+            chained
+        });
+        if !added {
+            self.callback_fn = None;
+            self.callback_context = 0;
+            return 1450; // ERROR_NO_SYSTEM_RESOURCES
+        }
+
+        self.reg_handle = reg_handle;
+        self.adopted
+            .store(cpp as *const TlgProvider as *mut TlgProvider, Ordering::Relaxed);
+
+        let level_plus1 = cpp.level_plus1.load(Ordering::Relaxed);
+        if level_plus1 != 0 {
+            self.outer_callback_impl(
+                provider_id,
+                1, // EVENT_CONTROL_CODE_ENABLE_PROVIDER
+                (level_plus1 - 1) as u8,
+                cpp.keyword_any.load(Ordering::Relaxed),
+                cpp.keyword_all.load(Ordering::Relaxed),
+                0,
+            );
+        }
+
+        return 0;
+    }
+
+    /// Removes this context from the adopters of its C/C++ provider and waits for
+    /// enable callbacks that are using this context to return. If this was the last
+    /// adopter, restores the C/C++ provider's original enable callback (unless the
+    /// C/C++ code has since replaced it, e.g. by registering again).
+    fn unadopt(&mut self) {
+        let cpp_provider: *const TlgProvider = self.adopted.load(Ordering::Relaxed);
+
+        if let Some(slot) = ADOPTIONS.find(cpp_provider as usize) {
+            ADOPTIONS.remove(slot, self, |chained| {
+                let cpp = unsafe { &*cpp_provider };
+                let _ = cpp.enable_callback.compare_exchange(
+                    Self::ADOPTED_CALLBACKS[slot] as usize,
+                    chained,
+                    Ordering::Release,
+                    Ordering::Relaxed,
+                );
+            });
+        }
+
+        self.adopted.store(ptr::null_mut(), Ordering::Relaxed);
+    }
+
+    /// Updates the enable state, then invokes the user's callbacks (if any). Enable
+    /// callbacks are serialized: the user's callback will not be invoked on one
+    /// thread while another thread's enable callback is in progress.
+    fn outer_callback_impl(
+        &self,
         source_id: &Guid,
         event_control_code: u32,
         level: u8,
@@ -387,28 +1214,62 @@ impl ProviderContextInner {
         match_all_keyword: u64,
         filter_data: usize,
     ) {
-        match event_control_code {
-            0 => {
-                self.level = -1;
-            }
-            1 => {
-                self.level = level as i32;
-                self.keyword_any = match_any_keyword;
-                self.keyword_all = match_all_keyword;
+        self.enable.update(
+            event_control_code,
+            level,
+            match_any_keyword,
+            match_all_keyword,
+            || {
+                let control_code = EventControlCode::from(event_control_code);
+                if control_code == EventControlCode::EnableProvider {
+                    let filter = unsafe { EventFilterDescriptor::from_filter_data(filter_data) };
+                    self.filter_type
+                        .store(filter.map_or(0, |f| f.filter_type), Ordering::Relaxed);
+                } else if control_code == EventControlCode::DisableProvider
+                    && self.enable.snapshot().level < 0
+                {
+                    self.filter_type.store(0, Ordering::Relaxed);
+                }
+
+                if let Some(callback_fn) = self.callback_fn {
+                    callback_fn(
+                        source_id,
+                        event_control_code,
+                        Level(level),
+                        match_any_keyword,
+                        match_all_keyword,
+                        filter_data,
+                        self.callback_context,
+                    );
+                }
+
+                self.notify_enable_changed();
+
+                if control_code == EventControlCode::CaptureState {
+                    for source in self.state_sources.iter() {
+                        source.write_state();
+                    }
+                }
+            },
+        );
+    }
+
+    /// Invokes the enable_changed callback (if any) if the enable state has changed
+    /// since the last notification. Must only be called while enable updates are
+    /// serialized, i.e. from the `then` parameter of `EnableState::update`.
+    fn notify_enable_changed(&self) {
+        let snapshot = self.enable.snapshot();
+        let notified = self
+            .enable_notified
+            .swap(snapshot.generation, atomic::Ordering::Relaxed);
+        if notified != snapshot.generation {
+            let callback_ptr = self.enable_changed.load(Ordering::Acquire);
+            if !callback_ptr.is_null() {
+                // Safety: non-null values are only stored by set_enable_changed_callback.
+                let callback =
+                    unsafe { mem::transmute::<*mut (), EnableChangedCallback>(callback_ptr) };
+                callback(ProviderEnableState::from_snapshot(&snapshot));
             }
-            _ => {}
-        }
-
-        if let Some(callback_fn) = self.callback_fn {
-            callback_fn(
-                source_id,
-                event_control_code,
-                Level(level),
-                match_any_keyword,
-                match_all_keyword,
-                filter_data,
-                self.callback_context,
-            );
         }
     }
 
@@ -422,7 +1283,7 @@ impl ProviderContextInner {
         filter_data: usize,
         outer_context: usize,
     ) {
-        (*(outer_context as *mut Self)).outer_callback_impl(
+        (*(outer_context as *const Self)).outer_callback_impl(
             source_id,
             event_control_code,
             level,
@@ -431,6 +1292,70 @@ impl ProviderContextInner {
             filter_data,
         );
     }
+
+    /// The enable callback of each adoption slot.
+    const ADOPTED_CALLBACKS: [OuterEnableCallback; ADOPTION_SLOTS] = [
+        Self::adopted_callback::<0>,
+        Self::adopted_callback::<1>,
+        Self::adopted_callback::<2>,
+        Self::adopted_callback::<3>,
+        Self::adopted_callback::<4>,
+        Self::adopted_callback::<5>,
+        Self::adopted_callback::<6>,
+        Self::adopted_callback::<7>,
+    ];
+
+    /// Implements the enable callback of the C/C++ provider adopted in slot `SLOT`:
+    /// handles the callback for each adopter, then invokes the callback that was set
+    /// before the provider was adopted (if any). `outer_context` is the C/C++
+    /// provider's own callback context. The slot is never rebound, so this may run
+    /// after the last adopter is removed (if ETW read the callback before it was
+    /// restored) without reaching a different C/C++ provider.
+    unsafe extern "system" fn adopted_callback<const SLOT: usize>(
+        source_id: &Guid,
+        event_control_code: u32,
+        level: u8,
+        match_any_keyword: u64,
+        match_all_keyword: u64,
+        filter_data: usize,
+        outer_context: usize,
+    ) {
+        ADOPTIONS.dispatch(
+            SLOT,
+            |adopter| {
+                adopter.outer_callback_impl(
+                    source_id,
+                    event_control_code,
+                    level,
+                    match_any_keyword,
+                    match_all_keyword,
+                    filter_data,
+                );
+            },
+            |chained_callback| {
+                if chained_callback != 0 {
+                    let chained_callback =
+                        mem::transmute::<usize, OuterEnableCallback>(chained_callback);
+                    chained_callback(
+                        source_id,
+                        event_control_code,
+                        level,
+                        match_any_keyword,
+                        match_all_keyword,
+                        filter_data,
+                        outer_context,
+                    );
+                }
+            },
+        );
+    }
+}
+
+#[cfg(all(windows, feature = "etw"))]
+impl Adopter for ProviderContextInner {
+    fn adopter_link(&self) -> &AdopterLink {
+        return &self.adopter_link;
+    }
 }
 
 #[cfg(all(windows, feature = "etw", not(feature = "kernel_mode")))]
@@ -456,7 +1381,33 @@ extern "system" {
         data_count: u32,
         data: *const EventDataDescriptor,
     ) -> u32;
+    fn EventWriteEx(
+        reg_handle: u64,
+        descriptor: &EventDescriptor,
+        filter: u64,
+        flags: u32,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        data_count: u32,
+        data: *const EventDataDescriptor,
+    ) -> u32;
     fn EventActivityIdControl(control_code: u32, activity_id: &mut Guid) -> u32;
+    fn EnumerateTraceGuidsEx(
+        trace_query_info_class: u32,
+        in_buffer: *const c_void,
+        in_buffer_size: u32,
+        out_buffer: *mut c_void,
+        out_buffer_size: u32,
+        return_length: &mut u32,
+    ) -> u32;
+    fn GetCurrentProcessId() -> u32;
+    fn QueryPerformanceCounter(performance_count: &mut i64) -> i32;
+    fn RtlCaptureStackBackTrace(
+        frames_to_skip: u32,
+        frames_to_capture: u32,
+        back_trace: &mut usize,
+        back_trace_hash: *mut u32,
+    ) -> u16;
 }
 
 #[cfg(all(windows, feature = "etw", feature = "kernel_mode"))]
@@ -482,5 +1433,22 @@ extern "system" {
         data_count: u32,
         data: *const EventDataDescriptor,
     ) -> u32;
+    fn EtwWriteEx(
+        reg_handle: u64,
+        descriptor: &EventDescriptor,
+        filter: u64,
+        flags: u32,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        data_count: u32,
+        data: *const EventDataDescriptor,
+    ) -> u32;
     fn EtwActivityIdControl(control_code: u32, activity_id: &mut Guid) -> u32;
+    fn KeQueryPerformanceCounter(performance_frequency: *mut i64) -> i64;
+    fn RtlCaptureStackBackTrace(
+        frames_to_skip: u32,
+        frames_to_capture: u32,
+        back_trace: &mut usize,
+        back_trace_hash: *mut u32,
+    ) -> u16;
 }
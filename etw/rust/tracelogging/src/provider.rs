@@ -1,15 +1,33 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+use core::ffi::c_void;
 use core::fmt;
+use core::mem;
+use core::ptr;
 use core::str::from_utf8;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering;
 
 use crate::descriptors::EventDataDescriptor;
 use crate::descriptors::EventDescriptor;
+use crate::descriptors::EventFilterDescriptor;
+use crate::enable_state::ProviderEnableState;
 use crate::enums::Level;
 use crate::guid::Guid;
+use crate::native::EnableChangedCallback;
+#[cfg(feature = "write_hook")]
+use crate::native::PreWriteHook;
 use crate::native::ProviderContext;
 use crate::native::ProviderEnableCallback;
+use crate::native::SET_INFORMATION_NOT_SUPPORTED;
+use crate::native::WRITE_BUFFERS_FULL;
+use crate::provider_meta::ProviderMetadata;
+#[cfg(feature = "stats")]
+use crate::provider_stats::ProviderStats;
+use crate::session_info::ProviderEnabledInfo;
+use crate::state_source::StateSource;
+use crate::state_source::StateSources;
 
 #[allow(unused_imports)] // For docs
 #[cfg(feature = "macros")]
@@ -19,6 +37,10 @@ use crate::define_provider;
 #[cfg(feature = "macros")]
 use crate::write_event;
 
+/// Signature for a function that decides whether an event should be written. Return
+/// true to write the event, false to skip it. See [`Provider::set_event_filter`].
+pub type EventFilter = fn(descriptor: &EventDescriptor) -> bool;
+
 /// A connection to ETW for writing TraceLogging (manifest-free) events.
 ///
 /// # Overview
@@ -33,9 +55,27 @@ pub struct Provider {
     context: ProviderContext,
     meta: &'static [u8], // provider metadata
     id: Guid,
+    event_filter: AtomicPtr<()>, // null or EventFilter
 }
 
 impl Provider {
+    /// The keyword used by [`write_state_event!`](crate::write_state_event) for state
+    /// (rundown) events: `0x0000_8000_0000_0000`, the highest user-defined keyword bit.
+    ///
+    /// To collect state events, a session should enable the provider with this keyword
+    /// and then send a capture-state request. Providers that use
+    /// [`write_state_event!`](crate::write_state_event) should not use this bit for
+    /// other purposes.
+    pub const STATE_KEYWORD: u64 = 0x0000_8000_0000_0000;
+
+    /// The keyword used by [`mark!`](crate::mark) for marker events:
+    /// `0x0000_4000_0000_0000`, the second-highest user-defined keyword bit.
+    ///
+    /// To collect only marker events, a session should enable the provider with this
+    /// keyword. Providers that use [`mark!`](crate::mark) should not use this bit for
+    /// other purposes.
+    pub const MARKER_KEYWORD: u64 = 0x0000_4000_0000_0000;
+
     /// Returns the current thread's thread-local activity id.
     /// (Calls
     /// [EventActivityIdControl](https://docs.microsoft.com/windows/win32/api/evntprov/nf-evntprov-eventactivityidcontrol)
@@ -113,11 +153,43 @@ impl Provider {
         return Guid::from_name(name);
     }
 
+    /// Returns a nonzero 16-bit event id generated from a hash of the specified event
+    /// name. Given the same name, it will always generate the same id. This is the id
+    /// used by [`write_event!`] for events that specify the `auto_id(hash)` option.
+    ///
+    /// The hash is the 32-bit FNV-1a hash of the name's UTF-8 bytes, folded to 16 bits
+    /// by XOR-ing the high and low halves. If the folded value is 0, the id is 1.
+    ///
+    /// Different names may generate the same id. Event consumers should continue to
+    /// identify TraceLogging events by provider and event name.
+    /// ```
+    /// use tracelogging as tlg;
+    /// assert_eq!(tlg::Provider::event_id_from_name("MyEvent"), 0x2708);
+    /// ```
+    pub const fn event_id_from_name(event_name: &str) -> u16 {
+        let hash = fnv1a_hash(event_name.as_bytes());
+        let id = (hash ^ (hash >> 16)) as u16;
+        return if id == 0 { 1 } else { id };
+    }
+
     /// *Advanced:* Returns this provider's encoded metadata bytes.
     pub const fn raw_meta(&self) -> &[u8] {
         return self.meta;
     }
 
+    /// For use by the write_event macro: Returns this provider. The `write_event!`
+    /// expansion calls `primary()` on its provider symbol so that the same expansion
+    /// works for a [`ProviderSet`](crate::ProviderSet).
+    #[doc(hidden)]
+    pub const fn primary(&'static self) -> &'static Provider {
+        return self;
+    }
+
+    /// Returns this provider's parsed metadata (name, group id, and traits).
+    pub fn metadata(&self) -> ProviderMetadata<'_> {
+        return ProviderMetadata::parse(self.meta).unwrap();
+    }
+
     /// Returns this provider's name.
     pub fn name(&self) -> &str {
         let mut name_end = 2;
@@ -134,6 +206,8 @@ impl Provider {
 
     /// Returns true if any ETW logging session is listening to this provider for events
     /// with the specified level and keyword.
+    /// If feature `keyword_levels` is enabled, events with levels not allowed by
+    /// `Provider::set_keyword_level` are reported as disabled.
     ///
     /// This method is only supported on the ETW implementation of Provider.
     /// For similar functionality that works with all implementations, use the
@@ -141,11 +215,404 @@ impl Provider {
     ///
     /// Note: [`write_event!`] already checks `enabled()`. You only need to make your own
     /// call to `enabled()` if you want to skip something other than [`write_event!`].
+    ///
+    /// Thread safety: ETW may update the provider's level and keywords at any time.
+    /// `enabled()` always evaluates a consistent level + keyword snapshot, but the
+    /// result may be out-of-date by the time it is used.
     #[inline(always)]
-    pub const fn enabled(&self, level: Level, keyword: u64) -> bool {
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
         return self.context.enabled(level, keyword);
     }
 
+    /// Returns a counter that increments each time an ETW enable callback changes
+    /// this provider's enabled level or keywords (wraps on overflow). Enable
+    /// callbacks that do not change the level or keywords do not increment the
+    /// counter.
+    ///
+    /// This can be used to invalidate cached results of [`Provider::enabled`]: if
+    /// the generation has not changed, the cached results are still valid.
+    ///
+    /// This method is only supported on the ETW implementation of Provider. On other
+    /// implementations it always returns 0.
+    pub fn enable_generation(&self) -> u32 {
+        return self.context.enable_generation();
+    }
+
+    /// Returns the level and keywords with which ETW sessions currently have this
+    /// provider enabled.
+    ///
+    /// This method is only supported on the ETW implementation of Provider. On other
+    /// implementations it always returns [`ProviderEnableState::DISABLED`].
+    pub fn enable_state(&self) -> ProviderEnableState {
+        return self.context.enable_state();
+    }
+
+    /// Queries ETW for the sessions that currently have this provider enabled in this
+    /// process, reporting each session's logger id, level, and keywords. Returns a
+    /// Win32 error code if the query fails.
+    ///
+    /// Unlike [`Provider::enable_state`], which reports the combined state from the
+    /// most recent enable callback, this asks ETW for each session's own settings
+    /// (via `EnumerateTraceGuidsEx(TraceGuidQueryInfo, ...)`). This is intended for
+    /// diagnostics, e.g. determining why an event is missing from a trace, and is
+    /// much more expensive than [`Provider::enabled`].
+    ///
+    /// The query uses an 8 KB stack buffer. If the provider is registered by many
+    /// processes, the query may fail with `ERROR_MORE_DATA` or
+    /// `ERROR_INSUFFICIENT_BUFFER`.
+    ///
+    /// This method is only supported on the user-mode ETW implementation of Provider.
+    /// On other implementations it always returns `Err(ERROR_NOT_SUPPORTED)` (50).
+    pub fn enabled_info(&self) -> Result<ProviderEnabledInfo, u32> {
+        return ProviderContext::enabled_info(&self.id);
+    }
+
+    /// Returns the filter type (e.g. [`EventFilterDescriptor::TYPE_PAYLOAD`]) that
+    /// was passed to the most recent enable callback, or 0 if it had no filter or if
+    /// the provider is disabled.
+    ///
+    /// Filters are specified by the session controller (e.g. via `EnableTraceEx2`),
+    /// not by the provider, and most filter types are evaluated by ETW, so the
+    /// provider still writes every enabled event. ETW does not pass every filter type
+    /// to providers. A custom callback passed to [`Provider::register_with_callback`]
+    /// can inspect the filter data using [`EventFilterDescriptor::from_filter_data`].
+    ///
+    /// This method is only supported on the ETW implementation of Provider. On other
+    /// implementations it always returns 0.
+    pub fn enable_filter_type(&self) -> u32 {
+        return self.context.enable_filter_type();
+    }
+
+    /// Returns true if the most recent enable callback included a payload filter
+    /// ([`EventFilterDescriptor::TYPE_PAYLOAD`]), i.e. if a session is using ETW's
+    /// field-value filtering for this provider. See [`Provider::enable_filter_type`].
+    ///
+    /// This method is only supported on the ETW implementation of Provider. On other
+    /// implementations it always returns false.
+    pub fn payload_filter_active(&self) -> bool {
+        return self.enable_filter_type() == EventFilterDescriptor::TYPE_PAYLOAD;
+    }
+
+    /// Sets (or with `None`, removes) a callback that is invoked each time the level or
+    /// keywords with which ETW sessions have enabled this provider change, e.g. when a
+    /// session starts or stops listening. The callback receives the new
+    /// [`ProviderEnableState`].
+    ///
+    /// This makes it practical to allocate expensive diagnostic resources only while a
+    /// session is listening: allocate them when `state.is_enabled()` becomes true and
+    /// release them when it becomes false.
+    ///
+    /// - The callback runs on the thread that ETW uses for the enable notification,
+    ///   after the provider's state has been updated (and after the callback passed
+    ///   to [`Provider::register_with_callback`], if any). Callbacks for a provider
+    ///   are serialized, so the callback must not unregister the provider (see
+    ///   [`ProviderEnableCallback`]).
+    /// - Notifications that do not change the state are not reported.
+    /// - Unregistering the provider reports a disabled state if the provider was
+    ///   enabled.
+    /// - Setting the callback does not report the current state. Call
+    ///   [`Provider::enable_state`] after setting the callback to get it.
+    ///
+    /// The callback can be changed at any time, from any thread. This method is only
+    /// supported on the ETW implementation of Provider. On other implementations the
+    /// callback is never invoked.
+    ///
+    /// ```
+    /// use tracelogging as tlg;
+    ///
+    /// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+    ///
+    /// fn enable_changed(state: tlg::ProviderEnableState) {
+    ///     if state.is_enabled() && state.max_level() >= tlg::Level::Verbose {
+    ///         // Start collecting verbose diagnostics.
+    ///     } else {
+    ///         // Stop collecting verbose diagnostics.
+    ///     }
+    /// }
+    ///
+    /// MY_PROVIDER.on_enable_changed(Some(enable_changed));
+    /// unsafe { MY_PROVIDER.register(); }
+    /// enable_changed(MY_PROVIDER.enable_state());
+    /// ```
+    pub fn on_enable_changed(&self, callback: Option<EnableChangedCallback>) {
+        self.context.set_enable_changed_callback(callback);
+    }
+
+    /// Sets the number of times a write to this provider will be retried if ETW reports
+    /// that the session's buffers are full (`ERROR_NOT_ENOUGH_MEMORY`) (requires
+    /// feature `write_retries`). Default is 0 (no retry). Values greater than 3 are
+    /// treated as 3.
+    ///
+    /// During bursty logging, a session's buffers may briefly fill up while ETW
+    /// flushes them. A retry after a short spin often succeeds, reducing dropped
+    /// events at the cost of some extra latency on the writing thread when the
+    /// buffers are full. Writes that fail for other reasons are not retried.
+    ///
+    /// **Retries can duplicate events.** If several sessions are listening to the
+    /// provider, ETW reports the error if any one of them dropped the event, even if
+    /// the others recorded it. A retry writes the event to all of the sessions again,
+    /// so sessions that recorded the first attempt receive the event twice. Only
+    /// enable retries if the provider's events are collected by a single session or
+    /// if the consumers tolerate duplicate events.
+    #[cfg(feature = "write_retries")]
+    pub fn set_write_retries(&self, retries: u8) {
+        self.context.set_write_retries(retries);
+    }
+
+    /// Returns the number of times a write to this provider will be retried if ETW
+    /// reports that the session's buffers are full (requires feature `write_retries`).
+    /// Default is 0.
+    #[cfg(feature = "write_retries")]
+    pub fn write_retries(&self) -> u8 {
+        return self.context.write_retries();
+    }
+
+    /// Sets (or with `None`, removes) the most-verbose level that [`Provider::enabled`]
+    /// will allow for events with any of the bits in `keywords`, in addition to the
+    /// level set by ETW (requires feature `keyword_levels`). Default is `None` (no
+    /// restriction).
+    ///
+    /// ETW sessions enable a provider at a single level for all keywords. Keyword
+    /// levels give finer-grained control without needing multiple providers, e.g.
+    /// allow `Verbose` events with a networking keyword while allowing only
+    /// `Informational` events with other keywords:
+    ///
+    /// ```
+    /// use tracelogging as tlg;
+    ///
+    /// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+    ///
+    /// const NETWORKING_KEYWORD: u64 = 0x4;
+    /// MY_PROVIDER.set_keyword_level(NETWORKING_KEYWORD, Some(tlg::Level::Verbose));
+    /// MY_PROVIDER.set_default_keyword_level(Some(tlg::Level::Informational));
+    /// ```
+    ///
+    /// If an event's keyword has one or more bits with a keyword level, the most-verbose
+    /// of those levels applies. Otherwise (including for keyword 0) the default
+    /// keyword level set by [`Provider::set_default_keyword_level`] applies. Keyword
+    /// levels can only restrict events: they cannot enable events that ETW has not
+    /// enabled.
+    ///
+    /// Keyword levels can be changed at any time, from any thread. If no keyword levels
+    /// are set, the cost to [`Provider::enabled`] is one atomic load. If feature
+    /// `keyword_levels` is not enabled, the provider does not store keyword levels
+    /// and [`Provider::enabled`] only checks the state set by ETW.
+    #[cfg(feature = "keyword_levels")]
+    pub fn set_keyword_level(&self, keywords: u64, level: Option<Level>) {
+        self.context.set_keyword_level(keywords, level);
+    }
+
+    /// Sets (or with `None`, removes) the most-verbose level that [`Provider::enabled`]
+    /// will allow for events whose keyword has no bits with a keyword level. Default
+    /// is `None` (no restriction). See [`Provider::set_keyword_level`].
+    #[cfg(feature = "keyword_levels")]
+    pub fn set_default_keyword_level(&self, level: Option<Level>) {
+        self.context.set_default_keyword_level(level);
+    }
+
+    /// Returns the most-verbose level that [`Provider::enabled`] will allow for events
+    /// with the specified keyword, or `None` if keyword levels do not restrict such
+    /// events. See [`Provider::set_keyword_level`].
+    #[cfg(feature = "keyword_levels")]
+    pub fn keyword_level(&self, keyword: u64) -> Option<Level> {
+        return self.context.keyword_level(keyword);
+    }
+
+    /// Removes all levels set by [`Provider::set_keyword_level`] and
+    /// [`Provider::set_default_keyword_level`].
+    #[cfg(feature = "keyword_levels")]
+    pub fn clear_keyword_levels(&self) {
+        self.context.clear_keyword_levels();
+    }
+
+    /// Sets a function that [`write_event!`] will call for each enabled event. If the
+    /// function returns false, the event is skipped. Replaces any previous filter.
+    ///
+    /// The filter is called after the level and keyword check and before the event's
+    /// field values are evaluated. This can be used by a host application to suppress
+    /// specific noisy events at runtime without changing ETW session configuration:
+    ///
+    /// ```
+    /// use tracelogging as tlg;
+    ///
+    /// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+    ///
+    /// fn skip_event_5(descriptor: &tlg::EventDescriptor) -> bool {
+    ///     return descriptor.id != 5;
+    /// }
+    ///
+    /// MY_PROVIDER.set_event_filter(skip_event_5);
+    /// ```
+    ///
+    /// The filter can be changed at any time, from any thread. If no filter is set, the
+    /// cost to [`write_event!`] is one atomic load.
+    pub fn set_event_filter(&self, filter: EventFilter) {
+        self.event_filter
+            .store(filter as *mut (), Ordering::Release);
+    }
+
+    /// Removes the filter set by [`Provider::set_event_filter`].
+    pub fn clear_event_filter(&self) {
+        self.event_filter.store(ptr::null_mut(), Ordering::Release);
+    }
+
+    /// Returns the filter set by [`Provider::set_event_filter`], if any.
+    pub fn event_filter(&self) -> Option<EventFilter> {
+        let filter = self.event_filter.load(Ordering::Acquire);
+        return if filter.is_null() {
+            None
+        } else {
+            // Safety: non-null values are only stored by set_event_filter.
+            Some(unsafe { mem::transmute::<*mut (), EventFilter>(filter) })
+        };
+    }
+
+    /// Returns true if this provider is currently registered, i.e. if [`Provider::register`]
+    /// succeeded and [`Provider::unregister`] has not yet been called.
+    ///
+    /// This is a simple read of the registration handle. It does not allocate, does not
+    /// access thread-local storage, and does not call into ETW. On non-Windows
+    /// implementations it always returns false.
+    #[inline(always)]
+    pub const fn is_registered(&self) -> bool {
+        return self.context.reg_handle() != 0;
+    }
+
+    /// Sets (or with `None`, removes) a hook that is invoked before each event from
+    /// this provider is sent to ETW (requires feature `write_hook`). The hook receives
+    /// the event's descriptor and the sizes of the event's metadata and data.
+    ///
+    /// This is intended for tests and diagnostics, e.g. counting the events written
+    /// by a component and their sizes without an ETW session or a decoder. The hook
+    /// is invoked only for events that reach the write, i.e. events that are enabled and are not rejected by the
+    /// provider's event filter.
+    /// The hook can be changed at any time, from any thread.
+    #[cfg(feature = "write_hook")]
+    pub fn set_pre_write_hook(&self, hook: Option<PreWriteHook>) {
+        self.context.set_pre_write_hook(hook);
+    }
+
+    /// Returns the hook set by [`Provider::set_pre_write_hook`], if any (requires
+    /// feature `write_hook`).
+    #[cfg(feature = "write_hook")]
+    pub fn pre_write_hook(&self) -> Option<PreWriteHook> {
+        return self.context.pre_write_hook();
+    }
+
+    /// Returns the number of times an event was written (or skipped as not enabled)
+    /// while this provider was not registered (requires feature
+    /// `unregistered_writes`).
+    ///
+    /// Writing to an unregistered provider is a no-op, so a component that logs
+    /// before registering its provider (or after unregistering it) silently loses
+    /// events. Check this counter in tests to catch such initialization-order bugs.
+    /// With feature `unregistered_writes_panic`, such writes also fail a debug
+    /// assertion. On non-Windows implementations the provider is never registered,
+    /// so every write is counted (but does not fail the assertion).
+    #[cfg(feature = "unregistered_writes")]
+    pub fn unregistered_writes(&self) -> u32 {
+        return self.context.unregistered_writes();
+    }
+
+    /// Returns a snapshot of this provider's write statistics (requires feature
+    /// `stats`): the number of events that were written, that were skipped because
+    /// the provider was not enabled (or were rejected by the provider's event
+    /// filter), and that failed, along with the error code of the most recent failure.
+    ///
+    /// This is cheap telemetry about the provider's own telemetry, e.g. a rising
+    /// `failed` count with `last_error` 8 (`ERROR_NOT_ENOUGH_MEMORY`) indicates that
+    /// a session is dropping events because its buffers are full. Events skipped by a
+    /// `sample_rate` are not counted. The counters are updated with relaxed atomics,
+    /// so a snapshot taken while other threads are writing may be slightly
+    /// inconsistent.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> ProviderStats {
+        return self.context.stats();
+    }
+
+    /// Adds a source of state events to this provider. The source will be invoked each
+    /// time an ETW session sends a capture-state request to this provider
+    /// (`EVENT_CONTROL_CODE_CAPTURE_STATE`). See [`StateSource`].
+    ///
+    /// Sources cannot be removed, and each source can be added to only one provider.
+    /// Returns false (and does nothing) if `source` was already added to a provider.
+    ///
+    /// Sources are invoked on the thread that ETW uses for the capture-state request,
+    /// after the callback passed to [`Provider::register_with_callback`] (if any), in
+    /// the reverse of the order in which they were added.
+    pub fn add_state_source(&self, source: &'static StateSource) -> bool {
+        return self.context.state_sources().add(source);
+    }
+
+    /// Returns an iterator over the sources that were added by
+    /// [`Provider::add_state_source`], most-recently-added first.
+    pub fn state_sources(&self) -> StateSources {
+        return self.context.state_sources().iter();
+    }
+
+    /// Invokes all of this provider's state sources, i.e. writes the provider's state
+    /// events now (same as when ETW sends a capture-state request).
+    ///
+    /// This is useful for writing the initial state immediately after registering the
+    /// provider. As with any event, state events are only written if a session has
+    /// enabled the provider with [`Provider::STATE_KEYWORD`].
+    pub fn capture_state(&self) {
+        for source in self.state_sources() {
+            source.write_state();
+        }
+    }
+
+    /// *Advanced:* Writes an event with pre-encoded TraceLogging metadata and data,
+    /// e.g. to relog an event captured from another provider, process, or file under
+    /// this provider.
+    ///
+    /// - `event_meta` is the event's encoded metadata: u16 size (must equal
+    ///   `event_meta.len()`), tag, NUL-terminated event name, then field definitions.
+    /// - `data` is the event's encoded field values, as one or more blocks (e.g. the
+    ///   values of each field). The blocks are sent to ETW without being copied.
+    ///
+    /// The event is sent with this provider's metadata, so it is decoded as an event
+    /// from this provider. The metadata and data are not validated beyond the metadata
+    /// size, and the event is not checked against [`Provider::enabled`] or the
+    /// provider's event filter.
+    ///
+    /// Returns 0 for success or a Win32 error code for failure:
+    /// `ERROR_INVALID_PARAMETER` (87) if the metadata size is wrong or if `data` has
+    /// more than 126 blocks, otherwise the result of `EventWriteTransfer`. The return
+    /// value is for diagnostic purposes only and should generally be ignored in retail
+    /// builds.
+    pub fn write_raw(
+        &self,
+        descriptor: &EventDescriptor,
+        event_meta: &[u8],
+        data: &[&[u8]],
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        const MAX_EVENT_DATA_DESCRIPTORS: usize = 128;
+        if event_meta.len() < 3
+            || event_meta.len() != u16::from_le_bytes([event_meta[0], event_meta[1]]) as usize
+            || data.len() > MAX_EVENT_DATA_DESCRIPTORS - 2
+        {
+            return 87; // ERROR_INVALID_PARAMETER
+        }
+
+        let mut dd: [EventDataDescriptor; MAX_EVENT_DATA_DESCRIPTORS] =
+            core::array::from_fn(|_| EventDataDescriptor::default());
+        dd[0] = EventDataDescriptor::from_raw_bytes(self.meta, 2); // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
+        dd[1] = EventDataDescriptor::from_raw_bytes(event_meta, 1); // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
+        for (i, block) in data.iter().enumerate() {
+            dd[2 + i] = EventDataDescriptor::from_raw_bytes(block, 0);
+        }
+
+        return self.context.write_transfer(
+            descriptor,
+            activity_id.map(|g| g.as_bytes_raw()),
+            related_id.map(|g| g.as_bytes_raw()),
+            &dd[..2 + data.len()],
+        );
+    }
+
     /// If this provider is not registered, does nothing and returns 0.
     /// Otherwise, unregisters the provider.
     ///
@@ -184,6 +651,27 @@ impl Provider {
     ///   You'll typically register the provider during `DLL_PROCESS_ATTACH` and
     ///   unregister during `DLL_PROCESS_DETACH`.
     pub unsafe fn register(&self) -> u32 {
+        return register_result(self.register_impl(None, 0));
+    }
+
+    /// Same as [`Provider::register`], but returns a [RegisterError] that indicates
+    /// which step of registration failed.
+    ///
+    /// Registration calls `EventRegister` and then sets the provider's traits (name
+    /// and group) with `EventSetInformation`. If either step fails, the provider is
+    /// left unregistered, i.e. [`Provider::is_registered`] returns false and the
+    /// provider may be registered again. A failure to set the traits because the
+    /// operating system does not support provider traits (`ERROR_NOT_SUPPORTED`, or
+    /// `STATUS_NOT_SUPPORTED` in kernel mode) is not an error.
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [`Provider::register`].
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`Provider::register`].
+    pub unsafe fn try_register(&self) -> Result<(), RegisterError> {
         return self.register_impl(None, 0);
     }
 
@@ -219,9 +707,116 @@ impl Provider {
         callback_fn: ProviderEnableCallback,
         callback_context: usize,
     ) -> u32 {
+        return register_result(self.register_impl(Some(callback_fn), callback_context));
+    }
+
+    /// Same as [`Provider::register_with_callback`], but returns a [RegisterError]
+    /// that indicates which step of registration failed. See
+    /// [`Provider::try_register`].
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [`Provider::register`].
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`Provider::register`].
+    pub unsafe fn try_register_with_callback(
+        &self,
+        callback_fn: ProviderEnableCallback,
+        callback_context: usize,
+    ) -> Result<(), RegisterError> {
         return self.register_impl(Some(callback_fn), callback_context);
     }
 
+    /// Same as [`Provider::try_register`], but uses the specified
+    /// [RegisterOptions], e.g. to set a custom provider enable callback and context or
+    /// to retry registration after a transient failure.
+    ///
+    /// Returns a [RegisterError] that indicates which step of registration failed.
+    /// Use [`RegisterError::code`] to get the Win32 error code.
+    ///
+    /// ```
+    /// # use tracelogging as tlg;
+    /// # tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+    /// let options = tlg::RegisterOptions::new().busy_retries(3);
+    /// if let Err(error) = unsafe { MY_PROVIDER.register_with_options(&options) } {
+    ///     println!("{} (error {})", error, error.code());
+    /// }
+    /// # MY_PROVIDER.unregister();
+    /// ```
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [`Provider::register`].
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`Provider::register`].
+    pub unsafe fn register_with_options(
+        &self,
+        options: &RegisterOptions,
+    ) -> Result<(), RegisterError> {
+        return self.register_impl_with_retries(
+            options.callback_fn,
+            options.callback_context,
+            options.busy_retries,
+        );
+    }
+
+    /// Connects the provider to the ETW registration of a C/C++
+    /// `TraceLoggingHProvider` with the same provider id, so that code written in
+    /// both languages can log to the provider without registering it twice.
+    ///
+    /// `cpp_provider` is the value of the C/C++ `TraceLoggingHProvider` (e.g. the
+    /// provider symbol declared by `TRACELOGGING_DEFINE_PROVIDER`), which must already
+    /// be registered via `TraceLoggingRegister` or `TraceLoggingRegisterEx`.
+    ///
+    /// The provider writes events using the C/C++ provider's registration handle and
+    /// receives its enable callbacks: `adopt_registration` chains the provider's
+    /// callback in front of the C/C++ provider's enable callback (if any), and
+    /// [`Provider::unregister`] removes it from the chain instead of calling
+    /// `EventUnregister`. Any number of providers (including `tracelogging_dynamic`
+    /// providers) may adopt the same C/C++ provider and may be unregistered in any
+    /// order; the C/C++ provider's original callback is restored when the last one is
+    /// unregistered. The C/C++ provider's callback context is never modified. The
+    /// provider's own metadata (name and traits) is still used for its events.
+    ///
+    /// Returns 0 for success or a Win32 error code for failure:
+    /// `ERROR_INVALID_PARAMETER` (87) if `cpp_provider` is null or has a different
+    /// provider id, `ERROR_INVALID_HANDLE` (6) if `cpp_provider` is not registered, or
+    /// `ERROR_NO_SYSTEM_RESOURCES` (1450) if 8 other C/C++ providers have been adopted
+    /// by this process (each stays counted after its adopters are unregistered) or if
+    /// 8 providers have already adopted `cpp_provider`.
+    /// The return value is for diagnostic purposes only and should generally be
+    /// ignored in retail builds.
+    ///
+    /// Enable callbacks of the providers that adopted the same C/C++ provider are
+    /// serialized with each other, with the C/C++ provider's own callback, and with
+    /// `adopt_registration` for that C/C++ provider. Code that runs during an adopting
+    /// provider's enable callback (its `on_enable_changed` callback and state sources)
+    /// may register, unregister, or adopt providers that are not connected to the same
+    /// C/C++ provider. It must not call `adopt_registration` for the same C/C++
+    /// provider or unregister any provider that adopted it (including itself), or it
+    /// will deadlock.
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [`Provider::register`].
+    /// - Must not be called at the same time as the C/C++ code registers or
+    ///   unregisters `cpp_provider`.
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`Provider::register`].
+    /// - `cpp_provider` must be null or a valid `TraceLoggingHProvider` that is not
+    ///   freed (e.g. by unloading the DLL that defines it) until every provider that
+    ///   adopted it has been unregistered. The C/C++ code may unregister it first; after
+    ///   that, events written by the adopting providers are dropped.
+    pub unsafe fn adopt_registration(&self, cpp_provider: *mut c_void) -> u32 {
+        return self.context.adopt(&self.id, cpp_provider, None, 0);
+    }
+
     /// Safety:
     ///
     /// 1. Pinning: The only way to construct a provider is `provider_new`.
@@ -237,18 +832,37 @@ impl Provider {
         &self,
         callback_fn: Option<ProviderEnableCallback>,
         callback_context: usize,
-    ) -> u32 {
+    ) -> Result<(), RegisterError> {
+        return self.register_impl_with_retries(callback_fn, callback_context, 0);
+    }
+
+    /// Safety: Same as [`Provider::register_impl`].
+    fn register_impl_with_retries(
+        &self,
+        callback_fn: Option<ProviderEnableCallback>,
+        callback_context: usize,
+        busy_retries: u8,
+    ) -> Result<(), RegisterError> {
         let result = unsafe {
-            self.context
-                .register(&self.id, callback_fn, callback_context)
+            self.context.register_with_retries(
+                &self.id,
+                callback_fn,
+                callback_context,
+                busy_retries,
+            )
         };
+        if result != 0 {
+            return Err(RegisterError::EventRegister(result));
+        }
 
-        if result == 0 {
-            // 2 == EventProviderSetTraits
-            self.context.set_information(2, self.meta);
+        // 2 == EventProviderSetTraits
+        let result = self.context.set_information(2, self.meta);
+        if result != 0 && result != SET_INFORMATION_NOT_SUPPORTED {
+            self.context.unregister();
+            return Err(RegisterError::SetTraits(result));
         }
 
-        return result;
+        return Ok(());
     }
 }
 
@@ -265,6 +879,254 @@ impl fmt::Debug for Provider {
     }
 }
 
+/// Error returned by [`Provider::try_register`] and similar methods, indicating which
+/// step of registration failed. In each case, the provider is left unregistered.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum RegisterError {
+    /// `EventRegister` (`EtwRegister` for kernel mode) failed with the specified Win32
+    /// error code (NTSTATUS for kernel mode).
+    EventRegister(u32),
+
+    /// `EventRegister` succeeded, but setting the provider's traits with
+    /// `EventSetInformation` (`EtwSetInformation` for kernel mode) failed with the
+    /// specified Win32 error code (NTSTATUS for kernel mode), so the provider was
+    /// unregistered.
+    SetTraits(u32),
+}
+
+impl RegisterError {
+    /// Returns the Win32 error code, i.e. the value returned by [`Provider::register`]
+    /// for this error.
+    pub const fn code(self) -> u32 {
+        return match self {
+            RegisterError::EventRegister(error) => error,
+            RegisterError::SetTraits(error) => error,
+        };
+    }
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            RegisterError::EventRegister(error) => {
+                write!(f, "EventRegister failed with error {}", error)
+            }
+            RegisterError::SetTraits(error) => {
+                write!(
+                    f,
+                    "EventSetInformation (provider traits) failed with error {}",
+                    error
+                )
+            }
+        };
+    }
+}
+
+impl From<RegisterError> for u32 {
+    fn from(val: RegisterError) -> Self {
+        return val.code();
+    }
+}
+
+/// Error from writing an event, i.e. a nonzero value returned by
+/// [`write_event!`], `EventBuilder::write`, or similar APIs, for code that
+/// needs to handle specific failures without hard-coding Win32 error codes.
+///
+/// Use [`WriteError::check`] to convert a result code into a `Result`, and
+/// [`WriteError::code`] to get the original code. Codes that do not have a named
+/// variant (including most kernel-mode `NTSTATUS` values) are reported as
+/// [`WriteError::Other`].
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// match tlg::WriteError::check(tlg::write_event!(MY_PROVIDER, "MyEvent")) {
+///     Ok(()) => {}
+///     Err(tlg::WriteError::BuffersFull) => {} // Session is busy: event was dropped.
+///     Err(error) => println!("write failed: {}", error),
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[non_exhaustive]
+pub enum WriteError {
+    /// The provider is not registered (`ERROR_INVALID_HANDLE`), e.g. returned by
+    /// [`try_write_event!`](crate::try_write_event).
+    InvalidHandle,
+
+    /// The session's buffers are full, so the event was dropped
+    /// (`ERROR_NOT_ENOUGH_MEMORY`, or `STATUS_NO_MEMORY` for kernel mode). This is
+    /// usually a transient condition; see `Provider::set_write_retries` (feature
+    /// `write_retries`).
+    BuffersFull,
+
+    /// The event is larger than the session's buffer size (`ERROR_MORE_DATA`).
+    MoreData,
+
+    /// The event is larger than the maximum ETW event size (64KB) or has too much
+    /// metadata (`ERROR_ARITHMETIC_OVERFLOW`).
+    TooLarge,
+
+    /// An argument was invalid (`ERROR_INVALID_PARAMETER`).
+    InvalidParameter,
+
+    /// Some other Win32 error code (or `NTSTATUS` for kernel mode).
+    Other(u32),
+}
+
+impl WriteError {
+    /// Returns the error for the specified nonzero result code, or `None` if `code`
+    /// is 0 (`ERROR_SUCCESS`).
+    pub const fn from_code(code: u32) -> Option<WriteError> {
+        return match code {
+            0 => None,
+            6 => Some(WriteError::InvalidHandle),
+            WRITE_BUFFERS_FULL => Some(WriteError::BuffersFull),
+            87 => Some(WriteError::InvalidParameter),
+            234 => Some(WriteError::MoreData),
+            534 => Some(WriteError::TooLarge),
+            _ => Some(WriteError::Other(code)),
+        };
+    }
+
+    /// Returns `Ok(())` if `code` is 0 (`ERROR_SUCCESS`), otherwise returns the error
+    /// for the specified result code.
+    pub const fn check(code: u32) -> Result<(), WriteError> {
+        return match WriteError::from_code(code) {
+            None => Ok(()),
+            Some(error) => Err(error),
+        };
+    }
+
+    /// Returns the Win32 error code (or `NTSTATUS` for kernel mode) for this error.
+    pub const fn code(self) -> u32 {
+        return match self {
+            WriteError::InvalidHandle => 6,
+            WriteError::BuffersFull => WRITE_BUFFERS_FULL,
+            WriteError::InvalidParameter => 87,
+            WriteError::MoreData => 234,
+            WriteError::TooLarge => 534,
+            WriteError::Other(code) => code,
+        };
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            WriteError::InvalidHandle => f.write_str("provider is not registered"),
+            WriteError::BuffersFull => f.write_str("session buffers are full"),
+            WriteError::MoreData => f.write_str("event is larger than the session buffer"),
+            WriteError::TooLarge => f.write_str("event is too large"),
+            WriteError::InvalidParameter => f.write_str("invalid parameter"),
+            WriteError::Other(code) => write!(f, "write failed with error {}", code),
+        };
+    }
+}
+
+impl From<WriteError> for u32 {
+    fn from(val: WriteError) -> Self {
+        return val.code();
+    }
+}
+
+/// Options for [`Provider::register_with_options`].
+///
+/// By default, no custom enable callback is used and registration is not retried.
+#[derive(Clone, Copy, Default)]
+pub struct RegisterOptions {
+    callback_fn: Option<ProviderEnableCallback>,
+    callback_context: usize,
+    busy_retries: u8,
+}
+
+impl RegisterOptions {
+    /// Returns the default options.
+    pub const fn new() -> Self {
+        return Self {
+            callback_fn: None,
+            callback_context: 0,
+            busy_retries: 0,
+        };
+    }
+
+    /// Sets a custom provider enable callback. `callback_context` is passed to the
+    /// callback, as with [`Provider::register_with_callback`].
+    pub const fn callback(
+        mut self,
+        callback_fn: ProviderEnableCallback,
+        callback_context: usize,
+    ) -> Self {
+        self.callback_fn = Some(callback_fn);
+        self.callback_context = callback_context;
+        return self;
+    }
+
+    /// Sets the number of times `EventRegister` is retried if it fails with a
+    /// transient error: `ERROR_BUSY` (170) or `ERROR_NOT_ENOUGH_MEMORY` (8), or for
+    /// kernel mode, `STATUS_DEVICE_BUSY` or `STATUS_INSUFFICIENT_RESOURCES`. The
+    /// provider spins briefly before each retry. Default is 0 (no retries).
+    pub const fn busy_retries(mut self, retries: u8) -> Self {
+        self.busy_retries = retries;
+        return self;
+    }
+
+    /// Returns the custom provider enable callback and its context, if any.
+    pub const fn enable_callback(&self) -> Option<(ProviderEnableCallback, usize)> {
+        return match self.callback_fn {
+            Some(callback_fn) => Some((callback_fn, self.callback_context)),
+            None => None,
+        };
+    }
+
+    /// Returns the number of times `EventRegister` is retried after a transient
+    /// failure.
+    pub const fn busy_retry_limit(&self) -> u8 {
+        return self.busy_retries;
+    }
+}
+
+impl fmt::Debug for RegisterOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "RegisterOptions {{ callback: {}, callback_context: {:#x}, busy_retries: {} }}",
+            self.callback_fn.is_some(),
+            self.callback_context,
+            self.busy_retries
+        );
+    }
+}
+
+/// Converts the result of a `try_register` method to the result of the corresponding
+/// `register` method.
+pub fn register_result(result: Result<(), RegisterError>) -> u32 {
+    return match result {
+        Ok(()) => 0,
+        Err(error) => error.code(),
+    };
+}
+
+/// Returns the 32-bit FNV-1a hash of `bytes`. Used by [`Provider::event_id_from_name`]
+/// and by tracelogging_dynamic's event sampling table.
+pub const fn fnv1a_hash(bytes: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c9dc5;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = (hash ^ bytes[i] as u32).wrapping_mul(0x01000193);
+        i += 1;
+    }
+    return hash;
+}
+
+/// For tests: makes the next registration of the provider fail at the specified
+/// step. Has no effect on Windows/etw. See [`ProviderContext::inject_register_error`].
+pub fn provider_inject_register_error(provider: &Provider, error: Option<RegisterError>) {
+    provider.context.inject_register_error(error);
+}
+
 /// For use by the define_provider macro: creates a new provider.
 ///
 /// # Safety
@@ -276,18 +1138,119 @@ pub const unsafe fn provider_new(meta: &'static [u8], id: &Guid) -> Provider {
         context: ProviderContext::new(),
         meta,
         id: *id,
+        event_filter: AtomicPtr::new(ptr::null_mut()),
     };
 }
 
+/// For use by the write_event macro: The destination of an event, i.e. a [`Provider`]
+/// or a [`ProviderSet`](crate::ProviderSet).
+pub trait EventTarget {
+    /// Returns the provider metadata for the first data descriptor of the event.
+    fn raw_meta(&self) -> &[u8];
+
+    /// Returns false if the target's event filter rejects the event.
+    fn filter_event(&self, descriptor: &EventDescriptor) -> bool;
+
+    /// Notes a write that was skipped because the target is not enabled.
+    fn note_write(&self);
+
+    /// Writes the event. `dd[0]` is the provider metadata descriptor, which may be
+    /// replaced before each write.
+    fn write_transfer(
+        &self,
+        descriptor: &EventDescriptor,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        dd: &mut [EventDataDescriptor],
+    ) -> u32;
+}
+
+impl<T: EventTarget + ?Sized> EventTarget for &T {
+    fn raw_meta(&self) -> &[u8] {
+        return T::raw_meta(self);
+    }
+
+    fn filter_event(&self, descriptor: &EventDescriptor) -> bool {
+        return T::filter_event(self, descriptor);
+    }
+
+    fn note_write(&self) {
+        T::note_write(self);
+    }
+
+    fn write_transfer(
+        &self,
+        descriptor: &EventDescriptor,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        dd: &mut [EventDataDescriptor],
+    ) -> u32 {
+        return T::write_transfer(self, descriptor, activity_id, related_id, dd);
+    }
+}
+
+impl EventTarget for Provider {
+    fn raw_meta(&self) -> &[u8] {
+        return self.meta;
+    }
+
+    #[inline(always)]
+    fn filter_event(&self, descriptor: &EventDescriptor) -> bool {
+        return match self.event_filter() {
+            None => true,
+            Some(filter) => {
+                let write = filter(descriptor);
+                if !write {
+                    self.context.note_filtered();
+                }
+                write
+            }
+        };
+    }
+
+    #[inline(always)]
+    fn note_write(&self) {
+        self.context.note_write();
+        self.context.note_filtered();
+    }
+
+    fn write_transfer(
+        &self,
+        descriptor: &EventDescriptor,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        dd: &mut [EventDataDescriptor],
+    ) -> u32 {
+        return self
+            .context
+            .write_transfer(descriptor, activity_id, related_id, dd);
+    }
+}
+
+/// For use by the write_event macro: Returns false if the provider's event filter
+/// rejects the event.
+#[inline(always)]
+pub fn provider_event_filter<T: EventTarget + ?Sized>(
+    provider: &T,
+    descriptor: &EventDescriptor,
+) -> bool {
+    return provider.filter_event(descriptor);
+}
+
+/// For use by the write_event macro: Notes a write that was skipped because the
+/// provider is not enabled. See [`Provider::unregistered_writes`].
+#[inline(always)]
+pub fn provider_note_write<T: EventTarget + ?Sized>(provider: &T) {
+    provider.note_write();
+}
+
 /// For use by the write_event macro: Calls EventWriteTransfer.
-pub fn provider_write_transfer(
-    provider: &Provider,
+pub fn provider_write_transfer<T: EventTarget + ?Sized>(
+    provider: &T,
     descriptor: &EventDescriptor,
     activity_id: Option<&[u8; 16]>,
     related_id: Option<&[u8; 16]>,
-    dd: &[EventDataDescriptor],
+    dd: &mut [EventDataDescriptor],
 ) -> u32 {
-    return provider
-        .context
-        .write_transfer(descriptor, activity_id, related_id, dd);
+    return provider.write_transfer(descriptor, activity_id, related_id, dd);
 }
@@ -0,0 +1,160 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::iter::FusedIterator;
+use core::str::from_utf8;
+
+use crate::guid::Guid;
+
+/// Parsed view of a provider's encoded metadata, i.e. the bytes returned by
+/// [`Provider::raw_meta`](crate::Provider::raw_meta).
+///
+/// Provider metadata is a `u16` size (the size of the entire blob, little-endian),
+/// the NUL-terminated UTF-8 provider name, and then zero or more
+/// [provider traits](https://docs.microsoft.com/windows/win32/etw/provider-traits).
+/// Each trait is a `u16` size (including the size and type), a `u8` trait type, and
+/// the trait data. The provider group id (from `group_id`) is stored as trait type 1
+/// with a 16-byte GUID.
+///
+/// Use this to inspect a provider's traits (e.g. when relogging or filtering events)
+/// without re-implementing the binary format.
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(
+///     MY_PROVIDER,
+///     "MyCompany.MyComponent",
+///     group_id("f73b8292-f610-4fa7-ba62-708353d162c4"),
+///     trait(128, b"Data"),
+/// );
+///
+/// let meta = tlg::ProviderMetadata::parse(MY_PROVIDER.raw_meta()).unwrap();
+/// assert_eq!("MyCompany.MyComponent", meta.name());
+/// assert_eq!(
+///     Some(tlg::Guid::from_u128(&0xf73b8292_f610_4fa7_ba62_708353d162c4)),
+///     meta.group_id()
+/// );
+/// assert_eq!(
+///     Some(tlg::ProviderTrait { trait_type: 128, data: b"Data" }),
+///     meta.traits().find(|t| t.trait_type == 128)
+/// );
+/// ```
+#[derive(Clone, Copy)]
+pub struct ProviderMetadata<'a> {
+    name: &'a str,
+    traits: &'a [u8],
+}
+
+impl<'a> ProviderMetadata<'a> {
+    /// Trait type of the provider group trait.
+    pub const GROUP_TRAIT_TYPE: u8 = 1;
+
+    /// Parses encoded provider metadata. Returns `None` if the metadata is malformed,
+    /// i.e. if the size does not match `meta.len()`, if the name is not NUL-terminated
+    /// UTF-8, or if a trait's size is out of range.
+    pub fn parse(meta: &'a [u8]) -> Option<Self> {
+        if meta.len() < 3 || u16::from_le_bytes([meta[0], meta[1]]) as usize != meta.len() {
+            return None;
+        }
+
+        let name_len = meta[2..].iter().position(|&b| b == 0)?;
+        let name = from_utf8(&meta[2..2 + name_len]).ok()?;
+        let traits = &meta[2 + name_len + 1..];
+
+        // Validate the trait framing so that iteration never fails.
+        let mut pos = 0;
+        while pos < traits.len() {
+            if traits.len() - pos < 3 {
+                return None;
+            }
+            let trait_len = u16::from_le_bytes([traits[pos], traits[pos + 1]]) as usize;
+            if trait_len < 3 || trait_len > traits.len() - pos {
+                return None;
+            }
+            pos += trait_len;
+        }
+
+        return Some(Self { name, traits });
+    }
+
+    /// Returns the provider's name.
+    pub const fn name(&self) -> &'a str {
+        return self.name;
+    }
+
+    /// Returns the provider's group id, i.e. the data of the first trait with type
+    /// [`ProviderMetadata::GROUP_TRAIT_TYPE`] if the data is 16 bytes, or `None` if the
+    /// provider has no group trait.
+    pub fn group_id(&self) -> Option<Guid> {
+        return self
+            .traits()
+            .find(|t| t.trait_type == Self::GROUP_TRAIT_TYPE)
+            .and_then(|t| <&[u8; 16]>::try_from(t.data).ok())
+            .map(Guid::from_bytes_le);
+    }
+
+    /// Returns an iterator over the provider's traits (including the group trait, if
+    /// any), in the order they appear in the metadata.
+    pub fn traits(&self) -> ProviderTraits<'a> {
+        return ProviderTraits {
+            remaining: self.traits,
+        };
+    }
+
+    /// *Advanced:* Returns the encoded traits, i.e. the metadata that follows the
+    /// provider name.
+    pub const fn raw_traits(&self) -> &'a [u8] {
+        return self.traits;
+    }
+}
+
+impl fmt::Debug for ProviderMetadata<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("ProviderMetadata")
+            .field("name", &self.name)
+            .field("group_id", &self.group_id())
+            .field("traits", &self.traits().count())
+            .finish();
+    }
+}
+
+/// A [provider trait](https://docs.microsoft.com/windows/win32/etw/provider-traits),
+/// as returned by [`ProviderMetadata::traits`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProviderTrait<'a> {
+    /// The trait type, e.g. [`ProviderMetadata::GROUP_TRAIT_TYPE`].
+    pub trait_type: u8,
+
+    /// The trait data (not including the trait's size and type).
+    pub data: &'a [u8],
+}
+
+/// Iterator over the traits of a [ProviderMetadata].
+#[derive(Clone, Debug)]
+pub struct ProviderTraits<'a> {
+    remaining: &'a [u8],
+}
+
+impl<'a> Iterator for ProviderTraits<'a> {
+    type Item = ProviderTrait<'a>;
+
+    fn next(&mut self) -> Option<ProviderTrait<'a>> {
+        if self.remaining.len() < 3 {
+            return None;
+        }
+
+        // Framing was validated by ProviderMetadata::parse.
+        let trait_len = u16::from_le_bytes([self.remaining[0], self.remaining[1]]) as usize;
+        let item = ProviderTrait {
+            trait_type: self.remaining[2],
+            data: &self.remaining[3..trait_len],
+        };
+        self.remaining = &self.remaining[trait_len..];
+        return Some(item);
+    }
+}
+
+impl FusedIterator for ProviderTraits<'_> {}
@@ -0,0 +1,112 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use crate::descriptors::EventDataDescriptor;
+use crate::descriptors::EventDescriptor;
+use crate::enums::Level;
+use crate::provider::EventTarget;
+use crate::provider::Provider;
+
+#[allow(unused_imports)] // For docs
+use crate::define_provider_set;
+
+#[allow(unused_imports)] // For docs
+#[cfg(feature = "macros")]
+use crate::write_event;
+
+/// A fixed set of providers that receive the same events, e.g. a public telemetry
+/// provider and a local debugging provider.
+///
+/// Use [`define_provider_set!`] to create a static provider set, then use the set's
+/// symbol in place of a provider symbol in [`write_event!`]. The event's metadata and
+/// data are built once and then written to each provider in the set that is enabled
+/// for the event's level and keyword and whose event filter accepts the event. The
+/// providers in the set are registered and unregistered individually.
+#[derive(Debug)]
+pub struct ProviderSet {
+    providers: &'static [&'static Provider],
+}
+
+impl ProviderSet {
+    /// For use by the define_provider_set macro: Creates a new provider set.
+    /// Panics (at compile time if used in a `static`) if `providers` is empty.
+    pub const fn new(providers: &'static [&'static Provider]) -> Self {
+        assert!(!providers.is_empty(), "provider set must not be empty");
+        return Self { providers };
+    }
+
+    /// Returns the providers in this set, in the order they were specified.
+    pub const fn providers(&self) -> &'static [&'static Provider] {
+        return self.providers;
+    }
+
+    /// Returns the first provider in this set. When feature `export_schema` is
+    /// enabled, the schemas of events written to the set are exported as events of
+    /// this provider.
+    pub const fn primary(&self) -> &'static Provider {
+        return self.providers[0];
+    }
+
+    /// Returns true if any provider in this set is enabled for events with the
+    /// specified level and keyword. See [`Provider::enabled`].
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return self
+            .providers
+            .iter()
+            .any(|provider| provider.enabled(level, keyword));
+    }
+
+    /// Returns true if any provider in this set is currently registered. See
+    /// [`Provider::is_registered`].
+    pub const fn is_registered(&self) -> bool {
+        let mut i = 0;
+        while i != self.providers.len() {
+            if self.providers[i].is_registered() {
+                return true;
+            }
+            i += 1;
+        }
+        return false;
+    }
+}
+
+impl EventTarget for ProviderSet {
+    fn raw_meta(&self) -> &[u8] {
+        return self.primary().raw_meta();
+    }
+
+    fn filter_event(&self, _descriptor: &EventDescriptor) -> bool {
+        // Each provider's event filter is checked by write_transfer.
+        return true;
+    }
+
+    fn note_write(&self) {
+        for provider in self.providers {
+            provider.note_write();
+        }
+    }
+
+    fn write_transfer(
+        &self,
+        descriptor: &EventDescriptor,
+        activity_id: Option<&[u8; 16]>,
+        related_id: Option<&[u8; 16]>,
+        dd: &mut [EventDataDescriptor],
+    ) -> u32 {
+        let mut result = 0;
+        for provider in self.providers {
+            if !provider.enabled(descriptor.level, descriptor.keyword) {
+                provider.note_write();
+            } else if provider.filter_event(descriptor) {
+                // Only the provider metadata differs between the providers.
+                dd[0] = EventDataDescriptor::from_raw_bytes(provider.raw_meta(), 2); // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
+                let provider_result =
+                    provider.write_transfer(descriptor, activity_id, related_id, dd);
+                if result == 0 {
+                    result = provider_result;
+                }
+            }
+        }
+        return result;
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicU64;
+use core::sync::atomic::Ordering;
+
+#[allow(unused_imports)] // For docs
+use crate::provider::Provider;
+
+/// A snapshot of the write statistics collected for a provider (requires feature
+/// `stats`). See [`Provider::stats`].
+///
+/// Each attempt to write an event is counted exactly once, as filtered, written, or
+/// failed, so `attempted == filtered + written + failed` (until the counters wrap).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ProviderStats {
+    /// Number of attempts to write an event.
+    pub attempted: u64,
+    /// Number of events that were sent to ETW successfully.
+    pub written: u64,
+    /// Number of events that were skipped because the provider was not enabled for
+    /// the event's level and keyword, or because the provider's event filter
+    /// rejected the event.
+    pub filtered: u64,
+    /// Number of events that were sent to ETW but failed, e.g. because the session's
+    /// buffers were full (after any retries).
+    pub failed: u64,
+    /// The Win32 error code of the most recent failed write, or 0 if no write has
+    /// failed.
+    pub last_error: u32,
+}
+
+/// The counters behind [`ProviderStats`].
+pub(crate) struct StatsCounters {
+    written: AtomicU64,
+    filtered: AtomicU64,
+    failed: AtomicU64,
+    last_error: AtomicU32,
+}
+
+impl StatsCounters {
+    pub const fn new() -> Self {
+        return Self {
+            written: AtomicU64::new(0),
+            filtered: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            last_error: AtomicU32::new(0),
+        };
+    }
+
+    #[inline(always)]
+    pub fn note_filtered(&self) {
+        self.filtered.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn note_result(&self, result: u32) {
+        if result == 0 {
+            self.written.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.note_failed(result);
+        }
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn note_failed(&self, result: u32) {
+        self.last_error.store(result, Ordering::Relaxed);
+        self.failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> ProviderStats {
+        let written = self.written.load(Ordering::Relaxed);
+        let filtered = self.filtered.load(Ordering::Relaxed);
+        let failed = self.failed.load(Ordering::Relaxed);
+        return ProviderStats {
+            attempted: written.wrapping_add(filtered).wrapping_add(failed),
+            written,
+            filtered,
+            failed,
+            last_error: self.last_error.load(Ordering::Relaxed),
+        };
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+
+use crate::enums::Level;
+
+/// The level and keywords with which one ETW session has enabled a provider, as
+/// returned by [`Provider::enabled_info`](crate::Provider::enabled_info).
+///
+/// This corresponds to the session's `TRACE_ENABLE_INFO` entry for the provider.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SessionEnableInfo {
+    /// The session's logger id (ETW session handle), e.g. for use with
+    /// `ControlTrace` or for correlating with the output of `logman query -ets`.
+    pub logger_id: u16,
+
+    /// The most verbose level that the session collects from the provider.
+    pub level: Level,
+
+    /// `EnableProperty` flags requested by the session, e.g.
+    /// `EVENT_ENABLE_PROPERTY_STACK_TRACE` (0x4).
+    pub enable_property: u32,
+
+    /// The session's `MatchAnyKeyword`: an event with keyword 0 or with at least one
+    /// of these bits is collected.
+    pub match_any_keyword: u64,
+
+    /// The session's `MatchAllKeyword`: an event must have all of these bits to be
+    /// collected.
+    pub match_all_keyword: u64,
+}
+
+impl SessionEnableInfo {
+    const EMPTY: Self = Self {
+        logger_id: 0,
+        level: Level::LogAlways,
+        enable_property: 0,
+        match_any_keyword: 0,
+        match_all_keyword: 0,
+    };
+
+    /// Returns true if this session collects events with the specified level and
+    /// keyword.
+    pub const fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return level.0 <= self.level.0
+            && (keyword == 0
+                || ((keyword & self.match_any_keyword) != 0
+                    && (keyword & self.match_all_keyword) == self.match_all_keyword));
+    }
+}
+
+/// The ETW sessions that currently have a provider enabled, as returned by
+/// [`Provider::enabled_info`](crate::Provider::enabled_info).
+///
+/// Use this when debugging why events are missing from a trace: each entry reports
+/// the level and keywords requested by one session, so an event that is filtered out
+/// by every session's level or keywords will not be collected.
+#[derive(Clone, Copy)]
+pub struct ProviderEnabledInfo {
+    sessions: [SessionEnableInfo; Self::MAX_SESSIONS],
+    len: u8,
+}
+
+impl ProviderEnabledInfo {
+    /// The maximum number of sessions that can enable a provider at the same time.
+    pub const MAX_SESSIONS: usize = 8;
+
+    /// Information for a provider that is not enabled by any session.
+    pub const EMPTY: Self = Self {
+        sessions: [SessionEnableInfo::EMPTY; Self::MAX_SESSIONS],
+        len: 0,
+    };
+
+    /// *Advanced:* Parses the `TRACE_GUID_INFO` returned by
+    /// `EnumerateTraceGuidsEx(TraceGuidQueryInfo, ...)`, collecting the enabled
+    /// sessions of the provider instances registered by process `pid`. Sessions that
+    /// appear in more than one instance are reported once. Returns `None` if the
+    /// buffer is malformed.
+    pub fn from_trace_guid_info(buffer: &[u8], pid: u32) -> Option<Self> {
+        const GUID_INFO_SIZE: usize = 8;
+        const INSTANCE_INFO_SIZE: usize = 16;
+        const ENABLE_INFO_SIZE: usize = 32;
+
+        let mut info = Self::EMPTY;
+        let instance_count = read_u32(buffer, 0)?;
+        let mut instance_pos = GUID_INFO_SIZE;
+        for _ in 0..instance_count {
+            let next_offset = read_u32(buffer, instance_pos)? as usize;
+            let enable_count = read_u32(buffer, instance_pos + 4)? as usize;
+            let instance_pid = read_u32(buffer, instance_pos + 8)?;
+
+            if instance_pid == pid {
+                let mut enable_pos = instance_pos + INSTANCE_INFO_SIZE;
+                for _ in 0..enable_count {
+                    let is_enabled = read_u32(buffer, enable_pos)?;
+                    if is_enabled != 0 {
+                        info.push(SessionEnableInfo {
+                            logger_id: read_u16(buffer, enable_pos + 6)?,
+                            level: Level(*buffer.get(enable_pos + 4)?),
+                            enable_property: read_u32(buffer, enable_pos + 8)?,
+                            match_any_keyword: read_u64(buffer, enable_pos + 16)?,
+                            match_all_keyword: read_u64(buffer, enable_pos + 24)?,
+                        });
+                    }
+                    enable_pos += ENABLE_INFO_SIZE;
+                }
+            }
+
+            if next_offset == 0 {
+                break;
+            }
+            instance_pos = instance_pos.checked_add(next_offset)?;
+        }
+
+        return Some(info);
+    }
+
+    /// Returns the sessions that have the provider enabled.
+    pub fn sessions(&self) -> &[SessionEnableInfo] {
+        return &self.sessions[..self.len as usize];
+    }
+
+    /// Returns true if no session has the provider enabled.
+    pub const fn is_empty(&self) -> bool {
+        return self.len == 0;
+    }
+
+    /// Returns true if any session collects events with the specified level and
+    /// keyword.
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return self
+            .sessions()
+            .iter()
+            .any(|session| session.enabled(level, keyword));
+    }
+
+    fn push(&mut self, session: SessionEnableInfo) {
+        let len = self.len as usize;
+        if len < Self::MAX_SESSIONS
+            && !self.sessions[..len]
+                .iter()
+                .any(|existing| existing.logger_id == session.logger_id)
+        {
+            self.sessions[len] = session;
+            self.len += 1;
+        }
+    }
+}
+
+impl Default for ProviderEnabledInfo {
+    fn default() -> Self {
+        return Self::EMPTY;
+    }
+}
+
+impl fmt::Debug for ProviderEnabledInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f.debug_list().entries(self.sessions()).finish();
+    }
+}
+
+fn read_u16(buffer: &[u8], pos: usize) -> Option<u16> {
+    let bytes = buffer.get(pos..pos.checked_add(2)?)?;
+    return Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+}
+
+fn read_u32(buffer: &[u8], pos: usize) -> Option<u32> {
+    let bytes = buffer.get(pos..pos.checked_add(4)?)?;
+    return Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
+
+fn read_u64(buffer: &[u8], pos: usize) -> Option<u64> {
+    let bytes = buffer.get(pos..pos.checked_add(8)?)?;
+    let mut value = [0u8; 8];
+    value.copy_from_slice(bytes);
+    return Some(u64::from_le_bytes(value));
+}
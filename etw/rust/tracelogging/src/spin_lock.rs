@@ -0,0 +1,97 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+use core::ops::DerefMut;
+
+#[cfg(not(loom))]
+use core::hint;
+#[cfg(not(loom))]
+use core::sync::atomic;
+
+// Under `--cfg loom`, use loom's atomics so that tests/loom.rs can explore the
+// interleavings of EnableState, which uses a spin lock to serialize updates.
+#[cfg(loom)]
+use loom::hint;
+#[cfg(loom)]
+use loom::sync::atomic;
+
+/// A minimal spin lock for `no_std` code, used for state that is updated rarely and
+/// held briefly (configuration tables, capture buffers, enable updates).
+///
+/// The lock is released when the [`SpinLockGuard`] is dropped, including during a
+/// panic. The lock is not reentrant: locking it again on a thread that already holds
+/// it spins forever.
+pub struct SpinLock<T> {
+    locked: atomic::AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for SpinLock<T> {}
+
+impl<T> SpinLock<T> {
+    /// Returns a new unlocked spin lock that protects `value`.
+    #[cfg(not(loom))]
+    pub const fn new(value: T) -> Self {
+        return Self {
+            locked: atomic::AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        };
+    }
+
+    /// Returns a new unlocked spin lock that protects `value`.
+    /// (Not `const` because loom's atomics cannot be created in a const context.)
+    #[cfg(loom)]
+    pub fn new(value: T) -> Self {
+        return Self {
+            locked: atomic::AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        };
+    }
+
+    /// Spins until the lock is acquired, then returns a guard that releases the lock
+    /// when dropped.
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        while self
+            .locked
+            .compare_exchange_weak(
+                false,
+                true,
+                atomic::Ordering::Acquire,
+                atomic::Ordering::Relaxed,
+            )
+            .is_err()
+        {
+            hint::spin_loop();
+        }
+
+        return SpinLockGuard { lock: self };
+    }
+}
+
+/// Holds a [`SpinLock`]. Dereferences to the protected value.
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        // Safety: we hold the lock.
+        return unsafe { &*self.lock.value.get() };
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: we hold the lock.
+        return unsafe { &mut *self.lock.value.get() };
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, atomic::Ordering::Release);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ptr;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering;
+
+/// A function that writes a component's current state as events, for use with
+/// [`Provider::add_state_source`](crate::Provider::add_state_source).
+///
+/// When an ETW session sends a capture-state request to a provider (e.g. via
+/// `EnableTraceEx2` with `EVENT_CONTROL_CODE_CAPTURE_STATE`, or
+/// `tracelog -capturestate`), the provider invokes each of its state sources. The
+/// state source should write its state (configuration, open handles, active
+/// requests, etc.) using [`write_state_event!`](crate::write_state_event) so that the
+/// trace contains the state that existed before the trace started ("rundown").
+///
+/// State sources are typically `static` so that they can be shared with a `static`
+/// provider:
+///
+/// ```
+/// use tracelogging as tlg;
+///
+/// tlg::define_provider!(MY_PROVIDER, "MyCompany.MyComponent");
+///
+/// static CONFIG_STATE: tlg::StateSource = tlg::StateSource::new(|| {
+///     tlg::write_state_event!(MY_PROVIDER, "Config", str8("Mode", "fast"), u32("Workers", &4));
+/// });
+///
+/// MY_PROVIDER.add_state_source(&CONFIG_STATE);
+/// unsafe { MY_PROVIDER.register(); }
+/// ```
+pub struct StateSource {
+    write_state: fn(),
+    added: AtomicBool,
+    next: AtomicPtr<StateSource>,
+}
+
+impl StateSource {
+    /// Creates a state source that invokes `write_state` to write state events.
+    pub const fn new(write_state: fn()) -> Self {
+        return Self {
+            write_state,
+            added: AtomicBool::new(false),
+            next: AtomicPtr::new(ptr::null_mut()),
+        };
+    }
+
+    /// Invokes the source's `write_state` function.
+    pub fn write_state(&self) {
+        (self.write_state)();
+    }
+
+    /// Returns true if this source has been added to a provider.
+    pub fn is_added(&self) -> bool {
+        return self.added.load(Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for StateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "StateSource {{ added: {} }}", self.is_added());
+    }
+}
+
+/// A lock-free list of state sources. Sources can be added but not removed.
+pub struct StateSourceList {
+    head: AtomicPtr<StateSource>,
+}
+
+impl StateSourceList {
+    pub const fn new() -> Self {
+        return Self {
+            head: AtomicPtr::new(ptr::null_mut()),
+        };
+    }
+
+    /// Adds `source` to the list. Returns false (and does nothing) if `source` has
+    /// already been added to a list.
+    pub fn add(&self, source: &'static StateSource) -> bool {
+        if source.added.swap(true, Ordering::Relaxed) {
+            return false;
+        }
+
+        let source_ptr = source as *const StateSource as *mut StateSource;
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            source.next.store(head, Ordering::Relaxed);
+            match self.head.compare_exchange_weak(
+                head,
+                source_ptr,
+                Ordering::Release,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Returns an iterator over the sources, most-recently-added first.
+    pub fn iter(&self) -> StateSources {
+        return StateSources {
+            next: self.head.load(Ordering::Acquire),
+        };
+    }
+}
+
+/// Iterator over the state sources of a provider, as returned by
+/// [`Provider::state_sources`](crate::Provider::state_sources).
+#[derive(Clone, Debug)]
+pub struct StateSources {
+    next: *const StateSource,
+}
+
+impl Iterator for StateSources {
+    type Item = &'static StateSource;
+
+    fn next(&mut self) -> Option<&'static StateSource> {
+        if self.next.is_null() {
+            return None;
+        }
+
+        // Safety: the list only contains &'static StateSource values.
+        let source = unsafe { &*self.next };
+        self.next = source.next.load(Ordering::Acquire);
+        return Some(source);
+    }
+}
+
+impl FusedIterator for StateSources {}
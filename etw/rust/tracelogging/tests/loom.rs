@@ -0,0 +1,321 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Model checks of the provider enable state and of the table of adopted C/C++
+//! providers.
+//!
+//! The enable state models explore the interleavings of enable callbacks
+//! (`EnableState::update`), the disable update done by unregistration, and writers
+//! (`EnableState::enabled` / `EnableState::snapshot`). The adoption models explore
+//! the interleavings of adopting and unadopting (`AdoptionTable::add` / `remove`)
+//! with the enable callbacks that ETW delivers through an adopted C/C++ provider
+//! (`AdoptionTable::dispatch`).
+//!
+//! The rest of the ETW registration (the register/unregister guard and the
+//! registration handle used by writers) calls the ETW APIs directly and is not
+//! modeled. The `stress` example exercises it on Windows instead.
+//!
+//! Run with:
+//!
+//! `RUSTFLAGS="--cfg loom" cargo test -p tracelogging --no-default-features --test loom --release`
+//!
+//! Default features are disabled because the ETW provider context (which requires
+//! `EnableState::new` to be `const`) does not build with loom's atomics.
+//!
+//! Readers run on spawned threads and updates run on the model's main thread. Loom
+//! explores far fewer interleavings if a reader runs on the main thread while the
+//! update that it races with runs on a spawned thread.
+
+#![cfg(loom)]
+#![allow(clippy::needless_return)]
+
+use loom::cell::UnsafeCell;
+use loom::sync::atomic::AtomicBool;
+use loom::sync::atomic::AtomicU32;
+use loom::sync::atomic::AtomicU8;
+use loom::sync::atomic::Ordering;
+use loom::sync::Arc;
+use loom::thread;
+
+use tracelogging as tlg;
+use tracelogging::_internal as tli;
+
+/// Keywords used by the models: a snapshot with level L is only valid if both
+/// keyword_any and keyword_all are `keyword_for(L)`.
+fn keyword_for(level: u8) -> u64 {
+    return level as u64 * 0x0101;
+}
+
+fn enable(state: &tli::EnableState, level: u8) {
+    state.update(1, level, keyword_for(level), keyword_for(level), || {});
+}
+
+fn disable(state: &tli::EnableState) {
+    state.update(0, 0, 0, 0, || {});
+}
+
+#[test]
+fn snapshot_not_torn() {
+    loom::model(|| {
+        let state = Arc::new(tli::EnableState::new());
+        enable(&state, 2);
+
+        let reader = {
+            let state = state.clone();
+            thread::spawn(move || {
+                let snap = state.snapshot();
+                assert!(snap.level == 2 || snap.level == 5);
+                assert_eq!(keyword_for(snap.level as u8), snap.keyword_any);
+                assert_eq!(keyword_for(snap.level as u8), snap.keyword_all);
+                assert_eq!(if snap.level == 2 { 1 } else { 2 }, snap.generation);
+            })
+        };
+
+        enable(&state, 5);
+        reader.join().unwrap();
+        assert_eq!(2, state.generation());
+    });
+}
+
+#[test]
+fn callbacks_serialized() {
+    loom::model(|| {
+        let state = Arc::new(tli::EnableState::new());
+        let in_callback = Arc::new(AtomicBool::new(false));
+        let notified = Arc::new(AtomicU32::new(0)); // As in notify_enable_changed.
+        let notifications = Arc::new(AtomicU32::new(0));
+
+        let update = {
+            let state = state.clone();
+            let in_callback = in_callback.clone();
+            let notified = notified.clone();
+            let notifications = notifications.clone();
+            move |level: u8| {
+                state.update(1, level, keyword_for(level), keyword_for(level), || {
+                    assert!(!in_callback.swap(true, Ordering::Relaxed));
+                    let generation = state.generation();
+                    if notified.swap(generation, Ordering::Relaxed) != generation {
+                        notifications.fetch_add(1, Ordering::Relaxed);
+                    }
+                    in_callback.store(false, Ordering::Relaxed);
+                });
+            }
+        };
+
+        let other = {
+            let update = update.clone();
+            thread::spawn(move || update(3))
+        };
+
+        update(4);
+        other.join().unwrap();
+
+        // Each update changed the state, and each change was reported exactly once.
+        assert_eq!(2, state.generation());
+        assert_eq!(2, notified.load(Ordering::Relaxed));
+        assert_eq!(2, notifications.load(Ordering::Relaxed));
+    });
+}
+
+#[test]
+fn disabled_after_unregister() {
+    loom::model(|| {
+        let state = Arc::new(tli::EnableState::new());
+        let unregistered = Arc::new(AtomicBool::new(false));
+        enable(&state, 4);
+
+        let writer = {
+            let state = state.clone();
+            let unregistered = unregistered.clone();
+            thread::spawn(move || {
+                // A writer that observes the end of unregistration must see the
+                // provider as disabled. Before that, it may see either state.
+                let done = unregistered.load(Ordering::Acquire);
+                let enabled = state.enabled(tlg::Level::Informational, keyword_for(4));
+                if done {
+                    assert!(!enabled);
+                }
+            })
+        };
+
+        disable(&state);
+        unregistered.store(true, Ordering::Release);
+
+        writer.join().unwrap();
+        assert!(!state.enabled(tlg::Level::LogAlways, 0));
+        assert_eq!(2, state.generation());
+    });
+}
+
+#[test]
+fn enable_and_unregister_race() {
+    loom::model(|| {
+        let state = Arc::new(tli::EnableState::new());
+        enable(&state, 1);
+
+        let enabler = {
+            let state = state.clone();
+            thread::spawn(move || enable(&state, 5))
+        };
+
+        disable(&state);
+        enabler.join().unwrap();
+
+        // Both updates changed the state, in one order or the other.
+        let snap = state.snapshot();
+        assert_eq!(3, snap.generation);
+        assert!(snap.level == -1 || snap.level == 5);
+        assert_eq!(keyword_for(5), snap.keyword_any);
+        assert_eq!(keyword_for(5), snap.keyword_all);
+    });
+}
+
+/// Addresses of the C/C++ providers used by the adoption models.
+const CPP_1: usize = 0x1000;
+const CPP_2: usize = 0x2000;
+const CPP_3: usize = 0x3000;
+
+/// The enable callbacks that the C/C++ providers had before they were adopted.
+const CHAINED_1: usize = 0x1100;
+const CHAINED_2: usize = 0x2200;
+
+/// A context for the adoption models. `data` is written by the model after the
+/// adopter has been removed, so loom reports a race if a dispatch can still use the
+/// adopter at that point.
+struct TestAdopter {
+    link: tli::AdopterLink,
+    state: tli::EnableState,
+    data: UnsafeCell<u32>,
+}
+
+unsafe impl Sync for TestAdopter {}
+
+impl TestAdopter {
+    fn new() -> Self {
+        return Self {
+            link: tli::AdopterLink::new(),
+            state: tli::EnableState::new(),
+            data: UnsafeCell::new(1),
+        };
+    }
+}
+
+impl tli::Adopter for TestAdopter {
+    fn adopter_link(&self) -> &tli::AdopterLink {
+        return &self.link;
+    }
+}
+
+type AdoptionTable = tli::AdoptionTable<TestAdopter>;
+
+/// Adds `adopter` to `slot` as `ProviderContext::adopt` does.
+fn adopt(table: &AdoptionTable, slot: usize, adopter: &TestAdopter, chained: usize) {
+    let _dispatch = table.lock_dispatch(slot);
+    assert!(table.add(slot, adopter, || chained));
+}
+
+#[test]
+fn remove_waits_for_dispatch() {
+    loom::model(|| {
+        let table = Arc::new(AdoptionTable::new());
+        let adopter = Arc::new(TestAdopter::new());
+        let slot = table.bind(CPP_1).unwrap();
+        adopt(&table, slot, &adopter, CHAINED_1);
+
+        let callback = {
+            let table = table.clone();
+            thread::spawn(move || unsafe {
+                table.dispatch(
+                    slot,
+                    |adopter| assert_eq!(1, adopter.data.with(|data| *data)),
+                    |chained| assert_eq!(CHAINED_1, chained),
+                );
+            })
+        };
+
+        // After remove returns, no dispatch may use the adopter (e.g. after it has
+        // been unregistered and freed).
+        table.remove(slot, &adopter, |chained| assert_eq!(CHAINED_1, chained));
+        adopter.data.with_mut(|data| unsafe { *data = 0 });
+
+        callback.join().unwrap();
+    });
+}
+
+#[test]
+fn late_callback_not_rebound() {
+    loom::model(|| {
+        let table = Arc::new(AdoptionTable::new());
+        let adopter_1 = Arc::new(TestAdopter::new());
+        let adopter_2 = Arc::new(TestAdopter::new());
+
+        // CPP_1 is adopted and unadopted. ETW read the slot's callback before it was
+        // restored, so a callback for CPP_1 is delivered through the slot late.
+        let slot_1 = table.bind(CPP_1).unwrap();
+        adopt(&table, slot_1, &adopter_1, CHAINED_1);
+        table.remove(slot_1, &adopter_1, |chained| assert_eq!(CHAINED_1, chained));
+
+        let late_callback = {
+            let table = table.clone();
+            thread::spawn(move || unsafe {
+                table.dispatch(
+                    slot_1,
+                    |_| panic!("late callback reached an adopter"),
+                    |chained| assert_eq!(CHAINED_1, chained),
+                );
+            })
+        };
+
+        // Other C/C++ providers are adopted while the late callback runs, until the
+        // table is full. None of them may get CPP_1's slot, even though it has no
+        // adopters.
+        for other in 1..tli::ADOPTION_SLOTS - 1 {
+            assert!(table.bind(CPP_2 + other).is_some());
+        }
+        let slot_2 = table.bind(CPP_2).unwrap();
+        assert_ne!(slot_1, slot_2);
+        adopt(&table, slot_2, &adopter_2, CHAINED_2);
+        assert_eq!(None, table.bind(CPP_3));
+
+        late_callback.join().unwrap();
+        assert_eq!(Some(slot_1), table.find(CPP_1));
+        assert_eq!(Some(slot_1), table.bind(CPP_1));
+    });
+}
+
+#[test]
+fn adopt_state_not_overwritten() {
+    loom::model(|| {
+        let table = Arc::new(AdoptionTable::new());
+        let adopter = Arc::new(TestAdopter::new());
+        let cpp_level = Arc::new(AtomicU8::new(2)); // The C/C++ provider's level.
+        let slot = table.bind(CPP_1).unwrap();
+
+        // ETW enables the C/C++ provider at level 5. The C/C++ provider's original
+        // callback (chained) updates its level.
+        let callback = {
+            let table = table.clone();
+            let cpp_level = cpp_level.clone();
+            thread::spawn(move || unsafe {
+                table.dispatch(
+                    slot,
+                    |adopter| enable(&adopter.state, 5),
+                    |_| cpp_level.store(5, Ordering::Relaxed),
+                );
+            })
+        };
+
+        // Adopt, then deliver the C/C++ provider's current level, as
+        // ProviderContext::adopt does.
+        {
+            let _dispatch = table.lock_dispatch(slot);
+            assert!(table.add(slot, &adopter, || CHAINED_1));
+            enable(&adopter.state, cpp_level.load(Ordering::Relaxed));
+        }
+
+        callback.join().unwrap();
+
+        // The level delivered by adopt never overwrites the newer level.
+        assert_eq!(5, adopter.state.snapshot().level);
+    });
+}
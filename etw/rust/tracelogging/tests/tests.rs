@@ -22,6 +22,28 @@ fn guid() {
 
     assert_eq!(Guid::from_name("myprovider"), myprovider);
     assert_eq!(Guid::from_name("MYPROVIDER"), myprovider);
+
+    // UUIDv5 (values from Python's uuid.uuid5).
+    assert_eq!(
+        Guid::from_name_with_namespace(&Guid::NAMESPACE_DNS, "python.org"),
+        Guid::from_u128(&0x886313e1_3b8a_5372_9b90_0c9aee199e5d)
+    );
+    assert_eq!(
+        Guid::from_name_with_namespace(&Guid::NAMESPACE_URL, "https://example.com/"),
+        Guid::from_u128(&0xdd2c1780_811a_5296_81c5_178a0ef488bc)
+    );
+    assert_eq!(
+        Guid::from_name_with_namespace(&Guid::NAMESPACE_OID, ""),
+        Guid::from_u128(&0x0a68eb57_c88a_5f34_9e9d_27f85e68af4f)
+    );
+    assert_eq!(
+        Guid::from_name_with_namespace(&Guid::NAMESPACE_X500, "CN=Test"),
+        Guid::from_u128(&0x5e2003ce_30b7_5e3b_8c3a_a164acc5b21e)
+    );
+    assert_ne!(
+        Guid::from_name_with_namespace(&myprovider, "name"),
+        Guid::from_name_with_namespace(&myprovider, "Name")
+    );
     assert_eq!(
         Guid::from_fields(
             0xa3a2a1a0,
@@ -133,6 +155,194 @@ fn guid_new() {
     assert_ne!(Guid::new(), Guid::zero());
 }
 
+#[test]
+fn ffi_layout() {
+    use tlg::EventDescriptor;
+    use tlg::Guid;
+    use windows::core::GUID;
+
+    // Same layout as the Windows EVENT_DESCRIPTOR structure.
+    #[repr(C)]
+    #[allow(non_snake_case)]
+    struct EVENT_DESCRIPTOR {
+        Id: u16,
+        Version: u8,
+        Channel: u8,
+        Level: u8,
+        Opcode: u8,
+        Task: u16,
+        Keyword: u64,
+    }
+
+    let g = Guid::from_u128(&0xa3a2a1a0_b1b0_c1c0_d7d6_d5d4d3d2d1d0);
+    let v = unsafe { &*(g.as_win32_ptr() as *const GUID) };
+    assert_eq!(*v, GUID::from_u128(g.to_u128()));
+    assert_eq!(g.as_win32_ptr(), &g as *const Guid as *const _);
+
+    let d = EventDescriptor::from_parts(
+        0x1234,
+        5,
+        tlg::Channel::TraceLogging,
+        tlg::Level::Warning,
+        tlg::Opcode::Start,
+        0x5678,
+        0x123456789ABCDEF0,
+    );
+    let v = unsafe { &*(d.as_win32_ptr() as *const EVENT_DESCRIPTOR) };
+    assert_eq!(v.Id, 0x1234);
+    assert_eq!(v.Version, 5);
+    assert_eq!(v.Channel, 11);
+    assert_eq!(v.Level, 3);
+    assert_eq!(v.Opcode, 1);
+    assert_eq!(v.Task, 0x5678);
+    assert_eq!(v.Keyword, 0x123456789ABCDEF0);
+}
+
+#[test]
+fn enum_names() {
+    use std::str::FromStr;
+
+    assert_eq!("Verbose", tlg::Level::Verbose.to_string());
+    assert_eq!(Some("Verbose"), tlg::Level::Verbose.as_str());
+    assert_eq!("6", tlg::Level::from_int(6).to_string());
+    assert_eq!(None, tlg::Level::from_int(6).as_str());
+    assert_eq!(Ok(tlg::Level::Warning), tlg::Level::from_str("Warning"));
+    assert_eq!(Ok(tlg::Level::from_int(6)), "6".parse::<tlg::Level>());
+    assert_eq!(Err(tlg::ParseEnumError), "warning".parse::<tlg::Level>());
+    assert_eq!(Err(tlg::ParseEnumError), "256".parse::<tlg::Level>());
+    assert_eq!(Ok(tlg::Level::Warning), tlg::Level::try_from("Warning"));
+    assert_eq!(Err(tlg::ParseEnumError), tlg::Level::try_from("warning"));
+
+    assert_eq!("TraceLogging", tlg::Channel::TraceLogging.to_string());
+    assert_eq!(
+        Some(tlg::Channel::TraceClassic),
+        tlg::Channel::from_name("TraceClassic")
+    );
+
+    assert_eq!("ActivityStart", tlg::Opcode::Start.to_string());
+    assert_eq!(
+        Some(tlg::Opcode::DC_Stop),
+        tlg::Opcode::from_name("DC_Stop")
+    );
+    assert_eq!(
+        Some("ReservedOpcode255"),
+        tlg::Opcode::from_int(255).as_str()
+    );
+
+    assert_eq!("BinaryC", tlg::InType::BinaryC.to_string());
+    assert_eq!(
+        Some(tlg::InType::HexSize),
+        tlg::InType::from_name("HexSize")
+    );
+    assert_eq!(
+        None,
+        tlg::InType::from_int(tlg::InType::VariableCountFlag).as_str()
+    );
+
+    assert_eq!("Win32Error", tlg::OutType::Win32Error.to_string());
+    assert_eq!(Some(tlg::OutType::IPv6), tlg::OutType::from_name("IPv6"));
+
+    assert_eq!(
+        "CaptureState",
+        tlg::EventControlCode::CaptureState.to_string()
+    );
+    assert_eq!(
+        Some(tlg::EventControlCode::DisableProvider),
+        tlg::EventControlCode::from_name("DisableProvider")
+    );
+    assert_eq!(tlg::EventControlCode::EnableProvider, 1u32.into());
+    assert_eq!(2u32, tlg::EventControlCode::CaptureState.into());
+    assert_eq!("3", tlg::EventControlCode::from_int(3).to_string());
+    for i in [0, 1, 2, 3, u32::MAX] {
+        let code = tlg::EventControlCode::from_int(i);
+        assert_eq!(Ok(code), code.to_string().parse());
+    }
+
+    for i in 0..=255u8 {
+        let level = tlg::Level::from_int(i);
+        assert_eq!(Ok(level), level.to_string().parse());
+        let channel = tlg::Channel::from_int(i);
+        assert_eq!(Ok(channel), channel.to_string().parse());
+        let opcode = tlg::Opcode::from_int(i);
+        assert_eq!(Ok(opcode), opcode.to_string().parse());
+        let intype = tlg::InType::from(i);
+        assert_eq!(Ok(intype), intype.to_string().parse());
+        let outtype = tlg::OutType::from(i);
+        assert_eq!(Ok(outtype), outtype.to_string().parse());
+    }
+}
+
+#[test]
+fn intype_sizes() {
+    use tlg::InType;
+
+    assert_eq!(Some(1), InType::U8.fixed_size());
+    assert_eq!(Some(2), InType::I16.fixed_size());
+    assert_eq!(Some(4), InType::Bool32.fixed_size());
+    assert_eq!(Some(8), InType::FileTime.fixed_size());
+    assert_eq!(Some(16), InType::SystemTime.fixed_size());
+    assert_eq!(Some(16), InType::Guid.fixed_size());
+    assert_eq!(
+        Some(core::mem::size_of::<usize>()),
+        InType::HexSize.fixed_size()
+    );
+    assert_eq!(
+        Some(4),
+        InType::from_int(InType::U32.as_int() | InType::VariableCountFlag).fixed_size()
+    );
+
+    for i in 0..=InType::TypeMask {
+        let intype = InType::from_int(i);
+        let kinds = intype.fixed_size().is_some() as u8
+            + intype.is_counted() as u8
+            + intype.is_nul_terminated() as u8;
+        match intype {
+            InType::Invalid | InType::_HexSize_PlatformSpecific | InType::Sid | InType::Struct => {
+                assert_eq!(0, kinds, "{}", intype)
+            }
+            _ if intype.as_str().is_none() => assert_eq!(0, kinds, "{}", intype),
+            _ => assert_eq!(1, kinds, "{}", intype),
+        }
+    }
+
+    assert!(InType::Str8.is_counted());
+    assert!(InType::BinaryC.is_counted());
+    assert!(!InType::CStr8.is_counted());
+    assert!(InType::CStr16.is_nul_terminated());
+    assert!(!InType::Str16.is_nul_terminated());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn enum_serde() {
+    use serde::de::value::{Error, StrDeserializer, U64Deserializer};
+    use serde::de::IntoDeserializer;
+    use serde::Deserialize;
+
+    fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T, Error> {
+        let deserializer: StrDeserializer<Error> = s.into_deserializer();
+        return T::deserialize(deserializer);
+    }
+
+    fn from_u64<'a, T: Deserialize<'a>>(v: u64) -> Result<T, Error> {
+        let deserializer: U64Deserializer<Error> = v.into_deserializer();
+        return T::deserialize(deserializer);
+    }
+
+    assert_eq!(tlg::Level::Warning, from_str("Warning").unwrap());
+    assert_eq!(tlg::Level::from_int(6), from_str("6").unwrap());
+    assert_eq!(tlg::Level::Verbose, from_u64(5).unwrap());
+    assert!(from_str::<tlg::Level>("warning").is_err());
+    assert!(from_u64::<tlg::Level>(256).is_err());
+    assert_eq!(
+        tlg::Channel::TraceLogging,
+        from_str("TraceLogging").unwrap()
+    );
+    assert_eq!(tlg::Opcode::Start, from_str("ActivityStart").unwrap());
+    assert_eq!(tlg::InType::Str8, from_str("Str8").unwrap());
+    assert_eq!(tlg::OutType::Json, from_str("Json").unwrap());
+}
+
 #[test]
 fn meta_as_bytes() {
     let x = 47i32;
@@ -330,6 +540,63 @@ fn define_provider() {
     assert!(!PROV.enabled(tlg::Level::LogAlways, 0));
     PROV.raw_meta();
 
+    #[cfg(feature = "write_retries")]
+    {
+        assert_eq!(0, PROV.write_retries());
+        PROV.set_write_retries(1);
+        assert_eq!(1, PROV.write_retries());
+        PROV.set_write_retries(200);
+        assert_eq!(3, PROV.write_retries());
+        PROV.set_write_retries(0);
+        assert_eq!(0, PROV.write_retries());
+    }
+
+    #[cfg(feature = "keyword_levels")]
+    {
+        assert_eq!(None, PROV.keyword_level(0x4));
+        PROV.set_keyword_level(0x4, Some(tlg::Level::Verbose));
+        PROV.set_keyword_level(0x8, Some(tlg::Level::Warning));
+        PROV.set_default_keyword_level(Some(tlg::Level::Informational));
+        assert_eq!(Some(tlg::Level::Verbose), PROV.keyword_level(0x4));
+        assert_eq!(Some(tlg::Level::Verbose), PROV.keyword_level(0xC));
+        assert_eq!(Some(tlg::Level::Warning), PROV.keyword_level(0x9));
+        assert_eq!(Some(tlg::Level::Informational), PROV.keyword_level(0x1));
+        assert_eq!(Some(tlg::Level::Informational), PROV.keyword_level(0));
+        PROV.set_keyword_level(0x4, None);
+        assert_eq!(Some(tlg::Level::Informational), PROV.keyword_level(0x4));
+        PROV.clear_keyword_levels();
+        assert_eq!(None, PROV.keyword_level(0x8));
+        assert_eq!(None, PROV.keyword_level(0));
+    }
+
+    fn skip_event_5(descriptor: &tlg::EventDescriptor) -> bool {
+        return descriptor.id != 5;
+    }
+
+    let desc5 = tlg::EventDescriptor {
+        id: 5,
+        ..tlg::EventDescriptor::new(tlg::Level::Verbose, 0)
+    };
+    let desc6 = tlg::EventDescriptor { id: 6, ..desc5 };
+    assert!(PROV.event_filter().is_none());
+    assert!(tli::provider_event_filter(&PROV, &desc5));
+    PROV.set_event_filter(skip_event_5);
+    assert!(PROV.event_filter().is_some());
+    assert!(!tli::provider_event_filter(&PROV, &desc5));
+    assert!(tli::provider_event_filter(&PROV, &desc6));
+    assert_eq!(0, tlg::write_event!(PROV, "Filtered", id_version(5, 0)));
+    PROV.clear_event_filter();
+    assert!(PROV.event_filter().is_none());
+
+    #[cfg(feature = "write_hook")]
+    {
+        fn hook(_descriptor: &tlg::EventDescriptor, _meta_len: usize, _data_len: usize) {}
+        assert!(PROV.pre_write_hook().is_none());
+        PROV.set_pre_write_hook(Some(hook));
+        assert!(PROV.pre_write_hook().is_some());
+        PROV.set_pre_write_hook(None);
+    }
+
     tlg::define_provider!(PROV1, "TestProvider1");
     assert_eq!("TestProvider1", PROV1.name());
     assert_eq!(&tlg::Guid::from_name("TestProvider1"), PROV1.id());
@@ -364,45 +631,2117 @@ fn define_provider() {
         &tlg::Guid::from_u128(&0x632a8743_6a0d_456f_9ae8_a26febe2dbc3),
         PROV4.id()
     );
+
+    const PROV5_ID: &str = "{d93270c9-8144-4209-99a6-3acd39aaae71}";
+    tlg::define_provider!(PROV5, "TestProvider5", id(PROV5_ID));
+    assert_eq!("TestProvider5", PROV5.name());
+    assert_eq!(
+        &tlg::Guid::from_u128(&0xd93270c9_8144_4209_99a6_3acd39aaae71),
+        PROV5.id()
+    );
+
+    tlg::define_provider!(
+        PROV6,
+        "TestProvider6",
+        id(concat!("632a8743", "6a0d456f", "9AE8A26FEBE2DBC3")),
+        group_id("e3c21ff5-164e-4cbb-b08c-d47e2dcf9197")
+    );
+    assert_eq!("TestProvider6", PROV6.name());
+    assert_eq!(
+        &tlg::Guid::from_u128(&0x632a8743_6a0d_456f_9ae8_a26febe2dbc3),
+        PROV6.id()
+    );
+
+    tlg::define_provider!(
+        PROV7,
+        "TestProvider7",
+        group_id("00000000-0000-0000-0000-000000000000"),
+        trait(128, b"ab"),
+        trait(0x02, b"\xFF")
+    );
+    assert_eq!("TestProvider7", PROV7.name());
+    assert_eq!(
+        PROV7.raw_meta(),
+        b"\x2C\x00TestProvider7\x00\
+          \x13\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+          \x05\x00\x80ab\
+          \x04\x00\x02\xFF"
+    );
+
+    let meta = PROV7.metadata();
+    assert_eq!("TestProvider7", meta.name());
+    assert_eq!(Some(tlg::Guid::zero()), meta.group_id());
+    assert_eq!(3, meta.traits().count());
+    assert_eq!(
+        Some(tlg::ProviderTrait {
+            trait_type: 2,
+            data: b"\xFF"
+        }),
+        meta.traits().last()
+    );
+    assert_eq!(None, PROV1.metadata().group_id());
+    assert_eq!(0, PROV1.metadata().traits().count());
+    assert_eq!(
+        Some(tlg::Guid::from_u128(
+            &0xe3c21ff5_164e_4cbb_b08c_d47e2dcf9197
+        )),
+        PROV2.metadata().group_id()
+    );
+
+    // Malformed metadata: wrong size, missing NUL, truncated trait.
+    assert!(tlg::ProviderMetadata::parse(b"\x04\x00A\x00\x00").is_none());
+    assert!(tlg::ProviderMetadata::parse(b"\x03\x00A").is_none());
+    assert!(tlg::ProviderMetadata::parse(b"\x07\x00A\x00\x05\x00\x80").is_none());
+    assert!(tlg::ProviderMetadata::parse(b"\x07\x00A\x00\x03\x00\x80").is_some());
+
+    // RFC 4122 name-based id: uuid5(NAMESPACE_DNS, "www.example.com").
+    tlg::define_provider!(
+        PROV8,
+        "www.example.com",
+        id_from_name_v5("6ba7b810-9dad-11d1-80b4-00c04fd430c8")
+    );
+    assert_eq!("www.example.com", PROV8.name());
+    assert_eq!(
+        &tlg::Guid::from_u128(&0x2ed6657d_e927_568b_95e1_2665a8aea6a2),
+        PROV8.id()
+    );
 }
 
 #[test]
-#[should_panic]
-fn provider_panic() {
-    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+fn provider_keywords() {
+    tlg::define_provider!(
+        PROV,
+        "TraceLoggingKeywordsTest",
+        keywords(NETWORK = 0x2, STORAGE = 0x4),
+        keywords(DIAG = 0x8000_0000_0000u64),
+    );
+    assert_eq!(0x2, PROV::KEYWORD_NETWORK);
+    assert_eq!(0x4, PROV::KEYWORD_STORAGE);
+    assert_eq!(0x8000_0000_0000, PROV::KEYWORD_DIAG);
+    assert_eq!(
+        &[
+            ("NETWORK", 0x2),
+            ("STORAGE", 0x4),
+            ("DIAG", 0x8000_0000_0000)
+        ],
+        PROV::KEYWORDS
+    );
+
     let _u = Unregister(&PROV);
     unsafe { PROV.register() };
-    unsafe { PROV.register() };
+    tlg::write_event!(
+        PROV,
+        "Keywords",
+        keyword(PROV::KEYWORD_NETWORK),
+        keyword(PROV::KEYWORD_STORAGE)
+    );
+    assert!(!PROV.enabled(tlg::Level::Verbose, PROV::KEYWORD_NETWORK));
 }
 
 #[test]
-fn write_event() {
-    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+fn provider_default_keyword() {
+    tlg::define_provider!(PROV1, "TraceLoggingDefaultKeywordTest1");
+    tlg::define_provider!(
+        PROV2,
+        "TraceLoggingDefaultKeywordTest2",
+        default_keyword(0x10),
+    );
+    tlg::define_provider!(
+        PROV3,
+        "TraceLoggingDefaultKeywordTest3",
+        default_keyword(0),
+        warn_default_keyword(),
+    );
+    assert_eq!(0x1, PROV1::DEFAULT_KEYWORD);
+    assert_eq!(0x10, PROV2::DEFAULT_KEYWORD);
+    #[allow(deprecated)]
+    let default3 = PROV3::DEFAULT_KEYWORD;
+    assert_eq!(0x0, default3);
+
+    tlg::write_event!(PROV1, "Default");
+    tlg::write_event!(PROV1, "None", keyword_none());
+    tlg::write_event!(PROV2, "Default");
+    tlg::write_event!(PROV2, "Explicit", keyword(0x4));
+    tlg::write_event!(PROV3, "None", keyword_none());
+    tlg::write_event!(PROV3, "Explicit", keyword(0x4));
+
+    #[cfg(feature = "export_schema")]
+    {
+        let keyword = |prov: &'static tlg::Provider, name: &str| {
+            prov.event_schemas()
+                .find(|schema| schema.name() == name)
+                .unwrap()
+                .descriptor()
+                .keyword
+        };
+        assert_eq!(0x1, keyword(&PROV1, "Default"));
+        assert_eq!(0x0, keyword(&PROV1, "None"));
+        assert_eq!(0x10, keyword(&PROV2, "Default"));
+        assert_eq!(0x4, keyword(&PROV2, "Explicit"));
+        assert_eq!(0x0, keyword(&PROV3, "None"));
+    }
+}
+
+#[test]
+fn provider_keywords_mask() {
+    tlg::define_provider!(PROV1, "TraceLoggingKeywordsMaskTest1");
+    tlg::define_provider!(
+        PROV2,
+        "TraceLoggingKeywordsMaskTest2",
+        keywords(NETWORK = 0x2, STORAGE = 0x4),
+        keywords_mask(0x00FF),
+    );
+    tlg::define_provider!(
+        PROV3,
+        "TraceLoggingKeywordsMaskTest3",
+        default_keyword(0x100),
+        keywords_mask(0xFF00),
+    );
+    assert_eq!(u64::MAX, PROV1::KEYWORDS_MASK);
+    assert_eq!(
+        0x00FF | tlg::Provider::STATE_KEYWORD | tlg::Provider::MARKER_KEYWORD,
+        PROV2::KEYWORDS_MASK
+    );
+    assert_eq!(
+        0xFF00 | tlg::Provider::STATE_KEYWORD | tlg::Provider::MARKER_KEYWORD,
+        PROV3::KEYWORDS_MASK
+    );
+
+    tlg::write_event!(PROV1, "Any", keyword(0x8000_0000_0000_0000));
+    tlg::write_event!(PROV2, "Default");
+    tlg::write_event!(PROV2, "None", keyword_none());
+    tlg::write_event!(
+        PROV2,
+        "Named",
+        keyword(PROV2::KEYWORD_NETWORK),
+        keyword(0x80)
+    );
+    tlg::write_event!(PROV2, "State", keyword(tlg::Provider::STATE_KEYWORD));
+    tlg::write_event!(PROV3, "Default");
+    tlg::write_event!(PROV3, "Explicit", keyword(0x8000));
+}
+
+#[test]
+fn register_errors() {
+    tlg::define_provider!(PROV, "TraceLoggingRegisterErrorTest");
+    let _u = Unregister(&PROV);
+
+    assert_eq!(Ok(()), unsafe { PROV.try_register() });
+    PROV.unregister();
+
+    // Failure injection is supported by the non-Windows implementation.
+    if let tlg::NativeImplementation::Windows = tlg::NATIVE_IMPLEMENTATION {
+        return;
+    }
+
+    let error = tlg::RegisterError::EventRegister(8);
+    tlg::_internal::provider_inject_register_error(&PROV, Some(error));
+    assert_eq!(Err(error), unsafe { PROV.try_register() });
+    assert!(!PROV.is_registered());
+    assert_eq!(
+        "EventRegister failed with error 8",
+        std::format!("{}", error)
+    );
+
+    let error = tlg::RegisterError::SetTraits(87);
+    tlg::_internal::provider_inject_register_error(&PROV, Some(error));
+    assert_eq!(87, unsafe { PROV.register() });
+    assert!(!PROV.is_registered());
+
+    // The provider can be registered again after a failure.
+    assert_eq!(Ok(()), unsafe { PROV.try_register() });
+    PROV.unregister();
+
+    // Providers without traits support are still registered.
+    // (ERROR_NOT_SUPPORTED, or STATUS_NOT_SUPPORTED in kernel mode.)
+    tlg::_internal::provider_inject_register_error(
+        &PROV,
+        Some(tlg::RegisterError::SetTraits(
+            tlg::_internal::SET_INFORMATION_NOT_SUPPORTED,
+        )),
+    );
+    assert_eq!(Ok(()), unsafe {
+        PROV.try_register_with_callback(callback, 0)
+    });
+    PROV.unregister();
+
+    fn callback(_: &tlg::Guid, _: u32, _: tlg::Level, _: u64, _: u64, _: usize, _: usize) {}
+}
+
+#[test]
+fn register_options() {
+    tlg::define_provider!(PROV, "TraceLoggingRegisterOptionsTest");
+    let _u = Unregister(&PROV);
+
+    let options = tlg::RegisterOptions::new()
+        .callback(callback, 0x1234)
+        .busy_retries(2);
+    assert_eq!(2, options.busy_retry_limit());
+    assert_eq!(Some(0x1234), options.enable_callback().map(|c| c.1));
+    assert!(tlg::RegisterOptions::new().enable_callback().is_none());
+    assert_eq!(Ok(()), unsafe { PROV.register_with_options(&options) });
+    PROV.unregister();
+
+    // Failure injection is supported by the non-Windows implementation.
+    if let tlg::NativeImplementation::Windows = tlg::NATIVE_IMPLEMENTATION {
+        return;
+    }
+
+    // Injected errors affect one attempt, so a retry succeeds. The transient error
+    // codes are Win32 errors in user mode and NTSTATUS values in kernel mode.
+    let busy_code = if cfg!(feature = "kernel_mode") {
+        0x80000011 // STATUS_DEVICE_BUSY
+    } else {
+        170 // ERROR_BUSY
+    };
+    let busy = tlg::RegisterError::EventRegister(busy_code);
+    tlg::_internal::provider_inject_register_error(&PROV, Some(busy));
+    assert_eq!(Ok(()), unsafe { PROV.register_with_options(&options) });
+    PROV.unregister();
+
+    // No retries by default.
+    tlg::_internal::provider_inject_register_error(&PROV, Some(busy));
+    let result = unsafe { PROV.register_with_options(&tlg::RegisterOptions::new()) };
+    assert_eq!(Err(busy), result);
+    assert_eq!(busy_code, result.unwrap_err().code());
+
+    // Errors that are not transient are not retried.
+    let denied = tlg::RegisterError::EventRegister(5);
+    tlg::_internal::provider_inject_register_error(&PROV, Some(denied));
+    assert_eq!(Err(denied), unsafe { PROV.register_with_options(&options) });
+    assert!(!PROV.is_registered());
+
+    fn callback(_: &tlg::Guid, _: u32, _: tlg::Level, _: u64, _: u64, _: usize, _: usize) {}
+}
+
+#[test]
+fn adopt_registration() {
+    tlg::define_provider!(PROV, "TraceLoggingAdoptTest");
+    assert_eq!(87, unsafe {
+        PROV.adopt_registration(core::ptr::null_mut())
+    });
+    assert!(!PROV.is_registered());
+
+    #[cfg(all(windows, feature = "etw"))]
+    {
+        // Layout of the C/C++ _tlgProvider_t and _tlgProviderMetadata_t structures.
+        #[repr(C)]
+        struct TlgProvider {
+            level_plus1: u32,
+            provider_metadata_ptr: *const u8,
+            keyword_any: u64,
+            keyword_all: u64,
+            reg_handle: u64,
+            enable_callback: usize,
+            callback_context: usize,
+        }
+
+        // The C/C++ code updates the provider while it is adopted, so access it (and
+        // the provider id in its metadata) only through raw pointers.
+        let mut meta = [0u8; 19];
+        meta[1..17].copy_from_slice(PROV.id().as_bytes_raw());
+        let meta = meta.as_mut_ptr();
+        let mut cpp = TlgProvider {
+            level_plus1: 0,
+            provider_metadata_ptr: unsafe { meta.add(17) },
+            keyword_any: 0,
+            keyword_all: 0,
+            reg_handle: 0,
+            enable_callback: 0,
+            callback_context: 0,
+        };
+        let cpp = &mut cpp as *mut TlgProvider;
+        let cpp_ptr = cpp as *mut core::ffi::c_void;
+
+        // Not registered.
+        assert_eq!(6, unsafe { PROV.adopt_registration(cpp_ptr) });
+
+        // Different provider id.
+        unsafe {
+            (*cpp).reg_handle = 1;
+            *meta.add(1) ^= 1;
+        }
+        assert_eq!(87, unsafe { PROV.adopt_registration(cpp_ptr) });
+        assert!(!PROV.is_registered());
+
+        // Adopting replaces the callback but never the callback context, and the last
+        // unregister restores the original callback.
+        tlg::define_provider!(PROV2, "TraceLoggingAdoptTest");
+        let callback_fn: unsafe extern "system" fn(&tlg::Guid, u32, u8, u64, u64, usize, usize) =
+            callback;
+        let callback_address = callback_fn as usize;
+        unsafe {
+            *meta.add(1) ^= 1;
+            (*cpp).level_plus1 = 5; // Enabled at Level::Informational.
+            (*cpp).enable_callback = callback_address;
+            (*cpp).callback_context = 123;
+        }
+        assert_eq!(0, unsafe { PROV.adopt_registration(cpp_ptr) });
+        assert_eq!(0, unsafe { PROV2.adopt_registration(cpp_ptr) });
+        assert!(PROV.enabled(tlg::Level::Informational, 0));
+        assert!(PROV2.enabled(tlg::Level::Informational, 0));
+        unsafe {
+            assert_ne!(callback_address, (*cpp).enable_callback);
+            assert_eq!(123, (*cpp).callback_context);
+        }
+        PROV.unregister();
+        unsafe { assert_ne!(callback_address, (*cpp).enable_callback) };
+        PROV2.unregister();
+        unsafe {
+            assert_eq!(callback_address, (*cpp).enable_callback);
+            assert_eq!(123, (*cpp).callback_context);
+        }
+        assert!(!PROV.is_registered());
+        assert!(!PROV2.is_registered());
+
+        unsafe extern "system" fn callback(
+            _: &tlg::Guid,
+            _: u32,
+            _: u8,
+            _: u64,
+            _: u64,
+            _: usize,
+            _: usize,
+        ) {
+        }
+    }
+}
+
+#[test]
+fn write_raw() {
+    tlg::define_provider!(PROV, "TraceLoggingWriteRawTest");
+    let descriptor = tlg::EventDescriptor::new(tlg::Level::Verbose, 0x1);
+
+    // u16 size, tag, "Raw\0", "Value\0", InType::U32.
+    let meta = b"\x0E\x00\x00Raw\x00Value\x00\x08";
+    let value = 5u32.to_le_bytes();
+    let aid = tlg::Guid::from_name("activity");
+
+    // Unregistered provider: does nothing.
+    assert_eq!(0, PROV.write_raw(&descriptor, meta, &[&value], None, None));
 
     let _u = Unregister(&PROV);
     unsafe { PROV.register() };
+    assert_eq!(
+        0,
+        PROV.write_raw(&descriptor, meta, &[&value], Some(&aid), None)
+    );
+    assert_eq!(0, PROV.write_raw(&descriptor, &meta[..], &[], None, None));
 
-    let sample_guid = tlg::Provider::guid_from_name("sample");
-    let sample_filetime = 0x01d7ace794497cb5i64;
-    let sample_rusttime =
-        std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1671930123);
-    let sample_systemtime = [2022, 1, 1, 2, 3, 4, 5, 6];
-    let sample_sid = [1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0];
-    let sample_ipv4 = [127, 0, 0, 1];
-    let sample_ipv6 = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    // Metadata size must match.
+    assert_eq!(87, PROV.write_raw(&descriptor, &meta[..5], &[], None, None));
+    assert_eq!(87, PROV.write_raw(&descriptor, &meta[..1], &[], None, None));
 
-    tlg::write_event!(PROV, "Default");
+    // At most 126 data blocks.
+    let blocks = [&value[..]; 127];
+    assert_eq!(87, PROV.write_raw(&descriptor, meta, &blocks, None, None));
+}
+
+#[test]
+fn field_tags() {
+    tlg::define_field_tags!(
+        TAGS,
+        /// Field contains personal data.
+        /// Remove before sharing.
+        PII = 0x0100_0000,
+        HASHED = 0x0FFF_FFFF,
+        /** Escaped "quotes". */
+        QUOTED = 0x1,
+    );
+    assert_eq!(0x0100_0000, TAGS::PII);
+    assert_eq!(0x0FFF_FFFF, TAGS::HASHED);
+    assert_eq!(
+        vec![
+            (
+                "PII",
+                0x0100_0000,
+                "Field contains personal data.\nRemove before sharing."
+            ),
+            ("HASHED", 0x0FFF_FFFF, ""),
+            ("QUOTED", 0x1, "Escaped \"quotes\". "),
+        ],
+        Vec::from_iter(TAGS::TAGS.iter().map(|t| (t.name(), t.value(), t.doc())))
+    );
+    assert_eq!("HASHED", TAGS::TAGS.lookup(0x0FFF_FFFF).unwrap().name());
+    assert_eq!(0x1, TAGS::TAGS.lookup_name("QUOTED").unwrap().value());
+    assert!(TAGS::TAGS.lookup(0x2).is_none());
+    assert!(TAGS::TAGS.lookup_name("pii").is_none());
+    assert_eq!("PII (0x1000000)", TAGS::TAGS.as_slice()[0].to_string());
 
+    tlg::define_provider!(PROV, "TraceLoggingFieldTagsTest");
     tlg::write_event!(
         PROV,
-        "4v2o6t123c0l3k11",
-        id_version(4, 2),
-        opcode(6),
-        task(123),
-        channel(0),
-        level(3),
-        keyword(0x11),
+        "Tagged",
+        tag(TAGS::QUOTED),
+        str8("User", "user", tag(TAGS::PII)),
+        struct("Hashes", tag(TAGS::HASHED), { u32("Hash", &0) }),
+    );
+
+    #[cfg(feature = "export_schema")]
+    {
+        let schema = PROV.event_schemas().next().unwrap();
+        assert_eq!(TAGS::QUOTED, schema.tag());
+        assert_eq!(
+            vec![TAGS::PII, TAGS::HASHED, 0],
+            Vec::from_iter(schema.fields().map(|f| f.tag))
+        );
+    }
+}
+
+#[test]
+fn activity_scope() {
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let is_windows = matches!(
+        tlg::NATIVE_IMPLEMENTATION,
+        tlg::NativeImplementation::Windows
+    );
+    let value = 5u32;
+    let outer = tlg::activity_scope!(
+        PROV,
+        "Outer",
+        level(Informational),
+        keyword(0x2),
+        u32("Value", &value),
     );
+    let inner = tlg::activity_scope!(PROV, "Inner");
+    if is_windows {
+        assert_ne!(outer.activity_id(), inner.activity_id());
+        assert_eq!(outer.activity_id(), inner.related_id());
+        assert_eq!(
+            inner.activity_id(),
+            &tlg::Provider::current_thread_activity_id()
+        );
+    }
+
+    drop(inner);
+    if is_windows {
+        assert_eq!(
+            outer.activity_id(),
+            &tlg::Provider::current_thread_activity_id()
+        );
+    }
+
+    let related_id = *outer.related_id();
+    drop(outer);
+    assert_eq!(related_id, tlg::Provider::current_thread_activity_id());
+}
+
+#[test]
+fn emit_policy() {
+    let policy = tlg::EmitPolicy::new(3, 10);
+    let emitted: Vec<u64> = (0..40).filter_map(|_| policy.check()).collect();
+    assert_eq!(vec![1, 2, 3, 13, 23, 33], emitted);
+    assert_eq!(40, policy.count());
+    policy.reset();
+    assert_eq!(Some(1), policy.check());
+
+    let policy = tlg::EmitPolicy::new(2, 0);
+    let emitted: Vec<u64> = (0..40).filter_map(|_| policy.check()).collect();
+    assert_eq!(vec![1, 2], emitted);
+
+    let policy = tlg::EmitPolicy::new(0, 1);
+    assert_eq!(Some(1), policy.check());
+    assert_eq!(Some(2), policy.check());
+
+    tlg::define_provider!(PROV, "TraceLoggingEmitPolicyTest");
+    let mut emitted = Vec::new();
+    let mut results = Vec::new();
+    for i in 0..25u32 {
+        if let Some(n) = tlg::emit_policy!(first(2), then_every(10)) {
+            emitted.push(n);
+        }
+        results.push(tlg::emit_policy!(
+            first(2),
+            then_every(10),
+            PROV,
+            "Storm",
+            level(Warning),
+            u32("Index", &i),
+        ));
+    }
+    assert_eq!(vec![1, 2, 12, 22], emitted);
+    assert!(results.iter().all(|&result| result == 0));
+
+    #[cfg(feature = "export_schema")]
+    {
+        let schema = PROV.event_schemas().next().unwrap();
+        let names = Vec::from_iter(schema.fields().map(|field| field.name));
+        assert_eq!(vec!["Occurrences", "Index"], names);
+        assert_eq!(tlg::InType::U64, schema.fields().next().unwrap().in_type);
+    }
+}
+
+#[test]
+fn mark() {
+    tlg::define_provider!(PROV, "TraceLoggingMarkTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let phase = 2u32;
+    assert_eq!(0, tlg::mark!(PROV, "PhaseStart", u32("Phase", &phase)));
+    assert_eq!(0, tlg::mark!(PROV, "PhaseStop"));
+
+    let first = tli::next_mark_sequence();
+    assert!(first >= 1);
+    assert_eq!(first + 1, tli::next_mark_sequence());
+    if let tlg::NativeImplementation::Other = tlg::NATIVE_IMPLEMENTATION {
+        assert_eq!(0, tli::performance_counter());
+    }
+
+    #[cfg(feature = "export_schema")]
+    {
+        let start = PROV
+            .event_schemas()
+            .find(|s| s.name() == "PhaseStart")
+            .unwrap();
+        assert_eq!(tlg::Provider::MARKER_KEYWORD, start.descriptor().keyword);
+        assert_eq!(tlg::Level::LogAlways, start.descriptor().level);
+        let names = Vec::from_iter(start.fields().map(|field| field.name));
+        assert_eq!(vec!["MarkSequence", "MarkQpc", "Phase"], names);
+    }
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn schema_artifact() {
+    tlg::define_provider!(PROV, "TraceLoggingSchemaArtifactTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    tlg::write_event!(
+        PROV,
+        "ArtifactEvent",
+        id_version(5, 1),
+        level(Warning),
+        keyword(0x12),
+        u32("Value", &1),
+    );
+
+    let mut artifact = String::new();
+    tlg::write_schema_artifact(&mut artifact).unwrap();
+    assert!(artifact.starts_with("tracelogging-schema 1\n"));
+
+    let provider_line = artifact
+        .lines()
+        .find(|line| line.starts_with("provider\tTraceLoggingSchemaArtifactTest\t"))
+        .unwrap();
+    assert_eq!(
+        vec![
+            "provider",
+            "TraceLoggingSchemaArtifactTest",
+            &format!("{:?}", PROV.id()),
+            &format!("{}::PROV", module_path!()),
+        ],
+        provider_line.split('\t').collect::<Vec<_>>()
+    );
+
+    let schema = PROV.event_schemas().next().unwrap();
+    let meta_hex: String = schema
+        .raw_meta()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let event_line = artifact
+        .lines()
+        .find(|line| line.starts_with("event\tTraceLoggingSchemaArtifactTest\t"))
+        .unwrap();
+    let values: Vec<&str> = event_line.split('\t').collect();
+    assert_eq!(
+        ["5", "1", "11", "3", "0", "0", "0x12", meta_hex.as_str()],
+        values[3..]
+    );
+}
+
+#[test]
+fn activity_start() {
+    tlg::define_provider!(PROV, "TraceLoggingActivityStartTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let is_windows = matches!(
+        tlg::NATIVE_IMPLEMENTATION,
+        tlg::NativeImplementation::Windows
+    );
+    let parent = tlg::Guid::from_name("parent");
+    let value = 5u32;
+    let (result, activity_id) = tlg::write_event!(
+        PROV,
+        "Start",
+        activity(start),
+        related_id(&parent),
+        u32("Value", &value),
+    );
+    assert_eq!(0, result);
+
+    let (_, activity_id2) = tlg::write_event!(PROV, "Start2", activity(start), opcode(Info));
+    if is_windows {
+        assert_ne!(tlg::Guid::zero(), activity_id);
+        assert_ne!(activity_id, activity_id2);
+    }
+
+    let result: u32 = tlg::write_event!(PROV, "Stop", opcode(Stop), activity_id(&activity_id));
+    assert_eq!(0, result);
+
+    #[cfg(feature = "export_schema")]
+    {
+        let opcode = |name: &str| {
+            PROV.event_schemas()
+                .find(|schema| schema.name() == name)
+                .unwrap()
+                .descriptor()
+                .opcode
+        };
+        assert_eq!(tlg::Opcode::ActivityStart, opcode("Start"));
+        assert_eq!(tlg::Opcode::Info, opcode("Start2"));
+    }
+}
+
+#[cfg(feature = "alloc_stats")]
+#[test]
+fn alloc_stats() {
+    use std::alloc::{GlobalAlloc, Layout, System};
+
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+
+    assert_eq!(0, tlg::AllocStats::size_class(0));
+    assert_eq!(0, tlg::AllocStats::size_class(16));
+    assert_eq!(1, tlg::AllocStats::size_class(17));
+    assert_eq!(6, tlg::AllocStats::size_class(65536));
+    assert_eq!(7, tlg::AllocStats::size_class(65537));
+    assert_eq!(7, tlg::AllocStats::size_class(usize::MAX));
+
+    let allocator = tlg::StatsAllocator::new(System, &PROV, tlg::Level::Verbose, 0x1, 2);
+    assert_eq!(tlg::AllocStats::default(), allocator.stats());
+
+    unsafe {
+        let small = Layout::from_size_align(10, 8).unwrap();
+        let large = Layout::from_size_align(5000, 8).unwrap();
+        let p1 = allocator.alloc(small);
+        let p2 = allocator.alloc_zeroed(large);
+        let p1 = allocator.realloc(p1, small, 100);
+        allocator.dealloc(p1, Layout::from_size_align(100, 8).unwrap());
+        allocator.dealloc(p2, large);
+    }
+
+    let stats = allocator.stats();
+    assert_eq!([1, 0, 1, 0, 0, 1, 0, 0], stats.alloc_count);
+    assert_eq!([10, 0, 100, 0, 0, 5000, 0, 0], stats.alloc_bytes);
+    assert_eq!(3, stats.free_count);
+    assert_eq!(5110, stats.free_bytes);
+
+    unsafe { PROV.register() };
+    assert_eq!(0, allocator.write_stats());
+    PROV.unregister();
+}
+
+#[cfg(feature = "log-adapter")]
+#[test]
+fn log_adapter() {
+    use log::Log;
+
+    assert_eq!(tlg::Level::Error, tlg::level_from_log(log::Level::Error));
+    assert_eq!(tlg::Level::Warning, tlg::level_from_log(log::Level::Warn));
+    assert_eq!(
+        tlg::Level::Informational,
+        tlg::level_from_log(log::Level::Info)
+    );
+    assert_eq!(tlg::Level::Verbose, tlg::level_from_log(log::Level::Debug));
+    assert_eq!(tlg::Level::Verbose, tlg::level_from_log(log::Level::Trace));
+
+    tlg::define_provider!(PROV, "TraceLoggingLogTest");
+    static LOGGER: tlg::LogAdapter = tlg::LogAdapter::new(&PROV, 0x10);
+    assert_eq!(0x10, LOGGER.keyword());
+    assert_eq!("TraceLoggingLogTest", LOGGER.provider().name());
+
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let metadata = log::Metadata::builder()
+        .level(log::Level::Info)
+        .target("tests")
+        .build();
+    assert_eq!(
+        PROV.enabled(tlg::Level::Informational, 0x10),
+        LOGGER.enabled(&metadata)
+    );
+
+    let long = "\u{e9}".repeat(1000);
+    LOGGER.log(
+        &log::Record::builder()
+            .args(format_args!("Formatted {} {}", 1, long))
+            .level(log::Level::Warn)
+            .target("tests")
+            .module_path(Some(module_path!()))
+            .file(Some(file!()))
+            .line(Some(line!()))
+            .build(),
+    );
+    LOGGER.log(
+        &log::Record::builder()
+            .args(format_args!("Static"))
+            .level(log::Level::Trace)
+            .build(),
+    );
+    LOGGER.flush();
+}
+
+#[test]
+fn debug_ir() {
+    tlg::define_provider!(PROV, "TraceLoggingDebugIrTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    // debug_ir() adds a string constant to the expansion and does not change the event.
+    let result = tlg::write_event!(
+        PROV,
+        "Ir\"Event",
+        debug_ir(),
+        level(Warning),
+        keyword(0x3),
+        tag(0x12),
+        u32("Id", &1, format(Hex)),
+        str8_json("Json", "{}", tag(0x20)),
+        u16_array("Array", 2, &[1, 2]),
+        binary_capped("Capped", &[1, 2, 3], 2),
+        struct("Struct", {
+            i32_slice("Slice", &[1]),
+        }),
+    );
+    assert_eq!(0, result);
+}
+
+#[test]
+fn sample_rate() {
+    tlg::define_provider!(PROV, "TraceLoggingSampleRateTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    const RATE: u64 = 10;
+    for i in 0..100u32 {
+        let result = tlg::write_event!(PROV, "Sampled", sample_rate(RATE), u32("I", &i));
+        assert_eq!(0, result);
+    }
+
+    let result = tlg::write_event!(
+        PROV,
+        "SampledEveryTime",
+        level(Warning),
+        sample_rate(1),
+        keyword(0x2),
+        str8("S", "abc"),
+    );
+    assert_eq!(0, result);
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn meta_dedup() {
+    tlg::define_provider!(PROV, "TraceLoggingMetaDedupTest");
+
+    // Two call sites with identical schemas.
+    if PROV.is_registered() {
+        tlg::write_event!(
+            PROV,
+            "Dup",
+            tag(0x12),
+            u32("Id", &1),
+            u8_array("A", 2, &[1, 2])
+        );
+        tlg::write_event!(
+            PROV,
+            "Dup",
+            tag(0x12),
+            u32("Id", &2),
+            u8_array("A", 2, &[3, 4])
+        );
+    }
+
+    let schemas = Vec::from_iter(PROV.event_schemas());
+    assert_eq!(2, schemas.len());
+
+    // Whether the identical constants share one allocation is up to the compiler and
+    // linker, so only the bytes are compared.
+    assert_eq!(schemas[0].raw_meta(), schemas[1].raw_meta());
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn binary_capped() {
+    tlg::define_provider!(PROV, "TraceLoggingBinaryCappedTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    const MAX_LEN: u16 = 4;
+    let bytes = [1u8, 2, 3, 4, 5, 6];
+    let result = tlg::write_event!(
+        PROV,
+        "Capped",
+        binary_capped("Data", &bytes, MAX_LEN, tag(0x20)),
+        binary_capped("Short", &bytes[..2], 16, format(IPv6)),
+        u32("After", &1),
+    );
+    assert_eq!(0, result);
+
+    let schema = PROV.event_schemas().next().unwrap();
+    let fields = Vec::from_iter(schema.fields());
+    assert_eq!(5, fields.len());
+    assert_eq!("Data", fields[0].name);
+    assert_eq!(tlg::InType::Binary, fields[0].in_type);
+    assert_eq!(0x20, fields[0].tag);
+    assert_eq!(
+        tlg::EventSchemaField {
+            name: "DataLength",
+            in_type: tlg::InType::U32,
+            out_type: tlg::OutType::Default,
+            tag: 0,
+            is_array: false,
+            constant_count: None,
+        },
+        fields[1]
+    );
+    assert_eq!("Short", fields[2].name);
+    assert_eq!(tlg::OutType::IPv6, fields[2].out_type);
+    assert_eq!("ShortLength", fields[3].name);
+    assert_eq!("After", fields[4].name);
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn binary_ptr_len() {
+    tlg::define_provider!(PROV, "TraceLoggingBinaryPtrLenTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let bytes = [1u8, 2, 3, 4, 5, 6];
+    let result = unsafe {
+        tlg::write_event!(
+            PROV,
+            "PtrLen",
+            binary_ptr_len("Data", bytes.as_ptr(), bytes.len(), tag(0x20)),
+            binary_ptr_len("Null", core::ptr::null(), 0, format(IPv6)),
+            u32("After", &1),
+        )
+    };
+    assert_eq!(0, result);
+
+    let schema = PROV.event_schemas().next().unwrap();
+    let fields = Vec::from_iter(schema.fields());
+    assert_eq!(3, fields.len());
+    assert_eq!("Data", fields[0].name);
+    assert_eq!(tlg::InType::Binary, fields[0].in_type);
+    assert_eq!(0x20, fields[0].tag);
+    assert_eq!("Null", fields[1].name);
+    assert_eq!(tlg::OutType::IPv6, fields[1].out_type);
+    assert_eq!("After", fields[2].name);
+
+    // Lengths are capped to 65535 without creating a slice.
+    let desc = unsafe { tli::EventDataDescriptor::from_ptr_len(bytes.as_ptr(), usize::MAX) };
+    assert_eq!(65535, tli::ptr_len_size(&desc));
+    let desc = unsafe { tli::EventDataDescriptor::from_ptr_len(bytes.as_ptr(), bytes.len()) };
+    assert_eq!(6, tli::ptr_len_size(&desc));
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn char32() {
+    tlg::define_provider!(PROV, "TraceLoggingChar32Test");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let chars = ['a', '\u{1F600}'];
+    let result = tlg::write_event!(
+        PROV,
+        "Char32",
+        char32("Char", &'a'),
+        char32_slice("Chars", &chars),
+        char32_array("Array", 2, &chars),
+        opt_char32("Opt", &Some('b')),
+        str32("Str", &chars, format(Hex)),
+    );
+    assert_eq!(0, result);
+
+    let schema = PROV.event_schemas().next().unwrap();
+    let fields = Vec::from_iter(schema.fields());
+    assert_eq!(5, fields.len());
+    for field in &fields {
+        assert_eq!(tlg::InType::U32, field.in_type);
+    }
+    assert!(!fields[0].is_array);
+    assert_eq!(tlg::OutType::String, fields[0].out_type);
+    assert!(fields[1].is_array);
+    assert_eq!(Some(2), fields[2].constant_count);
+    assert!(fields[3].is_array);
+    assert!(fields[4].is_array);
+    assert_eq!(tlg::OutType::Hex, fields[4].out_type);
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn raw_custom() {
+    tlg::define_provider!(PROV, "TraceLoggingRawCustomTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    const SCHEMA: &[u8] = &[0x10, 0x20, 0x30];
+    let result = tlg::write_event!(
+        PROV,
+        "Custom",
+        raw_custom("Bond", 1, SCHEMA, &[1, 2], tag(0x20)),
+        raw_custom("Empty", 31, &[], &[]),
+        u32("After", &1),
+    );
+    assert_eq!(0, result);
+
+    let schema = PROV.event_schemas().next().unwrap();
+    let fields = Vec::from_iter(schema.fields());
+    assert_eq!(3, fields.len());
+    assert_eq!("Bond", fields[0].name);
+    assert_eq!(tlg::InType::from_int(1), fields[0].in_type);
+    assert_eq!(0x20, fields[0].tag);
+    assert!(!fields[0].is_array);
+    assert_eq!("Empty", fields[1].name);
+    assert_eq!(tlg::InType::from_int(31), fields[1].in_type);
+    assert_eq!("After", fields[2].name);
+
+    let meta = schema.raw_meta();
+    let bond = b"Bond\0\xE1\x80\x80\x80\x80\x20\x03\x00\x10\x20\x30";
+    assert!(meta.windows(bond.len()).any(|w| w == bond));
+    let empty = b"Empty\0\x7F\x00\x00";
+    assert!(meta.windows(empty.len()).any(|w| w == empty));
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn export_schema() {
+    tlg::define_provider!(PROV, "TraceLoggingSchemaTest");
+    tlg::define_provider!(OTHER, "TraceLoggingSchemaOther");
+
+    // Events are in the table even though they are never written.
+    if PROV.is_registered() {
+        tlg::write_event!(
+            PROV,
+            "Schema1",
+            id_version(10, 1),
+            level(Warning),
+            keyword(0x5),
+            tag(0x1234),
+            u32("Id", &1),
+            str8_json("Json", "{}", tag(0x20)),
+            u16_array("Array", 2, &[1, 2]),
+            struct("Struct", {
+                i32_slice("Slice", &[1]),
+            }),
+        );
+        tlg::write_event!(PROV, "Schema2");
+        tlg::write_event!(OTHER, "Other");
+    }
+
+    let mut names = Vec::from_iter(PROV.event_schemas().map(|schema| schema.name()));
+    names.sort_unstable();
+    assert_eq!(vec!["Schema1", "Schema2"], names);
+    assert!(tlg::event_schemas().any(|schema| schema.name() == "Other"));
+
+    let schema1 = PROV
+        .event_schemas()
+        .find(|schema| schema.name() == "Schema1")
+        .unwrap();
+    assert!(core::ptr::eq(&PROV, schema1.provider()));
+    assert_eq!(0x1234, schema1.tag());
+    assert_eq!(10, schema1.descriptor().id);
+    assert_eq!(1, schema1.descriptor().version);
+    assert_eq!(tlg::Level::Warning, schema1.descriptor().level);
+    assert_eq!(0x5, schema1.descriptor().keyword);
+    assert_eq!(
+        schema1.raw_meta().len(),
+        u16::from_le_bytes([schema1.raw_meta()[0], schema1.raw_meta()[1]]) as usize
+    );
+
+    let fields = Vec::from_iter(schema1.fields());
+    assert_eq!(5, fields.len());
+    assert_eq!(
+        tlg::EventSchemaField {
+            name: "Id",
+            in_type: tlg::InType::U32,
+            out_type: tlg::OutType::Default,
+            tag: 0,
+            is_array: false,
+            constant_count: None,
+        },
+        fields[0]
+    );
+    assert_eq!("Json", fields[1].name);
+    assert_eq!(tlg::InType::Str8, fields[1].in_type);
+    assert_eq!(tlg::OutType::Json, fields[1].out_type);
+    assert_eq!(0x20, fields[1].tag);
+    assert_eq!(tlg::InType::U16, fields[2].in_type);
+    assert!(fields[2].is_array);
+    assert_eq!(Some(2), fields[2].constant_count);
+    assert_eq!(tlg::InType::Struct, fields[3].in_type);
+    assert_eq!(1, fields[3].out_type.as_int());
+    assert_eq!("Slice", fields[4].name);
+    assert!(fields[4].is_array);
+    assert_eq!(None, fields[4].constant_count);
+
+    println!("{:?}", schema1);
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn provider_schemas() {
+    tlg::define_provider!(PROV, "TraceLoggingProviderSchemaTest");
+
+    let schema = tlg::provider_schemas()
+        .find(|schema| core::ptr::eq(&PROV, schema.provider()))
+        .unwrap();
+    assert_eq!("PROV", schema.symbol());
+    assert_eq!("tests", schema.module_path());
+    assert_eq!("tests", schema.crate_name());
+    println!("{:?}", schema);
+
+    // Several tests in this crate define "TraceLoggingDynamicTest".
+    let duplicates = Vec::from_iter(tlg::duplicate_providers("tests"));
+    assert!(duplicates.iter().any(|(first, second)| {
+        return first.provider().name() == "TraceLoggingDynamicTest"
+            && second.provider().name() == "TraceLoggingDynamicTest";
+    }));
+    assert!(!duplicates.iter().any(|(first, second)| {
+        return core::ptr::eq(&PROV, first.provider()) || core::ptr::eq(&PROV, second.provider());
+    }));
+    assert_eq!(0, tlg::duplicate_providers("other_crate").count());
+}
+
+#[cfg(feature = "export_schema")]
+#[test]
+fn duplicate_event_ids() {
+    tlg::define_provider!(PROV, "TraceLoggingDuplicateEventIdTest");
+
+    tlg::write_event!(PROV, "First", id_version(5, 0));
+    tlg::write_event!(PROV, "Second", id_version(5, 0));
+    tlg::write_event!(PROV, "First", id_version(5, 0)); // Same name: not a collision.
+    tlg::write_event!(PROV, "Third", id_version(5, 1)); // Different version.
+    tlg::write_event!(PROV, "Auto", auto_id(hash));
+
+    let auto = PROV
+        .event_schemas()
+        .find(|schema| schema.name() == "Auto")
+        .unwrap();
+    assert_eq!(
+        tlg::Provider::event_id_from_name("Auto"),
+        auto.descriptor().id
+    );
+
+    let duplicates = Vec::from_iter(
+        tlg::duplicate_event_ids("tests")
+            .filter(|(first, _)| core::ptr::eq(&PROV, first.provider())),
+    );
+    assert_eq!(2, duplicates.len()); // Each "First" collides with "Second".
+    for (first, second) in duplicates {
+        let mut names = [first.name(), second.name()];
+        names.sort_unstable();
+        assert_eq!(["First", "Second"], names);
+    }
+    assert_eq!(0, tlg::duplicate_event_ids("other_crate").count());
+}
+
+#[test]
+#[cfg(feature = "unregistered_writes")]
+fn unregistered_writes() {
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    assert_eq!(0, PROV.unregistered_writes());
+
+    tlg::write_event!(PROV, "BeforeRegister");
+    assert_eq!(1, PROV.unregistered_writes());
+
+    // try_write_event! reports the unregistered provider instead.
+    assert_eq!(6, tlg::try_write_event!(PROV, "BeforeRegister"));
+    assert_eq!(1, PROV.unregistered_writes());
+
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    tlg::write_event!(PROV, "AfterRegister");
+    let expected = if PROV.is_registered() { 1 } else { 2 };
+    assert_eq!(expected, PROV.unregistered_writes());
+}
+
+#[test]
+#[cfg(feature = "stats")]
+fn provider_stats() {
+    tlg::define_provider!(PROV, "TraceLoggingStatsTest");
+    assert_eq!(tlg::ProviderStats::default(), PROV.stats());
+
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    PROV.set_event_filter(|descriptor| descriptor.id != 2);
+    tlg::write_event!(PROV, "Event1", id_version(1, 0), level(Critical));
+    tlg::write_event!(PROV, "Event2", id_version(2, 0), level(Critical));
+
+    let stats = PROV.stats();
+    assert_eq!(2, stats.attempted);
+    assert_eq!(
+        stats.attempted,
+        stats.written + stats.filtered + stats.failed
+    );
+    assert_eq!(0, stats.failed);
+    assert_eq!(0, stats.last_error);
+    if !PROV.enabled(tlg::Level::Critical, 1) {
+        assert_eq!(2, stats.filtered);
+    }
+}
+
+#[test]
+#[should_panic]
+fn provider_panic() {
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    unsafe { PROV.register() };
+}
+
+#[test]
+fn enable_state() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    let state = tli::EnableState::new();
+    assert_eq!(0, state.generation());
+    assert!(!state.enabled(tlg::Level::LogAlways, 0));
+
+    state.update(1, 4, 0xF0, 0x30, || {});
+    assert_eq!(1, state.generation());
+    assert!(state.enabled(tlg::Level::Informational, 0));
+    assert!(state.enabled(tlg::Level::Informational, 0x30));
+    assert!(!state.enabled(tlg::Level::Informational, 0x10));
+    assert!(!state.enabled(tlg::Level::Informational, 0x0F));
+    assert!(!state.enabled(tlg::Level::Verbose, 0));
+
+    // Redundant notifications are coalesced.
+    state.update(1, 4, 0xF0, 0x30, || {});
+    state.update(2, 0, 0, 0, || {});
+    assert_eq!(1, state.generation());
+
+    state.update(0, 0, 0, 0, || {});
+    assert_eq!(2, state.generation());
+    assert!(!state.enabled(tlg::Level::LogAlways, 0));
+
+    // Stress: readers must never observe a torn level/keyword snapshot. Each
+    // writer publishes states where keyword_any == keyword_all == level * 0x0101.
+    let state = Arc::new(tli::EnableState::new());
+    let done = Arc::new(AtomicBool::new(false));
+    let in_callback = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let state = state.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let mut last_generation = 0u32;
+                while !done.load(Ordering::Relaxed) {
+                    let snap = state.snapshot();
+                    if snap.level >= 0 {
+                        let expected = snap.level as u64 * 0x0101;
+                        assert_eq!(expected, snap.keyword_any);
+                        assert_eq!(expected, snap.keyword_all);
+                    }
+                    assert!(snap.generation.wrapping_sub(last_generation) < 0x8000_0000);
+                    last_generation = snap.generation;
+                }
+            })
+        })
+        .collect();
+
+    let writers: Vec<_> = (0..4)
+        .map(|n| {
+            let state = state.clone();
+            let in_callback = in_callback.clone();
+            std::thread::spawn(move || {
+                for i in 0..20000u32 {
+                    let level = ((i + n) % 6) as u8;
+                    let keyword = level as u64 * 0x0101;
+                    state.update(i % 2, level, keyword, keyword, || {
+                        // Callbacks are serialized.
+                        assert!(!in_callback.swap(true, Ordering::Acquire));
+                        in_callback.store(false, Ordering::Release);
+                    });
+                }
+            })
+        })
+        .collect();
+
+    for writer in writers {
+        writer.join().unwrap();
+    }
+
+    done.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    assert_eq!(0, PROV.enable_generation());
+}
+
+#[test]
+fn enable_changed() {
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    fn enable_changed(state: tlg::ProviderEnableState) {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        assert_eq!(state.is_enabled(), state.enabled(state.max_level(), 0));
+    }
+
+    let disabled = tlg::ProviderEnableState::DISABLED;
+    assert_eq!(disabled, tlg::ProviderEnableState::default());
+    assert!(!disabled.is_enabled());
+    assert_eq!(tlg::Level::LogAlways, disabled.max_level());
+    assert_eq!(0, disabled.any_keyword());
+    assert_eq!(0, disabled.all_keyword());
+    assert!(!disabled.enabled(tlg::Level::LogAlways, 0));
+
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    assert_eq!(disabled, PROV.enable_state());
+
+    PROV.on_enable_changed(Some(enable_changed));
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    let state = PROV.enable_state();
+    assert_eq!(state.is_enabled(), PROV.enabled(tlg::Level::LogAlways, 0));
+    PROV.unregister();
+    assert_eq!(disabled, PROV.enable_state());
+    PROV.on_enable_changed(None);
+
+    if let tlg::NativeImplementation::Other = tlg::NATIVE_IMPLEMENTATION {
+        assert_eq!(0, CALLS.load(Ordering::Relaxed));
+    }
+}
+
+#[test]
+fn enabled_info() {
+    fn enable_info(is_enabled: u32, level: u8, logger_id: u16, any: u64, all: u64) -> Vec<u8> {
+        let mut info = Vec::new();
+        info.extend_from_slice(&is_enabled.to_le_bytes());
+        info.extend_from_slice(&[level, 0]);
+        info.extend_from_slice(&logger_id.to_le_bytes());
+        info.extend_from_slice(&0x4u32.to_le_bytes()); // EnableProperty
+        info.extend_from_slice(&0u32.to_le_bytes());
+        info.extend_from_slice(&any.to_le_bytes());
+        info.extend_from_slice(&all.to_le_bytes());
+        return info;
+    }
+
+    fn instance(last: bool, pid: u32, enables: &[Vec<u8>]) -> Vec<u8> {
+        let size = 16 + 32 * enables.len() as u32;
+        let mut info = Vec::new();
+        info.extend_from_slice(&(if last { 0 } else { size }).to_le_bytes());
+        info.extend_from_slice(&(enables.len() as u32).to_le_bytes());
+        info.extend_from_slice(&pid.to_le_bytes());
+        info.extend_from_slice(&0u32.to_le_bytes()); // Flags
+        for enable in enables {
+            info.extend_from_slice(enable);
+        }
+        return info;
+    }
+
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(&3u32.to_le_bytes()); // InstanceCount
+    buffer.extend_from_slice(&0u32.to_le_bytes());
+    buffer.extend(instance(false, 7, &[enable_info(1, 5, 99, 0xFF, 0)]));
+    buffer.extend(instance(
+        false,
+        42,
+        &[
+            enable_info(1, 4, 12, 0xF0, 0x30),
+            enable_info(0, 5, 13, 0xFF, 0), // Not enabled.
+            enable_info(1, 2, 14, 0x1, 0),
+        ],
+    ));
+    buffer.extend(instance(true, 42, &[enable_info(1, 4, 12, 0xF0, 0x30)]));
+
+    let info = tlg::ProviderEnabledInfo::from_trace_guid_info(&buffer, 42).unwrap();
+    assert_eq!(2, info.sessions().len()); // Session 12 is reported once.
+    assert_eq!(
+        tlg::SessionEnableInfo {
+            logger_id: 12,
+            level: tlg::Level::Informational,
+            enable_property: 0x4,
+            match_any_keyword: 0xF0,
+            match_all_keyword: 0x30,
+        },
+        info.sessions()[0]
+    );
+    assert_eq!(14, info.sessions()[1].logger_id);
+    assert_eq!(tlg::Level::Error, info.sessions()[1].level);
+    assert!(info.enabled(tlg::Level::Informational, 0x30));
+    assert!(info.enabled(tlg::Level::Error, 0x1));
+    assert!(!info.enabled(tlg::Level::Informational, 0x1));
+    assert!(!info.enabled(tlg::Level::Verbose, 0));
+
+    let other = tlg::ProviderEnabledInfo::from_trace_guid_info(&buffer, 1).unwrap();
+    assert!(other.is_empty());
+    assert!(tlg::ProviderEnabledInfo::from_trace_guid_info(&buffer[..100], 42).is_none());
+
+    tlg::define_provider!(PROV, "TraceLoggingEnabledInfoTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    match tlg::NATIVE_IMPLEMENTATION {
+        tlg::NativeImplementation::Windows => {
+            let info = PROV.enabled_info().unwrap();
+            assert_eq!(info.is_empty(), !PROV.enable_state().is_enabled());
+        }
+        _ => assert_eq!(Err(50), PROV.enabled_info().map(|_| ())),
+    }
+}
+
+#[test]
+fn enable_filter() {
+    let no_filter = unsafe { tlg::EventFilterDescriptor::from_filter_data(0) };
+    assert_eq!(None, no_filter);
+
+    let filter_data = [1u8, 2, 3];
+    let filter = tlg::EventFilterDescriptor {
+        ptr: filter_data.as_ptr() as usize as u64,
+        size: filter_data.len() as u32,
+        filter_type: tlg::EventFilterDescriptor::TYPE_PAYLOAD,
+    };
+    let from_data =
+        unsafe { tlg::EventFilterDescriptor::from_filter_data(&filter as *const _ as usize) }
+            .unwrap();
+    assert_eq!(&filter, from_data);
+    assert_eq!(&filter_data, unsafe { from_data.data() });
+    assert_eq!(16, core::mem::size_of::<tlg::EventFilterDescriptor>());
+
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    if let tlg::NativeImplementation::Other = tlg::NATIVE_IMPLEMENTATION {
+        assert_eq!(0, PROV.enable_filter_type());
+        assert!(!PROV.payload_filter_active());
+    }
+    PROV.unregister();
+    assert_eq!(0, PROV.enable_filter_type());
+}
+
+#[test]
+fn state_source() {
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    static CONFIG: tlg::StateSource = tlg::StateSource::new(|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+        tlg::write_state_event!(PROV, "Config", u32("Workers", &4), str8("Mode", "fast"),);
+    });
+    static EMPTY: tlg::StateSource = tlg::StateSource::new(|| {
+        CALLS.fetch_add(0x100, Ordering::Relaxed);
+        tlg::write_state_event!(PROV, "Empty");
+        tlg::write_state_event!(PROV, "Keyword", keyword(0x2), level(Informational));
+    });
+
+    assert_eq!(0x0000_8000_0000_0000, tlg::Provider::STATE_KEYWORD);
+    assert_eq!(0, PROV.state_sources().count());
+    assert!(!CONFIG.is_added());
+    assert!(PROV.add_state_source(&CONFIG));
+    assert!(CONFIG.is_added());
+    assert!(!PROV.add_state_source(&CONFIG));
+    assert!(PROV.add_state_source(&EMPTY));
+
+    let sources: Vec<_> = PROV.state_sources().collect();
+    assert_eq!(2, sources.len());
+    assert!(std::ptr::eq(&EMPTY, sources[0]));
+    assert!(std::ptr::eq(&CONFIG, sources[1]));
+
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+    PROV.capture_state();
+    assert_eq!(0x101, CALLS.load(Ordering::Relaxed));
+    println!("{:?}", CONFIG);
+}
+
+#[test]
+fn error_chain() {
+    #[derive(Debug)]
+    struct Outer(std::io::Error);
+    impl std::fmt::Display for Outer {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            return f.write_str("request failed");
+        }
+    }
+    impl std::error::Error for Outer {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            return Some(&self.0);
+        }
+    }
+
+    let inner = std::io::Error::new(std::io::ErrorKind::Other, "disk full");
+    let outer = Outer(inner);
+    assert_eq!(
+        b"\x0E\x00request failed\x01\x00\x09\x00disk full"[..],
+        tlg::_tlg_error_chain_data!(&outer)[..]
+    );
+
+    let long = std::io::Error::new(std::io::ErrorKind::Other, "\u{e9}".repeat(40000));
+    let data = tlg::_tlg_error_chain_data!(&long);
+    assert_eq!(65534, u16::from_le_bytes([data[0], data[1]])); // Truncated at char boundary.
+    assert_eq!(2 + 65534 + 2, data.len()); // No sources.
+
+    tlg::define_provider!(PROV, "TraceLoggingErrorTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let boxed: Box<dyn std::error::Error> = Box::new(outer);
+    tlg::write_event!(
+        PROV,
+        "Error",
+        error("Error", boxed.as_ref(), tag(0x1234)),
+        struct("Context", {
+            error("Error", &std::fmt::Error),
+        }),
+    );
+}
+
+#[test]
+fn write_event_cpp_style() {
+    tlg::define_provider!(PROV, "TraceLoggingCppStyleTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let count = 3u32;
+    let values = [1i32, 2, 3, 4];
+    let guid = tlg::Guid::from_name("sample");
+    let text = "hello";
+    let wide = [0x68u16, 0x69, 0];
+    tlg::write_event_cpp_style!(
+        PROV,
+        "CppStyle",
+        TraceLoggingLevel(WINEVENT_LEVEL_WARNING),
+        TraceLoggingOpcode(WINEVENT_OPCODE_INFO),
+        TraceLoggingKeyword(0x5),
+        TraceLoggingEventTag(0x1234),
+        TraceLoggingDescription("Ignored"),
+        TraceLoggingUInt32(count),
+        TraceLoggingHexInt32(values[0], "Hex", "Description", 0x20),
+        TraceLoggingGuid(guid, "Guid"),
+        TraceLoggingUtf8String(text, "Text"),
+        TraceLoggingWideString(wide, "Wide"),
+        TraceLoggingCountedUtf8String(text, 2, "Counted"),
+        TraceLoggingInt32Array(values, count, "Array"),
+        TraceLoggingInt32FixedArray(values, 4, "Fixed"),
+        TraceLoggingStruct(2, "Struct"),
+        TraceLoggingBoolean(true, "Bool"),
+        TraceLoggingValue(-1.5, "Float"),
+        TraceLoggingValue("str"),
+        u8("Native", &1),
+    );
+    tlg::write_event_cpp_style!(PROV, "Empty");
+
+    #[cfg(feature = "export_schema")]
+    {
+        let schema = PROV
+            .event_schemas()
+            .find(|schema| schema.name() == "CppStyle")
+            .unwrap();
+        assert_eq!(tlg::Level::Warning, schema.descriptor().level);
+        assert_eq!(0x5, schema.descriptor().keyword);
+        assert_eq!(0x1234, schema.tag());
+
+        let fields = Vec::from_iter(schema.fields().map(|f| (f.name, f.in_type, f.tag)));
+        assert_eq!(
+            vec![
+                ("count", tlg::InType::U32, 0),
+                ("Hex", tlg::InType::Hex32, 0x20),
+                ("Guid", tlg::InType::Guid, 0),
+                ("Text", tlg::InType::CStr8, 0),
+                ("Wide", tlg::InType::CStr16, 0),
+                ("Counted", tlg::InType::Str8, 0),
+                ("Array", tlg::InType::I32, 0),
+                ("Fixed", tlg::InType::I32, 0),
+                ("Struct", tlg::InType::Struct, 0),
+                ("Bool", tlg::InType::U8, 0),
+                ("Float", tlg::InType::F64, 0),
+                ("\"str\"", tlg::InType::Str8, 0),
+                ("Native", tlg::InType::U8, 0),
+            ],
+            fields
+        );
+    }
+}
+
+#[test]
+fn include_manifest() {
+    tlg::include_manifest!("tests/test.man");
+
+    let _u = Unregister(&MANIFEST_TEST_PROVIDER);
+    unsafe { MANIFEST_TEST_PROVIDER.register() };
+    assert_eq!("TraceLogging-Manifest-Test", MANIFEST_TEST_PROVIDER.name());
+    assert_eq!(
+        &tlg::Guid::from_u128(&0xa1b2c3d4_1234_5678_9abc_def012345678),
+        MANIFEST_TEST_PROVIDER.id()
+    );
+    assert_eq!(0x1, MANIFEST_TEST_PROVIDER::KEYWORD_NETWORK);
+    assert_eq!(0x2, MANIFEST_TEST_PROVIDER::KEYWORD_STORAGE);
+
+    request_start("/index.html", &1, &0);
+    request_retry("/index.html", &1, &5);
+    arrays_event(&2, &[1, 2], &0x10, &tlg::Guid::zero(), &[0xAB]);
+    event4();
+
+    #[cfg(feature = "export_schema")]
+    {
+        let schema = MANIFEST_TEST_PROVIDER
+            .event_schemas()
+            .find(|schema| schema.name() == "RequestStart")
+            .unwrap();
+        let descriptor = schema.descriptor();
+        assert_eq!(1, descriptor.id);
+        assert_eq!(1, descriptor.version);
+        assert_eq!(tlg::Level::Informational, descriptor.level);
+        assert_eq!(0x0001_0000_0000_0001, descriptor.keyword);
+        assert_eq!(tlg::Opcode::Start, descriptor.opcode);
+        assert_eq!(1, descriptor.task);
+
+        let fields = Vec::from_iter(schema.fields().map(|f| (f.name, f.in_type)));
+        assert_eq!(
+            vec![
+                ("Url", tlg::InType::Str8),
+                ("RequestId", tlg::InType::U32),
+                ("Error", tlg::InType::U32),
+            ],
+            fields
+        );
+
+        let schema = MANIFEST_TEST_PROVIDER
+            .event_schemas()
+            .find(|schema| schema.name() == "RequestRetry")
+            .unwrap();
+        assert_eq!(10, schema.descriptor().opcode.as_int());
+        assert_eq!(tlg::Level::Warning, schema.descriptor().level);
+
+        let schema = MANIFEST_TEST_PROVIDER
+            .event_schemas()
+            .find(|schema| schema.name() == "ARRAYS_EVENT")
+            .unwrap();
+        assert_eq!(16, schema.descriptor().level.as_int());
+        assert_eq!(0x2, schema.descriptor().keyword);
+    }
+}
+
+#[test]
+fn include_event_catalog() {
+    tlg::include_event_catalog!("tests/test_catalog.json");
+
+    let _u = Unregister(&CATALOG_TEST_PROVIDER);
+    unsafe { CATALOG_TEST_PROVIDER.register() };
+    assert_eq!("TraceLogging-Catalog-Test", CATALOG_TEST_PROVIDER.name());
+    assert_eq!(
+        &tlg::Guid::from_u128(&0xb1b2c3d4_1234_5678_9abc_def012345678),
+        CATALOG_TEST_PROVIDER.id()
+    );
+    assert_eq!(0x1, CATALOG_TEST_PROVIDER::KEYWORD_NETWORK);
+    assert_eq!(0x2, CATALOG_TEST_PROVIDER::KEYWORD_STORAGE);
+
+    let name: Vec<u16> = "Name".encode_utf16().collect();
+    request_start(
+        "/index.html",
+        &1,
+        &[1, 2],
+        &tlg::Guid::zero(),
+        &[0xAB],
+        &name,
+        &Some(3),
+    );
+    write_shutdown();
+
+    #[cfg(feature = "export_schema")]
+    {
+        let schema = CATALOG_TEST_PROVIDER
+            .event_schemas()
+            .find(|schema| schema.name() == "RequestStart")
+            .unwrap();
+        let descriptor = schema.descriptor();
+        assert_eq!(1, descriptor.id);
+        assert_eq!(2, descriptor.version);
+        assert_eq!(tlg::Level::Informational, descriptor.level);
+        assert_eq!(0x3, descriptor.keyword);
+        assert_eq!(tlg::Opcode::Start, descriptor.opcode);
+        assert_eq!(3, descriptor.task);
+        assert_eq!(0x1234, schema.tag());
+
+        let fields = Vec::from_iter(schema.fields());
+        assert_eq!(7, fields.len());
+        assert_eq!(
+            tlg::EventSchemaField {
+                name: "RequestId",
+                in_type: tlg::InType::U32,
+                out_type: tlg::OutType::Hex,
+                tag: 5,
+                is_array: false,
+                constant_count: None,
+            },
+            fields[1]
+        );
+        assert_eq!("Name\u{E9}", fields[5].name);
+        assert_eq!(
+            vec![
+                tlg::InType::Str8,
+                tlg::InType::U32,
+                tlg::InType::I64,
+                tlg::InType::Guid,
+                tlg::InType::Binary,
+                tlg::InType::Str16,
+                tlg::InType::U16,
+            ],
+            Vec::from_iter(fields.iter().map(|f| f.in_type))
+        );
+
+        let schema = CATALOG_TEST_PROVIDER
+            .event_schemas()
+            .find(|schema| schema.name() == "Shutdown")
+            .unwrap();
+        assert_eq!(16, schema.descriptor().level.as_int());
+        assert_eq!(0, schema.fields().count());
+    }
+}
+
+#[test]
+fn lazy_fields() {
+    tlg::define_provider!(PROV, "TraceLoggingLazyFieldsTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let calls = std::cell::Cell::new(0u32);
+    let render = || {
+        calls.set(calls.get() + 1);
+        return String::from("expensive");
+    };
+    let values = [1u32, 2, 3];
+    let result = tlg::write_event!(
+        PROV,
+        "Lazy",
+        level(Verbose),
+        str8("Detail", || render().to_uppercase()),
+        u32("Count", || values.len() as u32, format(Hex)),
+        u32_slice("Tail", || &values[1..]),
+        systemtime("When", move || std::time::SystemTime::UNIX_EPOCH),
+        struct("Nested", {
+            str16("Wide", || Vec::from_iter(render().encode_utf16())),
+        }),
+    );
+    assert_eq!(0, result);
+
+    let expected_calls = if PROV.enabled(tlg::Level::Verbose, 0) {
+        2
+    } else {
+        0
+    };
+    assert_eq!(expected_calls, calls.get());
+}
+
+#[test]
+fn caller_codepointer() {
+    tlg::define_provider!(PROV, "TraceLoggingCallerTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    #[inline(never)]
+    fn library_function() -> u32 {
+        return tlg::write_event!(
+            PROV,
+            "LibraryCall",
+            level(Verbose),
+            caller_codepointer("Caller", 1),
+            caller_codepointer("CallerCaller", 2, tag(0x1234)),
+        );
+    }
+
+    #[inline(never)]
+    fn caller_address() -> usize {
+        return tli::ProviderContext::caller_address(1);
+    }
+
+    assert_eq!(0, library_function());
+    if let tlg::NativeImplementation::Other = tlg::NATIVE_IMPLEMENTATION {
+        assert_eq!(0, caller_address());
+    } else {
+        assert_ne!(0, caller_address());
+    }
+}
+
+#[test]
+fn malformed_sid() {
+    tlg::define_provider!(PROV, "TraceLoggingSidTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let valid = [1u8, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0, 0xFF];
+    let short = [1u8, 2, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0];
+    assert_eq!(Some(12), tli::sid_length(&valid));
+    assert_eq!(Some(12), tli::sid_length(&tli::NULL_SID));
+    assert_eq!(None, tli::sid_length(&[]));
+    assert_eq!(None, tli::sid_length(&[1]));
+    assert_eq!(None, tli::sid_length(&short));
+    assert_eq!(None, tli::sid_length(&[2, 0, 0, 0, 0, 0, 0, 0]));
+    assert_eq!(None, tli::sid_length(&[1, 16, 0, 0, 0, 0, 0, 0]));
+
+    // Malformed values are logged as the NULL SID instead of panicking.
+    let _ = tli::EventDataDescriptor::from_sid(&[]);
+    let _ = tli::EventDataDescriptor::from_sid(&short);
+    let empty: &[u8] = &[];
+    assert_eq!(
+        0,
+        tlg::write_event!(
+            PROV,
+            "Sid",
+            level(LogAlways),
+            win_sid("Short", &short),
+            win_sid("Empty", empty),
+        )
+    );
+}
+
+#[test]
+fn write_error() {
+    use tlg::WriteError;
+
+    tlg::define_provider!(PROV, "TraceLoggingWriteErrorTest");
+    assert_eq!(
+        Err(WriteError::InvalidHandle),
+        WriteError::check(tlg::try_write_event!(PROV, "Unregistered"))
+    );
+
+    assert_eq!(Ok(()), WriteError::check(0));
+    assert_eq!(None, WriteError::from_code(0));
+    for code in [6, 87, 234, 534, 1234] {
+        let error = WriteError::from_code(code).unwrap();
+        assert_eq!(code, error.code());
+        assert_eq!(code, u32::from(error));
+    }
+
+    assert_eq!(Some(WriteError::TooLarge), WriteError::from_code(534));
+    assert_eq!(Some(WriteError::Other(1234)), WriteError::from_code(1234));
+    assert_eq!(
+        WriteError::BuffersFull,
+        WriteError::from_code(WriteError::BuffersFull.code()).unwrap()
+    );
+    assert_eq!("event is too large", WriteError::TooLarge.to_string());
+    assert_eq!(
+        "write failed with error 1234",
+        WriteError::Other(1234).to_string()
+    );
+}
+
+#[test]
+fn compiled_in() {
+    use tlg::Level;
+
+    // Environment variables not set: everything is compiled in.
+    assert!(tli::compiled_in(Level::Verbose, 0x10, None, None));
+    assert!(tli::compiled_in(Level::Verbose, 0x10, Some(""), Some("")));
+
+    assert!(tli::compiled_in(Level::Warning, 1, Some("Warning"), None));
+    assert!(tli::compiled_in(
+        Level::Error,
+        1,
+        Some("informational"),
+        None
+    ));
+    assert!(tli::compiled_in(Level::LogAlways, 1, Some("0"), None));
+    assert!(!tli::compiled_in(
+        Level::Informational,
+        1,
+        Some("Warning"),
+        None
+    ));
+    assert!(!tli::compiled_in(Level::Verbose, 1, Some("4"), None));
+    assert!(!tli::compiled_in(
+        Level::from_int(6),
+        1,
+        Some("Verbose"),
+        None
+    ));
+
+    assert!(tli::compiled_in(Level::Verbose, 0x11, None, Some("0x10")));
+    assert!(tli::compiled_in(Level::Verbose, 0x2, None, Some("3")));
+    assert!(tli::compiled_in(Level::Verbose, 0, None, Some("0")));
+    assert!(!tli::compiled_in(Level::Verbose, 0x4, None, Some("0X3")));
+    assert!(!tli::compiled_in(
+        Level::Error,
+        0x4,
+        Some("Verbose"),
+        Some("0")
+    ));
+
+    // The check is usable in const context, as generated by write_event!.
+    const VERBOSE_IN: [bool; 1] = [tli::compiled_in(Level::Verbose, 1, Some("Error"), None)];
+    assert_eq!([false], VERBOSE_IN);
+}
+
+#[test]
+fn derive_event() {
+    tlg::define_provider!(PROV, "TraceLoggingDeriveTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    #[derive(tlg::TraceLoggingEvent)]
+    #[event(level(Warning), keyword(0x10))]
+    #[event(opcode(Info))]
+    struct PacketSent<'a> {
+        bytes: u32,
+        pub dest: &'a str,
+        name: String,
+        #[event(format(Hex))]
+        flags: u16,
+        #[event(u32_hex, tag(0x1234))]
+        mask: u32,
+        #[event(str8_json)]
+        json: &'a str,
+        payload: Vec<u8>,
+        sizes: &'a [u64],
+        ids: [tlg::Guid; 2],
+        retry: Option<u32>,
+        ok: bool,
+        when: std::time::SystemTime,
+        r#type: u8,
+        grade: char,
+        #[event(skip)]
+        _skipped: std::collections::HashMap<u32, String>,
+    }
+
+    #[derive(tlg::TraceLoggingEvent)]
+    struct Generic<T: Copy, const N: usize>
+    where
+        T: Default,
+    {
+        values: [u32; N],
+        #[event(skip)]
+        _value: T,
+    }
+
+    let packet = PacketSent {
+        bytes: 1500,
+        dest: "192.168.0.1",
+        name: String::from("name"),
+        flags: 0x12,
+        mask: 0xFF00,
+        json: "{}",
+        payload: vec![1, 2, 3],
+        sizes: &[1, 2],
+        ids: [tlg::Guid::zero(); 2],
+        retry: None,
+        ok: true,
+        when: std::time::SystemTime::now(),
+        r#type: 3,
+        grade: 'A',
+        _skipped: std::collections::HashMap::new(),
+    };
+    assert_eq!(0, packet.write(&PROV));
+
+    let generic = Generic {
+        values: [1, 2, 3],
+        _value: 0u8,
+    };
+    assert_eq!(0, generic.write(&PROV));
+}
+
+#[test]
+fn fmt_fields() {
+    tlg::define_provider!(PROV, "TraceLoggingFmtTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let index = 3;
+    let result = tlg::write_event!(
+        PROV,
+        "Fmt",
+        level(Verbose),
+        fmt("Message", format_args!("{} of {}", index, 10)),
+        fmt(
+            "Json",
+            format_args!("{{\"index\":{}}}", index),
+            format(Json)
+        ),
+    );
+    assert_eq!(0, result);
+
+    let buffer = tli::FmtBuffer::new(format_args!("{} of {}", index, 10));
+    assert_eq!(b"3 of 10", buffer.as_ref());
+
+    // Truncated at a character boundary.
+    let long = "\u{20AC}".repeat(tli::FmtBuffer::CAPACITY);
+    let buffer = tli::FmtBuffer::new(format_args!("{}", long));
+    assert_eq!(
+        tli::FmtBuffer::CAPACITY - tli::FmtBuffer::CAPACITY % 3,
+        buffer.as_ref().len()
+    );
+    assert!(std::str::from_utf8(buffer.as_ref()).is_ok());
+}
+
+#[test]
+fn str_slice_fields() {
+    tlg::define_provider!(PROV, "TraceLoggingStrSliceTest");
+    let _u = Unregister(&PROV);
+    unsafe { PROV.register() };
+
+    let args = ["a", "bc"];
+    let owned = vec![String::from("x")];
+    let wide = [[0x61u16, 0x62], [0x63, 0x64]];
+    let result = tlg::write_event!(
+        PROV,
+        "StrSlice",
+        level(Verbose),
+        str8_slice("Args", &args),
+        str8_slice("Owned", &owned, tag(0x20)),
+        str8_slice("Empty", &[] as &[&str]),
+        str16_slice("Wide", &wide),
+    );
+    assert_eq!(0, result);
+
+    let buffer = tli::StrSliceBuffer::new8(&args);
+    assert_eq!(b"\x02\x00\x01\x00a\x02\x00bc", buffer.as_ref());
+
+    let buffer = tli::StrSliceBuffer::new16(&wide[..1]);
+    assert_eq!(b"\x01\x00\x04\x00a\x00b\x00", buffer.as_ref());
+
+    let buffer = tli::StrSliceBuffer::new8(&[] as &[&str]);
+    assert_eq!(b"\x00\x00", buffer.as_ref());
+
+    // Ends with the last element that fits.
+    let half = "x".repeat(tli::StrSliceBuffer::CAPACITY / 2);
+    let buffer = tli::StrSliceBuffer::new8(&[&half, "y", &half]);
+    assert_eq!(2 + 2 + half.len() + 2 + 1, buffer.as_ref().len());
+    assert_eq!(b"\x02\x00", &buffer.as_ref()[..2]);
+}
+
+#[test]
+fn provider_set() {
+    tlg::define_provider!(PROV1, "TraceLoggingProviderSetTest1");
+    tlg::define_provider!(
+        PROV2,
+        "TraceLoggingProviderSetTest2",
+        default_keyword(0x2),
+        keywords_mask(0x00FF),
+    );
+    tlg::define_provider_set!(SET, [PROV1, PROV2]);
+    let _u1 = Unregister(&PROV1);
+    let _u2 = Unregister(&PROV2);
+    unsafe { PROV1.register() };
+    unsafe { PROV2.register() };
+
+    assert_eq!(2, SET.providers().len());
+    assert!(core::ptr::eq(&PROV1, SET.primary()));
+    assert!(core::ptr::eq(&PROV2, SET.providers()[1]));
+    assert_eq!(PROV1.is_registered(), SET.is_registered());
+    assert_eq!(
+        PROV1.enabled(tlg::Level::Verbose, 1) || PROV2.enabled(tlg::Level::Verbose, 1),
+        SET.enabled(tlg::Level::Verbose, 1)
+    );
+
+    // Constants for write_event!.
+    assert_eq!(PROV1::DEFAULT_KEYWORD, SET::DEFAULT_KEYWORD);
+    assert_eq!(PROV2::KEYWORDS_MASK, SET::KEYWORDS_MASK);
+
+    let result = tlg::write_event!(
+        SET,
+        "SetEvent",
+        level(Verbose),
+        keyword(0x4),
+        u32("Value", &1),
+        str8("Text", "hello"),
+    );
+    assert_eq!(0, result);
+    assert_eq!(0, tlg::mark!(SET, "SetMark"));
+
+    let result = tlg::try_write_event!(SET, "SetTry", u32("Value", &2));
+    assert_eq!(if SET.is_registered() { 0 } else { 6 }, result);
+
+    // Schemas are exported as events of the first provider.
+    #[cfg(feature = "export_schema")]
+    {
+        assert!(PROV1
+            .event_schemas()
+            .any(|schema| schema.name() == "SetEvent"));
+        assert_eq!(0, PROV2.event_schemas().count());
+    }
+}
+
+#[test]
+fn write_event() {
+    tlg::define_provider!(PROV, "TraceLoggingDynamicTest");
+
+    let _u = Unregister(&PROV);
+    assert!(!PROV.is_registered());
+    assert_eq!(
+        6,
+        tlg::try_write_event!(PROV, "TryUnregistered", u8("u8", &1))
+    );
+
+    unsafe { PROV.register() };
+    if let tlg::NativeImplementation::Windows = tlg::NATIVE_IMPLEMENTATION {
+        assert!(PROV.is_registered());
+        assert_eq!(
+            0,
+            tlg::try_write_event!(PROV, "TryRegistered", u8("u8", &1))
+        );
+    }
+
+    let sample_guid = tlg::Provider::guid_from_name("sample");
+    let sample_filetime = 0x01d7ace794497cb5i64;
+    let sample_rusttime =
+        std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1671930123);
+    let sample_systemtime = [2022, 1, 1, 2, 3, 4, 5, 6];
+    let sample_sid = [1, 1, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0];
+    let sample_ipv4 = [127, 0, 0, 1];
+    let sample_ipv6 = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+
+    tlg::write_event!(PROV, "Default");
+
+    tlg::write_event!(
+        PROV,
+        "4v2o6t123c0l3k11",
+        id_version(4, 2),
+        opcode(6),
+        task(123),
+        channel(0),
+        level(3),
+        keyword(0x11),
+    );
+
+    let some_u32 = Some(5u32);
+    let none_u32: Option<u32> = None;
+    tlg::write_event!(
+        PROV,
+        "Optional",
+        opt_u32("Some", &some_u32),
+        opt_u32("None", &none_u32, tag(0x1234)),
+        opt_guid("Guid", &Some(sample_guid)),
+        opt_ipv4("IPv4", &Some(sample_ipv4)),
+        opt_win_systemtime("SystemTime", &None, format(DateTimeUtc)),
+    );
+    assert_eq!(&[5u32][..], tlg::_internal::option_as_slice(&some_u32));
+    assert!(tlg::_internal::option_as_slice(&none_u32).is_empty());
+
+    const ARRAY_LEN: usize = 3;
+    let u32_vals = [1u32, 2, 3];
+    tlg::write_event!(
+        PROV,
+        "Array",
+        u32_array("U32", ARRAY_LEN, &u32_vals),
+        u32_array("Empty", 0, &[]),
+        u8_hex_array("Hex", 2, &[0xAB, 0xCD], tag(0x1234)),
+        guid_array("Guid", 1, &[sample_guid]),
+        ipv4_array("IPv4", 2, &[sample_ipv4, sample_ipv4]),
+        win_systemtime_array("SystemTime", 1, &[sample_systemtime], format(DateTimeUtc)),
+    );
+
+    tlg::write_event!(PROV, "AutoId", auto_id(hash), level(Verbose));
+    const AUTO_ID: u16 = tlg::Provider::event_id_from_name("AutoId");
+    assert_ne!(0, AUTO_ID);
+    assert_eq!(0x2708, tlg::Provider::event_id_from_name("MyEvent"));
+    assert_eq!(0x1cd9, tlg::Provider::event_id_from_name(""));
 
     tlg::write_event!(PROV, "tag0xFE00000", tag(0xFE00000));
     tlg::write_event!(PROV, "tag0xFEDC000", tag(0xFEDC000));
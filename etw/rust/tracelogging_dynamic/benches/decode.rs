@@ -0,0 +1,251 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Measures the cost of reading fields from a captured event: decoding the whole
+//! event with `DecodedEvent::decode`, finding one field with the zero-copy
+//! `RawFields` API, and (on Windows) finding the same field with TDH
+//! (`TdhGetEventInformation` and `TdhGetProperty`) from a synthetic `EVENT_RECORD`.
+//!
+//! Run with `cargo bench -p tracelogging_dynamic --bench decode`.
+
+#![allow(clippy::needless_return)]
+
+use criterion::black_box;
+use criterion::criterion_group;
+use criterion::Criterion;
+use tracelogging_dynamic as tld;
+
+/// Captures a typical request event: 8 fields (including a struct with an array),
+/// about 150 bytes of metadata and 130 bytes of data.
+fn capture_event() -> tld::CapturedEvent {
+    let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    provider.set_capture(1);
+
+    let client: Vec<u16> = "client.contoso.com".encode_utf16().collect();
+    let mut builder = tld::EventBuilder::new();
+    builder
+        .reset("RequestCompleted", tld::Level::Informational, 0x1, 0)
+        .add_u32("Id", 12345, tld::OutType::Default, 0)
+        .add_str8("Method", "GET", tld::OutType::Utf8, 0)
+        .add_str8("Path", "/api/items/12345/details", tld::OutType::Utf8, 0)
+        .add_str16("Client", &client, tld::OutType::Default, 0)
+        .add_struct("Headers", 2, 0)
+        .add_str8("UserAgent", "bench/1.0", tld::OutType::Utf8, 0)
+        .add_u16_sequence("Ports", &[80, 443, 8080], tld::OutType::Port, 0)
+        .add_u64("DurationNs", 987654, tld::OutType::Default, 0)
+        .add_u32("Status", 404, tld::OutType::Default, 0)
+        .write(&provider, None, None);
+
+    return provider.drain_captured().remove(0);
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let event = capture_event();
+    let mut group = c.benchmark_group("decode_status");
+
+    group.bench_function("decoded_event", |b| {
+        b.iter(|| {
+            let decoded = event.decode().unwrap();
+            return black_box(match decoded.field("Status").unwrap().value() {
+                Some(&tld::DecodedValue::U32(status)) => status,
+                _ => 0,
+            });
+        });
+    });
+
+    group.bench_function("raw_fields", |b| {
+        b.iter(|| {
+            let status = event.raw_fields().unwrap().field("Status").unwrap();
+            let bytes = status.unwrap().value().unwrap().bytes();
+            return black_box(u32::from_le_bytes(bytes.try_into().unwrap()));
+        });
+    });
+
+    #[cfg(windows)]
+    tdh::bench(&mut group, &event);
+
+    group.finish();
+}
+
+#[cfg(windows)]
+mod tdh {
+    use core::ffi::c_void;
+    use core::mem::size_of;
+    use core::ptr;
+
+    use criterion::black_box;
+    use criterion::measurement::WallTime;
+    use criterion::BenchmarkGroup;
+    use tracelogging_dynamic as tld;
+
+    const EVENT_HEADER_EXT_TYPE_EVENT_SCHEMA_TL: u16 = 11;
+    const EVENT_HEADER_FLAG_32_BIT_HEADER: u16 = 0x0020;
+    const EVENT_HEADER_FLAG_64_BIT_HEADER: u16 = 0x0040;
+    const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+    /// Adds benchmarks that read the `Status` field of `event` with TDH.
+    pub fn bench(group: &mut BenchmarkGroup<WallTime>, event: &tld::CapturedEvent) {
+        let ext = EventHeaderExtendedDataItem {
+            reserved1: 0,
+            ext_type: EVENT_HEADER_EXT_TYPE_EVENT_SCHEMA_TL,
+            linkage: 0,
+            data_size: event.meta.len() as u16,
+            data_ptr: event.meta.as_ptr() as usize as u64,
+        };
+        let record = EventRecord {
+            event_header: EventHeader {
+                size: size_of::<EventHeader>() as u16,
+                header_type: 0,
+                flags: if size_of::<usize>() == 8 {
+                    EVENT_HEADER_FLAG_64_BIT_HEADER
+                } else {
+                    EVENT_HEADER_FLAG_32_BIT_HEADER
+                },
+                event_property: 0,
+                thread_id: 0,
+                process_id: 0,
+                time_stamp: 0,
+                provider_id: tld::Provider::guid_from_name("MyCompany.MyComponent"),
+                event_descriptor: event.descriptor,
+                processor_time: 0,
+                activity_id: tld::Guid::zero(),
+            },
+            buffer_context: 0,
+            extended_data_count: 1,
+            user_data_length: event.data.len() as u16,
+            extended_data: &ext,
+            user_data: event.data.as_ptr().cast(),
+            user_context: ptr::null(),
+        };
+
+        let name: Vec<u16> = "Status".encode_utf16().chain(Some(0)).collect();
+        let descriptor = PropertyDataDescriptor {
+            property_name: name.as_ptr() as usize as u64,
+            array_index: u32::MAX,
+            reserved: 0,
+        };
+
+        // Check that TDH decodes the synthetic record before measuring it.
+        assert_eq!(404, get_status(&record, &descriptor));
+
+        group.bench_function("tdh_get_property", |b| {
+            b.iter(|| {
+                return black_box(get_status(&record, &descriptor));
+            });
+        });
+
+        group.bench_function("tdh_event_information", |b| {
+            let mut buffer = Vec::<u64>::new();
+            b.iter(|| {
+                let mut size = 0u32;
+                let mut result = unsafe {
+                    TdhGetEventInformation(&record, 0, ptr::null(), ptr::null_mut(), &mut size)
+                };
+                if result == ERROR_INSUFFICIENT_BUFFER {
+                    buffer.resize((size as usize + 7) / 8, 0);
+                    result = unsafe {
+                        TdhGetEventInformation(
+                            &record,
+                            0,
+                            ptr::null(),
+                            buffer.as_mut_ptr().cast(),
+                            &mut size,
+                        )
+                    };
+                }
+                assert_eq!(0, result);
+                return black_box(size);
+            });
+        });
+    }
+
+    fn get_status(record: &EventRecord, descriptor: &PropertyDataDescriptor) -> u32 {
+        let mut status = [0u8; 4];
+        let result = unsafe {
+            TdhGetProperty(
+                record,
+                0,
+                ptr::null(),
+                1,
+                descriptor,
+                status.len() as u32,
+                status.as_mut_ptr(),
+            )
+        };
+        assert_eq!(0, result);
+        return u32::from_le_bytes(status);
+    }
+
+    /// EVENT_RECORD.
+    #[repr(C)]
+    struct EventRecord {
+        event_header: EventHeader,
+        buffer_context: u32,
+        extended_data_count: u16,
+        user_data_length: u16,
+        extended_data: *const EventHeaderExtendedDataItem,
+        user_data: *const c_void,
+        user_context: *const c_void,
+    }
+
+    /// EVENT_HEADER.
+    #[repr(C)]
+    struct EventHeader {
+        size: u16,
+        header_type: u16,
+        flags: u16,
+        event_property: u16,
+        thread_id: u32,
+        process_id: u32,
+        time_stamp: i64,
+        provider_id: tld::Guid,
+        event_descriptor: tld::EventDescriptor,
+        processor_time: u64,
+        activity_id: tld::Guid,
+    }
+
+    /// EVENT_HEADER_EXTENDED_DATA_ITEM.
+    #[repr(C)]
+    struct EventHeaderExtendedDataItem {
+        reserved1: u16,
+        ext_type: u16,
+        linkage: u16,
+        data_size: u16,
+        data_ptr: u64,
+    }
+
+    /// PROPERTY_DATA_DESCRIPTOR.
+    #[repr(C)]
+    struct PropertyDataDescriptor {
+        property_name: u64,
+        array_index: u32,
+        reserved: u32,
+    }
+
+    #[link(name = "tdh")]
+    extern "system" {
+        fn TdhGetEventInformation(
+            event: *const EventRecord,
+            context_count: u32,
+            context: *const c_void,
+            buffer: *mut c_void,
+            buffer_size: *mut u32,
+        ) -> u32;
+        fn TdhGetProperty(
+            event: *const EventRecord,
+            context_count: u32,
+            context: *const c_void,
+            property_data_count: u32,
+            property_data: *const PropertyDataDescriptor,
+            buffer_size: u32,
+            buffer: *mut u8,
+        ) -> u32;
+    }
+}
+
+criterion_group!(benches, bench_decode);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
@@ -0,0 +1,141 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Measures the cost of building and writing dynamic events, and checks that small
+//! events (metadata and data of up to 256 bytes each) do not allocate.
+//!
+//! Run with `cargo bench -p tracelogging_dynamic --bench event_builder`.
+
+#![allow(clippy::needless_return)]
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use criterion::black_box;
+use criterion::criterion_group;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use tracelogging_dynamic as tld;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        return System.realloc(ptr, layout, new_size);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Builds a typical small event: 3 fields, about 50 bytes of metadata and 30 bytes
+/// of data.
+fn build_small(builder: &mut tld::EventBuilder, id: u32) {
+    builder
+        .reset("RequestCompleted", tld::Level::Informational, 0x1, 0)
+        .add_u32("Id", id, tld::OutType::Default, 0)
+        .add_str8("Path", "/api/items", tld::OutType::Utf8, 0)
+        .add_u64("DurationNs", 12345, tld::OutType::Default, 0);
+}
+
+/// Builds an event with a single binary field containing `data`.
+fn build_binary(builder: &mut tld::EventBuilder, data: &[u8]) {
+    builder
+        .reset("Blob", tld::Level::Verbose, 0x1, 0)
+        .add_binary("Data", data, tld::OutType::Default, 0);
+}
+
+fn bench_small(c: &mut Criterion) {
+    let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    let mut group = c.benchmark_group("small_event");
+
+    group.bench_function("new_builder", |b| {
+        let mut id = 0;
+        b.iter(|| {
+            let mut builder = tld::EventBuilder::new();
+            build_small(&mut builder, id);
+            id += 1;
+            return black_box(builder.write(&provider, None, None));
+        });
+    });
+
+    group.bench_function("reused_builder", |b| {
+        let mut builder = tld::EventBuilder::new();
+        let mut id = 0;
+        b.iter(|| {
+            build_small(&mut builder, id);
+            id += 1;
+            return black_box(builder.write(&provider, None, None));
+        });
+    });
+
+    group.finish();
+}
+
+fn bench_sizes(c: &mut Criterion) {
+    let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    let mut group = c.benchmark_group("binary_event");
+
+    // 64 and 200 fit inline; 1024 and 16384 are stored on the heap.
+    for data_len in [64usize, 200, 1024, 16384] {
+        let data = vec![0xAB; data_len];
+        group.throughput(Throughput::Bytes(data_len as u64));
+        group.bench_with_input(
+            BenchmarkId::new("new_builder", data_len),
+            &data,
+            |b, data| {
+                b.iter(|| {
+                    let mut builder = tld::EventBuilder::new();
+                    build_binary(&mut builder, data);
+                    return black_box(builder.write(&provider, None, None));
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+/// Panics if building and writing a small event with a new builder allocates.
+fn check_small_event_allocations() {
+    const ITERATIONS: usize = 1000;
+    let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    for id in 0..ITERATIONS {
+        let mut builder = tld::EventBuilder::new();
+        build_small(&mut builder, id as u32);
+        black_box(builder.write(&provider, None, None));
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "small_event: {:.2} allocations/event",
+        allocations as f64 / ITERATIONS as f64
+    );
+    assert_eq!(0, allocations, "small events should not allocate");
+}
+
+criterion_group!(benches, bench_small, bench_sizes);
+
+fn main() {
+    check_small_event_allocations();
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+}
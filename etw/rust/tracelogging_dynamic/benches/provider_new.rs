@@ -0,0 +1,82 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Measures the cost of creating dynamic providers, e.g. for processes that create a
+//! provider per tenant. Reports the time and number of heap allocations per provider.
+//!
+//! Run with `cargo bench -p tracelogging_dynamic --bench provider_new`.
+
+#![allow(clippy::needless_return)]
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use tracelogging_dynamic as tld;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        return System.realloc(ptr, layout, new_size);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const ITERATIONS: usize = 100000;
+
+fn bench(label: &str, options: &tld::ProviderOptions) {
+    let names: Vec<String> = (0..ITERATIONS)
+        .map(|i| format!("MyCompany.MyComponent.Tenant{}", i))
+        .collect();
+    let mut providers = Vec::with_capacity(ITERATIONS);
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for name in &names {
+        providers.push(tld::Provider::new(name, options));
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    let meta_len: usize = providers.iter().map(|p| p.meta_len()).sum();
+    println!(
+        "{:<24} {:>8.1} ns/provider {:>6.2} allocations/provider {:>6.1} meta bytes/provider",
+        label,
+        elapsed.as_nanos() as f64 / ITERATIONS as f64,
+        allocations as f64 / ITERATIONS as f64,
+        meta_len as f64 / ITERATIONS as f64
+    );
+}
+
+fn main() {
+    bench("Provider::new", &tld::Provider::options());
+    bench(
+        "Provider::new (group)",
+        tld::Provider::options().group_id(&tld::Guid::from_name("MyCompany.Group")),
+    );
+    bench(
+        "Provider::new (traits)",
+        tld::Provider::options()
+            .group_id(&tld::Guid::from_name("MyCompany.Group"))
+            .add_trait(2, b"trait data")
+            .add_trait(3, b"more trait data"),
+    );
+}
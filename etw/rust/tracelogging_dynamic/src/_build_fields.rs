@@ -0,0 +1,227 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! For use by the [`build_event!`](crate::build_event) macro: one function per
+//! supported field type, named after the corresponding `write_event!` field type.
+//! Each function adds the field to the builder using the field type's default
+//! [OutType] unless the field has a `format` option.
+
+use core::borrow::Borrow;
+
+use crate::builder::EventBuilder;
+use crate::Guid;
+use crate::OutType;
+
+/// Fields whose value is `impl Borrow<T>` (passed to `add_xxx` by value) and whose
+/// `_slice` value is `impl IntoIterator<Item = &T>`.
+macro_rules! value_fields {
+    ($($name:ident, $slice_name:ident, $value_type:ty, $add:ident, $add_sequence:ident, $out_type:ident;)*) => {$(
+        pub fn $name(
+            builder: &mut EventBuilder,
+            field_name: &str,
+            field_value: impl Borrow<$value_type>,
+            out_type: Option<OutType>,
+            field_tag: u32,
+        ) {
+            builder.$add(
+                field_name,
+                *field_value.borrow(),
+                out_type.unwrap_or(OutType::$out_type),
+                field_tag,
+            );
+        }
+
+        pub fn $slice_name<'a>(
+            builder: &mut EventBuilder,
+            field_name: &str,
+            field_values: impl IntoIterator<Item = &'a $value_type>,
+            out_type: Option<OutType>,
+            field_tag: u32,
+        ) {
+            builder.$add_sequence(
+                field_name,
+                field_values,
+                out_type.unwrap_or(OutType::$out_type),
+                field_tag,
+            );
+        }
+    )*};
+}
+
+/// Fields whose value is `impl Borrow<T>` (passed to `add_xxx` by reference) and
+/// whose `_slice` value is `impl IntoIterator<Item = &T>`.
+macro_rules! ref_fields {
+    ($($name:ident, $slice_name:ident, $value_type:ty, $add:ident, $add_sequence:ident;)*) => {$(
+        pub fn $name(
+            builder: &mut EventBuilder,
+            field_name: &str,
+            field_value: impl Borrow<$value_type>,
+            out_type: Option<OutType>,
+            field_tag: u32,
+        ) {
+            builder.$add(
+                field_name,
+                field_value.borrow(),
+                out_type.unwrap_or(OutType::Default),
+                field_tag,
+            );
+        }
+
+        pub fn $slice_name<'a>(
+            builder: &mut EventBuilder,
+            field_name: &str,
+            field_values: impl IntoIterator<Item = &'a $value_type>,
+            out_type: Option<OutType>,
+            field_tag: u32,
+        ) {
+            builder.$add_sequence(
+                field_name,
+                field_values,
+                out_type.unwrap_or(OutType::Default),
+                field_tag,
+            );
+        }
+    )*};
+}
+
+/// Fields whose value is `impl AsRef<[T]>` and whose `_slice` value is
+/// `impl IntoIterator<Item: AsRef<[T]>>`.
+macro_rules! counted_fields {
+    ($($name:ident, $slice_name:ident, $element_type:ty, $add:ident, $add_sequence:ident, $out_type:ident;)*) => {$(
+        pub fn $name(
+            builder: &mut EventBuilder,
+            field_name: &str,
+            field_value: impl AsRef<[$element_type]>,
+            out_type: Option<OutType>,
+            field_tag: u32,
+        ) {
+            builder.$add(
+                field_name,
+                field_value,
+                out_type.unwrap_or(OutType::$out_type),
+                field_tag,
+            );
+        }
+
+        pub fn $slice_name<T: IntoIterator>(
+            builder: &mut EventBuilder,
+            field_name: &str,
+            field_values: T,
+            out_type: Option<OutType>,
+            field_tag: u32,
+        ) where
+            T::Item: AsRef<[$element_type]>,
+        {
+            builder.$add_sequence(
+                field_name,
+                field_values,
+                out_type.unwrap_or(OutType::$out_type),
+                field_tag,
+            );
+        }
+    )*};
+}
+
+value_fields! {
+    i8, i8_slice, i8, add_i8, add_i8_sequence, Default;
+    u8, u8_slice, u8, add_u8, add_u8_sequence, Default;
+    i16, i16_slice, i16, add_i16, add_i16_sequence, Default;
+    u16, u16_slice, u16, add_u16, add_u16_sequence, Default;
+    i32, i32_slice, i32, add_i32, add_i32_sequence, Default;
+    u32, u32_slice, u32, add_u32, add_u32_sequence, Default;
+    i64, i64_slice, i64, add_i64, add_i64_sequence, Default;
+    u64, u64_slice, u64, add_u64, add_u64_sequence, Default;
+    isize, isize_slice, isize, add_isize, add_isize_sequence, Default;
+    usize, usize_slice, usize, add_usize, add_usize_sequence, Default;
+    f32, f32_slice, f32, add_f32, add_f32_sequence, Default;
+    f64, f64_slice, f64, add_f64, add_f64_sequence, Default;
+    bool32, bool32_slice, i32, add_bool32, add_bool32_sequence, Default;
+    hex32, hex32_slice, u32, add_hex32, add_hex32_sequence, Default;
+    hex64, hex64_slice, u64, add_hex64, add_hex64_sequence, Default;
+    hexsize, hexsize_slice, usize, add_hexsize, add_hexsize_sequence, Default;
+    pointer, pointer_slice, usize, add_hexsize, add_hexsize_sequence, Default;
+    codepointer, codepointer_slice, usize, add_hexsize, add_hexsize_sequence, CodePointer;
+    char8_cp1252, char8_cp1252_slice, u8, add_u8, add_u8_sequence, String;
+    char16, char16_slice, u16, add_u16, add_u16_sequence, String;
+    char32, char32_slice, char, add_char32, add_char32_sequence, String;
+    errno, errno_slice, i32, add_i32, add_i32_sequence, Default;
+    hresult, hresult_slice, i32, add_i32, add_i32_sequence, HResult;
+    win_error, win_error_slice, u32, add_u32, add_u32_sequence, Win32Error;
+    win_filetime, win_filetime_slice, i64, add_filetime, add_filetime_sequence, Default;
+}
+
+ref_fields! {
+    guid, guid_slice, Guid, add_guid, add_guid_sequence;
+    win_systemtime, win_systemtime_slice, [u16; 8], add_systemtime, add_systemtime_sequence;
+}
+
+counted_fields! {
+    str8, str8_slice, u8, add_str8, add_str8_sequence, Utf8;
+    str16, str16_slice, u16, add_str16, add_str16_sequence, Default;
+    cstr8, cstr8_slice, u8, add_cstr8, add_cstr8_sequence, Utf8;
+    cstr16, cstr16_slice, u16, add_cstr16, add_cstr16_sequence, Default;
+    binaryc, binaryc_slice, u8, add_binaryc, add_binaryc_sequence, Default;
+    win_sid, win_sid_slice, u8, add_sid, add_sid_sequence, Default;
+}
+
+pub fn binary(
+    builder: &mut EventBuilder,
+    field_name: &str,
+    field_value: impl AsRef<[u8]>,
+    out_type: Option<OutType>,
+    field_tag: u32,
+) {
+    builder.add_binary(
+        field_name,
+        field_value,
+        out_type.unwrap_or(OutType::Default),
+        field_tag,
+    );
+}
+
+pub fn str32(
+    builder: &mut EventBuilder,
+    field_name: &str,
+    field_value: impl AsRef<[char]>,
+    out_type: Option<OutType>,
+    field_tag: u32,
+) {
+    builder.add_char32_sequence(
+        field_name,
+        field_value.as_ref(),
+        out_type.unwrap_or(OutType::String),
+        field_tag,
+    );
+}
+
+pub fn bool8(
+    builder: &mut EventBuilder,
+    field_name: &str,
+    field_value: impl Borrow<bool>,
+    out_type: Option<OutType>,
+    field_tag: u32,
+) {
+    builder.add_u8(
+        field_name,
+        *field_value.borrow() as u8,
+        out_type.unwrap_or(OutType::Boolean),
+        field_tag,
+    );
+}
+
+pub fn bool8_slice<'a>(
+    builder: &mut EventBuilder,
+    field_name: &str,
+    field_values: impl IntoIterator<Item = &'a bool>,
+    out_type: Option<OutType>,
+    field_tag: u32,
+) {
+    builder.add_u8_sequence(
+        field_name,
+        field_values
+            .into_iter()
+            .map(|value| if *value { &1u8 } else { &0u8 }),
+        out_type.unwrap_or(OutType::Boolean),
+        field_tag,
+    );
+}
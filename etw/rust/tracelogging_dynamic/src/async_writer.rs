@@ -0,0 +1,193 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::pin::Pin;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+
+use tracelogging::EventDescriptor;
+use tracelogging::Guid;
+
+use crate::builder::EventBuilder;
+use crate::provider::Provider;
+
+/// An event waiting in the [AsyncWriter] queue.
+struct QueuedEvent {
+    meta: Vec<u8>,
+    data: Vec<u8>,
+    descriptor: EventDescriptor,
+    activity_id: Option<Guid>,
+    related_id: Option<Guid>,
+}
+
+/// Writes events from a dedicated background thread (requires feature `std`).
+///
+/// An `AsyncWriter` owns a [Provider] and a bounded queue of encoded events. Calls to
+/// [`AsyncWriter::write`] move the event out of an [EventBuilder] (via
+/// [`EventBuilder::into_parts`]) and add it to the queue without blocking. A background
+/// thread removes events from the queue and sends them to ETW via
+/// [`EventBuilder::write`]. This keeps the cost of `EventWriteTransfer` off of
+/// latency-critical threads.
+///
+/// If the queue is full, the event is dropped and [`AsyncWriter::dropped_count`] is
+/// incremented. `write` never waits for space in the queue.
+///
+/// Dropping the `AsyncWriter` waits for the background thread to write all queued
+/// events and then unregisters the provider.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let writer = tld::AsyncWriter::new(
+///     tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()),
+///     1000, // Queue up to 1000 events.
+/// );
+/// unsafe { writer.register(); }
+///
+/// if writer.provider().enabled(tld::Level::Verbose, 0x1) {
+///     let mut builder = tld::EventBuilder::new();
+///     builder
+///         .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///         .add_u32("Id", 5, tld::OutType::Default, 0);
+///     writer.write(builder, None, None);
+/// }
+/// ```
+pub struct AsyncWriter {
+    provider: Pin<Arc<Provider>>,
+    sender: Option<mpsc::SyncSender<QueuedEvent>>,
+    thread: Option<thread::JoinHandle<()>>,
+    capacity: usize,
+    dropped: AtomicUsize,
+}
+
+impl AsyncWriter {
+    /// Creates a new `AsyncWriter` that owns `provider` and can queue up to `capacity`
+    /// events. Starts the background thread.
+    ///
+    /// The provider is usually unregistered at this point. Use
+    /// [`AsyncWriter::register`] to register it.
+    pub fn new(provider: Provider, capacity: usize) -> Self {
+        let provider = Arc::pin(provider);
+        let (sender, receiver) = mpsc::sync_channel::<QueuedEvent>(capacity);
+        let thread_provider = provider.clone();
+        let thread = thread::Builder::new()
+            .name(alloc::format!("tld:{}", provider.name()))
+            .spawn(move || {
+                for event in receiver {
+                    EventBuilder::from_parts(event.meta, event.data, event.descriptor).write(
+                        &thread_provider,
+                        event.activity_id.as_ref(),
+                        event.related_id.as_ref(),
+                    );
+                }
+            })
+            .expect("failed to spawn AsyncWriter thread");
+        return Self {
+            provider,
+            sender: Some(sender),
+            thread: Some(thread),
+            capacity,
+            dropped: AtomicUsize::new(0),
+        };
+    }
+
+    /// Returns the provider owned by this writer, e.g. for use with
+    /// [`Provider::enabled`].
+    pub fn provider(&self) -> Pin<&Provider> {
+        return self.provider.as_ref();
+    }
+
+    /// Registers the provider owned by this writer. Same as [`Provider::register`].
+    ///
+    /// Returns 0 for success or a Win32 error from `EventRegister` for failure. The
+    /// return value is for diagnostic purposes only and should generally be ignored in
+    /// retail builds.
+    ///
+    /// # Preconditions
+    ///
+    /// - Provider must not already be registered. Verified at runtime, failure = panic.
+    ///
+    /// # Safety
+    ///
+    /// - If the writer is in a DLL, it **must** be dropped (or the provider
+    ///   unregistered) before the DLL unloads. See [`Provider::register`].
+    pub unsafe fn register(&self) -> u32 {
+        return self.provider.as_ref().register();
+    }
+
+    /// Returns the maximum number of events that can be waiting in the queue.
+    pub const fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+
+    /// Returns the number of events that were dropped because the queue was full
+    /// (wraps on overflow).
+    pub fn dropped_count(&self) -> usize {
+        return self.dropped.load(Ordering::Relaxed);
+    }
+
+    /// Moves the event in `builder` into the queue. The background thread will send the
+    /// event to ETW via this writer's provider, using the specified activity id and
+    /// related id (same as [`EventBuilder::write`]).
+    ///
+    /// If the provider is not enabled for the event's level and keyword, the event is
+    /// discarded without being queued. If the queue is full, the event is discarded and
+    /// [`AsyncWriter::dropped_count`] is incremented.
+    ///
+    /// Returns false if the event was dropped because the queue was full, true
+    /// otherwise. The return value is for diagnostic purposes only and should generally
+    /// be ignored in retail builds.
+    pub fn write(
+        &self,
+        builder: EventBuilder,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> bool {
+        let (meta, data, descriptor) = builder.into_parts();
+        if !self.provider.enabled(descriptor.level, descriptor.keyword) {
+            return true;
+        }
+
+        let event = QueuedEvent {
+            meta,
+            data,
+            descriptor,
+            activity_id: activity_id.copied(),
+            related_id: related_id.copied(),
+        };
+        let sender = self.sender.as_ref().unwrap();
+        if sender.try_send(event).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+
+        return true;
+    }
+}
+
+impl Drop for AsyncWriter {
+    /// Waits for the background thread to write all queued events.
+    fn drop(&mut self) {
+        drop(self.sender.take()); // Closing the queue ends the thread's loop.
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl fmt::Debug for AsyncWriter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "AsyncWriter {{ provider: \"{}\", capacity: {}, dropped: {} }}",
+            self.provider.name(),
+            self.capacity,
+            self.dropped_count()
+        );
+    }
+}
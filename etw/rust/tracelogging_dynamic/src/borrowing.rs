@@ -0,0 +1,186 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::vec::Vec;
+use core::mem::size_of;
+use core::mem::size_of_val;
+use core::slice;
+
+use tracelogging::Guid;
+use tracelogging::InType;
+use tracelogging::OutType;
+
+use crate::builder::utf8_truncate;
+use crate::builder::EventBuilder;
+use crate::provider::Provider;
+
+/// An [EventBuilder] adapter that can add field values borrowed from caller-owned
+/// memory.
+///
+/// Get a `BorrowingEventBuilder` by calling [`EventBuilder::borrowing`] after
+/// [`EventBuilder::reset`]. Values added by the `add_TYPE` methods of
+/// `BorrowingEventBuilder` are not copied into the builder. Instead, each value is
+/// sent to ETW as a separate data descriptor that points at the caller's memory. This
+/// avoids allocating and copying when an event contains a few large values, e.g. a
+/// large binary blob or string.
+///
+/// Other fields (and event options) can be added via [`BorrowingEventBuilder::builder`].
+/// Fields are written in the order they were added, regardless of whether they were
+/// added via the `BorrowingEventBuilder` or via the underlying builder.
+///
+/// ETW limits the number of data descriptors per event. After
+/// [`BorrowingEventBuilder::MAX_BORROWED`] values have been borrowed, additional
+/// values are copied into the builder as usual.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = Box::pin(tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()));
+/// unsafe { provider.as_ref().register(); }
+///
+/// let payload = vec![0u8; 50000];
+/// let mut builder = tld::EventBuilder::new();
+/// builder.reset("MyEvent", tld::Level::Verbose, 0x1, 0);
+/// let mut borrowing = builder.borrowing();
+/// borrowing
+///     .builder()
+///     .add_u32("Id", 5, tld::OutType::Default, 0);
+/// borrowing.add_binaryc("Payload", &payload, tld::OutType::Default, 0);
+/// borrowing.write(&provider, None, None);
+/// ```
+#[derive(Debug)]
+pub struct BorrowingEventBuilder<'b, 'a> {
+    builder: &'b mut EventBuilder,
+    borrowed: Vec<(usize, &'a [u8])>,
+}
+
+impl<'b, 'a> BorrowingEventBuilder<'b, 'a> {
+    /// The maximum number of values that will be borrowed (62). ETW supports at most
+    /// 128 data descriptors per event. Each borrowed value uses up to 2 descriptors
+    /// (the value and the builder data that follows it), and the event uses 3 more
+    /// (provider metadata, event metadata, and the builder data before the first
+    /// borrowed value).
+    pub const MAX_BORROWED: usize = 62;
+
+    pub(crate) fn new(builder: &'b mut EventBuilder) -> Self {
+        return Self {
+            builder,
+            borrowed: Vec::new(),
+        };
+    }
+
+    /// Returns the underlying builder, for adding fields whose values should be
+    /// copied and for setting event options such as [`EventBuilder::opcode`].
+    ///
+    /// Do not call [`EventBuilder::reset`] or [`EventBuilder::write`] on the
+    /// returned builder. Use [`BorrowingEventBuilder::write`] to write the event.
+    pub fn builder(&mut self) -> &mut EventBuilder {
+        return self.builder;
+    }
+
+    /// Returns the number of values that are currently borrowed.
+    pub fn borrowed_count(&self) -> usize {
+        return self.borrowed.len();
+    }
+
+    /// Adds a BinaryC field whose value is borrowed. Same as
+    /// [`EventBuilder::add_binaryc`] (including truncation to 65535 bytes) except that
+    /// the value is not copied.
+    pub fn add_binaryc(
+        &mut self,
+        field_name: &str,
+        field_value: &'a [u8],
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        self.builder
+            .raw_add_meta_scalar(field_name, InType::BinaryC, out_type, field_tag);
+        return self.raw_add_data_counted(field_value);
+    }
+
+    /// Adds a Str8 field whose value is borrowed. Same as [`EventBuilder::add_str8`]
+    /// except that the value is not copied.
+    pub fn add_str8(
+        &mut self,
+        field_name: &str,
+        field_value: &'a [u8],
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        self.builder
+            .raw_add_meta_scalar(field_name, InType::Str8, out_type, field_tag);
+        return self.raw_add_data_counted(field_value);
+    }
+
+    /// Adds a Str8 field (counted UTF-8 string) with out_type Utf8 whose value is
+    /// borrowed. Same as [`EventBuilder::add_str`] except that the value is not copied.
+    pub fn add_str(&mut self, field_name: &str, field_value: &'a str, field_tag: u32) -> &mut Self {
+        self.builder
+            .raw_add_meta_scalar(field_name, InType::Str8, OutType::Utf8, field_tag);
+        return self.raw_add_data_counted(utf8_truncate(field_value));
+    }
+
+    /// Adds a Str16 field whose value is borrowed. Same as [`EventBuilder::add_str16`]
+    /// except that the value is not copied.
+    pub fn add_str16(
+        &mut self,
+        field_name: &str,
+        field_value: &'a [u16],
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        self.builder
+            .raw_add_meta_scalar(field_name, InType::Str16, out_type, field_tag);
+        return self.raw_add_data_counted(field_value);
+    }
+
+    /// *Advanced scenarios:* Directly adds unchecked borrowed data to the event. Same
+    /// as [`EventBuilder::raw_add_data_slice`] except that the value is not copied.
+    pub fn raw_add_data_slice<T: Copy>(&mut self, value: &'a [T]) -> &mut Self {
+        if value.is_empty() {
+            // Nothing to send.
+        } else if self.borrowed.len() >= Self::MAX_BORROWED {
+            self.builder.raw_add_data_slice(value);
+        } else {
+            // Safety: value is a valid slice of Copy elements, viewed as bytes.
+            let bytes =
+                unsafe { slice::from_raw_parts(value.as_ptr() as *const u8, size_of_val(value)) };
+            self.borrowed.push((self.builder.data_len(), bytes));
+        }
+        return self;
+    }
+
+    /// Sends the event to ETW via the specified provider. Same as
+    /// [`EventBuilder::write`], except that the borrowed values are sent along with
+    /// the builder's data.
+    ///
+    /// Returns 0 for success or a Win32 error from `EventWrite` for failure. The return
+    /// value is for diagnostic purposes only and should generally be ignored in retail
+    /// builds.
+    pub fn write(
+        &mut self,
+        provider: &Provider,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        return self.builder.write_impl(
+            provider,
+            provider.sampling.is_active(),
+            activity_id,
+            related_id,
+            &self.borrowed,
+        );
+    }
+
+    fn raw_add_data_counted<T: Copy>(&mut self, value: &'a [T]) -> &mut Self {
+        let max_len = 65535 / size_of::<T>();
+        let value = if value.len() > max_len {
+            &value[0..max_len]
+        } else {
+            value
+        };
+        self.builder
+            .raw_add_data_value(&((value.len() as u16) * (size_of::<T>() as u16)));
+        return self.raw_add_data_slice(value);
+    }
+}
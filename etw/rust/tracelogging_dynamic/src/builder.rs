@@ -1,20 +1,59 @@
 // Copyright (c) Microsoft Corporation.
 // Licensed under the MIT license.
 
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
 use core::mem::size_of;
-use core::ptr::copy_nonoverlapping;
+use core::sync::atomic;
+#[cfg(feature = "std")]
+use std::net;
 
+use tracelogging::_internal::sid_length;
+use tracelogging::_internal::EventDataDescriptor;
+use tracelogging::_internal::EventDescriptor;
+use tracelogging::_internal::MetaReader;
+use tracelogging::_internal::NULL_SID;
 use tracelogging::Channel;
 use tracelogging::Guid;
 use tracelogging::InType;
 use tracelogging::Level;
 use tracelogging::Opcode;
 use tracelogging::OutType;
-use tracelogging::_internal::EventDataDescriptor;
-use tracelogging::_internal::EventDescriptor;
-
+use tracelogging::WriteError;
+use tracelogging::WriteFlags;
+
+use crate::borrowing::BorrowingEventBuilder;
+use crate::capture::CapturedEvent;
+use crate::dictionary::StringDictionary;
+use crate::error_message::format_error_message;
+use crate::error_message::ErrorCodeKind;
+use crate::event_field::IntoEventField;
 use crate::provider::Provider;
+use crate::provider_set::ProviderSet;
+use crate::sanitizer::sanitize_fields;
+use crate::schema::Schema;
+use crate::schema::SchemaMismatch;
+use crate::small_buffer::SmallBuffer;
+
+/// Upper bound on the size of a chunk event's metadata (event name and field
+/// definitions) plus the data of its fixed-size fields and binary length prefixes.
+const CHUNK_OVERHEAD: usize = 128;
+
+/// Returns a locally-unique id for the chunks of an event written by
+/// [`EventBuilder::write_chunked`].
+fn new_chunk_id() -> Guid {
+    static NEXT_CHUNK_ID: atomic::AtomicUsize = atomic::AtomicUsize::new(1);
+
+    let id = Provider::create_activity_id();
+    return if id != Guid::zero() {
+        id
+    } else {
+        // create_activity_id is not supported if not Windows/etw.
+        Guid::from_u128(&(NEXT_CHUNK_ID.fetch_add(1, atomic::Ordering::Relaxed) as u128))
+    };
+}
 
 /// `EventBuilder` is a builder for events to be written through a [Provider].
 ///
@@ -75,39 +114,123 @@ use crate::provider::Provider;
 /// and data (field values). Events that are too large will cause builder.write to return
 /// an error.
 ///
+/// To write events that may exceed the limit, use [`EventBuilder::write_chunked`],
+/// which splits oversized events into a series of smaller continuation events.
+///
 /// Most ETW decoding tools are unable to decode an event with more than 128 fields.
 #[derive(Debug)]
 pub struct EventBuilder {
-    meta: Vec<u8>,
-    data: Vec<u8>,
+    meta: SmallBuffer,
+    data: SmallBuffer,
     descriptor: EventDescriptor,
 }
 
 impl EventBuilder {
+    /// The maximum number of bytes in a single `Binary` or `BinaryC` field (65535).
+    /// Longer values are truncated by [`EventBuilder::add_binaryc`] and split by
+    /// [`EventBuilder::add_binaryc_chunked`].
+    pub const BINARYC_CHUNK_MAX: usize = u16::MAX as usize;
+
+    /// The largest event (provider metadata + event metadata + data, in bytes) that
+    /// [`EventBuilder::write_chunked`] writes as a single event (63 KB). This leaves
+    /// 1 KB of the 64 KB ETW limit for the event headers.
+    pub const CHUNKED_EVENT_SIZE_MAX: usize = 63 * 1024;
+
+    /// Name of the continuation events written by [`EventBuilder::write_chunked`].
+    pub const CHUNK_EVENT_NAME: &'static str = "EventChunk";
+
     /// Returns a new event builder with default initial buffer capacity.
     ///
     /// Default capacity is currently 256 bytes for meta and 256 bytes for data.
     /// Buffers will automatically grow as needed.
+    ///
+    /// Buffers of up to 256 bytes are stored inline in the builder, so creating a
+    /// builder and building and writing a small event does not allocate.
     pub fn new() -> EventBuilder {
         return Self::new_with_capacity(256, 256);
     }
 
     /// Returns a new event builder with specified initial buffer capacities.
-    /// Buffers will automatically grow as needed.
+    /// Buffers will automatically grow as needed. Capacities of 256 bytes or less do
+    /// not allocate.
     pub fn new_with_capacity(meta_capacity: u16, data_capacity: u16) -> EventBuilder {
         let mut b = EventBuilder {
-            meta: Vec::with_capacity(if meta_capacity < 4 {
+            meta: SmallBuffer::with_capacity(if meta_capacity < 4 {
                 4
             } else {
                 meta_capacity as usize
             }),
-            data: Vec::with_capacity(data_capacity as usize),
+            data: SmallBuffer::with_capacity(data_capacity as usize),
             descriptor: EventDescriptor::zero(),
         };
-        b.meta.resize(4, 0); // u16 size = 0, u8 tag = 0, u8 name_nul_termination = 0;
+        b.meta.extend_from_slice(&[0; 4]); // u16 size = 0, u8 tag = 0, u8 name_nul_termination = 0;
         return b;
     }
 
+    /// Returns a builder for an event previously taken apart by
+    /// [`EventBuilder::into_parts`].
+    ///
+    /// This allows a partially or fully built event to be stored or sent to another
+    /// thread (e.g. built on a hot thread, then written by a background thread) as
+    /// plain buffers. Fields may be added to the returned builder as usual.
+    ///
+    /// `meta` and `data` should be values returned by `into_parts`. Panics if `meta` is
+    /// too short to contain an event name. Other invalid values will result in events
+    /// that do not decode correctly.
+    pub fn from_parts(meta: Vec<u8>, data: Vec<u8>, descriptor: EventDescriptor) -> EventBuilder {
+        assert!(
+            meta.len() >= 4 && meta[2..].contains(&0),
+            "meta must contain an event name"
+        );
+        return EventBuilder {
+            meta: SmallBuffer::from_vec(meta),
+            data: SmallBuffer::from_vec(data),
+            descriptor,
+        };
+    }
+
+    /// Consumes the builder and returns the event's metadata, data, and descriptor.
+    /// Use [`EventBuilder::from_parts`] to rebuild the builder.
+    ///
+    /// The returned buffers use an internal encoding (TraceLogging metadata and field
+    /// data as they will be sent to ETW). They should be treated as opaque and passed
+    /// unchanged to `from_parts`, e.g. in the same process after moving them across a
+    /// queue.
+    ///
+    /// The returned buffers are always heap-allocated, so this allocates if the
+    /// event was small enough to be stored inline in the builder.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = Box::pin(tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()));
+    /// unsafe { provider.as_ref().register(); }
+    ///
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder
+    ///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+    ///     .add_u32("Id", 5, tld::OutType::Default, 0);
+    /// let (meta, data, descriptor) = builder.into_parts();
+    ///
+    /// std::thread::spawn(move || {
+    ///     let mut builder = tld::EventBuilder::from_parts(meta, data, descriptor);
+    ///     builder.write(&provider, None, None);
+    /// })
+    /// .join()
+    /// .unwrap();
+    /// ```
+    pub fn into_parts(self) -> (Vec<u8>, Vec<u8>, EventDescriptor) {
+        return (self.meta.into_vec(), self.data.into_vec(), self.descriptor);
+    }
+
+    /// Returns the total capacity (in bytes) of the builder's metadata and data
+    /// buffers. The buffers grow as needed and are not shrunk by
+    /// [`EventBuilder::reset`], so the capacity reflects the largest event built so far.
+    /// Buffers stored inline in the builder count as 256 bytes each.
+    pub fn capacity(&self) -> usize {
+        return self.meta.capacity() + self.data.capacity();
+    }
+
     /// Clears the previous event (if any) from the builder and starts building a new
     /// event.
     ///
@@ -167,7 +290,11 @@ impl EventBuilder {
     /// builds.
     ///
     /// provider: Should usually be a registered provider. Calling write on an
-    /// unregistered provider is a no-op.
+    /// unregistered provider is a no-op. If the provider has a sampling rate configured
+    /// for this event (see [Provider::set_event_sampling]), events skipped by sampling
+    /// are not sent and write returns 0. Likewise, events whose name matches the
+    /// provider's deny-list (see [Provider::set_event_deny_list]) are not sent and
+    /// write returns 0.
     ///
     /// activity_id: Contains the activity id to be used for the event. If None, the event will
     /// use the current thread's thread-local activity id for its activity id.
@@ -181,29 +308,446 @@ impl EventBuilder {
         activity_id: Option<&Guid>,
         related_id: Option<&Guid>,
     ) -> u32 {
+        return self.write_impl(
+            provider,
+            provider.sampling.is_active(),
+            activity_id,
+            related_id,
+            &[],
+        );
+    }
+
+    /// Same as [`EventBuilder::write`], but returns a [WriteError] instead of a Win32
+    /// error code if the write fails.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// let result = tld::EventBuilder::new()
+    ///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+    ///     .try_write(&provider, None, None);
+    /// if let Err(tld::WriteError::BuffersFull) = result {
+    ///     // Session is busy: event was dropped.
+    /// }
+    /// ```
+    pub fn try_write(
+        &mut self,
+        provider: &Provider,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> Result<(), WriteError> {
+        return WriteError::check(self.write(provider, activity_id, related_id));
+    }
+
+    /// *Advanced:* Sends the built event to ETW via the specified provider using
+    /// `EventWriteEx`, with per-write control over the ETW write flags and the sessions
+    /// that receive the event.
+    ///
+    /// filter: A bitmask of the ids of sessions that should NOT receive the event, or
+    /// 0 to send the event to all sessions. A session's id is provided to the enable
+    /// callback (the `filter_data` parameter of a [`ProviderEnableCallback`] for
+    /// `EVENT_FILTER_TYPE_...` session filters).
+    ///
+    /// flags: A combination of [WriteFlags] values, e.g. [WriteFlags::InPrivate].
+    ///
+    /// Otherwise the same as [EventBuilder::write]. If `filter` is 0 and `flags` is
+    /// [WriteFlags::None], the event is written with `EventWriteTransfer`.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// let result = tld::EventBuilder::new()
+    ///     .reset("PageVisit", tld::Level::Verbose, 0x1, 0)
+    ///     .add_str8("Url", "https://example.com/", tld::OutType::Default, 0)
+    ///     .write_ex(&provider, 0, tld::WriteFlags::InPrivate, None, None);
+    /// assert_eq!(0, result);
+    /// ```
+    ///
+    /// [`ProviderEnableCallback`]: crate::ProviderEnableCallback
+    pub fn write_ex(
+        &mut self,
+        provider: &Provider,
+        filter: u64,
+        flags: WriteFlags,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        return self.write_impl_ex(
+            provider,
+            provider.sampling.is_active(),
+            filter,
+            flags,
+            activity_id,
+            related_id,
+            &[],
+        );
+    }
+
+    /// Sends the built event to ETW via each provider in the specified set.
+    ///
+    /// This is the same as calling [`EventBuilder::write`] for each provider in the
+    /// set, in order: the event's metadata and data are not rebuilt, and each
+    /// provider's sampling, deny-list, sanitizer, and capture settings apply to that
+    /// provider's write.
+    ///
+    /// Returns 0 if all writes succeed, or the Win32 error code of the first write that
+    /// fails. A failed write does not prevent the writes to the remaining providers.
+    pub fn write_set(
+        &mut self,
+        providers: &ProviderSet,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        let mut result = 0;
+        for provider in providers.providers() {
+            let provider_result = self.write(provider, activity_id, related_id);
+            if result == 0 {
+                result = provider_result;
+            }
+        }
+        return result;
+    }
+
+    /// Sends the built event to ETW via the specified provider, splitting it into
+    /// continuation events if it is too large to be written as a single event.
+    ///
+    /// If the event's size (provider metadata + event metadata + data) is at most
+    /// [`EventBuilder::CHUNKED_EVENT_SIZE_MAX`], this is the same as
+    /// [`EventBuilder::write`]. Otherwise, instead of writing the event (which ETW would
+    /// drop), writes a series of events named
+    /// [`EventChunk`](EventBuilder::CHUNK_EVENT_NAME), each of which is small enough
+    /// to be written. The chunk events have the following fields:
+    ///
+    /// - `ChunkId` (Guid): a locally-unique id shared by all chunks of the event.
+    /// - `ChunkIndex` (U16): the index of the chunk, starting at 0.
+    /// - `ChunkCount` (U16): the number of chunks.
+    /// - `EventMeta` (Binary): in chunk 0, the original event's TraceLogging metadata
+    ///   (metadata size, event tag, event name, and field definitions). Empty in the
+    ///   other chunks.
+    /// - `EventData` (Binary): the next portion of the original event's field data.
+    ///
+    /// To reassemble the event, collect the chunks with the same `ChunkId`, then
+    /// decode the concatenation of their `EventData` values (in `ChunkIndex` order)
+    /// using the `EventMeta` from chunk 0. The chunk events have the original event's
+    /// level, keyword, channel, and task, but have id 0 and version 0. Chunk 0 has
+    /// the original event's opcode and `related_id`; the other chunks use
+    /// [`Opcode::Info`] and no related id. All chunks use `activity_id`.
+    ///
+    /// The deny-list and sampling are applied once, to the original event. If writing
+    /// a chunk fails, the remaining chunks are not written. Returns 0 for success or a
+    /// Win32 error code for failure, e.g. 534 (`ERROR_ARITHMETIC_OVERFLOW`) if the
+    /// event's metadata is too large to fit into a chunk.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_capture(16);
+    ///
+    /// let report = vec![b'x'; 100_000];
+    /// tld::EventBuilder::new()
+    ///     .reset("Report", tld::Level::Verbose, 0x1, 0)
+    ///     .add_str8("Text", &report, tld::OutType::Default, 0)
+    ///     .write_chunked(&provider, None, None);
+    ///
+    /// let events = provider.drain_captured();
+    /// assert_eq!(2, events.len());
+    /// assert_eq!(tld::EventBuilder::CHUNK_EVENT_NAME, events[0].name());
+    /// ```
+    pub fn write_chunked(
+        &mut self,
+        provider: &Provider,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        if !self.should_write(provider) {
+            return 0; // Skipped by deny-list or sampling.
+        }
+
+        let meta_len = self.meta.len();
+        let chunk_data_max =
+            Self::CHUNKED_EVENT_SIZE_MAX.saturating_sub(provider.meta.len() + CHUNK_OVERHEAD);
+        if provider.meta.len() + meta_len + self.data.len() <= Self::CHUNKED_EVENT_SIZE_MAX {
+            return self.write_impl(provider, false, activity_id, related_id, &[]);
+        } else if meta_len >= chunk_data_max {
+            return 534; // ERROR_ARITHMETIC_OVERFLOW
+        }
+
+        let first_chunk_len = chunk_data_max - meta_len;
+        let remaining_len = self.data.len().saturating_sub(first_chunk_len);
+        let chunk_count = 1 + (remaining_len + chunk_data_max - 1) / chunk_data_max;
+        if chunk_count > u16::MAX as usize {
+            return 534; // ERROR_ARITHMETIC_OVERFLOW
+        }
+
+        self.meta[0] = meta_len as u8;
+        self.meta[1] = (meta_len >> 8) as u8;
+
+        // Sanitize before splitting: the chunks' fields are not the event's fields.
+        let sanitized;
+        let data = match provider.field_sanitizer() {
+            None => &self.data[..],
+            Some(sanitizer) => {
+                let mut copy = self.data.to_vec();
+                sanitize_fields(&self.meta, &mut copy, sanitizer);
+                sanitized = copy;
+                &sanitized[..]
+            }
+        };
+
+        let chunk_id = new_chunk_id();
+        let mut chunk = EventBuilder::new_with_capacity(256, (chunk_data_max + 32) as u16);
+        let mut data_pos = 0;
+        let mut result = 0;
+        for chunk_index in 0..chunk_count {
+            let chunk_len = if chunk_index == 0 {
+                first_chunk_len
+            } else {
+                chunk_data_max
+            }
+            .min(data.len() - data_pos);
+
+            chunk
+                .reset(
+                    Self::CHUNK_EVENT_NAME,
+                    self.descriptor.level,
+                    self.descriptor.keyword,
+                    0,
+                )
+                .add_guid("ChunkId", &chunk_id, OutType::Default, 0)
+                .add_u16("ChunkIndex", chunk_index as u16, OutType::Default, 0)
+                .add_u16("ChunkCount", chunk_count as u16, OutType::Default, 0)
+                .add_binary(
+                    "EventMeta",
+                    if chunk_index == 0 {
+                        &self.meta[..]
+                    } else {
+                        &[]
+                    },
+                    OutType::Default,
+                    0,
+                )
+                .add_binary(
+                    "EventData",
+                    &data[data_pos..data_pos + chunk_len],
+                    OutType::Default,
+                    0,
+                );
+            chunk.descriptor = EventDescriptor {
+                id: 0,
+                version: 0,
+                ..self.descriptor
+            };
+            if chunk_index != 0 {
+                chunk.descriptor.opcode = Opcode::Info;
+            }
+
+            // Send without sanitizing: the chunk's data is already sanitized.
+            provider.context.note_write();
+            let chunk_meta_len = chunk.meta.len();
+            chunk.meta[0] = chunk_meta_len as u8;
+            chunk.meta[1] = (chunk_meta_len >> 8) as u8;
+            result = chunk.send(
+                provider,
+                &chunk.data,
+                activity_id,
+                if chunk_index == 0 { related_id } else { None },
+                &[],
+                0,
+                WriteFlags::None,
+            );
+            if result != 0 {
+                break;
+            }
+
+            data_pos += chunk_len;
+        }
+
+        return result;
+    }
+
+    /// Returns a [BorrowingEventBuilder] that adds fields to this builder's current
+    /// event and can also add fields whose values are borrowed from caller-owned memory
+    /// instead of being copied into the builder.
+    pub fn borrowing<'a>(&mut self) -> BorrowingEventBuilder<'_, 'a> {
+        return BorrowingEventBuilder::new(self);
+    }
+
+    /// Writes the event. `borrowed` is a list of (data offset, value) pairs, sorted by
+    /// offset: each value is sent to ETW as if it had been inserted into `self.data`
+    /// at the corresponding offset.
+    pub(crate) fn write_impl(
+        &mut self,
+        provider: &Provider,
+        check_sampling: bool,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+        borrowed: &[(usize, &[u8])],
+    ) -> u32 {
+        return self.write_impl_ex(
+            provider,
+            check_sampling,
+            0,
+            WriteFlags::None,
+            activity_id,
+            related_id,
+            borrowed,
+        );
+    }
+
+    /// Same as write_impl, but writes with EventWriteEx if `filter` or `flags` is
+    /// nonzero.
+    #[allow(clippy::too_many_arguments)]
+    fn write_impl_ex(
+        &mut self,
+        provider: &Provider,
+        check_sampling: bool,
+        filter: u64,
+        flags: WriteFlags,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+        borrowed: &[(usize, &[u8])],
+    ) -> u32 {
+        provider.context.note_write();
+
         let result;
         let meta_len = self.meta.len();
-        if meta_len > 65535 {
+        if check_sampling && !self.should_write(provider) {
+            provider.context.note_filtered();
+            result = 0; // Skipped by deny-list or sampling.
+        } else if meta_len > 65535 {
             result = 534; // ERROR_ARITHMETIC_OVERFLOW
         } else {
             self.meta[0] = meta_len as u8;
             self.meta[1] = (meta_len >> 8) as u8;
+            if let Some(sanitizer) = provider.field_sanitizer() {
+                // Sanitize a copy so that the builder and borrowed values are unchanged.
+                let mut data = merge_borrowed(&self.data, borrowed);
+                sanitize_fields(&self.meta, &mut data, sanitizer);
+                result = self.send(provider, &data, activity_id, related_id, &[], filter, flags);
+            } else {
+                result = self.send(
+                    provider,
+                    &self.data,
+                    activity_id,
+                    related_id,
+                    borrowed,
+                    filter,
+                    flags,
+                );
+            }
+        }
+        return result;
+    }
+
+    /// Captures (if capture is enabled), mirrors (if an event log bridge is set), and
+    /// sends the event with the specified data.
+    /// The metadata size must already be set.
+    #[allow(clippy::too_many_arguments)]
+    fn send(
+        &self,
+        provider: &Provider,
+        data: &[u8],
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+        borrowed: &[(usize, &[u8])],
+        filter: u64,
+        flags: WriteFlags,
+    ) -> u32 {
+        if provider.capture.is_active() {
+            self.capture(provider, data, activity_id, related_id, borrowed);
+        }
+
+        #[cfg(feature = "eventlog")]
+        if provider.event_log.is_active() {
+            provider
+                .event_log
+                .report(provider.name(), &self.descriptor, &self.meta, || {
+                    merge_borrowed(data, borrowed)
+                });
+        }
+
+        let result;
+        let ctx = &provider.context;
+        let activity_id = activity_id.map(|g| g.as_bytes_raw());
+        let related_id = related_id.map(|g| g.as_bytes_raw());
+        if borrowed.is_empty() {
             let dd = [
                 EventDataDescriptor::from_raw_bytes(&provider.meta, 2), // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
                 EventDataDescriptor::from_raw_bytes(&self.meta, 1), // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
-                EventDataDescriptor::from_raw_bytes(&self.data, 0), // EVENT_DATA_DESCRIPTOR_TYPE_NONE
+                EventDataDescriptor::from_raw_bytes(data, 0), // EVENT_DATA_DESCRIPTOR_TYPE_NONE
             ];
-            let ctx = &provider.context;
-            result = ctx.write_transfer(
+            result = ctx.write_ex(
                 &self.descriptor,
-                activity_id.map(|g| g.as_bytes_raw()),
-                related_id.map(|g| g.as_bytes_raw()),
+                filter,
+                flags,
+                activity_id,
+                related_id,
+                &dd,
+            );
+        } else {
+            let mut dd = Vec::with_capacity(3 + 2 * borrowed.len());
+            dd.push(EventDataDescriptor::from_raw_bytes(&provider.meta, 2)); // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
+            dd.push(EventDataDescriptor::from_raw_bytes(&self.meta, 1)); // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
+            let mut data_pos = 0;
+            for &(offset, value) in borrowed {
+                let offset = offset.min(data.len());
+                if data_pos < offset {
+                    dd.push(EventDataDescriptor::from_raw_bytes(
+                        &data[data_pos..offset],
+                        0,
+                    ));
+                    data_pos = offset;
+                }
+                dd.push(EventDataDescriptor::from_raw_bytes(value, 0));
+            }
+            if data_pos != data.len() {
+                dd.push(EventDataDescriptor::from_raw_bytes(&data[data_pos..], 0));
+            }
+            result = ctx.write_ex(
+                &self.descriptor,
+                filter,
+                flags,
+                activity_id,
+                related_id,
                 &dd,
             );
         }
         return result;
     }
 
+    /// Copies the event into the provider's capture buffer, merging borrowed values
+    /// into the data.
+    fn capture(
+        &self,
+        provider: &Provider,
+        data: &[u8],
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+        borrowed: &[(usize, &[u8])],
+    ) {
+        provider.capture.push(CapturedEvent {
+            descriptor: self.descriptor,
+            activity_id: activity_id.copied(),
+            related_id: related_id.copied(),
+            meta: self.meta.to_vec(),
+            data: merge_borrowed(data, borrowed),
+        });
+    }
+
+    /// Checks that the fields added to the event since the last call to `reset` match
+    /// the specified schema (field names, intypes, and array-ness, in order).
+    ///
+    /// Returns `Ok(())` if the event matches the schema, or a [SchemaMismatch]
+    /// describing the first field that does not match. This is intended for use in
+    /// debug builds or in a strict mode, e.g.
+    /// `debug_assert!(builder.validate(&schema).is_ok())`.
+    pub fn validate(&self, schema: &Schema) -> Result<(), SchemaMismatch> {
+        return schema.validate_meta(&self.meta);
+    }
+
     /// Sets the id and version of the event. Default is id = 0, version = 0.
     ///
     /// TraceLogging events are primarily identified by event name, not by id.
@@ -263,6 +807,30 @@ impl EventBuilder {
         return self;
     }
 
+    /// Adds a field with the InType and OutType that best match the type of the value,
+    /// e.g. U32 for `u32`, Str8 with format Utf8 for `&str`, or a variable-length
+    /// array of Guid for `&[Guid]`. See [IntoEventField] for the supported types.
+    ///
+    /// This is a convenience for code that maps values from another type system onto
+    /// event fields. The field has no field tag. Use the `add_xxx` methods to specify a
+    /// non-default format or a field tag.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder
+    ///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+    ///     .add("Count", 5u32)
+    ///     .add("Name", "Hello")
+    ///     .add("Ok", true)
+    ///     .add("Values", &[1.5f64, 2.5]);
+    /// ```
+    pub fn add(&mut self, field_name: &str, field_value: impl IntoEventField) -> &mut Self {
+        field_value.add_to(self, field_name);
+        return self;
+    }
+
     /// Adds a CStr16 field (nul-terminated UTF16-LE) from a `&[u16]` value.
     ///
     /// If the string contains characters after a `'\0'`, they will be discarded.
@@ -573,6 +1141,64 @@ impl EventBuilder {
             });
     }
 
+    /// Adds a U32 field from a `char` value (UTF-32 code point).
+    ///
+    /// Usually out_type should be String (formats as UTF-32 char). If out_type is
+    /// Default, field will format as Unsigned (the code point's value).
+    /// Other useful out_type value: Hex.
+    pub fn add_char32(
+        &mut self,
+        field_name: &str,
+        field_value: char,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self
+            .raw_add_meta_scalar(field_name, InType::U32, out_type, field_tag)
+            .raw_add_data_value(&(field_value as u32));
+    }
+
+    /// Adds a U32 variable-length array field from an iterator-of-`&char` value
+    /// (UTF-32 code points).
+    ///
+    /// Usually out_type should be String (formats as UTF-32 chars). If out_type is
+    /// Default, field will format as Unsigned (the code points' values).
+    /// Other useful out_type value: Hex.
+    pub fn add_char32_sequence<'a>(
+        &mut self,
+        field_name: &str,
+        field_values: impl IntoIterator<Item = &'a char>,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self
+            .raw_add_meta_vcount(field_name, InType::U32, out_type, field_tag)
+            .raw_add_data_range(field_values, |this, value| {
+                this.raw_add_data_value(&(*value as u32));
+            });
+    }
+
+    /// Adds a UTF-32 string field from a `&[char]` value. This is the same as
+    /// `add_char32_sequence` with out_type String.
+    ///
+    /// ETW has no UTF-32 string encoding, so the field is a U32 variable-length array
+    /// with format String, which decoders may display as an array of characters
+    /// rather than as a string. Prefer `add_str8` for strings that are available as
+    /// `&str`.
+    pub fn add_str32(
+        &mut self,
+        field_name: &str,
+        field_value: impl AsRef<[char]>,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self.add_char32_sequence(
+            field_name,
+            field_value.as_ref(),
+            OutType::String,
+            field_tag,
+        );
+    }
+
     /// Adds an I64 field from an `i64` value.
     ///
     /// If out_type is Default, field will format as Signed.
@@ -814,7 +1440,8 @@ impl EventBuilder {
     /// Note: There is no `add_binary_sequence` method because the ETW's `Binary` encoding does
     /// not decode correctly with arrays. Array of binary can be created using
     /// `add_binaryc_sequence`, though the resulting event will only decode correctly
-    /// if the decoder supports ETW's newer `BinaryC` encoding.
+    /// if the decoder supports ETW's newer `BinaryC` encoding. `add_binary_auto_sequence`
+    /// chooses between `BinaryC` and a struct-of-`Binary` workaround.
     pub fn add_binary(
         &mut self,
         field_name: &str,
@@ -827,6 +1454,139 @@ impl EventBuilder {
             .raw_add_data_counted(field_value.as_ref());
     }
 
+    /// Adds a Binary field containing at most the first `max_len` bytes of a `&[u8]`
+    /// value, followed by a U32 field named "{field_name}Length" containing the
+    /// original length of the value (saturated to `u32::MAX`).
+    ///
+    /// Use this for values that may be large (e.g. packet or buffer dumps) when the
+    /// first few bytes are enough for diagnosis. The length field shows whether the
+    /// value was truncated. `field_tag` applies only to the Binary field.
+    ///
+    /// If out_type is Default, field will format as Hex.
+    pub fn add_binary_capped(
+        &mut self,
+        field_name: &str,
+        field_value: impl AsRef<[u8]>,
+        max_len: u16,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        let value = field_value.as_ref();
+        let original_length = u32::try_from(value.len()).unwrap_or(u32::MAX);
+        self.add_binary(
+            field_name,
+            &value[..value.len().min(max_len as usize)],
+            out_type,
+            field_tag,
+        );
+
+        // "{field_name}Length" without allocating a new name.
+        self.meta.extend_from_slice(field_name.as_bytes());
+        self.meta.extend_from_slice(b"Length\0");
+        self.meta.push(InType::U32.as_int());
+        return self.raw_add_data_value(&original_length);
+    }
+
+    /// Adds a string field that is written as a `U16` id if the value is in the
+    /// specified [StringDictionary], or as a UTF-8 string otherwise.
+    ///
+    /// If `dictionary.id(field_value)` is `Some(id)`, adds a U16 field named
+    /// `"{field_name}DictId"` with value `id`. Otherwise, this is the same as
+    /// `add_str(field_name, field_value, field_tag)`. See [StringDictionary] for
+    /// details.
+    pub fn add_str_dict(
+        &mut self,
+        field_name: &str,
+        field_value: &str,
+        dictionary: &StringDictionary,
+        field_tag: u32,
+    ) -> &mut Self {
+        return match dictionary.id(field_value) {
+            None => self.add_str(field_name, field_value, field_tag),
+            Some(id) => {
+                // "{field_name}DictId" without allocating a new name.
+                self.meta.extend_from_slice(field_name.as_bytes());
+                self.raw_add_meta(
+                    StringDictionary::ID_SUFFIX,
+                    InType::U16.as_int(),
+                    OutType::Default.as_int(),
+                    field_tag,
+                )
+                .raw_add_data_value(&id)
+            }
+        };
+    }
+
+    /// Adds a U32 field with format IPv4 from a `&Ipv4Addr` value (requires feature
+    /// `std`).
+    #[cfg(feature = "std")]
+    pub fn add_ipv4(
+        &mut self,
+        field_name: &str,
+        field_value: &net::Ipv4Addr,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self
+            .raw_add_meta_scalar(field_name, InType::U32, OutType::IPv4, field_tag)
+            .raw_add_data_value(&field_value.octets());
+    }
+
+    /// Adds a Binary field with format IPv6 from a `&Ipv6Addr` value (requires feature
+    /// `std`).
+    #[cfg(feature = "std")]
+    pub fn add_ipv6(
+        &mut self,
+        field_name: &str,
+        field_value: &net::Ipv6Addr,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self
+            .raw_add_meta_scalar(field_name, InType::Binary, OutType::IPv6, field_tag)
+            .raw_add_data_counted(&field_value.octets());
+    }
+
+    /// Adds a Binary field with format SocketAddress from a `&SocketAddr` value
+    /// (requires feature `std`).
+    ///
+    /// The value is encoded as a Windows `SOCKADDR_IN` (for V4) or `SOCKADDR_IN6`
+    /// (for V6) structure.
+    #[cfg(feature = "std")]
+    pub fn add_socketaddr(
+        &mut self,
+        field_name: &str,
+        field_value: &net::SocketAddr,
+        field_tag: u32,
+    ) -> &mut Self {
+        const AF_INET: u16 = 2;
+        const AF_INET6: u16 = 23;
+
+        self.raw_add_meta_scalar(
+            field_name,
+            InType::Binary,
+            OutType::SocketAddress,
+            field_tag,
+        );
+        match field_value {
+            net::SocketAddr::V4(addr) => {
+                let mut sockaddr = [0u8; 16]; // SOCKADDR_IN
+                sockaddr[0..2].copy_from_slice(&AF_INET.to_le_bytes());
+                sockaddr[2..4].copy_from_slice(&addr.port().to_be_bytes());
+                sockaddr[4..8].copy_from_slice(&addr.ip().octets());
+                self.raw_add_data_counted(&sockaddr);
+            }
+            net::SocketAddr::V6(addr) => {
+                let mut sockaddr = [0u8; 28]; // SOCKADDR_IN6
+                sockaddr[0..2].copy_from_slice(&AF_INET6.to_le_bytes());
+                sockaddr[2..4].copy_from_slice(&addr.port().to_be_bytes());
+                sockaddr[4..8].copy_from_slice(&addr.flowinfo().to_be_bytes());
+                sockaddr[8..24].copy_from_slice(&addr.ip().octets());
+                sockaddr[24..28].copy_from_slice(&addr.scope_id().to_le_bytes());
+                self.raw_add_data_counted(&sockaddr);
+            }
+        }
+        return self;
+    }
+
     /// Adds a Guid field from a `&Guid` value.
     ///
     /// GUID is assumed to be encoded in Windows (little-endian) byte order.
@@ -949,7 +1709,9 @@ impl EventBuilder {
 
     /// Adds a Sid field from a `&[u8]` value.
     ///
-    /// Sid size is determined by `8 + field_value[1] * 4`.
+    /// Sid size is determined by `8 + field_value[1] * 4`. If the value is not a valid
+    /// SID (e.g. it is shorter than the sid size), the field's value is the NULL SID
+    /// (`S-1-0-0`). Use [`EventBuilder::try_add_sid`] to detect invalid values.
     ///
     /// If out_type is Default, field will format as SID.
     pub fn add_sid(
@@ -964,9 +1726,47 @@ impl EventBuilder {
             .raw_add_data_sid(field_value.as_ref());
     }
 
+    /// Adds a Sid field from a `&[u8]` value, or returns `Err(ERROR_INVALID_PARAMETER)`
+    /// (87) without adding the field if the value is not a valid SID.
+    ///
+    /// A value is a valid SID if it has revision 1, at most 15 sub-authorities, and is
+    /// at least `8 + field_value[1] * 4` bytes long. Use this instead of
+    /// [`EventBuilder::add_sid`] for values from untrusted sources (e.g. IPC) if the
+    /// event should record that the value was malformed:
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let sid_from_ipc: &[u8] = &[1, 1, 0, 0]; // Truncated.
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder.reset("Request", tld::Level::Informational, 0x1, 0);
+    /// if builder.try_add_sid("User", sid_from_ipc, tld::OutType::Default, 0).is_err() {
+    ///     // Log the raw bytes instead.
+    ///     builder.add_binary("UserInvalid", sid_from_ipc, tld::OutType::Default, 0);
+    /// }
+    /// ```
+    pub fn try_add_sid(
+        &mut self,
+        field_name: &str,
+        field_value: impl AsRef<[u8]>,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> Result<&mut Self, u32> {
+        const ERROR_INVALID_PARAMETER: u32 = 87;
+
+        let value = field_value.as_ref();
+        return match sid_length(value) {
+            Some(len) => Ok(self
+                .raw_add_meta_scalar(field_name, InType::Sid, out_type, field_tag)
+                .raw_add_data_slice(&value[..len])),
+            None => Err(ERROR_INVALID_PARAMETER),
+        };
+    }
+
     /// Adds a Sid variable-length array field from an iterator-of-`&[u8]` value.
     ///
-    /// Sid size is determined by `8 + field_value[1] * 4`.
+    /// Sid size is determined by `8 + field_value[1] * 4`. Values that are not valid
+    /// SIDs are logged as the NULL SID (`S-1-0-0`).
     ///
     /// If out_type is Default, field will format as SID.
     pub fn add_sid_sequence<T: IntoIterator>(
@@ -1182,6 +1982,193 @@ impl EventBuilder {
             });
     }
 
+    /// Adds a Str8 field (counted UTF-8 string) with out_type Utf8 from a `&str` value.
+    ///
+    /// This is the same as `add_str8(field_name, field_value, OutType::Utf8, field_tag)`
+    /// except that values longer than 65535 bytes are truncated at a character
+    /// boundary. Use this for Rust strings so that decoders use UTF-8 (not CP1252) to
+    /// decode the value.
+    pub fn add_str(
+        &mut self,
+        field_name: &str,
+        field_value: impl AsRef<str>,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self
+            .raw_add_meta_scalar(field_name, InType::Str8, OutType::Utf8, field_tag)
+            .raw_add_data_counted(utf8_truncate(field_value.as_ref()));
+    }
+
+    /// Adds a Str8 variable-length array field (counted UTF-8 string) with out_type Utf8
+    /// from an iterator-of-`&str` value.
+    ///
+    /// This is the same as
+    /// `add_str8_sequence(field_name, field_values, OutType::Utf8, field_tag)` except
+    /// that values longer than 65535 bytes are truncated at a character boundary.
+    pub fn add_str_sequence<T: IntoIterator>(
+        &mut self,
+        field_name: &str,
+        field_values: T,
+        field_tag: u32,
+    ) -> &mut Self
+    where
+        T::Item: AsRef<str>,
+    {
+        return self
+            .raw_add_meta_vcount(field_name, InType::Str8, OutType::Utf8, field_tag)
+            .raw_add_data_range(field_values, |this, value| {
+                this.raw_add_data_counted(utf8_truncate(value.as_ref()));
+            });
+    }
+
+    /// Adds a Str8 field containing the lowercase hexadecimal representation of a
+    /// `&[u8]` value, e.g. `[0x1A, 0x2B]` is logged as `"1a2b"`.
+    ///
+    /// This is useful for values like hashes that analysts want to search for as text.
+    /// Binary fields are formatted inconsistently by different decoding tools.
+    ///
+    /// The hex digits are written directly into the event data without an intermediate
+    /// allocation. Values longer than 32767 bytes are truncated (the formatted string is
+    /// limited to 65535 characters).
+    pub fn add_hex_string(
+        &mut self,
+        field_name: &str,
+        field_value: impl AsRef<[u8]>,
+        field_tag: u32,
+    ) -> &mut Self {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+        let value = field_value.as_ref();
+        let value = &value[..value.len().min(65535 / 2)];
+        self.raw_add_meta_scalar(field_name, InType::Str8, OutType::Default, field_tag)
+            .raw_add_data_value(&((value.len() * 2) as u16));
+        self.data.reserve(value.len() * 2);
+        for &byte in value {
+            self.data.push(HEX_DIGITS[(byte >> 4) as usize]);
+            self.data.push(HEX_DIGITS[(byte & 0xF) as usize]);
+        }
+        return self;
+    }
+
+    /// Adds a Str8 field (counted UTF-8 string) with out_type Utf8 containing the text
+    /// produced by a [`format_args!`] value, e.g.
+    /// `add_fmt("Message", format_args!("{} of {}", index, count), 0)`.
+    ///
+    /// The text is formatted directly into the event data without an intermediate
+    /// `String`. Text longer than 65535 bytes is truncated at a character boundary.
+    pub fn add_fmt(
+        &mut self,
+        field_name: &str,
+        field_value: fmt::Arguments,
+        field_tag: u32,
+    ) -> &mut Self {
+        self.raw_add_meta_scalar(field_name, InType::Str8, OutType::Utf8, field_tag);
+
+        // Reserve space for size.
+        let old_data_size = self.data.len();
+        self.raw_add_data_value(&0u16);
+
+        // An error means the text was truncated.
+        let _ = fmt::write(
+            &mut CappedWriter {
+                data: &mut self.data,
+                remaining: 65535,
+            },
+            field_value,
+        );
+
+        // Save actual value of size.
+        let size = (self.data.len() - old_data_size - 2) as u16;
+        self.data[old_data_size] = size as u8;
+        self.data[old_data_size + 1] = (size >> 8) as u8;
+        return self;
+    }
+
+    /// Adds a Str16 field containing the system message text for a Win32 error code,
+    /// e.g. `2` is logged as `"The system cannot find the file specified."`.
+    ///
+    /// This is intended as a companion to a Win32Error field (e.g.
+    /// `add_u32("Error", code, OutType::Win32Error, 0)`) so that analysts do not need
+    /// to look up the code. The message is looked up by calling `FormatMessageW`, which
+    /// is much more expensive than adding a field, so only add the message when it is
+    /// needed, e.g. when the provider is enabled for a diagnostics keyword:
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// const DIAGNOSTICS_KEYWORD: u64 = 0x8000;
+    ///
+    /// let provider = Box::pin(tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()));
+    /// unsafe { provider.as_ref().register(); }
+    ///
+    /// let code = 2u32;
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder
+    ///     .reset("OpenFailed", tld::Level::Error, 0x1, 0)
+    ///     .add_u32("Error", code, tld::OutType::Win32Error, 0);
+    /// if provider.enabled(tld::Level::Error, DIAGNOSTICS_KEYWORD) {
+    ///     builder.add_win_error_message("ErrorMessage", code, 0);
+    /// }
+    /// builder.write(&provider, None, None);
+    /// ```
+    ///
+    /// If no message is found for the code (or if not running on Windows), the field
+    /// value is an empty string. Messages longer than 511 characters are truncated.
+    pub fn add_win_error_message(
+        &mut self,
+        field_name: &str,
+        field_value: u32,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self.raw_add_error_message(
+            field_name,
+            ErrorCodeKind::Win32Error,
+            field_value,
+            field_tag,
+        );
+    }
+
+    /// Adds a Str16 field containing the system message text for an HRESULT, e.g.
+    /// `0x80070005` is logged as `"Access is denied."`.
+    ///
+    /// This is the same as [`EventBuilder::add_win_error_message`] except that the
+    /// value is an HRESULT. It is intended as a companion to an HResult field (e.g.
+    /// `add_i32("Result", hr, OutType::HResult, 0)`).
+    pub fn add_hresult_message(
+        &mut self,
+        field_name: &str,
+        field_value: i32,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self.raw_add_error_message(
+            field_name,
+            ErrorCodeKind::HResult,
+            field_value as u32,
+            field_tag,
+        );
+    }
+
+    /// Adds a Str16 field containing the system message text for an NTSTATUS, e.g.
+    /// `0xC0000022` is logged as a message describing STATUS_ACCESS_DENIED.
+    ///
+    /// This is the same as [`EventBuilder::add_win_error_message`] except that the
+    /// value is an NTSTATUS (messages are looked up in ntdll.dll). It is intended as a
+    /// companion to an NtStatus field (e.g.
+    /// `add_hex32("Status", status as u32, OutType::NtStatus, 0)`).
+    pub fn add_win_ntstatus_message(
+        &mut self,
+        field_name: &str,
+        field_value: i32,
+        field_tag: u32,
+    ) -> &mut Self {
+        return self.raw_add_error_message(
+            field_name,
+            ErrorCodeKind::NtStatus,
+            field_value as u32,
+            field_tag,
+        );
+    }
+
     /// Adds a BinaryC field from a `&[u8]` value.
     ///
     /// If out_type is Default, field will format as Hex.
@@ -1203,6 +2190,50 @@ impl EventBuilder {
             .raw_add_data_counted(field_value.as_ref());
     }
 
+    /// Adds a `&[u8]` value that may be longer than the 65535-byte limit of a single
+    /// BinaryC field by splitting it into multiple BinaryC fields.
+    ///
+    /// The value is encoded as a U32 field named `"FieldName.Count"` containing the
+    /// number of chunks N, followed by N BinaryC fields named `"FieldName.0"`,
+    /// `"FieldName.1"`, ... `"FieldName.{N-1}"`. Each chunk except the last contains
+    /// exactly [`EventBuilder::BINARYC_CHUNK_MAX`] bytes. To reassemble the value, a
+    /// decoder reads `"FieldName.Count"` and concatenates the chunk fields in order. An
+    /// empty value is encoded as Count = 0 with no chunk fields.
+    ///
+    /// The `out_type` and `field_tag` apply to each chunk field. Decoders can use
+    /// [`DecodedEvent::binaryc_chunked`](crate::DecodedEvent::binaryc_chunked) to
+    /// reassemble the value.
+    ///
+    /// Note: This removes the per-field length limit but not ETW's limit on total
+    /// event size (64KB, or less if the session's buffers are smaller), so an event
+    /// with a long value would not be delivered by [`EventBuilder::write`]. Write the
+    /// event with [`EventBuilder::write_chunked`], which splits the event (and
+    /// therefore the chunk fields) across continuation events that fit within the
+    /// limit, and reassemble it with [`ChunkReassembler`](crate::ChunkReassembler).
+    pub fn add_binaryc_chunked(
+        &mut self,
+        field_name: &str,
+        field_value: impl AsRef<[u8]>,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        let value = field_value.as_ref();
+        let chunks = value.chunks(Self::BINARYC_CHUNK_MAX);
+
+        let mut chunk_name = String::with_capacity(field_name.len() + 6);
+        chunk_name.push_str(field_name);
+        chunk_name.push_str(".Count");
+        self.add_u32(&chunk_name, chunks.len() as u32, OutType::Default, 0);
+
+        for (index, chunk) in chunks.enumerate() {
+            chunk_name.truncate(field_name.len());
+            let _ = write!(chunk_name, ".{}", index);
+            self.add_binaryc(&chunk_name, chunk, out_type, field_tag);
+        }
+
+        return self;
+    }
+
     /// Adds a BinaryC variable-length array field from an iterator-of-`&[u8]` value.
     ///
     /// If out_type is Default, field will format as Hex.
@@ -1229,6 +2260,67 @@ impl EventBuilder {
             });
     }
 
+    /// Adds a binary field from a `&[u8]` value, using the encoding supported by the
+    /// decoders of `provider`'s events (see [`Provider::set_binaryc_supported`]).
+    ///
+    /// Same as [`EventBuilder::add_binaryc`] if `provider.binaryc_supported()` is true,
+    /// or [`EventBuilder::add_binary`] otherwise.
+    pub fn add_binary_auto(
+        &mut self,
+        provider: &Provider,
+        field_name: &str,
+        field_value: impl AsRef<[u8]>,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self {
+        return if provider.binaryc_supported() {
+            self.add_binaryc(field_name, field_value, out_type, field_tag)
+        } else {
+            self.add_binary(field_name, field_value, out_type, field_tag)
+        };
+    }
+
+    /// Adds an array-of-binary field from an iterator-of-`&[u8]` value, using the
+    /// encoding supported by the decoders of `provider`'s events (see
+    /// [`Provider::set_binaryc_supported`]).
+    ///
+    /// If `provider.binaryc_supported()` is true, this is the same as
+    /// [`EventBuilder::add_binaryc_sequence`]. Otherwise, since arrays of `Binary` do
+    /// not decode correctly, the values are added as a Struct field named
+    /// `field_name` with one `Binary` field per value, named `"0"`, `"1"`, and so on.
+    /// A struct has at most 127 fields, so in that case values after the first 127
+    /// are ignored. `field_tag` applies to the array or struct field.
+    pub fn add_binary_auto_sequence<T: IntoIterator>(
+        &mut self,
+        provider: &Provider,
+        field_name: &str,
+        field_values: T,
+        out_type: OutType,
+        field_tag: u32,
+    ) -> &mut Self
+    where
+        T::Item: AsRef<[u8]>,
+    {
+        if provider.binaryc_supported() {
+            return self.add_binaryc_sequence(field_name, field_values, out_type, field_tag);
+        }
+
+        let values: Vec<T::Item> = field_values
+            .into_iter()
+            .take(OutType::TypeMask as usize)
+            .collect();
+        self.add_struct(field_name, values.len() as u8, field_tag);
+
+        let mut member_name = String::with_capacity(3);
+        for (index, value) in values.iter().enumerate() {
+            member_name.clear();
+            let _ = write!(member_name, "{}", index);
+            self.add_binary(&member_name, value, out_type, 0);
+        }
+
+        return self;
+    }
+
     /// Adds a Struct field with the specified number of nested fields.
     ///
     /// A struct is a way to logically group a number of fields. To add a struct to
@@ -1257,6 +2349,77 @@ impl EventBuilder {
         );
     }
 
+    /// Adds a variable-length array-of-struct field with `elem_count` elements. Each
+    /// element is a struct with `struct_field_count` nested fields.
+    ///
+    /// `add_element` is called once per element with the builder and the element's
+    /// index (`0..elem_count`). Each call must add exactly `struct_field_count` fields
+    /// (e.g. via `add_u32` or `add_str8`; a nested struct and its fields count as 1
+    /// field). Every call must add the same fields (same names, types, and tags): the
+    /// field metadata added by the first call is used for all elements, and the
+    /// metadata added by subsequent calls is discarded (verified by `debug_assert`).
+    ///
+    /// If `elem_count` is 0, `add_element` is not called and the field is added as an
+    /// empty array of a struct with no nested fields. The `struct_field_count`
+    /// parameter must be in the range 0 to 127.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let points = [(1u32, 2u32), (3, 4)];
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder
+    ///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+    ///     .add_struct_sequence("Points", 2, points.len() as u16, 0, |b, i| {
+    ///         b.add_u32("X", points[i].0, tld::OutType::Default, 0)
+    ///             .add_u32("Y", points[i].1, tld::OutType::Default, 0);
+    ///     });
+    /// ```
+    pub fn add_struct_sequence(
+        &mut self,
+        field_name: &str,
+        struct_field_count: u8,
+        elem_count: u16,
+        field_tag: u32,
+        mut add_element: impl FnMut(&mut Self, usize),
+    ) -> &mut Self {
+        debug_assert_eq!(
+            struct_field_count & OutType::TypeMask,
+            struct_field_count,
+            "struct_field_count must be less than 128"
+        );
+
+        let struct_field_count = if elem_count == 0 {
+            0 // No element, so no nested field metadata.
+        } else {
+            struct_field_count & OutType::TypeMask
+        };
+        self.raw_add_meta(
+            field_name,
+            InType::Struct.as_int() | InType::VariableCountFlag,
+            struct_field_count,
+            field_tag,
+        )
+        .raw_add_data_value(&elem_count);
+
+        let members_start = self.meta.len();
+        let mut members_end = members_start;
+        for index in 0..elem_count as usize {
+            add_element(self, index);
+            if index == 0 {
+                members_end = self.meta.len();
+            } else {
+                debug_assert!(
+                    self.meta[members_end..] == self.meta[members_start..members_end],
+                    "add_element must add the same fields for each element"
+                );
+                self.meta.truncate(members_end);
+            }
+        }
+
+        return self;
+    }
+
     /// *Advanced scenarios:* Directly adds unchecked metadata to the event. Using this
     /// method may result in events that do not decode correctly.
     ///
@@ -1321,15 +2484,9 @@ impl EventBuilder {
     /// decode properly.
     pub fn raw_add_data_value<T: Copy>(&mut self, value: &T) -> &mut Self {
         let value_size = size_of::<T>();
-        let old_data_size = self.data.len();
-        self.data.reserve(value_size);
         unsafe {
-            copy_nonoverlapping(
-                value as *const T as *const u8,
-                self.data.as_mut_ptr().add(old_data_size),
-                value_size,
-            );
-            self.data.set_len(old_data_size + value_size);
+            self.data
+                .extend_from_raw(value as *const T as *const u8, value_size);
         }
         return self;
     }
@@ -1345,19 +2502,45 @@ impl EventBuilder {
     /// decode properly.
     pub fn raw_add_data_slice<T: Copy>(&mut self, value: &[T]) -> &mut Self {
         let value_size = value.len() * size_of::<T>();
-        let old_data_size = self.data.len();
-        self.data.reserve(value_size);
         unsafe {
-            copy_nonoverlapping(
-                value.as_ptr() as *const u8,
-                self.data.as_mut_ptr().add(old_data_size),
-                value_size,
-            );
-            self.data.set_len(old_data_size + value_size);
+            self.data
+                .extend_from_raw(value.as_ptr() as *const u8, value_size);
         }
         return self;
     }
 
+    /// *Advanced scenarios:* Adds a field with custom serialization, e.g. a value
+    /// serialized by a Bond or protobuf encoder. The field's metadata contains the
+    /// `protocol` and `schema`, and the field's data contains `field_value`.
+    ///
+    /// `protocol` identifies the serialization protocol and must be in the range 0 to
+    /// 31. The `schema` and `field_value` are each written with a 16-bit length
+    /// prefix, so each is truncated to 65535 bytes. TraceLogging decoders do not
+    /// decode custom fields: the consumer must understand the specified protocol.
+    pub fn raw_add_custom(
+        &mut self,
+        field_name: &str,
+        protocol: u8,
+        schema: impl AsRef<[u8]>,
+        field_value: impl AsRef<[u8]>,
+        field_tag: u32,
+    ) -> &mut Self {
+        debug_assert!(protocol <= 31, "protocol must not be greater than 31");
+
+        let schema = schema.as_ref();
+        let schema = &schema[..schema.len().min(65535)];
+        self.raw_add_meta(
+            field_name,
+            InType::CustomFlag | (protocol & InType::TypeMask),
+            0,
+            field_tag,
+        );
+        let schema_len = schema.len() as u16;
+        self.meta.extend_from_slice(&schema_len.to_le_bytes());
+        self.meta.extend_from_slice(schema);
+        return self.raw_add_data_counted(field_value.as_ref());
+    }
+
     fn raw_add_meta(
         &mut self,
         field_name: &str,
@@ -1397,13 +2580,50 @@ impl EventBuilder {
         return self;
     }
 
+    /// Returns the number of bytes of data added to the current event.
+    pub(crate) fn data_len(&self) -> usize {
+        return self.data.len();
+    }
+
+    /// Returns false if the provider's deny-list or sampling skips the current event.
+    /// The event's sampler only advances if the event would be delivered, i.e. if the
+    /// provider is enabled for the event or capture is active.
+    pub(crate) fn should_write(&self, provider: &Provider) -> bool {
+        return !provider.sampling.is_active()
+            || provider.sampling.should_write(
+                self.event_name(),
+                self.descriptor.id,
+                provider.capture.is_active()
+                    || provider.enabled(self.descriptor.level, self.descriptor.keyword),
+            );
+    }
+
+    /// Returns the event name from the metadata (after the size and event tag).
+    fn event_name(&self) -> &[u8] {
+        return MetaReader::new(&self.meta)
+            .read_event()
+            .map_or(&[], |(_, name)| name.as_bytes());
+    }
+
+    fn raw_add_error_message(
+        &mut self,
+        field_name: &str,
+        kind: ErrorCodeKind,
+        code: u32,
+        field_tag: u32,
+    ) -> &mut Self {
+        let mut buffer = [0u16; 512];
+        let len = format_error_message(kind, code, &mut buffer);
+        return self
+            .raw_add_meta_scalar(field_name, InType::Str16, OutType::Default, field_tag)
+            .raw_add_data_counted(&buffer[..len]);
+    }
+
     fn raw_add_data_sid(&mut self, value: &[u8]) -> &mut Self {
-        let sid_length = 8 + 4 * (value[1] as usize);
-        debug_assert!(
-            sid_length <= value.len(),
-            "add_sid(value) requires value.len() >= sid_length(value)"
-        );
-        return self.raw_add_data_slice(&value[0..sid_length]);
+        return match sid_length(value) {
+            Some(len) => self.raw_add_data_slice(&value[..len]),
+            None => self.raw_add_data_slice(&NULL_SID),
+        };
     }
 
     fn raw_add_data_cstr<T: Copy + Default + Eq>(&mut self, value: &[T]) -> &mut Self {
@@ -1458,8 +2678,59 @@ impl EventBuilder {
     }
 }
 
+/// Returns a copy of `data` with each borrowed value inserted at its offset.
+fn merge_borrowed(data: &[u8], borrowed: &[(usize, &[u8])]) -> Vec<u8> {
+    let mut merged = Vec::with_capacity(
+        data.len() + borrowed.iter().map(|(_, value)| value.len()).sum::<usize>(),
+    );
+    let mut data_pos = 0;
+    for &(offset, value) in borrowed {
+        let offset = offset.min(data.len());
+        merged.extend_from_slice(&data[data_pos..offset]);
+        data_pos = offset;
+        merged.extend_from_slice(value);
+    }
+    merged.extend_from_slice(&data[data_pos..]);
+    return merged;
+}
+
 impl Default for EventBuilder {
     fn default() -> Self {
         return Self::new();
     }
 }
+
+/// Returns the bytes of `value`, truncated at a character boundary to at most 65535
+/// bytes (the limit for a counted string).
+pub(crate) fn utf8_truncate(value: &str) -> &[u8] {
+    let mut len = value.len().min(65535);
+    while !value.is_char_boundary(len) {
+        len -= 1;
+    }
+    return &value.as_bytes()[..len];
+}
+
+/// Appends formatted text to event data, truncating at a character boundary once
+/// `remaining` bytes have been written.
+struct CappedWriter<'a> {
+    data: &'a mut SmallBuffer,
+    remaining: usize,
+}
+
+impl fmt::Write for CappedWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() <= self.remaining {
+            self.data.extend_from_slice(s.as_bytes());
+            self.remaining -= s.len();
+            return Ok(());
+        }
+
+        let mut len = self.remaining;
+        while !s.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.data.extend_from_slice(&s.as_bytes()[..len]);
+        self.remaining = 0;
+        return Err(fmt::Error);
+    }
+}
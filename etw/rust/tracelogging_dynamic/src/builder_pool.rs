@@ -0,0 +1,174 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+use core::ops::Deref;
+use core::ops::DerefMut;
+use std::sync::Mutex;
+
+use crate::builder::EventBuilder;
+
+/// A thread-safe pool of reusable [EventBuilder] objects (requires feature `std`).
+///
+/// Reusing a builder avoids allocating new buffers for each event. Threads that log
+/// frequently can get a builder from a shared pool instead of each keeping its own
+/// builder or allocating a new builder for each event.
+///
+/// [`EventBuilderPool::get`] returns a [`PooledEventBuilder`] that dereferences to an
+/// `EventBuilder` and returns the builder to the pool when dropped. If the pool is
+/// empty, `get` creates a new builder. When a builder is returned, the pool discards it
+/// instead of keeping it if the pool already holds `max_pooled` builders or if the
+/// builder's buffers have grown past `trim_threshold` bytes (see
+/// [`EventBuilder::capacity`]), so that one unusually-large event does not keep a
+/// large buffer alive indefinitely.
+///
+/// Pooled builders still contain the previous event, so always start with
+/// [`EventBuilder::reset`].
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// static POOL: tld::EventBuilderPool = tld::EventBuilderPool::new(16, 4096);
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// if provider.enabled(tld::Level::Verbose, 0x1) {
+///     POOL.get()
+///         .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///         .add_u32("Id", 5, tld::OutType::Default, 0)
+///         .write(&provider, None, None);
+/// }
+/// ```
+pub struct EventBuilderPool {
+    max_pooled: usize,
+    trim_threshold: usize,
+    builders: Mutex<Vec<EventBuilder>>,
+}
+
+impl EventBuilderPool {
+    /// Creates an empty pool that holds at most `max_pooled` builders and discards
+    /// returned builders whose capacity is greater than `trim_threshold` bytes.
+    pub const fn new(max_pooled: usize, trim_threshold: usize) -> Self {
+        return Self {
+            max_pooled,
+            trim_threshold,
+            builders: Mutex::new(Vec::new()),
+        };
+    }
+
+    /// Returns the maximum number of builders that the pool will hold.
+    pub const fn max_pooled(&self) -> usize {
+        return self.max_pooled;
+    }
+
+    /// Returns the capacity (in bytes) above which returned builders are discarded.
+    pub const fn trim_threshold(&self) -> usize {
+        return self.trim_threshold;
+    }
+
+    /// Returns the number of builders currently held by the pool.
+    pub fn len(&self) -> usize {
+        return self.lock().len();
+    }
+
+    /// Returns true if the pool does not hold any builders.
+    pub fn is_empty(&self) -> bool {
+        return self.lock().is_empty();
+    }
+
+    /// Removes all builders from the pool.
+    pub fn clear(&self) {
+        let builders = mem::take(&mut *self.lock());
+        drop(builders); // Free the buffers after releasing the lock.
+    }
+
+    /// Returns a builder from the pool, or a new builder if the pool is empty. The
+    /// builder is returned to the pool when the `PooledEventBuilder` is dropped.
+    pub fn get(&self) -> PooledEventBuilder<'_> {
+        let builder = self.lock().pop().unwrap_or_default();
+        return PooledEventBuilder {
+            pool: self,
+            builder: Some(builder),
+        };
+    }
+
+    /// Returns a builder to the pool, or discards it if the pool is full or the
+    /// builder is larger than the trim threshold.
+    fn put(&self, builder: EventBuilder) {
+        if builder.capacity() <= self.trim_threshold {
+            let mut builders = self.lock();
+            if builders.len() < self.max_pooled {
+                builders.push(builder);
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<EventBuilder>> {
+        // The list is always consistent, so a panic in another thread is not a problem.
+        return self
+            .builders
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+}
+
+impl fmt::Debug for EventBuilderPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "EventBuilderPool {{ len: {}, max_pooled: {}, trim_threshold: {} }}",
+            self.len(),
+            self.max_pooled,
+            self.trim_threshold
+        );
+    }
+}
+
+/// An [EventBuilder] borrowed from an [EventBuilderPool]. Dereferences to the
+/// builder. Returns the builder to the pool when dropped.
+pub struct PooledEventBuilder<'a> {
+    pool: &'a EventBuilderPool,
+    builder: Option<EventBuilder>, // None only after into_inner or during drop.
+}
+
+impl PooledEventBuilder<'_> {
+    /// Removes the builder from the pool's control, i.e. the builder will not be
+    /// returned to the pool.
+    pub fn into_inner(mut self) -> EventBuilder {
+        return self.builder.take().unwrap();
+    }
+}
+
+impl Deref for PooledEventBuilder<'_> {
+    type Target = EventBuilder;
+    fn deref(&self) -> &EventBuilder {
+        return self.builder.as_ref().unwrap();
+    }
+}
+
+impl DerefMut for PooledEventBuilder<'_> {
+    fn deref_mut(&mut self) -> &mut EventBuilder {
+        return self.builder.as_mut().unwrap();
+    }
+}
+
+impl Drop for PooledEventBuilder<'_> {
+    fn drop(&mut self) {
+        if let Some(builder) = self.builder.take() {
+            self.pool.put(builder);
+        }
+    }
+}
+
+impl fmt::Debug for PooledEventBuilder<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "PooledEventBuilder {{ capacity: {} }}",
+            self.builder
+                .as_ref()
+                .map_or(0, |builder| builder.capacity())
+        );
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::sync::atomic;
+
+use tracelogging::_internal::MetaReader;
+use tracelogging::_internal::SpinLock;
+use tracelogging::EventDescriptor;
+use tracelogging::Guid;
+
+use crate::decode::DecodeError;
+use crate::decode::DecodedEvent;
+use crate::decode::NameMap;
+use crate::decode::RawFields;
+
+/// A copy of an event written by a provider with capture enabled, as returned by
+/// [`Provider::drain_captured`](crate::Provider::drain_captured).
+///
+/// The event is self-describing: `meta` contains the event's TraceLogging metadata
+/// (event name, tag, and field names and types) in the same format that is sent to
+/// ETW, and `data` contains the field values. Fields added with a
+/// [`BorrowingEventBuilder`](crate::BorrowingEventBuilder) are copied into `data`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapturedEvent {
+    /// The event's descriptor (id, version, channel, level, opcode, task, keyword).
+    pub descriptor: EventDescriptor,
+
+    /// The activity id passed to `write`, or `None` if the event uses the thread's
+    /// activity id.
+    pub activity_id: Option<Guid>,
+
+    /// The related activity id passed to `write`, if any.
+    pub related_id: Option<Guid>,
+
+    /// The event's encoded metadata: u16 size, tag, NUL-terminated name, then field
+    /// definitions.
+    pub meta: Vec<u8>,
+
+    /// The event's encoded field values.
+    pub data: Vec<u8>,
+}
+
+impl CapturedEvent {
+    /// Returns the event's name (from the metadata).
+    pub fn name(&self) -> &str {
+        return MetaReader::new(&self.meta)
+            .read_event()
+            .map_or("", |(_, name)| name);
+    }
+
+    /// Decodes the event's metadata and data into its name, tag, and field values.
+    /// See [`DecodedEvent`].
+    pub fn decode(&self) -> Result<DecodedEvent<'_>, DecodeError> {
+        return DecodedEvent::decode(&self.meta, &self.data);
+    }
+
+    /// Decodes the event as with [`CapturedEvent::decode`], replacing event and field
+    /// names with the display names returned by `names`. See
+    /// [`DecodedEvent::decode_with_names`].
+    pub fn decode_with_names<'a>(
+        &'a self,
+        names: &'a dyn NameMap,
+    ) -> Result<DecodedEvent<'a>, DecodeError> {
+        return DecodedEvent::decode_with_names(&self.meta, &self.data, names);
+    }
+
+    /// Returns a zero-copy iterator over the event's fields, e.g. for inspecting a few
+    /// fields without decoding the whole event. See [`RawFields`].
+    pub fn raw_fields(&self) -> Result<RawFields<'_>, DecodeError> {
+        return RawFields::new(&self.meta, &self.data);
+    }
+}
+
+struct CaptureBuffer {
+    capacity: usize,
+    dropped: u64,
+    events: VecDeque<CapturedEvent>,
+}
+
+/// Ring buffer of captured events, protected by a spin lock. The write path checks
+/// `capacity` first, so providers that are not capturing pay only for one relaxed
+/// atomic load.
+pub(crate) struct EventCapture {
+    capacity: atomic::AtomicUsize,
+    buffer: SpinLock<Option<CaptureBuffer>>,
+}
+
+impl EventCapture {
+    pub const fn new() -> Self {
+        return Self {
+            capacity: atomic::AtomicUsize::new(0),
+            buffer: SpinLock::new(None),
+        };
+    }
+
+    /// Returns true if capture is enabled.
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        return self.capacity.load(atomic::Ordering::Relaxed) != 0;
+    }
+
+    /// Returns the capture capacity (0 if capture is disabled).
+    pub fn capacity(&self) -> usize {
+        return self.capacity.load(atomic::Ordering::Relaxed);
+    }
+
+    /// Sets the capacity. `capacity == 0` disables capture and discards the buffer.
+    /// If the buffer holds more than `capacity` events, the oldest are discarded.
+    pub fn set_capacity(&self, capacity: usize) {
+        self.with_buffer(|buffer| {
+            if capacity == 0 {
+                *buffer = None;
+            } else {
+                let buffer = buffer.get_or_insert_with(|| CaptureBuffer {
+                    capacity,
+                    dropped: 0,
+                    events: VecDeque::new(),
+                });
+                buffer.capacity = capacity;
+                while buffer.events.len() > capacity {
+                    buffer.events.pop_front();
+                    buffer.dropped += 1;
+                }
+            }
+        });
+        self.capacity.store(capacity, atomic::Ordering::Relaxed);
+    }
+
+    /// Adds an event, discarding the oldest event if the buffer is full. Does nothing
+    /// if capture is disabled.
+    pub fn push(&self, event: CapturedEvent) {
+        self.with_buffer(|buffer| {
+            if let Some(buffer) = buffer {
+                if buffer.events.len() >= buffer.capacity {
+                    buffer.events.pop_front();
+                    buffer.dropped += 1;
+                }
+                buffer.events.push_back(event);
+            }
+        });
+    }
+
+    /// Removes and returns all captured events, oldest first.
+    pub fn drain(&self) -> Vec<CapturedEvent> {
+        return self.with_buffer(|buffer| match buffer {
+            Some(buffer) => buffer.events.drain(..).collect(),
+            None => Vec::new(),
+        });
+    }
+
+    /// Returns the number of events discarded because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        return self.with_buffer(|buffer| buffer.as_ref().map_or(0, |b| b.dropped));
+    }
+
+    fn with_buffer<R>(&self, f: impl FnOnce(&mut Option<CaptureBuffer>) -> R) -> R {
+        return f(&mut self.buffer.lock());
+    }
+}
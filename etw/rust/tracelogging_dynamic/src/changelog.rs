@@ -1,43 +1,234 @@
-//! Release history
-
-#[allow(unused_imports)]
-use crate::*; // For docs
-
-/// # v1.2.2 (2024-05-20)
-/// - tracelogging crate supports use in kernel mode via feature
-///   `kernel_mode`.
-pub mod v1_2_2 {}
-
-/// # v1.2.1 (2023-06-29)
-/// - Guid: Fix `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
-pub mod v1_2_1 {}
-
-/// # v1.2.0 (2023-05-15)
-/// - Guid: Added `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
-/// - Opcode enum names clarified.
-pub mod v1_2_0 {}
-
-/// # v1.1.0 (2023-03-24, Breaking)
-/// - **Breaking:** Move [`Provider`] configuration parameters from
-///   [`Provider::register`] to [`Provider::new`]. This allows
-///   [`Provider::register`] to be an immutable operation, making the
-///   `Provider` type easier to use in multi-threaded scenarios.
-/// - Relax multithreading precondition on [`Provider::unregister`]. Now,
-///   only [`Provider::register`] has special preconditions.
-pub mod v1_1_0 {}
-
-/// # v1.0.2 (2023-03-13, Breaking)
-/// - **Breaking:** Rename the `filetime_from_systemtime` macro to
-///   [`win_filetime_from_systemtime`].
-/// - Improve the doc comments for [`win_filetime_from_systemtime`].
-/// - Define `Debug` trait on [`EventBuilder`].
-/// - Define `Debug` trait on [`ProviderOptions`].
-pub mod v1_0_2 {}
-
-/// # v1.0.1 (2023-03-13)
-/// - Add `filetime_from_systemtime` macro.
-pub mod v1_0_1 {}
-
-/// # v0.1.0 (2022-08-13)
-/// - Initial release.
-pub mod v0_1_0 {}
+//! Release history
+
+#[allow(unused_imports)]
+use crate::*; // For docs
+
+/// # v2.0.0 (Unreleased)
+/// - **Breaking change:** `Provider::enabled` is no longer a `const fn`.
+///   [`Provider::enabled`] always evaluates a consistent level + keyword snapshot,
+///   which requires atomic loads that cannot be done in a `const fn`.
+/// - New [`Provider::enable_generation`] method.
+/// - New [`EventLog`] bridge for mirroring selected events to the Windows Event Log.
+///   With feature `eventlog`, `Provider::set_event_log` attaches a bridge to a
+///   provider so that its selected events are mirrored when they are written.
+/// - New [`EventBuilder::add_binaryc_chunked`] method for binary values longer than
+///   65535 bytes. Write events with long values using [`EventBuilder::write_chunked`]
+///   and reassemble them with [`ChunkReassembler`] and
+///   [`DecodedEvent::binaryc_chunked`].
+/// - [`Channel`], [`Level`], [`Opcode`], [`InType`], and [`OutType`] have new
+///   `as_str()` and `from_name()` methods, implement `FromStr`, and `Display` now
+///   formats the canonical name (e.g. `Verbose`) instead of the integer value.
+/// - New [`Schema`] type and [`EventBuilder::validate`] method for checking that a
+///   dynamically-built event matches an expected list of fields.
+/// - New [`Provider::set_event_sampling`] and [`Provider::set_event_id_sampling`]
+///   methods for runtime-configurable sampling of specific events.
+/// - New feature `write_retries` with `Provider::set_write_retries` method for
+///   retrying writes that fail because a session's buffers are full. (Retries may
+///   duplicate events in sessions that recorded the first attempt.)
+/// - New [`Provider::event_id_from_name`] method for generating a stable event id
+///   from the event name (same id as `tracelogging`'s `auto_id(hash)` option).
+/// - New [`Provider::session`] method and [`ProviderSession`] type for writing many
+///   events using a snapshot of the provider's enable state.
+/// - New feature `keyword_levels` with `Provider::set_keyword_level` and
+///   `Provider::set_default_keyword_level` methods for restricting the levels allowed
+///   by [`Provider::enabled`] per keyword.
+/// - New feature `write_hook` with `Provider::set_pre_write_hook` method for
+///   observing events (descriptor and sizes) before they are written.
+/// - `EventDescriptor` is re-exported from `tracelogging`.
+/// - New feature `std` with `EventBuilder::add_ipv4`, `add_ipv6`, and
+///   `add_socketaddr` methods for logging `std::net` address types.
+/// - [`Channel`], [`Level`], [`Opcode`], [`InType`], and [`OutType`] implement
+///   `TryFrom<&str>`. New feature `serde` implements `Serialize` and `Deserialize`
+///   for these types (as the canonical name or the integer value).
+/// - New [`EventBuilder::add_hex_string`] method for logging a byte slice as a
+///   lowercase hex string.
+/// - Add `BorrowingEventBuilder` (via `EventBuilder::borrowing`) for adding field
+///   values that are sent from caller-owned memory instead of being copied.
+/// - New [`Provider::set_event_deny_list`] method for skipping events by name or
+///   name prefix based on runtime configuration.
+/// - New [`ProviderOptions::add_trait`] method to add custom provider traits to the
+///   provider metadata, and new [`Provider::raw_meta`] method. `ProviderOptions` no
+///   longer implements `Copy`.
+/// - New [`EventBuilder::add_win_error_message`], [`EventBuilder::add_hresult_message`],
+///   and [`EventBuilder::add_win_ntstatus_message`] methods for logging the system
+///   message text for an error code (via `FormatMessageW`).
+/// - New [`EventBuilder::into_parts`] and [`EventBuilder::from_parts`] methods for
+///   moving a built event across threads or queues as plain buffers.
+/// - New `AsyncWriter` type (feature `std`) that writes events from a background
+///   thread using a bounded queue, dropping and counting events when the queue is
+///   full.
+/// - New [`EventBuilder::add_struct_sequence`] method for adding array-of-struct
+///   fields.
+/// - New `Provider::on_enable_changed` and `Provider::enable_state` methods for
+///   tracking the level and keywords with which sessions have enabled the provider.
+/// - New `Provider::meta_len` method. `ProviderOptions::add_trait` reserves the exact
+///   size of each trait, so creating a provider with traits no longer reallocates.
+///   Added a `provider_new` benchmark.
+/// - New `EventBuilder::add_str` and `add_str_sequence` methods (and
+///   `BorrowingEventBuilder::add_str`) for `&str` values. They always use out_type
+///   Utf8, so the values decode as UTF-8 (not CP1252).
+/// - New `ProviderFactory` (requires feature `std`) creates, registers, and caches
+///   providers by name (e.g. one per tenant), up to a fixed capacity, and unregisters
+///   idle providers.
+/// - New [`Provider::set_capture`] method for capturing written events in an
+///   in-process ring buffer, and [`Provider::drain_captured`] method and
+///   [`CapturedEvent`] type for inspecting them without an ETW session.
+/// - New [`Provider::enable_filter_type`] and [`Provider::payload_filter_active`]
+///   methods. [`EventFilterDescriptor`] is exported from the crate root.
+/// - New `InType::fixed_size`, `InType::is_counted`, and `InType::is_nul_terminated`
+///   methods for determining the size of a field value.
+/// - New feature `tracing` with `TracingLayer`, a `tracing-subscriber` layer that
+///   writes `tracing` spans as ETW activities (start and stop events, with the parent
+///   span as the related activity) and `tracing` events as TraceLogging events.
+/// - New [`Provider::write_raw`] method for writing an event with pre-encoded
+///   TraceLogging metadata and data, e.g. to relog a [`CapturedEvent`] under another
+///   provider without using [`EventBuilder`].
+/// - New [`Provider::adopt_registration`] method for sharing the ETW registration of
+///   a C/C++ `TraceLoggingHProvider` with the same provider id instead of registering
+///   the provider a second time.
+/// - New `EventDeduper` type (requires `std`) that suppresses identical events (same
+///   name and key) within a time window and writes a `Deduplicated` summary event with
+///   the suppressed count when the window expires.
+/// - New `EventBuilderPool` type (requires `std`) that hands out reusable
+///   [`EventBuilder`] objects to multiple threads, discarding builders whose buffers
+///   have grown past a configurable threshold. New [`EventBuilder::capacity`] method.
+/// - New [`Provider::write_pair`] method for writing two related events (e.g. a
+///   request and its response) with the same activity id and consecutive `Sequence`
+///   field values, without interleaving with pairs written by other threads.
+/// - New [`EventBuilder::add_binary_capped`] method that adds at most `max_len` bytes
+///   of a value followed by a `U32` field with the value's original length.
+/// - New [`build_event!`] macro that resets an [`EventBuilder`] and adds fields using
+///   the same terse field syntax as `tracelogging::write_event!`, with the event's
+///   name, level, and keyword provided at runtime.
+/// - New [`EventBuilder::add`] method and [`IntoEventField`] trait for adding a field
+///   whose InType and OutType are selected based on the type of the value, e.g.
+///   `builder.add("Count", 5u32).add("Name", "Hello")`.
+/// - New [`StringDictionary`] type and [`EventBuilder::add_str_dict`] method for
+///   writing frequently-logged strings as `U16` ids, with the strings written once
+///   in a separate `StringDictionary` event.
+/// - New [`Provider::metadata`] method and [`ProviderMetadata`] type (re-exported from
+///   `tracelogging`) for parsing a provider's encoded metadata into its name, group
+///   id, and traits.
+/// - New [`Provider::try_register`] method that returns a [`RegisterError`]
+///   indicating which step of registration failed. If registration fails, the
+///   provider is now left unregistered.
+/// - New [`Provider::enabled_info`] method that reports the ETW sessions that have the
+///   provider enabled, with each session's level and keywords
+///   ([`ProviderEnabledInfo`], Windows only).
+/// - New [`EventBuilder::write_chunked`] method that splits an event that is too
+///   large for ETW into a series of `EventChunk` events (with a shared `ChunkId`) that
+///   can be reassembled by the consumer, instead of the event being dropped.
+/// - New [`Provider::write_rundown`] method (requires feature `std`) that writes
+///   `ProcessRundown` and `ModuleRundown` events describing the current process and
+///   its loaded modules, for symbolizing traces collected without kernel providers.
+/// - New [`EventBuilder::add_fmt`] method that formats a `format_args!` value
+///   directly into the event data as a UTF-8 string field.
+/// - [`EventBuilder::add_sid`] and [`EventBuilder::add_sid_sequence`] no longer
+///   panic if a value is not a valid SID; the NULL SID (`S-1-0-0`) is logged instead.
+///   New [`EventBuilder::try_add_sid`] method returns an error instead of adding the
+///   field.
+/// - New [`ProviderOptions::trait_bytes`] method that adds encoded provider traits,
+///   e.g. from [`ProviderMetadata::raw_traits`], so a dynamic provider can use the
+///   same group and traits as a `define_provider!` provider. Malformed traits panic.
+/// - [`ProviderOptions::add_trait`] with trait type 1 (the provider group trait) now
+///   sets the group id instead of adding a second group trait.
+/// - New `unregistered_writes` feature: [`Provider::unregistered_writes`] counts
+///   events that were written while the provider was not registered. The
+///   `unregistered_writes_panic` feature also fails a `debug_assert!` on each such
+///   write.
+/// - New [`Provider::add_state_source`], [`Provider::state_sources`], and
+///   [`Provider::capture_state`] methods and [`Provider::STATE_KEYWORD`] constant, so
+///   a dynamic provider can write state (rundown) events when a session sends a
+///   capture-state request, same as a `define_provider!` provider.
+/// - Re-export [`EventControlCode`], [`StateSource`], and [`StateSources`] from
+///   `tracelogging`.
+/// - New [`Provider::set_field_sanitizer`] method that sets a [`FieldSanitizer`]
+///   function, invoked with the name and value of each string and binary field before
+///   an event is written, e.g. to redact or hash personal data in one place.
+/// - New `stats` feature: [`Provider::stats`] returns counts of the events that were
+///   written, filtered (by the deny-list or sampling), and failed, along with the
+///   most recent error code.
+/// - New [`EventBuilder::add_char32`], [`EventBuilder::add_char32_sequence`], and
+///   [`EventBuilder::add_str32`] methods for adding `char` values as UTF-32
+///   characters, with matching `build_event!` field types and [`IntoEventField`]
+///   implementations for `char` and `&[char]`.
+/// - New [`DecodedEvent::decode`] and [`CapturedEvent::decode`] methods that parse
+///   an event's TraceLogging metadata and data into its name, tag, and field values
+///   ([`DecodedField`], [`FieldValue`], [`DecodedValue`]), formatted as TDH would
+///   format them, for use in tests and cross-platform tools.
+/// - New `loopback` feature (Windows only): `LoopbackSession` starts a real-time ETW
+///   session for a provider and returns the events it receives as [`CapturedEvent`]
+///   values, so tests can check that events written to ETW decode correctly.
+/// - New [`EventBuilder::add_binary_auto`] and [`EventBuilder::add_binary_auto_sequence`]
+///   methods that use the `BinaryC` encoding if [`Provider::set_binaryc_supported`]
+///   was set for the provider, or fall back to `Binary` (a struct of `Binary` fields
+///   for arrays) otherwise.
+/// - New [`EventBuilder::raw_add_custom`] method for adding custom-serialized fields
+///   (e.g. Bond or protobuf values) with a protocol id and schema.
+/// - New [`Provider::register_with_options`] method that accepts [`RegisterOptions`],
+///   e.g. to retry `EventRegister` after transient failures such as `ERROR_BUSY`.
+/// - Guid: Added `from_name_with_namespace()` for RFC 4122 name-based (version 5)
+///   UUIDs.
+/// - New [`EventBuilder::write_ex`] method that writes with `EventWriteEx`, with
+///   [`WriteFlags`] and a session filter mask.
+/// - New [`EventBuilder::try_write`] method that returns a [`WriteError`] instead of
+///   a Win32 error code. [`WriteError`] and [`RegisterError`] are `#[non_exhaustive]`
+///   and implement `Hash`.
+/// - New [`ProviderSet`] type and [`EventBuilder::write_set`] method for writing an
+///   event to several providers without rebuilding it.
+/// - [`EventBuilder`] stores metadata and data buffers of up to 256 bytes each inline,
+///   so building and writing a small event no longer allocates. Added criterion
+///   benchmarks for building and writing events.
+/// - New [`RawFields`] API ([`CapturedEvent::raw_fields`]) for reading an event's
+///   fields without allocating: each [`RawField`] and [`RawValue`] borrows from the
+///   event's metadata and data, and strings are converted lazily. Added criterion
+///   benchmarks that compare it with [`DecodedEvent::decode`] and TDH.
+/// - New [`DecodedEvent::decode_with_names`] and [`CapturedEvent::decode_with_names`]
+///   methods that replace event and field names with display names from a
+///   [`NameMap`], e.g. friendly or localized names loaded from a resource.
+/// - New [`EventFormatter`] type that formats a decoded event ([`FormatRecord`]) as
+///   a `tracefmt`-style line of text based on a template with placeholders such as
+///   `{timestamp}`, `{provider}`, `{event}`, and `{fields}`.
+/// - New [`StringDictionary::from_event`] and [`StringDictionary::expand`] methods
+///   that re-expand the `DictId` fields of a decoded event using the provider's
+///   dictionary event.
+pub mod v2_0_0 {}
+
+/// # v1.2.2 (2024-05-20)
+/// - tracelogging crate supports use in kernel mode via feature
+///   `kernel_mode`.
+pub mod v1_2_2 {}
+
+/// # v1.2.1 (2023-06-29)
+/// - Guid: Fix `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
+pub mod v1_2_1 {}
+
+/// # v1.2.0 (2023-05-15)
+/// - Guid: Added `as_bytes_raw()` method and `borrow<[u8; 16]>` trait.
+/// - Opcode enum names clarified.
+pub mod v1_2_0 {}
+
+/// # v1.1.0 (2023-03-24, Breaking)
+/// - **Breaking:** Move [`Provider`] configuration parameters from
+///   [`Provider::register`] to [`Provider::new`]. This allows
+///   [`Provider::register`] to be an immutable operation, making the
+///   `Provider` type easier to use in multi-threaded scenarios.
+/// - Relax multithreading precondition on [`Provider::unregister`]. Now,
+///   only [`Provider::register`] has special preconditions.
+pub mod v1_1_0 {}
+
+/// # v1.0.2 (2023-03-13, Breaking)
+/// - **Breaking:** Rename the `filetime_from_systemtime` macro to
+///   [`win_filetime_from_systemtime`].
+/// - Improve the doc comments for [`win_filetime_from_systemtime`].
+/// - Define `Debug` trait on [`EventBuilder`].
+/// - Define `Debug` trait on [`ProviderOptions`].
+pub mod v1_0_2 {}
+
+/// # v1.0.1 (2023-03-13)
+/// - Add `filetime_from_systemtime` macro.
+pub mod v1_0_1 {}
+
+/// # v0.1.0 (2022-08-13)
+/// - Initial release.
+pub mod v0_1_0 {}
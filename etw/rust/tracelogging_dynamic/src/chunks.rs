@@ -0,0 +1,166 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use tracelogging::Guid;
+
+use crate::builder::EventBuilder;
+use crate::capture::CapturedEvent;
+use crate::decode::DecodedEvent;
+use crate::decode::DecodedValue;
+
+/// Reassembles events that [`EventBuilder::write_chunked`] split into
+/// [`EventChunk`](EventBuilder::CHUNK_EVENT_NAME) continuation events, e.g. events
+/// with values added by [`EventBuilder::add_binaryc_chunked`].
+///
+/// Pass each event received from a provider to [`ChunkReassembler::add`]. Events
+/// that are not chunks are returned unchanged. Chunks are held until all chunks with
+/// the same `ChunkId` have been added (in any order), and then the original event is
+/// returned.
+///
+/// The reassembled event has the `EventMeta` from chunk 0, the concatenation of the
+/// chunks' `EventData` values, and the descriptor, activity id, and related id of
+/// chunk 0, so its id and version are 0 (see [`EventBuilder::write_chunked`]).
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// provider.set_capture(16);
+///
+/// let report = vec![b'x'; 100_000];
+/// tld::EventBuilder::new()
+///     .reset("Report", tld::Level::Verbose, 0x1, 0)
+///     .add_binaryc_chunked("Data", &report, tld::OutType::Default, 0)
+///     .write_chunked(&provider, None, None);
+///
+/// let mut reassembler = tld::ChunkReassembler::new();
+/// let mut events = Vec::new();
+/// for captured in provider.drain_captured() {
+///     events.extend(reassembler.add(captured));
+/// }
+///
+/// assert_eq!(1, events.len());
+/// let event = events[0].decode().unwrap();
+/// assert_eq!("Report", event.name);
+/// assert_eq!(Some(report), event.binaryc_chunked("Data"));
+/// ```
+#[derive(Debug, Default)]
+pub struct ChunkReassembler {
+    pending: BTreeMap<Guid, PendingEvent>,
+}
+
+#[derive(Debug)]
+struct PendingEvent {
+    first: Option<CapturedEvent>,
+    chunks: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+impl ChunkReassembler {
+    /// Returns a reassembler with no pending chunks.
+    pub fn new() -> Self {
+        return Self {
+            pending: BTreeMap::new(),
+        };
+    }
+
+    /// Returns the number of events for which some, but not all, chunks have been
+    /// added.
+    pub fn pending(&self) -> usize {
+        return self.pending.len();
+    }
+
+    /// Discards all pending chunks, e.g. after events were lost.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Adds an event. Returns the event if it is not a chunk, the reassembled event if
+    /// it is the last missing chunk of an event, or `None` if more chunks are needed.
+    ///
+    /// A chunk that cannot be decoded, or whose `ChunkCount` does not match the
+    /// other chunks with the same `ChunkId`, is discarded.
+    pub fn add(&mut self, event: CapturedEvent) -> Option<CapturedEvent> {
+        if event.name() != EventBuilder::CHUNK_EVENT_NAME {
+            return Some(event);
+        }
+
+        let (id, index, count, meta, data) = {
+            let decoded = event.decode().ok()?;
+            let chunk = Chunk::from_event(&decoded)?;
+            (
+                chunk.id,
+                chunk.index as usize,
+                chunk.count as usize,
+                chunk.meta.to_vec(),
+                chunk.data.to_vec(),
+            )
+        };
+
+        if index >= count {
+            return None;
+        }
+
+        let pending = self.pending.entry(id).or_insert_with(|| PendingEvent {
+            first: None,
+            chunks: (0..count).map(|_| None).collect(),
+            received: 0,
+        });
+        if pending.chunks.len() != count || pending.chunks[index].is_some() {
+            return None;
+        }
+
+        if index == 0 {
+            pending.first = Some(CapturedEvent { meta, ..event });
+        }
+        pending.chunks[index] = Some(data);
+        pending.received += 1;
+        if pending.received != count {
+            return None;
+        }
+
+        let pending = self.pending.remove(&id)?;
+        let mut first = pending.first?;
+        first.data = pending.chunks.into_iter().flatten().flatten().collect();
+        return Some(first);
+    }
+}
+
+struct Chunk<'a> {
+    id: Guid,
+    index: u16,
+    count: u16,
+    meta: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Chunk<'a> {
+    fn from_event(event: &DecodedEvent<'a>) -> Option<Self> {
+        let value = |name: &str| event.field(name).and_then(|field| field.value());
+        return match (
+            value("ChunkId"),
+            value("ChunkIndex"),
+            value("ChunkCount"),
+            value("EventMeta"),
+            value("EventData"),
+        ) {
+            (
+                Some(&DecodedValue::Guid(id)),
+                Some(&DecodedValue::U16(index)),
+                Some(&DecodedValue::U16(count)),
+                Some(&DecodedValue::Binary(meta)),
+                Some(&DecodedValue::Binary(data)),
+            ) => Some(Self {
+                id,
+                index,
+                count,
+                meta,
+                data,
+            }),
+            _ => None,
+        };
+    }
+}
@@ -0,0 +1,1171 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+use core::iter::FusedIterator;
+use core::mem::size_of;
+use core::str::from_utf8;
+
+use tracelogging::_internal::MetaField;
+use tracelogging::_internal::MetaReader;
+use tracelogging::Guid;
+use tracelogging::InType;
+use tracelogging::OutType;
+
+#[allow(unused_imports)] // For docs
+use crate::CapturedEvent;
+
+/// An event decoded from its TraceLogging metadata and data, as returned by
+/// [`DecodedEvent::decode`] or [`CapturedEvent::decode`].
+///
+/// Decoding follows the rules that TDH (the Windows trace data helper) uses for
+/// TraceLogging events, so the result can be used by cross-platform tools or by tests
+/// that check the contents of an event without an ETW session.
+///
+/// The `Display` implementation formats the event as `Name { Field: value, ... }`,
+/// formatting each value based on its [InType] and [OutType], e.g. `Hex` values as
+/// `0x1A`, `IPv4` values as `127.0.0.1`, and `FileTime` values as
+/// `2024-05-20T12:34:56.0000000Z`.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// provider.set_capture(1);
+///
+/// let mut builder = tld::EventBuilder::new();
+/// builder
+///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///     .add_u32("Flags", 0x1A, tld::OutType::Hex, 0)
+///     .add_str8("Name", "abc", tld::OutType::Utf8, 0)
+///     .write(&provider, None, None);
+///
+/// let captured = provider.drain_captured();
+/// let event = captured[0].decode().unwrap();
+/// assert_eq!("MyEvent", event.name);
+/// assert_eq!(Some(&tld::DecodedValue::U32(0x1A)), event.fields[0].value());
+/// assert_eq!("MyEvent { Flags: 0x1A, Name: \"abc\" }", event.to_string());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedEvent<'a> {
+    /// The event's name.
+    pub name: &'a str,
+    /// The event's tag (0 if the event has no tag).
+    pub tag: u32,
+    /// The event's top-level fields, in the order they appear in the event.
+    pub fields: Vec<DecodedField<'a>>,
+}
+
+impl<'a> DecodedEvent<'a> {
+    /// Decodes an event. `meta` is the event's TraceLogging metadata (u16 size, tag,
+    /// NUL-terminated name, then field definitions, e.g. [`CapturedEvent::meta`]) and
+    /// `data` is the event's field values (e.g. [`CapturedEvent::data`]).
+    ///
+    /// Fields with [`InType::_HexSize_PlatformSpecific`] are decoded using the
+    /// current process's pointer size.
+    pub fn decode(meta: &'a [u8], data: &'a [u8]) -> Result<Self, DecodeError> {
+        let raw = RawFields::new(meta, data)?;
+        let name = raw.name();
+        let tag = raw.tag();
+        let mut fields = Vec::new();
+        for field in raw {
+            fields.push(field?.decode());
+        }
+
+        return Ok(Self { name, tag, fields });
+    }
+
+    /// Decodes an event as with [`DecodedEvent::decode`], then replaces the event's
+    /// name and the names of its fields (including the members of structs) with the
+    /// display names returned by `names`, e.g. friendly or localized names loaded
+    /// from a resource. Names for which `names` returns `None` are unchanged.
+    ///
+    /// Since the names are replaced, [`DecodedEvent::field`] finds fields by their
+    /// display names.
+    ///
+    /// ```
+    /// use std::collections::BTreeMap;
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_capture(1);
+    ///
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder
+    ///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+    ///     .add_u32("errCnt", 2, tld::OutType::Default, 0)
+    ///     .write(&provider, None, None);
+    ///
+    /// let mut names = BTreeMap::new();
+    /// names.insert("errCnt".to_string(), "Error count".to_string());
+    ///
+    /// let captured = provider.drain_captured();
+    /// let event = captured[0].decode_with_names(&names).unwrap();
+    /// assert_eq!("MyEvent { Error count: 2 }", event.to_string());
+    /// ```
+    pub fn decode_with_names(
+        meta: &'a [u8],
+        data: &'a [u8],
+        names: &'a dyn NameMap,
+    ) -> Result<Self, DecodeError> {
+        let mut event = Self::decode(meta, data)?;
+        let event_name = event.name;
+        event.name = names.event_name(event_name).unwrap_or(event_name);
+        rename_fields(&mut event.fields, event_name, names);
+        return Ok(event);
+    }
+
+    /// Returns the first top-level field with the specified name, if any.
+    pub fn field(&self, name: &str) -> Option<&DecodedField<'a>> {
+        return self.fields.iter().find(|field| field.name == name);
+    }
+
+    /// Returns the value of a field added by
+    /// [`EventBuilder::add_binaryc_chunked`](crate::EventBuilder::add_binaryc_chunked),
+    /// reassembled from the `"{name}.Count"` field and the `"{name}.0"`,
+    /// `"{name}.1"`, ... chunk fields. Returns `None` if the event does not have the
+    /// count field or any of the chunk fields.
+    pub fn binaryc_chunked(&self, name: &str) -> Option<Vec<u8>> {
+        let mut field_name = String::with_capacity(name.len() + 6);
+        field_name.push_str(name);
+        field_name.push_str(".Count");
+        let count = match self.field(&field_name)?.value() {
+            Some(&DecodedValue::U32(count)) => count,
+            _ => return None,
+        };
+
+        let mut value = Vec::new();
+        for index in 0..count {
+            field_name.truncate(name.len());
+            let _ = write!(field_name, ".{}", index);
+            match self.field(&field_name)?.value() {
+                Some(DecodedValue::Binary(chunk)) => value.extend_from_slice(chunk),
+                _ => return None,
+            }
+        }
+        return Some(value);
+    }
+}
+
+impl fmt::Display for DecodedEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)?;
+        f.write_str(" ")?;
+        return fmt_fields(f, &self.fields);
+    }
+}
+
+/// Maps the internal names of events and fields to display names, as used by
+/// [`DecodedEvent::decode_with_names`].
+///
+/// Implemented for `BTreeMap<String, String>` (and, with feature `std`,
+/// `HashMap<String, String>`), which map field names to display names for all
+/// events and leave event names unchanged.
+pub trait NameMap {
+    /// Returns the display name for the field `field_name` of the event `event_name`
+    /// (the event's internal name), or `None` to keep the field's name. Called for
+    /// each top-level field and each member of a struct.
+    fn field_name(&self, event_name: &str, field_name: &str) -> Option<&str>;
+
+    /// Returns the display name for the event `event_name`, or `None` to keep the
+    /// event's name. The default implementation returns `None`.
+    fn event_name(&self, event_name: &str) -> Option<&str> {
+        let _ = event_name;
+        return None;
+    }
+}
+
+impl NameMap for BTreeMap<String, String> {
+    fn field_name(&self, _event_name: &str, field_name: &str) -> Option<&str> {
+        return self.get(field_name).map(String::as_str);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S: core::hash::BuildHasher> NameMap for std::collections::HashMap<String, String, S> {
+    fn field_name(&self, _event_name: &str, field_name: &str) -> Option<&str> {
+        return self.get(field_name).map(String::as_str);
+    }
+}
+
+fn rename_fields<'a>(fields: &mut [DecodedField<'a>], event_name: &str, names: &'a dyn NameMap) {
+    for field in fields {
+        field.name = names
+            .field_name(event_name, field.name)
+            .unwrap_or(field.name);
+        match &mut field.value {
+            FieldValue::Struct(members) => rename_fields(members, event_name, names),
+            FieldValue::StructArray(elements) => {
+                for members in elements {
+                    rename_fields(members, event_name, names);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A field of a [DecodedEvent].
+#[derive(Clone, Debug, PartialEq)]
+pub struct DecodedField<'a> {
+    /// The field's name.
+    pub name: &'a str,
+    /// The field's encoding, without array flags.
+    pub in_type: InType,
+    /// The field's format. For a struct, this is [`OutType::Default`].
+    pub out_type: OutType,
+    /// The field's tag (0 if the field has no tag).
+    pub tag: u32,
+    /// The field's value.
+    pub value: FieldValue<'a>,
+}
+
+impl<'a> DecodedField<'a> {
+    /// Returns the field's value if the field is a scalar (not an array, struct, or
+    /// custom field).
+    pub fn value(&self) -> Option<&DecodedValue<'a>> {
+        return match &self.value {
+            FieldValue::Scalar(value) => Some(value),
+            _ => None,
+        };
+    }
+}
+
+impl fmt::Display for DecodedField<'_> {
+    /// Formats the field's value.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match &self.value {
+            FieldValue::Scalar(value) => fmt_value(f, value, self.out_type),
+            FieldValue::Array(values) => {
+                f.write_str("[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    fmt_value(f, value, self.out_type)?;
+                }
+                f.write_str("]")
+            }
+            FieldValue::Struct(fields) => fmt_fields(f, fields),
+            FieldValue::StructArray(elements) => {
+                f.write_str("[")?;
+                for (i, fields) in elements.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    fmt_fields(f, fields)?;
+                }
+                f.write_str("]")
+            }
+            FieldValue::Custom { data, .. } => fmt_hex_bytes(f, data),
+        };
+    }
+}
+
+/// The value of a [DecodedField].
+#[derive(Clone, Debug, PartialEq)]
+pub enum FieldValue<'a> {
+    /// A single value.
+    Scalar(DecodedValue<'a>),
+    /// An array (variable-length or fixed-length) of values.
+    Array(Vec<DecodedValue<'a>>),
+    /// A struct: the values of the struct's member fields.
+    Struct(Vec<DecodedField<'a>>),
+    /// An array of structs: the member fields of each element.
+    StructArray(Vec<Vec<DecodedField<'a>>>),
+    /// A custom-serialized field ([`InType::CustomFlag`]): the schema from the
+    /// metadata and the serialized value, neither of which is interpreted.
+    Custom {
+        /// The field's schema.
+        schema: &'a [u8],
+        /// The field's serialized value.
+        data: &'a [u8],
+    },
+}
+
+/// A single decoded value, as determined by the field's [InType].
+///
+/// `Hex32` and `Hex64` values are decoded as `U32` and `U64` (their [OutType] is
+/// [`OutType::Hex`]). Strings are converted to UTF-8: `Str16`/`CStr16` values from
+/// UTF-16, and `Str8`/`CStr8` values from UTF-8 if the field's format is `Utf8`,
+/// `Json`, or `Xml`, or from CP1252 otherwise. Invalid sequences are replaced with
+/// U+FFFD.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodedValue<'a> {
+    /// I8 value.
+    I8(i8),
+    /// U8 value.
+    U8(u8),
+    /// I16 value.
+    I16(i16),
+    /// U16 value.
+    U16(u16),
+    /// I32 value.
+    I32(i32),
+    /// U32 or Hex32 value.
+    U32(u32),
+    /// I64 value.
+    I64(i64),
+    /// U64 or Hex64 value.
+    U64(u64),
+    /// F32 value.
+    F32(f32),
+    /// F64 value.
+    F64(f64),
+    /// Bool32 value.
+    Bool32(i32),
+    /// Guid value.
+    Guid(Guid),
+    /// FileTime value: 100ns intervals since 1601-01-01T00:00:00Z.
+    FileTime(i64),
+    /// SystemTime value: year, month, day of week, day, hour, minute, second,
+    /// milliseconds.
+    SystemTime([u16; 8]),
+    /// Str8, CStr8, Str16, or CStr16 value.
+    Str(String),
+    /// Binary or BinaryC value.
+    Binary(&'a [u8]),
+    /// Sid value.
+    Sid(&'a [u8]),
+}
+
+/// The error returned by [`DecodedEvent::decode`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The metadata is malformed at the specified offset.
+    Meta(usize),
+    /// The data does not match the metadata at the specified offset.
+    Data(usize),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            DecodeError::Meta(offset) => {
+                write!(f, "invalid event metadata at offset {}", offset)
+            }
+            DecodeError::Data(offset) => {
+                write!(f, "event data does not match metadata at offset {}", offset)
+            }
+        };
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Zero-copy iterator over the fields of an event, as returned by [`RawFields::new`]
+/// or [`CapturedEvent::raw_fields`].
+///
+/// Unlike [`DecodedEvent::decode`], which converts every field of the event into a
+/// [DecodedField], `RawFields` does not allocate: each [RawField] borrows its name
+/// and value bytes from the event's metadata and data, and values are only
+/// interpreted when requested. High-throughput consumers (e.g. relays and filters)
+/// can use it to inspect a few fields of each event without decoding the whole
+/// event.
+///
+/// Each item is a top-level field (for the iterator returned by
+/// [`RawField::members`], each member of the struct). If the metadata is malformed
+/// or the data does not match the metadata, the iterator returns an error and then
+/// ends. At the end of the top-level fields, it returns an error if there is data
+/// after the last field.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// provider.set_capture(1);
+///
+/// let mut builder = tld::EventBuilder::new();
+/// builder
+///     .reset("Request", tld::Level::Verbose, 0x1, 0)
+///     .add_str8("Path", "/index.html", tld::OutType::Utf8, 0)
+///     .add_u32("Status", 404, tld::OutType::Default, 0)
+///     .write(&provider, None, None);
+///
+/// let captured = provider.drain_captured();
+/// let mut fields = captured[0].raw_fields().unwrap();
+/// assert_eq!("Request", fields.name());
+/// let status = fields.field("Status").unwrap().unwrap();
+/// assert_eq!(404u32.to_le_bytes(), status.value().unwrap().bytes());
+/// ```
+#[derive(Clone, Debug)]
+pub struct RawFields<'a> {
+    name: &'a str,
+    tag: u32,
+    reader: MetaReader<'a>,
+    data: &'a [u8],
+    pos: usize,
+    remaining: usize, // Fields left to read: usize::MAX for top-level fields.
+}
+
+impl<'a> RawFields<'a> {
+    /// Returns an iterator over the top-level fields of an event. `meta` is the event's
+    /// TraceLogging metadata (e.g. [`CapturedEvent::meta`]) and `data` is the event's
+    /// field values (e.g. [`CapturedEvent::data`]). Returns an error if the event's
+    /// name and tag cannot be read.
+    pub fn new(meta: &'a [u8], data: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = MetaReader::with_size(meta).map_err(DecodeError::Meta)?;
+        let (tag, name) = reader.read_event().map_err(DecodeError::Meta)?;
+        return Ok(Self {
+            name,
+            tag,
+            reader,
+            data,
+            pos: 0,
+            remaining: usize::MAX,
+        });
+    }
+
+    /// Returns the event's name (for the members of a struct, the struct's name).
+    pub fn name(&self) -> &'a str {
+        return self.name;
+    }
+
+    /// Returns the event's tag (for the members of a struct, the struct's tag).
+    pub fn tag(&self) -> u32 {
+        return self.tag;
+    }
+
+    /// Returns the first remaining field with the specified name, skipping (but not
+    /// interpreting) the fields before it.
+    pub fn field(&mut self, name: &str) -> Result<Option<RawField<'a>>, DecodeError> {
+        for field in self {
+            let field = field?;
+            if field.name == name {
+                return Ok(Some(field));
+            }
+        }
+        return Ok(None);
+    }
+
+    fn read_field(&mut self) -> Result<RawField<'a>, DecodeError> {
+        let meta = self.reader.read_field().map_err(DecodeError::Meta)?;
+        let members = self.reader.clone();
+        if meta.is_struct() {
+            skip_members(&mut self.reader, meta.out_type)?;
+        }
+
+        let start = self.pos;
+        self.pos = field_end(&meta, &members, self.data, start)?;
+        return Ok(RawField {
+            name: meta.name,
+            in_type: meta.in_type,
+            out_type: if meta.is_struct() {
+                OutType::Default
+            } else {
+                OutType::from_int(meta.out_type)
+            },
+            tag: meta.tag,
+            meta,
+            members,
+            data: &self.data[start..self.pos],
+        });
+    }
+}
+
+impl<'a> Iterator for RawFields<'a> {
+    type Item = Result<RawField<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        } else if self.reader.at_end() {
+            let top_level = self.remaining == usize::MAX;
+            self.remaining = 0;
+            return if !top_level {
+                Some(Err(DecodeError::Meta(self.reader.pos())))
+            } else if self.pos != self.data.len() {
+                Some(Err(DecodeError::Data(self.pos)))
+            } else {
+                None
+            };
+        }
+
+        if self.remaining != usize::MAX {
+            self.remaining -= 1;
+        }
+
+        let result = self.read_field();
+        if result.is_err() {
+            self.remaining = 0;
+        }
+        return Some(result);
+    }
+}
+
+impl FusedIterator for RawFields<'_> {}
+
+/// A field of an event, as returned by [RawFields]. Borrows the field's name and value
+/// bytes from the event's metadata and data.
+#[derive(Clone, Debug)]
+pub struct RawField<'a> {
+    /// The field's name.
+    pub name: &'a str,
+    /// The field's encoding, without array flags.
+    pub in_type: InType,
+    /// The field's format. For a struct, this is [`OutType::Default`].
+    pub out_type: OutType,
+    /// The field's tag (0 if the field has no tag).
+    pub tag: u32,
+    meta: MetaField<'a>,
+    members: MetaReader<'a>, // For a struct, positioned at the first member.
+    data: &'a [u8],
+}
+
+impl<'a> RawField<'a> {
+    /// Returns true if the field is an array (variable-length or fixed-length).
+    pub fn is_array(&self) -> bool {
+        return self.meta.is_array();
+    }
+
+    /// Returns true if the field is a struct or an array of structs.
+    pub fn is_struct(&self) -> bool {
+        return self.meta.is_struct();
+    }
+
+    /// Returns the field's encoded data, including any array count and length
+    /// prefixes, e.g. for relaying the field without interpreting it.
+    pub fn raw_data(&self) -> &'a [u8] {
+        return self.data;
+    }
+
+    /// Returns the field's value if the field is a scalar (not an array, struct, or
+    /// custom field).
+    pub fn value(&self) -> Option<RawValue<'a>> {
+        return if self.meta.flags != 0 || self.is_struct() {
+            None
+        } else {
+            self.values().next()
+        };
+    }
+
+    /// Returns an iterator over the field's values: one value for a scalar, each
+    /// element for an array, and no values for a struct or custom field.
+    pub fn values(&self) -> RawValues<'a> {
+        let (pos, count) = match self.meta.flags {
+            _ if self.is_struct() => (0, 0),
+            0 => (0, 1),
+            InType::ConstantCountFlag => (0, self.meta.constant_count),
+            InType::VariableCountFlag => (2, read_u16(self.data, 0).unwrap_or(0)),
+            _ => (0, 0),
+        };
+        return RawValues {
+            in_type: self.in_type,
+            out_type: self.out_type,
+            data: self.data,
+            pos,
+            remaining: count,
+        };
+    }
+
+    /// Returns an iterator over the members of the field if the field is a struct
+    /// (not an array of structs).
+    pub fn members(&self) -> Option<RawFields<'a>> {
+        return if self.meta.flags != 0 {
+            None
+        } else {
+            self.elements().next()
+        };
+    }
+
+    /// Returns an iterator over the elements of the field if the field is a struct or
+    /// an array of structs. Each element is an iterator over the struct's members.
+    pub fn elements(&self) -> RawElements<'a> {
+        let (pos, count) = match self.meta.flags {
+            _ if !self.is_struct() => (0, 0),
+            0 => (0, 1),
+            InType::ConstantCountFlag => (0, self.meta.constant_count),
+            _ => (2, read_u16(self.data, 0).unwrap_or(0)),
+        };
+        return RawElements {
+            name: self.name,
+            tag: self.tag,
+            members: self.members.clone(),
+            member_count: self.meta.out_type,
+            data: self.data,
+            pos,
+            remaining: count,
+        };
+    }
+
+    /// Returns the schema and serialized value of a custom-serialized field
+    /// ([`InType::CustomFlag`]).
+    pub fn custom(&self) -> Option<(&'a [u8], &'a [u8])> {
+        return if self.meta.flags == InType::CustomFlag {
+            Some((self.meta.schema, &self.data[2..]))
+        } else {
+            None
+        };
+    }
+
+    /// Decodes the field (including the members of a struct) into a [DecodedField].
+    /// Allocates for arrays, structs, and strings.
+    pub fn decode(&self) -> DecodedField<'a> {
+        let value = if let Some((schema, data)) = self.custom() {
+            FieldValue::Custom { schema, data }
+        } else if self.is_struct() {
+            if self.is_array() {
+                FieldValue::StructArray(self.elements().map(decode_members).collect())
+            } else {
+                FieldValue::Struct(self.members().map(decode_members).unwrap_or_default())
+            }
+        } else if self.is_array() {
+            FieldValue::Array(self.values().map(|value| value.decode()).collect())
+        } else {
+            // The field's data was checked when the field was read, so a scalar always
+            // has a value.
+            FieldValue::Scalar(
+                self.values()
+                    .next()
+                    .map_or(DecodedValue::U8(0), |v| v.decode()),
+            )
+        };
+
+        return DecodedField {
+            name: self.name,
+            in_type: self.in_type,
+            out_type: self.out_type,
+            tag: self.tag,
+            value,
+        };
+    }
+}
+
+/// Iterator over the values of a [RawField], as returned by [`RawField::values`].
+#[derive(Clone, Debug)]
+pub struct RawValues<'a> {
+    in_type: InType,
+    out_type: OutType,
+    data: &'a [u8],
+    pos: usize,
+    remaining: u16,
+}
+
+impl<'a> Iterator for RawValues<'a> {
+    type Item = RawValue<'a>;
+
+    fn next(&mut self) -> Option<RawValue<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        return match value_bounds(self.in_type, self.data, self.pos) {
+            Ok((start, end, next)) => {
+                self.pos = next;
+                Some(RawValue {
+                    in_type: self.in_type,
+                    out_type: self.out_type,
+                    bytes: &self.data[start..end],
+                })
+            }
+            Err(_) => {
+                self.remaining = 0;
+                None
+            }
+        };
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (0, Some(self.remaining as usize));
+    }
+}
+
+impl FusedIterator for RawValues<'_> {}
+
+/// Iterator over the elements of a struct [RawField], as returned by
+/// [`RawField::elements`].
+#[derive(Clone, Debug)]
+pub struct RawElements<'a> {
+    name: &'a str,
+    tag: u32,
+    members: MetaReader<'a>,
+    member_count: u8,
+    data: &'a [u8],
+    pos: usize,
+    remaining: u16,
+}
+
+impl<'a> Iterator for RawElements<'a> {
+    type Item = RawFields<'a>;
+
+    fn next(&mut self) -> Option<RawFields<'a>> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        self.remaining -= 1;
+        return match members_end(self.members.clone(), self.member_count, self.data, self.pos) {
+            Ok(end) => {
+                let start = self.pos;
+                self.pos = end;
+                Some(RawFields {
+                    name: self.name,
+                    tag: self.tag,
+                    reader: self.members.clone(),
+                    data: &self.data[start..end],
+                    pos: 0,
+                    remaining: self.member_count as usize,
+                })
+            }
+            Err(_) => {
+                self.remaining = 0;
+                None
+            }
+        };
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        return (0, Some(self.remaining as usize));
+    }
+}
+
+impl FusedIterator for RawElements<'_> {}
+
+/// A single value of a [RawField], borrowed from the event's data.
+///
+/// The value is not interpreted until requested: use [`RawValue::bytes`] for the
+/// value's bytes, [`RawValue::as_str`] or [`RawValue::utf16_chars`] for strings
+/// without allocating, or [`RawValue::decode`] to convert the value to a
+/// [DecodedValue].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RawValue<'a> {
+    in_type: InType,
+    out_type: OutType,
+    bytes: &'a [u8],
+}
+
+impl<'a> RawValue<'a> {
+    /// Returns the value's encoding, without array flags.
+    pub fn in_type(&self) -> InType {
+        return self.in_type;
+    }
+
+    /// Returns the value's format.
+    pub fn out_type(&self) -> OutType {
+        return self.out_type;
+    }
+
+    /// Returns the value's bytes, excluding any length prefix or NUL terminator, e.g.
+    /// the little-endian bytes of an integer, the UTF-8 or CP1252 bytes of a
+    /// `Str8` value, or the UTF-16LE bytes of a `Str16` value.
+    pub fn bytes(&self) -> &'a [u8] {
+        return self.bytes;
+    }
+
+    /// Returns the value as a string slice if it is a `Str8` or `CStr8` value that
+    /// contains valid UTF-8. Does not convert CP1252 values: `Str8` values with
+    /// non-ASCII characters are only valid UTF-8 if their format is `Utf8`, `Json`,
+    /// or `Xml`.
+    pub fn as_str(&self) -> Option<&'a str> {
+        return if matches!(self.in_type, InType::Str8 | InType::CStr8) {
+            from_utf8(self.bytes).ok()
+        } else {
+            None
+        };
+    }
+
+    /// Returns the value as a slice of UTF-16 code units if it is a `Str16` or
+    /// `CStr16` value and its bytes are suitably aligned (on a little-endian
+    /// platform). Otherwise returns `None`; use [`RawValue::utf16_units`] instead.
+    pub fn as_utf16(&self) -> Option<&'a [u16]> {
+        if !matches!(self.in_type, InType::Str16 | InType::CStr16) || cfg!(target_endian = "big") {
+            return None;
+        }
+
+        // Safety: any bit pattern is a valid u16.
+        let (prefix, units, suffix) = unsafe { self.bytes.align_to::<u16>() };
+        return if prefix.is_empty() && suffix.is_empty() {
+            Some(units)
+        } else {
+            None
+        };
+    }
+
+    /// Returns an iterator over the value's bytes as little-endian UTF-16 code units,
+    /// e.g. for a `Str16` or `CStr16` value. Does not allocate.
+    pub fn utf16_units(&self) -> impl Iterator<Item = u16> + Clone + 'a {
+        return self
+            .bytes
+            .chunks_exact(2)
+            .map(|unit| u16::from_le_bytes([unit[0], unit[1]]));
+    }
+
+    /// Returns an iterator over the characters of a `Str16` or `CStr16` value,
+    /// replacing invalid UTF-16 with U+FFFD. Converts lazily and does not allocate.
+    pub fn utf16_chars(&self) -> impl Iterator<Item = char> + Clone + 'a {
+        return char::decode_utf16(self.utf16_units())
+            .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+
+    /// Converts the value to a [DecodedValue]. Allocates for strings.
+    pub fn decode(&self) -> DecodedValue<'a> {
+        let bytes = self.bytes;
+        return match self.in_type {
+            InType::I8 => DecodedValue::I8(bytes[0] as i8),
+            InType::U8 => DecodedValue::U8(bytes[0]),
+            InType::I16 => DecodedValue::I16(i16::from_le_bytes(array(bytes))),
+            InType::U16 => DecodedValue::U16(u16::from_le_bytes(array(bytes))),
+            InType::I32 => DecodedValue::I32(i32::from_le_bytes(array(bytes))),
+            InType::U32 | InType::Hex32 => DecodedValue::U32(u32::from_le_bytes(array(bytes))),
+            InType::I64 => DecodedValue::I64(i64::from_le_bytes(array(bytes))),
+            InType::U64 | InType::Hex64 => DecodedValue::U64(u64::from_le_bytes(array(bytes))),
+            InType::F32 => DecodedValue::F32(f32::from_le_bytes(array(bytes))),
+            InType::F64 => DecodedValue::F64(f64::from_le_bytes(array(bytes))),
+            InType::Bool32 => DecodedValue::Bool32(i32::from_le_bytes(array(bytes))),
+            InType::Guid => DecodedValue::Guid(Guid::from_bytes_le(&array(bytes))),
+            InType::FileTime => DecodedValue::FileTime(i64::from_le_bytes(array(bytes))),
+            InType::SystemTime => {
+                let mut value = [0u16; 8];
+                for (i, item) in value.iter_mut().enumerate() {
+                    *item = u16::from_le_bytes([bytes[i * 2], bytes[i * 2 + 1]]);
+                }
+                DecodedValue::SystemTime(value)
+            }
+            InType::_HexSize_PlatformSpecific => {
+                if size_of::<usize>() == 8 {
+                    DecodedValue::U64(u64::from_le_bytes(array(bytes)))
+                } else {
+                    DecodedValue::U32(u32::from_le_bytes(array(bytes)))
+                }
+            }
+            InType::Str8 | InType::CStr8 => {
+                DecodedValue::Str(string_from_str8(bytes, self.out_type))
+            }
+            InType::Str16 | InType::CStr16 => DecodedValue::Str(self.utf16_chars().collect()),
+            InType::Sid => DecodedValue::Sid(bytes),
+            _ => DecodedValue::Binary(bytes),
+        };
+    }
+}
+
+fn decode_members<'a>(members: RawFields<'a>) -> Vec<DecodedField<'a>> {
+    // The members' data was checked when the struct was read.
+    return members
+        .filter_map(|member| member.ok())
+        .map(|member| member.decode())
+        .collect();
+}
+
+/// Skips the metadata of `count` fields (including the members of structs).
+fn skip_members(reader: &mut MetaReader, count: u8) -> Result<(), DecodeError> {
+    for _ in 0..count {
+        let field = reader.read_field().map_err(DecodeError::Meta)?;
+        if field.is_struct() {
+            skip_members(reader, field.out_type)?;
+        }
+    }
+    return Ok(());
+}
+
+/// Returns the position in `data` after the values of `field` that start at `pos`.
+/// For a struct, `members` is positioned at the struct's first member.
+fn field_end(
+    field: &MetaField,
+    members: &MetaReader,
+    data: &[u8],
+    pos: usize,
+) -> Result<usize, DecodeError> {
+    let (count, mut pos) = match field.flags {
+        0 => (1, pos),
+        InType::ConstantCountFlag => (field.constant_count, pos),
+        InType::VariableCountFlag => (read_u16(data, pos)?, pos + 2),
+        _ => {
+            // Custom-serialized field: u16 size, then opaque data.
+            let len = read_u16(data, pos)? as usize;
+            return checked_end(data, pos + 2, len);
+        }
+    };
+
+    for _ in 0..count {
+        pos = if field.is_struct() {
+            members_end(members.clone(), field.out_type, data, pos)?
+        } else {
+            value_bounds(field.in_type, data, pos)?.2
+        };
+    }
+    return Ok(pos);
+}
+
+/// Returns the position in `data` after one value of the `count` members that
+/// `members` is positioned at.
+fn members_end(
+    mut members: MetaReader,
+    count: u8,
+    data: &[u8],
+    mut pos: usize,
+) -> Result<usize, DecodeError> {
+    for _ in 0..count {
+        let field = members.read_field().map_err(DecodeError::Meta)?;
+        let field_members = members.clone();
+        if field.is_struct() {
+            skip_members(&mut members, field.out_type)?;
+        }
+        pos = field_end(&field, &field_members, data, pos)?;
+    }
+    return Ok(pos);
+}
+
+/// Returns the start and end of the bytes of a value with the specified intype that
+/// starts at `pos` (excluding any length prefix or NUL terminator), and the position
+/// of the next value.
+fn value_bounds(
+    in_type: InType,
+    data: &[u8],
+    pos: usize,
+) -> Result<(usize, usize, usize), DecodeError> {
+    let tail = data.get(pos..).ok_or(DecodeError::Data(pos))?;
+    let bounds = if let Some(size) = in_type.fixed_size() {
+        (pos, checked_end(data, pos, size)?, pos + size)
+    } else if in_type.is_counted() {
+        let len = read_u16(data, pos)? as usize;
+        let end = checked_end(data, pos + 2, len)?;
+        (pos + 2, end, end)
+    } else if in_type == InType::_HexSize_PlatformSpecific {
+        let end = checked_end(data, pos, size_of::<usize>())?;
+        (pos, end, end)
+    } else if in_type == InType::CStr8 {
+        let len = tail
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or(DecodeError::Data(pos))?;
+        (pos, pos + len, pos + len + 1)
+    } else if in_type == InType::CStr16 {
+        let len = tail
+            .chunks_exact(2)
+            .position(|c| c == [0, 0])
+            .ok_or(DecodeError::Data(pos))?;
+        (pos, pos + len * 2, pos + len * 2 + 2)
+    } else if in_type == InType::Sid {
+        let sub_authority_count = *tail.get(1).ok_or(DecodeError::Data(pos))? as usize;
+        let end = checked_end(data, pos, 8 + 4 * sub_authority_count)?;
+        (pos, end, end)
+    } else {
+        return Err(DecodeError::Data(pos));
+    };
+    return Ok(bounds);
+}
+
+/// Returns `pos + len` if `data` has at least `len` bytes at `pos`.
+fn checked_end(data: &[u8], pos: usize, len: usize) -> Result<usize, DecodeError> {
+    let end = pos.checked_add(len).ok_or(DecodeError::Data(pos))?;
+    return if end <= data.len() {
+        Ok(end)
+    } else {
+        Err(DecodeError::Data(pos))
+    };
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, DecodeError> {
+    let end = checked_end(data, pos, 2)?;
+    return Ok(u16::from_le_bytes(array(&data[pos..end])));
+}
+
+/// Copies a value's bytes (whose length was checked when they were read) to an array.
+fn array<const N: usize>(bytes: &[u8]) -> [u8; N] {
+    let mut value = [0; N];
+    value.copy_from_slice(bytes);
+    return value;
+}
+
+/// Converts a Str8/CStr8 value to a string: UTF-8 for the formats that decode as
+/// UTF-8, CP1252 otherwise.
+fn string_from_str8(bytes: &[u8], out_type: OutType) -> String {
+    return if matches!(out_type, OutType::Utf8 | OutType::Json | OutType::Xml) {
+        String::from_utf8_lossy(bytes).into_owned()
+    } else {
+        bytes.iter().map(|&b| char_from_cp1252(b)).collect()
+    };
+}
+
+/// Windows-1252 characters 0x80..0x9F. Unassigned values map to the corresponding C1
+/// control character, as with `MultiByteToWideChar`.
+const CP1252_80_9F: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, //
+    0x02C6, 0x2030, 0x0160, 0x2039, 0x0152, 0x008D, 0x017D, 0x008F, //
+    0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014, //
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178, //
+];
+
+fn char_from_cp1252(value: u8) -> char {
+    let code = match value {
+        0x80..=0x9F => CP1252_80_9F[(value - 0x80) as usize] as u32,
+        _ => value as u32,
+    };
+    return char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER);
+}
+
+fn fmt_fields(f: &mut fmt::Formatter<'_>, fields: &[DecodedField]) -> fmt::Result {
+    f.write_str("{")?;
+    for (i, field) in fields.iter().enumerate() {
+        f.write_str(if i == 0 { " " } else { ", " })?;
+        write!(f, "{}: {}", field.name, field)?;
+    }
+    return f.write_str(" }");
+}
+
+fn fmt_value(f: &mut fmt::Formatter<'_>, value: &DecodedValue, out_type: OutType) -> fmt::Result {
+    return match *value {
+        DecodedValue::I8(v) => match out_type {
+            OutType::String => fmt_char(f, char_from_cp1252(v as u8)),
+            _ => write!(f, "{}", v),
+        },
+        DecodedValue::U8(v) => match out_type {
+            OutType::String => fmt_char(f, char_from_cp1252(v)),
+            OutType::Boolean => fmt_bool(f, v as u32),
+            OutType::Hex => write!(f, "0x{:X}", v),
+            _ => write!(f, "{}", v),
+        },
+        DecodedValue::I16(v) => write!(f, "{}", v),
+        DecodedValue::U16(v) => match out_type {
+            OutType::String => fmt_char(
+                f,
+                char::from_u32(v as u32).unwrap_or(char::REPLACEMENT_CHARACTER),
+            ),
+            OutType::Hex => write!(f, "0x{:X}", v),
+            OutType::Port => write!(f, "{}", u16::from_be(v)),
+            _ => write!(f, "{}", v),
+        },
+        DecodedValue::I32(v) => match out_type {
+            OutType::HResult | OutType::NtStatus => write!(f, "0x{:08X}", v as u32),
+            _ => write!(f, "{}", v),
+        },
+        DecodedValue::U32(v) => match out_type {
+            OutType::String => {
+                fmt_char(f, char::from_u32(v).unwrap_or(char::REPLACEMENT_CHARACTER))
+            }
+            OutType::Boolean => fmt_bool(f, v),
+            OutType::Hex | OutType::CodePointer => write!(f, "0x{:X}", v),
+            OutType::IPv4 => {
+                let b = v.to_le_bytes();
+                write!(f, "{}.{}.{}.{}", b[0], b[1], b[2], b[3])
+            }
+            OutType::Win32Error | OutType::NtStatus | OutType::HResult => {
+                write!(f, "0x{:08X}", v)
+            }
+            _ => write!(f, "{}", v),
+        },
+        DecodedValue::I64(v) => write!(f, "{}", v),
+        DecodedValue::U64(v) => match out_type {
+            OutType::Hex | OutType::CodePointer => write!(f, "0x{:X}", v),
+            _ => write!(f, "{}", v),
+        },
+        DecodedValue::F32(v) => write!(f, "{}", v),
+        DecodedValue::F64(v) => write!(f, "{}", v),
+        DecodedValue::Bool32(v) => fmt_bool(f, v as u32),
+        DecodedValue::Guid(v) => write!(f, "{:?}", v),
+        DecodedValue::FileTime(v) => fmt_filetime(f, v),
+        DecodedValue::SystemTime(v) => write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}{}",
+            v[0],
+            v[1],
+            v[3],
+            v[4],
+            v[5],
+            v[6],
+            v[7],
+            if out_type == OutType::DateTimeUtc {
+                "Z"
+            } else {
+                ""
+            }
+        ),
+        DecodedValue::Str(ref v) => write!(f, "{:?}", v),
+        DecodedValue::Binary(v) => match out_type {
+            OutType::IPv6 if v.len() == 16 => {
+                for i in 0..8 {
+                    if i != 0 {
+                        f.write_str(":")?;
+                    }
+                    write!(f, "{:x}", u16::from_be_bytes([v[i * 2], v[i * 2 + 1]]))?;
+                }
+                Ok(())
+            }
+            _ => fmt_hex_bytes(f, v),
+        },
+        DecodedValue::Sid(v) => {
+            // S-Revision-Authority-SubAuthority...
+            let mut authority = 0u64;
+            for &b in &v[2..8] {
+                authority = (authority << 8) | b as u64;
+            }
+            write!(f, "S-{}-{}", v[0], authority)?;
+            for sub_authority in v[8..].chunks_exact(4) {
+                let sub_authority = u32::from_le_bytes([
+                    sub_authority[0],
+                    sub_authority[1],
+                    sub_authority[2],
+                    sub_authority[3],
+                ]);
+                write!(f, "-{}", sub_authority)?;
+            }
+            Ok(())
+        }
+    };
+}
+
+fn fmt_char(f: &mut fmt::Formatter<'_>, value: char) -> fmt::Result {
+    return write!(f, "{:?}", value);
+}
+
+fn fmt_bool(f: &mut fmt::Formatter<'_>, value: u32) -> fmt::Result {
+    return match value {
+        0 => f.write_str("false"),
+        1 => f.write_str("true"),
+        _ => write!(f, "{}", value),
+    };
+}
+
+fn fmt_hex_bytes(f: &mut fmt::Formatter<'_>, value: &[u8]) -> fmt::Result {
+    f.write_str("0x")?;
+    for b in value {
+        write!(f, "{:02X}", b)?;
+    }
+    return Ok(());
+}
+
+/// Formats a FILETIME as an ISO 8601 UTC date-time, or as an integer if it is
+/// negative.
+pub(crate) fn fmt_filetime(f: &mut fmt::Formatter<'_>, value: i64) -> fmt::Result {
+    if value < 0 {
+        return write!(f, "{}", value);
+    }
+
+    const TICKS_PER_SECOND: i64 = 10_000_000;
+    const SECONDS_PER_DAY: i64 = 86400;
+    const DAYS_1601_TO_1970: i64 = 134774;
+
+    let seconds = value / TICKS_PER_SECOND;
+    let ticks = value % TICKS_PER_SECOND;
+    let days = seconds / SECONDS_PER_DAY - DAYS_1601_TO_1970;
+    let time = seconds % SECONDS_PER_DAY;
+
+    // Civil date from days since 1970-01-01 (proleptic Gregorian calendar).
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return write!(
+        f,
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:07}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        ticks
+    );
+}
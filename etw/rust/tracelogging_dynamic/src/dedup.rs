@@ -0,0 +1,245 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::Hash;
+use core::hash::Hasher;
+use std::collections::hash_map::DefaultHasher;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::builder::EventBuilder;
+use crate::provider::Provider;
+use crate::Level;
+use crate::OutType;
+
+/// Suppresses identical events within a time window and summarizes the suppressed
+/// events when the window expires (requires feature `std`).
+///
+/// Events are identified by event name plus a key, typically a tuple of the values
+/// of selected fields (e.g. the error code and the remote address). The key may be
+/// any type that implements [`Hash`]; only its hash is stored.
+///
+/// Call [`EventDeduper::check`] before building an event. The first occurrence of an
+/// event in a window is allowed (`check` returns true); subsequent occurrences of the
+/// same event within `window` are counted and suppressed (`check` returns false). When
+/// the window expires, the deduper writes a summary event named `Deduplicated` with
+/// fields `EventName` (string), `Suppressed` (number of suppressed occurrences), and
+/// `WindowMs` (window duration in milliseconds), using the level and keyword of the
+/// original event. No summary is written if no occurrences were suppressed.
+///
+/// An expired entry is summarized the next time the same event is checked or when
+/// [`EventDeduper::flush_expired`] is called. Call `flush_expired` periodically (e.g.
+/// from a timer) to write summaries for events that stop occurring and to remove
+/// their entries, and call [`EventDeduper::flush`] before shutdown.
+///
+/// ```
+/// use std::time::Duration;
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// let deduper = tld::EventDeduper::new(Duration::from_secs(10));
+///
+/// for _ in 0..100 {
+///     let (code, host) = (5u32, "contoso.com");
+///     if deduper.check(&provider, "ConnectFailed", tld::Level::Warning, 0x1, &(code, host)) {
+///         tld::EventBuilder::new()
+///             .reset("ConnectFailed", tld::Level::Warning, 0x1, 0)
+///             .add_u32("Code", code, tld::OutType::Default, 0)
+///             .add_str8("Host", host, tld::OutType::Default, 0)
+///             .write(&provider, None, None);
+///     }
+/// }
+///
+/// deduper.flush(&provider); // Writes "Deduplicated" with Suppressed = 99.
+/// ```
+pub struct EventDeduper {
+    window: Duration,
+    entries: Mutex<BTreeMap<u64, DedupEntry>>,
+}
+
+struct DedupEntry {
+    name: String,
+    level: Level,
+    keyword: u64,
+    start: Instant,
+    suppressed: u64,
+}
+
+/// A summary event to be written after the lock is released.
+struct DedupSummary {
+    name: String,
+    level: Level,
+    keyword: u64,
+    suppressed: u64,
+}
+
+impl EventDeduper {
+    /// Creates a deduper that suppresses identical events within `window`.
+    pub fn new(window: Duration) -> Self {
+        return Self {
+            window,
+            entries: Mutex::new(BTreeMap::new()),
+        };
+    }
+
+    /// Returns the deduplication window.
+    pub const fn window(&self) -> Duration {
+        return self.window;
+    }
+
+    /// Returns the number of events currently being tracked.
+    pub fn len(&self) -> usize {
+        return self.lock().len();
+    }
+
+    /// Returns true if no events are being tracked.
+    pub fn is_empty(&self) -> bool {
+        return self.lock().is_empty();
+    }
+
+    /// Records an occurrence of the event identified by `name` and `key`. Returns true
+    /// if the event should be written (first occurrence in the window) or false if it
+    /// should be suppressed.
+    ///
+    /// If the event's previous window has expired, writes the summary for that window
+    /// to `provider` (if any occurrences were suppressed) and starts a new window.
+    /// `level` and `keyword` are used for the summary event.
+    pub fn check<K: Hash + ?Sized>(
+        &self,
+        provider: &Provider,
+        name: &str,
+        level: Level,
+        keyword: u64,
+        key: &K,
+    ) -> bool {
+        let mut hasher = DefaultHasher::new();
+        name.hash(&mut hasher);
+        key.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let now = Instant::now();
+        let summary;
+        {
+            let mut entries = self.lock();
+            match entries.get_mut(&hash) {
+                Some(entry) if now.duration_since(entry.start) < self.window => {
+                    entry.suppressed += 1;
+                    return false;
+                }
+                Some(entry) => {
+                    summary = Self::take_summary(entry);
+                    entry.level = level;
+                    entry.keyword = keyword;
+                    entry.start = now;
+                }
+                None => {
+                    summary = None;
+                    entries.insert(
+                        hash,
+                        DedupEntry {
+                            name: String::from(name),
+                            level,
+                            keyword,
+                            start: now,
+                            suppressed: 0,
+                        },
+                    );
+                }
+            }
+        }
+
+        if let Some(summary) = summary {
+            self.write_summary(provider, &summary);
+        }
+
+        return true;
+    }
+
+    /// Removes the entries whose window has expired, writing a summary to `provider`
+    /// for each entry with suppressed occurrences. Returns the number of summaries
+    /// written.
+    pub fn flush_expired(&self, provider: &Provider) -> usize {
+        let now = Instant::now();
+        return self.flush_if(provider, |entry| {
+            now.duration_since(entry.start) >= self.window
+        });
+    }
+
+    /// Removes all entries, writing a summary to `provider` for each entry with
+    /// suppressed occurrences. Returns the number of summaries written.
+    pub fn flush(&self, provider: &Provider) -> usize {
+        return self.flush_if(provider, |_| true);
+    }
+
+    fn flush_if(&self, provider: &Provider, mut expired: impl FnMut(&DedupEntry) -> bool) -> usize {
+        let mut summaries = Vec::new();
+        self.lock().retain(|_, entry| {
+            if !expired(entry) {
+                return true;
+            }
+
+            summaries.extend(Self::take_summary(entry));
+            return false;
+        });
+
+        for summary in &summaries {
+            self.write_summary(provider, summary);
+        }
+
+        return summaries.len();
+    }
+
+    /// Returns the summary for the entry's window (None if nothing was suppressed)
+    /// and resets the entry's suppressed count.
+    fn take_summary(entry: &mut DedupEntry) -> Option<DedupSummary> {
+        let suppressed = core::mem::replace(&mut entry.suppressed, 0);
+        return if suppressed == 0 {
+            None
+        } else {
+            Some(DedupSummary {
+                name: entry.name.clone(),
+                level: entry.level,
+                keyword: entry.keyword,
+                suppressed,
+            })
+        };
+    }
+
+    fn write_summary(&self, provider: &Provider, summary: &DedupSummary) {
+        EventBuilder::new()
+            .reset("Deduplicated", summary.level, summary.keyword, 0)
+            .add_str8("EventName", &summary.name, OutType::Default, 0)
+            .add_u64("Suppressed", summary.suppressed, OutType::Default, 0)
+            .add_u64(
+                "WindowMs",
+                self.window.as_millis() as u64,
+                OutType::Default,
+                0,
+            )
+            .write(provider, None, None);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, DedupEntry>> {
+        // The map is always consistent, so a panic in another thread is not a problem.
+        return self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+}
+
+impl fmt::Debug for EventDeduper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "EventDeduper {{ window: {:?}, len: {} }}",
+            self.window,
+            self.len()
+        );
+    }
+}
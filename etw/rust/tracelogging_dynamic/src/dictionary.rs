@@ -0,0 +1,244 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic;
+
+use tracelogging::InType;
+use tracelogging::OutType;
+
+use crate::builder::EventBuilder;
+use crate::decode::DecodedEvent;
+use crate::decode::DecodedField;
+use crate::decode::DecodedValue;
+use crate::decode::FieldValue;
+use crate::provider::Provider;
+use crate::Level;
+
+/// A table of frequently-logged strings (e.g. operation names) that are written as
+/// small integer ids instead of as strings.
+///
+/// Create one dictionary per provider with the strings that the provider logs most
+/// often, then use [`EventBuilder::add_str_dict`] instead of
+/// [`EventBuilder::add_str`] for fields whose values are usually in the dictionary:
+///
+/// - If the value is in the dictionary, the field is written as a `U16` field named
+///   `"{field_name}DictId"` (see [`StringDictionary::ID_SUFFIX`]) containing the
+///   value's id, i.e. its index in the dictionary.
+/// - Otherwise, the field is written as a normal UTF-8 string field named
+///   `field_name`.
+///
+/// The dictionary itself is written as an event named `StringDictionary` (see
+/// [`StringDictionary::EVENT_NAME`]) with a single field `Strings`, an array of UTF-8
+/// strings in id order. A decoder uses the most recent dictionary event from the
+/// provider to re-expand the `DictId` fields (see [`StringDictionary::from_event`]
+/// and [`StringDictionary::expand`]). Write the dictionary event with
+/// [`StringDictionary::write_once`] before writing events that use the dictionary,
+/// and with [`StringDictionary::write`] whenever a new trace session may need it
+/// (e.g. periodically or when a session enables the provider).
+///
+/// The dictionary is immutable after creation so that ids remain stable. It holds at
+/// most 65536 strings; additional strings are ignored. If a string appears more than
+/// once, its first id is used.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// let operations = tld::StringDictionary::new(["Open", "Read", "Write", "Close"]);
+///
+/// operations.write_once(&provider, tld::Level::Informational, 0x1);
+/// tld::EventBuilder::new()
+///     .reset("Operation", tld::Level::Verbose, 0x1, 0)
+///     .add_str_dict("Name", "Read", &operations, 0) // U16 "NameDictId" = 1.
+///     .add_str_dict("Path", "C:\\temp", &operations, 0) // Str8 "Path" = "C:\temp".
+///     .write(&provider, None, None);
+/// ```
+pub struct StringDictionary {
+    strings: Vec<String>,
+    ids: BTreeMap<String, u16>,
+    written: atomic::AtomicBool,
+}
+
+impl StringDictionary {
+    /// Name of the event that contains the dictionary's strings.
+    pub const EVENT_NAME: &'static str = "StringDictionary";
+
+    /// Suffix appended to the name of a field whose value was replaced by its id.
+    pub const ID_SUFFIX: &'static str = "DictId";
+
+    /// Creates a dictionary containing the specified strings. The first string has id
+    /// 0, the second has id 1, and so on.
+    pub fn new<T: IntoIterator>(strings: T) -> Self
+    where
+        T::Item: AsRef<str>,
+    {
+        let mut dictionary = Self {
+            strings: Vec::new(),
+            ids: BTreeMap::new(),
+            written: atomic::AtomicBool::new(false),
+        };
+
+        for value in strings.into_iter().take(u16::MAX as usize + 1) {
+            let value = value.as_ref();
+            let id = dictionary.strings.len() as u16;
+            dictionary.strings.push(String::from(value));
+            dictionary.ids.entry(String::from(value)).or_insert(id);
+        }
+
+        return dictionary;
+    }
+
+    /// Returns the number of strings in the dictionary.
+    pub fn len(&self) -> usize {
+        return self.strings.len();
+    }
+
+    /// Returns true if the dictionary contains no strings.
+    pub fn is_empty(&self) -> bool {
+        return self.strings.is_empty();
+    }
+
+    /// Returns the id of the specified string, or `None` if the string is not in the
+    /// dictionary.
+    pub fn id(&self, value: &str) -> Option<u16> {
+        return self.ids.get(value).copied();
+    }
+
+    /// Returns the string with the specified id, or `None` if the id is out of range.
+    pub fn get(&self, id: u16) -> Option<&str> {
+        return self.strings.get(id as usize).map(|value| value.as_str());
+    }
+
+    /// Returns the dictionary's strings in id order.
+    pub fn strings(&self) -> &[String] {
+        return &self.strings;
+    }
+
+    /// Writes the dictionary event to `provider` if the provider is enabled for the
+    /// specified level and keyword (or if [capture](Provider::set_capture) is enabled).
+    /// Returns 0 for success or a Win32 error code, as with [`EventBuilder::write`].
+    /// Returns 0 without writing if the provider is not enabled.
+    pub fn write(&self, provider: &Provider, level: Level, keyword: u64) -> u32 {
+        if !provider.enabled(level, keyword) && provider.capture_capacity() == 0 {
+            return 0;
+        }
+
+        let result = EventBuilder::new()
+            .reset(Self::EVENT_NAME, level, keyword, 0)
+            .add_str_sequence("Strings", &self.strings, 0)
+            .write(provider, None, None);
+        if result == 0 {
+            self.written.store(true, atomic::Ordering::Relaxed);
+        }
+        return result;
+    }
+
+    /// Writes the dictionary event to `provider` unless this dictionary's event has
+    /// already been written successfully. Returns 0 for success or a Win32 error code.
+    ///
+    /// If the provider is not enabled, nothing is written and the event will be
+    /// written by a later call.
+    pub fn write_once(&self, provider: &Provider, level: Level, keyword: u64) -> u32 {
+        if self.written.load(atomic::Ordering::Relaxed) {
+            return 0;
+        }
+
+        return self.write(provider, level, keyword);
+    }
+
+    /// Returns the dictionary contained in a decoded dictionary event (an event named
+    /// [`StringDictionary::EVENT_NAME`] with a `Strings` array of strings), or `None`
+    /// if `event` is not a dictionary event.
+    ///
+    /// A decoder should keep the most recent dictionary from each provider and use
+    /// [`StringDictionary::expand`] to re-expand the provider's later events.
+    pub fn from_event(event: &DecodedEvent) -> Option<Self> {
+        if event.name != Self::EVENT_NAME {
+            return None;
+        }
+
+        let values = match &event.field("Strings")?.value {
+            FieldValue::Array(values) => values,
+            _ => return None,
+        };
+
+        let mut strings = Vec::with_capacity(values.len());
+        for value in values {
+            match value {
+                DecodedValue::Str(value) => strings.push(value),
+                _ => return None,
+            }
+        }
+
+        return Some(Self::new(strings));
+    }
+
+    /// Re-expands the `"{name}DictId"` fields of a decoded event (including the
+    /// members of structs) that were written by [`EventBuilder::add_str_dict`]: each
+    /// `U16` field whose name ends with [`StringDictionary::ID_SUFFIX`] and whose value
+    /// is an id in this dictionary is replaced by a UTF-8 string field named `name`
+    /// containing the id's string. Other fields are unchanged.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// let operations = tld::StringDictionary::new(["Open", "Read"]);
+    /// provider.set_capture(2);
+    ///
+    /// operations.write_once(&provider, tld::Level::Informational, 0x1);
+    /// tld::EventBuilder::new()
+    ///     .reset("Operation", tld::Level::Verbose, 0x1, 0)
+    ///     .add_str_dict("Name", "Read", &operations, 0)
+    ///     .write(&provider, None, None);
+    ///
+    /// let captured = provider.drain_captured();
+    /// let dictionary = tld::StringDictionary::from_event(&captured[0].decode().unwrap()).unwrap();
+    /// let mut event = captured[1].decode().unwrap();
+    /// assert_eq!("Operation { NameDictId: 1 }", event.to_string());
+    /// dictionary.expand(&mut event);
+    /// assert_eq!("Operation { Name: \"Read\" }", event.to_string());
+    /// ```
+    pub fn expand(&self, event: &mut DecodedEvent) {
+        self.expand_fields(&mut event.fields);
+    }
+
+    fn expand_fields(&self, fields: &mut [DecodedField]) {
+        for field in fields {
+            match &mut field.value {
+                FieldValue::Scalar(DecodedValue::U16(id)) if field.in_type == InType::U16 => {
+                    let name = match field.name.strip_suffix(Self::ID_SUFFIX) {
+                        Some(name) => name,
+                        None => continue,
+                    };
+                    if let Some(value) = self.get(*id) {
+                        field.name = name;
+                        field.in_type = InType::Str8;
+                        field.out_type = OutType::Utf8;
+                        field.value = FieldValue::Scalar(DecodedValue::Str(String::from(value)));
+                    }
+                }
+                FieldValue::Struct(members) => self.expand_fields(members),
+                FieldValue::StructArray(elements) => {
+                    for members in elements {
+                        self.expand_fields(members);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl fmt::Debug for StringDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return f
+            .debug_struct("StringDictionary")
+            .field("strings", &self.strings)
+            .finish();
+    }
+}
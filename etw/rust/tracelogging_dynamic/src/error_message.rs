@@ -0,0 +1,87 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+/// The kind of error code, used to select the message table(s) to search.
+#[derive(Clone, Copy)]
+pub(crate) enum ErrorCodeKind {
+    Win32Error,
+    HResult,
+    NtStatus,
+}
+
+/// Looks up the system message text for the specified error code via
+/// [FormatMessageW](https://learn.microsoft.com/windows/win32/api/winbase/nf-winbase-formatmessagew).
+///
+/// Writes the message (without trailing whitespace or line breaks) to `buffer` and
+/// returns the number of UTF-16 code units written. Returns 0 if no message was found
+/// or if not running on Windows.
+pub(crate) fn format_error_message(kind: ErrorCodeKind, code: u32, buffer: &mut [u16]) -> usize {
+    let mut len;
+    #[cfg(not(all(windows, feature = "etw")))]
+    {
+        let _ = (kind, code);
+        len = 0;
+    }
+    #[cfg(all(windows, feature = "etw"))]
+    {
+        const FORMAT_MESSAGE_IGNORE_INSERTS: u32 = 0x00000200;
+        const FORMAT_MESSAGE_FROM_HMODULE: u32 = 0x00000800;
+        const FORMAT_MESSAGE_FROM_SYSTEM: u32 = 0x00001000;
+        const FORMAT_MESSAGE_MAX_WIDTH_MASK: u32 = 0x000000FF; // Line breaks become spaces.
+        const NTDLL: [u16; 10] = [
+            b'n' as u16,
+            b't' as u16,
+            b'd' as u16,
+            b'l' as u16,
+            b'l' as u16,
+            b'.' as u16,
+            b'd' as u16,
+            b'l' as u16,
+            b'l' as u16,
+            0,
+        ];
+
+        let mut flags = FORMAT_MESSAGE_FROM_SYSTEM
+            | FORMAT_MESSAGE_IGNORE_INSERTS
+            | FORMAT_MESSAGE_MAX_WIDTH_MASK;
+        let mut source = 0;
+        if let ErrorCodeKind::NtStatus = kind {
+            // NTSTATUS messages are in ntdll.dll's message table.
+            source = unsafe { GetModuleHandleW(NTDLL.as_ptr()) };
+            if source != 0 {
+                flags |= FORMAT_MESSAGE_FROM_HMODULE;
+            }
+        }
+
+        len = unsafe {
+            FormatMessageW(
+                flags,
+                source,
+                code,
+                0, // language_id: default lookup order
+                buffer.as_mut_ptr(),
+                buffer.len() as u32,
+                core::ptr::null(),
+            )
+        } as usize;
+    }
+
+    while len != 0 && matches!(buffer[len - 1], 0x09 | 0x0A | 0x0D | 0x20) {
+        len -= 1;
+    }
+    return len;
+}
+
+#[cfg(all(windows, feature = "etw"))]
+extern "system" {
+    fn FormatMessageW(
+        flags: u32,
+        source: usize,
+        message_id: u32,
+        language_id: u32,
+        buffer: *mut u16,
+        size: u32,
+        arguments: *const usize,
+    ) -> u32;
+    fn GetModuleHandleW(module_name: *const u16) -> usize;
+}
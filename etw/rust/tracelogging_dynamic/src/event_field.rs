@@ -0,0 +1,187 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::time::SystemTime;
+
+use tracelogging::Guid;
+use tracelogging::OutType;
+
+use crate::builder::EventBuilder;
+
+/// A value that can be added to an event by [`EventBuilder::add`], which picks the
+/// field's [InType](tracelogging::InType) and [OutType] based on the value's type.
+///
+/// Implemented for the following types:
+///
+/// | Value type | Added as
+/// |------------|---------
+/// | `i8`, `u8`, `i16`, `u16`, `i32`, `u32`, `i64`, `u64`, `isize`, `usize`, `f32`, `f64` | Same as `add_i8`, `add_u8`, etc. with [OutType::Default].
+/// | `bool` | U8 with [OutType::Boolean].
+/// | `char` | Same as [`EventBuilder::add_char32`] with [OutType::String] (`&[char]` is the same as [`EventBuilder::add_str32`]).
+/// | `&str`, `String`, `&String` | Same as [`EventBuilder::add_str`] (Str8 with [OutType::Utf8]).
+/// | `Guid`, `&Guid` | Guid.
+/// | `SystemTime` (requires feature `std`) | FileTime, converted with [`win_filetime_from_systemtime!`](crate::win_filetime_from_systemtime).
+/// | `&[T]`, `&[T; N]`, `&Vec<T>` where `T` is one of the above (other than `&String`) | Variable-length array of the corresponding type.
+///
+/// Use the `add_xxx` methods for other field types or for non-default formats, e.g.
+/// `add_u32(name, value, OutType::Hex, 0)`.
+///
+/// This trait is intended for code that maps values from another type system (e.g.
+/// a scripting language binding) onto event fields. It may be implemented for other
+/// types, typically by calling one of the `add_xxx` methods of the builder.
+pub trait IntoEventField {
+    /// Adds a field with the specified name and this value to the builder.
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str);
+}
+
+macro_rules! impl_scalars {
+    ($($value_type:ty, $add:ident, $add_sequence:ident;)*) => {$(
+        impl IntoEventField for $value_type {
+            fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+                builder.$add(field_name, self, OutType::Default, 0);
+            }
+        }
+
+        impl IntoEventField for &[$value_type] {
+            fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+                builder.$add_sequence(field_name, self, OutType::Default, 0);
+            }
+        }
+    )*};
+}
+
+impl_scalars! {
+    i8, add_i8, add_i8_sequence;
+    u8, add_u8, add_u8_sequence;
+    i16, add_i16, add_i16_sequence;
+    u16, add_u16, add_u16_sequence;
+    i32, add_i32, add_i32_sequence;
+    u32, add_u32, add_u32_sequence;
+    i64, add_i64, add_i64_sequence;
+    u64, add_u64, add_u64_sequence;
+    isize, add_isize, add_isize_sequence;
+    usize, add_usize, add_usize_sequence;
+    f32, add_f32, add_f32_sequence;
+    f64, add_f64, add_f64_sequence;
+}
+
+impl IntoEventField for bool {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_u8(field_name, self as u8, OutType::Boolean, 0);
+    }
+}
+
+impl IntoEventField for &[bool] {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_u8_sequence(
+            field_name,
+            self.iter().map(|value| if *value { &1u8 } else { &0u8 }),
+            OutType::Boolean,
+            0,
+        );
+    }
+}
+
+impl IntoEventField for char {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_char32(field_name, self, OutType::String, 0);
+    }
+}
+
+impl IntoEventField for &[char] {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_str32(field_name, self, 0);
+    }
+}
+
+impl IntoEventField for &str {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_str(field_name, self, 0);
+    }
+}
+
+impl IntoEventField for String {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_str(field_name, self, 0);
+    }
+}
+
+impl IntoEventField for &String {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_str(field_name, self, 0);
+    }
+}
+
+impl IntoEventField for &[&str] {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_str_sequence(field_name, self, 0);
+    }
+}
+
+impl IntoEventField for &[String] {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_str_sequence(field_name, self, 0);
+    }
+}
+
+impl IntoEventField for Guid {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_guid(field_name, &self, OutType::Default, 0);
+    }
+}
+
+impl IntoEventField for &Guid {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_guid(field_name, self, OutType::Default, 0);
+    }
+}
+
+impl IntoEventField for &[Guid] {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_guid_sequence(field_name, self, OutType::Default, 0);
+    }
+}
+
+#[cfg(feature = "std")]
+impl IntoEventField for SystemTime {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        builder.add_filetime(
+            field_name,
+            crate::win_filetime_from_systemtime!(self),
+            OutType::Default,
+            0,
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+impl IntoEventField for &[SystemTime] {
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        let filetimes = Vec::from_iter(
+            self.iter()
+                .map(|value| crate::win_filetime_from_systemtime!(value)),
+        );
+        builder.add_filetime_sequence(field_name, &filetimes, OutType::Default, 0);
+    }
+}
+
+impl<'a, T, const N: usize> IntoEventField for &'a [T; N]
+where
+    &'a [T]: IntoEventField,
+{
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        self.as_slice().add_to(builder, field_name);
+    }
+}
+
+impl<'a, T> IntoEventField for &'a Vec<T>
+where
+    &'a [T]: IntoEventField,
+{
+    fn add_to(self, builder: &mut EventBuilder, field_name: &str) {
+        self.as_slice().add_to(builder, field_name);
+    }
+}
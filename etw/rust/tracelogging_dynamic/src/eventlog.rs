@@ -0,0 +1,346 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::string::String;
+#[cfg(feature = "eventlog")]
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+#[cfg(feature = "eventlog")]
+use core::sync::atomic;
+
+#[cfg(feature = "eventlog")]
+use tracelogging::_internal::MetaReader;
+#[cfg(feature = "eventlog")]
+use tracelogging::_internal::SpinLock;
+#[cfg(feature = "eventlog")]
+use tracelogging::EventDescriptor;
+
+use crate::decode::DecodedEvent;
+use crate::Level;
+
+/// Mirrors selected events to the classic Windows Event Log (Event Viewer) via
+/// [ReportEventW](https://learn.microsoft.com/windows/win32/api/winbase/nf-winbase-reporteventw).
+///
+/// ETW events are only visible to a trace session that is listening when the event is
+/// written. Some events (e.g. critical errors) should also be visible to administrators
+/// who are not running a trace session. An `EventLog` bridge can be used to report a
+/// formatted message for such events to the Windows Event Log in addition to writing
+/// them to ETW.
+///
+/// An event is reported if its level is less than or equal to the bridge's level and
+/// if its keyword is 0 or has at least one bit in common with the bridge's keyword mask.
+///
+/// ```no_run
+/// use tracelogging_dynamic as tld;
+///
+/// let event_log = tld::EventLog::new("MyCompany.MyComponent", tld::Level::Error, !0);
+/// let error_code = 5;
+/// event_log.report_fmt(
+///     tld::Level::Error,
+///     0x1,
+///     1000, // Event ID shown in Event Viewer.
+///     format_args!("Access denied (error {})", error_code));
+/// ```
+///
+/// With feature `eventlog`, [`Provider::set_event_log`](crate::Provider::set_event_log)
+/// attaches a bridge to a provider so that each selected event written by the
+/// provider is also reported, with a message formatted by [`EventLog::message`].
+///
+/// Messages are reported without a message file, so Event Viewer will display the
+/// message text along with a note that the event ID's description could not be found.
+pub struct EventLog {
+    handle: usize,
+    level: Level,
+    keyword_mask: u64,
+}
+
+impl EventLog {
+    /// Opens a handle to the event log for the specified source name (calls
+    /// `RegisterEventSourceW`). Events with level <= `level` and keyword matching
+    /// `keyword_mask` will be reported.
+    ///
+    /// If the source cannot be opened (or if not Windows/etw), the returned bridge is
+    /// closed and all reports are ignored.
+    pub fn new(source_name: &str, level: Level, keyword_mask: u64) -> Self {
+        let handle;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            let _ = source_name;
+            handle = 0;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let source_name16 = to_utf16z(source_name);
+            handle = unsafe { RegisterEventSourceW(core::ptr::null(), source_name16.as_ptr()) };
+        }
+
+        return Self {
+            handle,
+            level,
+            keyword_mask,
+        };
+    }
+
+    /// Returns true if the event log handle was opened successfully.
+    pub const fn is_open(&self) -> bool {
+        return self.handle != 0;
+    }
+
+    /// Returns the most-verbose level that will be reported.
+    pub const fn level(&self) -> Level {
+        return self.level;
+    }
+
+    /// Returns the keyword mask used to select events.
+    pub const fn keyword_mask(&self) -> u64 {
+        return self.keyword_mask;
+    }
+
+    /// Returns true if an event with the specified level and keyword is selected by
+    /// this bridge's level and keyword mask.
+    pub fn would_report(&self, level: Level, keyword: u64) -> bool {
+        return level <= self.level && (keyword == 0 || (keyword & self.keyword_mask) != 0);
+    }
+
+    /// If an event with the specified level and keyword is selected by this bridge,
+    /// reports `message` to the event log with the specified event ID.
+    ///
+    /// The event type is `EVENTLOG_ERROR_TYPE` for `Critical` and `Error`,
+    /// `EVENTLOG_WARNING_TYPE` for `Warning`, and `EVENTLOG_INFORMATION_TYPE` otherwise
+    /// (including `LogAlways`).
+    ///
+    /// Returns 0 for success (or if the event was not selected or the bridge is not
+    /// open) or a Win32 error code from `ReportEventW`. The return value is for
+    /// diagnostic purposes only and should generally be ignored in retail builds.
+    pub fn report(&self, level: Level, keyword: u64, event_id: u32, message: &str) -> u32 {
+        if self.handle == 0 || !self.would_report(level, keyword) {
+            return 0;
+        }
+
+        return self.report_utf16z(level, event_id, &to_utf16z(message));
+    }
+
+    /// Same as [`EventLog::report`], but formats the message from `args`. The message
+    /// is only formatted if the event is selected by this bridge.
+    pub fn report_fmt(
+        &self,
+        level: Level,
+        keyword: u64,
+        event_id: u32,
+        args: fmt::Arguments,
+    ) -> u32 {
+        if self.handle == 0 || !self.would_report(level, keyword) {
+            return 0;
+        }
+
+        let mut message = String::new();
+        let _ = message.write_fmt(args);
+        return self.report_utf16z(level, event_id, &to_utf16z(&message));
+    }
+
+    /// Returns the message reported for an event written by a provider with this
+    /// bridge attached (see [`Provider::set_event_log`](crate::Provider::set_event_log)):
+    /// the provider name, the event name, and the event's top-level fields as
+    /// `name=value` pairs (formatted as by [`EventFormatter`](crate::EventFormatter)).
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_capture(1);
+    /// tld::EventBuilder::new()
+    ///     .reset("AccessDenied", tld::Level::Error, 0x1, 0)
+    ///     .add_str8("Path", "/data", tld::OutType::Utf8, 0)
+    ///     .add_u32("Error", 5, tld::OutType::Default, 0)
+    ///     .write(&provider, None, None);
+    ///
+    /// let captured = provider.drain_captured();
+    /// assert_eq!(
+    ///     "MyCompany.MyComponent: AccessDenied Path=\"/data\" Error=5",
+    ///     tld::EventLog::message(provider.name(), &captured[0].decode().unwrap())
+    /// );
+    /// ```
+    pub fn message(provider_name: &str, event: &DecodedEvent) -> String {
+        let mut message = String::new();
+        let _ = write!(message, "{}: {}", provider_name, event.name);
+        for field in &event.fields {
+            let _ = write!(message, " {}={}", field.name, field);
+        }
+        return message;
+    }
+
+    /// Reports an event written by a provider with this bridge attached, if the
+    /// event is selected. `data` returns the event's field values and is only called
+    /// if the event is selected. If the event cannot be decoded, the message has only
+    /// the provider name and event name.
+    #[cfg(feature = "eventlog")]
+    fn report_event(
+        &self,
+        provider_name: &str,
+        descriptor: &EventDescriptor,
+        meta: &[u8],
+        data: impl FnOnce() -> Vec<u8>,
+    ) -> u32 {
+        if self.handle == 0 || !self.would_report(descriptor.level, descriptor.keyword) {
+            return 0;
+        }
+
+        let data = data();
+        let message = match DecodedEvent::decode(meta, &data) {
+            Ok(event) => Self::message(provider_name, &event),
+            Err(_) => {
+                let name = MetaReader::new(meta)
+                    .read_event()
+                    .map_or("", |(_, name)| name);
+                let mut message = String::new();
+                let _ = write!(message, "{}: {}", provider_name, name);
+                message
+            }
+        };
+        return self.report_utf16z(descriptor.level, descriptor.id as u32, &to_utf16z(&message));
+    }
+
+    fn report_utf16z(&self, level: Level, event_id: u32, _message16: &[u16]) -> u32 {
+        const EVENTLOG_ERROR_TYPE: u16 = 0x0001;
+        const EVENTLOG_WARNING_TYPE: u16 = 0x0002;
+        const EVENTLOG_INFORMATION_TYPE: u16 = 0x0004;
+
+        // LogAlways (0) is not an error level, so it is reported as information.
+        let _event_type = if level == Level::Critical || level == Level::Error {
+            EVENTLOG_ERROR_TYPE
+        } else if level == Level::Warning {
+            EVENTLOG_WARNING_TYPE
+        } else {
+            EVENTLOG_INFORMATION_TYPE
+        };
+
+        let result;
+        #[cfg(not(all(windows, feature = "etw")))]
+        {
+            let _ = event_id;
+            result = 0;
+        }
+        #[cfg(all(windows, feature = "etw"))]
+        {
+            let strings = [_message16.as_ptr()];
+            let ok = unsafe {
+                ReportEventW(
+                    self.handle,
+                    _event_type,
+                    0, // category
+                    event_id,
+                    core::ptr::null(),
+                    strings.len() as u16,
+                    0,
+                    strings.as_ptr(),
+                    core::ptr::null(),
+                )
+            };
+            result = if ok != 0 {
+                0
+            } else {
+                unsafe { GetLastError() }
+            };
+        }
+        return result;
+    }
+}
+
+impl Drop for EventLog {
+    /// Calls `DeregisterEventSource`.
+    fn drop(&mut self) {
+        #[cfg(all(windows, feature = "etw"))]
+        if self.handle != 0 {
+            unsafe { DeregisterEventSource(self.handle) };
+        }
+    }
+}
+
+impl fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "EventLog {{ handle: {:x}, level: {}, keyword_mask: {:x} }}",
+            self.handle, self.level, self.keyword_mask
+        );
+    }
+}
+
+/// The bridge set by `Provider::set_event_log`, if any. The write path checks
+/// `active` first, so providers without a bridge pay only for one relaxed atomic
+/// load.
+#[cfg(feature = "eventlog")]
+pub(crate) struct EventLogMirror {
+    active: atomic::AtomicBool,
+    event_log: SpinLock<Option<Arc<EventLog>>>,
+}
+
+#[cfg(feature = "eventlog")]
+impl EventLogMirror {
+    pub const fn new() -> Self {
+        return Self {
+            active: atomic::AtomicBool::new(false),
+            event_log: SpinLock::new(None),
+        };
+    }
+
+    /// Returns true if a bridge is set.
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        return self.active.load(atomic::Ordering::Relaxed);
+    }
+
+    /// Sets or removes the bridge. A removed bridge is closed after any reports in
+    /// progress on other threads have finished.
+    pub fn set(&self, event_log: Option<EventLog>) {
+        let previous = {
+            let mut current = self.event_log.lock();
+            self.active
+                .store(event_log.is_some(), atomic::Ordering::Relaxed);
+            core::mem::replace(&mut *current, event_log.map(Arc::new))
+        };
+        drop(previous); // Close the previous bridge without holding the lock.
+    }
+
+    /// Reports the event to the bridge, if one is set. The bridge is not locked while
+    /// the event is reported, so reports from several threads do not wait for each
+    /// other.
+    pub fn report(
+        &self,
+        provider_name: &str,
+        descriptor: &EventDescriptor,
+        meta: &[u8],
+        data: impl FnOnce() -> Vec<u8>,
+    ) {
+        let event_log = self.event_log.lock().clone();
+        if let Some(event_log) = event_log {
+            event_log.report_event(provider_name, descriptor, meta, data);
+        }
+    }
+}
+
+fn to_utf16z(value: &str) -> Vec<u16> {
+    let mut value16: Vec<u16> = value.encode_utf16().collect();
+    value16.push(0);
+    return value16;
+}
+
+#[cfg(all(windows, feature = "etw"))]
+extern "system" {
+    fn RegisterEventSourceW(unc_server_name: *const u16, source_name: *const u16) -> usize;
+    fn DeregisterEventSource(event_log: usize) -> i32;
+    fn ReportEventW(
+        event_log: usize,
+        event_type: u16,
+        category: u16,
+        event_id: u32,
+        user_sid: *const u8,
+        num_strings: u16,
+        data_size: u32,
+        strings: *const *const u16,
+        raw_data: *const u8,
+    ) -> i32;
+    fn GetLastError() -> u32;
+}
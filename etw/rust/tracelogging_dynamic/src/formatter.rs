@@ -0,0 +1,236 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Write;
+
+use tracelogging::EventDescriptor;
+
+use crate::decode::fmt_filetime;
+use crate::decode::DecodedEvent;
+
+/// Formats decoded events as single lines of text, like `tracefmt`, based on a
+/// template.
+///
+/// The template is text with placeholders in braces. `{{` and `}}` are literal
+/// braces. The placeholders are:
+///
+/// - `{timestamp}`: the event's timestamp as an ISO 8601 UTC date-time, or `-` if
+///   the event has no timestamp.
+/// - `{provider}`: the provider's name.
+/// - `{event}`: the event's name.
+/// - `{level}`, `{keyword}`, `{id}`, `{opcode}`, `{task}`: values from the event's
+///   descriptor. The level and opcode are formatted as names when they have one
+///   (e.g. `Warning`), and the keyword in hex.
+/// - `{fields}`: the event's top-level fields as `name=value` pairs, separated by
+///   the [field separator](EventFormatter::field_separator) (default `" "`). Values
+///   are formatted as by [`DecodedField`](crate::DecodedField)'s `Display`
+///   implementation.
+/// - `{field:NAME}`: the value of the top-level field `NAME`, or nothing if the
+///   event has no such field.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// provider.set_capture(1);
+///
+/// let mut builder = tld::EventBuilder::new();
+/// builder
+///     .reset("Request", tld::Level::Warning, 0x1, 0)
+///     .add_str8("Path", "/index.html", tld::OutType::Utf8, 0)
+///     .add_u32("Status", 404, tld::OutType::Default, 0)
+///     .write(&provider, None, None);
+///
+/// let captured = provider.drain_captured();
+/// let event = captured[0].decode().unwrap();
+/// let record = tld::FormatRecord {
+///     timestamp: Some(0x19DB1DED53E8000), // 1970-01-01
+///     provider: provider.name(),
+///     descriptor: &captured[0].descriptor,
+///     event: &event,
+/// };
+///
+/// let formatter = tld::EventFormatter::new("[{provider}] {level} {event}: {fields}").unwrap();
+/// assert_eq!(
+///     "[MyCompany.MyComponent] Warning Request: Path=\"/index.html\" Status=404",
+///     formatter.format(&record)
+/// );
+///
+/// assert_eq!(
+///     "1970-01-01T00:00:00.0000000Z [MyCompany.MyComponent] Request: Path=\"/index.html\" Status=404",
+///     tld::EventFormatter::default().format(&record)
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventFormatter {
+    segments: Vec<Segment>,
+    field_separator: String,
+}
+
+impl EventFormatter {
+    /// The template used by [`EventFormatter::default`].
+    pub const DEFAULT_TEMPLATE: &'static str = "{timestamp} [{provider}] {event}: {fields}";
+
+    /// Creates a formatter for the specified template. Returns an error if the
+    /// template has an unknown or unterminated placeholder or an unmatched `}`.
+    pub fn new(template: &str) -> Result<Self, TemplateError> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(pos) = rest.find(['{', '}']) {
+            let offset = template.len() - rest.len() + pos;
+            text.push_str(&rest[..pos]);
+            let tail = &rest[pos..];
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                text.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            } else if tail.starts_with('}') {
+                return Err(TemplateError { offset });
+            }
+
+            let end = tail.find('}').ok_or(TemplateError { offset })?;
+            let segment = match &tail[1..end] {
+                "timestamp" => Segment::Timestamp,
+                "provider" => Segment::Provider,
+                "event" => Segment::Event,
+                "level" => Segment::Level,
+                "keyword" => Segment::Keyword,
+                "id" => Segment::Id,
+                "opcode" => Segment::Opcode,
+                "task" => Segment::Task,
+                "fields" => Segment::Fields,
+                name => match name.strip_prefix("field:") {
+                    Some(field) if !field.contains('{') => Segment::Field(String::from(field)),
+                    _ => return Err(TemplateError { offset }),
+                },
+            };
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(core::mem::take(&mut text)));
+            }
+            segments.push(segment);
+            rest = &tail[end + 1..];
+        }
+
+        text.push_str(rest);
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        return Ok(Self {
+            segments,
+            field_separator: String::from(" "),
+        });
+    }
+
+    /// Sets the text written between the `name=value` pairs of `{fields}`.
+    pub fn field_separator(&mut self, separator: &str) -> &mut Self {
+        self.field_separator = String::from(separator);
+        return self;
+    }
+
+    /// Writes the formatted event to `out`, without a trailing newline.
+    pub fn write(&self, out: &mut dyn Write, record: &FormatRecord) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.write_str(text)?,
+                Segment::Timestamp => match record.timestamp {
+                    Some(timestamp) => write!(out, "{}", FileTime(timestamp))?,
+                    None => out.write_str("-")?,
+                },
+                Segment::Provider => out.write_str(record.provider)?,
+                Segment::Event => out.write_str(record.event.name)?,
+                Segment::Level => write!(out, "{}", record.descriptor.level)?,
+                Segment::Keyword => write!(out, "0x{:X}", record.descriptor.keyword)?,
+                Segment::Id => write!(out, "{}", record.descriptor.id)?,
+                Segment::Opcode => write!(out, "{}", record.descriptor.opcode)?,
+                Segment::Task => write!(out, "{}", record.descriptor.task)?,
+                Segment::Fields => {
+                    for (i, field) in record.event.fields.iter().enumerate() {
+                        if i != 0 {
+                            out.write_str(&self.field_separator)?;
+                        }
+                        write!(out, "{}={}", field.name, field)?;
+                    }
+                }
+                Segment::Field(name) => {
+                    if let Some(field) = record.event.field(name) {
+                        write!(out, "{}", field)?;
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    /// Returns the formatted event, without a trailing newline.
+    pub fn format(&self, record: &FormatRecord) -> String {
+        let mut out = String::new();
+        let _ = self.write(&mut out, record); // Writing to a String does not fail.
+        return out;
+    }
+}
+
+impl Default for EventFormatter {
+    /// Returns a formatter for [`EventFormatter::DEFAULT_TEMPLATE`].
+    fn default() -> Self {
+        return Self::new(Self::DEFAULT_TEMPLATE).unwrap();
+    }
+}
+
+/// An event and its context, as formatted by [EventFormatter].
+#[derive(Clone, Copy, Debug)]
+pub struct FormatRecord<'a> {
+    /// The event's timestamp (100ns intervals since 1601-01-01T00:00:00Z, as in a
+    /// FILETIME), if known.
+    pub timestamp: Option<i64>,
+    /// The name of the provider that wrote the event.
+    pub provider: &'a str,
+    /// The event's descriptor, e.g. [`CapturedEvent::descriptor`](crate::CapturedEvent::descriptor).
+    pub descriptor: &'a EventDescriptor,
+    /// The decoded event.
+    pub event: &'a DecodedEvent<'a>,
+}
+
+/// The error returned by [`EventFormatter::new`] for an invalid template.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TemplateError {
+    /// The offset of the invalid placeholder or brace in the template.
+    pub offset: usize,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "invalid template placeholder at offset {}", self.offset);
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TemplateError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Timestamp,
+    Provider,
+    Event,
+    Level,
+    Keyword,
+    Id,
+    Opcode,
+    Task,
+    Fields,
+    Field(String),
+}
+
+struct FileTime(i64);
+
+impl fmt::Display for FileTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return fmt_filetime(f, self.0);
+    }
+}
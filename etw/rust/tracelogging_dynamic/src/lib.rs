@@ -75,7 +75,7 @@
 //!         // Most events specify 0 for event tag.
 //!         .reset("MyEventName", my_event_level, my_event_keyword, 0)
 //!         // Most fields use Default for event format and 0 for field tag.
-//!         .add_str8("FieldName1", field1_value, tld::OutType::Default, 0)
+//!         .add_str("FieldName1", field1_value, 0)
 //!         .add_u8("FieldName2", field2_value, tld::OutType::String, 0)
 //!         // If activity_id is None, event uses the current thread's activity.
 //!         // If related_id is None, event will not have a related activity.
@@ -113,19 +113,80 @@
 
 // Re-exports from tracelogging:
 pub use tracelogging::Channel;
+pub use tracelogging::EnableChangedCallback;
+pub use tracelogging::EventControlCode;
+pub use tracelogging::EventDescriptor;
+pub use tracelogging::EventFilterDescriptor;
 pub use tracelogging::Guid;
 pub use tracelogging::InType;
 pub use tracelogging::Level;
 pub use tracelogging::NativeImplementation;
 pub use tracelogging::Opcode;
 pub use tracelogging::OutType;
+pub use tracelogging::ParseEnumError;
+#[cfg(feature = "write_hook")]
+pub use tracelogging::PreWriteHook;
 pub use tracelogging::ProviderEnableCallback;
+pub use tracelogging::ProviderEnableState;
+pub use tracelogging::ProviderEnabledInfo;
+pub use tracelogging::ProviderMetadata;
+#[cfg(feature = "stats")]
+pub use tracelogging::ProviderStats;
+pub use tracelogging::ProviderTrait;
+pub use tracelogging::ProviderTraits;
+pub use tracelogging::RegisterError;
+pub use tracelogging::RegisterOptions;
+pub use tracelogging::SessionEnableInfo;
+pub use tracelogging::StateSource;
+pub use tracelogging::StateSources;
+pub use tracelogging::WriteError;
+pub use tracelogging::WriteFlags;
 pub use tracelogging::NATIVE_IMPLEMENTATION;
 
 // Exports from tracelogging_dynamic:
+#[cfg(feature = "std")]
+pub use async_writer::AsyncWriter;
+pub use borrowing::BorrowingEventBuilder;
 pub use builder::EventBuilder;
+#[cfg(feature = "std")]
+pub use builder_pool::EventBuilderPool;
+#[cfg(feature = "std")]
+pub use builder_pool::PooledEventBuilder;
+pub use capture::CapturedEvent;
+pub use chunks::ChunkReassembler;
+pub use decode::DecodeError;
+pub use decode::DecodedEvent;
+pub use decode::DecodedField;
+pub use decode::DecodedValue;
+pub use decode::FieldValue;
+pub use decode::NameMap;
+pub use decode::RawElements;
+pub use decode::RawField;
+pub use decode::RawFields;
+pub use decode::RawValue;
+pub use decode::RawValues;
+#[cfg(feature = "std")]
+pub use dedup::EventDeduper;
+pub use dictionary::StringDictionary;
+pub use event_field::IntoEventField;
+pub use eventlog::EventLog;
+pub use formatter::EventFormatter;
+pub use formatter::FormatRecord;
+pub use formatter::TemplateError;
+#[cfg(all(windows, feature = "loopback"))]
+pub use loopback::LoopbackSession;
 pub use provider::Provider;
 pub use provider::ProviderOptions;
+pub use provider_set::ProviderSet;
+#[cfg(feature = "std")]
+pub use provider_factory::ProviderFactory;
+pub use sanitizer::FieldSanitizer;
+pub use schema::Schema;
+pub use schema::SchemaField;
+pub use schema::SchemaMismatch;
+pub use session::ProviderSession;
+#[cfg(feature = "tracing")]
+pub use tracing_layer::TracingLayer;
 
 pub mod changelog;
 
@@ -163,6 +224,190 @@ macro_rules! win_filetime_from_systemtime {
     };
 }
 
+/// Resets an [EventBuilder] and adds fields to it using the same terse field syntax as
+/// `tracelogging::write_event!`, with a runtime event name, level, and keyword.
+///
+/// `build_event!(&mut builder, event_name, options and fields...)`
+///
+/// Evaluates to the `&mut EventBuilder`, so the event can be written with
+/// [`EventBuilder::write`] or extended with additional `add_xxx` calls. This is useful
+/// when the event's name, level, or keyword is determined at runtime but its fields
+/// are fixed.
+///
+/// Options:
+///
+/// - `level(LEVEL)`: The event's [Level]. Default is [`Level::Verbose`].
+/// - `keyword(KEYWORD)`: The event's keyword bits. May be used more than once (the
+///   values are combined). Default is `0x1`.
+/// - `tag(TAG)`: The event's tag. Default is `0`.
+/// - `id_version(ID, VERSION)`, `channel(CHANNEL)`, `opcode(OPCODE)`, `task(TASK)`:
+///   Same as the corresponding [EventBuilder] methods.
+///
+/// Option values are runtime expressions, e.g. `level(tld::Level::Warning)` or
+/// `level(my_level)`.
+///
+/// Fields use the syntax `TYPE("NAME", VALUE, format(FORMAT), tag(TAG))`, where the
+/// `format` and `tag` options are optional and FORMAT is the name of an [OutType]
+/// value, e.g. `u32("Flags", flags, format(Hex))`. Supported types are the
+/// `write_event!` field types that have a corresponding `add_xxx` method: `i8`, `u8`,
+/// `i16`, `u16`, `i32`, `u32`, `i64`, `u64`, `isize`, `usize`, `f32`, `f64`,
+/// `bool8`, `bool32`, `hex32`, `hex64`, `hexsize`, `pointer`, `codepointer`,
+/// `char8_cp1252`, `char16`, `char32`, `errno`, `hresult`, `win_error`,
+/// `win_filetime`, `guid`, `win_systemtime`, `str8`, `str16`, `str32`, `cstr8`,
+/// `cstr16`, `binary`, `binaryc`, and `win_sid`, plus a `TYPE_slice` variant of each
+/// type except `binary` and `str32`. Each field type uses the same default format as in `write_event!`, e.g.
+/// `str8` fields default to [`OutType::Utf8`].
+///
+/// Scalar field values may be given by value or by reference (e.g. `5u32` or `&id`),
+/// string and binary values may be any type that implements `AsRef<[u8]>` or
+/// `AsRef<[u16]>` (e.g. `&str` or `&String` for `str8`), and slice values may be any
+/// iterable of references to the element type (e.g. `&[u32]` or `&Vec<u32>`). Use the
+/// `add_xxx` methods directly for other field types, e.g. structs.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+/// let mut builder = tld::EventBuilder::new();
+///
+/// let event_name = String::from("RequestCompleted"); // e.g. from configuration
+/// let level = tld::Level::Informational;
+/// if provider.enabled(level, 0x2) {
+///     tld::build_event!(
+///         &mut builder,
+///         &event_name,
+///         level(level),
+///         keyword(0x2),
+///         u32("Status", 200u32),
+///         str8("Url", "https://contoso.com/"),
+///         u32("Flags", &0x12u32, format(Hex)),
+///         u16_slice("Ports", &[80u16, 443]),
+///     )
+///     .write(&provider, None, None);
+/// }
+/// ```
+#[macro_export]
+macro_rules! build_event {
+    ($builder:expr, $name:expr $(, $kind:ident ( $($args:tt)* ))* $(,)?) => {{
+        let _tld_builder: &mut $crate::EventBuilder = $builder;
+        #[allow(unused_mut)]
+        let mut _tld_level: $crate::Level = $crate::Level::Verbose;
+        #[allow(unused_mut)]
+        let mut _tld_keyword: ::core::option::Option<u64> = ::core::option::Option::None;
+        #[allow(unused_mut)]
+        let mut _tld_tag: u32 = 0;
+        $( $crate::_tld_build_event_option!(_tld_level, _tld_keyword, _tld_tag, $kind ( $($args)* )); )*
+        _tld_builder.reset(
+            $name,
+            _tld_level,
+            ::core::option::Option::unwrap_or(_tld_keyword, 1),
+            _tld_tag,
+        );
+        $( $crate::_tld_build_event_item!(_tld_builder, $kind ( $($args)* )); )*
+        _tld_builder
+    }};
+}
+
+/// For use by the build_event macro: handles the options needed by `reset`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tld_build_event_option {
+    ($level:ident, $keyword:ident, $tag:ident, level ( $value:expr $(,)? )) => {
+        $level = $value;
+    };
+    ($level:ident, $keyword:ident, $tag:ident, keyword ( $value:expr $(,)? )) => {
+        $keyword = ::core::option::Option::Some(
+            ::core::option::Option::unwrap_or($keyword, 0) | $value,
+        );
+    };
+    ($level:ident, $keyword:ident, $tag:ident, tag ( $value:expr $(,)? )) => {
+        $tag = $value;
+    };
+    ($level:ident, $keyword:ident, $tag:ident, $kind:ident ( $($args:tt)* )) => {};
+}
+
+/// For use by the build_event macro: handles the other options and the fields.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tld_build_event_item {
+    ($builder:ident, level ( $($args:tt)* )) => {};
+    ($builder:ident, keyword ( $($args:tt)* )) => {};
+    ($builder:ident, tag ( $($args:tt)* )) => {};
+    ($builder:ident, id_version ( $id:expr, $version:expr $(,)? )) => {
+        $builder.id_version($id, $version);
+    };
+    ($builder:ident, channel ( $value:expr $(,)? )) => {
+        $builder.channel($value);
+    };
+    ($builder:ident, opcode ( $value:expr $(,)? )) => {
+        $builder.opcode($value);
+    };
+    ($builder:ident, task ( $value:expr $(,)? )) => {
+        $builder.task($value);
+    };
+    ($builder:ident, $field_type:ident ( $name:expr, $value:expr $(, $option:ident ( $($option_args:tt)* ))* $(,)? )) => {{
+        #[allow(unused_mut)]
+        let mut _tld_format: ::core::option::Option<$crate::OutType> = ::core::option::Option::None;
+        #[allow(unused_mut)]
+        let mut _tld_field_tag: u32 = 0;
+        $( $crate::_tld_build_event_field_option!(_tld_format, _tld_field_tag, $option ( $($option_args)* )); )*
+        $crate::_build_fields::$field_type($builder, $name, $value, _tld_format, _tld_field_tag);
+    }};
+}
+
+/// For use by the build_event macro: handles the options of a field.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! _tld_build_event_field_option {
+    ($format:ident, $tag:ident, format ( $value:ident $(,)? )) => {
+        $format = ::core::option::Option::Some($crate::OutType::$value);
+    };
+    ($format:ident, $tag:ident, tag ( $value:expr $(,)? )) => {
+        $tag = $value;
+    };
+    ($format:ident, $tag:ident, $option:ident ( $($args:tt)* )) => {
+        ::core::compile_error!(::core::concat!(
+            "unrecognized field option \"",
+            ::core::stringify!($option),
+            "\" (expected format(FORMAT) or tag(TAG))"
+        ));
+    };
+}
+
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(feature = "std")]
+mod async_writer;
+#[doc(hidden)]
+pub mod _build_fields;
+mod borrowing;
 mod builder;
+#[cfg(feature = "std")]
+mod builder_pool;
+mod capture;
+mod chunks;
+mod decode;
+#[cfg(feature = "std")]
+mod dedup;
+mod dictionary;
+mod error_message;
+mod event_field;
+mod eventlog;
+mod formatter;
+#[cfg(all(windows, feature = "loopback"))]
+mod loopback;
+mod pair;
 mod provider;
+mod provider_set;
+#[cfg(feature = "std")]
+mod provider_factory;
+#[cfg(feature = "std")]
+mod rundown;
+mod sampling;
+mod sanitizer;
+mod schema;
+mod session;
+mod small_buffer;
+#[cfg(feature = "tracing")]
+mod tracing_layer;
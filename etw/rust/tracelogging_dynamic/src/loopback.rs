@@ -0,0 +1,454 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::mem::zeroed;
+use core::ptr;
+use core::slice;
+use core::sync::atomic;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use tracelogging::EventDescriptor;
+use tracelogging::Guid;
+use tracelogging::Level;
+
+use crate::capture::CapturedEvent;
+
+#[allow(unused_imports)] // For docs
+use crate::Provider;
+
+/// A real-time ETW session that collects the TraceLogging events of one provider and
+/// delivers them back to the current process (requires Windows and feature
+/// `loopback`).
+///
+/// This is a testing helper: it lets a test verify that the events written by a
+/// provider (`write_event!` or [`Provider`]) actually reach ETW and decode correctly,
+/// instead of only checking that the write returned 0. Events are returned as
+/// [`CapturedEvent`] values, so they can be decoded with [`CapturedEvent::decode`].
+///
+/// Starting a session requires administrator rights or membership in the
+/// "Performance Log Users" group. [`LoopbackSession::start`] returns
+/// `ERROR_ACCESS_DENIED` (5) otherwise, which a test might treat as "skip".
+///
+/// ETW delivers real-time events in batches, typically within a second of the write.
+/// Use [`LoopbackSession::wait_for`] to wait for the expected number of events.
+///
+/// ```no_run
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = Box::pin(tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()));
+/// unsafe { provider.as_ref().register(); }
+///
+/// let session = tld::LoopbackSession::start(provider.id(), tld::Level::Verbose, !0).unwrap();
+/// let mut builder = tld::EventBuilder::new();
+/// builder
+///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///     .add_u32("Value", 5, tld::OutType::Default, 0)
+///     .write(&provider, None, None);
+///
+/// let events = session.wait_for(1, std::time::Duration::from_secs(10));
+/// assert_eq!("MyEvent { Value: 5 }", events[0].decode().unwrap().to_string());
+/// ```
+pub struct LoopbackSession {
+    session_handle: u64,
+    properties: Box<SessionProperties>,
+    consumer: Option<thread::JoinHandle<()>>,
+    shared: Arc<Shared>,
+}
+
+impl LoopbackSession {
+    /// Starts a real-time session named `TraceLogging.Loopback.{pid}.{n}` and enables
+    /// the provider with the specified id for events with the specified level and any
+    /// of the specified keywords (use `!0` for all keywords).
+    ///
+    /// Returns a Win32 error code if the session cannot be started or the provider
+    /// cannot be enabled. Events written after `start` returns are collected, whether
+    /// or not the provider is registered yet.
+    pub fn start(provider_id: &Guid, level: Level, keyword_any: u64) -> Result<Self, u32> {
+        static NEXT_SESSION: atomic::AtomicU32 = atomic::AtomicU32::new(0);
+        let session_name = format!(
+            "TraceLogging.Loopback.{}.{}",
+            std::process::id(),
+            NEXT_SESSION.fetch_add(1, atomic::Ordering::Relaxed)
+        );
+
+        let mut properties = Box::new(SessionProperties::new());
+        let mut session_handle = 0;
+        let name = to_utf16z(&session_name);
+        let mut result =
+            unsafe { StartTraceW(&mut session_handle, name.as_ptr(), &mut *properties) };
+        if result == ERROR_ALREADY_EXISTS {
+            // Left over from a process that had the same id. Stop it and try again.
+            let mut stale = SessionProperties::new();
+            unsafe { ControlTraceW(0, name.as_ptr(), &mut stale, EVENT_TRACE_CONTROL_STOP) };
+            properties = Box::new(SessionProperties::new());
+            result = unsafe { StartTraceW(&mut session_handle, name.as_ptr(), &mut *properties) };
+        }
+
+        if result != 0 {
+            return Err(result);
+        }
+
+        let mut session = Self {
+            session_handle,
+            properties,
+            consumer: None,
+            shared: Arc::new(Shared {
+                provider_id: *provider_id,
+                events: Mutex::new(Vec::new()),
+                received: Condvar::new(),
+            }),
+        };
+
+        // Connect the consumer before enabling the provider so that no events are lost.
+        let mut logfile: EventTraceLogfile = unsafe { zeroed() };
+        logfile.logger_name = name.as_ptr() as *mut u16;
+        logfile.process_trace_mode = PROCESS_TRACE_MODE_REAL_TIME | PROCESS_TRACE_MODE_EVENT_RECORD;
+        logfile.event_record_callback = Some(event_record_callback);
+        logfile.context = Arc::as_ptr(&session.shared) as *mut c_void;
+        let trace_handle = unsafe { OpenTraceW(&mut logfile) };
+        if trace_handle == INVALID_PROCESSTRACE_HANDLE {
+            return Err(unsafe { GetLastError() });
+        }
+
+        session.consumer = Some(thread::spawn(move || {
+            let mut handle = trace_handle;
+            unsafe {
+                ProcessTrace(&mut handle, 1, ptr::null(), ptr::null());
+                CloseTrace(handle);
+            }
+        }));
+
+        let result = unsafe {
+            EnableTraceEx2(
+                session_handle,
+                provider_id,
+                EVENT_CONTROL_CODE_ENABLE_PROVIDER,
+                level.as_int(),
+                keyword_any,
+                0,
+                0,
+                ptr::null(),
+            )
+        };
+        if result != 0 {
+            return Err(result);
+        }
+
+        return Ok(session);
+    }
+
+    /// Waits until at least `count` events have been received or until `timeout`
+    /// elapses, then removes and returns all of the events received so far, in the
+    /// order they were received.
+    pub fn wait_for(&self, count: usize, timeout: Duration) -> Vec<CapturedEvent> {
+        let deadline = Instant::now() + timeout;
+        let mut events = self.shared.events.lock().unwrap();
+        while events.len() < count {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+
+            events = self
+                .shared
+                .received
+                .wait_timeout(events, deadline - now)
+                .unwrap()
+                .0;
+        }
+
+        return core::mem::take(&mut *events);
+    }
+
+    /// Removes and returns the events received so far, without waiting.
+    pub fn drain(&self) -> Vec<CapturedEvent> {
+        return core::mem::take(&mut *self.shared.events.lock().unwrap());
+    }
+}
+
+impl Drop for LoopbackSession {
+    /// Stops the session. Events that ETW has not delivered yet are discarded.
+    fn drop(&mut self) {
+        unsafe {
+            ControlTraceW(
+                self.session_handle,
+                ptr::null(),
+                &mut *self.properties,
+                EVENT_TRACE_CONTROL_STOP,
+            );
+        }
+
+        // ProcessTrace returns once the session stops. The consumer holds a pointer to
+        // `shared`, so it must finish before `shared` is dropped.
+        if let Some(consumer) = self.consumer.take() {
+            let _ = consumer.join();
+        }
+    }
+}
+
+/// State shared with the consumer thread. The consumer's context pointer refers to
+/// this object.
+struct Shared {
+    provider_id: Guid,
+    events: Mutex<Vec<CapturedEvent>>,
+    received: Condvar,
+}
+
+unsafe extern "system" fn event_record_callback(record: *mut EventRecord) {
+    let record = &*record;
+    let shared = &*(record.user_context as *const Shared);
+    let header = &record.event_header;
+    if header.provider_id != shared.provider_id {
+        return; // e.g. the session's own header event.
+    }
+
+    let mut meta: &[u8] = &[];
+    let mut related_id = None;
+    if !record.extended_data.is_null() {
+        let items =
+            slice::from_raw_parts(record.extended_data, record.extended_data_count as usize);
+        for item in items {
+            let data =
+                slice::from_raw_parts(item.data_ptr as usize as *const u8, item.data_size as usize);
+            match item.ext_type {
+                EVENT_HEADER_EXT_TYPE_EVENT_SCHEMA_TL => meta = data,
+                EVENT_HEADER_EXT_TYPE_RELATED_ACTIVITYID if data.len() == 16 => {
+                    related_id = Some(Guid::from_bytes_le(&data.try_into().unwrap()));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if meta.is_empty() {
+        return; // Not a TraceLogging event.
+    }
+
+    let data = if record.user_data.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(
+            record.user_data as *const u8,
+            record.user_data_length as usize,
+        )
+    };
+
+    let event = CapturedEvent {
+        descriptor: header.event_descriptor,
+        activity_id: if header.activity_id == Guid::zero() {
+            None
+        } else {
+            Some(header.activity_id)
+        },
+        related_id,
+        meta: meta.to_vec(),
+        data: data.to_vec(),
+    };
+
+    shared.events.lock().unwrap().push(event);
+    shared.received.notify_all();
+}
+
+fn to_utf16z(value: &str) -> Vec<u16> {
+    return value.encode_utf16().chain(Some(0)).collect();
+}
+
+const ERROR_ALREADY_EXISTS: u32 = 183;
+const WNODE_FLAG_TRACED_GUID: u32 = 0x00020000;
+const EVENT_TRACE_REAL_TIME_MODE: u32 = 0x00000100;
+const EVENT_TRACE_CONTROL_STOP: u32 = 1;
+const EVENT_CONTROL_CODE_ENABLE_PROVIDER: u32 = 1;
+const PROCESS_TRACE_MODE_REAL_TIME: u32 = 0x00000100;
+const PROCESS_TRACE_MODE_EVENT_RECORD: u32 = 0x10000000;
+const INVALID_PROCESSTRACE_HANDLE: u64 = usize::MAX as u64; // INVALID_HANDLE_VALUE
+const EVENT_HEADER_EXT_TYPE_RELATED_ACTIVITYID: u16 = 1;
+const EVENT_HEADER_EXT_TYPE_EVENT_SCHEMA_TL: u16 = 11;
+const MAX_SESSION_NAME: usize = 1024;
+
+// Sizes of the Windows structures on 64-bit targets.
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(
+    size_of::<SessionProperties>() - MAX_SESSION_NAME * 2 == 120
+        && size_of::<EventTraceLogfile>() == 448
+        && size_of::<EventRecord>() == 112
+        && size_of::<EventHeader>() == 80
+        && size_of::<EventHeaderExtendedDataItem>() == 16
+);
+
+/// EVENT_TRACE_PROPERTIES followed by space for the session name.
+#[repr(C)]
+struct SessionProperties {
+    // WNODE_HEADER
+    wnode_buffer_size: u32,
+    wnode_provider_id: u32,
+    wnode_historical_context: u64,
+    wnode_time_stamp: i64,
+    wnode_guid: Guid,
+    wnode_client_context: u32,
+    wnode_flags: u32,
+
+    buffer_size: u32,
+    minimum_buffers: u32,
+    maximum_buffers: u32,
+    maximum_file_size: u32,
+    log_file_mode: u32,
+    flush_timer: u32,
+    enable_flags: u32,
+    age_limit: i32,
+    number_of_buffers: u32,
+    free_buffers: u32,
+    events_lost: u32,
+    buffers_written: u32,
+    log_buffers_lost: u32,
+    real_time_buffers_lost: u32,
+    logger_thread_id: usize,
+    log_file_name_offset: u32,
+    logger_name_offset: u32,
+
+    logger_name: [u16; MAX_SESSION_NAME],
+}
+
+impl SessionProperties {
+    fn new() -> Self {
+        let mut properties: Self = unsafe { zeroed() };
+        properties.wnode_buffer_size = size_of::<Self>() as u32;
+        properties.wnode_client_context = 1; // QueryPerformanceCounter timestamps.
+        properties.wnode_flags = WNODE_FLAG_TRACED_GUID;
+        properties.log_file_mode = EVENT_TRACE_REAL_TIME_MODE;
+        properties.flush_timer = 1; // Deliver events at least once per second.
+        properties.logger_name_offset = (size_of::<Self>() - MAX_SESSION_NAME * 2) as u32;
+        return properties;
+    }
+}
+
+/// EVENT_TRACE_LOGFILEW. Fields that are not used are opaque.
+#[repr(C)]
+struct EventTraceLogfile {
+    log_file_name: *mut u16,
+    logger_name: *mut u16,
+    current_time: i64,
+    buffers_read: u32,
+    process_trace_mode: u32,
+    current_event: EventTrace,
+    logfile_header: TraceLogfileHeader,
+    buffer_callback: usize,
+    buffer_size: u32,
+    filled: u32,
+    events_lost: u32,
+    event_record_callback: Option<unsafe extern "system" fn(record: *mut EventRecord)>,
+    is_kernel_trace: u32,
+    context: *mut c_void,
+}
+
+/// EVENT_TRACE.
+#[repr(C)]
+struct EventTrace {
+    header: [u64; 6], // EVENT_TRACE_HEADER
+    instance_id: u32,
+    parent_instance_id: u32,
+    parent_guid: Guid,
+    mof_data: *mut c_void,
+    mof_length: u32,
+    client_context: u32,
+}
+
+/// TRACE_LOGFILE_HEADER.
+#[repr(C)]
+struct TraceLogfileHeader {
+    header: [u32; 10], // BufferSize through BuffersWritten.
+    log_instance_guid: Guid,
+    logger_name: *mut u16,
+    log_file_name: *mut u16,
+    time_zone: [u32; 43], // TIME_ZONE_INFORMATION
+    boot_time: i64,
+    perf_freq: i64,
+    start_time: i64,
+    reserved_flags: u32,
+    buffers_lost: u32,
+}
+
+/// EVENT_RECORD.
+#[repr(C)]
+struct EventRecord {
+    event_header: EventHeader,
+    buffer_context: u32,
+    extended_data_count: u16,
+    user_data_length: u16,
+    extended_data: *const EventHeaderExtendedDataItem,
+    user_data: *const c_void,
+    user_context: *const c_void,
+}
+
+/// EVENT_HEADER.
+#[repr(C)]
+struct EventHeader {
+    size: u16,
+    header_type: u16,
+    flags: u16,
+    event_property: u16,
+    thread_id: u32,
+    process_id: u32,
+    time_stamp: i64,
+    provider_id: Guid,
+    event_descriptor: EventDescriptor,
+    processor_time: u64,
+    activity_id: Guid,
+}
+
+/// EVENT_HEADER_EXTENDED_DATA_ITEM.
+#[repr(C)]
+struct EventHeaderExtendedDataItem {
+    reserved1: u16,
+    ext_type: u16,
+    linkage: u16,
+    data_size: u16,
+    data_ptr: u64,
+}
+
+#[link(name = "advapi32")]
+extern "system" {
+    fn StartTraceW(
+        session_handle: &mut u64,
+        session_name: *const u16,
+        properties: *mut SessionProperties,
+    ) -> u32;
+    fn ControlTraceW(
+        session_handle: u64,
+        session_name: *const u16,
+        properties: *mut SessionProperties,
+        control_code: u32,
+    ) -> u32;
+    fn EnableTraceEx2(
+        session_handle: u64,
+        provider_id: &Guid,
+        control_code: u32,
+        level: u8,
+        match_any_keyword: u64,
+        match_all_keyword: u64,
+        timeout: u32,
+        enable_parameters: *const c_void,
+    ) -> u32;
+    fn OpenTraceW(logfile: *mut EventTraceLogfile) -> u64;
+    fn ProcessTrace(
+        handle_array: *mut u64,
+        handle_count: u32,
+        start_time: *const i64,
+        end_time: *const i64,
+    ) -> u32;
+    fn CloseTrace(trace_handle: u64) -> u32;
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    fn GetLastError() -> u32;
+}
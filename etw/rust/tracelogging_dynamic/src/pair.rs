@@ -0,0 +1,29 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use tracelogging::_internal::SpinLock;
+
+/// Sequence numbers for [`Provider::write_pair`](crate::Provider::write_pair),
+/// protected by a spin lock that is held while both events of a pair are written, so
+/// pairs written by different threads are not interleaved.
+pub(crate) struct PairSequence {
+    next: SpinLock<u64>,
+}
+
+impl PairSequence {
+    pub const fn new() -> Self {
+        return Self {
+            next: SpinLock::new(0),
+        };
+    }
+
+    /// Reserves two consecutive sequence numbers and calls `f` with the first one
+    /// while holding the lock.
+    pub fn with_next_pair<R>(&self, f: impl FnOnce(u64) -> R) -> R {
+        // The guard releases the lock even if f panics.
+        let mut next = self.next.lock();
+        let sequence = *next;
+        *next = sequence.wrapping_add(2);
+        return f(sequence);
+    }
+}
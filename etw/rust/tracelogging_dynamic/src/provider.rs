@@ -2,17 +2,51 @@
 // Licensed under the MIT license.
 
 use alloc::vec::Vec;
+use core::ffi::c_void;
 use core::fmt;
+use core::mem;
 use core::pin::Pin;
+use core::ptr;
 use core::str::from_utf8;
+use core::sync::atomic::AtomicBool;
+use core::sync::atomic::AtomicPtr;
+use core::sync::atomic::Ordering;
 
+use tracelogging::EnableChangedCallback;
+use tracelogging::EventFilterDescriptor;
 use tracelogging::Guid;
 use tracelogging::Level;
+use tracelogging::OutType;
+#[cfg(feature = "write_hook")]
+use tracelogging::PreWriteHook;
 use tracelogging::ProviderEnableCallback;
+use tracelogging::ProviderEnableState;
+use tracelogging::ProviderEnabledInfo;
+use tracelogging::ProviderMetadata;
+#[cfg(feature = "stats")]
+use tracelogging::ProviderStats;
+use tracelogging::RegisterError;
+use tracelogging::RegisterOptions;
+use tracelogging::StateSource;
+use tracelogging::StateSources;
+use tracelogging::_internal::EventDataDescriptor;
+use tracelogging::_internal::EventDescriptor;
 use tracelogging::_internal::ProviderContext;
+use tracelogging::_internal::SET_INFORMATION_NOT_SUPPORTED;
+use tracelogging::_internal::register_result;
 
+use crate::capture::CapturedEvent;
+use crate::capture::EventCapture;
+#[cfg(feature = "eventlog")]
+use crate::eventlog::EventLogMirror;
+use crate::pair::PairSequence;
+use crate::sampling::EventSampling;
+use crate::sanitizer::FieldSanitizer;
+use crate::session::ProviderSession;
 #[allow(unused_imports)] // For docs
 use crate::EventBuilder;
+#[cfg(feature = "eventlog")]
+use crate::EventLog;
 
 /// Represents a connection for writing dynamic TraceLogging (manifest-free) events to
 /// ETW.
@@ -84,12 +118,23 @@ use crate::EventBuilder;
 pub struct Provider {
     pub(crate) context: ProviderContext,
     pub(crate) meta: Vec<u8>, // provider metadata
+    pub(crate) sampling: EventSampling,
+    pub(crate) capture: EventCapture,
+    #[cfg(feature = "eventlog")]
+    pub(crate) event_log: EventLogMirror,
+    field_sanitizer: AtomicPtr<()>, // null or FieldSanitizer
+    binaryc_supported: AtomicBool,
+    pairs: PairSequence,
     id: Guid,
     callback_fn: Option<ProviderEnableCallback>,
     callback_context: usize,
 }
 
 impl Provider {
+    /// The keyword for state (rundown) events written by a [`StateSource`]:
+    /// `0x0000_8000_0000_0000`. Same as [`tracelogging::Provider::STATE_KEYWORD`].
+    pub const STATE_KEYWORD: u64 = tracelogging::Provider::STATE_KEYWORD;
+
     /// Returns the current thread's thread-local activity id.
     /// (Calls
     /// [EventActivityIdControl](https://docs.microsoft.com/windows/win32/api/evntprov/nf-evntprov-eventactivityidcontrol)
@@ -167,6 +212,21 @@ impl Provider {
         return Guid::from_name(name);
     }
 
+    /// Returns a nonzero 16-bit event id generated from a hash of the specified event
+    /// name. Given the same name, it will always generate the same id. The result can
+    /// be used with [`EventBuilder::id_version`].
+    ///
+    /// This is the same id that `tracelogging::write_event!` uses for events that
+    /// specify the `auto_id(hash)` option, so dynamic and static events with the same
+    /// name get the same id. (Same as `tracelogging::Provider::event_id_from_name`.)
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    /// assert_eq!(tld::Provider::event_id_from_name("MyEvent"), 0x2708);
+    /// ```
+    pub const fn event_id_from_name(event_name: &str) -> u16 {
+        return tracelogging::Provider::event_id_from_name(event_name);
+    }
+
     /// Returns a default ProviderOptions.
     pub fn options() -> ProviderOptions {
         return ProviderOptions::new();
@@ -218,24 +278,31 @@ impl Provider {
 
         const GROUP_TRAIT_LEN: u16 = 2 + 1 + 16;
         let name_len = name.len() as u16;
-        let traits_len = if options.group_id.is_some() {
+        let group_len = if options.group_id.is_some() {
             GROUP_TRAIT_LEN
         } else {
             0
         };
-        let meta_len = 2 + name_len + 1 + traits_len;
+        let traits_len = group_len as usize + options.traits.len();
+        assert!(
+            2 + name.len() + 1 + traits_len <= 65535,
+            "provider name + provider traits must be less than 64KB"
+        );
+        let meta_len = 2 + name_len + 1 + traits_len as u16;
         let mut meta = Vec::with_capacity(meta_len as usize);
 
         meta.extend_from_slice(&meta_len.to_le_bytes());
         meta.extend_from_slice(name.as_bytes());
         meta.push(0);
 
-        if traits_len != 0 {
+        if group_len != 0 {
             meta.extend_from_slice(&GROUP_TRAIT_LEN.to_le_bytes());
             meta.push(1); // EtwProviderTraitTypeGroup
             meta.extend_from_slice(&options.group_id.unwrap().to_bytes_le());
         }
 
+        meta.extend_from_slice(&options.traits);
+
         debug_assert_eq!(
             meta.len(),
             meta_len as usize,
@@ -245,6 +312,13 @@ impl Provider {
         return Self {
             context: ProviderContext::new(),
             meta,
+            sampling: EventSampling::new(),
+            capture: EventCapture::new(),
+            #[cfg(feature = "eventlog")]
+            event_log: EventLogMirror::new(),
+            field_sanitizer: AtomicPtr::new(ptr::null_mut()),
+            binaryc_supported: AtomicBool::new(false),
+            pairs: PairSequence::new(),
             id: *id,
             callback_fn: options.callback_fn,
             callback_context: options.callback_context,
@@ -266,13 +340,674 @@ impl Provider {
         return &self.id;
     }
 
+    /// *Advanced:* Returns this provider's encoded metadata bytes (name and traits).
+    pub fn raw_meta(&self) -> &[u8] {
+        return &self.meta;
+    }
+
+    /// Returns this provider's parsed metadata (name, group id, and traits).
+    pub fn metadata(&self) -> ProviderMetadata<'_> {
+        return ProviderMetadata::parse(&self.meta).unwrap();
+    }
+
+    /// Returns the size (in bytes) of this provider's encoded metadata, i.e.
+    /// `raw_meta().len()`. The metadata is allocated with exactly this size when the
+    /// provider is created.
+    pub fn meta_len(&self) -> usize {
+        return self.meta.len();
+    }
+
     /// Returns true if any ETW logging session is listening to this provider for events
     /// with the specified level and keyword.
+    /// If feature `keyword_levels` is enabled, events with levels not allowed by
+    /// `Provider::set_keyword_level` are reported as disabled.
+    ///
+    /// Thread safety: ETW may update the provider's level and keywords at any time.
+    /// `enabled()` always evaluates a consistent level + keyword snapshot, but the
+    /// result may be out-of-date by the time it is used.
     #[inline(always)]
-    pub const fn enabled(&self, level: Level, keyword: u64) -> bool {
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
         return self.context.enabled(level, keyword);
     }
 
+    /// Returns a counter that increments each time an ETW enable callback changes
+    /// this provider's enabled level or keywords (wraps on overflow). Always 0 if
+    /// not Windows/etw.
+    pub fn enable_generation(&self) -> u32 {
+        return self.context.enable_generation();
+    }
+
+    /// Returns the level and keywords with which ETW sessions currently have this
+    /// provider enabled. Always [`ProviderEnableState::DISABLED`] if not Windows/etw.
+    pub fn enable_state(&self) -> ProviderEnableState {
+        return self.context.enable_state();
+    }
+
+    /// Queries ETW for the sessions that currently have this provider enabled in this
+    /// process, reporting each session's logger id, level, and keywords. Returns a
+    /// Win32 error code if the query fails. Always `Err(ERROR_NOT_SUPPORTED)` (50) if
+    /// not Windows/etw. See
+    /// [`tracelogging::Provider::enabled_info`](tracelogging::Provider::enabled_info).
+    pub fn enabled_info(&self) -> Result<ProviderEnabledInfo, u32> {
+        return ProviderContext::enabled_info(&self.id);
+    }
+
+    /// Returns the filter type (e.g. [`EventFilterDescriptor::TYPE_PAYLOAD`]) that
+    /// was passed to the most recent enable callback, or 0 if it had no filter or if
+    /// the provider is disabled. Same as
+    /// [`tracelogging::Provider::enable_filter_type`]. Always 0 if not Windows/etw.
+    pub fn enable_filter_type(&self) -> u32 {
+        return self.context.enable_filter_type();
+    }
+
+    /// Returns true if the most recent enable callback included a payload filter
+    /// ([`EventFilterDescriptor::TYPE_PAYLOAD`]). Same as
+    /// [`tracelogging::Provider::payload_filter_active`]. Always false if not
+    /// Windows/etw.
+    pub fn payload_filter_active(&self) -> bool {
+        return self.enable_filter_type() == EventFilterDescriptor::TYPE_PAYLOAD;
+    }
+
+    /// Sets (or with `None`, removes) a callback that is invoked each time the level or
+    /// keywords with which ETW sessions have enabled this provider change, e.g. when a
+    /// session starts or stops listening. Same as
+    /// [`tracelogging::Provider::on_enable_changed`].
+    ///
+    /// The callback is not invoked for the current state. Call
+    /// [`Provider::enable_state`] after setting the callback to get it. Never invoked
+    /// if not Windows/etw. Callbacks are serialized, so the callback must not
+    /// unregister the provider (see [`ProviderEnableCallback`]).
+    pub fn on_enable_changed(&self, callback: Option<EnableChangedCallback>) {
+        self.context.set_enable_changed_callback(callback);
+    }
+
+    /// Returns a [ProviderSession] that captures this provider's current enabled level
+    /// and keywords, for use when writing many events in a tight loop.
+    pub fn session(&self) -> ProviderSession<'_> {
+        return ProviderSession::new(self);
+    }
+
+    /// Sets the number of times a write to this provider will be retried if ETW reports
+    /// that the session's buffers are full (`ERROR_NOT_ENOUGH_MEMORY`) (requires
+    /// feature `write_retries`). Default is 0 (no retry). Values greater than 3 are
+    /// treated as 3.
+    ///
+    /// During bursty logging, a session's buffers may briefly fill up while ETW
+    /// flushes them. A retry after a short spin often succeeds, reducing dropped
+    /// events at the cost of some extra latency on the writing thread when the
+    /// buffers are full. Writes that fail for other reasons are not retried.
+    ///
+    /// **Retries can duplicate events.** If several sessions are listening to the
+    /// provider, ETW reports the error if any one of them dropped the event, even if
+    /// the others recorded it. A retry writes the event to all of the sessions again,
+    /// so sessions that recorded the first attempt receive the event twice. Only
+    /// enable retries if the provider's events are collected by a single session or
+    /// if the consumers tolerate duplicate events.
+    #[cfg(feature = "write_retries")]
+    pub fn set_write_retries(&self, retries: u8) {
+        self.context.set_write_retries(retries);
+    }
+
+    /// Returns the number of times a write to this provider will be retried if ETW
+    /// reports that the session's buffers are full (requires feature `write_retries`).
+    /// Default is 0.
+    #[cfg(feature = "write_retries")]
+    pub fn write_retries(&self) -> u8 {
+        return self.context.write_retries();
+    }
+
+    /// Sets (or with `None`, removes) the most-verbose level that [`Provider::enabled`]
+    /// will allow for events with any of the bits in `keywords`, in addition to the
+    /// level set by ETW (requires feature `keyword_levels`). Default is `None` (no
+    /// restriction).
+    ///
+    /// ETW sessions enable a provider at a single level for all keywords. Keyword
+    /// levels give finer-grained control without needing multiple providers, e.g.
+    /// allow `Verbose` events with a networking keyword while allowing only
+    /// `Informational` events with other keywords:
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    ///
+    /// const NETWORKING_KEYWORD: u64 = 0x4;
+    /// provider.set_keyword_level(NETWORKING_KEYWORD, Some(tld::Level::Verbose));
+    /// provider.set_default_keyword_level(Some(tld::Level::Informational));
+    /// ```
+    ///
+    /// If an event's keyword has one or more bits with a keyword level, the most-verbose
+    /// of those levels applies. Otherwise (including for keyword 0) the default
+    /// keyword level set by [`Provider::set_default_keyword_level`] applies. Keyword
+    /// levels can only restrict events: they cannot enable events that ETW has not
+    /// enabled.
+    ///
+    /// Keyword levels can be changed at any time, from any thread. If no keyword levels
+    /// are set, the cost to [`Provider::enabled`] is one atomic load. If feature
+    /// `keyword_levels` is not enabled, the provider does not store keyword levels
+    /// and [`Provider::enabled`] only checks the state set by ETW.
+    #[cfg(feature = "keyword_levels")]
+    pub fn set_keyword_level(&self, keywords: u64, level: Option<Level>) {
+        self.context.set_keyword_level(keywords, level);
+    }
+
+    /// Sets (or with `None`, removes) the most-verbose level that [`Provider::enabled`]
+    /// will allow for events whose keyword has no bits with a keyword level. Default
+    /// is `None` (no restriction). See [`Provider::set_keyword_level`].
+    #[cfg(feature = "keyword_levels")]
+    pub fn set_default_keyword_level(&self, level: Option<Level>) {
+        self.context.set_default_keyword_level(level);
+    }
+
+    /// Returns the most-verbose level that [`Provider::enabled`] will allow for events
+    /// with the specified keyword, or `None` if keyword levels do not restrict such
+    /// events. See [`Provider::set_keyword_level`].
+    #[cfg(feature = "keyword_levels")]
+    pub fn keyword_level(&self, keyword: u64) -> Option<Level> {
+        return self.context.keyword_level(keyword);
+    }
+
+    /// Removes all levels set by [`Provider::set_keyword_level`] and
+    /// [`Provider::set_default_keyword_level`].
+    #[cfg(feature = "keyword_levels")]
+    pub fn clear_keyword_levels(&self) {
+        self.context.clear_keyword_levels();
+    }
+
+    /// Sets (or with `None`, removes) a hook that is invoked before each event from
+    /// this provider is sent to ETW (requires feature `write_hook`). The hook receives
+    /// the event's descriptor and the sizes of the event's metadata and data.
+    ///
+    /// This is intended for tests and diagnostics, e.g. counting the events written
+    /// by a component and their sizes without an ETW session or a decoder. The hook
+    /// is invoked only for events that reach the write, i.e. events that are not skipped by sampling.
+    /// The hook can be changed at any time, from any thread.
+    #[cfg(feature = "write_hook")]
+    pub fn set_pre_write_hook(&self, hook: Option<PreWriteHook>) {
+        self.context.set_pre_write_hook(hook);
+    }
+
+    /// Returns the hook set by [`Provider::set_pre_write_hook`], if any (requires
+    /// feature `write_hook`).
+    #[cfg(feature = "write_hook")]
+    pub fn pre_write_hook(&self) -> Option<PreWriteHook> {
+        return self.context.pre_write_hook();
+    }
+
+    /// Sets (or with `None`, removes) an [`EventLog`] bridge that mirrors this
+    /// provider's events to the Windows Event Log (requires feature `eventlog`).
+    ///
+    /// Each event written by [`EventBuilder::write`] (or [`Provider::write_raw`]) that
+    /// is selected by the bridge's level and keyword mask is also reported to the
+    /// event log, with the event's id as the Event Viewer event ID and a message
+    /// formatted by [`EventLog::message`]. Events are reported even if no ETW session
+    /// is listening, but not if they are skipped by sampling or the deny-list. If a
+    /// field sanitizer is set (see [`Provider::set_field_sanitizer`]), the reported
+    /// message contains the sanitized values.
+    ///
+    /// ```no_run
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_event_log(Some(tld::EventLog::new(
+    ///     "MyCompany.MyComponent",
+    ///     tld::Level::Error,
+    ///     !0,
+    /// )));
+    ///
+    /// // Written to ETW and reported to the event log.
+    /// tld::EventBuilder::new()
+    ///     .reset("AccessDenied", tld::Level::Error, 0x1, 0)
+    ///     .add_u32("Error", 5, tld::OutType::Win32Error, 0)
+    ///     .write(&provider, None, None);
+    /// ```
+    ///
+    /// The bridge can be changed at any time, from any thread. If no bridge is set,
+    /// the cost to [`EventBuilder::write`] is one atomic load. Reporting an event
+    /// decodes and formats it and calls `ReportEventW`, so bridges should only select
+    /// rare events such as critical errors.
+    #[cfg(feature = "eventlog")]
+    pub fn set_event_log(&self, event_log: Option<EventLog>) {
+        self.event_log.set(event_log);
+    }
+
+    /// Returns the number of times an event was written to this provider while the
+    /// provider was not registered (requires feature `unregistered_writes`).
+    ///
+    /// [`EventBuilder::write`] on an unregistered provider is a no-op, so this
+    /// counter catches components that log before registering the provider (or after
+    /// unregistering it). With feature `unregistered_writes_panic`, such writes also
+    /// fail a debug assertion. On non-Windows implementations the provider is never
+    /// registered, so every write is counted (but does not fail the assertion).
+    #[cfg(feature = "unregistered_writes")]
+    pub fn unregistered_writes(&self) -> u32 {
+        return self.context.unregistered_writes();
+    }
+
+    /// Returns a snapshot of this provider's write statistics (requires feature
+    /// `stats`). Same as [`tracelogging::Provider::stats`].
+    ///
+    /// [`EventBuilder::write`] does not check [`Provider::enabled`], so events
+    /// written while the provider is not enabled are counted as written (ETW discards
+    /// them). Events skipped by the provider's deny-list or sampling are counted as
+    /// filtered. Each chunk of an event written by [`EventBuilder::write_chunked`] is
+    /// counted separately.
+    #[cfg(feature = "stats")]
+    pub fn stats(&self) -> ProviderStats {
+        return self.context.stats();
+    }
+
+    /// Adds a source of state events to this provider. The source will be invoked each
+    /// time an ETW session sends a capture-state request to this provider
+    /// ([`EventControlCode::CaptureState`](tracelogging::EventControlCode::CaptureState)).
+    /// Same as [`tracelogging::Provider::add_state_source`].
+    ///
+    /// A state source should write its events with [`Provider::STATE_KEYWORD`] and
+    /// opcode [`DC_Start`](tracelogging::Opcode::DC_Start). Sources cannot be removed,
+    /// and each source can be added to only one provider. Returns false (and does
+    /// nothing) if `source` was already added to a provider. Never invoked by ETW if
+    /// not Windows/etw.
+    pub fn add_state_source(&self, source: &'static StateSource) -> bool {
+        return self.context.state_sources().add(source);
+    }
+
+    /// Returns an iterator over the sources that were added by
+    /// [`Provider::add_state_source`], most-recently-added first.
+    pub fn state_sources(&self) -> StateSources {
+        return self.context.state_sources().iter();
+    }
+
+    /// Invokes all of this provider's state sources, i.e. writes the provider's state
+    /// events now (same as when ETW sends a capture-state request). Same as
+    /// [`tracelogging::Provider::capture_state`].
+    pub fn capture_state(&self) {
+        for source in self.state_sources() {
+            source.write_state();
+        }
+    }
+
+    /// Sets the sampling rate for events with the specified name: [EventBuilder::write]
+    /// will send 1 of every `one_in` such events to ETW and will skip the others.
+    ///
+    /// - `one_in == 1` writes every event (removes any sampling for the name).
+    /// - `one_in == 0` skips every event.
+    ///
+    /// Sampling can be changed at any time, from any thread, e.g. to dial down a noisy
+    /// event based on runtime configuration. If the provider has no sampling rates
+    /// configured, the cost to [EventBuilder::write] is one atomic load. Otherwise, the
+    /// write does a hash lookup of the event's id and name, without taking a lock.
+    ///
+    /// Only events that would be delivered (the provider is enabled for the event's
+    /// level and keyword, or capture is active) count towards the rate, so events
+    /// written while no session is listening do not change which events are sent.
+    pub fn set_event_sampling(&self, event_name: &str, one_in: u32) {
+        self.sampling.set_name(event_name.as_bytes(), one_in);
+    }
+
+    /// Sets the sampling rate for events with the specified nonzero event id (as set by
+    /// [EventBuilder::id_version]). Rates set by id take precedence over rates set by
+    /// name. See [Provider::set_event_sampling].
+    pub fn set_event_id_sampling(&self, event_id: u16, one_in: u32) {
+        debug_assert!(event_id != 0, "event_id must be nonzero");
+        self.sampling.set_id(event_id, one_in);
+    }
+
+    /// Returns the sampling rate for events with the specified name (1 if not set).
+    pub fn event_sampling(&self, event_name: &str) -> u32 {
+        return self.sampling.get_name(event_name.as_bytes());
+    }
+
+    /// Returns the sampling rate for events with the specified id (1 if not set).
+    pub fn event_id_sampling(&self, event_id: u16) -> u32 {
+        return self.sampling.get_id(event_id);
+    }
+
+    /// Removes all sampling rates set by [Provider::set_event_sampling] and
+    /// [Provider::set_event_id_sampling].
+    pub fn clear_event_sampling(&self) {
+        self.sampling.clear();
+    }
+
+    /// Sets the provider's event name deny-list, replacing any previous deny-list:
+    /// [EventBuilder::write] will skip events whose name matches an entry.
+    ///
+    /// An entry ending with `'*'` matches every event name that starts with the rest
+    /// of the entry, e.g. `"Net*"` matches `"NetConnect"` and `"NetSend"`. Other
+    /// entries match an event name exactly.
+    ///
+    /// The deny-list can be changed at any time, from any thread, e.g. to suppress a
+    /// buggy or noisy event in production based on runtime configuration while
+    /// keeping the provider's other events. Denied events are skipped before sampling
+    /// is applied. Like sampling, the cost to [EventBuilder::write] is one atomic load
+    /// if the provider has no deny-list entries or sampling rates.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_event_deny_list(["NoisyEvent", "Debug*"]);
+    /// assert!(provider.event_denied("NoisyEvent"));
+    /// assert!(provider.event_denied("DebugDump"));
+    /// assert!(!provider.event_denied("NoisyEvent2"));
+    /// ```
+    pub fn set_event_deny_list<'a>(&self, entries: impl IntoIterator<Item = &'a str>) {
+        self.sampling.set_deny_list(entries);
+    }
+
+    /// Returns true if events with the specified name will be skipped because of the
+    /// deny-list set by [Provider::set_event_deny_list].
+    pub fn event_denied(&self, event_name: &str) -> bool {
+        return self.sampling.is_denied(event_name.as_bytes());
+    }
+
+    /// Removes all entries from the deny-list set by [Provider::set_event_deny_list].
+    pub fn clear_event_deny_list(&self) {
+        self.sampling.clear_deny_list();
+    }
+
+    /// Sets (or with `None`, removes) a function that [EventBuilder::write] will call
+    /// for each string and binary field value of each event before the event is
+    /// written, e.g. to redact or hash personal data in one place. See
+    /// [`FieldSanitizer`].
+    ///
+    /// The sanitizer is applied to a copy of the event's data, so the builder (and any
+    /// values borrowed by a [`BorrowingEventBuilder`](crate::BorrowingEventBuilder))
+    /// are not modified. Captured events (see [Provider::set_capture]) contain the
+    /// sanitized data. Events that are skipped by sampling or the deny-list are not
+    /// sanitized.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// fn redact_email(field_name: &str, _in_type: tld::InType, value: &mut [u8]) {
+    ///     if field_name == "Email" {
+    ///         value.fill(b'*');
+    ///     }
+    /// }
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_field_sanitizer(Some(redact_email));
+    /// provider.set_capture(1);
+    ///
+    /// tld::EventBuilder::new()
+    ///     .reset("SignIn", tld::Level::Verbose, 0x1, 0)
+    ///     .add_str8("Email", "a@b.c", tld::OutType::Default, 0)
+    ///     .write(&provider, None, None);
+    /// assert!(provider.drain_captured()[0].data.ends_with(b"*****"));
+    /// ```
+    ///
+    /// The sanitizer can be changed at any time, from any thread. If no sanitizer is
+    /// set, the cost to [EventBuilder::write] is one atomic load. If a sanitizer is
+    /// set, each write copies the event's data and parses its metadata.
+    pub fn set_field_sanitizer(&self, sanitizer: Option<FieldSanitizer>) {
+        let sanitizer_ptr = match sanitizer {
+            Some(sanitizer) => sanitizer as *mut (),
+            None => ptr::null_mut(),
+        };
+        self.field_sanitizer.store(sanitizer_ptr, Ordering::Release);
+    }
+
+    /// Returns the sanitizer set by [`Provider::set_field_sanitizer`], if any.
+    pub fn field_sanitizer(&self) -> Option<FieldSanitizer> {
+        let sanitizer = self.field_sanitizer.load(Ordering::Acquire);
+        return if sanitizer.is_null() {
+            None
+        } else {
+            // Safety: non-null values are only stored by set_field_sanitizer.
+            Some(unsafe { mem::transmute::<*mut (), FieldSanitizer>(sanitizer) })
+        };
+    }
+
+    /// Sets whether the decoders that consume this provider's events support the
+    /// `BinaryC` field encoding. Default is `false`.
+    ///
+    /// This controls the encoding used by [`EventBuilder::add_binary_auto`] and
+    /// [`EventBuilder::add_binary_auto_sequence`]: `BinaryC` if supported, or the older
+    /// `Binary` encoding (which decodes in all versions of Windows) otherwise. Set this
+    /// to `true` if all of the tools that will decode the events are known to support
+    /// `BinaryC`, e.g. TDH on current versions of Windows.
+    pub fn set_binaryc_supported(&self, supported: bool) {
+        self.binaryc_supported.store(supported, Ordering::Relaxed);
+    }
+
+    /// Returns the value set by [`Provider::set_binaryc_supported`].
+    pub fn binaryc_supported(&self) -> bool {
+        return self.binaryc_supported.load(Ordering::Relaxed);
+    }
+
+    /// Enables (or with `capacity == 0`, disables) capture mode: each event written by
+    /// [EventBuilder::write] is also copied into an in-process ring buffer that holds
+    /// up to `capacity` events. When the buffer is full, the oldest event is discarded.
+    ///
+    /// Capture lets unit tests and crash handlers inspect recently-written events
+    /// without an ETW session. Captured events include the descriptor, activity ids,
+    /// metadata, and data, so they can be decoded without the provider. Events are
+    /// captured even if the provider is not registered, but not if they are skipped
+    /// by sampling or the deny-list. Note that code that checks
+    /// [Provider::enabled] before building an event will not write (or capture) the
+    /// event unless a session is listening.
+    ///
+    /// Capture can be changed at any time, from any thread. Disabling capture discards
+    /// any captured events. If capture is disabled, the cost to [EventBuilder::write]
+    /// is one atomic load.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_capture(16);
+    ///
+    /// let mut builder = tld::EventBuilder::new();
+    /// builder.reset("MyEvent", tld::Level::Verbose, 0x1, 0);
+    /// builder.add_u32("Value", 5, tld::OutType::Default, 0);
+    /// builder.write(&provider, None, None);
+    ///
+    /// let events = provider.drain_captured();
+    /// assert_eq!(1, events.len());
+    /// assert_eq!("MyEvent", events[0].name());
+    /// assert_eq!(5u32.to_le_bytes(), events[0].data[..]);
+    /// ```
+    pub fn set_capture(&self, capacity: usize) {
+        self.capture.set_capacity(capacity);
+    }
+
+    /// Returns the capacity set by [Provider::set_capture], or 0 if capture is
+    /// disabled.
+    pub fn capture_capacity(&self) -> usize {
+        return self.capture.capacity();
+    }
+
+    /// Removes and returns the events captured since capture was enabled or since the
+    /// previous call to `drain_captured`, oldest first. Returns an empty `Vec` if
+    /// capture is disabled. See [Provider::set_capture].
+    pub fn drain_captured(&self) -> Vec<CapturedEvent> {
+        return self.capture.drain();
+    }
+
+    /// Returns the number of captured events that were discarded because the capture
+    /// buffer was full. Reset when capture is disabled.
+    pub fn captured_dropped(&self) -> u64 {
+        return self.capture.dropped();
+    }
+
+    /// *Advanced:* Writes an event with pre-encoded TraceLogging metadata and data,
+    /// e.g. to relog a [`CapturedEvent`] or an event captured from another process or
+    /// file under this provider, without going through [`EventBuilder`].
+    ///
+    /// - `event_meta` is the event's encoded metadata: u16 size (must equal
+    ///   `event_meta.len()`), tag, NUL-terminated event name, then field definitions.
+    /// - `data` is the event's encoded field values, as one or more blocks (e.g. the
+    ///   values of each field). The blocks are sent to ETW without being copied.
+    ///
+    /// The event is sent with this provider's metadata, so it is decoded as an event
+    /// from this provider. The metadata and data are not validated beyond the metadata
+    /// size. The event is captured if capture is enabled (see
+    /// [`Provider::set_capture`]) but is not subject to sampling or the deny-list.
+    ///
+    /// Returns 0 for success or a Win32 error code for failure:
+    /// `ERROR_INVALID_PARAMETER` (87) if the metadata size is wrong, otherwise the
+    /// result of `EventWriteTransfer`. The return value is for diagnostic purposes only
+    /// and should generally be ignored in retail builds.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let source = tld::Provider::new("MyCompany.Source", &tld::Provider::options());
+    /// let target = tld::Provider::new("MyCompany.Relog", &tld::Provider::options());
+    /// source.set_capture(16);
+    /// target.set_capture(16);
+    ///
+    /// tld::EventBuilder::new()
+    ///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+    ///     .add_u32("Value", 5, tld::OutType::Default, 0)
+    ///     .write(&source, None, None);
+    ///
+    /// for event in source.drain_captured() {
+    ///     target.write_raw(&event.descriptor, &event.meta, &[&event.data], None, None);
+    /// }
+    /// assert_eq!("MyEvent", target.drain_captured()[0].name());
+    /// ```
+    pub fn write_raw(
+        &self,
+        descriptor: &EventDescriptor,
+        event_meta: &[u8],
+        data: &[&[u8]],
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        if event_meta.len() < 3
+            || event_meta.len() != u16::from_le_bytes([event_meta[0], event_meta[1]]) as usize
+        {
+            return 87; // ERROR_INVALID_PARAMETER
+        }
+
+        #[cfg(feature = "eventlog")]
+        if self.event_log.is_active() {
+            self.event_log
+                .report(self.name(), descriptor, event_meta, || data.concat());
+        }
+
+        if self.capture.is_active() {
+            self.capture.push(CapturedEvent {
+                descriptor: *descriptor,
+                activity_id: activity_id.copied(),
+                related_id: related_id.copied(),
+                meta: event_meta.to_vec(),
+                data: data.concat(),
+            });
+        }
+
+        let mut dd = Vec::with_capacity(2 + data.len());
+        dd.push(EventDataDescriptor::from_raw_bytes(&self.meta, 2)); // EVENT_DATA_DESCRIPTOR_TYPE_PROVIDER_METADATA
+        dd.push(EventDataDescriptor::from_raw_bytes(event_meta, 1)); // EVENT_DATA_DESCRIPTOR_TYPE_EVENT_METADATA
+        dd.extend(
+            data.iter()
+                .map(|block| EventDataDescriptor::from_raw_bytes(block, 0)),
+        );
+        return self.context.write_transfer(
+            descriptor,
+            activity_id.map(|g| g.as_bytes_raw()),
+            related_id.map(|g| g.as_bytes_raw()),
+            &dd,
+        );
+    }
+
+    /// Writes two related events (e.g. a request and its response) as an ordered
+    /// pair: both events use `activity_id` (and `related_id`, if any), and each event
+    /// gets a `u64` field named `Sequence` with consecutive provider-wide pair
+    /// sequence numbers (an even number `n` for `first` and `n + 1` for `second`).
+    ///
+    /// The sequence numbers are reserved and both events are written while holding a
+    /// per-provider lock, so the events of pairs written concurrently by different
+    /// threads are not interleaved. (Events written by other means, e.g.
+    /// [`EventBuilder::write`], may still appear between the events of a pair.)
+    /// Consumers can match the events of a pair by activity id and sequence number.
+    ///
+    /// `first` and `second` must each contain an event, i.e. [`EventBuilder::reset`]
+    /// must have been called. The `Sequence` field is added to the end of each event.
+    /// The deny-list and sampling (see [`Provider::set_event_sampling`]) are applied
+    /// to `first`; if `first` is skipped, neither event is written and no sequence
+    /// numbers are used.
+    ///
+    /// Returns 0 for success or a Win32 error code for failure: the result of writing
+    /// `first` if it failed, otherwise the result of writing `second`. The return
+    /// value is for diagnostic purposes only and should generally be ignored in retail
+    /// builds.
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options());
+    /// provider.set_capture(16);
+    ///
+    /// let activity_id = tld::Provider::create_activity_id();
+    /// let mut request = tld::EventBuilder::new();
+    /// request
+    ///     .reset("Request", tld::Level::Informational, 0x1, 0)
+    ///     .add_str8("Url", "/index.html", tld::OutType::Default, 0);
+    /// let mut response = tld::EventBuilder::new();
+    /// response
+    ///     .reset("Response", tld::Level::Informational, 0x1, 0)
+    ///     .add_u32("Status", 200, tld::OutType::Default, 0);
+    /// provider.write_pair(&mut request, &mut response, &activity_id, None);
+    ///
+    /// let events = provider.drain_captured();
+    /// assert_eq!("Request", events[0].name());
+    /// assert_eq!("Response", events[1].name());
+    /// assert_eq!(Some(activity_id), events[1].activity_id);
+    /// ```
+    pub fn write_pair(
+        &self,
+        first: &mut EventBuilder,
+        second: &mut EventBuilder,
+        activity_id: &Guid,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        if !first.should_write(self) {
+            return 0; // Skipped by deny-list or sampling.
+        }
+
+        return self.pairs.with_next_pair(|sequence| {
+            let result = first
+                .add_u64("Sequence", sequence, OutType::Default, 0)
+                .write_impl(self, false, Some(activity_id), related_id, &[]);
+            let result2 = second
+                .add_u64("Sequence", sequence.wrapping_add(1), OutType::Default, 0)
+                .write_impl(self, false, Some(activity_id), related_id, &[]);
+            return if result != 0 { result } else { result2 };
+        });
+    }
+
+    /// Writes rundown events describing the current process and its loaded modules
+    /// (requires feature `std`), so that a trace collected without the kernel's
+    /// process and image-load events still has enough context to identify the
+    /// process and symbolize addresses (e.g. stack traces or code pointers).
+    ///
+    /// Does nothing and returns 0 unless the provider is enabled for the specified
+    /// level and keyword (or [capture](Provider::set_capture) is enabled). Otherwise
+    /// writes the following events with the specified level and keyword and opcode
+    /// [`DC_Start`](crate::Opcode::DC_Start):
+    ///
+    /// - One `ProcessRundown` event with fields `ProcessId` (U32), `ParentProcessId`
+    ///   (U32), `SessionId` (U32), `PebAddress` (HexSize), `PointerSize` (U8),
+    ///   `ImagePath` (Str8), and `CommandLine` (Str8). The `ParentProcessId`,
+    ///   `SessionId`, and `PebAddress` fields are 0 if not Windows/etw.
+    /// - One `ModuleRundown` event for each loaded module (EXE or DLL), with fields
+    ///   `ImagePath` (Str8), `ImageBase` (HexSize), `ImageSize` (Hex32), and
+    ///   `TimeDateStamp` (Hex32, from the module's PE header). `ImageSize` and
+    ///   `TimeDateStamp` identify the module's binary on a symbol server. Module
+    ///   events are only written on Windows/etw.
+    ///
+    /// Call this after registering the provider and again whenever a new session may
+    /// need the information, e.g. periodically.
+    ///
+    /// Returns 0 for success or the Win32 error code from the first event that could
+    /// not be written.
+    #[cfg(feature = "std")]
+    pub fn write_rundown(&self, level: Level, keyword: u64) -> u32 {
+        return crate::rundown::write_rundown(self, level, keyword);
+    }
+
     /// If this provider is not registered, does nothing and returns 0.
     /// Otherwise, unregisters the provider.
     ///
@@ -329,17 +1064,130 @@ impl Provider {
     ///   provider is dropped. (This is implied by the rules for `Pin` but repeated here
     ///   for clarity.)
     pub unsafe fn register(self: Pin<&Self>) -> u32 {
-        let result = self
-            .context
-            .register(&self.id, self.callback_fn, self.callback_context);
-        if result == 0 {
-            self.context.set_information(
-                2, // EventProviderSetTraits
-                &self.meta[..],
-            );
+        return register_result(self.try_register());
+    }
+
+    /// Same as [`Provider::register`], but returns a [RegisterError] that indicates
+    /// which step of registration failed.
+    ///
+    /// Registration calls `EventRegister` and then sets the provider's traits (name
+    /// and group) with `EventSetInformation`. If either step fails, the provider is
+    /// left unregistered and may be registered again. A failure to set the traits
+    /// because the operating system does not support provider traits
+    /// (`ERROR_NOT_SUPPORTED`, or `STATUS_NOT_SUPPORTED` in kernel mode) is not an
+    /// error.
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [`Provider::register`].
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`Provider::register`].
+    pub unsafe fn try_register(self: Pin<&Self>) -> Result<(), RegisterError> {
+        return self.register_with_options(&RegisterOptions::new());
+    }
+
+    /// Same as [`Provider::try_register`], but uses the specified [RegisterOptions],
+    /// e.g. to retry registration after a transient failure such as `ERROR_BUSY`.
+    ///
+    /// If `options` specifies an enable callback, it is used instead of the callback
+    /// from [`ProviderOptions::callback`] (if any).
+    ///
+    /// Returns a [RegisterError] that indicates which step of registration failed.
+    /// Use [`RegisterError::code`] to get the Win32 error code.
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [`Provider::register`].
+    ///
+    /// # Safety
+    ///
+    /// - Same as [`Provider::register`].
+    pub unsafe fn register_with_options(
+        self: Pin<&Self>,
+        options: &RegisterOptions,
+    ) -> Result<(), RegisterError> {
+        let (callback_fn, callback_context) = match options.enable_callback() {
+            Some((callback_fn, callback_context)) => (Some(callback_fn), callback_context),
+            None => (self.callback_fn, self.callback_context),
+        };
+        let result = self.context.register_with_retries(
+            &self.id,
+            callback_fn,
+            callback_context,
+            options.busy_retry_limit(),
+        );
+        if result != 0 {
+            return Err(RegisterError::EventRegister(result));
         }
 
-        return result;
+        let result = self.context.set_information(
+            2, // EventProviderSetTraits
+            &self.meta[..],
+        );
+        if result != 0 && result != SET_INFORMATION_NOT_SUPPORTED {
+            self.context.unregister();
+            return Err(RegisterError::SetTraits(result));
+        }
+
+        return Ok(());
+    }
+
+    /// Connects the provider to the ETW registration of a C/C++
+    /// `TraceLoggingHProvider` with the same provider id instead of registering the
+    /// provider, so that code written in both languages can log to the provider without
+    /// registering it twice.
+    ///
+    /// `cpp_provider` is the value of the C/C++ `TraceLoggingHProvider`, which must
+    /// already be registered via `TraceLoggingRegister` or `TraceLoggingRegisterEx`.
+    /// The provider writes events using the C/C++ provider's registration handle and
+    /// receives its enable callbacks (including the callback from
+    /// [`ProviderOptions::callback`], if any) by chaining in front of the C/C++
+    /// provider's enable callback. [Provider::unregister()] (or dropping the provider)
+    /// removes the provider from the chain instead of calling `EventUnregister`. Any
+    /// number of providers may adopt the same C/C++ provider and may be unregistered in
+    /// any order; the C/C++ provider's original callback is restored when the last one
+    /// is unregistered. The C/C++ provider's callback context is never modified.
+    ///
+    /// Returns 0 for success or a Win32 error code for failure:
+    /// `ERROR_INVALID_PARAMETER` (87) if `cpp_provider` is null or has a different
+    /// provider id, `ERROR_INVALID_HANDLE` (6) if `cpp_provider` is not registered, or
+    /// `ERROR_NO_SYSTEM_RESOURCES` (1450) if 8 other C/C++ providers have been adopted
+    /// by this process (each stays counted after its adopters are unregistered) or if
+    /// 8 providers have already adopted `cpp_provider`.
+    /// The return value is for diagnostic purposes only and should generally be ignored
+    /// in retail builds.
+    ///
+    /// Enable callbacks of the providers that adopted the same C/C++ provider are
+    /// serialized with each other, with the C/C++ provider's own callback, and with
+    /// `adopt_registration` for that C/C++ provider. Code that runs during an adopting
+    /// provider's enable callback ([`ProviderOptions::callback`], `on_enable_changed`
+    /// callback, and state sources) may register, unregister, or adopt providers that
+    /// are not connected to the same C/C++ provider. It must not call `adopt_registration` for the same C/C++
+    /// provider or unregister any provider that adopted it (including itself), or it
+    /// will deadlock.
+    ///
+    /// # Preconditions
+    ///
+    /// - Same as [Provider::register()].
+    /// - Must not be called at the same time as the C/C++ code registers or
+    ///   unregisters `cpp_provider`.
+    ///
+    /// # Safety
+    ///
+    /// - Same as [Provider::register()].
+    /// - `cpp_provider` must be null or a valid `TraceLoggingHProvider` that is not
+    ///   freed (e.g. by unloading the DLL that defines it) until every provider that
+    ///   adopted it has been unregistered. The C/C++ code may unregister it first; after
+    ///   that, events written by the adopting providers are dropped.
+    pub unsafe fn adopt_registration(self: Pin<&Self>, cpp_provider: *mut c_void) -> u32 {
+        return self.context.adopt(
+            &self.id,
+            cpp_provider,
+            self.callback_fn,
+            self.callback_context,
+        );
     }
 }
 
@@ -382,6 +1230,20 @@ impl fmt::Debug for Provider {
 /// }
 /// ```
 ///
+/// If your provider needs to include other
+/// [provider traits](https://docs.microsoft.com/windows/win32/etw/provider-traits) in
+/// its metadata, use `add_trait(trait_type, trait_data)`.
+/// ```
+/// # use core::pin::Pin;
+/// # use tracelogging_dynamic as tld;
+/// let provider = Box::pin(tld::Provider::new(
+///     "MyCompany.MyComponent",
+///     tld::Provider::options().add_trait(128, b"VendorData")));
+/// unsafe {
+///     provider.as_ref().register();
+/// }
+/// ```
+///
 /// If your provider needs a custom provider enable callback, use `callback(fn, ctx)`.
 /// ```
 /// # use core::pin::Pin;
@@ -406,9 +1268,10 @@ impl fmt::Debug for Provider {
 ///     provider.as_ref().register();
 /// }
 /// ```
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Default)]
 pub struct ProviderOptions {
     group_id: Option<Guid>,
+    traits: Vec<u8>, // Encoded traits (u16 size, u8 type, data), not including group_id.
     callback_fn: Option<ProviderEnableCallback>,
     callback_context: usize,
 }
@@ -416,10 +1279,12 @@ pub struct ProviderOptions {
 impl ProviderOptions {
     /// Creates default provider options.
     /// - No provider group id.
+    /// - No other provider traits.
     /// - No enable callback function or callback context.
     pub const fn new() -> Self {
         return Self {
             group_id: None,
+            traits: Vec::new(),
             callback_fn: None,
             callback_context: 0,
         };
@@ -430,17 +1295,101 @@ impl ProviderOptions {
     /// that the provider should join.
     ///
     /// Most providers do not join any provider group so this is usually not called.
+    /// If the group id was already set (by `group_id`, `add_trait`, or
+    /// `trait_bytes`), the new value replaces it.
     pub fn group_id(&mut self, value: &Guid) -> &mut Self {
         self.group_id = Some(*value);
         return self;
     }
 
+    /// Adds a
+    /// [provider trait](https://docs.microsoft.com/windows/win32/etw/provider-traits)
+    /// with the specified type and data to the provider's metadata. Traits are
+    /// included in the metadata after the provider group trait (if any), in the order
+    /// they were added.
+    ///
+    /// Most providers do not need custom traits so this is usually not called. Use
+    /// [`ProviderOptions::group_id`] to join a provider group. A trait with type
+    /// [`ProviderMetadata::GROUP_TRAIT_TYPE`] (1) is treated as a call to `group_id`,
+    /// so the provider never has more than one group trait.
+    ///
+    /// Panics if `trait_data.len()` is greater than 65532, or if `trait_type` is 1
+    /// and `trait_data.len()` is not 16.
+    pub fn add_trait(&mut self, trait_type: u8, trait_data: &[u8]) -> &mut Self {
+        assert!(
+            trait_data.len() <= 65535 - 3,
+            "trait_data.len() must be less than 64KB"
+        );
+        if trait_type == ProviderMetadata::GROUP_TRAIT_TYPE {
+            let group_id =
+                <&[u8; 16]>::try_from(trait_data).expect("group trait data must be a 16-byte GUID");
+            return self.group_id(&Guid::from_bytes_le(group_id));
+        }
+
+        let trait_len = 3 + trait_data.len() as u16; // sizeof(size) + sizeof(type) + data
+        self.traits.reserve_exact(trait_len as usize);
+        self.traits.extend_from_slice(&trait_len.to_le_bytes());
+        self.traits.push(trait_type);
+        self.traits.extend_from_slice(trait_data);
+        return self;
+    }
+
+    /// Adds zero or more encoded
+    /// [provider traits](https://docs.microsoft.com/windows/win32/etw/provider-traits)
+    /// to the provider's metadata, as if by calling [`ProviderOptions::add_trait`] for
+    /// each trait. Each trait is a `u16` size (including the size and type), a `u8`
+    /// trait type, and the trait data.
+    ///
+    /// This is the format returned by [`ProviderMetadata::raw_traits`], so it can be
+    /// used to give a dynamic provider the same group and traits as another provider,
+    /// e.g. a provider created by `tracelogging::define_provider!`:
+    ///
+    /// ```
+    /// use tracelogging_dynamic as tld;
+    ///
+    /// let provider = tld::Provider::new(
+    ///     "MyCompany.MyComponent.Dynamic",
+    ///     tld::Provider::options().trait_bytes(
+    ///         b"\x13\x00\x01\x92\x82\x3b\xf7\x10\xf6\xa7\x4f\xba\x62\x70\x83\x53\xd1\x62\xc4\
+    ///           \x07\x00\x80Data"));
+    /// let meta = provider.metadata();
+    /// assert_eq!(
+    ///     Some(tld::Guid::from_u128(&0xf73b8292_f610_4fa7_ba62_708353d162c4)),
+    ///     meta.group_id());
+    /// assert_eq!(b"\x07\x00\x80Data", &meta.raw_traits()[19..]);
+    /// ```
+    ///
+    /// Panics if `traits` is not a sequence of well-formed traits, i.e. if a trait's
+    /// size is less than 3 or extends past the end of `traits`, or if a group trait's
+    /// data is not 16 bytes.
+    pub fn trait_bytes(&mut self, traits: &[u8]) -> &mut Self {
+        let mut pos = 0;
+        while pos < traits.len() {
+            assert!(
+                traits.len() - pos >= 3,
+                "trait_bytes: truncated trait at offset {}",
+                pos
+            );
+            let trait_len = u16::from_le_bytes([traits[pos], traits[pos + 1]]) as usize;
+            assert!(
+                trait_len >= 3 && trait_len <= traits.len() - pos,
+                "trait_bytes: invalid trait size {} at offset {}",
+                trait_len,
+                pos
+            );
+            self.add_trait(traits[pos + 2], &traits[pos + 3..pos + trait_len]);
+            pos += trait_len;
+        }
+        return self;
+    }
+
     /// Sets a custom
     /// [provider enable callback](https://docs.microsoft.com/windows/win32/api/evntprov/nc-evntprov-penablecallback)
     /// and context.
     ///
     /// Most providers do not need a custom provider enable callback so this is usually
-    /// not called.
+    /// not called. If a callback was already set, the new callback and context replace
+    /// it.
     pub fn callback(
         &mut self,
         callback_fn: ProviderEnableCallback,
@@ -460,8 +1409,11 @@ impl fmt::Debug for ProviderOptions {
         };
         return write!(
             f,
-            "ProviderOptions {{ group_id: \"{:?}\", callback_fn: {:?}, callback_context: {:x} }}",
-            self.group_id, callback_ptr, self.callback_context
+            "ProviderOptions {{ group_id: \"{:?}\", traits_len: {}, callback_fn: {:?}, callback_context: {:x} }}",
+            self.group_id,
+            self.traits.len(),
+            callback_ptr,
+            self.callback_context
         );
     }
 }
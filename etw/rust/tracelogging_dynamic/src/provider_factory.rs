@@ -0,0 +1,148 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::fmt;
+use core::pin::Pin;
+use std::sync::Mutex;
+
+use crate::provider::Provider;
+use crate::provider::ProviderOptions;
+
+/// Creates, registers, and caches dynamic [Provider] objects by name, e.g. one
+/// provider per tenant in a multi-tenant host (requires feature `std`).
+///
+/// [`ProviderFactory::get`] returns a handle to the registered provider with the
+/// specified name, creating and registering the provider if it is not already in the
+/// cache. Each provider's id is generated from its name (same as [`Provider::new`]).
+/// All providers use the options that were given to [`ProviderFactory::new`].
+///
+/// The factory holds at most `capacity` providers. A provider is "idle" if the
+/// factory holds the only handle to it. When the factory is full, `get` removes idle
+/// providers to make room. [`ProviderFactory::remove_idle`] can also be called
+/// periodically (e.g. from a timer) to unregister providers that are no longer in use.
+/// A provider is unregistered when it has been removed from the factory and the last
+/// handle to it has been dropped.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let factory = unsafe { tld::ProviderFactory::new(tld::Provider::options(), 100) };
+///
+/// let provider = factory.get("MyCompany.MyService.TenantA").unwrap();
+/// if provider.enabled(tld::Level::Verbose, 0x1) {
+///     tld::EventBuilder::new()
+///         .reset("RequestStart", tld::Level::Verbose, 0x1, 0)
+///         .add_str("Url", "/index.html", 0)
+///         .write(&provider, None, None);
+/// }
+///
+/// drop(provider);
+/// assert_eq!(1, factory.remove_idle()); // Unregisters TenantA's provider.
+/// ```
+pub struct ProviderFactory {
+    options: ProviderOptions,
+    capacity: usize,
+    providers: Mutex<BTreeMap<String, Arc<Provider>>>,
+}
+
+impl ProviderFactory {
+    /// Creates a new factory that will create providers using the specified options
+    /// and will hold at most `capacity` providers.
+    ///
+    /// # Safety
+    ///
+    /// - The factory registers the providers that it creates. If the factory is in a
+    ///   DLL, the factory and all provider handles returned by
+    ///   [`ProviderFactory::get`] **must** be dropped before the DLL unloads. See
+    ///   [`Provider::register`].
+    pub unsafe fn new(options: ProviderOptions, capacity: usize) -> Self {
+        return Self {
+            options,
+            capacity,
+            providers: Mutex::new(BTreeMap::new()),
+        };
+    }
+
+    /// Returns the maximum number of providers that the factory will hold.
+    pub const fn capacity(&self) -> usize {
+        return self.capacity;
+    }
+
+    /// Returns the number of providers currently held by the factory.
+    pub fn len(&self) -> usize {
+        return self.lock().len();
+    }
+
+    /// Returns true if the factory does not hold any providers.
+    pub fn is_empty(&self) -> bool {
+        return self.lock().is_empty();
+    }
+
+    /// Returns a handle to the registered provider with the specified name. If the
+    /// factory does not already hold a provider with that name, creates and registers
+    /// a new provider (removing idle providers first if the factory is full).
+    ///
+    /// Returns None if the factory is full and none of its providers are idle.
+    ///
+    /// Names are compared exactly (case-sensitive), so names that differ only in case
+    /// get separate providers even though they have the same provider id.
+    ///
+    /// Note: `get` should not be called from a provider enable callback (e.g. the
+    /// callback given to [`ProviderOptions::callback`]) because registering a new
+    /// provider can invoke its enable callback while the factory is locked.
+    pub fn get(&self, name: &str) -> Option<Pin<Arc<Provider>>> {
+        let mut providers = self.lock();
+        let provider = if let Some(provider) = providers.get(name) {
+            provider.clone()
+        } else {
+            if providers.len() >= self.capacity {
+                providers.retain(|_, provider| Arc::strong_count(provider) != 1);
+                if providers.len() >= self.capacity {
+                    return None;
+                }
+            }
+
+            let provider = Arc::new(Provider::new(name, &self.options));
+            // Safety: the provider is never moved out of the Arc. The DLL unload
+            // condition is a precondition of ProviderFactory::new.
+            unsafe { Pin::new_unchecked(&*provider).register() };
+            providers.insert(String::from(name), provider.clone());
+            provider
+        };
+
+        // Safety: the provider is never moved out of the Arc.
+        return Some(unsafe { Pin::new_unchecked(provider) });
+    }
+
+    /// Removes the providers that are idle (not referenced by any handle other than the
+    /// factory's), unregistering them. Returns the number of providers removed.
+    pub fn remove_idle(&self) -> usize {
+        let mut providers = self.lock();
+        let old_len = providers.len();
+        providers.retain(|_, provider| Arc::strong_count(provider) != 1);
+        return old_len - providers.len();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Arc<Provider>>> {
+        // The map is always consistent, so a panic in another thread is not a problem.
+        return self
+            .providers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+    }
+}
+
+impl fmt::Debug for ProviderFactory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "ProviderFactory {{ len: {}, capacity: {}, options: {:?} }}",
+            self.len(),
+            self.capacity,
+            self.options
+        );
+    }
+}
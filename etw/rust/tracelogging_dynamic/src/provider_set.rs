@@ -0,0 +1,67 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::vec::Vec;
+
+use crate::provider::Provider;
+use crate::Level;
+
+/// A set of providers that receive the same events, e.g. a public telemetry provider
+/// and a local debugging provider.
+///
+/// Use [`EventBuilder::write_set`](crate::EventBuilder::write_set) to write an event
+/// to each provider in the set. The event is built once; each provider receives the
+/// same event metadata and data, with that provider's own metadata, sampling,
+/// sanitizer, and capture settings.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let options = tld::Provider::options();
+/// let telemetry = tld::Provider::new("MyCompany.MyComponent.Telemetry", &options);
+/// let debug = tld::Provider::new("MyCompany.MyComponent.Debug", &options);
+///
+/// let mut providers = tld::ProviderSet::new();
+/// providers.add(&telemetry).add(&debug);
+///
+/// if providers.enabled(tld::Level::Informational, 0x1) {
+///     tld::EventBuilder::new()
+///         .reset("Started", tld::Level::Informational, 0x1, 0)
+///         .add_u32("Port", 443, tld::OutType::Default, 0)
+///         .write_set(&providers, None, None);
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct ProviderSet<'a> {
+    providers: Vec<&'a Provider>,
+}
+
+impl<'a> ProviderSet<'a> {
+    /// Returns a new empty provider set.
+    pub fn new() -> Self {
+        return Self {
+            providers: Vec::new(),
+        };
+    }
+
+    /// Adds a provider to the set. Events are written to the providers in the order
+    /// they were added.
+    pub fn add(&mut self, provider: &'a Provider) -> &mut Self {
+        self.providers.push(provider);
+        return self;
+    }
+
+    /// Returns the providers in this set, in the order they were added.
+    pub fn providers(&self) -> &[&'a Provider] {
+        return &self.providers;
+    }
+
+    /// Returns true if any provider in this set is enabled for events with the
+    /// specified level and keyword. See [`Provider::enabled`].
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return self
+            .providers
+            .iter()
+            .any(|provider| provider.enabled(level, keyword));
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use std::env;
+use std::process;
+
+use crate::builder::EventBuilder;
+use crate::provider::Provider;
+use crate::Level;
+use crate::Opcode;
+use crate::OutType;
+
+/// Process information that is only available on Windows.
+#[derive(Default)]
+struct ProcessInfo {
+    parent_id: u32,
+    session_id: u32,
+    peb_address: usize,
+}
+
+/// A module (EXE or DLL) loaded in the current process.
+struct ModuleInfo {
+    path: String,
+    base: usize,
+    size: u32,
+    timestamp: u32,
+}
+
+/// Implements [`Provider::write_rundown`].
+pub(crate) fn write_rundown(provider: &Provider, level: Level, keyword: u64) -> u32 {
+    if !provider.enabled(level, keyword) && provider.capture_capacity() == 0 {
+        return 0;
+    }
+
+    let info = process_info();
+    let mut builder = EventBuilder::new();
+    let mut result = builder
+        .reset("ProcessRundown", level, keyword, 0)
+        .opcode(Opcode::DC_Start)
+        .add_u32("ProcessId", process::id(), OutType::Default, 0)
+        .add_u32("ParentProcessId", info.parent_id, OutType::Default, 0)
+        .add_u32("SessionId", info.session_id, OutType::Default, 0)
+        .add_hexsize("PebAddress", info.peb_address, OutType::Default, 0)
+        .add_u8(
+            "PointerSize",
+            core::mem::size_of::<usize>() as u8,
+            OutType::Default,
+            0,
+        )
+        .add_str("ImagePath", image_path(), 0)
+        .add_str("CommandLine", command_line(), 0)
+        .write(provider, None, None);
+
+    for module in modules() {
+        let module_result = builder
+            .reset("ModuleRundown", level, keyword, 0)
+            .opcode(Opcode::DC_Start)
+            .add_str("ImagePath", &module.path, 0)
+            .add_hexsize("ImageBase", module.base, OutType::Default, 0)
+            .add_u32("ImageSize", module.size, OutType::Hex, 0)
+            .add_u32("TimeDateStamp", module.timestamp, OutType::Hex, 0)
+            .write(provider, None, None);
+        if result == 0 {
+            result = module_result;
+        }
+    }
+
+    return result;
+}
+
+fn image_path() -> String {
+    return match env::current_exe() {
+        Ok(path) => path.to_string_lossy().into_owned(),
+        Err(_) => String::new(),
+    };
+}
+
+#[cfg(not(all(windows, feature = "etw")))]
+fn command_line() -> String {
+    return Vec::from_iter(env::args_os().map(|arg| arg.to_string_lossy().into_owned())).join(" ");
+}
+
+#[cfg(all(windows, feature = "etw"))]
+fn command_line() -> String {
+    // Use the raw command line (including quoting) rather than the parsed arguments.
+    return unsafe { string_from_utf16z(GetCommandLineW()) };
+}
+
+#[cfg(not(all(windows, feature = "etw")))]
+fn process_info() -> ProcessInfo {
+    return ProcessInfo::default();
+}
+
+#[cfg(all(windows, feature = "etw"))]
+fn process_info() -> ProcessInfo {
+    let mut info = ProcessInfo::default();
+
+    let mut basic = ProcessBasicInformation::default();
+    let status = unsafe {
+        NtQueryInformationProcess(
+            GetCurrentProcess(),
+            0, // ProcessBasicInformation
+            &mut basic,
+            core::mem::size_of::<ProcessBasicInformation>() as u32,
+            core::ptr::null_mut(),
+        )
+    };
+    if status >= 0 {
+        info.parent_id = basic.inherited_from_unique_process_id as u32;
+        info.peb_address = basic.peb_base_address;
+    }
+
+    unsafe { ProcessIdToSessionId(process::id(), &mut info.session_id) };
+    return info;
+}
+
+#[cfg(not(all(windows, feature = "etw")))]
+fn modules() -> Vec<ModuleInfo> {
+    return Vec::new();
+}
+
+#[cfg(all(windows, feature = "etw"))]
+fn modules() -> Vec<ModuleInfo> {
+    let process = unsafe { GetCurrentProcess() };
+
+    let mut handles: Vec<usize> = alloc::vec![0; 256];
+    loop {
+        let mut needed = 0u32;
+        let ok = unsafe {
+            K32EnumProcessModules(
+                process,
+                handles.as_mut_ptr(),
+                (handles.len() * core::mem::size_of::<usize>()) as u32,
+                &mut needed,
+            )
+        };
+        if ok == 0 {
+            return Vec::new();
+        }
+
+        let count = needed as usize / core::mem::size_of::<usize>();
+        if count <= handles.len() {
+            handles.truncate(count);
+            break;
+        }
+
+        // Leave room for modules that are loaded before the next call.
+        handles.resize(count + 16, 0);
+    }
+
+    let mut modules = Vec::with_capacity(handles.len());
+    for handle in handles {
+        // Hold a reference to the module so that it cannot be unloaded by another
+        // thread while its information and PE header are read.
+        let mut pinned = 0usize;
+        let ok = unsafe {
+            GetModuleHandleExW(
+                GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS,
+                handle as *const u16,
+                &mut pinned,
+            )
+        };
+        if ok == 0 {
+            continue; // Module was unloaded.
+        }
+
+        let mut info = ModuleInformation::default();
+        let ok = unsafe {
+            K32GetModuleInformation(
+                process,
+                pinned,
+                &mut info,
+                core::mem::size_of::<ModuleInformation>() as u32,
+            )
+        };
+        if ok != 0 {
+            modules.push(ModuleInfo {
+                path: module_path(pinned),
+                base: info.base_of_dll,
+                size: info.size_of_image,
+                timestamp: unsafe { image_timestamp(info.base_of_dll) },
+            });
+        }
+
+        unsafe { FreeLibrary(pinned) };
+    }
+
+    return modules;
+}
+
+#[cfg(all(windows, feature = "etw"))]
+fn module_path(handle: usize) -> String {
+    let mut path16: Vec<u16> = Vec::new();
+    let mut capacity = 260;
+    loop {
+        path16.resize(capacity, 0);
+        let len = unsafe { GetModuleFileNameW(handle, path16.as_mut_ptr(), capacity as u32) };
+        if (len as usize) < capacity || capacity >= 32768 {
+            return String::from_utf16_lossy(&path16[..len as usize]);
+        }
+
+        capacity *= 2;
+    }
+}
+
+/// Returns the `TimeDateStamp` from the PE header of the image loaded at `base`.
+///
+/// Safety: `base` must be the base address of a loaded image, and the caller must
+/// hold a reference to the module (e.g. from `GetModuleHandleExW`) so that it cannot
+/// be unloaded during the call.
+#[cfg(all(windows, feature = "etw"))]
+unsafe fn image_timestamp(base: usize) -> u32 {
+    const MZ: u16 = 0x5A4D;
+    const PE: u32 = 0x00004550;
+
+    if core::ptr::read_unaligned(base as *const u16) != MZ {
+        return 0;
+    }
+
+    let nt_headers = base + core::ptr::read_unaligned((base + 0x3C) as *const u32) as usize;
+    if core::ptr::read_unaligned(nt_headers as *const u32) != PE {
+        return 0;
+    }
+
+    // IMAGE_NT_HEADERS.FileHeader.TimeDateStamp
+    return core::ptr::read_unaligned((nt_headers + 8) as *const u32);
+}
+
+#[cfg(all(windows, feature = "etw"))]
+unsafe fn string_from_utf16z(value16: *const u16) -> String {
+    if value16.is_null() {
+        return String::new();
+    }
+
+    let mut len = 0;
+    while *value16.add(len) != 0 {
+        len += 1;
+    }
+
+    return String::from_utf16_lossy(core::slice::from_raw_parts(value16, len));
+}
+
+/// GetModuleHandleExW: `module_name` is an address in the module, not a name.
+#[cfg(all(windows, feature = "etw"))]
+const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS: u32 = 4;
+
+/// PROCESS_BASIC_INFORMATION
+#[cfg(all(windows, feature = "etw"))]
+#[repr(C)]
+#[derive(Default)]
+struct ProcessBasicInformation {
+    exit_status: i32,
+    peb_base_address: usize,
+    affinity_mask: usize,
+    base_priority: i32,
+    unique_process_id: usize,
+    inherited_from_unique_process_id: usize,
+}
+
+/// MODULEINFO
+#[cfg(all(windows, feature = "etw"))]
+#[repr(C)]
+#[derive(Default)]
+struct ModuleInformation {
+    base_of_dll: usize,
+    size_of_image: u32,
+    entry_point: usize,
+}
+
+#[cfg(all(windows, feature = "etw"))]
+extern "system" {
+    fn GetCurrentProcess() -> usize;
+    fn GetCommandLineW() -> *const u16;
+    fn ProcessIdToSessionId(process_id: u32, session_id: &mut u32) -> i32;
+    fn K32EnumProcessModules(
+        process: usize,
+        modules: *mut usize,
+        modules_size: u32,
+        needed: &mut u32,
+    ) -> i32;
+    fn K32GetModuleInformation(
+        process: usize,
+        module: usize,
+        module_info: &mut ModuleInformation,
+        module_info_size: u32,
+    ) -> i32;
+    fn GetModuleFileNameW(module: usize, filename: *mut u16, size: u32) -> u32;
+    fn GetModuleHandleExW(flags: u32, module_name: *const u16, module: &mut usize) -> i32;
+    fn FreeLibrary(module: usize) -> i32;
+}
+
+#[cfg(all(windows, feature = "etw"))]
+#[link(name = "ntdll")]
+extern "system" {
+    fn NtQueryInformationProcess(
+        process: usize,
+        process_information_class: u32,
+        process_information: &mut ProcessBasicInformation,
+        process_information_length: u32,
+        return_length: *mut u32,
+    ) -> i32;
+}
@@ -0,0 +1,415 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::ptr;
+use core::sync::atomic;
+
+use tracelogging::_internal::fnv1a_hash;
+use tracelogging::_internal::SpinLock;
+
+/// Per-event sampling state: write 1 of every `one_in` events.
+struct Sampler {
+    one_in: u32,
+    counter: atomic::AtomicU32,
+}
+
+impl Sampler {
+    fn new(one_in: u32, counter: u32) -> Self {
+        return Self {
+            one_in,
+            counter: atomic::AtomicU32::new(counter),
+        };
+    }
+
+    fn should_write(&self) -> bool {
+        if self.one_in == 0 {
+            return false;
+        }
+
+        let one_in = self.one_in;
+        let previous = self
+            .counter
+            .fetch_update(atomic::Ordering::Relaxed, atomic::Ordering::Relaxed, |n| {
+                return Some(if n + 1 >= one_in { 0 } else { n + 1 });
+            })
+            .unwrap();
+        return previous == 0;
+    }
+}
+
+/// Entry in the table's `names` hash table. A name is in the table if it has a
+/// sampling rate, is on the deny-list, or both.
+struct NameEntry {
+    hash: u32,
+    name: Box<[u8]>,
+    denied: bool,
+    sampler: Option<Sampler>,
+}
+
+struct IdEntry {
+    id: u16,
+    sampler: Sampler,
+}
+
+/// Immutable snapshot of the sampling configuration, used by the write path. Lookups
+/// by name or id are open-addressing hash lookups. Only the samplers' counters change
+/// after the table is published.
+struct SamplingTable {
+    names: Box<[Option<NameEntry>]>,
+    ids: Box<[Option<IdEntry>]>,
+    denied_prefixes: Box<[Box<[u8]>]>,
+}
+
+impl SamplingTable {
+    fn find_name(&self, name: &[u8]) -> Option<&NameEntry> {
+        let hash = fnv1a_hash(name);
+        return find_slot(&self.names, hash, |entry| {
+            return entry.hash == hash && &*entry.name == name;
+        });
+    }
+
+    fn find_id(&self, id: u16) -> Option<&IdEntry> {
+        return find_slot(&self.ids, id_hash(id), |entry| entry.id == id);
+    }
+
+    fn should_write(&self, name: &[u8], id: u16, sample: bool) -> bool {
+        let name_entry = self.find_name(name);
+        if name_entry.map_or(false, |entry| entry.denied)
+            || self
+                .denied_prefixes
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        {
+            return false;
+        } else if !sample {
+            return true;
+        }
+
+        if id != 0 {
+            if let Some(entry) = self.find_id(id) {
+                return entry.sampler.should_write();
+            }
+        }
+
+        return match name_entry.and_then(|entry| entry.sampler.as_ref()) {
+            Some(sampler) => sampler.should_write(),
+            None => true,
+        };
+    }
+}
+
+fn id_hash(id: u16) -> u32 {
+    return fnv1a_hash(&id.to_le_bytes());
+}
+
+/// Returns the first entry in the probe sequence for `hash` that matches, or None if
+/// an empty slot is reached first. `slots.len()` must be 0 or a power of 2, and at
+/// least one slot must be empty.
+fn find_slot<T>(slots: &[Option<T>], hash: u32, matches: impl Fn(&T) -> bool) -> Option<&T> {
+    if slots.is_empty() {
+        return None;
+    }
+
+    let mask = slots.len() - 1;
+    let mut index = hash as usize & mask;
+    loop {
+        match &slots[index] {
+            None => return None,
+            Some(entry) if matches(entry) => return Some(entry),
+            Some(_) => index = (index + 1) & mask,
+        }
+    }
+}
+
+/// Returns a hash table with room for `entries` (at most half full).
+fn new_slots<T>(entries: impl ExactSizeIterator<Item = (u32, T)>) -> Box<[Option<T>]> {
+    if entries.len() == 0 {
+        return Box::new([]);
+    }
+
+    let len = (entries.len() * 2).next_power_of_two();
+    let mut slots = Vec::with_capacity(len);
+    slots.resize_with(len, || None);
+    let mask = len - 1;
+    for (hash, entry) in entries {
+        let mut index = hash as usize & mask;
+        while slots[index].is_some() {
+            index = (index + 1) & mask;
+        }
+        slots[index] = Some(entry);
+    }
+    return slots.into_boxed_slice();
+}
+
+/// The configuration as set by the user. Protected by the `EventSampling` lock.
+#[derive(Default)]
+struct SamplingConfig {
+    by_name: BTreeMap<Vec<u8>, u32>,
+    by_id: BTreeMap<u16, u32>,
+    denied_names: BTreeSet<Vec<u8>>,
+    denied_prefixes: Vec<Vec<u8>>,
+}
+
+impl SamplingConfig {
+    fn is_empty(&self) -> bool {
+        return self.by_name.is_empty()
+            && self.by_id.is_empty()
+            && self.denied_names.is_empty()
+            && self.denied_prefixes.is_empty();
+    }
+
+    fn is_denied(&self, name: &[u8]) -> bool {
+        return self.denied_names.contains(name)
+            || self
+                .denied_prefixes
+                .iter()
+                .any(|prefix| name.starts_with(prefix));
+    }
+
+    /// Builds the table for the current configuration. Samplers whose rate is
+    /// unchanged keep their position from `old`.
+    fn build(&self, old: Option<&SamplingTable>) -> SamplingTable {
+        let counter = |sampler: Option<&Sampler>, one_in: u32| {
+            return match sampler {
+                Some(sampler) if sampler.one_in == one_in => {
+                    sampler.counter.load(atomic::Ordering::Relaxed)
+                }
+                _ => 0,
+            };
+        };
+
+        let mut names: Vec<&[u8]> = self
+            .by_name
+            .keys()
+            .chain(self.denied_names.iter())
+            .map(|name| &name[..])
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+
+        let names = names.into_iter().map(|name| {
+            let hash = fnv1a_hash(name);
+            let sampler = self.by_name.get(name).map(|&one_in| {
+                let old_sampler = old
+                    .and_then(|table| table.find_name(name))
+                    .and_then(|entry| entry.sampler.as_ref());
+                return Sampler::new(one_in, counter(old_sampler, one_in));
+            });
+            let entry = NameEntry {
+                hash,
+                name: name.into(),
+                denied: self.denied_names.contains(name),
+                sampler,
+            };
+            return (hash, entry);
+        });
+
+        let ids = self.by_id.iter().map(|(&id, &one_in)| {
+            let old_sampler = old.and_then(|table| table.find_id(id)).map(|e| &e.sampler);
+            let entry = IdEntry {
+                id,
+                sampler: Sampler::new(one_in, counter(old_sampler, one_in)),
+            };
+            return (id_hash(id), entry);
+        });
+
+        return SamplingTable {
+            names: new_slots(names),
+            ids: new_slots(ids),
+            denied_prefixes: self
+                .denied_prefixes
+                .iter()
+                .map(|prefix| prefix[..].into())
+                .collect(),
+        };
+    }
+}
+
+/// A table that has been replaced but may still be in use by a reader.
+struct RetiredTable(*mut SamplingTable);
+
+// Safety: the table is only freed (by Drop), never accessed through this pointer.
+unsafe impl Send for RetiredTable {}
+
+impl Drop for RetiredTable {
+    fn drop(&mut self) {
+        // Safety: created by Box::into_raw, and no reader can still be using it.
+        drop(unsafe { Box::from_raw(self.0) });
+    }
+}
+
+#[derive(Default)]
+struct SamplingState {
+    config: SamplingConfig,
+    retired: Vec<RetiredTable>,
+}
+
+/// Runtime-configurable map from event name or event id to sampling rate, plus a
+/// deny-list of event names and name prefixes.
+///
+/// The write path does not take a lock. The configuration is compiled into an
+/// immutable `SamplingTable` that is published via an atomic pointer, so a write
+/// costs one relaxed atomic load if the provider has no sampling configured, or two
+/// atomic increments plus a hash lookup otherwise. Configuration changes take a spin
+/// lock, build a new table, and swap it in. A replaced table is freed once no write
+/// is reading a table, i.e. by the first configuration change (or drop) that sees no
+/// writes in progress.
+pub(crate) struct EventSampling {
+    table: atomic::AtomicPtr<SamplingTable>, // Null if nothing is configured.
+    readers: atomic::AtomicUsize,            // Number of writes reading `table`.
+    state: SpinLock<Option<SamplingState>>,
+}
+
+impl EventSampling {
+    pub const fn new() -> Self {
+        return Self {
+            table: atomic::AtomicPtr::new(ptr::null_mut()),
+            readers: atomic::AtomicUsize::new(0),
+            state: SpinLock::new(None),
+        };
+    }
+
+    /// Returns true if any sampling rates or deny-list entries are configured.
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        return !self.table.load(atomic::Ordering::Relaxed).is_null();
+    }
+
+    /// Sets the rate for the named event. `one_in == 1` removes the entry.
+    pub fn set_name(&self, name: &[u8], one_in: u32) {
+        self.update(|config| {
+            if one_in == 1 {
+                config.by_name.remove(name);
+            } else {
+                config.by_name.insert(name.to_vec(), one_in);
+            }
+        });
+    }
+
+    /// Sets the rate for the event id. `one_in == 1` removes the entry.
+    pub fn set_id(&self, id: u16, one_in: u32) {
+        self.update(|config| {
+            if one_in == 1 {
+                config.by_id.remove(&id);
+            } else {
+                config.by_id.insert(id, one_in);
+            }
+        });
+    }
+
+    /// Returns the rate for the named event (1 if not configured).
+    pub fn get_name(&self, name: &[u8]) -> u32 {
+        return self.read(|config| config.by_name.get(name).copied().unwrap_or(1));
+    }
+
+    /// Returns the rate for the event id (1 if not configured).
+    pub fn get_id(&self, id: u16) -> u32 {
+        return self.read(|config| config.by_id.get(&id).copied().unwrap_or(1));
+    }
+
+    /// Replaces the deny-list. Entries ending with `'*'` are prefixes (without the
+    /// `'*'`). Other entries are exact names.
+    pub fn set_deny_list<'a>(&self, entries: impl IntoIterator<Item = &'a str>) {
+        let mut names = BTreeSet::new();
+        let mut prefixes = Vec::new();
+        for entry in entries {
+            match entry.strip_suffix('*') {
+                Some(prefix) => prefixes.push(prefix.as_bytes().to_vec()),
+                None => {
+                    names.insert(entry.as_bytes().to_vec());
+                }
+            }
+        }
+
+        self.update(|config| {
+            config.denied_names = names;
+            config.denied_prefixes = prefixes;
+        });
+    }
+
+    /// Returns true if the named event matches the deny-list.
+    pub fn is_denied(&self, name: &[u8]) -> bool {
+        return self.is_active() && self.read(|config| config.is_denied(name));
+    }
+
+    /// Removes all deny-list entries.
+    pub fn clear_deny_list(&self) {
+        self.update(|config| {
+            config.denied_names.clear();
+            config.denied_prefixes.clear();
+        });
+    }
+
+    /// Removes all sampling rates (the deny-list is not changed).
+    pub fn clear(&self) {
+        self.update(|config| {
+            config.by_name.clear();
+            config.by_id.clear();
+        });
+    }
+
+    /// Returns false if the named event matches the deny-list. Otherwise, if `sample`
+    /// is true, advances the sampler for the event (if any) and returns true if the
+    /// event should be written. Events with a nonzero id are looked up by id first,
+    /// then by name.
+    ///
+    /// `sample` should be false if the event will not be delivered (e.g. the provider
+    /// is not enabled for the event), so that such events do not advance the samplers.
+    pub fn should_write(&self, name: &[u8], id: u16, sample: bool) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+
+        self.readers.fetch_add(1, atomic::Ordering::SeqCst);
+        let table = self.table.load(atomic::Ordering::SeqCst);
+        // Safety: a table is not freed while `readers` is nonzero (see `update`).
+        let result = table.is_null() || unsafe { &*table }.should_write(name, id, sample);
+        self.readers.fetch_sub(1, atomic::Ordering::Release);
+        return result;
+    }
+
+    fn read<R>(&self, f: impl FnOnce(&SamplingConfig) -> R) -> R {
+        let mut guard = self.state.lock();
+        return f(&guard.get_or_insert_with(SamplingState::default).config);
+    }
+
+    fn update(&self, f: impl FnOnce(&mut SamplingConfig)) {
+        let mut guard = self.state.lock();
+        let state = guard.get_or_insert_with(SamplingState::default);
+        f(&mut state.config);
+
+        // Only `update` stores to `table`, and the lock is held, so `old` is current.
+        let old = self.table.load(atomic::Ordering::Relaxed);
+        let new = if state.config.is_empty() {
+            ptr::null_mut()
+        } else {
+            // Safety: `old` is not freed while the lock is held.
+            let table = state.config.build(unsafe { old.as_ref() });
+            Box::into_raw(Box::new(table))
+        };
+        self.table.swap(new, atomic::Ordering::SeqCst);
+        if !old.is_null() {
+            state.retired.push(RetiredTable(old));
+        }
+
+        // A write that increments `readers` after this load sees the new table, so
+        // if there are no readers now, no write can be using a retired table.
+        if self.readers.load(atomic::Ordering::SeqCst) == 0 {
+            state.retired.clear();
+        }
+    }
+}
+
+impl Drop for EventSampling {
+    fn drop(&mut self) {
+        let table = *self.table.get_mut();
+        if !table.is_null() {
+            // Safety: created by Box::into_raw in `update`; no writes are in progress.
+            drop(unsafe { Box::from_raw(table) });
+        }
+    }
+}
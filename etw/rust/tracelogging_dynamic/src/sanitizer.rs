@@ -0,0 +1,187 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::vec::Vec;
+
+use tracelogging::_internal::skip_meta_fields;
+use tracelogging::_internal::MetaField;
+use tracelogging::_internal::MetaReader;
+use tracelogging::InType;
+
+#[allow(unused_imports)] // For docs
+use crate::Provider;
+
+/// Signature for a function that can modify the string and binary field values of
+/// each event before it is written. See [`Provider::set_field_sanitizer`].
+///
+/// - `field_name` is the field's name. For arrays, the function is invoked once for
+///   each element, with the array's name.
+/// - `in_type` is the field's encoding: [`InType::Str8`], [`InType::CStr8`] (UTF-8),
+///   [`InType::Str16`], [`InType::CStr16`] (UTF-16LE), [`InType::Binary`], or
+///   [`InType::BinaryC`].
+/// - `value` is the field's value, excluding any length prefix or NUL terminator.
+///
+/// The value can be modified in place (e.g. masked or replaced with a hash) but its
+/// length cannot be changed. For NUL-terminated strings, do not write NUL values
+/// into the string.
+pub type FieldSanitizer = fn(field_name: &str, in_type: InType, value: &mut [u8]);
+
+/// Invokes `sanitizer` for each string and binary value in `data` (the event's field
+/// values), as described by `meta` (the event's metadata). Stops at the first value
+/// that does not match the metadata, e.g. if the event was built with incorrect raw
+/// fields.
+pub(crate) fn sanitize_fields(meta: &[u8], data: &mut [u8], sanitizer: FieldSanitizer) {
+    let fields = read_fields(meta);
+    let mut walker = DataWalker {
+        fields: &fields,
+        data,
+        pos: 0,
+        sanitizer,
+    };
+
+    let mut index = 0;
+    while index < fields.len() {
+        match walker.walk_field(index) {
+            Some(next) => index = next,
+            None => break,
+        }
+    }
+}
+
+/// Parses the field definitions that follow the event name in `meta`, stopping at
+/// the first malformed definition.
+fn read_fields(meta: &[u8]) -> Vec<MetaField<'_>> {
+    let mut fields = Vec::new();
+    let mut reader = MetaReader::new(meta);
+    let _ = reader.read_event();
+    while !reader.at_end() {
+        match reader.read_field() {
+            Ok(field) => fields.push(field),
+            Err(_) => break,
+        }
+    }
+
+    return fields;
+}
+
+/// Returns true if `value` is a NUL-terminated string value that contains a NUL.
+fn contains_nul(in_type: InType, value: &[u8]) -> bool {
+    return match in_type {
+        InType::CStr8 => value.contains(&0),
+        InType::CStr16 => value.chunks_exact(2).any(|c| c == [0, 0]),
+        _ => false,
+    };
+}
+
+struct DataWalker<'a, 'b> {
+    fields: &'b [MetaField<'a>],
+    data: &'b mut [u8],
+    pos: usize,
+    sanitizer: FieldSanitizer,
+}
+
+impl<'a, 'b> DataWalker<'a, 'b> {
+    /// Walks the values of `fields[index]` (including the members of a struct) and
+    /// returns the index of the next field, or `None` if the data does not match.
+    fn walk_field(&mut self, index: usize) -> Option<usize> {
+        let field = self.fields.get(index)?;
+        let count = match field.flags {
+            0 => 1,
+            InType::ConstantCountFlag => field.constant_count,
+            InType::VariableCountFlag => self.read_u16()?,
+            _ => {
+                // Custom-serialized field: u16 size, then opaque data.
+                let len = self.read_u16()? as usize;
+                self.pos = self
+                    .pos
+                    .checked_add(len)
+                    .filter(|&end| end <= self.data.len())?;
+                return Some(index + 1);
+            }
+        };
+
+        if field.in_type != InType::Struct {
+            for _ in 0..count {
+                self.walk_value(field)?;
+            }
+            return Some(index + 1);
+        }
+
+        let members_end = skip_meta_fields(self.fields, index + 1, field.out_type)?;
+        for _ in 0..count {
+            let mut member = index + 1;
+            while member != members_end {
+                member = self.walk_field(member)?;
+            }
+        }
+        return Some(members_end);
+    }
+
+    /// Walks one value of `field`, invoking the sanitizer if it is a string or binary
+    /// value.
+    fn walk_value(&mut self, field: &MetaField) -> Option<()> {
+        let in_type = field.in_type;
+        let start;
+        let end;
+        let next;
+        if let Some(size) = in_type.fixed_size() {
+            start = self.pos;
+            end = start.checked_add(size)?;
+            next = end;
+        } else if in_type.is_counted() {
+            let len = self.read_u16()? as usize;
+            start = self.pos;
+            end = start + len;
+            next = end;
+        } else if in_type == InType::CStr8 {
+            start = self.pos;
+            end = start + self.data.get(start..)?.iter().position(|&b| b == 0)?;
+            next = end + 1;
+        } else if in_type == InType::CStr16 {
+            start = self.pos;
+            end = start
+                + 2 * self
+                    .data
+                    .get(start..)?
+                    .chunks_exact(2)
+                    .position(|c| c == [0, 0])?;
+            next = end + 2;
+        } else if in_type == InType::Sid {
+            start = self.pos;
+            end = start + 8 + 4 * *self.data.get(start + 1)? as usize;
+            next = end;
+        } else {
+            return None;
+        }
+
+        if end > self.data.len() {
+            return None;
+        }
+
+        if matches!(
+            in_type,
+            InType::Str8
+                | InType::CStr8
+                | InType::Str16
+                | InType::CStr16
+                | InType::Binary
+                | InType::BinaryC
+        ) {
+            let value = &mut self.data[start..end];
+            (self.sanitizer)(field.name, in_type, value);
+            debug_assert!(
+                !contains_nul(in_type, value),
+                "sanitizer must not write NUL into a NUL-terminated string"
+            );
+        }
+
+        self.pos = next;
+        return Some(());
+    }
+
+    fn read_u16(&mut self) -> Option<u16> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(2)?)?;
+        self.pos += 2;
+        return Some(u16::from_le_bytes([bytes[0], bytes[1]]));
+    }
+}
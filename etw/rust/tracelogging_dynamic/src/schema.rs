@@ -0,0 +1,182 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use tracelogging::_internal::MetaReader;
+use tracelogging::InType;
+
+#[allow(unused_imports)] // For docs
+use crate::EventBuilder;
+
+/// The expected name and type of one field of an event, for use with [`Schema`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SchemaField {
+    /// The field's name.
+    pub name: String,
+    /// The field's [InType] (without flags).
+    pub in_type: InType,
+    /// true if the field is a variable-length array (e.g. added by an
+    /// `add_TYPE_sequence` method).
+    pub is_array: bool,
+}
+
+impl fmt::Display for SchemaField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "\"{}\": {}{}",
+            self.name,
+            self.in_type,
+            if self.is_array { "[]" } else { "" }
+        );
+    }
+}
+
+/// The expected list of fields (names and [InType]s) for an event, used with
+/// [`EventBuilder::validate`].
+///
+/// Layers that build events at runtime (e.g. on behalf of a scripting language) may
+/// promise consumers a stable schema for some events. A `Schema` can be used to check
+/// that an event built by an [`EventBuilder`] still matches that schema, e.g. in debug
+/// builds or in a strict mode, so that drift caused by upstream callers is detected.
+///
+/// Only field names, intypes, and array-ness are compared. Outtypes and tags are not
+/// compared. Struct fields are compared as a field of type [InType::Struct] followed
+/// by the struct's member fields.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let mut schema = tld::Schema::new();
+/// schema
+///     .field("Id", tld::InType::U32)
+///     .array_field("Names", tld::InType::Str8);
+///
+/// let mut builder = tld::EventBuilder::new();
+/// builder
+///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///     .add_u32("Id", 5, tld::OutType::Default, 0)
+///     .add_str8_sequence("Names", ["a", "b"], tld::OutType::Default, 0);
+/// assert!(builder.validate(&schema).is_ok());
+///
+/// builder
+///     .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///     .add_u64("Id", 5, tld::OutType::Default, 0);
+/// assert!(builder.validate(&schema).is_err());
+/// ```
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Schema {
+    fields: Vec<SchemaField>,
+}
+
+impl Schema {
+    /// Returns a new schema with no fields.
+    pub const fn new() -> Self {
+        return Self { fields: Vec::new() };
+    }
+
+    /// Returns the fields of this schema.
+    pub fn fields(&self) -> &[SchemaField] {
+        return &self.fields;
+    }
+
+    /// Appends an expected scalar field.
+    pub fn field(&mut self, name: &str, in_type: InType) -> &mut Self {
+        return self.push(name, in_type, false);
+    }
+
+    /// Appends an expected variable-length array field.
+    pub fn array_field(&mut self, name: &str, in_type: InType) -> &mut Self {
+        return self.push(name, in_type, true);
+    }
+
+    /// Appends an expected struct field. The struct's member fields should be
+    /// appended immediately after the struct.
+    pub fn struct_field(&mut self, name: &str) -> &mut Self {
+        return self.push(name, InType::Struct, false);
+    }
+
+    /// Compares the fields in the specified event metadata (as generated by
+    /// EventBuilder) against this schema.
+    pub(crate) fn validate_meta(&self, meta: &[u8]) -> Result<(), SchemaMismatch> {
+        let mut reader = MetaReader::new(meta);
+        let _ = reader.read_event();
+
+        let mut index = 0;
+        loop {
+            let actual = if reader.at_end() {
+                None
+            } else {
+                reader.read_field().ok().map(|field| SchemaField {
+                    name: String::from(field.name),
+                    in_type: field.in_type,
+                    is_array: field.is_array(),
+                })
+            };
+            let expected = self.fields.get(index);
+            match (expected, actual) {
+                (None, None) => return Ok(()),
+                (Some(expected), Some(actual)) if *expected == actual => {}
+                (expected, actual) => {
+                    return Err(SchemaMismatch {
+                        field_index: index,
+                        expected: expected.cloned(),
+                        actual,
+                    })
+                }
+            }
+            index += 1;
+        }
+    }
+
+    fn push(&mut self, name: &str, in_type: InType, is_array: bool) -> &mut Self {
+        self.fields.push(SchemaField {
+            name: String::from(name),
+            in_type,
+            is_array,
+        });
+        return self;
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        for (i, field) in self.fields.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            field.fmt(f)?;
+        }
+        return f.write_str("]");
+    }
+}
+
+/// Returned by [`EventBuilder::validate`] when an event does not match a [`Schema`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SchemaMismatch {
+    /// Index of the first field that does not match.
+    pub field_index: usize,
+    /// The field expected by the schema, or `None` if the event has extra fields.
+    pub expected: Option<SchemaField>,
+    /// The field found in the event, or `None` if the event is missing fields.
+    pub actual: Option<SchemaField>,
+}
+
+impl fmt::Display for SchemaMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "field {}: expected ", self.field_index)?;
+        match &self.expected {
+            Some(field) => field.fmt(f)?,
+            None => f.write_str("end of event")?,
+        }
+        f.write_str(", found ")?;
+        return match &self.actual {
+            Some(field) => field.fmt(f),
+            None => f.write_str("end of event"),
+        };
+    }
+}
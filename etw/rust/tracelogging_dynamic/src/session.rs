@@ -0,0 +1,122 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use core::fmt;
+
+use tracelogging::Guid;
+use tracelogging::Level;
+use tracelogging::_internal::EnableSnapshot;
+
+use crate::builder::EventBuilder;
+use crate::provider::Provider;
+
+/// A snapshot of a [Provider]'s enable state, for writing many events in a tight loop.
+///
+/// Get a session by calling [`Provider::session`]. The session captures the provider's
+/// enabled level and keywords (and whether the provider has any sampling or deny-list
+/// configured) at the time it was created. [`ProviderSession::enabled`] and
+/// [`ProviderSession::write`] then use the captured state instead of re-reading the
+/// provider's shared state for each event.
+///
+/// The captured state becomes stale if ETW changes the provider's level or keywords or
+/// if sampling or a deny-list is configured after the session was created. A session is intended to
+/// be short-lived, e.g. one session per batch of events. Use
+/// [`ProviderSession::is_current`] to check whether the captured state is still valid.
+///
+/// ```
+/// use tracelogging_dynamic as tld;
+///
+/// let provider = Box::pin(tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()));
+/// unsafe { provider.as_ref().register(); }
+///
+/// let session = provider.session();
+/// if session.enabled(tld::Level::Verbose, 0x1) {
+///     let mut builder = tld::EventBuilder::new();
+///     for i in 0..100u32 {
+///         builder
+///             .reset("MyEvent", tld::Level::Verbose, 0x1, 0)
+///             .add_u32("Index", i, tld::OutType::Default, 0);
+///         session.write(&mut builder, None, None);
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct ProviderSession<'a> {
+    provider: &'a Provider,
+    enable: EnableSnapshot,
+    sampling_active: bool,
+}
+
+impl<'a> ProviderSession<'a> {
+    pub(crate) fn new(provider: &'a Provider) -> Self {
+        return Self {
+            provider,
+            enable: provider.context.enable_snapshot(),
+            sampling_active: provider.sampling.is_active(),
+        };
+    }
+
+    /// Returns the provider from which this session was created.
+    pub const fn provider(&self) -> &'a Provider {
+        return self.provider;
+    }
+
+    /// Returns the provider's [`Provider::enable_generation`] at the time this session
+    /// was created.
+    pub const fn generation(&self) -> u32 {
+        return self.enable.generation;
+    }
+
+    /// Returns true if the provider's level and keywords have not changed since this
+    /// session was created.
+    pub fn is_current(&self) -> bool {
+        return self.provider.enable_generation() == self.enable.generation;
+    }
+
+    /// Returns true if the provider was enabled at the specified level and keyword when
+    /// this session was created and the level is allowed by the provider's keyword
+    /// levels (`Provider::set_keyword_level`, feature `keyword_levels`). Unlike
+    /// [`Provider::enabled`], this does not read the provider's ETW enable state.
+    #[inline(always)]
+    pub fn enabled(&self, level: Level, keyword: u64) -> bool {
+        return self.enable.enabled(level, keyword)
+            && self.provider.context.keyword_level_allows(level, keyword);
+    }
+
+    /// Sends the event in `builder` to ETW via this session's provider. Same as
+    /// [`EventBuilder::write`], except that sampling and the deny-list are only checked
+    /// if the provider had sampling or a deny-list configured when this session was
+    /// created.
+    ///
+    /// Returns 0 for success or a Win32 error from `EventWrite` for failure. The return
+    /// value is for diagnostic purposes only and should generally be ignored in retail
+    /// builds.
+    pub fn write(
+        &self,
+        builder: &mut EventBuilder,
+        activity_id: Option<&Guid>,
+        related_id: Option<&Guid>,
+    ) -> u32 {
+        return builder.write_impl(
+            self.provider,
+            self.sampling_active,
+            activity_id,
+            related_id,
+            &[],
+        );
+    }
+}
+
+impl fmt::Debug for ProviderSession<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "ProviderSession {{ provider: \"{}\", generation: {}, level: {}, keyword_any: {:x}, keyword_all: {:x} }}",
+            self.provider.name(),
+            self.enable.generation,
+            self.enable.level,
+            self.enable.keyword_any,
+            self.enable.keyword_all
+        );
+    }
+}
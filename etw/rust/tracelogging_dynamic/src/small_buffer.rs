@@ -0,0 +1,152 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::ops::DerefMut;
+use core::ptr::copy_nonoverlapping;
+
+/// Growable byte buffer that stores up to [`SmallBuffer::INLINE_CAPACITY`] bytes
+/// inline and moves its contents to a `Vec` when it needs more room. Used for the
+/// metadata and data buffers of an [`EventBuilder`](crate::EventBuilder) so that
+/// building and writing a small event does not allocate.
+///
+/// Once spilled to the heap, the buffer stays on the heap (it is not shrunk by
+/// `clear`), matching the behavior of `Vec`.
+#[allow(clippy::large_enum_variant)] // Boxing the inline variant would defeat its purpose.
+pub(crate) enum SmallBuffer {
+    Inline {
+        len: usize,
+        buf: [u8; SmallBuffer::INLINE_CAPACITY],
+    },
+    Heap(Vec<u8>),
+}
+
+impl SmallBuffer {
+    /// Number of bytes that can be stored without allocating.
+    pub const INLINE_CAPACITY: usize = 256;
+
+    /// Returns an empty buffer that can hold at least `capacity` bytes before growing.
+    /// Does not allocate if `capacity <= INLINE_CAPACITY`.
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= Self::INLINE_CAPACITY {
+            return Self::Inline {
+                len: 0,
+                buf: [0; Self::INLINE_CAPACITY],
+            };
+        } else {
+            return Self::Heap(Vec::with_capacity(capacity));
+        }
+    }
+
+    /// Returns a buffer that owns `vec`. Does not copy or allocate.
+    pub fn from_vec(vec: Vec<u8>) -> Self {
+        return Self::Heap(vec);
+    }
+
+    /// Returns the contents as a `Vec`. Allocates if the buffer is inline.
+    pub fn into_vec(self) -> Vec<u8> {
+        return match self {
+            Self::Inline { len, buf } => buf[..len].to_vec(),
+            Self::Heap(vec) => vec,
+        };
+    }
+
+    /// Returns the number of bytes the buffer can hold before growing.
+    pub fn capacity(&self) -> usize {
+        return match self {
+            Self::Inline { .. } => Self::INLINE_CAPACITY,
+            Self::Heap(vec) => vec.capacity(),
+        };
+    }
+
+    /// Removes all bytes. Does not change the capacity.
+    pub fn clear(&mut self) {
+        match self {
+            Self::Inline { len, .. } => *len = 0,
+            Self::Heap(vec) => vec.clear(),
+        }
+    }
+
+    /// Shortens the buffer to `new_len` bytes. No effect if `new_len >= len`.
+    pub fn truncate(&mut self, new_len: usize) {
+        match self {
+            Self::Inline { len, .. } => *len = new_len.min(*len),
+            Self::Heap(vec) => vec.truncate(new_len),
+        }
+    }
+
+    /// Ensures that the buffer can hold at least `additional` more bytes, moving the
+    /// contents to the heap if they no longer fit inline.
+    pub fn reserve(&mut self, additional: usize) {
+        match self {
+            Self::Inline { len, buf } => {
+                let required = len.checked_add(additional).expect("capacity overflow");
+                if required > Self::INLINE_CAPACITY {
+                    let mut vec = Vec::with_capacity(required.max(2 * Self::INLINE_CAPACITY));
+                    vec.extend_from_slice(&buf[..*len]);
+                    *self = Self::Heap(vec);
+                }
+            }
+            Self::Heap(vec) => vec.reserve(additional),
+        }
+    }
+
+    /// Appends a byte.
+    pub fn push(&mut self, value: u8) {
+        self.extend_from_slice(&[value]);
+    }
+
+    /// Appends the bytes of `values`.
+    pub fn extend_from_slice(&mut self, values: &[u8]) {
+        unsafe {
+            self.extend_from_raw(values.as_ptr(), values.len());
+        }
+    }
+
+    /// Appends `count` bytes copied from `src`.
+    ///
+    /// # Safety
+    ///
+    /// `src` must be valid for reads of `count` bytes.
+    pub unsafe fn extend_from_raw(&mut self, src: *const u8, count: usize) {
+        self.reserve(count);
+        match self {
+            Self::Inline { len, buf } => {
+                copy_nonoverlapping(src, buf.as_mut_ptr().add(*len), count);
+                *len += count;
+            }
+            Self::Heap(vec) => {
+                let old_len = vec.len();
+                copy_nonoverlapping(src, vec.as_mut_ptr().add(old_len), count);
+                vec.set_len(old_len + count);
+            }
+        }
+    }
+}
+
+impl Deref for SmallBuffer {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        return match self {
+            Self::Inline { len, buf } => &buf[..*len],
+            Self::Heap(vec) => vec,
+        };
+    }
+}
+
+impl DerefMut for SmallBuffer {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        return match self {
+            Self::Inline { len, buf } => &mut buf[..*len],
+            Self::Heap(vec) => vec,
+        };
+    }
+}
+
+impl fmt::Debug for SmallBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return fmt::Debug::fmt(&**self, f);
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+use alloc::format;
+use alloc::string::ToString;
+use alloc::sync::Arc;
+use core::fmt;
+use core::pin::Pin;
+
+use tracing_core::field;
+use tracing_core::span;
+use tracing_core::Event;
+use tracing_core::Subscriber;
+use tracing_subscriber::layer;
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::builder::EventBuilder;
+use crate::provider::Provider;
+use crate::Guid;
+use crate::Level;
+use crate::Opcode;
+use crate::OutType;
+
+/// A [`tracing_subscriber::Layer`] that writes `tracing` spans and events to a
+/// dynamic [Provider] as TraceLogging events (requires feature `tracing`).
+///
+/// - Each span is an ETW activity. When a span is created, the layer generates a new
+///   activity id (via [`Provider::create_activity_id`]) and writes an
+///   [`ActivityStart`](Opcode::ActivityStart) event named after the span, with the
+///   span's fields, the new activity id, and the parent span's activity id (if any) as
+///   the related id. When the span is closed, the layer writes a matching
+///   [`ActivityStop`](Opcode::ActivityStop) event.
+/// - Each event is written as a TraceLogging event named after the event's metadata
+///   name (e.g. `event src/main.rs:10`), with the event's fields and the activity id
+///   of the event's span. Events outside of any span use the thread's activity id.
+///
+/// The level is mapped from the `tracing` level: `ERROR` →
+/// [`Level::Error`](const@Level::Error), `WARN` → [`Level::Warning`], `INFO` →
+/// [`Level::Informational`], `DEBUG` and `TRACE` → [`Level::Verbose`]. All events use
+/// the layer's keyword.
+///
+/// Fields are added as follows: `i64` → `I64`, `u64` → `U64`, `f64` → `F64`, `bool` →
+/// `Bool32`, `&str` → UTF-8 string, errors → UTF-8 string (`to_string()`), and other
+/// values (including `message`) → UTF-8 string (`Debug` format). Values recorded
+/// after a span is created (`Span::record`) are not written.
+///
+/// Events are only built if the provider is enabled for the event's level and the
+/// layer's keyword (or if the provider is capturing events, see
+/// [`Provider::set_capture`]). The layer does not filter the spans and events seen by
+/// other layers.
+///
+/// ```
+/// use std::sync::Arc;
+/// use tracelogging_dynamic as tld;
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let provider = Arc::pin(tld::Provider::new("MyCompany.MyComponent", &tld::Provider::options()));
+/// unsafe { provider.as_ref().register(); }
+///
+/// let subscriber = tracing_subscriber::registry().with(tld::TracingLayer::new(provider, 0x1));
+/// tracing::subscriber::with_default(subscriber, || {
+///     let _span = tracing::info_span!("Request", url = "/index.html").entered();
+///     tracing::info!(status = 200u64, "Request complete");
+/// });
+/// ```
+pub struct TracingLayer {
+    provider: Pin<Arc<Provider>>,
+    keyword: u64,
+}
+
+impl TracingLayer {
+    /// Returns a layer that writes spans and events to `provider` with the specified
+    /// keyword. The layer does not register the provider.
+    pub fn new(provider: Pin<Arc<Provider>>, keyword: u64) -> Self {
+        return Self { provider, keyword };
+    }
+
+    /// Returns the layer's provider.
+    pub fn provider(&self) -> &Pin<Arc<Provider>> {
+        return &self.provider;
+    }
+
+    /// Returns the layer's keyword.
+    pub const fn keyword(&self) -> u64 {
+        return self.keyword;
+    }
+
+    fn wants(&self, level: Level) -> bool {
+        return self.provider.enabled(level, self.keyword) || self.provider.capture_capacity() != 0;
+    }
+}
+
+/// Returns the ETW level that corresponds to a `tracing` level.
+fn level_from_tracing(level: &tracing_core::Level) -> Level {
+    return match *level {
+        tracing_core::Level::ERROR => Level::Error,
+        tracing_core::Level::WARN => Level::Warning,
+        tracing_core::Level::INFO => Level::Informational,
+        _ => Level::Verbose,
+    };
+}
+
+/// Activity ids for a span, stored in the span's extensions.
+struct SpanActivity {
+    activity_id: Guid,
+    related_id: Option<Guid>,
+}
+
+impl<S> tracing_subscriber::Layer<S> for TracingLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: layer::Context<'_, S>) {
+        let span = match ctx.span(id) {
+            Some(span) => span,
+            None => return,
+        };
+
+        let related_id = span.parent().and_then(|parent| {
+            parent
+                .extensions()
+                .get::<SpanActivity>()
+                .map(|parent_activity| parent_activity.activity_id)
+        });
+        let activity = SpanActivity {
+            activity_id: Provider::create_activity_id(),
+            related_id,
+        };
+
+        let metadata = attrs.metadata();
+        let level = level_from_tracing(metadata.level());
+        if self.wants(level) {
+            let mut builder = EventBuilder::new();
+            builder
+                .reset(metadata.name(), level, self.keyword, 0)
+                .opcode(Opcode::ActivityStart);
+            attrs.record(&mut FieldVisitor(&mut builder));
+            builder.write(
+                &self.provider,
+                Some(&activity.activity_id),
+                activity.related_id.as_ref(),
+            );
+        }
+
+        span.extensions_mut().insert(activity);
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: layer::Context<'_, S>) {
+        let metadata = event.metadata();
+        let level = level_from_tracing(metadata.level());
+        if !self.wants(level) {
+            return;
+        }
+
+        let activity_id = ctx.event_span(event).and_then(|span| {
+            span.extensions()
+                .get::<SpanActivity>()
+                .map(|activity| activity.activity_id)
+        });
+
+        let mut builder = EventBuilder::new();
+        builder.reset(metadata.name(), level, self.keyword, 0);
+        event.record(&mut FieldVisitor(&mut builder));
+        builder.write(&self.provider, activity_id.as_ref(), None);
+    }
+
+    fn on_close(&self, id: span::Id, ctx: layer::Context<'_, S>) {
+        let span = match ctx.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+
+        let metadata = span.metadata();
+        let level = level_from_tracing(metadata.level());
+        if !self.wants(level) {
+            return;
+        }
+
+        let extensions = span.extensions();
+        if let Some(activity) = extensions.get::<SpanActivity>() {
+            EventBuilder::new()
+                .reset(metadata.name(), level, self.keyword, 0)
+                .opcode(Opcode::ActivityStop)
+                .write(&self.provider, Some(&activity.activity_id), None);
+        }
+    }
+}
+
+impl fmt::Debug for TracingLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "TracingLayer {{ provider: \"{}\", keyword: 0x{:X} }}",
+            self.provider.name(),
+            self.keyword
+        );
+    }
+}
+
+/// Adds `tracing` field values to an event.
+struct FieldVisitor<'a>(&'a mut EventBuilder);
+
+impl field::Visit for FieldVisitor<'_> {
+    fn record_f64(&mut self, field: &field::Field, value: f64) {
+        self.0.add_f64(field.name(), value, OutType::Default, 0);
+    }
+
+    fn record_i64(&mut self, field: &field::Field, value: i64) {
+        self.0.add_i64(field.name(), value, OutType::Default, 0);
+    }
+
+    fn record_u64(&mut self, field: &field::Field, value: u64) {
+        self.0.add_u64(field.name(), value, OutType::Default, 0);
+    }
+
+    fn record_bool(&mut self, field: &field::Field, value: bool) {
+        self.0
+            .add_bool32(field.name(), value as i32, OutType::Default, 0);
+    }
+
+    fn record_str(&mut self, field: &field::Field, value: &str) {
+        self.0.add_str(field.name(), value, 0);
+    }
+
+    fn record_error(&mut self, field: &field::Field, value: &(dyn std::error::Error + 'static)) {
+        self.0.add_str(field.name(), value.to_string(), 0);
+    }
+
+    fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+        self.0.add_str(field.name(), format!("{:?}", value), 0);
+    }
+}
@@ -72,57 +72,2059 @@ fn provider() {
     assert_eq!(provider.name(), "MyCompany.MyComponent");
     assert_eq!(provider.id(), &Guid::from_name("MyCompany.MyComponent"));
 
+    let provider = pin!(Provider::new(
+        "Traits",
+        Provider::options()
+            .add_trait(128, b"ab")
+            .group_id(&Guid::zero())
+            .add_trait(2, b""),
+    ));
+    assert_eq!(
+        provider.raw_meta(),
+        b"\x24\x00Traits\x00\
+          \x13\x00\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\
+          \x05\x00\x80ab\
+          \x03\x00\x02"
+    );
+    assert_eq!(provider.meta_len(), 0x24);
+
+    let meta = provider.metadata();
+    assert_eq!("Traits", meta.name());
+    assert_eq!(Some(Guid::zero()), meta.group_id());
+    assert_eq!(
+        vec![
+            ProviderTrait {
+                trait_type: 1,
+                data: &[0; 16]
+            },
+            ProviderTrait {
+                trait_type: 128,
+                data: b"ab"
+            },
+            ProviderTrait {
+                trait_type: 2,
+                data: b""
+            },
+        ],
+        Vec::from_iter(meta.traits())
+    );
+
     let provider = pin!(Provider::new_with_id("Hello", &Provider::options(), &aid1));
     assert_eq!(provider.name(), "Hello");
     assert_eq!(provider.id(), &aid1);
 
-    unsafe { provider.as_ref().register() };
-    assert_eq!(provider.name(), "Hello");
-    assert_eq!(provider.id(), &aid1);
+    unsafe { provider.as_ref().register() };
+    assert_eq!(provider.name(), "Hello");
+    assert_eq!(provider.id(), &aid1);
+
+    provider.unregister();
+
+    let provider = pin!(Provider::new("MyCompany.MyComponent", &Provider::options()));
+    unsafe {
+        provider.as_ref().register();
+    };
+
+    struct HasProvider {
+        provider: Provider,
+    }
+
+    let mut my_pinned_struct = HasProvider {
+        provider: Provider::new(
+            "MyCompany.MyComponent",
+            Provider::options().callback(my_callback, 0xDEADBEEF),
+        ),
+    };
+    let my_pinned_struct = unsafe { Pin::new_unchecked(&mut my_pinned_struct) };
+
+    let provider = unsafe { my_pinned_struct.map_unchecked_mut(|s| &mut s.provider) };
+    unsafe { provider.as_ref().register() };
+
+    _ = provider.enabled(Level::Verbose, 0x123);
+
+    provider.unregister();
+
+    let options = RegisterOptions::new()
+        .callback(my_callback, 0xDEADBEEF)
+        .busy_retries(3);
+    assert_eq!(Ok(()), unsafe {
+        provider.as_ref().register_with_options(&options)
+    });
+    provider.unregister();
+
+    let mut b = EventBuilder::new();
+    let aid = Provider::create_activity_id();
+    let rid = Provider::current_thread_activity_id();
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        Provider::options()
+            .callback(my_callback, 0xDEADBEEF)
+            .group_id(&Guid::from_name("TraceLoggingDynamicTestGroup")),
+    ));
+    unsafe { provider.as_ref().register() };
+    #[cfg(feature = "write_retries")]
+    {
+        assert_eq!(0, provider.write_retries());
+        provider.set_write_retries(1);
+        assert_eq!(1, provider.write_retries());
+    }
+
+    #[cfg(feature = "keyword_levels")]
+    {
+        provider.set_keyword_level(0x2, Some(Level::Error));
+        assert_eq!(Some(Level::Error), provider.keyword_level(0x2));
+        assert!(!provider.enabled(Level::Verbose, 0x2));
+        assert!(!provider.session().enabled(Level::Verbose, 0x2));
+        provider.clear_keyword_levels();
+        assert_eq!(None, provider.keyword_level(0x2));
+    }
+
+    let session = provider.session();
+    assert!(session.is_current());
+    assert_eq!(provider.enable_generation(), session.generation());
+    assert_eq!(
+        provider.enabled(Level::Verbose, 0x1),
+        session.enabled(Level::Verbose, 0x1)
+    );
+    b.reset("SessionEvent", Level::Verbose, 0x1, 0);
+    assert_eq!(0, session.write(&mut b, None, None));
+    b.reset("GroupEvent-Start", Level::Verbose, 0x1, 0)
+        .opcode(Opcode::ActivityStart)
+        .write(&provider, Some(&aid), Some(&rid));
+    b.reset("GroupEvent-Stop", Level::Verbose, 0x1, 0)
+        .opcode(Opcode::ActivityStop)
+        .write(&provider, Some(&aid), None);
+}
+
+#[test]
+fn provider_options_traits() {
+    let group = Guid::from_u128(&0xf73b8292_f610_4fa7_ba62_708353d162c4);
+    let expected = Provider::new(
+        "Traits",
+        Provider::options()
+            .group_id(&group)
+            .add_trait(128, b"ab")
+            .add_trait(2, b""),
+    );
+
+    // A group trait passed to add_trait is the same as group_id.
+    let provider = Provider::new(
+        "Traits",
+        Provider::options()
+            .add_trait(128, b"ab")
+            .add_trait(1, &group.to_bytes_le())
+            .add_trait(2, b""),
+    );
+    assert_eq!(expected.raw_meta(), provider.raw_meta());
+
+    // trait_bytes accepts the output of raw_traits, including the group trait.
+    let provider = Provider::new(
+        "Traits",
+        Provider::options().trait_bytes(expected.metadata().raw_traits()),
+    );
+    assert_eq!(expected.raw_meta(), provider.raw_meta());
+
+    // The last group id wins, and there is only one group trait.
+    let provider = Provider::new(
+        "Traits",
+        Provider::options()
+            .trait_bytes(expected.metadata().raw_traits())
+            .group_id(&Guid::zero()),
+    );
+    let meta = provider.metadata();
+    assert_eq!(Some(Guid::zero()), meta.group_id());
+    assert_eq!(1, meta.traits().filter(|t| t.trait_type == 1).count());
+    assert_eq!(3, meta.traits().count());
+
+    let provider = Provider::new("Traits", Provider::options().trait_bytes(b""));
+    assert_eq!(0, provider.metadata().traits().count());
+}
+
+#[test]
+#[should_panic(expected = "trait_bytes: invalid trait size")]
+fn provider_options_trait_bytes_malformed() {
+    Provider::options().trait_bytes(b"\x05\x00\x80ab\x04\x00\x80");
+}
+
+#[test]
+#[cfg(feature = "write_hook")]
+fn pre_write_hook() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static EVENTS: AtomicUsize = AtomicUsize::new(0);
+    static DATA_LEN: AtomicUsize = AtomicUsize::new(0);
+
+    fn hook(descriptor: &EventDescriptor, meta_len: usize, data_len: usize) {
+        assert_eq!(7, descriptor.id);
+        assert!(meta_len > "HookEvent".len());
+        EVENTS.fetch_add(1, Ordering::Relaxed);
+        DATA_LEN.fetch_add(data_len, Ordering::Relaxed);
+    }
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert!(provider.pre_write_hook().is_none());
+    provider.set_pre_write_hook(Some(hook));
+    assert!(provider.pre_write_hook().is_some());
+
+    let mut b = EventBuilder::new();
+    b.reset("HookEvent", Level::Verbose, 0x1, 0)
+        .id_version(7, 0)
+        .add_u32("Value", 5, OutType::Default, 0)
+        .write(&provider, None, None);
+    b.write(&provider, None, None);
+    assert_eq!(2, EVENTS.load(Ordering::Relaxed));
+    assert_eq!(8, DATA_LEN.load(Ordering::Relaxed));
+
+    provider.set_pre_write_hook(None);
+    b.write(&provider, None, None);
+    assert_eq!(2, EVENTS.load(Ordering::Relaxed));
+}
+
+#[test]
+#[cfg(feature = "unregistered_writes")]
+fn unregistered_writes() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert_eq!(0, provider.unregistered_writes());
+
+    let mut b = EventBuilder::new();
+    b.reset("BeforeRegister", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+    assert_eq!(1, provider.unregistered_writes());
+
+    unsafe { provider.as_ref().register() };
+    b.reset("AfterRegister", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+    let expected = if cfg!(all(windows, feature = "etw")) {
+        1
+    } else {
+        2
+    };
+    assert_eq!(expected, provider.unregistered_writes());
+    provider.unregister();
+}
+
+#[test]
+fn event_sampling() {
+    let provider = Provider::new("TraceLoggingDynamicTest", &Provider::options());
+    let mut b = EventBuilder::new();
+    let mut write = |name: &str, index: u32| {
+        b.reset(name, Level::Verbose, 0x1, 0)
+            .add_u32("Index", index, OutType::Default, 0)
+            .write(&provider, None, None);
+    };
+
+    // The provider is not enabled and capture is off, so the sampler does not advance.
+    provider.set_event_sampling("Sampled", 3);
+    provider.set_event_deny_list(["Denied", "Noisy*"]);
+    write("Sampled", 0);
+    write("Sampled", 1);
+    provider.set_capture(100);
+    for index in 2..8 {
+        write("Sampled", index);
+    }
+
+    // Changing other settings does not reset the sampler.
+    write("Sampled", 8);
+    provider.set_event_id_sampling(9, 2);
+    write("Sampled", 9);
+    write("Sampled", 10);
+    write("Sampled", 11);
+    write("Denied", 12);
+    write("NoisyEvent", 13);
+    write("Other", 14);
+
+    let indexes = Vec::from_iter(
+        provider
+            .drain_captured()
+            .iter()
+            .map(|event| u32::from_le_bytes(event.data[..4].try_into().unwrap())),
+    );
+    assert_eq!([2, 5, 8, 11, 14], indexes[..]);
+
+    // Many entries, including names that share hash slots.
+    let names = Vec::from_iter((0..200).map(|i| format!("Event{}", i)));
+    for name in &names[..100] {
+        provider.set_event_sampling(name, 0);
+    }
+    for (index, name) in names.iter().enumerate() {
+        write(name, index as u32);
+    }
+    assert_eq!(100, provider.drain_captured().len());
+    assert_eq!(0, provider.event_sampling("Event99"));
+    assert_eq!(1, provider.event_sampling("Event100"));
+
+    // Writes are not blocked by concurrent configuration changes.
+    provider.clear_event_sampling();
+    provider.clear_event_deny_list();
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut b = EventBuilder::new();
+                for _ in 0..1000 {
+                    b.reset("Sampled", Level::Verbose, 0x1, 0)
+                        .write(&provider, None, None);
+                }
+            });
+        }
+        for i in 0..1000 {
+            provider.set_event_sampling("Other", 2 + i % 2);
+        }
+    });
+    assert_eq!(100, provider.drain_captured().len());
+}
+
+#[test]
+#[cfg(feature = "stats")]
+fn provider_stats() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert_eq!(ProviderStats::default(), provider.stats());
+
+    unsafe { provider.as_ref().register() };
+    let mut b = EventBuilder::new();
+    b.reset("Written", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+    provider.set_event_deny_list(["Denied"]);
+    b.reset("Denied", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+
+    let stats = provider.stats();
+    assert_eq!(2, stats.attempted);
+    assert_eq!(1, stats.filtered);
+    assert_eq!(stats.written + stats.failed, 1);
+    provider.unregister();
+}
+
+#[test]
+fn state_source() {
+    use std::sync::atomic::AtomicU32;
+    use std::sync::atomic::Ordering;
+
+    static CALLS: AtomicU32 = AtomicU32::new(0);
+    static SOURCE: StateSource = StateSource::new(|| {
+        CALLS.fetch_add(1, Ordering::Relaxed);
+    });
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert_eq!(
+        tracelogging::Provider::STATE_KEYWORD,
+        Provider::STATE_KEYWORD
+    );
+    assert_eq!(0, provider.state_sources().count());
+    assert!(provider.add_state_source(&SOURCE));
+    assert!(!provider.add_state_source(&SOURCE));
+    assert_eq!(1, provider.state_sources().count());
+
+    provider.capture_state();
+    assert_eq!(1, CALLS.load(Ordering::Relaxed));
+}
+
+#[test]
+fn borrowing() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    unsafe { provider.as_ref().register() };
+
+    let payload = vec![0x5Au8; 70000];
+    let name16 = to_utf16("Name16");
+
+    let mut schema = Schema::new();
+    schema
+        .field("Id", InType::U32)
+        .field("Payload", InType::BinaryC)
+        .field("Name8", InType::Str8)
+        .field("Name16", InType::Str16)
+        .field("Str", InType::Str8)
+        .field("Empty", InType::BinaryC);
+
+    let mut b = EventBuilder::new();
+    b.reset("Borrowing", Level::Verbose, 0x1, 0);
+    let mut borrowing = b.borrowing();
+    borrowing.builder().add_u32("Id", 5, OutType::Default, 0);
+    borrowing
+        .add_binaryc("Payload", &payload, OutType::Default, 0)
+        .add_str8("Name8", b"Name8", OutType::Utf8, 0)
+        .add_str16("Name16", &name16, OutType::Default, 0)
+        .add_str("Str", "Str", 0)
+        .add_binaryc("Empty", &[], OutType::Default, 0);
+    assert_eq!(4, borrowing.borrowed_count());
+    assert_eq!(0, borrowing.write(&provider, None, None));
+    assert_eq!(Ok(()), b.validate(&schema));
+
+    // Values beyond MAX_BORROWED are copied.
+    b.reset("Borrowing", Level::Verbose, 0x1, 0);
+    let mut borrowing = b.borrowing();
+    for _ in 0..BorrowingEventBuilder::MAX_BORROWED + 2 {
+        borrowing.add_binaryc("Chunk", &payload[..100], OutType::Default, 0);
+    }
+    assert_eq!(
+        BorrowingEventBuilder::MAX_BORROWED,
+        borrowing.borrowed_count()
+    );
+    assert_eq!(0, borrowing.write(&provider, None, None));
+}
+
+#[test]
+fn capture() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert_eq!(0, provider.capture_capacity());
+
+    let mut b = EventBuilder::new();
+    b.reset("NotCaptured", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+    assert!(provider.drain_captured().is_empty());
+
+    provider.set_capture(2);
+    assert_eq!(2, provider.capture_capacity());
+
+    let aid = Guid::from_name("activity");
+    b.reset("Captured", Level::Warning, 0x3, 0x1234)
+        .id_version(7, 1)
+        .add_u32("Value", 5, OutType::Default, 0)
+        .add_str8("Str", "abc", OutType::Default, 0)
+        .write(&provider, Some(&aid), None);
+
+    // Borrowed values are merged into the captured data.
+    b.reset("Captured", Level::Warning, 0x3, 0x1234)
+        .id_version(7, 1)
+        .add_u32("Value", 5, OutType::Default, 0);
+    b.borrowing()
+        .add_str8("Str", b"abc", OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(2, events.len());
+    assert_eq!("Captured", events[0].name());
+    assert_eq!(7, events[0].descriptor.id);
+    assert_eq!(1, events[0].descriptor.version);
+    assert_eq!(Level::Warning, events[0].descriptor.level);
+    assert_eq!(0x3, events[0].descriptor.keyword);
+    assert_eq!(Some(aid), events[0].activity_id);
+    assert_eq!(None, events[0].related_id);
+    assert_eq!(events[0].meta.len(), events[0].meta[0] as usize);
+    assert_eq!(events[0].data, [5, 0, 0, 0, 3, 0, b'a', b'b', b'c']);
+    assert_eq!(None, events[1].activity_id);
+    assert_eq!(events[0].meta, events[1].meta);
+    assert_eq!(events[0].data, events[1].data);
+    assert!(provider.drain_captured().is_empty());
+
+    // Oldest events are discarded when full.
+    for i in 0..5u16 {
+        b.reset("Ring", Level::Verbose, 0x1, 0)
+            .id_version(i + 1, 0)
+            .write(&provider, None, None);
+    }
+    assert_eq!(3, provider.captured_dropped());
+    let events = provider.drain_captured();
+    assert_eq!(
+        vec![4, 5],
+        Vec::from_iter(events.iter().map(|e| e.descriptor.id))
+    );
+
+    // Skipped events are not captured.
+    provider.set_event_deny_list(["Ring"]);
+    b.write(&provider, None, None);
+    assert!(provider.drain_captured().is_empty());
+
+    provider.set_capture(0);
+    assert_eq!(0, provider.captured_dropped());
+    b.reset("Disabled", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+    assert!(provider.drain_captured().is_empty());
+}
+
+#[test]
+fn field_sanitizer() {
+    fn mask(field_name: &str, in_type: InType, value: &mut [u8]) {
+        if field_name.starts_with("Secret") {
+            match in_type {
+                InType::Str16 | InType::CStr16 => value.fill(0x2A), // "⨪" (U+2A2A)
+                _ => value.fill(b'*'),
+            }
+        }
+    }
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert!(provider.field_sanitizer().is_none());
+    provider.set_field_sanitizer(Some(mask));
+    assert!(provider.field_sanitizer().is_some());
+    provider.set_capture(4);
+
+    let secret = b"hidden".to_vec();
+    let mut b = EventBuilder::new();
+    b.reset("Sanitized", Level::Verbose, 0x1, 0)
+        .add_u16("SecretNumber", 0x4141, OutType::Default, 0)
+        .add_str8("SecretStr8", "abc", OutType::Default, 0)
+        .add_str8("Public", "abc", OutType::Default, 0)
+        .add_cstr8("SecretCStr8", "de", OutType::Default, 0)
+        .add_str16("SecretStr16", [0x41u16], OutType::Default, 0)
+        .add_struct_sequence("Items", 2, 2, 0, |b, i| {
+            b.add_u8("Index", i as u8, OutType::Default, 0)
+                .add_binaryc_sequence("SecretBlobs", [[7u8, 7]], OutType::Default, 0);
+        });
+    b.borrowing()
+        .add_binaryc("SecretBorrowed", &secret, OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(1, events.len());
+    assert_eq!(
+        events[0].data,
+        [
+            0x41, 0x41, // SecretNumber (not a string)
+            3, 0, b'*', b'*', b'*', // SecretStr8
+            3, 0, b'a', b'b', b'c', // Public
+            b'*', b'*', 0, // SecretCStr8
+            2, 0, 0x2A, 0x2A, // SecretStr16
+            2, 0, // Items count
+            0, 1, 0, 2, 0, b'*', b'*', // Items[0]
+            1, 1, 0, 2, 0, b'*', b'*', // Items[1]
+            6, 0, b'*', b'*', b'*', b'*', b'*', b'*', // SecretBorrowed
+        ]
+    );
+
+    // The builder's data and borrowed values are not modified.
+    assert_eq!(b"hidden", &secret[..]);
+    provider.set_field_sanitizer(None);
+    assert!(provider.field_sanitizer().is_none());
+    b.write(&provider, None, None);
+    assert_eq!(b"abc", &provider.drain_captured()[0].data[4..7]);
+    provider.set_field_sanitizer(Some(mask));
+
+    // Chunks contain the sanitized data.
+    b.reset("Chunked", Level::Verbose, 0x1, 0)
+        .add_str8("Secret", vec![b'x'; 70000], OutType::Default, 0)
+        .write_chunked(&provider, None, None);
+    let events = provider.drain_captured();
+    assert_eq!(2, events.len());
+    assert!(!events
+        .iter()
+        .any(|e| e.data.windows(4).any(|w| w == b"xxxx")));
+    assert!(events
+        .iter()
+        .all(|e| e.data.windows(4).any(|w| w == b"****")));
+}
+
+#[test]
+fn add_binary_capped() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(4);
+
+    let bytes = [1u8, 2, 3, 4, 5, 6];
+    let mut b = EventBuilder::new();
+    b.reset("Capped", Level::Verbose, 0x1, 0)
+        .add_binary_capped("Data", bytes, 4, OutType::Default, 0)
+        .write(&provider, None, None);
+    b.reset("Capped", Level::Verbose, 0x1, 0)
+        .add_binary_capped("Data", &bytes[..2], 4, OutType::Default, 0)
+        .write(&provider, None, None);
+
+    // Same metadata as a Binary field followed by a U32 length field.
+    b.reset("Capped", Level::Verbose, 0x1, 0)
+        .add_binary("Data", [1, 2, 3, 4], OutType::Default, 0)
+        .add_u32("DataLength", 6, OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(3, events.len());
+    assert_eq!(events[0].data, [4, 0, 1, 2, 3, 4, 6, 0, 0, 0]);
+    assert_eq!(events[1].data, [2, 0, 1, 2, 2, 0, 0, 0]);
+    assert_eq!(events[2].meta, events[0].meta);
+    assert_eq!(events[2].data, events[0].data);
+}
+
+#[test]
+fn write_ex() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(4);
+
+    let flags = WriteFlags::NoFaulting | WriteFlags::InPrivate;
+    assert_eq!(3, flags.as_int());
+    assert_eq!(WriteFlags::None, WriteFlags::default());
+
+    let mut b = EventBuilder::new();
+    b.reset("Ex", Level::Verbose, 0x1, 0)
+        .add_u32("Value", 5, OutType::Default, 0);
+    assert_eq!(0, b.write_ex(&provider, 0x2, flags, None, None));
+    assert_eq!(0, b.write_ex(&provider, 0, WriteFlags::None, None, None));
+    b.write(&provider, None, None);
+
+    assert_eq!(Ok(()), b.try_write(&provider, None, None));
+
+    // The deny-list applies to write_ex.
+    provider.set_event_deny_list(["Ex"]);
+    assert_eq!(0, b.write_ex(&provider, 0, flags, None, None));
+
+    let events = provider.drain_captured();
+    assert_eq!(4, events.len());
+    assert_eq!(events[0].meta, events[2].meta);
+    assert_eq!(events[0].data, events[2].data);
+    assert_eq!(events[1].data, [5, 0, 0, 0]);
+}
+
+#[test]
+fn write_set() {
+    let provider1 = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    let provider2 = pin!(Provider::new(
+        "TraceLoggingDynamicTest2",
+        &Provider::options()
+    ));
+    provider1.set_capture(4);
+    provider2.set_capture(4);
+
+    let mut providers = ProviderSet::new();
+    providers.add(&provider1).add(&provider2);
+    assert_eq!(2, providers.providers().len());
+    assert_eq!(
+        provider1.enabled(Level::Verbose, 0x1) || provider2.enabled(Level::Verbose, 0x1),
+        providers.enabled(Level::Verbose, 0x1)
+    );
+
+    // The deny-list applies per provider.
+    provider2.set_event_deny_list(["Denied"]);
+
+    let mut b = EventBuilder::new();
+    b.reset("Set", Level::Verbose, 0x1, 0)
+        .add_u32("Value", 5, OutType::Default, 0);
+    assert_eq!(0, b.write_set(&providers, None, None));
+    b.reset("Denied", Level::Verbose, 0x1, 0);
+    assert_eq!(0, b.write_set(&providers, None, None));
+    assert_eq!(0, b.write_set(&ProviderSet::new(), None, None));
+
+    let events1 = provider1.drain_captured();
+    let events2 = provider2.drain_captured();
+    assert_eq!(2, events1.len());
+    assert_eq!(1, events2.len());
+    assert_eq!(events1[0].meta, events2[0].meta);
+    assert_eq!(events1[0].data, events2[0].data);
+    assert_eq!("Denied", events1[1].name());
+}
+
+#[test]
+fn add_fmt() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(4);
+
+    let mut b = EventBuilder::new();
+    b.reset("Fmt", Level::Verbose, 0x1, 0)
+        .add_fmt("Message", format_args!("{} of {}", 3, 10), 0)
+        .write(&provider, None, None);
+
+    // Same encoding as a Str8 + Utf8 field.
+    b.reset("Fmt", Level::Verbose, 0x1, 0)
+        .add_str("Message", "3 of 10", 0)
+        .write(&provider, None, None);
+
+    // Truncated at a character boundary: byte 65535 is in the middle of a "\u{20AC}".
+    let long = "x\u{20AC}".repeat(20000);
+    b.reset("Fmt", Level::Verbose, 0x1, 0)
+        .add_fmt("Message", format_args!("{}", long), 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(3, events.len());
+    assert_eq!(events[0].data, b"\x07\x003 of 10");
+    assert_eq!(events[1].meta, events[0].meta);
+    assert_eq!(events[1].data, events[0].data);
+    assert_eq!(2 + 65533, events[2].data.len());
+    assert_eq!(&events[2].data[..2], 65533u16.to_le_bytes());
+}
+
+#[test]
+fn add_sid_malformed() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+
+    const NULL_SID: [u8; 12] = [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    let valid: &[u8] = &[1, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0, 0xFF];
+    let malformed: [&[u8]; 5] = [
+        &[],                                     // Empty.
+        &[1],                                    // No sub-authority count.
+        &[1, 2, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0],  // Shorter than 8 + 2 * 4.
+        &[2, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0],  // Wrong revision.
+        &[1, 16, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0], // Too many sub-authorities.
+    ];
+
+    let mut b = EventBuilder::new();
+    b.reset("Sid", Level::Verbose, 0x1, 0)
+        .add_sid("Sid", valid, OutType::Default, 0)
+        .write(&provider, None, None);
+    for value in malformed {
+        b.reset("Sid", Level::Verbose, 0x1, 0)
+            .add_sid("Sid", value, OutType::Default, 0)
+            .write(&provider, None, None);
+    }
+    b.reset("Sid", Level::Verbose, 0x1, 0)
+        .add_sid_sequence("Sids", [valid, malformed[2]], OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(7, events.len());
+    assert_eq!(events[0].data, valid[..12]);
+    for event in &events[1..6] {
+        assert_eq!(event.meta, events[0].meta);
+        assert_eq!(event.data, NULL_SID);
+    }
+    assert_eq!(events[6].data[..2], [2, 0]);
+    assert_eq!(events[6].data[2..14], valid[..12]);
+    assert_eq!(events[6].data[14..], NULL_SID);
+
+    // try_add_sid adds the field only if the value is valid.
+    b.reset("Sid", Level::Verbose, 0x1, 0);
+    assert!(b.try_add_sid("Sid", valid, OutType::Default, 0).is_ok());
+    for value in malformed {
+        assert_eq!(
+            87,
+            b.try_add_sid("Sid", value, OutType::Default, 0)
+                .unwrap_err()
+        );
+    }
+    b.write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(1, events.len());
+    assert_eq!(events[0].data, valid[..12]);
+}
+
+#[test]
+fn build_event() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(4);
+
+    let event_name = String::from("BuildEvent");
+    let ports = vec![80u16, 443];
+    let names = ["a", "bc"];
+    let mut b = EventBuilder::new();
+    build_event!(
+        &mut b,
+        &event_name,
+        level(Level::Warning),
+        keyword(0x2),
+        keyword(0x4),
+        opcode(Opcode::Start),
+        tag(0x123),
+        u32("Status", 200u32),
+        u32("Flags", &0x12u32, format(Hex), tag(0x5)),
+        bool8("Ok", true),
+        u16_slice("Ports", &ports),
+        str8("Url", "https://contoso.com/"),
+        str8_slice("Names", names),
+        win_error("Error", 5u32),
+    )
+    .write(&provider, None, None);
+
+    b.reset(&event_name, Level::Warning, 0x6, 0x123)
+        .opcode(Opcode::Start)
+        .add_u32("Status", 200, OutType::Default, 0)
+        .add_u32("Flags", 0x12, OutType::Hex, 0x5)
+        .add_u8("Ok", 1, OutType::Boolean, 0)
+        .add_u16_sequence("Ports", &ports, OutType::Default, 0)
+        .add_str8("Url", "https://contoso.com/", OutType::Utf8, 0)
+        .add_str8_sequence("Names", names, OutType::Utf8, 0)
+        .add_u32("Error", 5, OutType::Win32Error, 0)
+        .write(&provider, None, None);
+
+    // Defaults: Verbose, keyword 0x1, tag 0.
+    build_event!(&mut b, "Defaults").write(&provider, None, None);
+    b.reset("Defaults", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(4, events.len());
+    assert_eq!(events[0].meta, events[1].meta);
+    assert_eq!(events[0].data, events[1].data);
+    assert_eq!(events[0].descriptor.level, Level::Warning);
+    assert_eq!(events[0].descriptor.keyword, 0x6);
+    assert_eq!(events[2].meta, events[3].meta);
+    assert_eq!(events[2].descriptor.level, Level::Verbose);
+    assert_eq!(events[2].descriptor.keyword, 0x1);
+}
+
+#[test]
+fn add_value() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(4);
+
+    let guid = Guid::from_name("AddValue");
+    let name = String::from("Name");
+    let names = vec![String::from("a"), String::from("bc")];
+    let mut b = EventBuilder::new();
+    b.reset("AddValue", Level::Verbose, 0x1, 0)
+        .add("I8", -1i8)
+        .add("U32", 5u32)
+        .add("USize", 6usize)
+        .add("F64", 1.5f64)
+        .add("Bool", true)
+        .add("Str", "Hello")
+        .add("String", &name)
+        .add("Guid", guid)
+        .add("U16s", &[1u16, 2, 3])
+        .add("Bools", &[true, false][..])
+        .add("Strs", &["a", "bc"])
+        .add("Strings", &names)
+        .add("Guids", &vec![guid])
+        .write(&provider, None, None);
+
+    b.reset("AddValue", Level::Verbose, 0x1, 0)
+        .add_i8("I8", -1, OutType::Default, 0)
+        .add_u32("U32", 5, OutType::Default, 0)
+        .add_usize("USize", 6, OutType::Default, 0)
+        .add_f64("F64", 1.5, OutType::Default, 0)
+        .add_u8("Bool", 1, OutType::Boolean, 0)
+        .add_str8("Str", "Hello", OutType::Utf8, 0)
+        .add_str8("String", "Name", OutType::Utf8, 0)
+        .add_guid("Guid", &guid, OutType::Default, 0)
+        .add_u16_sequence("U16s", &[1, 2, 3], OutType::Default, 0)
+        .add_u8_sequence("Bools", &[1, 0], OutType::Boolean, 0)
+        .add_str8_sequence("Strs", ["a", "bc"], OutType::Utf8, 0)
+        .add_str8_sequence("Strings", ["a", "bc"], OutType::Utf8, 0)
+        .add_guid_sequence("Guids", &[guid], OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(2, events.len());
+    assert_eq!(events[0].meta, events[1].meta);
+    assert_eq!(events[0].data, events[1].data);
+}
+
+#[test]
+fn char32() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+
+    let chars = ['a', '\u{1F600}'];
+    let mut b = EventBuilder::new();
+    b.reset("Char32", Level::Verbose, 0x1, 0)
+        .add_char32("Char", 'a', OutType::String, 0)
+        .add_char32_sequence("Chars", &chars, OutType::Hex, 0)
+        .add_str32("Str", chars, 0)
+        .write(&provider, None, None);
+    b.reset("Char32", Level::Verbose, 0x1, 0)
+        .add_u32("Char", 0x61, OutType::String, 0)
+        .add_u32_sequence("Chars", &[0x61, 0x1F600], OutType::Hex, 0)
+        .add_u32_sequence("Str", &[0x61, 0x1F600], OutType::String, 0)
+        .write(&provider, None, None);
+    build_event!(
+        &mut b,
+        "Char32",
+        char32("Char", 'a'),
+        char32_slice("Chars", &chars, format(Hex)),
+        str32("Str", &chars),
+    )
+    .write(&provider, None, None);
+    b.reset("Char32", Level::Verbose, 0x1, 0)
+        .add("Char", 'a')
+        .add_char32_sequence("Chars", &chars, OutType::Hex, 0)
+        .add("Str", &chars)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(4, events.len());
+    for event in &events[1..] {
+        assert_eq!(events[0].meta, event.meta);
+        assert_eq!(events[0].data, event.data);
+    }
+}
+
+#[test]
+fn decode() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+
+    let guid = Guid::from_u128(&0x01234567_89ab_cdef_0123_456789abcdef);
+    let sid = [1u8, 1, 0, 0, 0, 0, 0, 5, 18, 0, 0, 0];
+    let mut b = EventBuilder::new();
+    b.reset("Decode", Level::Verbose, 0x1, 0x123)
+        .add_i32("I32", -5, OutType::Default, 0)
+        .add_u16("Port", 0x5000, OutType::Port, 0)
+        .add_u32("IPv4", 0x0100007F, OutType::IPv4, 0x456)
+        .add_u32("Hex", 0x1A, OutType::Hex, 0)
+        .add_bool32("Bool", 1, OutType::Default, 0)
+        .add_str8("Cp1252", b"\x80a", OutType::Default, 0)
+        .add_str8("Utf8", "\u{20AC}a", OutType::Utf8, 0)
+        .add_cstr16("Str16", to_utf16("hi"), OutType::Default, 0)
+        .add_struct("Struct", 2, 0)
+        .add_binary("Bin", [0xAB, 0x01], OutType::Default, 0)
+        .add_u8_sequence("Chars", b"ok", OutType::String, 0)
+        .add_guid("Guid", &guid, OutType::Default, 0)
+        .add_filetime("Time", 0x19DB1DED53E8000, OutType::Default, 0)
+        .add_sid("Sid", sid, OutType::Default, 0)
+        .write(&provider, None, None);
+    b.reset("Truncated", Level::Verbose, 0x1, 0)
+        .add_str8("Str", "abc", OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(2, events.len());
+
+    let event = events[0].decode().unwrap();
+    assert_eq!("Decode", event.name);
+    assert_eq!(0x123, event.tag);
+    assert_eq!(12, event.fields.len());
+    assert_eq!(Some(&DecodedValue::I32(-5)), event.fields[0].value());
+    assert_eq!(0x456, event.field("IPv4").unwrap().tag);
+    assert_eq!(
+        Some(&DecodedValue::Str("\u{20AC}a".to_string())),
+        event.field("Cp1252").unwrap().value()
+    );
+    assert_eq!(
+        event.field("Cp1252").unwrap().value(),
+        event.field("Utf8").unwrap().value()
+    );
+    assert_eq!(
+        Some(&DecodedValue::Guid(guid)),
+        event.field("Guid").unwrap().value()
+    );
+    match &event.field("Struct").unwrap().value {
+        FieldValue::Struct(members) => {
+            assert_eq!(2, members.len());
+            assert_eq!("Chars", members[1].name);
+            assert_eq!(
+                FieldValue::Array(vec![DecodedValue::U8(b'o'), DecodedValue::U8(b'k')]),
+                members[1].value
+            );
+        }
+        value => panic!("unexpected value {:?}", value),
+    }
+    assert_eq!(
+        "Decode { I32: -5, Port: 80, IPv4: 127.0.0.1, Hex: 0x1A, Bool: true, \
+            Cp1252: \"\u{20AC}a\", Utf8: \"\u{20AC}a\", Str16: \"hi\", \
+            Struct: { Bin: 0xAB01, Chars: ['o', 'k'] }, \
+            Guid: 01234567-89ab-cdef-0123-456789abcdef, \
+            Time: 1970-01-01T00:00:00.0000000Z, Sid: S-1-5-18 }",
+        event.to_string()
+    );
+
+    assert_eq!(
+        Err(DecodeError::Data(2)),
+        DecodedEvent::decode(&events[1].meta, &events[1].data[..4])
+    );
+    assert_eq!(
+        Err(DecodeError::Meta(0)),
+        DecodedEvent::decode(&events[1].meta[..4], &events[1].data)
+    );
+}
+
+#[test]
+fn raw_fields() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+
+    let mut b = EventBuilder::new();
+    b.reset("Raw", Level::Verbose, 0x1, 0x12)
+        .add_str8("Path", "/a", OutType::Utf8, 0)
+        .add_u16_sequence("Ports", &[80u16, 443], OutType::Port, 0)
+        .add_struct_sequence("Items", 2, 2, 0, |b, i| {
+            b.add_u32("Id", i as u32, OutType::Default, 0).add_cstr16(
+                "Name",
+                to_utf16(if i == 0 { "x" } else { "\u{20AC}" }),
+                OutType::Default,
+                0,
+            );
+        })
+        .add_str16("Str16", to_utf16("hi"), OutType::Default, 0)
+        .add_u8("Last", 7, OutType::Default, 0x34)
+        .write(&provider, None, None);
+    b.reset("Truncated", Level::Verbose, 0x1, 0)
+        .add_u32("A", 1, OutType::Default, 0)
+        .add_str8("Str", "abc", OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(2, events.len());
+
+    let mut fields = events[0].raw_fields().unwrap();
+    assert_eq!("Raw", fields.name());
+    assert_eq!(0x12, fields.tag());
+    let path = fields.next().unwrap().unwrap();
+    assert_eq!(Some("/a"), path.value().unwrap().as_str());
+    assert_eq!(b"/a", path.value().unwrap().bytes());
+
+    // Skips Ports and Items without interpreting them.
+    let last = fields.field("Last").unwrap().unwrap();
+    assert_eq!(0x34, last.tag);
+    assert_eq!(&[7], last.value().unwrap().bytes());
+    assert!(fields.next().is_none());
+
+    let fields: Vec<RawField> = events[0]
+        .raw_fields()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(5, fields.len());
+    assert!(fields[1].is_array());
+    assert!(fields[1].value().is_none());
+    let ports: Vec<&[u8]> = fields[1].values().map(|v| v.bytes()).collect();
+    assert_eq!(
+        vec![&80u16.to_le_bytes()[..], &443u16.to_le_bytes()[..]],
+        ports
+    );
+
+    assert!(fields[2].is_struct());
+    assert!(fields[2].members().is_none());
+    let names: Vec<String> = fields[2]
+        .elements()
+        .map(|mut members| {
+            let name = members.field("Name").unwrap().unwrap();
+            name.value().unwrap().utf16_chars().collect()
+        })
+        .collect();
+    assert_eq!(vec!["x".to_string(), "\u{20AC}".to_string()], names);
+
+    let str16 = fields[3].value().unwrap();
+    assert_eq!(None, str16.as_str());
+    assert_eq!(
+        vec![b'h' as u16, b'i' as u16],
+        str16.utf16_units().collect::<Vec<_>>()
+    );
+    if let Some(units) = str16.as_utf16() {
+        assert_eq!(&[b'h' as u16, b'i' as u16], units);
+    }
+
+    let decoded: Vec<DecodedField> = fields.iter().map(|f| f.decode()).collect();
+    assert_eq!(events[0].decode().unwrap().fields, decoded);
+
+    // Errors are reported when the bad field is reached.
+    let mut fields = RawFields::new(&events[1].meta, &events[1].data[..6]).unwrap();
+    assert_eq!(
+        &1u32.to_le_bytes(),
+        fields.next().unwrap().unwrap().raw_data()
+    );
+    assert_eq!(
+        Some(Err(DecodeError::Data(6))),
+        fields.next().map(|f| f.map(|_| ()))
+    );
+    assert!(fields.next().is_none());
+
+    let data = [&events[1].data[..], &[0]].concat();
+    let results: Vec<_> = RawFields::new(&events[1].meta, &data)
+        .unwrap()
+        .map(|f| f.map(|f| f.name))
+        .collect();
+    assert_eq!(vec![Ok("A"), Ok("Str"), Err(DecodeError::Data(9))], results);
+}
+
+#[test]
+fn decode_with_names() {
+    struct Names;
+    impl NameMap for Names {
+        fn field_name(&self, event_name: &str, field_name: &str) -> Option<&str> {
+            match (event_name, field_name) {
+                ("Named", "id") => Some("Id"),
+                ("Named", "m") => Some("Member"),
+                _ => None,
+            }
+        }
+
+        fn event_name(&self, event_name: &str) -> Option<&str> {
+            if event_name == "Named" {
+                Some("Named event")
+            } else {
+                None
+            }
+        }
+    }
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+
+    let mut b = EventBuilder::new();
+    b.reset("Named", Level::Verbose, 0x1, 0)
+        .add_u32("id", 1, OutType::Default, 0)
+        .add_struct_sequence("s", 1, 2, 0, |b, i| {
+            b.add_u8("m", i as u8, OutType::Default, 0);
+        })
+        .write(&provider, None, None);
+    b.reset("Other", Level::Verbose, 0x1, 0)
+        .add_u32("id", 1, OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    let event = events[0].decode_with_names(&Names).unwrap();
+    assert_eq!(
+        "Named event { Id: 1, s: [{ Member: 0 }, { Member: 1 }] }",
+        event.to_string()
+    );
+    assert!(event.field("Id").is_some());
+    assert_eq!(
+        "Other { id: 1 }",
+        events[1].decode_with_names(&Names).unwrap().to_string()
+    );
+
+    let mut names = std::collections::BTreeMap::new();
+    names.insert("id".to_string(), "Identifier".to_string());
+    assert_eq!(
+        "Other { Identifier: 1 }",
+        events[1].decode_with_names(&names).unwrap().to_string()
+    );
+}
+
+#[test]
+fn event_formatter() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+
+    let mut b = EventBuilder::new();
+    b.reset("Fmt", Level::Error, 0x12, 0)
+        .id_version(7, 0)
+        .add_u32("A", 0x1A, OutType::Hex, 0)
+        .add_str8("B", "x y", OutType::Utf8, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    let event = events[0].decode().unwrap();
+    let record = FormatRecord {
+        timestamp: None,
+        provider: "Prov",
+        descriptor: &events[0].descriptor,
+        event: &event,
+    };
+
+    let mut formatter =
+        EventFormatter::new("{{{timestamp}}} {provider}/{event}#{id} {level} {keyword}: {fields}")
+            .unwrap();
+    assert_eq!(
+        "{-} Prov/Fmt#7 Error 0x12: A=0x1A B=\"x y\"",
+        formatter.format(&record)
+    );
+    formatter.field_separator(", ");
+    assert_eq!(
+        "{-} Prov/Fmt#7 Error 0x12: A=0x1A, B=\"x y\"",
+        formatter.format(&record)
+    );
+
+    let formatter = EventFormatter::new("b={field:B} c={field:C}").unwrap();
+    assert_eq!("b=\"x y\" c=", formatter.format(&record));
+
+    assert_eq!(
+        Err(TemplateError { offset: 2 }),
+        EventFormatter::new("a {b}")
+    );
+    assert_eq!(
+        Err(TemplateError { offset: 2 }),
+        EventFormatter::new("a {event")
+    );
+    assert_eq!(Err(TemplateError { offset: 1 }), EventFormatter::new("a}"));
+}
+
+#[test]
+fn binary_auto() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
+    assert!(!provider.binaryc_supported());
+
+    let values = [&[1u8, 2][..], &[3]];
+    let mut b = EventBuilder::new();
+    for supported in [false, true] {
+        provider.set_binaryc_supported(supported);
+        b.reset("BinaryAuto", Level::Verbose, 0x1, 0)
+            .add_binary_auto(&provider, "Scalar", [0xAB], OutType::Default, 0)
+            .add_binary_auto_sequence(&provider, "Array", values, OutType::Default, 0)
+            .write(&provider, None, None);
+    }
+
+    let events = provider.drain_captured();
+    let binary = events[0].decode().unwrap();
+    assert_eq!(InType::Binary, binary.fields[0].in_type);
+    assert_eq!(InType::Struct, binary.fields[1].in_type);
+    assert_eq!(
+        "BinaryAuto { Scalar: 0xAB, Array: { 0: 0x0102, 1: 0x03 } }",
+        binary.to_string()
+    );
+
+    let binaryc = events[1].decode().unwrap();
+    assert_eq!(InType::BinaryC, binaryc.fields[0].in_type);
+    assert_eq!(InType::BinaryC, binaryc.fields[1].in_type);
+    assert_eq!(
+        "BinaryAuto { Scalar: 0xAB, Array: [0x0102, 0x03] }",
+        binaryc.to_string()
+    );
+
+    provider.set_binaryc_supported(false);
+    b.reset("BinaryAuto", Level::Verbose, 0x1, 0)
+        .add_binary_auto_sequence(&provider, "Array", [[0u8]; 200], OutType::Default, 0)
+        .write(&provider, None, None);
+    match &provider.drain_captured()[0].decode().unwrap().fields[0].value {
+        FieldValue::Struct(members) => assert_eq!(127, members.len()),
+        value => panic!("unexpected value {:?}", value),
+    }
+}
 
-    provider.unregister();
+#[test]
+fn raw_custom() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(8);
 
-    let provider = pin!(Provider::new("MyCompany.MyComponent", &Provider::options()));
-    unsafe {
-        provider.as_ref().register();
+    let mut b = EventBuilder::new();
+    b.reset("Custom", Level::Verbose, 0x1, 0)
+        .raw_add_custom("Bond", 1, [0x10, 0x20, 0x30], [1, 2], 0x20)
+        .raw_add_custom("Empty", 31, [], [], 0)
+        .add_u32("After", 1, OutType::Default, 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    let bond = b"Bond\0\xE1\x80\x80\x80\x80\x20\x03\x00\x10\x20\x30";
+    assert!(events[0].meta.windows(bond.len()).any(|w| w == bond));
+    assert_eq!([2, 0, 1, 2, 0, 0, 1, 0, 0, 0][..], events[0].data[..]);
+
+    let event = events[0].decode().unwrap();
+    assert_eq!(InType::from_int(1), event.fields[0].in_type);
+    assert_eq!(0x20, event.fields[0].tag);
+    assert_eq!(
+        FieldValue::Custom {
+            schema: &[0x10, 0x20, 0x30],
+            data: &[1, 2],
+        },
+        event.fields[0].value
+    );
+    assert_eq!(
+        FieldValue::Custom {
+            schema: &[],
+            data: &[],
+        },
+        event.fields[1].value
+    );
+    assert_eq!(
+        Some(&DecodedValue::U32(1)),
+        event.field("After").unwrap().value()
+    );
+}
+
+#[cfg(all(windows, feature = "loopback"))]
+#[test]
+fn loopback_session() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest.Loopback",
+        &Provider::options()
+    ));
+    unsafe { provider.as_ref().register() };
+
+    let session = match LoopbackSession::start(provider.id(), Level::Verbose, !0) {
+        Ok(session) => session,
+        Err(5) => return, // ERROR_ACCESS_DENIED: not running as an administrator.
+        Err(error) => panic!("LoopbackSession::start failed: {}", error),
     };
 
-    struct HasProvider {
-        provider: Provider,
+    let related_id = Guid::from_u128(&0x01234567_89ab_cdef_0123_456789abcdef);
+    let mut b = EventBuilder::new();
+    b.reset("Loopback", Level::Verbose, 0x1, 0)
+        .add_u32("Value", 5, OutType::Hex, 0)
+        .add_str8("Name", "abc", OutType::Utf8, 0);
+    assert_eq!(0, b.write(&provider, None, Some(&related_id)));
+
+    let events = session.wait_for(1, std::time::Duration::from_secs(30));
+    assert_eq!(1, events.len());
+    assert_eq!(Level::Verbose, events[0].descriptor.level);
+    assert_eq!(Some(related_id), events[0].related_id);
+    assert_eq!(
+        "Loopback { Value: 0x5, Name: \"abc\" }",
+        events[0].decode().unwrap().to_string()
+    );
+}
+
+#[test]
+fn string_dictionary() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+
+    let dictionary = StringDictionary::new(["Open", "Read", "Open", "Close"]);
+    assert_eq!(4, dictionary.len());
+    assert_eq!(Some(0), dictionary.id("Open"));
+    assert_eq!(Some(3), dictionary.id("Close"));
+    assert_eq!(None, dictionary.id("Write"));
+    assert_eq!(Some("Read"), dictionary.get(1));
+    assert_eq!(None, dictionary.get(4));
+
+    // Not enabled and not capturing: nothing is written, so write_once will retry.
+    assert_eq!(0, dictionary.write_once(&provider, Level::Verbose, 0x1));
+
+    provider.set_capture(4);
+    assert_eq!(0, dictionary.write_once(&provider, Level::Verbose, 0x1));
+    assert_eq!(0, dictionary.write_once(&provider, Level::Verbose, 0x1));
+
+    let mut b = EventBuilder::new();
+    b.reset("Dict", Level::Verbose, 0x1, 0)
+        .add_str_dict("Op", "Close", &dictionary, 0x5)
+        .add_str_dict("Other", "Write", &dictionary, 0)
+        .write(&provider, None, None);
+    b.reset("Dict", Level::Verbose, 0x1, 0)
+        .add_u16("OpDictId", 3, OutType::Default, 0x5)
+        .add_str("Other", "Write", 0)
+        .write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(3, events.len());
+    assert_eq!(StringDictionary::EVENT_NAME, events[0].name());
+    b.reset(StringDictionary::EVENT_NAME, Level::Verbose, 0x1, 0)
+        .add_str_sequence("Strings", ["Open", "Read", "Open", "Close"], 0);
+    assert_eq!(b.into_parts().1, events[0].data);
+    assert_eq!(events[1].meta, events[2].meta);
+    assert_eq!(events[1].data, events[2].data);
+
+    // Decode side: re-expand the DictId fields from the dictionary event.
+    let decoded = StringDictionary::from_event(&events[0].decode().unwrap()).unwrap();
+    assert_eq!(dictionary.strings(), decoded.strings());
+    assert!(StringDictionary::from_event(&events[1].decode().unwrap()).is_none());
+
+    EventBuilder::new()
+        .reset("Dict", Level::Verbose, 0x1, 0)
+        .add_struct("S", 2, 0)
+        .add_str_dict("Op", "Open", &dictionary, 0)
+        .add_u16("BadDictId", 9, OutType::Default, 0)
+        .add_u16("Count", 1, OutType::Default, 0)
+        .write(&provider, None, None);
+    let event = provider.drain_captured().remove(0);
+
+    let mut expanded = events[1].decode().unwrap();
+    decoded.expand(&mut expanded);
+    assert_eq!(
+        "Dict { Op: \"Close\", Other: \"Write\" }",
+        expanded.to_string()
+    );
+    assert_eq!(0x5, expanded.field("Op").unwrap().tag);
+
+    let mut expanded = event.decode().unwrap();
+    decoded.expand(&mut expanded);
+    assert_eq!(
+        "Dict { S: { Op: \"Open\", BadDictId: 9 }, Count: 1 }",
+        expanded.to_string()
+    );
+}
+
+#[test]
+fn adopt_registration() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    assert_eq!(87, unsafe {
+        provider.as_ref().adopt_registration(core::ptr::null_mut())
+    });
+
+    // Providers (and therefore adopting providers) can be shared between threads.
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Provider>();
+}
+
+#[test]
+fn write_raw() {
+    let source = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    let target = pin!(Provider::new(
+        "TraceLoggingDynamicRelogTest",
+        &Provider::options()
+    ));
+    source.set_capture(4);
+    target.set_capture(4);
+
+    let aid = Guid::from_name("activity");
+    let rid = Guid::from_name("related");
+    EventBuilder::new()
+        .reset("Relogged", Level::Warning, 0x3, 0x1234)
+        .id_version(7, 1)
+        .add_u32("Value", 5, OutType::Default, 0)
+        .add_str8("Str", "abc", OutType::Default, 0)
+        .write(&source, Some(&aid), Some(&rid));
+
+    let events = source.drain_captured();
+    assert_eq!(1, events.len());
+    let event = &events[0];
+
+    // Data may be split into any number of blocks.
+    let (data1, data2) = event.data.split_at(4);
+    assert_eq!(
+        0,
+        target.write_raw(
+            &event.descriptor,
+            &event.meta,
+            &[data1, data2],
+            event.activity_id.as_ref(),
+            event.related_id.as_ref()
+        )
+    );
+
+    let relogged = target.drain_captured();
+    assert_eq!(1, relogged.len());
+    assert_eq!("Relogged", relogged[0].name());
+    assert_eq!(7, relogged[0].descriptor.id);
+    assert_eq!(Level::Warning, relogged[0].descriptor.level);
+    assert_eq!(0x3, relogged[0].descriptor.keyword);
+    assert_eq!(Some(aid), relogged[0].activity_id);
+    assert_eq!(Some(rid), relogged[0].related_id);
+    assert_eq!(event.meta, relogged[0].meta);
+    assert_eq!(event.data, relogged[0].data);
+
+    // Metadata size must match.
+    let meta_len = event.meta.len();
+    assert_eq!(
+        87,
+        target.write_raw(
+            &event.descriptor,
+            &event.meta[..meta_len - 1],
+            &[],
+            None,
+            None
+        )
+    );
+    assert_eq!(
+        87,
+        target.write_raw(&event.descriptor, &event.meta[..2], &[], None, None)
+    );
+    assert!(target.drain_captured().is_empty());
+}
+
+#[test]
+fn write_pair() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(1000);
+
+    let aid = Guid::from_name("activity");
+    let rid = Guid::from_name("related");
+    let mut request = EventBuilder::new();
+    let mut response = EventBuilder::new();
+    for _ in 0..2 {
+        request
+            .reset("Request", Level::Verbose, 0x1, 0)
+            .add_u32("Id", 5, OutType::Default, 0);
+        response.reset("Response", Level::Verbose, 0x1, 0);
+        assert_eq!(
+            0,
+            provider.write_pair(&mut request, &mut response, &aid, Some(&rid))
+        );
     }
 
-    let mut my_pinned_struct = HasProvider {
-        provider: Provider::new(
-            "MyCompany.MyComponent",
-            Provider::options().callback(my_callback, 0xDEADBEEF),
-        ),
-    };
-    let my_pinned_struct = unsafe { Pin::new_unchecked(&mut my_pinned_struct) };
+    let events = provider.drain_captured();
+    assert_eq!(4, events.len());
+    for (i, event) in events.iter().enumerate() {
+        assert_eq!(
+            if i % 2 == 0 { "Request" } else { "Response" },
+            event.name()
+        );
+        assert_eq!(Some(aid), event.activity_id);
+        assert_eq!(Some(rid), event.related_id);
+        assert!(event.data.ends_with(&(i as u64).to_le_bytes()));
+    }
+    assert_eq!(5u32.to_le_bytes(), events[0].data[..4]);
 
-    let provider = unsafe { my_pinned_struct.map_unchecked_mut(|s| &mut s.provider) };
-    unsafe { provider.as_ref().register() };
+    // Sampling applies to the first event and skips the whole pair.
+    provider.set_event_sampling("Request", 2);
+    for _ in 0..2 {
+        request.reset("Request", Level::Verbose, 0x1, 0);
+        response.reset("Response", Level::Verbose, 0x1, 0);
+        provider.write_pair(&mut request, &mut response, &aid, None);
+    }
+    provider.clear_event_sampling();
+    let events = provider.drain_captured();
+    assert_eq!(2, events.len());
+    assert_eq!(4u64.to_le_bytes(), events[0].data[..]);
+    assert_eq!(5u64.to_le_bytes(), events[1].data[..]);
 
-    _ = provider.enabled(Level::Verbose, 0x123);
+    // Pairs written by different threads are not interleaved.
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let mut first = EventBuilder::new();
+                let mut second = EventBuilder::new();
+                for _ in 0..100 {
+                    first.reset("First", Level::Verbose, 0x1, 0);
+                    second.reset("Second", Level::Verbose, 0x1, 0);
+                    provider.write_pair(&mut first, &mut second, &aid, None);
+                }
+            });
+        }
+    });
 
-    provider.unregister();
+    let events = provider.drain_captured();
+    assert_eq!(800, events.len());
+    for (i, pair) in events.chunks(2).enumerate() {
+        assert_eq!("First", pair[0].name());
+        assert_eq!("Second", pair[1].name());
+        let sequence = 6 + 2 * i as u64;
+        assert_eq!(sequence.to_le_bytes(), pair[0].data[..]);
+        assert_eq!((sequence + 1).to_le_bytes(), pair[1].data[..]);
+    }
+}
+
+#[test]
+fn write_chunked() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(16);
 
+    // Events that fit are written normally.
     let mut b = EventBuilder::new();
-    let aid = Provider::create_activity_id();
-    let rid = Provider::current_thread_activity_id();
+    b.reset("Small", Level::Verbose, 0x1, 0)
+        .add_u32("Value", 5, OutType::Default, 0);
+    assert_eq!(0, b.write_chunked(&provider, None, None));
+    let events = provider.drain_captured();
+    assert_eq!(1, events.len());
+    assert_eq!("Small", events[0].name());
+
+    // Oversized events are split into chunks.
+    let text = Vec::from_iter((0..150_000u32).map(|i| b'a' + (i % 26) as u8));
+    let related_id = Guid::from_u128(&0x1234);
+    b.reset("Large", Level::Warning, 0x6, 0x10)
+        .opcode(Opcode::Start)
+        .task(7)
+        .add_u32("Before", 1, OutType::Default, 0)
+        .add_cstr8("Text", &text, OutType::Default, 0)
+        .add_u32("After", 2, OutType::Default, 0);
+    assert_eq!(0, b.write_chunked(&provider, None, Some(&related_id)));
+    let chunks = provider.drain_captured();
+    assert_eq!(3, chunks.len());
+
+    let mut meta = Vec::new();
+    let mut data = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        assert_eq!(EventBuilder::CHUNK_EVENT_NAME, chunk.name());
+        assert!(
+            provider.raw_meta().len() + chunk.meta.len() + chunk.data.len()
+                <= EventBuilder::CHUNKED_EVENT_SIZE_MAX
+        );
+        assert_eq!(Level::Warning, chunk.descriptor.level);
+        assert_eq!(0x6, chunk.descriptor.keyword);
+        assert_eq!(7, chunk.descriptor.task);
+        if index == 0 {
+            assert_eq!(Opcode::Start, chunk.descriptor.opcode);
+            assert_eq!(Some(related_id), chunk.related_id);
+        } else {
+            assert_eq!(Opcode::Info, chunk.descriptor.opcode);
+            assert_eq!(None, chunk.related_id);
+        }
+
+        let d = &chunk.data;
+        assert_eq!(chunks[0].data[..16], d[..16]); // ChunkId
+        assert_eq!(index as u16, u16::from_le_bytes([d[16], d[17]])); // ChunkIndex
+        assert_eq!(3, u16::from_le_bytes([d[18], d[19]])); // ChunkCount
+        let meta_len = u16::from_le_bytes([d[20], d[21]]) as usize;
+        meta.extend_from_slice(&d[22..22 + meta_len]);
+        let data_pos = 22 + meta_len;
+        let data_len = u16::from_le_bytes([d[data_pos], d[data_pos + 1]]) as usize;
+        assert_eq!(d.len(), data_pos + 2 + data_len);
+        data.extend_from_slice(&d[data_pos + 2..]);
+    }
+
+    // The reassembled event matches the original event.
+    b.write(&provider, None, None);
+    let original = provider.drain_captured();
+    assert_eq!(original[0].meta, meta);
+    assert_eq!(original[0].data, data);
+
+    // ChunkReassembler accepts chunks in any order and passes other events through.
+    let mut reassembler = ChunkReassembler::new();
+    assert!(reassembler.add(chunks[2].clone()).is_none());
+    assert!(reassembler.add(chunks[2].clone()).is_none()); // Duplicate, ignored.
+    assert_eq!(
+        Some(original[0].clone()),
+        reassembler.add(original[0].clone())
+    );
+    assert!(reassembler.add(chunks[0].clone()).is_none());
+    assert_eq!(1, reassembler.pending());
+    let event = reassembler.add(chunks[1].clone()).unwrap();
+    assert_eq!(0, reassembler.pending());
+    assert_eq!(original[0].meta, event.meta);
+    assert_eq!(original[0].data, event.data);
+    assert_eq!(Some(related_id), event.related_id);
+
+    // A chunked BinaryC value larger than an event is split across chunk events and
+    // reassembled from the decoded event.
+    let big = Vec::from_iter((0..200_000u32).map(|i| i as u8));
+    b.reset("Blob", Level::Verbose, 0x1, 0)
+        .add_binaryc_chunked("Data", &big, OutType::Default, 0)
+        .add_binaryc_chunked("Empty", [], OutType::Default, 0);
+    assert_eq!(0, b.write_chunked(&provider, None, None));
+    let mut events = Vec::new();
+    for chunk in provider.drain_captured() {
+        events.extend(reassembler.add(chunk));
+    }
+    assert_eq!(1, events.len());
+    let event = events[0].decode().unwrap();
+    assert_eq!(Some(big), event.binaryc_chunked("Data"));
+    assert_eq!(Some(Vec::new()), event.binaryc_chunked("Empty"));
+    assert_eq!(None, event.binaryc_chunked("Missing"));
+}
+
+#[test]
+#[cfg(feature = "tracing")]
+fn tracing_layer() {
+    use std::sync::Arc;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let provider = Arc::pin(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options(),
+    ));
+    provider.set_capture(10);
+
+    let subscriber = tracing_subscriber::registry().with(TracingLayer::new(provider.clone(), 0x5));
+    tracing::subscriber::with_default(subscriber, || {
+        let outer = tracing::info_span!("Outer", id = 7u64).entered();
+        let inner = tracing::debug_span!("Inner").entered();
+        tracing::warn!(ok = true, value = -3i64, name = "abc", "done {}", 1);
+        drop(inner);
+        drop(outer);
+    });
+
+    let events = provider.drain_captured();
+    assert_eq!(
+        vec![
+            ("Outer", Opcode::ActivityStart, Level::Informational),
+            ("Inner", Opcode::ActivityStart, Level::Verbose),
+            (events[2].name(), Opcode::Info, Level::Warning),
+            ("Inner", Opcode::ActivityStop, Level::Verbose),
+            ("Outer", Opcode::ActivityStop, Level::Informational),
+        ],
+        Vec::from_iter(
+            events
+                .iter()
+                .map(|e| (e.name(), e.descriptor.opcode, e.descriptor.level))
+        )
+    );
+    assert!(events[2].name().starts_with("event "));
+    assert!(events.iter().all(|e| e.descriptor.keyword == 0x5));
+
+    // Spans are activities; the inner span's related id is the outer span's id.
+    let outer_id = events[0].activity_id.unwrap();
+    let inner_id = events[1].activity_id.unwrap();
+    assert_eq!(None, events[0].related_id);
+    assert_eq!(Some(outer_id), events[1].related_id);
+    assert_eq!(Some(inner_id), events[2].activity_id);
+    assert_eq!(Some(inner_id), events[3].activity_id);
+    assert_eq!(Some(outer_id), events[4].activity_id);
+
+    // Fields: message (Debug), bool, i64, &str.
+    assert_eq!(events[0].data, 7u64.to_le_bytes());
+    let mut data = Vec::new();
+    data.extend_from_slice(&[6, 0]);
+    data.extend_from_slice(b"done 1");
+    data.extend_from_slice(&1i32.to_le_bytes());
+    data.extend_from_slice(&(-3i64).to_le_bytes());
+    data.extend_from_slice(&[3, 0]);
+    data.extend_from_slice(b"abc");
+    assert_eq!(events[2].data, data);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn net_addresses() {
+    use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
 
     let provider = pin!(Provider::new(
         "TraceLoggingDynamicTest",
-        Provider::options()
-            .callback(my_callback, 0xDEADBEEF)
-            .group_id(&Guid::from_name("TraceLoggingDynamicTestGroup")),
+        &Provider::options()
     ));
     unsafe { provider.as_ref().register() };
-    b.reset("GroupEvent-Start", Level::Verbose, 0x1, 0)
-        .opcode(Opcode::ActivityStart)
-        .write(&provider, Some(&aid), Some(&rid));
-    b.reset("GroupEvent-Stop", Level::Verbose, 0x1, 0)
-        .opcode(Opcode::ActivityStop)
-        .write(&provider, Some(&aid), None);
+
+    let mut schema = Schema::new();
+    schema
+        .field("IPv4", InType::U32)
+        .field("IPv6", InType::Binary)
+        .field("Sock4", InType::Binary)
+        .field("Sock6", InType::Binary);
+
+    let mut b = EventBuilder::new();
+    b.reset("NetAddresses", Level::Verbose, 0x1, 0)
+        .add_ipv4("IPv4", &Ipv4Addr::new(127, 0, 0, 1), 0)
+        .add_ipv6("IPv6", &Ipv6Addr::LOCALHOST, 0)
+        .add_socketaddr("Sock4", &"127.0.0.1:80".parse::<SocketAddr>().unwrap(), 0)
+        .add_socketaddr("Sock6", &"[::1]:443".parse::<SocketAddr>().unwrap(), 0);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.write(&provider, None, None);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn async_writer() {
+    let writer = AsyncWriter::new(
+        Provider::new("TraceLoggingDynamicTest", &Provider::options()),
+        4,
+    );
+    assert_eq!(4, writer.capacity());
+    assert_eq!("TraceLoggingDynamicTest", writer.provider().name());
+    unsafe { writer.register() };
+
+    let aid = Guid::from_name("Activity");
+    for i in 0..10u32 {
+        let mut b = EventBuilder::new();
+        b.reset("AsyncEvent", Level::Verbose, 0x1, 0)
+            .add_u32("Index", i, OutType::Default, 0);
+        writer.write(b, Some(&aid), None);
+    }
+
+    if !writer.provider().enabled(Level::Verbose, 0x1) {
+        // Events for a disabled provider are discarded before reaching the queue.
+        assert_eq!(0, writer.dropped_count());
+    }
+
+    println!("{:?}", writer);
+    drop(writer); // Waits for queued events to be written.
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn event_deduper() {
+    use std::time::Duration;
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(16);
+
+    let deduper = EventDeduper::new(Duration::from_secs(3600));
+    assert_eq!(Duration::from_secs(3600), deduper.window());
+    assert!(deduper.is_empty());
+
+    let check = |name: &str, code: u32| {
+        deduper.check(&provider, name, Level::Warning, 0x3, &(code, "host"))
+    };
+    assert!(check("Failed", 5));
+    assert!(!check("Failed", 5));
+    assert!(!check("Failed", 5));
+    assert!(check("Failed", 6));
+    assert!(check("Other", 5));
+    assert_eq!(3, deduper.len());
+
+    // Nothing has expired yet.
+    assert_eq!(0, deduper.flush_expired(&provider));
+    assert!(provider.drain_captured().is_empty());
+
+    // Only entries with suppressed events are summarized.
+    assert_eq!(1, deduper.flush(&provider));
+    assert!(deduper.is_empty());
+    let events = provider.drain_captured();
+    assert_eq!(1, events.len());
+    assert_eq!("Deduplicated", events[0].name());
+    assert_eq!(Level::Warning, events[0].descriptor.level);
+    assert_eq!(0x3, events[0].descriptor.keyword);
+    let mut data = b"\x06\x00Failed".to_vec();
+    data.extend_from_slice(&2u64.to_le_bytes());
+    data.extend_from_slice(&3_600_000u64.to_le_bytes());
+    assert_eq!(data, events[0].data);
+
+    // Expired windows are summarized by check and flush_expired.
+    let deduper = EventDeduper::new(Duration::from_millis(1));
+    assert!(deduper.check(&provider, "Fast", Level::Verbose, 0x1, &1u8));
+    assert!(!deduper.check(&provider, "Fast", Level::Verbose, 0x1, &1u8));
+    std::thread::sleep(Duration::from_millis(10));
+    assert!(deduper.check(&provider, "Fast", Level::Verbose, 0x1, &1u8));
+    assert_eq!(1, provider.drain_captured().len());
+    assert!(!deduper.check(&provider, "Fast", Level::Verbose, 0x1, &1u8));
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(1, deduper.flush_expired(&provider));
+    assert!(deduper.is_empty());
+    assert_eq!(1, provider.drain_captured().len());
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn event_builder_pool() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(16);
+
+    let pool = EventBuilderPool::new(2, 1024);
+    assert_eq!(2, pool.max_pooled());
+    assert_eq!(1024, pool.trim_threshold());
+    assert!(pool.is_empty());
+
+    pool.get()
+        .reset("Pooled", Level::Verbose, 0x1, 0)
+        .add_u32("Id", 5, OutType::Default, 0)
+        .write(&provider, None, None);
+    assert_eq!(1, pool.len());
+    let events = provider.drain_captured();
+    assert_eq!(1, events.len());
+    assert_eq!("Pooled", events[0].name());
+    assert_eq!(5u32.to_le_bytes().as_slice(), events[0].data.as_slice());
+
+    // The returned builder is reused, including its buffers.
+    let mut builder = pool.get();
+    assert!(pool.is_empty());
+    assert_ne!(0, builder.capacity());
+    builder
+        .reset("Reused", Level::Verbose, 0x1, 0)
+        .write(&provider, None, None);
+    drop(builder);
+    let events = provider.drain_captured();
+    assert_eq!("Reused", events[0].name());
+    assert!(events[0].data.is_empty());
+    assert_eq!(1, pool.len());
+
+    // At most max_pooled builders are kept.
+    let (a, b, c) = (pool.get(), pool.get(), pool.get());
+    drop((a, b, c));
+    assert_eq!(2, pool.len());
+
+    // Builders that grow past trim_threshold are discarded.
+    pool.get()
+        .reset("Large", Level::Verbose, 0x1, 0)
+        .add_binary("Payload", [0u8; 2048], OutType::Default, 0);
+    assert_eq!(1, pool.len());
+
+    // Builders removed with into_inner are not returned.
+    let builder = pool.get().into_inner();
+    assert!(pool.is_empty());
+    drop(builder);
+    assert!(pool.is_empty());
+
+    pool.get();
+    pool.get();
+    pool.clear();
+    assert!(pool.is_empty());
+
+    // Builders can be shared by multiple threads.
+    let pool = std::sync::Arc::new(EventBuilderPool::new(8, 4096));
+    let threads: Vec<_> = (0..4u32)
+        .map(|i| {
+            let pool = pool.clone();
+            std::thread::spawn(move || {
+                for j in 0..100u32 {
+                    pool.get().reset("Thread", Level::Verbose, 0x1, 0).add_u32(
+                        "Id",
+                        i * 100 + j,
+                        OutType::Default,
+                        0,
+                    );
+                }
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert!(pool.len() <= 4);
+
+    println!("{:?}", pool);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn provider_factory() {
+    use tracelogging_dynamic::ProviderFactory;
+
+    let factory =
+        unsafe { ProviderFactory::new(Provider::options().group_id(&Guid::zero()).clone(), 2) };
+    assert_eq!(2, factory.capacity());
+    assert!(factory.is_empty());
+
+    let a = factory.get("TraceLoggingDynamicTest.A").unwrap();
+    assert_eq!("TraceLoggingDynamicTest.A", a.name());
+    assert_eq!(&Guid::from_name("TraceLoggingDynamicTest.A"), a.id());
+    assert!(core::ptr::eq(
+        &*a,
+        &*factory.get("TraceLoggingDynamicTest.A").unwrap()
+    ));
+    let b = factory.get("TraceLoggingDynamicTest.B").unwrap();
+    assert_eq!(2, factory.len());
+
+    // Full, and no idle providers.
+    assert!(factory.get("TraceLoggingDynamicTest.C").is_none());
+
+    // B becomes idle, so it is replaced by C.
+    drop(b);
+    let c = factory.get("TraceLoggingDynamicTest.C").unwrap();
+    assert_eq!("TraceLoggingDynamicTest.C", c.name());
+    assert_eq!(2, factory.len());
+    assert_eq!(0, factory.remove_idle());
+
+    drop(a);
+    assert_eq!(1, factory.remove_idle());
+    assert_eq!(1, factory.len());
+    println!("{:?}", factory);
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn write_rundown() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+
+    // Not enabled and not capturing: nothing is written.
+    assert_eq!(0, provider.write_rundown(Level::Informational, 0x1));
+
+    provider.set_capture(1024);
+    assert_eq!(0, provider.write_rundown(Level::Informational, 0x1));
+    let events = provider.drain_captured();
+    assert_eq!("ProcessRundown", events[0].name());
+    assert_eq!(Opcode::DC_Start, events[0].descriptor.opcode);
+    assert_eq!(0x1, events[0].descriptor.keyword);
+    assert_eq!(std::process::id().to_le_bytes(), events[0].data[..4]);
+    assert!(events[1..]
+        .iter()
+        .all(|event| event.name() == "ModuleRundown"));
+    if let NativeImplementation::Windows = NATIVE_IMPLEMENTATION {
+        assert!(events.len() > 2); // At least the EXE and ntdll.
+    } else {
+        assert_eq!(1, events.len());
+    }
+}
+
+#[test]
+fn eventlog() {
+    let event_log = EventLog::new("TraceLoggingDynamicTest", Level::Warning, 0x0F);
+    assert_eq!(Level::Warning, event_log.level());
+    assert_eq!(0x0F, event_log.keyword_mask());
+    assert!(event_log.would_report(Level::Critical, 0));
+    assert!(event_log.would_report(Level::Warning, 0x11));
+    assert!(!event_log.would_report(Level::Warning, 0x10));
+    assert!(!event_log.would_report(Level::Informational, 0x01));
+
+    // Not selected, so nothing is reported.
+    assert_eq!(
+        0,
+        event_log.report(Level::Verbose, 0x01, 1000, "TraceLoggingDynamicTest")
+    );
+    assert_eq!(
+        0,
+        event_log.report_fmt(Level::Error, 0x10, 1000, format_args!("{}", 1))
+    );
+    println!("{:?}", event_log);
+
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(1);
+    let mut b = EventBuilder::new();
+    b.reset("EventLogEvent", Level::Error, 0x1, 0)
+        .add_str8("Path", "a", OutType::Utf8, 0)
+        .add_struct("Struct", 1, 0)
+        .add_u32("Code", 5, OutType::Default, 0);
+    b.write(&provider, None, None);
+    let captured = provider.drain_captured();
+    assert_eq!(
+        "TraceLoggingDynamicTest: EventLogEvent Path=\"a\" Struct={ Code: 5 }",
+        EventLog::message(provider.name(), &captured[0].decode().unwrap())
+    );
+
+    // Selected events are mirrored from the write path (a no-op if the event source
+    // could not be opened, e.g. on non-Windows).
+    #[cfg(feature = "eventlog")]
+    {
+        provider.set_event_log(Some(event_log));
+        assert_eq!(0, b.write(&provider, None, None));
+        provider.set_event_log(None);
+        assert_eq!(0, b.write(&provider, None, None));
+    }
+}
+
+#[test]
+fn builder_inline_buffers() {
+    let provider = pin!(Provider::new(
+        "TraceLoggingDynamicTest",
+        &Provider::options()
+    ));
+    provider.set_capture(16);
+
+    // Small events are stored inline: capacity stays at 256 + 256 bytes.
+    let mut b = EventBuilder::new();
+    assert_eq!(512, b.capacity());
+    b.reset("Small", Level::Verbose, 0x1, 0)
+        .add_binary("Data", [1u8; 200], OutType::Default, 0);
+    assert_eq!(512, b.capacity());
+    b.write(&provider, None, None);
+
+    // Larger events move to the heap, and the buffer is kept by reset.
+    b.reset("Large", Level::Verbose, 0x1, 0)
+        .add_binary("Data", [2u8; 1000], OutType::Default, 0);
+    assert!(b.capacity() >= 256 + 1002);
+    b.write(&provider, None, None);
+    let capacity = b.capacity();
+    b.reset("Empty", Level::Verbose, 0x1, 0);
+    assert_eq!(capacity, b.capacity());
+
+    // into_parts copies inline buffers out.
+    b = EventBuilder::new_with_capacity(16, 16);
+    b.reset("Parts", Level::Verbose, 0x1, 0)
+        .add_u32("Id", 5, OutType::Default, 0);
+    let (meta, data, descriptor) = b.into_parts();
+    assert_eq!(5u32.to_le_bytes().as_slice(), data.as_slice());
+    EventBuilder::from_parts(meta, data, descriptor).write(&provider, None, None);
+
+    let events = provider.drain_captured();
+    assert_eq!(3, events.len());
+    assert_eq!("Small", events[0].name());
+    assert_eq!(202, events[0].data.len());
+    assert_eq!(&[1u8; 200][..], &events[0].data[2..]);
+    assert_eq!("Large", events[1].name());
+    assert_eq!(&[2u8; 1000][..], &events[1].data[2..]);
+    assert_eq!("Parts", events[2].name());
+    assert_eq!(5u32.to_le_bytes().as_slice(), events[2].data.as_slice());
 }
 
 #[test]
@@ -518,6 +2520,165 @@ fn builder() {
             b.add_binaryc_sequence(n, v, o, t);
         },
     );
+
+    assert_eq!(1, p.event_sampling("Sampled"));
+    p.set_event_sampling("Sampled", 3);
+    p.set_event_id_sampling(7, 0);
+    assert_eq!(3, p.event_sampling("Sampled"));
+    assert_eq!(0, p.event_id_sampling(7));
+    for _ in 0..6 {
+        assert_eq!(
+            0,
+            b.reset("Sampled", Level::Verbose, 0x1, 0)
+                .write(&p, None, None)
+        );
+        b.reset("SampledById", Level::Verbose, 0x1, 0xFEDCBAF)
+            .id_version(7, 0)
+            .write(&p, None, None);
+    }
+    p.set_event_sampling("Sampled", 1);
+    assert_eq!(1, p.event_sampling("Sampled"));
+    p.clear_event_sampling();
+    assert_eq!(1, p.event_id_sampling(7));
+
+    assert!(!p.event_denied("Denied"));
+    p.set_event_deny_list(["Denied", "Noisy*"]);
+    assert!(p.event_denied("Denied"));
+    assert!(!p.event_denied("Denied2"));
+    assert!(p.event_denied("Noisy"));
+    assert!(p.event_denied("NoisyEvent"));
+    assert!(!p.event_denied("Sampled"));
+    p.clear_event_sampling();
+    assert!(p.event_denied("NoisyEvent"));
+    assert_eq!(
+        0,
+        b.reset("NoisyEvent", Level::Verbose, 0x1, 0)
+            .write(&p, None, None)
+    );
+    p.set_event_deny_list(["Other"]);
+    assert!(!p.event_denied("Denied"));
+    assert!(p.event_denied("Other"));
+    p.clear_event_deny_list();
+    assert!(!p.event_denied("Other"));
+
+    let mut schema = Schema::new();
+    schema
+        .field("u8", InType::U8)
+        .struct_field("struct")
+        .array_field("str8s", InType::Str8)
+        .field("tagged", InType::Hex32);
+    b.reset("Validate", Level::Verbose, 0x1, 0xFEDCBAF)
+        .add_u8("u8", 1, OutType::Default, 0)
+        .add_struct("struct", 2, 0)
+        .add_str8_sequence("str8s", ["a", "b"], OutType::Default, 0)
+        .add_hex32("tagged", 1, OutType::Signed, 0xFEDCBAF);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.add_u8("extra", 1, OutType::Default, 0);
+    let mismatch = b.validate(&schema).unwrap_err();
+    assert_eq!(4, mismatch.field_index);
+    assert_eq!(None, mismatch.expected);
+    assert_eq!("extra", mismatch.actual.unwrap().name);
+    b.reset("Validate", Level::Verbose, 0x1, 0)
+        .add_u8("u8", 1, OutType::Default, 0)
+        .add_struct("struct", 2, 0)
+        .add_str8("str8s", "a", OutType::Default, 0);
+    let mismatch = b.validate(&schema).unwrap_err();
+    assert_eq!(2, mismatch.field_index);
+    println!("{} {}", schema, mismatch);
+
+    let mut schema = Schema::new();
+    schema
+        .field("str", InType::Str8)
+        .array_field("strs", InType::Str8);
+    b.reset("Utf8", Level::Verbose, 0x1, 0)
+        .add_str("str", "h\u{e9}llo", 0)
+        .add_str_sequence("strs", ["a", "\u{1f600}"], 0);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.write(&p, None, None);
+    let (meta, data, _) = b.into_parts();
+    b = EventBuilder::new();
+    b.reset("Utf8", Level::Verbose, 0x1, 0)
+        .add_str8("str", "h\u{e9}llo", OutType::Utf8, 0)
+        .add_str8_sequence("strs", ["a", "\u{1f600}"], OutType::Utf8, 0);
+    let (meta8, data8, _) = b.into_parts();
+    assert_eq!(meta[2..], meta8[2..]); // write() fills in meta[0..2].
+    assert_eq!(data, data8);
+    b = EventBuilder::new();
+
+    let long = "\u{e9}".repeat(40000); // 80000 bytes
+    b.reset("Utf8Long", Level::Verbose, 0x1, 0)
+        .add_str("long", &long, 0);
+    let (_, data, _) = b.into_parts();
+    assert_eq!(&65534u16.to_le_bytes(), &data[..2]); // Truncated at a char boundary.
+    assert_eq!(2 + 65534, data.len());
+    b = EventBuilder::new();
+
+    let big = vec![0x42u8; EventBuilder::BINARYC_CHUNK_MAX + 10];
+    b.reset("BinaryChunked", Level::Verbose, 0x1, 0)
+        .add_binaryc_chunked("empty", [], OutType::Default, 0)
+        .add_binaryc_chunked("small", "0123".as_bytes(), OutType::Default, 0)
+        .add_binaryc_chunked("big", &big[..], OutType::Default, 0xFE00000)
+        .write_chunked(&p, None, None);
+
+    let mut schema = Schema::new();
+    schema
+        .field("empty", InType::Str8)
+        .field("hash", InType::Str8);
+    b.reset("HexString", Level::Verbose, 0x1, 0)
+        .add_hex_string("empty", [], 0)
+        .add_hex_string("hash", [0x00, 0x1A, 0x2B, 0xFF], 0);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.write(&p, None, None);
+
+    let points = [(1u32, "a"), (2, "bc"), (3, "def")];
+    let mut schema = Schema::new();
+    schema
+        .array_field("Points", InType::Struct)
+        .field("X", InType::U32)
+        .field("Name", InType::Str8)
+        .array_field("Empty", InType::Struct)
+        .field("After", InType::U8);
+    b.reset("StructSequence", Level::Verbose, 0x1, 0)
+        .add_struct_sequence("Points", 2, points.len() as u16, 0, |b, i| {
+            b.add_u32("X", points[i].0, OutType::Default, 0);
+            b.add_str8("Name", points[i].1, OutType::Default, 0);
+        })
+        .add_struct_sequence("Empty", 2, 0, 0x1234, |_, _| unreachable!())
+        .add_u8("After", 1, OutType::Default, 0);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.write(&p, None, None);
+
+    let mut schema = Schema::new();
+    schema
+        .field("Error", InType::U32)
+        .field("ErrorMessage", InType::Str16)
+        .field("HResultMessage", InType::Str16)
+        .field("NtStatusMessage", InType::Str16);
+    b.reset("ErrorMessages", Level::Verbose, 0x1, 0)
+        .add_u32("Error", 2, OutType::Win32Error, 0)
+        .add_win_error_message("ErrorMessage", 2, 0)
+        .add_hresult_message("HResultMessage", 0x80070005u32 as i32, 0)
+        .add_win_ntstatus_message("NtStatusMessage", 0xC0000022u32 as i32, 0);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.write(&p, None, None);
+
+    let mut schema = Schema::new();
+    schema
+        .field("Before", InType::U32)
+        .field("After", InType::U32);
+    b.reset("Parts", Level::Warning, 0x3, 0)
+        .id_version(5, 1)
+        .add_u32("Before", 1, OutType::Default, 0);
+    let (meta, data, descriptor) = b.into_parts();
+    assert_eq!(4, data.len());
+    assert_eq!(5, descriptor.id);
+    assert_eq!(Level::Warning, descriptor.level);
+    let mut b = std::thread::spawn(move || EventBuilder::from_parts(meta, data, descriptor))
+        .join()
+        .unwrap();
+    b.add_u32("After", 2, OutType::Default, 0);
+    assert_eq!(Ok(()), b.validate(&schema));
+    b.write(&p, None, None);
 }
 
 fn to_utf16(s: &str) -> Vec<u16> {
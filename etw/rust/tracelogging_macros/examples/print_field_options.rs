@@ -43,34 +43,22 @@ fn main() {
 }
 
 /// option_name_cmp tries to be human-friendly:
-/// - `foo_slice` sorts immediately after `foo`.
-/// - `foo_` sorts immediately after `foo_slice` ("str" < "str_xml" < "str16").
+/// - `foo_slice` sorts immediately after `foo`, and `foo_array` immediately after that.
+/// - `foo_` sorts immediately after `foo_array` ("str" < "str_xml" < "str16").
 /// - Numbers are sorted by value ("u8" < "u16").
 fn option_name_cmp(val1: &str, val2: &str) -> Ordering {
-    let mut v1 = val1.as_bytes();
-    let mut v2 = val2.as_bytes();
+    let (v1, suffix1) = split_suffix(val1.as_bytes());
+    let (v2, suffix2) = split_suffix(val2.as_bytes());
     let mut pos1 = 0;
     let mut pos2 = 0;
 
-    const SLICE: &[u8] = b"_slice";
-
-    let slice1 = v1.ends_with(SLICE);
-    if slice1 {
-        v1 = &v1[..v1.len() - SLICE.len()];
-    }
-
-    let slice2 = v2.ends_with(SLICE);
-    if slice2 {
-        v2 = &v2[..v2.len() - SLICE.len()];
-    }
-
     let len1 = v1.len();
     let len2 = v2.len();
 
     let cmp = loop {
         if pos1 == len1 {
             if pos2 == len2 {
-                break slice1.cmp(&slice2);
+                break suffix1.cmp(&suffix2);
             } else {
                 break Ordering::Less;
             }
@@ -112,9 +100,28 @@ fn option_name_cmp(val1: &str, val2: &str) -> Ordering {
     return cmp;
 }
 
+/// Returns the name without its `_slice` or `_array` suffix, and the suffix's sort
+/// rank (0 = no suffix, 1 = `_slice`, 2 = `_array`).
+fn split_suffix(val: &[u8]) -> (&[u8], u8) {
+    const SUFFIXES: [&[u8]; 2] = [b"_slice", b"_array"];
+    for (i, suffix) in SUFFIXES.iter().enumerate() {
+        if val.ends_with(suffix) {
+            return (&val[..val.len() - suffix.len()], i as u8 + 1);
+        }
+    }
+    return (val, 0);
+}
+
 trait ToMarkdown {
     fn to_markdown(&self) -> String;
-    fn normal_field(&self, s: &mut String, type_path: &[&str], is_slice: bool, note: &str);
+    fn normal_field(
+        &self,
+        s: &mut String,
+        type_path: &[&str],
+        is_slice: bool,
+        is_option: bool,
+        note: &str,
+    );
 }
 
 impl ToMarkdown for FieldOption {
@@ -131,7 +138,7 @@ impl ToMarkdown for FieldOption {
                 } else {
                     ""
                 };
-                self.normal_field(&mut s, self.value_type, false, note);
+                self.normal_field(&mut s, self.value_type, false, false, note);
             }
             FieldStrategy::Slice => {
                 let note = if self.option_name == "errno_slice" {
@@ -139,19 +146,31 @@ impl ToMarkdown for FieldOption {
                 } else {
                     ""
                 };
-                self.normal_field(&mut s, self.value_type, true, note);
+                self.normal_field(&mut s, self.value_type, true, false, note);
+            }
+            FieldStrategy::Optional => {
+                self.normal_field(&mut s, self.value_type, false, true, "opt");
+            }
+            FieldStrategy::Array => {
+                self.normal_field(&mut s, self.value_type, true, false, "array");
             }
             FieldStrategy::SystemTime => {
-                self.normal_field(&mut s, &["std", "time", "SystemTime"], false, "systemtime");
+                self.normal_field(
+                    &mut s,
+                    &["std", "time", "SystemTime"],
+                    false,
+                    false,
+                    "systemtime",
+                );
             }
             FieldStrategy::Time32 | FieldStrategy::Time64 => {
-                self.normal_field(&mut s, self.value_type, false, "time");
+                self.normal_field(&mut s, self.value_type, false, false, "time");
             }
             FieldStrategy::Sid => {
-                self.normal_field(&mut s, self.value_type, true, "sid");
+                self.normal_field(&mut s, self.value_type, true, false, "sid");
             }
             FieldStrategy::CStr => {
-                self.normal_field(&mut s, self.value_type, true, "cstr");
+                self.normal_field(&mut s, self.value_type, true, false, "cstr");
             }
             FieldStrategy::Counted => {
                 let note = if matches!(self.intype, InType::BinaryC) {
@@ -159,11 +178,55 @@ impl ToMarkdown for FieldOption {
                 } else {
                     ""
                 };
-                self.normal_field(&mut s, self.value_type, self.value_array_count == 0, note);
+                self.normal_field(
+                    &mut s,
+                    self.value_type,
+                    self.value_array_count == 0,
+                    false,
+                    note,
+                );
+            }
+            FieldStrategy::Capped => {
+                self.normal_field(&mut s, self.value_type, true, false, "capped");
+            }
+            FieldStrategy::Fmt => {
+                // The value is passed by value, not by reference.
+                self.normal_field(&mut s, &["std", "fmt", "Arguments"], false, false, "fmt");
+                s = s.replacen("| `&", "| `", 1);
+            }
+            FieldStrategy::CallerAddress => {
+                // The value is the stack depth (passed by value), not the address.
+                self.normal_field(&mut s, &["u32"], false, false, "caller");
+                s = s.replacen("| `&", "| `", 1);
+            }
+            FieldStrategy::PtrLen => {
+                // The value is a pointer and a length (passed by value).
+                self.normal_field(&mut s, &["*const u8, usize"], false, false, "ptr_len");
+                s = s.replacen("| `&", "| `", 1);
+            }
+            FieldStrategy::StrSlice => {
+                // The value is a slice of strings.
+                let element_type: &[&str] = if matches!(self.intype, InType::Str16) {
+                    &["&[u16]"]
+                } else {
+                    &["&str"]
+                };
+                self.normal_field(&mut s, element_type, true, false, "str_slice");
+            }
+            FieldStrategy::ErrorChain => {
+                // The value is a trait object, so the "path" includes `dyn`.
+                self.normal_field(
+                    &mut s,
+                    &["dyn std", "error", "Error"],
+                    false,
+                    false,
+                    "error",
+                );
             }
             FieldStrategy::Struct
             | FieldStrategy::RawStruct
             | FieldStrategy::RawStructSlice
+            | FieldStrategy::RawCustom
             | FieldStrategy::RawData
             | FieldStrategy::RawField
             | FieldStrategy::RawFieldSlice
@@ -174,7 +237,14 @@ impl ToMarkdown for FieldOption {
         return s;
     }
 
-    fn normal_field(&self, s: &mut String, type_path: &[&str], is_slice: bool, note: &str) {
+    fn normal_field(
+        &self,
+        s: &mut String,
+        type_path: &[&str],
+        is_slice: bool,
+        is_option: bool,
+        note: &str,
+    ) {
         use std::fmt::Write;
 
         s.push_str("/// | ");
@@ -190,6 +260,10 @@ impl ToMarkdown for FieldOption {
 
         s.push_str(" | `&");
 
+        if is_option {
+            s.push_str("Option<");
+        }
+
         if is_slice {
             s.push('[');
         }
@@ -210,9 +284,16 @@ impl ToMarkdown for FieldOption {
         }
 
         if is_slice {
+            if let FieldStrategy::Array = self.strategy {
+                s.push_str("; N");
+            }
             s.push(']');
         }
 
+        if is_option {
+            s.push('>');
+        }
+
         s.push_str("` | ");
 
         push_enum_value(s, "InType", intype_to_string(self.intype));
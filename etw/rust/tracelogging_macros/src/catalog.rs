@@ -0,0 +1,595 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Implements `include_event_catalog!`: reads a JSON event catalog and generates a
+//! `define_provider!` and a typed event-writing function for each event.
+
+use proc_macro::*;
+use std::fmt::Write;
+
+use crate::field_option::{FieldOption, FieldStrategy};
+use crate::field_options::FIELD_OPTIONS;
+use crate::manifest::{include_generated, parse_int, to_snake_case, to_upper_snake_case};
+use crate::strings::{LEVEL_ENUMS, OPCODE_ENUMS};
+
+/// A JSON value. Numbers are kept as text and converted when used.
+enum Json {
+    Null,
+    Bool,
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn type_name(&self) -> &'static str {
+        return match self {
+            Json::Null => "null",
+            Json::Bool => "boolean",
+            Json::Number(_) => "number",
+            Json::String(_) => "string",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        };
+    }
+}
+
+/// Minimal JSON parser (RFC 8259). Reports errors with the line number.
+struct JsonParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn parse_document(text: &'a str) -> Result<Json, String> {
+        let mut parser = JsonParser { text, pos: 0 };
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.text.len() {
+            return Err(parser.error("unexpected text after JSON value"));
+        }
+        return Ok(value);
+    }
+
+    fn rest(&self) -> &'a str {
+        return &self.text[self.pos..];
+    }
+
+    fn error(&self, message: &str) -> String {
+        let line = 1 + self.text[..self.pos].matches('\n').count();
+        return format!("line {}: {}", line, message);
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, ch: char) -> Result<(), String> {
+        self.skip_whitespace();
+        if !self.rest().starts_with(ch) {
+            return Err(self.error(&format!("expected '{}'", ch)));
+        }
+        self.pos += 1;
+        return Ok(());
+    }
+
+    fn parse_value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let first = match rest.chars().next() {
+            Some(ch) => ch,
+            None => return Err(self.error("expected JSON value")),
+        };
+
+        match first {
+            '{' => {
+                self.pos += 1;
+                let mut members: Vec<(String, Json)> = Vec::new();
+                self.skip_whitespace();
+                if self.rest().starts_with('}') {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if !self.rest().starts_with('"') {
+                        return Err(self.error("expected member name (string)"));
+                    }
+                    let name = self.parse_string()?;
+                    if members.iter().any(|(n, _)| *n == name) {
+                        return Err(self.error(&format!("duplicate member \"{}\"", name)));
+                    }
+                    self.expect(':')?;
+                    let value = self.parse_value()?;
+                    members.push((name, value));
+                    self.skip_whitespace();
+                    if self.rest().starts_with(',') {
+                        self.pos += 1;
+                    } else {
+                        self.expect('}')?;
+                        return Ok(Json::Object(members));
+                    }
+                }
+            }
+            '[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                self.skip_whitespace();
+                if self.rest().starts_with(']') {
+                    self.pos += 1;
+                    return Ok(Json::Array(items));
+                }
+                loop {
+                    items.push(self.parse_value()?);
+                    self.skip_whitespace();
+                    if self.rest().starts_with(',') {
+                        self.pos += 1;
+                    } else {
+                        self.expect(']')?;
+                        return Ok(Json::Array(items));
+                    }
+                }
+            }
+            '"' => return Ok(Json::String(self.parse_string()?)),
+            '-' | '0'..='9' => {
+                let len = rest
+                    .find(|ch: char| !matches!(ch, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+                    .unwrap_or(rest.len());
+                self.pos += len;
+                return Ok(Json::Number(String::from(&rest[..len])));
+            }
+            _ => {
+                for (keyword, value) in [
+                    ("true", Json::Bool),
+                    ("false", Json::Bool),
+                    ("null", Json::Null),
+                ] {
+                    if rest.starts_with(keyword) {
+                        self.pos += keyword.len();
+                        return Ok(value);
+                    }
+                }
+                return Err(self.error("expected JSON value"));
+            }
+        }
+    }
+
+    /// Parses a string starting at the opening quote.
+    fn parse_string(&mut self) -> Result<String, String> {
+        self.pos += 1; // Opening quote.
+        let mut result = String::new();
+        loop {
+            let rest = self.rest();
+            let len = match rest.find(|ch: char| ch == '"' || ch == '\\' || ch < ' ') {
+                Some(len) => len,
+                None => return Err(self.error("unterminated string")),
+            };
+            result.push_str(&rest[..len]);
+            self.pos += len;
+
+            let rest = self.rest();
+            if rest.starts_with('"') {
+                self.pos += 1;
+                return Ok(result);
+            } else if !rest.starts_with('\\') {
+                return Err(self.error("control character in string"));
+            }
+
+            let escape = match rest[1..].chars().next() {
+                Some(ch) => ch,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.pos += 1 + escape.len_utf8();
+            match escape {
+                '"' | '\\' | '/' => result.push(escape),
+                'b' => result.push('\u{8}'),
+                'f' => result.push('\u{c}'),
+                'n' => result.push('\n'),
+                'r' => result.push('\r'),
+                't' => result.push('\t'),
+                'u' => {
+                    let mut code = self.parse_hex4()?;
+                    if (0xD800..0xDC00).contains(&code) && self.rest().starts_with("\\u") {
+                        self.pos += 2;
+                        let low = self.parse_hex4()?;
+                        if (0xDC00..0xE000).contains(&low) {
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                    }
+                    result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                }
+                _ => return Err(self.error("invalid escape sequence in string")),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let hex = self.rest().get(..4).unwrap_or_default();
+        return match u32::from_str_radix(hex, 16) {
+            Ok(code) if hex.len() == 4 && !hex.starts_with('+') => {
+                self.pos += 4;
+                Ok(code)
+            }
+            _ => Err(self.error("invalid \\u escape sequence in string")),
+        };
+    }
+}
+
+/// The members of a JSON object, with helpers that report errors in context.
+struct Members<'a> {
+    context: String,
+    members: &'a [(String, Json)],
+}
+
+impl<'a> Members<'a> {
+    /// Verifies that value is an object with no members other than `allowed`.
+    fn new(value: &'a Json, context: String, allowed: &[&str]) -> Result<Self, String> {
+        let members = match value {
+            Json::Object(members) => members,
+            _ => {
+                return Err(format!(
+                    "{}: expected object, found {}",
+                    context,
+                    value.type_name()
+                ))
+            }
+        };
+        for (name, _) in members {
+            if !allowed.contains(&name.as_str()) {
+                return Err(format!("{}: unknown member \"{}\"", context, name));
+            }
+        }
+        return Ok(Self { context, members });
+    }
+
+    fn get(&self, name: &str) -> Option<&'a Json> {
+        return self.members.iter().find(|(n, _)| n == name).map(|(_, v)| v);
+    }
+
+    fn str(&self, name: &str) -> Result<Option<&'a str>, String> {
+        return match self.get(name) {
+            None => Ok(None),
+            Some(Json::String(value)) => Ok(Some(value)),
+            Some(value) => Err(format!(
+                "{}: \"{}\" must be a string, found {}",
+                self.context,
+                name,
+                value.type_name()
+            )),
+        };
+    }
+
+    fn required_str(&self, name: &str) -> Result<&'a str, String> {
+        return self
+            .str(name)?
+            .ok_or_else(|| format!("{}: missing \"{}\"", self.context, name));
+    }
+
+    /// Reads an integer given as a JSON number or as a string (decimal or 0x hex).
+    fn int(&self, name: &str, max: u64) -> Result<Option<u64>, String> {
+        let text = match self.get(name) {
+            None => return Ok(None),
+            Some(Json::Number(text)) | Some(Json::String(text)) => text,
+            Some(value) => {
+                return Err(format!(
+                    "{}: \"{}\" must be an integer, found {}",
+                    self.context,
+                    name,
+                    value.type_name()
+                ))
+            }
+        };
+        return match parse_int(text) {
+            Some(value) if value <= max => Ok(Some(value)),
+            _ => Err(format!(
+                "{}: \"{}\" must be an integer from 0 to {}",
+                self.context, name, max
+            )),
+        };
+    }
+
+    /// Reads an enum value given as a name from `known_values` or as an integer.
+    fn enum_value(&self, name: &str, known_values: &[&str]) -> Result<Option<String>, String> {
+        return match self.get(name) {
+            Some(Json::String(value)) if known_values.contains(&value.as_str()) => {
+                Ok(Some(value.clone()))
+            }
+            Some(Json::String(value)) => Err(format!(
+                "{}: unknown {} \"{}\" (expected one of {:?} or an integer)",
+                self.context, name, value, known_values
+            )),
+            _ => Ok(self.int(name, 255)?.map(|value| value.to_string())),
+        };
+    }
+
+    fn array(&self, name: &str) -> Result<&'a [Json], String> {
+        return match self.get(name) {
+            None => Ok(&[]),
+            Some(Json::Array(items)) => Ok(items),
+            Some(value) => Err(format!(
+                "{}: \"{}\" must be an array, found {}",
+                self.context,
+                name,
+                value.type_name()
+            )),
+        };
+    }
+}
+
+/// Returns the Rust parameter type for a write_event! field type, or None if the
+/// field type can't be used in a catalog (e.g. struct, raw, and array field types).
+fn param_type(option: &FieldOption) -> Option<String> {
+    let mut value_type = String::new();
+    for part in option.value_type {
+        value_type.push_str("::");
+        value_type.push_str(part);
+    }
+    if option.value_array_count != 0 {
+        value_type = format!("[{}; {}]", value_type, option.value_array_count);
+    }
+
+    return match option.strategy {
+        FieldStrategy::Scalar | FieldStrategy::Time32 | FieldStrategy::Time64 => {
+            Some(format!("&{}", value_type))
+        }
+        FieldStrategy::SystemTime => Some(String::from("&::std::time::SystemTime")),
+        FieldStrategy::Optional => Some(format!("&::core::option::Option<{}>", value_type)),
+        FieldStrategy::CStr | FieldStrategy::Counted if option.value_array_count != 0 => {
+            Some(format!("&{}", value_type))
+        }
+        FieldStrategy::CStr | FieldStrategy::Counted
+            if option.option_name.starts_with("str8")
+                || option.option_name.starts_with("cstr8") =>
+        {
+            Some(String::from("&str"))
+        }
+        FieldStrategy::Slice
+        | FieldStrategy::Sid
+        | FieldStrategy::CStr
+        | FieldStrategy::Counted => Some(format!("&[{}]", value_type)),
+        _ => None,
+    };
+}
+
+pub fn include_event_catalog(arg_span: Span, arg_tokens: TokenStream) -> TokenStream {
+    return include_generated(
+        arg_span,
+        arg_tokens,
+        "expected event catalog path (string literal relative to the crate root), e.g. include_event_catalog!(\"src/events.json\")",
+        |path, text| {
+            JsonParser::parse_document(text)
+                .and_then(|root| generate(&root))
+                .map_err(|e| format!("event catalog {}: {}", path, e))
+        },
+    );
+}
+
+/// Generates Rust source code for the provider and events in the catalog.
+fn generate(root: &Json) -> Result<String, String> {
+    let catalog = Members::new(
+        root,
+        String::from("catalog"),
+        &["provider", "symbol", "id", "group_id", "keywords", "events"],
+    )?;
+    let prov_name = catalog.required_str("provider")?;
+    let prov_symbol = to_upper_snake_case(catalog.str("symbol")?.unwrap_or(prov_name));
+
+    let mut code = String::new();
+    let _ = write!(
+        code,
+        "::tracelogging::define_provider!({}, {:?}",
+        prov_symbol, prov_name
+    );
+    if let Some(id) = catalog.str("id")? {
+        let _ = write!(code, ", id({:?})", id);
+    }
+    if let Some(group_id) = catalog.str("group_id")? {
+        let _ = write!(code, ", group_id({:?})", group_id);
+    }
+
+    // Keywords
+
+    let mut keywords: Vec<(&str, u64)> = Vec::new();
+    if let Some(value) = catalog.get("keywords") {
+        let keyword_members = match value {
+            Json::Object(members) => Members {
+                context: String::from("keywords"),
+                members,
+            },
+            _ => {
+                return Err(format!(
+                    "keywords: expected object, found {}",
+                    value.type_name()
+                ))
+            }
+        };
+        let mut keyword_decls = String::new();
+        for (name, _) in keyword_members.members {
+            let mask = keyword_members.int(name, u64::MAX)?.unwrap_or_default();
+            keywords.push((name, mask));
+            let _ = write!(
+                keyword_decls,
+                ", {} = 0x{:X}",
+                to_upper_snake_case(name),
+                mask
+            );
+        }
+        if !keyword_decls.is_empty() {
+            let _ = write!(
+                code,
+                ", keywords({})",
+                keyword_decls.trim_start_matches(", ")
+            );
+        }
+    }
+
+    code.push_str(");\n");
+
+    // Events
+
+    let mut function_names = Vec::new();
+    for (event_index, event_value) in catalog.array("events")?.iter().enumerate() {
+        let event = Members::new(
+            event_value,
+            format!("events[{}]", event_index),
+            &[
+                "name",
+                "function",
+                "description",
+                "level",
+                "keywords",
+                "id",
+                "version",
+                "opcode",
+                "task",
+                "tag",
+                "fields",
+            ],
+        )?;
+        let event_name = event.required_str("name")?;
+        let event = Members {
+            context: format!("event {}", event_name),
+            members: event.members,
+        };
+
+        let function_name = match event.str("function")? {
+            Some(name) => String::from(name),
+            None => to_snake_case(event_name),
+        };
+        if function_names.contains(&function_name) {
+            return Err(format!(
+                "{}: duplicate function name {}",
+                event.context, function_name
+            ));
+        }
+        function_names.push(function_name.clone());
+
+        let mut options = String::new();
+        if let Some(level) = event.enum_value("level", LEVEL_ENUMS)? {
+            let _ = write!(options, ", level({})", level);
+        }
+        let mut keyword = 0u64;
+        for keyword_value in event.array("keywords")? {
+            keyword |= match keyword_value {
+                Json::String(name) => {
+                    keywords
+                        .iter()
+                        .find(|k| k.0 == name)
+                        .ok_or_else(|| format!("{}: unknown keyword {}", event.context, name))?
+                        .1
+                }
+                _ => return Err(format!("{}: keywords must be keyword names", event.context)),
+            };
+        }
+        if keyword != 0 {
+            let _ = write!(options, ", keyword(0x{:X})", keyword);
+        }
+        let id = event.int("id", 65535)?;
+        let version = event.int("version", 255)?;
+        if id.is_some() || version.is_some() {
+            let _ = write!(
+                options,
+                ", id_version({}, {})",
+                id.unwrap_or_default(),
+                version.unwrap_or_default()
+            );
+        }
+        if let Some(opcode) = event.enum_value("opcode", OPCODE_ENUMS)? {
+            let _ = write!(options, ", opcode({})", opcode);
+        }
+        if let Some(task) = event.int("task", 65535)? {
+            let _ = write!(options, ", task({})", task);
+        }
+        if let Some(tag) = event.int("tag", 0x0FFFFFFF)? {
+            let _ = write!(options, ", tag(0x{:X})", tag);
+        }
+
+        // Fields
+
+        let mut params = String::new();
+        let mut fields = String::new();
+        let mut param_docs = String::new();
+        let mut param_names: Vec<String> = Vec::new();
+        for (field_index, field_value) in event.array("fields")?.iter().enumerate() {
+            let field = Members::new(
+                field_value,
+                format!("{} fields[{}]", event.context, field_index),
+                &["name", "type", "format", "tag", "description"],
+            )?;
+            let field_name = field.required_str("name")?;
+            let field_type = field.required_str("type")?;
+            let param_type = FIELD_OPTIONS
+                .binary_search_by(|o| o.option_name.cmp(field_type))
+                .ok()
+                .and_then(|index| param_type(&FIELD_OPTIONS[index]))
+                .ok_or_else(|| {
+                    format!(
+                        "{}: field {} has unsupported type \"{}\"",
+                        event.context, field_name, field_type
+                    )
+                })?;
+
+            let mut param_name = to_snake_case(field_name);
+            while param_names.contains(&param_name) {
+                param_name.push('_');
+            }
+
+            let mut field_options = String::new();
+            if let Some(format) = field.str("format")? {
+                if format.is_empty() || !format.chars().all(|ch| ch.is_ascii_alphanumeric()) {
+                    return Err(format!(
+                        "{}: field {} has invalid format \"{}\"",
+                        event.context, field_name, format
+                    ));
+                }
+                let _ = write!(field_options, ", format({})", format);
+            }
+            if let Some(tag) = field.int("tag", 0x0FFFFFFF)? {
+                let _ = write!(field_options, ", tag(0x{:X})", tag);
+            }
+
+            if let Some(description) = field.str("description")? {
+                let _ = write!(param_docs, "\n- `{}`: {}", param_name, description);
+            }
+
+            let _ = write!(params, "{}: {}, ", param_name, param_type);
+            let _ = write!(
+                fields,
+                ", {}({:?}, {}{})",
+                field_type, field_name, param_name, field_options
+            );
+            param_names.push(param_name);
+        }
+
+        let mut doc = format!(
+            "Writes the `{}` event of provider `{}`.",
+            event_name, prov_name
+        );
+        if let Some(description) = event.str("description")? {
+            let _ = write!(doc, "\n\n{}", description);
+        }
+        if !param_docs.is_empty() {
+            let _ = write!(doc, "\n{}", param_docs);
+        }
+
+        let _ = writeln!(
+            code,
+            "#[doc = {:?}]\n\
+            #[allow(dead_code, clippy::too_many_arguments)]\n\
+            pub fn {}({}) -> u32 {{\n    \
+            ::tracelogging::write_event!({}, {:?}{}{})\n\
+            }}",
+            doc,
+            function_name,
+            params.trim_end_matches(", "),
+            prov_symbol,
+            event_name,
+            options,
+            fields,
+        );
+    }
+
+    return Ok(code);
+}
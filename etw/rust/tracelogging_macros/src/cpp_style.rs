@@ -0,0 +1,421 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Translates the arguments of `write_event_cpp_style!` (C++ `TraceLoggingWrite`
+//! syntax) into the arguments of `write_event!`.
+
+use proc_macro::*;
+use std::iter;
+
+use crate::errors::Errors;
+use crate::field_options::FIELD_OPTIONS;
+use crate::parser::{ArgConstraints::*, ArgResult, Parser};
+use crate::tree::Tree;
+
+const CPP_PREFIX: &str = "TraceLogging";
+
+/// How the arguments of a C++ field macro map to the arguments of a write_event! field.
+#[derive(Clone, Copy, PartialEq)]
+enum CppArgs {
+    /// `TraceLoggingX(value, [name], [description], [tag])`
+    Value,
+    /// `TraceLoggingX(value, count, [name], [description], [tag])`
+    Counted,
+}
+
+/// C++ field macro names (without the `TraceLogging` prefix) and the corresponding
+/// write_event! field types. `TraceLoggingXArray` and `TraceLoggingXFixedArray` map
+/// to `TYPE_slice` and `TYPE_array` for each `Value` entry that has a `TYPE_slice`.
+/// List must be strcmp-sorted by C++ name (for binary search).
+#[rustfmt::skip]
+const CPP_FIELDS: &[(&str, &str, CppArgs)] = &[
+    ("Binary",              "binary",           CppArgs::Counted),
+    ("Bool",                "bool32",           CppArgs::Value),
+    ("Boolean",             "bool8",            CppArgs::Value),
+    ("Char",                "char8_cp1252",     CppArgs::Value),
+    ("Char16",              "char16",           CppArgs::Value),
+    ("CodePointer",         "codepointer",      CppArgs::Value),
+    ("CountedString",       "str8_cp1252",      CppArgs::Counted),
+    ("CountedUtf8String",   "str8",             CppArgs::Counted),
+    ("CountedWideString",   "str16",            CppArgs::Counted),
+    ("FileTime",            "win_filetime",     CppArgs::Value),
+    ("Float32",             "f32",              CppArgs::Value),
+    ("Float64",             "f64",              CppArgs::Value),
+    ("Guid",                "guid",             CppArgs::Value),
+    ("HResult",             "hresult",          CppArgs::Value),
+    ("HexInt16",            "i16_hex",          CppArgs::Value),
+    ("HexInt32",            "i32_hex",          CppArgs::Value),
+    ("HexInt64",            "i64_hex",          CppArgs::Value),
+    ("HexInt8",             "i8_hex",           CppArgs::Value),
+    ("HexIntPtr",           "isize_hex",        CppArgs::Value),
+    ("HexLong",             "i32_hex",          CppArgs::Value),
+    ("HexUInt16",           "u16_hex",          CppArgs::Value),
+    ("HexUInt32",           "u32_hex",          CppArgs::Value),
+    ("HexUInt64",           "u64_hex",          CppArgs::Value),
+    ("HexUInt8",            "u8_hex",           CppArgs::Value),
+    ("HexUIntPtr",          "usize_hex",        CppArgs::Value),
+    ("HexULong",            "u32_hex",          CppArgs::Value),
+    ("IPv4Address",         "ipv4",             CppArgs::Value),
+    ("IPv6Address",         "ipv6",             CppArgs::Value),
+    ("Int16",               "i16",              CppArgs::Value),
+    ("Int32",               "i32",              CppArgs::Value),
+    ("Int64",               "i64",              CppArgs::Value),
+    ("Int8",                "i8",               CppArgs::Value),
+    ("IntPtr",              "isize",            CppArgs::Value),
+    ("Long",                "i32",              CppArgs::Value),
+    ("NTStatus",            "win_ntstatus",     CppArgs::Value),
+    ("Pid",                 "pid",              CppArgs::Value),
+    ("Pointer",             "pointer",          CppArgs::Value),
+    ("Port",                "port",             CppArgs::Value),
+    ("Sid",                 "win_sid",          CppArgs::Value),
+    ("SocketAddress",       "socketaddress",    CppArgs::Counted),
+    ("String",              "cstr8_cp1252",     CppArgs::Value),
+    ("SystemTime",          "win_systemtime",   CppArgs::Value),
+    ("SystemTimeUtc",       "win_systemtime_utc", CppArgs::Value),
+    ("Tid",                 "tid",              CppArgs::Value),
+    ("UInt16",              "u16",              CppArgs::Value),
+    ("UInt32",              "u32",              CppArgs::Value),
+    ("UInt64",              "u64",              CppArgs::Value),
+    ("UInt8",               "u8",               CppArgs::Value),
+    ("UIntPtr",             "usize",            CppArgs::Value),
+    ("ULong",               "u32",              CppArgs::Value),
+    ("Utf8String",          "cstr8",            CppArgs::Value),
+    ("WChar",               "char16",           CppArgs::Value),
+    ("WideString",          "cstr16",           CppArgs::Value),
+    ("WinError",            "win_error",        CppArgs::Value),
+];
+
+/// C++ level and opcode constants and the corresponding write_event! enum names.
+/// List must be strcmp-sorted by C++ name (for binary search).
+#[rustfmt::skip]
+const CPP_ENUMS: &[(&str, &str)] = &[
+    ("WINEVENT_LEVEL_CRITICAL",     "Critical"),
+    ("WINEVENT_LEVEL_ERROR",        "Error"),
+    ("WINEVENT_LEVEL_INFO",         "Informational"),
+    ("WINEVENT_LEVEL_LOG_ALWAYS",   "LogAlways"),
+    ("WINEVENT_LEVEL_VERBOSE",      "Verbose"),
+    ("WINEVENT_LEVEL_WARNING",      "Warning"),
+    ("WINEVENT_OPCODE_DC_START",    "DC_Start"),
+    ("WINEVENT_OPCODE_DC_STOP",     "DC_Stop"),
+    ("WINEVENT_OPCODE_EXTENSION",   "Extension"),
+    ("WINEVENT_OPCODE_INFO",        "Info"),
+    ("WINEVENT_OPCODE_RECEIVE",     "Receive"),
+    ("WINEVENT_OPCODE_REPLY",       "Reply"),
+    ("WINEVENT_OPCODE_RESUME",      "Resume"),
+    ("WINEVENT_OPCODE_SEND",        "Send"),
+    ("WINEVENT_OPCODE_START",       "Start"),
+    ("WINEVENT_OPCODE_STOP",        "Stop"),
+    ("WINEVENT_OPCODE_SUSPEND",     "Suspend"),
+];
+
+/// Translates `PROVIDER, "EventName", TraceLoggingX(...), ...` into
+/// `PROVIDER, "EventName", x(...), ...`. Options that do not start with
+/// `TraceLogging` are passed through to write_event! unchanged.
+pub fn translate(arg_span: Span, arg_tokens: TokenStream) -> Result<TokenStream, TokenStream> {
+    #[cfg(debug_assertions)]
+    {
+        for i in 1..CPP_FIELDS.len() {
+            debug_assert!(CPP_FIELDS[i - 1].0 < CPP_FIELDS[i].0, "{}", CPP_FIELDS[i].0);
+        }
+        for i in 1..CPP_ENUMS.len() {
+            debug_assert!(CPP_ENUMS[i - 1].0 < CPP_ENUMS[i].0, "{}", CPP_ENUMS[i].0);
+        }
+    }
+
+    let mut errors = Errors::new();
+    let mut root_parser = Parser::new(&mut errors, arg_span, arg_tokens);
+    let mut tree = Tree::new(arg_span);
+
+    // provider, event name: passed through (write_event! validates them).
+
+    let provider_tokens = root_parser.next_tokens(
+        RequiredNotLast,
+        "expected identifier for provider symbol, e.g. MY_PROVIDER",
+    );
+    tree.add_tokens(provider_tokens).add_punct(",");
+    let name_tokens = root_parser.next_tokens(
+        Required,
+        "expected string literal for event name, e.g. write_event_cpp_style!(PROVIDER, \"EventName\", ...)",
+    );
+    tree.add_tokens(name_tokens);
+
+    // options
+
+    while let ArgResult::Option(option_ident, mut option_parser) = root_parser.next_arg(false) {
+        let option_name = option_ident.to_string();
+        let span = option_ident.span();
+
+        let cpp_name = match option_name.strip_prefix(CPP_PREFIX) {
+            Some(cpp_name) => cpp_name,
+            None => {
+                // Not a C++ macro. Pass through to write_event! unchanged.
+                let option_tokens =
+                    TokenStream::from_iter(iter::from_fn(|| option_parser.move_next()));
+                tree.add_punct(",")
+                    .add_token(option_ident)
+                    .add_group_paren(option_tokens);
+                continue;
+            }
+        };
+
+        let args = next_args(&mut option_parser);
+        let errors = option_parser.errors();
+        let mut arg_tree = Tree::new(span);
+        let rust_name = match cpp_name {
+            "Description" | "CustomAttribute" => {
+                // Not used by ETW. Ignore.
+                continue;
+            }
+            "Level" | "Opcode" | "Keyword" | "Channel" | "EventTag" | "IdVersion" => {
+                let (rust_name, arg_count) = match cpp_name {
+                    "Level" => ("level", 1),
+                    "Opcode" => ("opcode", 1),
+                    "Keyword" => ("keyword", 1),
+                    "Channel" => ("channel", 1),
+                    "EventTag" => ("tag", 1),
+                    _ => ("id_version", 2),
+                };
+                if args.len() != arg_count {
+                    errors.add(span, "wrong number of arguments");
+                }
+
+                for (i, arg) in args.into_iter().enumerate() {
+                    if i != 0 {
+                        arg_tree.add_punct(",");
+                    }
+                    arg_tree.add_tokens(translate_enum(arg));
+                }
+                String::from(rust_name)
+            }
+            "Struct" => {
+                // TraceLoggingStruct(fieldCount, name, [description], [tag])
+                let mut args = args.into_iter();
+                match (args.next(), args.next()) {
+                    (Some(field_count), Some(name)) => {
+                        arg_tree
+                            .add_tokens(name)
+                            .add_punct(",")
+                            .add_tokens(field_count);
+                        add_tag(&mut arg_tree, args.nth(1));
+                    }
+                    _ => {
+                        errors.add(span, "expected TraceLoggingStruct(fieldCount, \"Name\")");
+                    }
+                }
+                String::from("raw_struct")
+            }
+            "Value" => {
+                // TraceLoggingValue(value, [name], [description], [tag])
+                let mut args = args.into_iter();
+                let value = args.next().unwrap_or_default();
+                let field_type = literal_field_type(&value).unwrap_or_else(|| {
+                    errors.add(
+                        span,
+                        "TraceLoggingValue requires a literal value (write_event_cpp_style! cannot infer the type of an expression); use a typed macro such as TraceLoggingInt32 instead",
+                    );
+                    ""
+                });
+                add_field_args(&mut arg_tree, "", CppArgs::Value, value, args);
+                String::from(field_type)
+            }
+            _ => {
+                let mut args = args.into_iter();
+                match lookup_field_type(cpp_name) {
+                    Some((field_type, kind)) => {
+                        if let Some(value) = args.next() {
+                            add_field_args(&mut arg_tree, &field_type, kind, value, args);
+                        } else {
+                            errors.add(span, "expected field value");
+                        }
+                        field_type
+                    }
+                    None => {
+                        errors.add(span, "unrecognized TraceLogging macro");
+                        String::new()
+                    }
+                }
+            }
+        };
+
+        if errors.is_empty() {
+            tree.add_punct(",")
+                .push_span(span)
+                .add_ident(&rust_name)
+                .add_group_paren(arg_tree.drain())
+                .pop_span();
+        }
+    }
+
+    return if errors.is_empty() {
+        Ok(tree.drain().collect())
+    } else {
+        Err(errors.into_expression())
+    };
+}
+
+/// Reads the comma-separated arguments of an option.
+fn next_args(option_parser: &mut Parser) -> Vec<TokenStream> {
+    let mut args = Vec::new();
+    loop {
+        let arg = option_parser.next_tokens(Optional, "expected argument");
+        if arg.is_empty() {
+            break;
+        }
+        args.push(arg);
+    }
+    return args;
+}
+
+/// Returns the write_event! field type and argument kind for the specified C++ field
+/// macro name (without the `TraceLogging` prefix).
+fn lookup_field_type(cpp_name: &str) -> Option<(String, CppArgs)> {
+    let find = |name: &str| {
+        CPP_FIELDS
+            .binary_search_by(|f| f.0.cmp(name))
+            .ok()
+            .map(|i| CPP_FIELDS[i])
+    };
+
+    if let Some((_, field_type, kind)) = find(cpp_name) {
+        return Some((String::from(field_type), kind));
+    }
+
+    // TraceLoggingXFixedArray(values, count, ...) or TraceLoggingXArray(values, count, ...)
+    let (base_name, suffix) = if let Some(base_name) = cpp_name.strip_suffix("FixedArray") {
+        (base_name, "_array")
+    } else if let Some(base_name) = cpp_name.strip_suffix("Array") {
+        (base_name, "_slice")
+    } else {
+        return None;
+    };
+
+    let (_, field_type, _) = find(base_name).filter(|f| f.2 == CppArgs::Value)?;
+    let slice_type = String::from(field_type) + "_slice";
+    if FIELD_OPTIONS
+        .binary_search_by(|o| o.option_name.cmp(&slice_type))
+        .is_err()
+    {
+        return None;
+    }
+
+    return Some((String::from(field_type) + suffix, CppArgs::Counted));
+}
+
+/// Returns the field type for a TraceLoggingValue literal, e.g. `str8` for `"abc"`,
+/// `i32` for `5`, `u64` for `5u64`, `f64` for `1.5`, `bool8` for `true`.
+fn literal_field_type(value: &TokenStream) -> Option<&'static str> {
+    let mut tokens = value.clone().into_iter().peekable();
+    if let Some(TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() == '-' {
+            tokens.next();
+        }
+    }
+
+    let field_type = match tokens.next()? {
+        TokenTree::Ident(ident) => match ident.to_string().as_str() {
+            "true" | "false" => "bool8",
+            _ => return None,
+        },
+        TokenTree::Literal(literal) => {
+            let text = literal.to_string();
+            let is_hex = text.starts_with("0x");
+            const SUFFIXES: &[&str] = &[
+                "i8", "i16", "i32", "i64", "isize", "u8", "u16", "u32", "u64", "usize", "f32",
+                "f64",
+            ];
+            if text.starts_with('"') {
+                "str8"
+            } else if !text.starts_with(|ch: char| ch.is_ascii_digit()) {
+                return None;
+            } else if let Some(suffix) = SUFFIXES
+                .iter()
+                .find(|s| text.ends_with(*s) && !(is_hex && s.starts_with('f')))
+            {
+                suffix
+            } else if !is_hex && text.contains(['.', 'e', 'E'].as_ref()) {
+                "f64"
+            } else {
+                "i32"
+            }
+        }
+        _ => return None,
+    };
+
+    return if tokens.next().is_none() {
+        Some(field_type)
+    } else {
+        None
+    };
+}
+
+/// Adds the arguments of a write_event! field, i.e. `"Name", value_ref, tag(TAG)`.
+fn add_field_args(
+    arg_tree: &mut Tree,
+    field_type: &str,
+    kind: CppArgs,
+    value: TokenStream,
+    mut args: impl Iterator<Item = TokenStream>,
+) {
+    let count = if kind == CppArgs::Counted {
+        args.next()
+    } else {
+        None
+    };
+
+    // C++ uses the text of the value expression if the name is not specified.
+    let name = args
+        .next()
+        .unwrap_or_else(|| TokenTree::from(Literal::string(&value.to_string())).into());
+    let tag = args.nth(1); // Skip description.
+
+    arg_tree.add_tokens(name).add_punct(",");
+    match count {
+        None => {
+            // &(value)
+            arg_tree.add_punct("&").add_group_paren(value);
+        }
+        Some(count) if field_type.ends_with("_array") => {
+            // count, &(value)
+            arg_tree
+                .add_tokens(count)
+                .add_punct(",")
+                .add_punct("&")
+                .add_group_paren(value);
+        }
+        Some(count) => {
+            // &(value)[..(count) as usize]
+            let index_tokens = TokenStream::from_iter([
+                TokenTree::from(Punct::new('.', Spacing::Joint)),
+                TokenTree::from(Punct::new('.', Spacing::Alone)),
+                TokenTree::from(Group::new(Delimiter::Parenthesis, count)),
+                TokenTree::from(Ident::new("as", Span::call_site())),
+                TokenTree::from(Ident::new("usize", Span::call_site())),
+            ]);
+            arg_tree
+                .add_punct("&")
+                .add_group_paren(value)
+                .add_group_square(index_tokens);
+        }
+    }
+    add_tag(arg_tree, tag);
+}
+
+/// Adds `, tag(TAG)` to the tree if tag is present.
+fn add_tag(tree: &mut Tree, tag: Option<TokenStream>) {
+    if let Some(tag) = tag {
+        tree.add_punct(",").add_ident("tag").add_group_paren(tag);
+    }
+}
+
+/// Replaces a C++ level or opcode constant (e.g. `WINEVENT_LEVEL_INFO`) with the
+/// write_event! enum name (e.g. `Informational`). Other tokens are unchanged.
+fn translate_enum(arg: TokenStream) -> TokenStream {
+    let mut tokens = arg.clone().into_iter();
+    if let (Some(TokenTree::Ident(ident)), None) = (tokens.next(), tokens.next()) {
+        let name = ident.to_string();
+        if let Ok(i) = CPP_ENUMS.binary_search_by(|e| e.0.cmp(&name)) {
+            return TokenTree::from(Ident::new(CPP_ENUMS[i].1, ident.span())).into();
+        }
+    }
+    return arg;
+}
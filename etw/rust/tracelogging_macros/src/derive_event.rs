@@ -0,0 +1,499 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Implements `#[derive(TraceLoggingEvent)]`: translates a struct definition into a
+//! `write` method that uses the same code generation as `write_event!`.
+
+use proc_macro::*;
+
+use crate::errors::Errors;
+use crate::event_generator::EventGenerator;
+use crate::event_info::EventInfo;
+use crate::strings::*;
+use crate::tree::Tree;
+
+const ATTRIBUTE_NAME: &str = "event";
+const PROVIDER_PARAM: &str = "provider";
+
+const WRITE_DOC: &str = " Writes this value as an event (generated by `#[derive(TraceLoggingEvent)]`).\n Returns 0 for success or a Win32 error code, as with `write_event!`.";
+const EXPECTED_STRUCT: &str =
+    "TraceLoggingEvent can only be derived for a struct with named fields";
+const UNSUPPORTED_TYPE: &str =
+    "unsupported field type for TraceLoggingEvent: specify the field type with #[event(TYPE)], e.g. #[event(str8)], or skip the field with #[event(skip)]";
+
+/// Rust type names that map directly to a write_event! scalar field type.
+/// The field type supports the `TYPE_slice` and `opt_TYPE` variants.
+/// List must be strcmp-sorted by Rust type name (for binary search).
+const SCALAR_TYPES: &[(&str, &str)] = &[
+    ("Guid", "guid"),
+    ("bool", "bool8"),
+    ("char", "char32"),
+    ("f32", "f32"),
+    ("f64", "f64"),
+    ("i16", "i16"),
+    ("i32", "i32"),
+    ("i64", "i64"),
+    ("i8", "i8"),
+    ("isize", "isize"),
+    ("u16", "u16"),
+    ("u32", "u32"),
+    ("u64", "u64"),
+    ("u8", "u8"),
+    ("usize", "usize"),
+];
+
+/// Rust type names that map to a write_event! field type that does not have
+/// `TYPE_slice` or `opt_TYPE` variants.
+/// List must be strcmp-sorted by Rust type name (for binary search).
+const OTHER_TYPES: &[(&str, &str)] = &[
+    ("String", "str8"),
+    ("SystemTime", "systemtime"),
+    ("str", "str8"),
+];
+
+/// Generates:
+/*
+impl<PARAMS> Name<ARGS> where ... {
+    pub fn write(&self, provider: &Provider) -> u32 {
+        write_event!(provider, "Name", OPTIONS..., TYPE("field", &self.field, OPTIONS...), ...)
+    }
+}
+*/
+pub fn derive_trace_logging_event(arg_span: Span, input: TokenStream) -> TokenStream {
+    let mut errors = Errors::new();
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let mut pos = 0;
+
+    // #[event(OPTIONS...)] attributes: write_event! options for the event.
+    let mut event_options = Vec::new();
+    let mut has_keyword = false;
+    while let Some(attribute) = next_attribute(&tokens, &mut pos) {
+        if let Some(options) = attribute_options(&attribute) {
+            for option in split_commas(options) {
+                if let Some(TokenTree::Ident(ident)) = option.first() {
+                    let option_name = ident.to_string();
+                    has_keyword |= option_name == "keyword" || option_name == "keyword_none";
+                }
+                event_options.push(Punct::new(',', Spacing::Alone).into());
+                event_options.extend(option);
+            }
+        }
+    }
+
+    // write_event! defaults to PROVIDER::DEFAULT_KEYWORD, which is not available
+    // since the provider is a parameter. Use the usual default.
+    if !has_keyword {
+        event_options.extend(
+            Tree::new(arg_span)
+                .add_punct(",")
+                .add_ident("keyword")
+                .add_group_paren([TokenTree::from(Literal::u64_unsuffixed(1))])
+                .drain(),
+        );
+    }
+
+    skip_visibility(&tokens, &mut pos);
+    match tokens.get(pos) {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "struct" => pos += 1,
+        _ => {
+            errors.add(arg_span, EXPECTED_STRUCT);
+            return errors.into_items();
+        }
+    }
+
+    let name = match tokens.get(pos) {
+        Some(TokenTree::Ident(ident)) => ident.clone(),
+        _ => {
+            errors.add(arg_span, EXPECTED_STRUCT);
+            return errors.into_items();
+        }
+    };
+    pos += 1;
+
+    // Generic parameters (with bounds) and the corresponding arguments.
+    let mut generic_params = Vec::new();
+    let mut generic_args = Vec::new();
+    if is_punct(tokens.get(pos), '<') {
+        let end = find_generics_end(&tokens, pos);
+        generic_params.extend_from_slice(&tokens[pos..end]);
+        generic_args.push(tokens[pos].clone());
+        for param in split_commas(tokens[pos + 1..end - 1].to_vec()) {
+            if generic_args.len() != 1 {
+                generic_args.push(Punct::new(',', Spacing::Alone).into());
+            }
+            match param.as_slice() {
+                [TokenTree::Punct(quote), TokenTree::Ident(lifetime), ..]
+                    if quote.as_char() == '\'' =>
+                {
+                    generic_args.push(param[0].clone());
+                    generic_args.push(lifetime.clone().into());
+                }
+                [TokenTree::Ident(keyword), TokenTree::Ident(ident), ..]
+                    if keyword.to_string() == "const" =>
+                {
+                    generic_args.push(ident.clone().into());
+                }
+                [TokenTree::Ident(ident), ..] => generic_args.push(ident.clone().into()),
+                _ => {}
+            }
+        }
+        generic_args.push(tokens[end - 1].clone());
+        pos = end;
+    }
+
+    // where clause (everything up to the body).
+    let body_pos = tokens[pos..]
+        .iter()
+        .position(|token| matches!(token, TokenTree::Group(group) if group.delimiter() == Delimiter::Brace))
+        .map(|offset| pos + offset);
+    let body = match body_pos {
+        Some(body_pos) if is_where_clause(&tokens[pos..body_pos]) => match &tokens[body_pos] {
+            TokenTree::Group(group) => group.stream(),
+            _ => unreachable!(),
+        },
+        _ => {
+            errors.add(name.span(), EXPECTED_STRUCT);
+            return errors.into_items();
+        }
+    };
+    let where_clause = &tokens[pos..body_pos.unwrap()];
+
+    // write_event!(provider, "Name", OPTIONS..., FIELDS...)
+    let mut tree = Tree::new(arg_span);
+    tree.add_ident(PROVIDER_PARAM)
+        .add_punct(",")
+        .add_literal(Literal::string(&name.to_string()))
+        .add_tokens(event_options);
+
+    for field in split_commas(body.into_iter().collect()) {
+        add_field(&mut tree, &mut errors, &field);
+    }
+
+    if !errors.is_empty() {
+        return errors.into_items();
+    }
+
+    let write_tokens = match EventInfo::try_from_tokens(arg_span, tree.drain().collect()) {
+        // The error tokens are an expression, so they become the body of write.
+        Err(error_tokens) => error_tokens,
+        Ok(mut event) => {
+            event.export_schema = false;
+            event.check_keywords_mask = false;
+            EventGenerator::new(arg_span).generate(event)
+        }
+    };
+
+    let mut scratch_tree = Tree::new(arg_span);
+    return tree
+        .add_ident("impl")
+        .add_tokens(generic_params)
+        .add_token(name)
+        .add_tokens(generic_args)
+        .add_tokens(where_clause.iter().cloned())
+        .add_group_curly(
+            scratch_tree
+                // #[doc = "..."]
+                .add_punct("#")
+                .add_group_square(
+                    Tree::new(arg_span)
+                        .add_ident("doc")
+                        .add_punct("=")
+                        .add_literal(Literal::string(WRITE_DOC))
+                        .drain(),
+                )
+                // pub fn write(&self, provider: &Provider) -> u32 { write_tokens }
+                .add_ident("pub")
+                .add_ident("fn")
+                .add_ident("write")
+                .add_group_paren(
+                    Tree::new(arg_span)
+                        .add_punct("&")
+                        .add_ident("self")
+                        .add_punct(",")
+                        .add_ident(PROVIDER_PARAM)
+                        .add_punct(":")
+                        .add_punct("&")
+                        .add_path(PROVIDER_PATH)
+                        .drain(),
+                )
+                .add_punct("->")
+                .add_path(U32_PATH)
+                .add_group_curly(write_tokens)
+                .drain(),
+        )
+        .drain()
+        .collect();
+}
+
+/// Adds `TYPE("name", &self.name, OPTIONS...),` for a field, unless the field has
+/// `#[event(skip)]`.
+fn add_field(tree: &mut Tree, errors: &mut Errors, field: &[TokenTree]) {
+    let mut pos = 0;
+    let mut type_override = None;
+    let mut field_options = Vec::new();
+    while let Some(attribute) = next_attribute(field, &mut pos) {
+        let options = match attribute_options(&attribute) {
+            Some(options) => options,
+            None => continue,
+        };
+
+        for option in split_commas(options) {
+            match option.as_slice() {
+                [TokenTree::Ident(ident)] if ident.to_string() == "skip" => return,
+                [TokenTree::Ident(ident)] => type_override = Some(ident.clone()),
+                _ => {
+                    field_options.push(Punct::new(',', Spacing::Alone).into());
+                    field_options.extend(option);
+                }
+            }
+        }
+    }
+
+    skip_visibility(field, &mut pos);
+    let (ident, field_type) = match &field[pos..] {
+        [TokenTree::Ident(ident), TokenTree::Punct(colon), field_type @ ..]
+            if colon.as_char() == ':' && !field_type.is_empty() =>
+        {
+            (ident, field_type)
+        }
+        _ => {
+            let span = field
+                .get(pos)
+                .map_or(Span::call_site(), |token| token.span());
+            errors.add(span, EXPECTED_STRUCT);
+            return;
+        }
+    };
+
+    let type_ident = match type_override {
+        Some(type_ident) => type_ident,
+        None => match infer_field_type(field_type) {
+            Some(type_name) => Ident::new(&type_name, ident.span()),
+            None => {
+                errors.add(field_type[0].span(), UNSUPPORTED_TYPE);
+                return;
+            }
+        },
+    };
+
+    let ident_str = ident.to_string();
+    let field_name = ident_str.strip_prefix("r#").unwrap_or(&ident_str);
+
+    // , TYPE("name", &self.name, OPTIONS...)
+    let mut args = Tree::new(ident.span());
+    args.add_literal(Literal::string(field_name))
+        .add_punct(",")
+        .add_punct("&")
+        .add_ident("self")
+        .add_punct(".")
+        .add_token(ident.clone())
+        .add_tokens(field_options);
+    tree.add_punct(",")
+        .add_token(type_ident)
+        .add_group_paren(args.drain());
+}
+
+/// Returns the write_event! field type for a Rust type, or None if the type is not
+/// recognized.
+fn infer_field_type(field_type: &[TokenTree]) -> Option<String> {
+    let field_type = strip_reference(field_type);
+    if let [TokenTree::Group(group)] = field_type {
+        if group.delimiter() == Delimiter::Bracket {
+            // [T] or [T; N]
+            let element: Vec<TokenTree> = group
+                .stream()
+                .into_iter()
+                .take_while(|token| !is_punct(Some(token), ';'))
+                .collect();
+            return sequence_field_type(&element);
+        }
+    }
+
+    let (type_name, type_args) = split_type_path(field_type)?;
+    return match type_name.as_str() {
+        "Vec" => sequence_field_type(type_args),
+        "Option" => {
+            let (arg_name, arg_args) = split_type_path(type_args)?;
+            if !arg_args.is_empty() {
+                None
+            } else {
+                lookup(SCALAR_TYPES, &arg_name).map(|scalar| format!("opt_{}", scalar))
+            }
+        }
+        _ if type_args.is_empty() => lookup(SCALAR_TYPES, &type_name)
+            .or_else(|| lookup(OTHER_TYPES, &type_name))
+            .map(String::from),
+        _ => None,
+    };
+}
+
+/// Returns the write_event! field type for a sequence of the specified element type.
+fn sequence_field_type(element_type: &[TokenTree]) -> Option<String> {
+    let (element_name, element_args) = split_type_path(element_type)?;
+    return if !element_args.is_empty() {
+        None
+    } else if element_name == "u8" {
+        Some(String::from("binary"))
+    } else {
+        lookup(SCALAR_TYPES, &element_name).map(|scalar| format!("{}_slice", scalar))
+    };
+}
+
+fn lookup(table: &[(&str, &'static str)], type_name: &str) -> Option<&'static str> {
+    return table
+        .binary_search_by(|(rust_name, _)| (*rust_name).cmp(type_name))
+        .ok()
+        .map(|index| table[index].1);
+}
+
+/// Strips leading `&`, `&'a`, and `&mut` from a type.
+fn strip_reference(mut field_type: &[TokenTree]) -> &[TokenTree] {
+    while is_punct(field_type.first(), '&') {
+        field_type = &field_type[1..];
+        if is_punct(field_type.first(), '\'') {
+            field_type = &field_type[2.min(field_type.len())..];
+        }
+        if matches!(field_type.first(), Some(TokenTree::Ident(ident)) if ident.to_string() == "mut")
+        {
+            field_type = &field_type[1..];
+        }
+    }
+    return field_type;
+}
+
+/// Splits `path::Name<ARGS>` into the last path segment (`Name`) and the generic
+/// arguments (`ARGS`, empty if none). Returns None if the type is not a path.
+fn split_type_path(field_type: &[TokenTree]) -> Option<(String, &[TokenTree])> {
+    let args_start = field_type
+        .iter()
+        .position(|token| is_punct(Some(token), '<'))
+        .unwrap_or(field_type.len());
+
+    let mut type_name = None;
+    for token in &field_type[..args_start] {
+        match token {
+            TokenTree::Ident(ident) => type_name = Some(ident.to_string()),
+            TokenTree::Punct(punct) if punct.as_char() == ':' => {}
+            _ => return None,
+        }
+    }
+
+    let type_args = if args_start == field_type.len() {
+        &field_type[args_start..]
+    } else if is_punct(field_type.last(), '>') {
+        &field_type[args_start + 1..field_type.len() - 1]
+    } else {
+        return None;
+    };
+
+    return Some((type_name?, type_args));
+}
+
+/// If `tokens[*pos..]` starts with an outer attribute `#[...]`, advances `pos` past it
+/// and returns the attribute's contents.
+fn next_attribute(tokens: &[TokenTree], pos: &mut usize) -> Option<Vec<TokenTree>> {
+    return match &tokens[*pos..] {
+        [TokenTree::Punct(pound), TokenTree::Group(group), ..]
+            if pound.as_char() == '#' && group.delimiter() == Delimiter::Bracket =>
+        {
+            *pos += 2;
+            Some(group.stream().into_iter().collect())
+        }
+        _ => None,
+    };
+}
+
+/// If the attribute is `event(...)`, returns the tokens inside the parentheses.
+fn attribute_options(attribute: &[TokenTree]) -> Option<Vec<TokenTree>> {
+    return match attribute {
+        [TokenTree::Ident(ident), TokenTree::Group(group)]
+            if ident.to_string() == ATTRIBUTE_NAME
+                && group.delimiter() == Delimiter::Parenthesis =>
+        {
+            Some(group.stream().into_iter().collect())
+        }
+        _ => None,
+    };
+}
+
+/// Skips `pub` or `pub(...)`.
+fn skip_visibility(tokens: &[TokenTree], pos: &mut usize) {
+    if let Some(TokenTree::Ident(ident)) = tokens.get(*pos) {
+        if ident.to_string() == "pub" {
+            *pos += 1;
+            if let Some(TokenTree::Group(group)) = tokens.get(*pos) {
+                if group.delimiter() == Delimiter::Parenthesis {
+                    *pos += 1;
+                }
+            }
+        }
+    }
+}
+
+/// Splits tokens at commas that are not inside `<...>`, ignoring empty items (e.g.
+/// after a trailing comma).
+fn split_commas(tokens: Vec<TokenTree>) -> Vec<Vec<TokenTree>> {
+    let mut items = Vec::new();
+    let mut item = Vec::new();
+    let mut depth = 0u32;
+    for token in tokens {
+        if let TokenTree::Punct(punct) = &token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' if !is_arrow(&item) => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    if !item.is_empty() {
+                        items.push(item);
+                        item = Vec::new();
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        item.push(token);
+    }
+
+    if !item.is_empty() {
+        items.push(item);
+    }
+    return items;
+}
+
+/// Returns the position after the `>` that matches the `<` at `tokens[start]`.
+fn find_generics_end(tokens: &[TokenTree], start: usize) -> usize {
+    let mut depth = 0u32;
+    for (pos, token) in tokens.iter().enumerate().skip(start) {
+        if let TokenTree::Punct(punct) = token {
+            match punct.as_char() {
+                '<' => depth += 1,
+                '>' if !is_arrow(&tokens[..pos]) => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return pos + 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    return tokens.len();
+}
+
+/// Returns true if the tokens are empty or start with `where`.
+fn is_where_clause(tokens: &[TokenTree]) -> bool {
+    return match tokens.first() {
+        None => true,
+        Some(TokenTree::Ident(ident)) => ident.to_string() == "where",
+        Some(_) => false,
+    };
+}
+
+/// Returns true if the last token is the `-` of a `->`.
+fn is_arrow(preceding: &[TokenTree]) -> bool {
+    return matches!(preceding.last(), Some(TokenTree::Punct(punct)) if punct.as_char() == '-' && punct.spacing() == Spacing::Joint);
+}
+
+fn is_punct(token: Option<&TokenTree>, ch: char) -> bool {
+    return matches!(token, Some(TokenTree::Punct(punct)) if punct.as_char() == ch);
+}
@@ -46,7 +46,9 @@ pub enum InType {
 }
 
 impl InType {
+    pub const ConstantCountFlag: u8 = 0x20;
     pub const VariableCountFlag: u8 = 0x40;
+    pub const CustomFlag: u8 = 0x60;
 
     pub const fn to_token(self) -> EnumToken {
         match self {
@@ -56,6 +58,41 @@ impl InType {
             other => EnumToken::U8(other as u8),
         }
     }
+
+    /// Returns the name of the corresponding `tracelogging::InType` constant.
+    pub const fn name(self) -> &'static str {
+        match self {
+            InType::Invalid => "Invalid",
+            InType::CStr16 => "CStr16",
+            InType::CStr8 => "CStr8",
+            InType::I8 => "I8",
+            InType::U8 => "U8",
+            InType::I16 => "I16",
+            InType::U16 => "U16",
+            InType::I32 => "I32",
+            InType::U32 => "U32",
+            InType::I64 => "I64",
+            InType::U64 => "U64",
+            InType::F32 => "F32",
+            InType::F64 => "F64",
+            InType::Bool32 => "Bool32",
+            InType::Binary => "Binary",
+            InType::Guid => "Guid",
+            InType::_HexSizePlatformSpecific => "HexSize",
+            InType::FileTime => "FileTime",
+            InType::SystemTime => "SystemTime",
+            InType::Sid => "Sid",
+            InType::Hex32 => "Hex32",
+            InType::Hex64 => "Hex64",
+            InType::Str16 => "Str16",
+            InType::Str8 => "Str8",
+            InType::Struct => "Struct",
+            InType::BinaryC => "CountedBinary",
+            InType::ISize => "ISize",
+            InType::USize => "USize",
+            InType::HexSize => "HexSize",
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -88,4 +125,34 @@ pub enum OutType {
 
 impl OutType {
     pub const TypeMask: u8 = 0x7F;
+
+    /// Returns the name of the corresponding `tracelogging::OutType` constant.
+    pub const fn name(self) -> &'static str {
+        match self {
+            OutType::Default => "Default",
+            OutType::_NoPrint => "NoPrint",
+            OutType::String => "String",
+            OutType::Boolean => "Boolean",
+            OutType::Hex => "Hex",
+            OutType::Pid => "Pid",
+            OutType::Tid => "Tid",
+            OutType::Port => "Port",
+            OutType::IPv4 => "IPv4",
+            OutType::IPv6 => "IPv6",
+            OutType::SocketAddress => "SocketAddress",
+            OutType::Xml => "Xml",
+            OutType::Json => "Json",
+            OutType::Win32Error => "Win32Error",
+            OutType::NtStatus => "NtStatus",
+            OutType::HResult => "HResult",
+            OutType::_DateTime => "DateTime",
+            OutType::_Signed => "Signed",
+            OutType::_Unsigned => "Unsigned",
+            OutType::_DateTimeCultureInsensitive => "DateTimeCultureInsensitive",
+            OutType::Utf8 => "Utf8",
+            OutType::_Pkcs7WithTypeInfo => "Pkcs7WithTypeInfo",
+            OutType::CodePointer => "CodePointer",
+            OutType::DateTimeUtc => "DateTimeUtc",
+        }
+    }
 }
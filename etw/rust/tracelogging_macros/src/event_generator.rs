@@ -12,9 +12,11 @@ use crate::strings::*;
 use crate::tree::Tree;
 
 use crate::event_info::EventInfo;
+use crate::event_ir::event_ir_json;
 
 pub struct EventGenerator {
-    /// tokens for declaring the _TLG_TAGn constants.
+    /// tokens for declaring the _TLG_TAGn, _TLG_COUNTn, _TLG_PROTOCOLn, and
+    /// _TLG_SCHEMAn constants.
     tags_tree: Tree,
     /// tokens in the _TlgMeta(...) type definition.
     meta_type_tree: Tree,
@@ -38,6 +40,12 @@ pub struct EventGenerator {
     tree3: Tree,
     /// "_TLG_TAGn"
     tag_n: IdentBuilder,
+    /// "_TLG_COUNTn"
+    count_n: IdentBuilder,
+    /// "_TLG_PROTOCOLn"
+    protocol_n: IdentBuilder,
+    /// "_TLG_SCHEMAn"
+    schema_n: IdentBuilder,
     /// "_tlg_argN"
     arg_n: IdentBuilder,
     /// Buffered _TlgMeta bytes.
@@ -63,6 +71,9 @@ impl EventGenerator {
             tree2: Tree::new(span),
             tree3: Tree::new(span),
             tag_n: IdentBuilder::new(TLG_TAG_CONST),
+            count_n: IdentBuilder::new(TLG_COUNT_CONST),
+            protocol_n: IdentBuilder::new(TLG_PROTOCOL_CONST),
+            schema_n: IdentBuilder::new(TLG_SCHEMA_CONST),
             arg_n: IdentBuilder::new(TLG_ARG_VAR),
             meta_buffer: Vec::with_capacity(128),
             field_count: 0,
@@ -75,6 +86,13 @@ impl EventGenerator {
         self.field_count = 0;
         self.lengths_count = 0;
 
+        // debug_ir(): describe the event before its fields are consumed.
+        let event_ir = if event.debug_ir {
+            Some(event_ir_json(&event))
+        } else {
+            None
+        };
+
         // Before-field stuff:
 
         // metadata size: u16 = size_of::<_TlgMeta>() as u16
@@ -128,11 +146,12 @@ impl EventGenerator {
 
         // always-present args for the helper function's prototype
         self.func_args_tree
-            // _tlg_prov: &tlg::Provider
+            // _tlg_prov: &impl tlg::_internal::EventTarget (a Provider or a ProviderSet)
             .add_ident(TLG_PROV_VAR)
             .add_punct(":")
             .add_punct("&")
-            .add_path(PROVIDER_PATH)
+            .add_ident("impl")
+            .add_path(EVENT_TARGET_PATH)
             // , _tlg_meta: &[u8]
             .add_punct(",")
             .add_ident(TLG_META_VAR)
@@ -181,12 +200,10 @@ impl EventGenerator {
             // &PROVIDER
             .add_punct("&")
             .add_token(event.provider_symbol.clone())
-            // , tlg::meta_as_bytes(&_tlg_meta)
+            // , &_TLG_META
             .add_punct(",")
-            .add_path_call(
-                META_AS_BYTES_PATH,
-                self.tree1.add_punct("&").add_ident(TLG_META_CONST).drain(),
-            )
+            .add_punct("&")
+            .add_ident(TLG_META_CONST)
             // , &_TLG_DESC
             .add_punct(",")
             .add_punct("&")
@@ -215,12 +232,16 @@ impl EventGenerator {
         const _TLG_DESC = EventDescriptor::from_raw_parts(...);
         tags_tree...
         struct _TlgMeta(meta_type_tree...);
-        const _TLG_META = _TlgMeta(meta_init_tree...);
+        const _TLG_META: [u8; size_of::<_TlgMeta>()] = meta_to_bytes(_TlgMeta(meta_init_tree...));
+        _tlg_export_event!(PROVIDER, _TLG_DESC, _TLG_META, _TLG_COMPILED_IN); // If export_schema.
         fn _tlg_write(func_args_tree...) -> u32 {
             let _tlg_lengths = [lengths_init_tree...];
-            provider_write_transfer(prov, desc, aid, rid, &[data_desc_init_tree...]);
+            provider_write_transfer(prov, desc, aid, rid, &mut [data_desc_init_tree...]);
         }
-        _tlg_write(func_call_tree)
+        const _TLG_SAMPLE_RATE: u64 = N; static _TLG_SAMPLE: EmitPolicy = ...; // If sample_rate(N).
+        if !provider_event_filter(prov, &_TLG_DESC) { 0 }
+        else if Option::is_none(&EmitPolicy::check(&_TLG_SAMPLE)) { 0 } // If sample_rate(N).
+        else { _tlg_write(func_call_tree) }
         */
 
         self.enabled_tree
@@ -258,15 +279,50 @@ impl EventGenerator {
             .add_ident(TLG_META_TYPE)
             .add_group_paren(self.meta_type_tree.drain())
             .add_punct(";")
-            // const _TLG_META: _TlgMeta = _TlgMeta(...);
+            // const _TLG_META: [u8; size_of::<_TlgMeta>()] = meta_to_bytes(_TlgMeta(...));
+            // The metadata is stored as a byte array (not as the call-site-specific
+            // _TlgMeta type) so that identical metadata from different call sites
+            // produces identical constants that the compiler and linker can merge.
             .add_ident("const")
             .add_ident(TLG_META_CONST)
             .add_punct(":")
-            .add_ident(TLG_META_TYPE)
+            .add_group_square(
+                self.tree1
+                    .add_path(U8_PATH)
+                    .add_punct(";")
+                    .add_sizeof(TLG_META_TYPE)
+                    .drain(),
+            )
             .add_punct("=")
-            .add_ident(TLG_META_TYPE)
-            .add_group_paren(self.meta_init_tree.drain())
-            .add_punct(";")
+            .add_path_call(
+                META_TO_BYTES_PATH,
+                self.tree1
+                    .add_ident(TLG_META_TYPE)
+                    .add_group_paren(self.meta_init_tree.drain())
+                    .drain(),
+            )
+            .add_punct(";");
+
+        if event.export_schema {
+            self.enabled_tree
+                // _tlg_export_event!(PROVIDER, _TLG_DESC, _TLG_META, _TLG_COMPILED_IN);
+                .add_path(EXPORT_EVENT_PATH)
+                .add_punct("!")
+                .add_group_paren(
+                    self.tree1
+                        .add_token(event.provider_symbol.clone())
+                        .add_punct(",")
+                        .add_ident(TLG_DESC_CONST)
+                        .add_punct(",")
+                        .add_ident(TLG_META_CONST)
+                        .add_punct(",")
+                        .add_ident(TLG_COMPILED_IN_CONST)
+                        .drain(),
+                )
+                .add_punct(";");
+        }
+
+        self.enabled_tree
             // #[allow(clippy::too_many_arguments)]
             .add_outer_attribute(
                 "allow",
@@ -301,7 +357,7 @@ impl EventGenerator {
                     .add_punct("=")
                     .add_group_square(self.lengths_init_tree.drain())
                     .add_punct(";")
-                    // provider_write_transfer(_tlg_prov, meta, &_TLG_DESC, activity_id, related_id, &[data...])
+                    // provider_write_transfer(_tlg_prov, meta, &_TLG_DESC, activity_id, related_id, &mut [data...])
                     .add_path_call(
                         PROVIDER_WRITE_TRANSFER_PATH,
                         self.tree2
@@ -314,20 +370,71 @@ impl EventGenerator {
                             .add_ident(TLG_RELATED_ID_VAR)
                             .add_punct(",")
                             .add_punct("&")
+                            .add_ident("mut")
                             .add_group_square(self.data_desc_init_tree.drain())
                             .drain(),
                     )
                     .drain(),
+            );
+
+        let sampled = !event.sample_rate.is_empty();
+        if sampled {
+            self.add_sample_rate(event.sample_rate);
+        }
+
+        self.enabled_tree
+            // if !provider_event_filter(&PROVIDER, &_TLG_DESC) { 0 }
+            .add_ident("if")
+            .add_punct("!")
+            .add_path_call(
+                PROVIDER_EVENT_FILTER_PATH,
+                self.tree1
+                    .add_punct("&")
+                    .add_token(event.provider_symbol.clone())
+                    .add_punct(",")
+                    .add_punct("&")
+                    .add_ident(TLG_DESC_CONST)
+                    .drain(),
             )
-            // _tlg_write(prov, meta, aid, rid, values...)
-            .add_ident(TLG_WRITE_FUNC)
-            .add_group_paren(self.func_call_tree.drain());
+            .add_group_curly(self.tree1.add_literal(Literal::u32_suffixed(0)).drain())
+            .add_ident("else");
+
+        if sampled {
+            self.enabled_tree
+                // if Option::is_none(&EmitPolicy::check(&_TLG_SAMPLE)) { 0 } else
+                .add_ident("if")
+                .add_path_call(
+                    OPTION_IS_NONE_PATH,
+                    self.tree1
+                        .add_punct("&")
+                        .add_path_call(
+                            EMIT_POLICY_CHECK_PATH,
+                            self.tree2
+                                .add_punct("&")
+                                .add_ident(TLG_SAMPLE_STATIC)
+                                .drain(),
+                        )
+                        .drain(),
+                )
+                .add_group_curly(self.tree1.add_literal(Literal::u32_suffixed(0)).drain())
+                .add_ident("else");
+        }
+
+        self.enabled_tree
+            // { _tlg_write(prov, meta, aid, rid, values...) }
+            .add_group_curly(
+                self.tree1
+                    .add_ident(TLG_WRITE_FUNC)
+                    .add_group_paren(self.func_call_tree.drain())
+                    .drain(),
+            );
 
         // put it all together:
         /*
         const _TLG_KEYWORD = keywords...;
         const _TLG_LEVEL = level...;
         if(!tlg_prov_var.enabled(_TLG_LEVEL, _TLG_KEYWORD)) {
+            provider_note_write(&tlg_prov_var);
             0
         } else {
             enabled_tree...
@@ -336,7 +443,22 @@ impl EventGenerator {
 
         let event_tree = &mut self.tree2; // Alias tree2 to save a tree.
 
+        if let Some(event_ir) = event_ir {
+            // #[allow(dead_code)] const _TLG_EVENT_IR: &str = "{...}";
+            event_tree
+                .add_outer_attribute("allow", self.tree1.add_ident("dead_code").drain())
+                .add_ident("const")
+                .add_ident(TLG_EVENT_IR_CONST)
+                .add_punct(":")
+                .add_punct("&")
+                .add_path(STR_PATH)
+                .add_punct("=")
+                .add_literal(Literal::string(&event_ir))
+                .add_punct(";");
+        }
+
         // _TLG_KEYWORD
+        let keyword_span = event.keywords[0].context;
         if event.keywords.len() == 1 {
             // Generate simple output if only one keyword.
             // const _TLG_KEYWORD: u64 = KEYWORDS[0];
@@ -382,15 +504,97 @@ impl EventGenerator {
             event_tree.add_const_from_tokens(TLG_KEYWORD_CONST, U64_PATH, self.tree1.drain());
         }
 
+        if event.check_keywords_mask {
+            // const _: () = assert!(_TLG_KEYWORD & !PROVIDER::KEYWORDS_MASK == 0, "...");
+            event_tree
+                .push_span(keyword_span)
+                .add_ident("const")
+                .add_ident("_")
+                .add_punct(":")
+                .add_group_paren([])
+                .add_punct("=")
+                .add_path(ASSERT_PATH)
+                .add_punct("!")
+                .add_group_paren(
+                    self.tree1
+                        .push_span(keyword_span)
+                        .add_ident(TLG_KEYWORD_CONST)
+                        .add_punct("&")
+                        .add_punct("!")
+                        .add_token(event.provider_symbol.clone())
+                        .add_path(&[KEYWORDS_MASK_CONST])
+                        .add_punct("==")
+                        .add_literal(Literal::u64_unsuffixed(0))
+                        .add_punct(",")
+                        .add_literal(Literal::string(
+                            "event keyword has bits outside the provider's keywords_mask",
+                        ))
+                        .pop_span()
+                        .drain(),
+                )
+                .add_punct(";")
+                .pop_span();
+        }
+
         event_tree
             // const _TLG_LEVEL: Level = LEVEL;
             .push_span(event.level.context)
             .add_const_from_tokens(TLG_LEVEL_CONST, LEVEL_PATH, event.level.tokens)
             .pop_span()
-            // if !PROVIDER.enabled(_TLG_LEVEL, _TLG_KEYWORD) { 0 }
+            // const _TLG_COMPILED_IN: bool = compiled_in(_TLG_LEVEL, _TLG_KEYWORD,
+            //     option_env!("TRACELOGGING_MIN_LEVEL"), option_env!("TRACELOGGING_KEYWORD_MASK"));
+            .add_const_from_tokens(
+                TLG_COMPILED_IN_CONST,
+                BOOL_PATH,
+                self.tree1
+                    .add_path_call(
+                        COMPILED_IN_PATH,
+                        self.tree3
+                            .add_ident(TLG_LEVEL_CONST)
+                            .add_punct(",")
+                            .add_ident(TLG_KEYWORD_CONST)
+                            .add_punct(",")
+                            .add_path(OPTION_ENV_PATH)
+                            .add_punct("!")
+                            .add_group_paren([Literal::string(MIN_LEVEL_ENV_VAR).into()])
+                            .add_punct(",")
+                            .add_path(OPTION_ENV_PATH)
+                            .add_punct("!")
+                            .add_group_paren([Literal::string(KEYWORD_MASK_ENV_VAR).into()])
+                            .drain(),
+                    )
+                    .drain(),
+            )
+            // if !_TLG_COMPILED_IN { 0 } else
+            .add_ident("if")
+            .add_punct("!")
+            .add_ident(TLG_COMPILED_IN_CONST)
+            .add_group_curly(self.tree1.add_literal(Literal::u32_suffixed(0)).drain())
+            .add_ident("else");
+
+        if event.require_registered {
+            const ERROR_INVALID_HANDLE: u32 = 6;
+            event_tree
+                // if !PROVIDER.is_registered() { 6 } else
+                .add_ident("if")
+                .add_punct("!")
+                .add_token(event.provider_symbol.clone())
+                .add_punct(".")
+                .add_ident("is_registered")
+                .add_group_paren([])
+                .add_group_curly(
+                    self.tree1
+                        .add_literal(Literal::u32_suffixed(ERROR_INVALID_HANDLE))
+                        .drain(),
+                )
+                .add_ident("else");
+        }
+
+        event_tree
+            // if !PROVIDER.enabled(_TLG_LEVEL, _TLG_KEYWORD) { provider_note_write(&PROVIDER); 0 }
             .add_ident("if")
             .add_punct("!")
-            .add_token(event.provider_symbol)
+            .add_token(event.provider_symbol.clone())
             .add_punct(".")
             .add_ident("enabled")
             .add_group_paren(
@@ -400,17 +604,53 @@ impl EventGenerator {
                     .add_ident(TLG_KEYWORD_CONST)
                     .drain(),
             )
-            .add_group_curly(self.tree1.add_literal(Literal::u32_suffixed(0)).drain())
+            .add_group_curly(
+                self.tree1
+                    .add_path_call(
+                        PROVIDER_NOTE_WRITE_PATH,
+                        self.tree3
+                            .add_punct("&")
+                            .add_token(event.provider_symbol)
+                            .drain(),
+                    )
+                    .add_punct(";")
+                    .add_literal(Literal::u32_suffixed(0))
+                    .drain(),
+            )
             // else { enabled_tree... }
             .add_ident("else")
             .add_group_curly(self.enabled_tree.drain());
 
         // Wrap the event in "{...}":
-        let event_tokens = TokenStream::from(TokenTree::Group(Group::new(
+        let mut event_tokens = TokenStream::from(TokenTree::Group(Group::new(
             Delimiter::Brace,
             event_tree.drain().collect(),
         )));
 
+        if event.activity_start {
+            // { let _tlg_new_aid: Guid = Provider::create_activity_id(); ({...}, _tlg_new_aid) }
+            event_tokens = TokenStream::from(TokenTree::Group(Group::new(
+                Delimiter::Brace,
+                self.tree1
+                    .add_ident("let")
+                    .add_ident(TLG_NEW_ACTIVITY_ID_VAR)
+                    .add_punct(":")
+                    .add_path(GUID_PATH)
+                    .add_punct("=")
+                    .add_path_call(PROVIDER_CREATE_ACTIVITY_ID_PATH, [])
+                    .add_punct(";")
+                    .add_group_paren(
+                        self.tree3
+                            .add_tokens(event_tokens)
+                            .add_punct(",")
+                            .add_ident(TLG_NEW_ACTIVITY_ID_VAR)
+                            .drain(),
+                    )
+                    .drain()
+                    .collect(),
+            )));
+        }
+
         if event.debug {
             println!("{}", event_tokens);
         }
@@ -418,6 +658,52 @@ impl EventGenerator {
         return event_tokens;
     }
 
+    /// Wraps the generated start and stop events for an activity scope:
+    /*
+    {
+        let _tlg_scope = ActivityScope::new(|_tlg_scope_aid: &Guid| -> u32 { stop_tokens });
+        start_tokens;
+        _tlg_scope
+    }
+    */
+    pub fn generate_activity_scope(
+        &mut self,
+        start_tokens: TokenStream,
+        stop_tokens: TokenStream,
+    ) -> TokenStream {
+        self.tree1
+            // let _tlg_scope = ActivityScope::new(...);
+            .add_ident("let")
+            .add_ident(TLG_SCOPE_VAR)
+            .add_punct("=")
+            .add_path_call(
+                ACTIVITY_SCOPE_NEW_PATH,
+                self.tree2
+                    // |_tlg_scope_aid: &Guid| -> u32 { stop_tokens }
+                    .add_punct("|")
+                    .add_ident(TLG_SCOPE_AID_VAR)
+                    .add_punct(":")
+                    .add_punct("&")
+                    .add_path(GUID_PATH)
+                    .add_punct("|")
+                    .add_punct("->")
+                    .add_path(U32_PATH)
+                    .add_group_curly(stop_tokens)
+                    .drain(),
+            )
+            .add_punct(";")
+            // start_tokens;
+            .add_tokens(start_tokens)
+            .add_punct(";")
+            // _tlg_scope
+            .add_ident(TLG_SCOPE_VAR);
+
+        return TokenStream::from(TokenTree::Group(Group::new(
+            Delimiter::Brace,
+            self.tree1.drain().collect(),
+        )));
+    }
+
     fn add_field(&mut self, field: FieldInfo) {
         // Metadata
 
@@ -432,16 +718,25 @@ impl EventGenerator {
             let inflags = (if has_out || has_tag { 0x80 } else { 0 })
                 | (if field.option.strategy.is_slice() {
                     InType::VariableCountFlag
+                } else if let FieldStrategy::Array = field.option.strategy {
+                    InType::ConstantCountFlag
+                } else if let FieldStrategy::RawCustom = field.option.strategy {
+                    InType::CustomFlag
                 } else {
                     0
                 });
-            self.add_typecode_meta(
-                INTYPE_PATH,
-                field.intype_tokens,
-                field.type_name_span,
-                field.option.intype.to_token(),
-                inflags,
-            );
+            if let FieldStrategy::RawCustom = field.option.strategy {
+                self.protocol_n.set_suffix(self.field_count as usize);
+                self.add_custom_protocol(field.intype_tokens, field.type_name_span, inflags);
+            } else {
+                self.add_typecode_meta(
+                    INTYPE_PATH,
+                    field.intype_tokens,
+                    field.type_name_span,
+                    field.option.intype.to_token(),
+                    inflags,
+                );
+            }
 
             if has_out || has_tag {
                 let outflags = if has_tag { 0x80 } else { 0 };
@@ -458,6 +753,22 @@ impl EventGenerator {
                 self.tag_n.set_suffix(self.field_count as usize);
                 self.add_tag(field.tag);
             }
+
+            match field.option.strategy {
+                FieldStrategy::Array => {
+                    self.count_n.set_suffix(self.field_count as usize);
+                    self.add_array_count(field.array_count.clone());
+                }
+                FieldStrategy::Capped => {
+                    self.count_n.set_suffix(self.field_count as usize);
+                    self.add_capped_count(field.array_count.clone());
+                }
+                FieldStrategy::RawCustom => {
+                    self.schema_n.set_suffix(self.field_count as usize);
+                    self.add_custom_schema(field.schema);
+                }
+                _ => {}
+            }
         }
 
         // Data
@@ -508,6 +819,22 @@ impl EventGenerator {
                 self.add_data_desc_for_arg_n(DATADESC_FROM_VALUE_PATH);
             }
 
+            FieldStrategy::CallerAddress => {
+                self.tree1
+                    // , &caller_address(value_tokens...)
+                    .push_span(field.type_name_span) // Use caller_address(...) as a target for error messages.
+                    .add_punct("&")
+                    .add_path_call(CALLER_ADDRESS_PATH, field.value_tokens)
+                    .pop_span();
+
+                // Prototype: , _tlg_argN: &usize
+                // Call site: , &caller_address(value_tokens...)
+                self.add_func_scalar_arg(field.option); // consumes tree1
+
+                // EventDataDescriptor::from_value(_tlg_argN),
+                self.add_data_desc_for_arg_n(DATADESC_FROM_VALUE_PATH);
+            }
+
             FieldStrategy::SystemTime => {
                 self.tree1
                     // match SystemTime::duration_since(value_tokens, SystemTime::UNIX_EPOCH) { ... }
@@ -568,6 +895,38 @@ impl EventGenerator {
                 self.add_data_desc_for_arg_n(DATADESC_FROM_COUNTED_PATH);
             }
 
+            FieldStrategy::ErrorChain => {
+                // Prototype: , _tlg_argN: &[u8]
+                // Call site: , AsRef::<[u8]>::as_ref(&_tlg_error_chain_data!(value_tokens...))
+                let value_tokens = self
+                    .tree1
+                    .push_span(field.type_name_span)
+                    .add_punct("&")
+                    .add_path(ERROR_CHAIN_DATA_PATH)
+                    .add_punct("!")
+                    .add_group_paren(field.value_tokens)
+                    .pop_span()
+                    .drain()
+                    .collect();
+                self.add_func_slice_arg(field.option, field.type_name_span, value_tokens);
+
+                // EventDataDescriptor::from_counted(_tlg_argN),
+                self.add_data_desc_for_arg_n(DATADESC_FROM_COUNTED_PATH);
+            }
+
+            FieldStrategy::StrSlice => {
+                // Prototype: , _tlg_argN: &[u8]
+                // Call site: , AsRef::<[u8]>::as_ref(&StrSliceBuffer::newN(value_tokens...))
+                let buffer_option = FieldOption {
+                    value_type: U8_PATH,
+                    ..*field.option
+                };
+                self.add_func_slice_arg(&buffer_option, field.type_name_span, field.value_tokens);
+
+                // EventDataDescriptor::from_counted(_tlg_argN),
+                self.add_data_desc_for_arg_n(DATADESC_FROM_COUNTED_PATH);
+            }
+
             FieldStrategy::Sid => {
                 // Prototype: , _tlg_argN: &[value_type]
                 // Call site: , AsRef::<[value_type]>::as_ref(value_tokens...)
@@ -601,7 +960,7 @@ impl EventGenerator {
                     .add_punct(",");
             }
 
-            FieldStrategy::Counted => {
+            FieldStrategy::Counted | FieldStrategy::Fmt | FieldStrategy::RawCustom => {
                 if field.option.value_array_count == 0 {
                     // Prototype: , _tlg_argN: &[value_type]
                     // Call site: , AsRef::<[value_type]>::as_ref(value_tokens...)
@@ -637,6 +996,177 @@ impl EventGenerator {
                 self.add_data_desc_with_length(SLICE_COUNT_PATH, DATADESC_FROM_SLICE_PATH);
             }
 
+            FieldStrategy::Array => {
+                // [value_type; _TLG_COUNTn]
+                let array_type = TokenStream::from_iter(
+                    self.tree1
+                        .add_group_square(
+                            self.tree2
+                                .add_scalar_type_path(
+                                    &mut self.tree3,
+                                    field.option.value_type,
+                                    field.option.value_array_count,
+                                )
+                                .add_punct(";")
+                                .add_ident(self.count_n.current())
+                                .drain(),
+                        )
+                        .drain(),
+                );
+
+                // , _tlg_argN: &[value_type; _TLG_COUNTn]
+                self.func_args_tree
+                    .add_punct(",")
+                    .add_ident(self.arg_n.current())
+                    .add_punct(":")
+                    .add_punct("&")
+                    .add_tokens(array_type.clone());
+
+                // , identity::<&[value_type; _TLG_COUNTn]>(value_tokens...)
+                self.func_call_tree
+                    .add_punct(",")
+                    .push_span(field.type_name_span) // Use identity(...) as a target for error messages.
+                    .add_path(IDENTITY_PATH)
+                    .add_punct("::")
+                    .add_punct("<")
+                    .add_punct("&")
+                    .add_tokens(array_type)
+                    .add_punct(">")
+                    .add_group_paren(field.value_tokens)
+                    .pop_span();
+
+                // EventDataDescriptor::from_slice(_tlg_argN),
+                self.add_data_desc_for_arg_n(DATADESC_FROM_SLICE_PATH);
+            }
+
+            FieldStrategy::Capped => {
+                // Prototype: , _tlg_argN: &[value_type]
+                // Call site: , AsRef::<[value_type]>::as_ref(value_tokens...)
+                self.add_func_slice_arg(field.option, field.type_name_span, field.value_tokens);
+
+                // capped_slice(_tlg_argN, _TLG_COUNTn)
+                let capped_tokens: Vec<TokenTree> = self
+                    .tree1
+                    .add_path_call(
+                        CAPPED_SLICE_PATH,
+                        self.tree2
+                            .add_ident(self.arg_n.current())
+                            .add_punct(",")
+                            .add_ident(self.count_n.current())
+                            .drain(),
+                    )
+                    .drain()
+                    .collect();
+
+                // counted_size(capped_slice(_tlg_argN, _TLG_COUNTn)),
+                self.lengths_init_tree
+                    .add_path_call(COUNTED_SIZE_PATH, capped_tokens.iter().cloned())
+                    .add_punct(",");
+
+                self.data_desc_init_tree
+                    // EventDataDescriptor::from_value(&_tlg_lengths[N]),
+                    .add_path_call(
+                        DATADESC_FROM_VALUE_PATH,
+                        self.tree1
+                            .add_punct("&")
+                            .add_ident(TLG_LENGTHS_VAR)
+                            .add_group_square(
+                                self.tree2
+                                    .add_literal(Literal::u16_unsuffixed(self.lengths_count))
+                                    .drain(),
+                            )
+                            .drain(),
+                    )
+                    .add_punct(",")
+                    // EventDataDescriptor::from_counted(capped_slice(_tlg_argN, _TLG_COUNTn)),
+                    .add_path_call(DATADESC_FROM_COUNTED_PATH, capped_tokens)
+                    .add_punct(",")
+                    // EventDataDescriptor::from_value(&capped_length(_tlg_argN)),
+                    .add_path_call(
+                        DATADESC_FROM_VALUE_PATH,
+                        self.tree1
+                            .add_punct("&")
+                            .add_path_call(
+                                CAPPED_LENGTH_PATH,
+                                self.tree2.add_ident(self.arg_n.current()).drain(),
+                            )
+                            .drain(),
+                    )
+                    .add_punct(",");
+
+                self.lengths_count += 1;
+            }
+
+            FieldStrategy::PtrLen => {
+                // , _tlg_argN: EventDataDescriptor<'_>
+                self.func_args_tree
+                    .add_punct(",")
+                    .add_ident(self.arg_n.current())
+                    .add_punct(":")
+                    .add_path(DATADESC_PATH)
+                    .add_punct("<")
+                    .add_token(Punct::new('\'', Spacing::Joint))
+                    .add_ident("_")
+                    .add_punct(">");
+
+                // , EventDataDescriptor::from_ptr_len(value_tokens..., array_count...)
+                // Unsafe, so the event must be written from an unsafe block.
+                self.func_call_tree
+                    .add_punct(",")
+                    .push_span(field.type_name_span) // Use from_ptr_len(...) as a target for error messages.
+                    .add_path_call(
+                        DATADESC_FROM_PTR_LEN_PATH,
+                        self.tree1
+                            .add_group_paren(field.value_tokens)
+                            .add_punct(",")
+                            .add_group_paren(field.array_count.tokens)
+                            .drain(),
+                    )
+                    .pop_span();
+
+                // ptr_len_size(&_tlg_argN),
+                self.lengths_init_tree
+                    .add_path_call(
+                        PTR_LEN_SIZE_PATH,
+                        self.tree1
+                            .add_punct("&")
+                            .add_ident(self.arg_n.current())
+                            .drain(),
+                    )
+                    .add_punct(",");
+
+                self.data_desc_init_tree
+                    // EventDataDescriptor::from_value(&_tlg_lengths[N]),
+                    .add_path_call(
+                        DATADESC_FROM_VALUE_PATH,
+                        self.tree1
+                            .add_punct("&")
+                            .add_ident(TLG_LENGTHS_VAR)
+                            .add_group_square(
+                                self.tree2
+                                    .add_literal(Literal::u16_unsuffixed(self.lengths_count))
+                                    .drain(),
+                            )
+                            .drain(),
+                    )
+                    .add_punct(",")
+                    // _tlg_argN,
+                    .add_ident(self.arg_n.current())
+                    .add_punct(",");
+
+                self.lengths_count += 1;
+            }
+
+            FieldStrategy::Optional => {
+                // Prototype: , _tlg_argN: &[value_type]
+                // Call site: , option_as_slice::<value_type>(value_tokens...)
+                self.add_func_option_arg(field.option, field.type_name_span, field.value_tokens);
+
+                // EventDataDescriptor::from_value(&_tlg_lengths[N]),
+                // EventDataDescriptor::from_slice(_tlg_argN),
+                self.add_data_desc_with_length(SLICE_COUNT_PATH, DATADESC_FROM_SLICE_PATH);
+            }
+
             FieldStrategy::Struct
             | FieldStrategy::RawStruct
             | FieldStrategy::RawStructSlice
@@ -782,6 +1312,45 @@ impl EventGenerator {
             .pop_span();
     }
 
+    fn add_func_option_arg(
+        &mut self,
+        field_option: &FieldOption,
+        field_type_name_span: Span,
+        field_value_tokens: TokenStream,
+    ) {
+        // , _tlg_argN: &[VALUE_TYPE]
+        self.func_args_tree
+            .add_punct(",")
+            .add_ident(self.arg_n.current())
+            .add_punct(":")
+            .add_punct("&")
+            .add_group_square(
+                self.tree1
+                    .add_scalar_type_path(
+                        &mut self.tree2,
+                        field_option.value_type,
+                        field_option.value_array_count,
+                    )
+                    .drain(),
+            );
+
+        // , option_as_slice::<VALUE_TYPE>(value_tokens...)
+        self.func_call_tree
+            .add_punct(",")
+            .push_span(field_type_name_span) // Use option_as_slice(...) as a target for error messages.
+            .add_path(OPTION_AS_SLICE_PATH)
+            .add_punct("::")
+            .add_punct("<")
+            .add_scalar_type_path(
+                &mut self.tree1,
+                field_option.value_type,
+                field_option.value_array_count,
+            )
+            .add_punct(">")
+            .add_group_paren(field_value_tokens)
+            .pop_span();
+    }
+
     fn add_typecode_meta(
         &mut self,
         enum_type_path: &[&str],
@@ -908,6 +1477,292 @@ impl EventGenerator {
         );
     }
 
+    fn add_array_count(&mut self, expression: Expression) {
+        // Implicitly uses self.count_n as the name for the count's constant.
+
+        self.flush_meta_buffer();
+
+        // const _TLG_COUNTn: usize = COUNT;
+        self.tags_tree
+            .push_span(expression.context)
+            .add_const_from_tokens(self.count_n.current(), USIZE_PATH, expression.tokens)
+            // #[allow(clippy::assertions_on_constants, clippy::absurd_extreme_comparisons)]
+            .add_outer_attribute(
+                "allow",
+                self.tree1
+                    .push_span(expression.context)
+                    .add_ident("clippy")
+                    .add_punct("::")
+                    .add_ident("assertions_on_constants")
+                    .add_punct(",")
+                    .add_ident("clippy")
+                    .add_punct("::")
+                    .add_ident("absurd_extreme_comparisons")
+                    .pop_span()
+                    .drain(),
+            )
+            // const _: () = assert!(_TLG_COUNTn <= 65535, "...");
+            .add_ident("const")
+            .add_ident("_")
+            .add_punct(":")
+            .add_group_paren([])
+            .add_punct("=")
+            .add_path(ASSERT_PATH)
+            .add_punct("!")
+            .add_group_paren(
+                self.tree1
+                    .push_span(expression.context)
+                    .add_ident(self.count_n.current())
+                    .add_punct("<=")
+                    .add_literal(Literal::usize_unsuffixed(65535))
+                    .add_punct(",")
+                    .add_literal(Literal::string(
+                        "array length must not be greater than 65535",
+                    ))
+                    .pop_span()
+                    .drain(),
+            )
+            .add_punct(";")
+            .pop_span();
+
+        // , [u8; 2]
+        self.meta_type_tree.add_punct(",").add_group_square(
+            self.tree1
+                .add_path(U8_PATH)
+                .add_punct(";")
+                .add_literal(Literal::usize_unsuffixed(2))
+                .drain(),
+        );
+
+        // , u16::to_le_bytes(_TLG_COUNTn as u16)
+        self.meta_init_tree.add_punct(",").add_path_call(
+            U16_TO_LE_BYTES_PATH,
+            self.tree1
+                .add_ident(self.count_n.current())
+                .add_ident("as")
+                .add_path(U16_PATH)
+                .drain(),
+        );
+    }
+
+    fn add_custom_protocol(&mut self, tokens: TokenStream, span: Span, flags: u8) {
+        // Implicitly uses self.protocol_n as the name for the protocol's constant.
+
+        self.flush_meta_buffer();
+
+        // const _TLG_PROTOCOLn: u8 = PROTOCOL;
+        self.tags_tree
+            .push_span(span)
+            .add_const_from_tokens(self.protocol_n.current(), U8_PATH, tokens)
+            // #[allow(clippy::assertions_on_constants)]
+            .add_outer_attribute(
+                "allow",
+                self.tree1
+                    .push_span(span)
+                    .add_ident("clippy")
+                    .add_punct("::")
+                    .add_ident("assertions_on_constants")
+                    .pop_span()
+                    .drain(),
+            )
+            // const _: () = assert!(_TLG_PROTOCOLn <= 31, "...");
+            .add_ident("const")
+            .add_ident("_")
+            .add_punct(":")
+            .add_group_paren([])
+            .add_punct("=")
+            .add_path(ASSERT_PATH)
+            .add_punct("!")
+            .add_group_paren(
+                self.tree1
+                    .push_span(span)
+                    .add_ident(self.protocol_n.current())
+                    .add_punct("<=")
+                    .add_literal(Literal::u8_unsuffixed(31))
+                    .add_punct(",")
+                    .add_literal(Literal::string(
+                        "custom protocol must not be greater than 31",
+                    ))
+                    .pop_span()
+                    .drain(),
+            )
+            .add_punct(";")
+            .pop_span();
+
+        // , u8
+        self.meta_type_tree.add_punct(",").add_path(U8_PATH);
+
+        // , _TLG_PROTOCOLn | flags
+        self.meta_init_tree
+            .add_punct(",")
+            .add_ident(self.protocol_n.current())
+            .add_punct("|")
+            .add_literal(Literal::u8_unsuffixed(flags));
+    }
+
+    fn add_custom_schema(&mut self, expression: Expression) {
+        // Implicitly uses self.schema_n as the name for the schema's constant.
+
+        self.flush_meta_buffer();
+
+        // const _TLG_SCHEMAn: &[u8] = SCHEMA;
+        self.tags_tree
+            .push_span(expression.context)
+            .add_ident("const")
+            .add_ident(self.schema_n.current())
+            .add_punct(":")
+            .add_punct("&")
+            .add_group_square(self.tree1.add_path(U8_PATH).drain())
+            .add_punct("=")
+            .add_tokens(expression.tokens)
+            .add_punct(";")
+            // #[allow(clippy::assertions_on_constants)]
+            .add_outer_attribute(
+                "allow",
+                self.tree1
+                    .push_span(expression.context)
+                    .add_ident("clippy")
+                    .add_punct("::")
+                    .add_ident("assertions_on_constants")
+                    .pop_span()
+                    .drain(),
+            )
+            // const _: () = assert!(_TLG_SCHEMAn.len() <= 65535, "...");
+            .add_ident("const")
+            .add_ident("_")
+            .add_punct(":")
+            .add_group_paren([])
+            .add_punct("=")
+            .add_path(ASSERT_PATH)
+            .add_punct("!")
+            .add_group_paren(
+                self.tree1
+                    .push_span(expression.context)
+                    .add_ident(self.schema_n.current())
+                    .add_punct(".")
+                    .add_ident("len")
+                    .add_group_paren([])
+                    .add_punct("<=")
+                    .add_literal(Literal::usize_unsuffixed(65535))
+                    .add_punct(",")
+                    .add_literal(Literal::string(
+                        "custom schema must not be longer than 65535 bytes",
+                    ))
+                    .pop_span()
+                    .drain(),
+            )
+            .add_punct(";")
+            .pop_span();
+
+        // , [u8; 2], [u8; _TLG_SCHEMAn.len()]
+        self.meta_type_tree
+            .add_punct(",")
+            .add_group_square(
+                self.tree1
+                    .add_path(U8_PATH)
+                    .add_punct(";")
+                    .add_literal(Literal::usize_unsuffixed(2))
+                    .drain(),
+            )
+            .add_punct(",")
+            .add_group_square(
+                self.tree1
+                    .add_path(U8_PATH)
+                    .add_punct(";")
+                    .add_ident(self.schema_n.current())
+                    .add_punct(".")
+                    .add_ident("len")
+                    .add_group_paren([])
+                    .drain(),
+            );
+
+        // , u16::to_le_bytes(_TLG_SCHEMAn.len() as u16), custom_schema(_TLG_SCHEMAn)
+        self.meta_init_tree
+            .add_punct(",")
+            .add_path_call(
+                U16_TO_LE_BYTES_PATH,
+                self.tree1
+                    .add_ident(self.schema_n.current())
+                    .add_punct(".")
+                    .add_ident("len")
+                    .add_group_paren([])
+                    .add_ident("as")
+                    .add_path(U16_PATH)
+                    .drain(),
+            )
+            .add_punct(",")
+            .add_path_call(
+                CUSTOM_SCHEMA_PATH,
+                self.tree1.add_ident(self.schema_n.current()).drain(),
+            );
+    }
+
+    fn add_capped_count(&mut self, expression: Expression) {
+        // Implicitly uses self.count_n as the name for the count's constant.
+
+        // const _TLG_COUNTn: u16 = MAX_LEN;
+        self.tags_tree
+            .push_span(expression.context)
+            .add_const_from_tokens(self.count_n.current(), U16_PATH, expression.tokens)
+            .pop_span();
+    }
+
+    /// Adds the constant and the per-call-site `EmitPolicy` for the sample_rate option
+    /// to `enabled_tree`.
+    fn add_sample_rate(&mut self, expression: Expression) {
+        self.enabled_tree
+            // const _TLG_SAMPLE_RATE: u64 = RATE;
+            .push_span(expression.context)
+            .add_const_from_tokens(TLG_SAMPLE_RATE_CONST, U64_PATH, expression.tokens)
+            // #[allow(clippy::assertions_on_constants)]
+            .add_outer_attribute(
+                "allow",
+                self.tree1
+                    .push_span(expression.context)
+                    .add_ident("clippy")
+                    .add_punct("::")
+                    .add_ident("assertions_on_constants")
+                    .pop_span()
+                    .drain(),
+            )
+            // const _: () = assert!(_TLG_SAMPLE_RATE != 0, "...");
+            .add_ident("const")
+            .add_ident("_")
+            .add_punct(":")
+            .add_group_paren([])
+            .add_punct("=")
+            .add_path(ASSERT_PATH)
+            .add_punct("!")
+            .add_group_paren(
+                self.tree1
+                    .push_span(expression.context)
+                    .add_ident(TLG_SAMPLE_RATE_CONST)
+                    .add_punct("!=")
+                    .add_literal(Literal::u64_unsuffixed(0))
+                    .add_punct(",")
+                    .add_literal(Literal::string("sample_rate must not be 0"))
+                    .pop_span()
+                    .drain(),
+            )
+            .add_punct(";")
+            .pop_span()
+            // static _TLG_SAMPLE: EmitPolicy = EmitPolicy::new(1, _TLG_SAMPLE_RATE);
+            .add_ident("static")
+            .add_ident(TLG_SAMPLE_STATIC)
+            .add_punct(":")
+            .add_path(EMIT_POLICY_PATH)
+            .add_punct("=")
+            .add_path_call(
+                EMIT_POLICY_NEW_PATH,
+                self.tree1
+                    .add_literal(Literal::u64_unsuffixed(1))
+                    .add_punct(",")
+                    .add_ident(TLG_SAMPLE_RATE_CONST)
+                    .drain(),
+            )
+            .add_punct(";");
+    }
+
     /// If `meta_buffer` is empty, does nothing, otherwise, if there are `N` bytes of
     /// metadata in meta_buffer, adds a `[u8;N]` field to `meta_type_tree`, adds a binary
     /// literal containing the data to `meta_init_tree`, then clears `meta_buffer`.
@@ -3,11 +3,11 @@
 
 use proc_macro::*;
 
-use crate::enums::OutType;
+use crate::enums::{InType, OutType};
 use crate::errors::Errors;
 use crate::expression::Expression;
 use crate::field_info::FieldInfo;
-use crate::field_option::FieldStrategy;
+use crate::field_option::{FieldOption, FieldStrategy};
 use crate::field_options::FIELD_OPTIONS;
 use crate::parser::{ArgConstraints::*, ArgResult, Parser};
 use crate::strings::*;
@@ -18,6 +18,44 @@ const STRUCT_FIELDS_MAX: u8 = 127; // TraceLogging limit
 const DATA_DESC_MAX: u8 = 128; // EventWrite limit
 const FIELDS_MAX: usize = 128; // TDH limit
 
+/// Members of the struct generated for an `error` field. The data for both members
+/// comes from the `error` field's value (`_tlg_error_chain_data!`).
+static ERROR_MEMBERS: [(&str, FieldOption); 2] = [
+    (
+        "Message",
+        FieldOption::new(
+            "error",
+            U8_PATH,
+            InType::Str8,
+            OutType::Utf8,
+            FieldStrategy::RawMeta,
+            0,
+        ),
+    ),
+    (
+        "Sources",
+        FieldOption::new(
+            "error",
+            U8_PATH,
+            InType::Str8,
+            OutType::Utf8,
+            FieldStrategy::RawMetaSlice,
+            0,
+        ),
+    ),
+];
+
+/// Member added after a `binary_capped` field. Holds the original length of the
+/// field's value (`capped_length`); the data comes from the `binary_capped` field.
+static CAPPED_LENGTH_MEMBER: FieldOption = FieldOption::new(
+    "binary_capped",
+    U32_PATH,
+    InType::U32,
+    OutType::Default,
+    FieldStrategy::RawMeta,
+    0,
+);
+
 pub struct EventInfo {
     pub provider_symbol: Ident,
     pub name: String,
@@ -31,8 +69,28 @@ pub struct EventInfo {
     pub tag: Expression,
     pub activity_id: Expression,
     pub related_id: Expression,
+    /// If not empty, only 1 of every N occurrences of the event is written.
+    pub sample_rate: Expression,
     pub fields: Vec<FieldInfo>,
     pub debug: bool,
+    /// true if debug_ir() was specified: emit a JSON description of the event.
+    pub debug_ir: bool,
+    /// true for try_write_event: return ERROR_INVALID_HANDLE if provider not registered.
+    pub require_registered: bool,
+    /// true for activity_scope: opcode, activity_id, and related_id are set automatically.
+    pub activity_scope: bool,
+    /// false for derive(TraceLoggingEvent): provider_symbol is a parameter (not a
+    /// static), so the event's schema cannot be exported.
+    pub export_schema: bool,
+    /// false for derive(TraceLoggingEvent): provider_symbol is a parameter (not a
+    /// static), so the provider's KEYWORDS_MASK is not available.
+    pub check_keywords_mask: bool,
+    /// true if activity(start) was specified: the macro generates a new activity id,
+    /// uses it for the event, and returns it.
+    pub activity_start: bool,
+
+    // true if keyword_none() was specified.
+    keyword_none: bool,
 
     // Set to 0 if we've already emitted an error message.
     data_desc_used: u8,
@@ -47,6 +105,23 @@ impl EventInfo {
     pub fn try_from_tokens(
         arg_span: Span,
         arg_tokens: TokenStream,
+    ) -> Result<EventInfo, TokenStream> {
+        return Self::try_from_tokens_impl(arg_span, arg_tokens, false);
+    }
+
+    /// Parses the arguments of an activity_scope macro. Same as try_from_tokens except
+    /// that the opcode, activity_id, and related_id options are not allowed.
+    pub fn try_activity_scope_from_tokens(
+        arg_span: Span,
+        arg_tokens: TokenStream,
+    ) -> Result<EventInfo, TokenStream> {
+        return Self::try_from_tokens_impl(arg_span, arg_tokens, true);
+    }
+
+    fn try_from_tokens_impl(
+        arg_span: Span,
+        arg_tokens: TokenStream,
+        activity_scope: bool,
     ) -> Result<EventInfo, TokenStream> {
         let mut event = EventInfo {
             provider_symbol: Ident::new("x", arg_span),
@@ -61,8 +136,16 @@ impl EventInfo {
             tag: Expression::empty(arg_span),
             activity_id: Expression::empty(arg_span),
             related_id: Expression::empty(arg_span),
+            sample_rate: Expression::empty(arg_span),
             fields: Vec::new(),
             debug: false,
+            debug_ir: false,
+            require_registered: false,
+            activity_scope,
+            export_schema: true,
+            check_keywords_mask: true,
+            activity_start: false,
+            keyword_none: false,
             data_desc_used: 2,                    // provider_meta, event_meta
             estimated_metadata_bytes_used: 2 + 4, // metadata_size + estimated event tag size
         };
@@ -143,9 +226,28 @@ impl EventInfo {
             );
         }
 
-        // opcode default: Opcode::Info
+        // opcode default: Opcode::ActivityStart if activity(start), otherwise Opcode::Info
         if event.opcode_tokens.is_empty() {
-            event.opcode_tokens = scratch_tree.add_path(OPCODE_INFO_PATH).drain().collect();
+            event.opcode_tokens = scratch_tree
+                .add_path(if event.activity_start {
+                    OPCODE_ACTIVITY_START_PATH
+                } else {
+                    OPCODE_INFO_PATH
+                })
+                .drain()
+                .collect();
+        }
+
+        // activity(start): activity_id = &_tlg_new_aid (generated by EventGenerator)
+        if event.activity_start {
+            event.activity_id = Expression::new(
+                arg_span,
+                scratch_tree
+                    .add_punct("&")
+                    .add_ident(TLG_NEW_ACTIVITY_ID_VAR)
+                    .drain()
+                    .collect(),
+            );
         }
 
         // task default: 0
@@ -156,12 +258,13 @@ impl EventInfo {
                 .collect();
         }
 
-        // keyword default: 1u64
+        // keyword default: PROVIDER::DEFAULT_KEYWORD (normally 1)
         if event.keywords.is_empty() {
             event.keywords.push(Expression::new(
                 arg_span,
                 scratch_tree
-                    .add_literal(Literal::u64_suffixed(1))
+                    .add_token(event.provider_symbol.clone())
+                    .add_path(&[DEFAULT_KEYWORD_CONST])
                     .drain()
                     .collect(),
             ));
@@ -187,6 +290,70 @@ impl EventInfo {
         };
     }
 
+    /// For activity_scope: sets this event's opcode to ActivityStart and its
+    /// activity_id and related_id to the scope's ids, then returns the matching
+    /// ActivityStop event (same name and options, no fields, activity_id from the
+    /// stop function's parameter).
+    pub fn split_activity_stop(&mut self, span: Span, scratch_tree: &mut Tree) -> EventInfo {
+        self.opcode_tokens = scratch_tree
+            .add_path(OPCODE_ACTIVITY_START_PATH)
+            .drain()
+            .collect();
+        self.activity_id = Expression::new(
+            span,
+            scratch_tree
+                .add_ident(TLG_SCOPE_VAR)
+                .add_punct(".")
+                .add_ident("activity_id")
+                .add_group_paren([])
+                .drain()
+                .collect(),
+        );
+        self.related_id = Expression::new(
+            span,
+            scratch_tree
+                .add_ident(TLG_SCOPE_VAR)
+                .add_punct(".")
+                .add_ident("related_id")
+                .add_group_paren([])
+                .drain()
+                .collect(),
+        );
+
+        return EventInfo {
+            provider_symbol: self.provider_symbol.clone(),
+            name: self.name.clone(),
+            id_tokens: self.id_tokens.clone(),
+            version_tokens: self.version_tokens.clone(),
+            channel_tokens: self.channel_tokens.clone(),
+            opcode_tokens: scratch_tree
+                .add_path(OPCODE_ACTIVITY_STOP_PATH)
+                .drain()
+                .collect(),
+            task_tokens: self.task_tokens.clone(),
+            level: self.level.clone(),
+            keywords: self.keywords.clone(),
+            tag: self.tag.clone(),
+            activity_id: Expression::new(
+                span,
+                scratch_tree.add_ident(TLG_SCOPE_AID_VAR).drain().collect(),
+            ),
+            related_id: Expression::empty(span),
+            sample_rate: Expression::empty(span), // Not allowed in activity_scope.
+            fields: Vec::new(),
+            debug: self.debug,
+            debug_ir: self.debug_ir,
+            require_registered: false,
+            activity_scope: true,
+            export_schema: self.export_schema,
+            check_keywords_mask: self.check_keywords_mask,
+            activity_start: false,
+            keyword_none: self.keyword_none,
+            data_desc_used: 2,
+            estimated_metadata_bytes_used: 0, // Already checked by the start event.
+        };
+    }
+
     /// Parses options. Returns the number of logical fields added to the event.
     fn parse_event_options(
         &mut self,
@@ -213,6 +380,8 @@ impl EventInfo {
                     outtype_or_field_count_expr: Expression::empty(option_ident.span()),
                     outtype_or_field_count_int: FIELD_OPTIONS[field_option_index].outtype as u8,
                     tag: Expression::empty(option_ident.span()),
+                    array_count: Expression::empty(option_ident.span()),
+                    schema: Expression::empty(option_ident.span()),
                 };
 
                 let field_has_metadata = field.option.strategy.has_metadata();
@@ -243,10 +412,27 @@ impl EventInfo {
                     | FieldStrategy::Sid
                     | FieldStrategy::CStr
                     | FieldStrategy::Counted
-                    | FieldStrategy::Slice => {
+                    | FieldStrategy::Fmt
+                    | FieldStrategy::CallerAddress
+                    | FieldStrategy::Slice
+                    | FieldStrategy::Optional
+                    | FieldStrategy::Capped
+                    | FieldStrategy::PtrLen
+                    | FieldStrategy::StrSlice => {
+                        field_accepts_tag = true;
+                        field_accepts_format = true;
+                        field_wants_struct = false;
+                    }
+                    FieldStrategy::Array => {
                         field_accepts_tag = true;
                         field_accepts_format = true;
                         field_wants_struct = false;
+
+                        let tokens = option_parser.next_tokens(
+                            RequiredNotLast,
+                            "expected array length (must be a constant expression, e.g. 4)",
+                        );
+                        field.array_count = Expression::new(option_ident.span(), tokens);
                     }
                     FieldStrategy::Struct => {
                         field_accepts_tag = true;
@@ -272,6 +458,21 @@ impl EventInfo {
                             scratch_tree,
                         );
                     }
+                    FieldStrategy::RawCustom => {
+                        field_accepts_tag = true;
+                        field_accepts_format = false;
+                        field_wants_struct = false;
+
+                        field.intype_tokens = option_parser.next_tokens(
+                            RequiredNotLast,
+                            "expected custom protocol (must be a constant expression from 0 to 31)",
+                        );
+                        let tokens = option_parser.next_tokens(
+                            RequiredNotLast,
+                            "expected schema (must be a constant &[u8] expression, e.g. b\"...\")",
+                        );
+                        field.schema = Expression::new(option_ident.span(), tokens);
+                    }
                     FieldStrategy::RawStruct | FieldStrategy::RawStructSlice => {
                         field_accepts_tag = true;
                         field_accepts_format = false;
@@ -300,17 +501,80 @@ impl EventInfo {
                         field_accepts_format = false;
                         field_wants_struct = false;
                     }
+                    FieldStrategy::ErrorChain => {
+                        field_accepts_tag = true;
+                        field_accepts_format = false;
+                        field_wants_struct = false;
+
+                        field.outtype_or_field_count_int = ERROR_MEMBERS.len() as u8;
+                    }
                 }
 
                 if field.option.strategy.data_count() != 0 {
-                    field.value_tokens =
-                        option_parser.next_tokens(Required, "expected field value");
+                    field.value_tokens = call_if_closure(
+                        option_parser.next_tokens(Required, "expected field value"),
+                        option_ident.span(),
+                        scratch_tree,
+                    );
+
+                    if let FieldStrategy::Fmt = field.option.strategy {
+                        // &FmtBuffer::new(value_tokens...)
+                        field.value_tokens = scratch_tree
+                            .push_span(option_ident.span())
+                            .add_punct("&")
+                            .add_path_call(FMT_BUFFER_NEW_PATH, field.value_tokens)
+                            .pop_span()
+                            .drain()
+                            .collect();
+                    } else if let FieldStrategy::StrSlice = field.option.strategy {
+                        // &StrSliceBuffer::new8(value_tokens...) or new16
+                        let new_path = if let InType::Str16 = field.option.intype {
+                            STR_SLICE_BUFFER_NEW16_PATH
+                        } else {
+                            STR_SLICE_BUFFER_NEW8_PATH
+                        };
+                        field.value_tokens = scratch_tree
+                            .push_span(option_ident.span())
+                            .add_punct("&")
+                            .add_path_call(new_path, field.value_tokens)
+                            .pop_span()
+                            .drain()
+                            .collect();
+                    }
+                }
+
+                let field_is_capped = matches!(field.option.strategy, FieldStrategy::Capped);
+                if field_is_capped {
+                    let tokens = option_parser.next_tokens(
+                        Required,
+                        "expected maximum length (must be a constant expression, e.g. 64)",
+                    );
+                    field.array_count = Expression::new(option_ident.span(), tokens);
+                } else if let FieldStrategy::PtrLen = field.option.strategy {
+                    let tokens = option_parser
+                        .next_tokens(Required, "expected length (must be a usize expression)");
+                    field.array_count = Expression::new(option_ident.span(), tokens);
                 }
 
                 loop {
                     match option_parser.next_arg(field_wants_struct) {
                         ArgResult::None => {
+                            let error_chain_span = match field.option.strategy {
+                                FieldStrategy::ErrorChain => Some(field.type_name_span),
+                                _ => None,
+                            };
+                            let capped_length_name = if field_is_capped {
+                                Some((field.name.clone() + "Length", field.type_name_span))
+                            } else {
+                                None
+                            };
                             self.push_field(option_parser.errors(), field);
+                            if let Some(span) = error_chain_span {
+                                self.push_error_members(option_parser.errors(), span);
+                            }
+                            if let Some((name, span)) = capped_length_name {
+                                self.push_capped_length_member(option_parser.errors(), name, span);
+                            }
                             break;
                         }
                         ArgResult::Struct(mut struct_parser) => {
@@ -377,12 +641,34 @@ impl EventInfo {
 
                     logical_fields_added = logical_fields_added.saturating_add(1);
                 }
+
+                if field_is_capped {
+                    // The length member is a separate logical field.
+                    if in_struct && logical_fields_added == STRUCT_FIELDS_MAX {
+                        option_parser
+                            .errors()
+                            .add(option_ident.span(), "too many fields in struct (limit 127)");
+                    }
+
+                    logical_fields_added = logical_fields_added.saturating_add(1);
+                }
             } else {
                 match option_name.as_str() {
+                    "opcode" | "activity" | "activity_id" | "related_id" if self.activity_scope => {
+                        errors.add(
+                            option_ident.span(),
+                            "option not allowed in activity_scope (set automatically)",
+                        );
+                        continue;
+                    }
                     "debug" if !in_struct => {
                         self.debug = true;
                         continue;
                     }
+                    "debug_ir" if !in_struct => {
+                        self.debug_ir = true;
+                        continue;
+                    }
                     "id_version" if !in_struct => {
                         if !self.id_tokens.is_empty() {
                             errors.add(option_ident.span(), "id_version already set");
@@ -392,6 +678,31 @@ impl EventInfo {
                         self.version_tokens = option_parser
                             .next_tokens(RequiredLast, "expected Version value, e.g. 0 or 0x1F");
                     }
+                    "auto_id" if !in_struct => {
+                        if !self.id_tokens.is_empty() {
+                            errors.add(
+                                option_ident.span(),
+                                "id already set (auto_id cannot be combined with id_version)",
+                            );
+                        }
+                        const EXPECTED_AUTO_ID: &str = "expected auto_id(hash)";
+                        if let Some(scheme) =
+                            option_parser.next_ident(RequiredLast, EXPECTED_AUTO_ID)
+                        {
+                            if scheme.to_string() != "hash" {
+                                option_parser.errors().add(scheme.span(), EXPECTED_AUTO_ID);
+                            }
+                        }
+                        // Evaluated by tracelogging so that the macro and the
+                        // runtime share one implementation of the hash.
+                        self.id_tokens = scratch_tree
+                            .add_path_call(
+                                PROVIDER_EVENT_ID_FROM_NAME_PATH,
+                                [TokenTree::from(Literal::string(&self.name))],
+                            )
+                            .drain()
+                            .collect();
+                    }
                     "channel" if !in_struct => {
                         if !self.channel_tokens.is_empty() {
                             errors.add(option_ident.span(), "channel already set");
@@ -448,12 +759,32 @@ impl EventInfo {
                             .next_tokens(RequiredLast, "expected Task value, e.g. 1 or 0x2001");
                     }
                     "keyword" if !in_struct => {
+                        if self.keyword_none {
+                            errors
+                                .add(option_ident.span(), "keyword not allowed with keyword_none");
+                        }
                         self.keywords.push(Expression::new(
                             option_ident.span(),
                             option_parser
                                 .next_tokens(RequiredLast, "expected Keyword value, e.g. 0x100F"),
                         ));
                     }
+                    "keyword_none" if !in_struct => {
+                        if self.keyword_none {
+                            errors.add(option_ident.span(), "keyword_none already set");
+                        } else if !self.keywords.is_empty() {
+                            errors
+                                .add(option_ident.span(), "keyword_none not allowed with keyword");
+                        }
+                        self.keyword_none = true;
+                        self.keywords.push(Expression::new(
+                            option_ident.span(),
+                            scratch_tree
+                                .add_literal(Literal::u64_suffixed(0))
+                                .drain()
+                                .collect(),
+                        ));
+                    }
                     "tag" if !in_struct => {
                         if !self.tag.is_empty() {
                             errors.add(option_ident.span(), "tag already set");
@@ -466,9 +797,31 @@ impl EventInfo {
                             ),
                         );
                     }
+                    "activity" if !in_struct => {
+                        if self.activity_start {
+                            errors.add(option_ident.span(), "activity already set");
+                        } else if !self.activity_id.is_empty() {
+                            errors
+                                .add(option_ident.span(), "activity not allowed with activity_id");
+                        }
+                        const EXPECTED_ACTIVITY: &str = "expected activity(start)";
+                        if let Some(mode) =
+                            option_parser.next_ident(RequiredLast, EXPECTED_ACTIVITY)
+                        {
+                            if mode.to_string() != "start" {
+                                option_parser.errors().add(mode.span(), EXPECTED_ACTIVITY);
+                            }
+                        }
+                        self.activity_start = true;
+                    }
                     "activity_id" if !in_struct => {
                         if !self.activity_id.is_empty() {
                             errors.add(option_ident.span(), "activity_id already set");
+                        } else if self.activity_start {
+                            errors.add(
+                                option_ident.span(),
+                                "activity_id not allowed with activity(start)",
+                            );
                         }
                         self.activity_id = Expression::new(
                             option_ident.span(),
@@ -476,6 +829,23 @@ impl EventInfo {
                                 .next_tokens(RequiredLast, "expected Activity Id variable"),
                         );
                     }
+                    "sample_rate" if !in_struct => {
+                        if self.activity_scope {
+                            errors.add(
+                                option_ident.span(),
+                                "sample_rate not allowed in activity_scope",
+                            );
+                        } else if !self.sample_rate.is_empty() {
+                            errors.add(option_ident.span(), "sample_rate already set");
+                        }
+                        self.sample_rate = Expression::new(
+                            option_ident.span(),
+                            option_parser.next_tokens(
+                                RequiredLast,
+                                "expected sample rate (must be a constant u64 expression, e.g. 100)",
+                            ),
+                        );
+                    }
                     "related_id" if !in_struct => {
                         if !self.related_id.is_empty() {
                             errors.add(option_ident.span(), "related_id already set");
@@ -505,6 +875,11 @@ impl EventInfo {
                 2 // intype + outtype
             } else {
                 1 // intype
+            }
+            + match field.option.strategy {
+                FieldStrategy::Array => 2,     // array count
+                FieldStrategy::RawCustom => 2, // schema size (schema is not included)
+                _ => 0,
             };
         self.add_estimated_metadata(errors, field.type_name_span, metadata_size);
         self.add_data_desc_used(
@@ -523,6 +898,46 @@ impl EventInfo {
         self.fields.push(field);
     }
 
+    /// Adds the member fields (metadata only) of the struct for an `error` field.
+    fn push_error_members(&mut self, errors: &mut Errors, span: Span) {
+        for (name, option) in &ERROR_MEMBERS {
+            self.push_field(
+                errors,
+                FieldInfo {
+                    type_name_span: span,
+                    option,
+                    name: String::from(*name),
+                    value_tokens: TokenStream::new(),
+                    intype_tokens: TokenStream::new(),
+                    outtype_or_field_count_expr: Expression::empty(span),
+                    outtype_or_field_count_int: option.outtype as u8,
+                    tag: Expression::empty(span),
+                    array_count: Expression::empty(span),
+                    schema: Expression::empty(span),
+                },
+            );
+        }
+    }
+
+    /// Adds the length member (metadata only) that follows a `binary_capped` field.
+    fn push_capped_length_member(&mut self, errors: &mut Errors, name: String, span: Span) {
+        self.push_field(
+            errors,
+            FieldInfo {
+                type_name_span: span,
+                option: &CAPPED_LENGTH_MEMBER,
+                name,
+                value_tokens: TokenStream::new(),
+                intype_tokens: TokenStream::new(),
+                outtype_or_field_count_expr: Expression::empty(span),
+                outtype_or_field_count_int: CAPPED_LENGTH_MEMBER.outtype as u8,
+                tag: Expression::empty(span),
+                array_count: Expression::empty(span),
+                schema: Expression::empty(span),
+            },
+        );
+    }
+
     fn add_data_desc_used(&mut self, errors: &mut Errors, span: Span, data_count: u8) {
         if self.data_desc_used == 0 {
             // Already emitted an error for this. Don't emit another.
@@ -561,6 +976,33 @@ fn expected_enum_message(
     );
 }
 
+/// If the field value is a closure (`|| EXPR` or `move || EXPR`), returns
+/// `&call_field_closure(CLOSURE)` so that the closure is invoked where the value would
+/// have been evaluated, i.e. only if the event is enabled. Otherwise returns the value
+/// unchanged.
+fn call_if_closure(tokens: TokenStream, span: Span, scratch_tree: &mut Tree) -> TokenStream {
+    let mut iter = tokens.clone().into_iter();
+    let is_closure = match iter.next() {
+        Some(TokenTree::Punct(punct)) => punct.as_char() == '|',
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "move" => {
+            matches!(iter.next(), Some(TokenTree::Punct(punct)) if punct.as_char() == '|')
+        }
+        _ => false,
+    };
+
+    return if !is_closure {
+        tokens
+    } else {
+        scratch_tree
+            .push_span(span)
+            .add_punct("&")
+            .add_path_call(CALL_FIELD_CLOSURE_PATH, tokens)
+            .pop_span()
+            .drain()
+            .collect()
+    };
+}
+
 fn filter_enum_tokens(
     tokens: TokenStream,
     enum_name: &str,
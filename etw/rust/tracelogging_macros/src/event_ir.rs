@@ -0,0 +1,149 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Implements the `debug_ir()` option: a JSON description of the event's schema that
+//! is emitted as a string constant in the expanded macro, e.g. for IDE tooling.
+
+use proc_macro::*;
+use std::fmt::Write;
+
+use crate::event_info::EventInfo;
+use crate::field_option::FieldStrategy;
+
+/// Version of the JSON format. Increment if the format changes incompatibly.
+const IR_VERSION: u32 = 1;
+
+/// Returns the JSON description of the event. Must be called before the event's
+/// fields are consumed.
+///
+/// Values that are expressions (e.g. `level(MY_LEVEL)`) are given as the expression's
+/// tokens (`TokenStream::to_string`). Fields are listed in metadata order; a `struct` field is followed by
+/// its `struct_fields` member fields.
+///
+/// ```json
+/// {"ir_version":1,"provider":"MY_PROVIDER","name":"MyEvent",
+///  "id":"0","version":"0","channel":":: tracelogging :: Channel :: TraceLogging",
+///  "level":":: tracelogging :: Level :: Verbose","opcode":":: tracelogging :: Opcode :: Info",
+///  "task":"0","keywords":["MY_PROVIDER :: DEFAULT_KEYWORD"],"tag":"0",
+///  "fields":[{"name":"Id","type":"u32","in_type":"U32","out_type":"Default","tag":"0"}]}
+/// ```
+pub fn event_ir_json(event: &EventInfo) -> String {
+    let mut json = String::with_capacity(256);
+    write!(json, "{{\"ir_version\":{}", IR_VERSION).unwrap();
+    add_string_member(&mut json, "provider", &event.provider_symbol.to_string());
+    add_string_member(&mut json, "name", &event.name);
+    add_tokens_member(&mut json, "id", &event.id_tokens);
+    add_tokens_member(&mut json, "version", &event.version_tokens);
+    add_tokens_member(&mut json, "channel", &event.channel_tokens);
+    add_tokens_member(&mut json, "level", &event.level.tokens);
+    add_tokens_member(&mut json, "opcode", &event.opcode_tokens);
+    add_tokens_member(&mut json, "task", &event.task_tokens);
+
+    json.push_str(",\"keywords\":[");
+    for (i, keyword) in event.keywords.iter().enumerate() {
+        if i != 0 {
+            json.push(',');
+        }
+        add_string(&mut json, &keyword.tokens.to_string());
+    }
+    json.push(']');
+
+    add_tokens_member(&mut json, "tag", &event.tag.tokens);
+
+    json.push_str(",\"fields\":[");
+    let mut first = true;
+    for field in &event.fields {
+        let strategy = field.option.strategy;
+        if !strategy.has_metadata() {
+            continue;
+        }
+
+        if !first {
+            json.push(',');
+        }
+        first = false;
+
+        json.push_str("{\"name\":");
+        add_string(&mut json, &field.name);
+        add_string_member(&mut json, "type", field.option.option_name);
+
+        if let FieldStrategy::RawCustom = strategy {
+            add_tokens_member(&mut json, "protocol", &field.intype_tokens);
+            add_tokens_member(&mut json, "schema", &field.schema.tokens);
+        } else if field.intype_tokens.is_empty() {
+            add_string_member(&mut json, "in_type", field.option.intype.name());
+        } else {
+            add_tokens_member(&mut json, "in_type", &field.intype_tokens);
+        }
+
+        match strategy {
+            FieldStrategy::Struct | FieldStrategy::ErrorChain => {
+                write!(
+                    json,
+                    ",\"struct_fields\":{}",
+                    field.outtype_or_field_count_int
+                )
+                .unwrap();
+            }
+            _ if !field.outtype_or_field_count_expr.is_empty() => {
+                add_tokens_member(
+                    &mut json,
+                    "out_type",
+                    &field.outtype_or_field_count_expr.tokens,
+                );
+            }
+            _ => {
+                add_string_member(&mut json, "out_type", field.option.outtype.name());
+            }
+        }
+
+        if strategy.is_slice() {
+            json.push_str(",\"array\":\"variable\"");
+        } else if let FieldStrategy::Array = strategy {
+            add_tokens_member(&mut json, "array", &field.array_count.tokens);
+        } else if let FieldStrategy::Capped = strategy {
+            add_tokens_member(&mut json, "max_len", &field.array_count.tokens);
+        } else if let FieldStrategy::PtrLen = strategy {
+            add_tokens_member(&mut json, "length", &field.array_count.tokens);
+        }
+
+        if field.tag.is_empty() {
+            json.push_str(",\"tag\":\"0\"");
+        } else {
+            add_tokens_member(&mut json, "tag", &field.tag.tokens);
+        }
+
+        json.push('}');
+    }
+    json.push_str("]}");
+
+    return json;
+}
+
+/// Appends `,"NAME":"VALUE"`.
+fn add_string_member(json: &mut String, name: &str, value: &str) {
+    write!(json, ",\"{}\":", name).unwrap();
+    add_string(json, value);
+}
+
+/// Appends `,"NAME":"TOKENS"`, where TOKENS is the source text of the tokens.
+fn add_tokens_member(json: &mut String, name: &str, tokens: &TokenStream) {
+    add_string_member(json, name, &tokens.to_string());
+}
+
+/// Appends a JSON string literal.
+fn add_string(json: &mut String, value: &str) {
+    json.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => write!(json, "\\u{:04x}", ch as u32).unwrap(),
+            ch => json.push(ch),
+        }
+    }
+    json.push('"');
+}
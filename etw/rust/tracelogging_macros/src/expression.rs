@@ -3,6 +3,7 @@
 
 use proc_macro::*;
 
+#[derive(Clone)]
 pub struct Expression {
     pub context: Span,
     pub tokens: TokenStream,
@@ -15,4 +15,6 @@ pub struct FieldInfo {
     pub outtype_or_field_count_expr: Expression, // If empty, use outtype_or_field_count_int
     pub outtype_or_field_count_int: u8, // Use only if outtype_or_field_count_expr is empty
     pub tag: Expression,
+    pub array_count: Expression, // Used only if option.strategy is Array, Capped, or PtrLen.
+    pub schema: Expression,      // Used only if option.strategy is RawCustom.
 }
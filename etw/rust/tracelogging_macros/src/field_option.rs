@@ -21,6 +21,10 @@ pub enum FieldStrategy {
     Counted,
     /// meta = array; data = slice_count + from_slice, adds bit to intype.
     Slice,
+    /// meta = array; data = slice_count + from_slice(option_as_slice), adds bit to intype.
+    Optional,
+    /// meta = array + count; data = from_slice, adds bit to intype.
+    Array,
     /// meta = scalar; data = none
     Struct,
     /// meta = scalar; data = none
@@ -37,6 +41,22 @@ pub enum FieldStrategy {
     RawMeta,
     /// meta = array; data = none
     RawMetaSlice,
+    /// meta = scalar + schema size + schema, adds custom bits to intype;
+    /// data = counted_size + from_counted
+    RawCustom,
+    /// meta = struct { Message: Str8, Sources: Str8[] }; data = from_slice(error_chain_data)
+    ErrorChain,
+    /// meta = scalar + U32 length field; data = counted_size(capped_slice) +
+    /// from_counted(capped_slice) + from_value(capped_length)
+    Capped,
+    /// meta = scalar; data = counted_size(FmtBuffer) + from_counted(FmtBuffer)
+    Fmt,
+    /// meta = scalar; data = from_value(caller_address(depth))
+    CallerAddress,
+    /// meta = scalar; data = ptr_len_size + from_ptr_len(ptr, len)
+    PtrLen,
+    /// meta = array; data = from_counted(StrSliceBuffer), adds bit to intype.
+    StrSlice,
 }
 
 impl FieldStrategy {
@@ -53,12 +73,23 @@ impl FieldStrategy {
             | FieldStrategy::RawStruct
             | FieldStrategy::RawData
             | FieldStrategy::RawField
-            | FieldStrategy::RawMeta => false,
+            | FieldStrategy::RawMeta
+            | FieldStrategy::RawCustom
+            | FieldStrategy::ErrorChain
+            | FieldStrategy::Capped
+            | FieldStrategy::Fmt
+            | FieldStrategy::CallerAddress
+            | FieldStrategy::PtrLen => false,
 
             FieldStrategy::Slice
+            | FieldStrategy::Optional
             | FieldStrategy::RawStructSlice
             | FieldStrategy::RawFieldSlice
-            | FieldStrategy::RawMetaSlice => true,
+            | FieldStrategy::RawMetaSlice
+            | FieldStrategy::StrSlice => true,
+
+            // Uses ConstantCountFlag instead of VariableCountFlag.
+            FieldStrategy::Array => false,
         }
     }
 
@@ -81,11 +112,21 @@ impl FieldStrategy {
             | FieldStrategy::Sid
             | FieldStrategy::RawData
             | FieldStrategy::RawField
-            | FieldStrategy::RawFieldSlice => 1,
+            | FieldStrategy::RawFieldSlice
+            | FieldStrategy::Array
+            | FieldStrategy::ErrorChain
+            | FieldStrategy::StrSlice
+            | FieldStrategy::CallerAddress => 1,
 
             | FieldStrategy::CStr       // 1 for data, 1 for nul termination.
             | FieldStrategy::Counted    // 1 for size, 1 for data.
-            | FieldStrategy::Slice => 2,// 1 for size, 1 for data.
+            | FieldStrategy::Fmt        // 1 for size, 1 for data.
+            | FieldStrategy::RawCustom  // 1 for size, 1 for data.
+            | FieldStrategy::PtrLen     // 1 for size, 1 for data.
+            | FieldStrategy::Slice      // 1 for size, 1 for data.
+            | FieldStrategy::Optional => 2,// 1 for size, 1 for data.
+
+            FieldStrategy::Capped => 3, // 1 for size, 1 for data, 1 for original length.
         }
     }
 }
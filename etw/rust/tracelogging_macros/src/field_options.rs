@@ -10,16 +10,27 @@ use crate::strings::*;
 #[rustfmt::skip]
 pub static FIELD_OPTIONS: &[Opt] = &[
     Opt::new("binary",                  U8_PATH,    I::Binary,     O::Default,       Counted,    0),
+    Opt::new("binary_capped",           U8_PATH,    I::Binary,     O::Default,       Capped,     0),
+    Opt::new("binary_ptr_len",          U8_PATH,    I::Binary,     O::Default,       PtrLen,     0),
     Opt::new("binaryc",                 U8_PATH,    I::BinaryC,    O::Default,       Counted,    0),
     Opt::new("bool32",                  I32_PATH,   I::Bool32,     O::Default,       Scalar,     0),
+    Opt::new("bool32_array",            I32_PATH,   I::Bool32,     O::Default,       Array,      0),
     Opt::new("bool32_slice",            I32_PATH,   I::Bool32,     O::Default,       Slice,      0),
     Opt::new("bool8",                   BOOL_PATH,  I::U8,         O::Boolean,       Scalar,     0),
+    Opt::new("bool8_array",             BOOL_PATH,  I::U8,         O::Boolean,       Array,      0),
     Opt::new("bool8_slice",             BOOL_PATH,  I::U8,         O::Boolean,       Slice,      0),
+    Opt::new("caller_codepointer",      USIZE_PATH, I::HexSize,    O::CodePointer,   CallerAddress, 0),
     Opt::new("char16",                  U16_PATH,   I::U16,        O::String,        Scalar,     0),
+    Opt::new("char16_array",            U16_PATH,   I::U16,        O::String,        Array,      0),
     Opt::new("char16_slice",            U16_PATH,   I::U16,        O::String,        Slice,      0),
+    Opt::new("char32",                  CHAR_PATH,  I::U32,        O::String,        Scalar,     0),
+    Opt::new("char32_array",            CHAR_PATH,  I::U32,        O::String,        Array,      0),
+    Opt::new("char32_slice",            CHAR_PATH,  I::U32,        O::String,        Slice,      0),
     Opt::new("char8_cp1252",            U8_PATH,    I::U8,         O::String,        Scalar,     0),
+    Opt::new("char8_cp1252_array",      U8_PATH,    I::U8,         O::String,        Array,      0),
     Opt::new("char8_cp1252_slice",      U8_PATH,    I::U8,         O::String,        Slice,      0),
     Opt::new("codepointer",             USIZE_PATH, I::HexSize,    O::CodePointer,   Scalar,     0),
+    Opt::new("codepointer_array",       USIZE_PATH, I::HexSize,    O::CodePointer,   Array,      0),
     Opt::new("codepointer_slice",       USIZE_PATH, I::HexSize,    O::CodePointer,   Slice,      0),
     Opt::new("cstr16",                  U16_PATH,   I::CStr16,     O::Default,       CStr,       0),
     Opt::new("cstr16_json",             U16_PATH,   I::CStr16,     O::Json,          CStr,       0),
@@ -29,45 +40,108 @@ pub static FIELD_OPTIONS: &[Opt] = &[
     Opt::new("cstr8_json",              U8_PATH,    I::CStr8,      O::Json,          CStr,       0),
     Opt::new("cstr8_xml",               U8_PATH,    I::CStr8,      O::Xml,           CStr,       0),
     Opt::new("errno",                   I32_PATH,   I::I32,        O::Default,       Scalar,     0),
+    Opt::new("errno_array",             I32_PATH,   I::I32,        O::Default,       Array,      0),
     Opt::new("errno_slice",             I32_PATH,   I::I32,        O::Default,       Slice,      0),
+    Opt::new("error",                   U8_PATH,    I::Struct,     O::Default,       ErrorChain, 0),
     Opt::new("f32",                     F32_PATH,   I::F32,        O::Default,       Scalar,     0),
+    Opt::new("f32_array",               F32_PATH,   I::F32,        O::Default,       Array,      0),
     Opt::new("f32_slice",               F32_PATH,   I::F32,        O::Default,       Slice,      0),
     Opt::new("f64",                     F64_PATH,   I::F64,        O::Default,       Scalar,     0),
+    Opt::new("f64_array",               F64_PATH,   I::F64,        O::Default,       Array,      0),
     Opt::new("f64_slice",               F64_PATH,   I::F64,        O::Default,       Slice,      0),
+    Opt::new("fmt",                     U8_PATH,    I::Str8,       O::Utf8,          Fmt,        0),
     Opt::new("guid",                    GUID_PATH,  I::Guid,       O::Default,       Scalar,     0),
+    Opt::new("guid_array",              GUID_PATH,  I::Guid,       O::Default,       Array,      0),
     Opt::new("guid_slice",              GUID_PATH,  I::Guid,       O::Default,       Slice,      0),
     Opt::new("hresult",                 I32_PATH,   I::I32,        O::HResult,       Scalar,     0),
+    Opt::new("hresult_array",           I32_PATH,   I::I32,        O::HResult,       Array,      0),
     Opt::new("hresult_slice",           I32_PATH,   I::I32,        O::HResult,       Slice,      0),
     Opt::new("i16",                     I16_PATH,   I::I16,        O::Default,       Scalar,     0),
+    Opt::new("i16_array",               I16_PATH,   I::I16,        O::Default,       Array,      0),
     Opt::new("i16_hex",                 I16_PATH,   I::U16,        O::Hex,           Scalar,     0),
+    Opt::new("i16_hex_array",           I16_PATH,   I::U16,        O::Hex,           Array,      0),
     Opt::new("i16_hex_slice",           I16_PATH,   I::U16,        O::Hex,           Slice,      0),
     Opt::new("i16_slice",               I16_PATH,   I::I16,        O::Default,       Slice,      0),
     Opt::new("i32",                     I32_PATH,   I::I32,        O::Default,       Scalar,     0),
+    Opt::new("i32_array",               I32_PATH,   I::I32,        O::Default,       Array,      0),
     Opt::new("i32_hex",                 I32_PATH,   I::Hex32,      O::Default,       Scalar,     0),
+    Opt::new("i32_hex_array",           I32_PATH,   I::Hex32,      O::Default,       Array,      0),
     Opt::new("i32_hex_slice",           I32_PATH,   I::Hex32,      O::Default,       Slice,      0),
     Opt::new("i32_slice",               I32_PATH,   I::I32,        O::Default,       Slice,      0),
     Opt::new("i64",                     I64_PATH,   I::I64,        O::Default,       Scalar,     0),
+    Opt::new("i64_array",               I64_PATH,   I::I64,        O::Default,       Array,      0),
     Opt::new("i64_hex",                 I64_PATH,   I::Hex64,      O::Default,       Scalar,     0),
+    Opt::new("i64_hex_array",           I64_PATH,   I::Hex64,      O::Default,       Array,      0),
     Opt::new("i64_hex_slice",           I64_PATH,   I::Hex64,      O::Default,       Slice,      0),
     Opt::new("i64_slice",               I64_PATH,   I::I64,        O::Default,       Slice,      0),
     Opt::new("i8",                      I8_PATH,    I::I8,         O::Default,       Scalar,     0),
+    Opt::new("i8_array",                I8_PATH,    I::I8,         O::Default,       Array,      0),
     Opt::new("i8_hex",                  I8_PATH,    I::U8,         O::Hex,           Scalar,     0),
+    Opt::new("i8_hex_array",            I8_PATH,    I::U8,         O::Hex,           Array,      0),
     Opt::new("i8_hex_slice",            I8_PATH,    I::U8,         O::Hex,           Slice,      0),
     Opt::new("i8_slice",                I8_PATH,    I::I8,         O::Default,       Slice,      0),
     Opt::new("ipv4",                    U8_PATH,    I::U32,        O::IPv4,          Scalar,     4),
+    Opt::new("ipv4_array",              U8_PATH,    I::U32,        O::IPv4,          Array,      4),
     Opt::new("ipv4_slice",              U8_PATH,    I::U32,        O::IPv4,          Slice,      4),
     Opt::new("ipv6",                    U8_PATH,    I::Binary,     O::IPv6,          Counted,    16),
     Opt::new("ipv6c",                   U8_PATH,    I::BinaryC,    O::IPv6,          Counted,    16),
     Opt::new("isize",                   ISIZE_PATH, I::ISize,      O::Default,       Scalar,     0),
+    Opt::new("isize_array",             ISIZE_PATH, I::ISize,      O::Default,       Array,      0),
     Opt::new("isize_hex",               ISIZE_PATH, I::HexSize,    O::Default,       Scalar,     0),
+    Opt::new("isize_hex_array",         ISIZE_PATH, I::HexSize,    O::Default,       Array,      0),
     Opt::new("isize_hex_slice",         ISIZE_PATH, I::HexSize,    O::Default,       Slice,      0),
     Opt::new("isize_slice",             ISIZE_PATH, I::ISize,      O::Default,       Slice,      0),
+    Opt::new("opt_bool32",              I32_PATH,   I::Bool32,     O::Default,       Optional,   0),
+    Opt::new("opt_bool8",               BOOL_PATH,  I::U8,         O::Boolean,       Optional,   0),
+    Opt::new("opt_char16",              U16_PATH,   I::U16,        O::String,        Optional,   0),
+    Opt::new("opt_char32",              CHAR_PATH,  I::U32,        O::String,        Optional,   0),
+    Opt::new("opt_char8_cp1252",        U8_PATH,    I::U8,         O::String,        Optional,   0),
+    Opt::new("opt_codepointer",         USIZE_PATH, I::HexSize,    O::CodePointer,   Optional,   0),
+    Opt::new("opt_errno",               I32_PATH,   I::I32,        O::Default,       Optional,   0),
+    Opt::new("opt_f32",                 F32_PATH,   I::F32,        O::Default,       Optional,   0),
+    Opt::new("opt_f64",                 F64_PATH,   I::F64,        O::Default,       Optional,   0),
+    Opt::new("opt_guid",                GUID_PATH,  I::Guid,       O::Default,       Optional,   0),
+    Opt::new("opt_hresult",             I32_PATH,   I::I32,        O::HResult,       Optional,   0),
+    Opt::new("opt_i16",                 I16_PATH,   I::I16,        O::Default,       Optional,   0),
+    Opt::new("opt_i16_hex",             I16_PATH,   I::U16,        O::Hex,           Optional,   0),
+    Opt::new("opt_i32",                 I32_PATH,   I::I32,        O::Default,       Optional,   0),
+    Opt::new("opt_i32_hex",             I32_PATH,   I::Hex32,      O::Default,       Optional,   0),
+    Opt::new("opt_i64",                 I64_PATH,   I::I64,        O::Default,       Optional,   0),
+    Opt::new("opt_i64_hex",             I64_PATH,   I::Hex64,      O::Default,       Optional,   0),
+    Opt::new("opt_i8",                  I8_PATH,    I::I8,         O::Default,       Optional,   0),
+    Opt::new("opt_i8_hex",              I8_PATH,    I::U8,         O::Hex,           Optional,   0),
+    Opt::new("opt_ipv4",                U8_PATH,    I::U32,        O::IPv4,          Optional,   4),
+    Opt::new("opt_isize",               ISIZE_PATH, I::ISize,      O::Default,       Optional,   0),
+    Opt::new("opt_isize_hex",           ISIZE_PATH, I::HexSize,    O::Default,       Optional,   0),
+    Opt::new("opt_pid",                 U32_PATH,   I::U32,        O::Pid,           Optional,   0),
+    Opt::new("opt_pointer",             USIZE_PATH, I::HexSize,    O::Default,       Optional,   0),
+    Opt::new("opt_port",                U16_PATH,   I::U16,        O::Port,          Optional,   0),
+    Opt::new("opt_tid",                 U32_PATH,   I::U32,        O::Tid,           Optional,   0),
+    Opt::new("opt_u16",                 U16_PATH,   I::U16,        O::Default,       Optional,   0),
+    Opt::new("opt_u16_hex",             U16_PATH,   I::U16,        O::Hex,           Optional,   0),
+    Opt::new("opt_u32",                 U32_PATH,   I::U32,        O::Default,       Optional,   0),
+    Opt::new("opt_u32_hex",             U32_PATH,   I::Hex32,      O::Default,       Optional,   0),
+    Opt::new("opt_u64",                 U64_PATH,   I::U64,        O::Default,       Optional,   0),
+    Opt::new("opt_u64_hex",             U64_PATH,   I::Hex64,      O::Default,       Optional,   0),
+    Opt::new("opt_u8",                  U8_PATH,    I::U8,         O::Default,       Optional,   0),
+    Opt::new("opt_u8_hex",              U8_PATH,    I::U8,         O::Hex,           Optional,   0),
+    Opt::new("opt_usize",               USIZE_PATH, I::USize,      O::Default,       Optional,   0),
+    Opt::new("opt_usize_hex",           USIZE_PATH, I::HexSize,    O::Default,       Optional,   0),
+    Opt::new("opt_win_error",           U32_PATH,   I::U32,        O::Win32Error,    Optional,   0),
+    Opt::new("opt_win_filetime",        I64_PATH,   I::FileTime,   O::Default,       Optional,   0),
+    Opt::new("opt_win_ntstatus",        I32_PATH,   I::Hex32,      O::NtStatus,      Optional,   0),
+    Opt::new("opt_win_systemtime",      U16_PATH,   I::SystemTime, O::Default,       Optional,   8),
+    Opt::new("opt_win_systemtime_utc",  U16_PATH,   I::SystemTime, O::DateTimeUtc,   Optional,   8),
     Opt::new("pid",                     U32_PATH,   I::U32,        O::Pid,           Scalar,     0),
+    Opt::new("pid_array",               U32_PATH,   I::U32,        O::Pid,           Array,      0),
     Opt::new("pid_slice",               U32_PATH,   I::U32,        O::Pid,           Slice,      0),
     Opt::new("pointer",                 USIZE_PATH, I::HexSize,    O::Default,       Scalar,     0),
+    Opt::new("pointer_array",           USIZE_PATH, I::HexSize,    O::Default,       Array,      0),
     Opt::new("pointer_slice",           USIZE_PATH, I::HexSize,    O::Default,       Slice,      0),
     Opt::new("port",                    U16_PATH,   I::U16,        O::Port,          Scalar,     0),
+    Opt::new("port_array",              U16_PATH,   I::U16,        O::Port,          Array,      0),
     Opt::new("port_slice",              U16_PATH,   I::U16,        O::Port,          Slice,      0),
+    Opt::new("raw_custom",              U8_PATH,    I::Invalid,    O::Default,       RawCustom,      0),
     Opt::new("raw_data",                U8_PATH,    I::Invalid,    O::Default,       RawData,        0),
     Opt::new("raw_field",               U8_PATH,    I::Invalid,    O::Default,       RawField,       0),
     Opt::new("raw_field_slice",         U8_PATH,    I::Invalid,    O::Default,       RawFieldSlice,  0),
@@ -79,46 +153,65 @@ pub static FIELD_OPTIONS: &[Opt] = &[
     Opt::new("socketaddressc",          U8_PATH,    I::BinaryC,    O::SocketAddress, Counted,    0),
     Opt::new("str16",                   U16_PATH,   I::Str16,      O::Default,       Counted,    0),
     Opt::new("str16_json",              U16_PATH,   I::Str16,      O::Json,          Counted,    0),
+    Opt::new("str16_slice",             U16_PATH,   I::Str16,      O::Default,       StrSlice,   0),
     Opt::new("str16_xml",               U16_PATH,   I::Str16,      O::Xml,           Counted,    0),
+    Opt::new("str32",                   CHAR_PATH,  I::U32,        O::String,        Slice,      0),
     Opt::new("str8",                    U8_PATH,    I::Str8,       O::Utf8,          Counted,    0),
     Opt::new("str8_cp1252",             U8_PATH,    I::Str8,       O::Default,       Counted,    0),
     Opt::new("str8_json",               U8_PATH,    I::Str8,       O::Json,          Counted,    0),
+    Opt::new("str8_slice",              U8_PATH,    I::Str8,       O::Utf8,          StrSlice,   0),
     Opt::new("str8_xml",                U8_PATH,    I::Str8,       O::Xml,           Counted,    0),
     Opt::new("struct",                  &[],        I::Struct,     O::Default,       Struct,     0),
     Opt::new("systemtime",              I64_PATH,   I::FileTime,   O::Default,       SystemTime, 0),
     Opt::new("tid",                     U32_PATH,   I::U32,        O::Tid,           Scalar,     0),
+    Opt::new("tid_array",               U32_PATH,   I::U32,        O::Tid,           Array,      0),
     Opt::new("tid_slice",               U32_PATH,   I::U32,        O::Tid,           Slice,      0),
     Opt::new("time32",                  I64_PATH,   I::FileTime,   O::Default,       Time32,     0),
     Opt::new("time64",                  I64_PATH,   I::FileTime,   O::Default,       Time64,     0),
     Opt::new("u16",                     U16_PATH,   I::U16,        O::Default,       Scalar,     0),
+    Opt::new("u16_array",               U16_PATH,   I::U16,        O::Default,       Array,      0),
     Opt::new("u16_hex",                 U16_PATH,   I::U16,        O::Hex,           Scalar,     0),
+    Opt::new("u16_hex_array",           U16_PATH,   I::U16,        O::Hex,           Array,      0),
     Opt::new("u16_hex_slice",           U16_PATH,   I::U16,        O::Hex,           Slice,      0),
     Opt::new("u16_slice",               U16_PATH,   I::U16,        O::Default,       Slice,      0),
     Opt::new("u32",                     U32_PATH,   I::U32,        O::Default,       Scalar,     0),
+    Opt::new("u32_array",               U32_PATH,   I::U32,        O::Default,       Array,      0),
     Opt::new("u32_hex",                 U32_PATH,   I::Hex32,      O::Default,       Scalar,     0),
+    Opt::new("u32_hex_array",           U32_PATH,   I::Hex32,      O::Default,       Array,      0),
     Opt::new("u32_hex_slice",           U32_PATH,   I::Hex32,      O::Default,       Slice,      0),
     Opt::new("u32_slice",               U32_PATH,   I::U32,        O::Default,       Slice,      0),
     Opt::new("u64",                     U64_PATH,   I::U64,        O::Default,       Scalar,     0),
+    Opt::new("u64_array",               U64_PATH,   I::U64,        O::Default,       Array,      0),
     Opt::new("u64_hex",                 U64_PATH,   I::Hex64,      O::Default,       Scalar,     0),
+    Opt::new("u64_hex_array",           U64_PATH,   I::Hex64,      O::Default,       Array,      0),
     Opt::new("u64_hex_slice",           U64_PATH,   I::Hex64,      O::Default,       Slice,      0),
     Opt::new("u64_slice",               U64_PATH,   I::U64,        O::Default,       Slice,      0),
     Opt::new("u8",                      U8_PATH,    I::U8,         O::Default,       Scalar,     0),
+    Opt::new("u8_array",                U8_PATH,    I::U8,         O::Default,       Array,      0),
     Opt::new("u8_hex",                  U8_PATH,    I::U8,         O::Hex,           Scalar,     0),
+    Opt::new("u8_hex_array",            U8_PATH,    I::U8,         O::Hex,           Array,      0),
     Opt::new("u8_hex_slice",            U8_PATH,    I::U8,         O::Hex,           Slice,      0),
     Opt::new("u8_slice",                U8_PATH,    I::U8,         O::Default,       Slice,      0),
     Opt::new("usize",                   USIZE_PATH, I::USize,      O::Default,       Scalar,     0),
+    Opt::new("usize_array",             USIZE_PATH, I::USize,      O::Default,       Array,      0),
     Opt::new("usize_hex",               USIZE_PATH, I::HexSize,    O::Default,       Scalar,     0),
+    Opt::new("usize_hex_array",         USIZE_PATH, I::HexSize,    O::Default,       Array,      0),
     Opt::new("usize_hex_slice",         USIZE_PATH, I::HexSize,    O::Default,       Slice,      0),
     Opt::new("usize_slice",             USIZE_PATH, I::USize,      O::Default,       Slice,      0),
     Opt::new("win_error",               U32_PATH,   I::U32,        O::Win32Error,    Scalar,     0),
+    Opt::new("win_error_array",         U32_PATH,   I::U32,        O::Win32Error,    Array,      0),
     Opt::new("win_error_slice",         U32_PATH,   I::U32,        O::Win32Error,    Slice,      0),
     Opt::new("win_filetime",            I64_PATH,   I::FileTime,   O::Default,       Scalar,     0),
+    Opt::new("win_filetime_array",      I64_PATH,   I::FileTime,   O::Default,       Array,      0),
     Opt::new("win_filetime_slice",      I64_PATH,   I::FileTime,   O::Default,       Slice,      0),
     Opt::new("win_ntstatus",            I32_PATH,   I::Hex32,      O::NtStatus,      Scalar,     0),
+    Opt::new("win_ntstatus_array",      I32_PATH,   I::Hex32,      O::NtStatus,      Array,      0),
     Opt::new("win_ntstatus_slice",      I32_PATH,   I::Hex32,      O::NtStatus,      Slice,      0),
     Opt::new("win_sid",                 U8_PATH,    I::Sid,        O::Default,       Sid,        0),
     Opt::new("win_systemtime",          U16_PATH,   I::SystemTime, O::Default,       Scalar,     8),
+    Opt::new("win_systemtime_array",    U16_PATH,   I::SystemTime, O::Default,       Array,      8),
     Opt::new("win_systemtime_slice",    U16_PATH,   I::SystemTime, O::Default,       Slice,      8),
     Opt::new("win_systemtime_utc",      U16_PATH,   I::SystemTime, O::DateTimeUtc,   Scalar,     8),
+    Opt::new("win_systemtime_utc_array",U16_PATH,   I::SystemTime, O::DateTimeUtc,   Array,      8),
     Opt::new("win_systemtime_utc_slice",U16_PATH,   I::SystemTime, O::DateTimeUtc,   Slice,      8),
 ];
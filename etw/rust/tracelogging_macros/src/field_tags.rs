@@ -0,0 +1,232 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Implements `define_field_tags!`: generates a module of named field tag constants
+//! and a `TAGS` registry for looking up tags by value or name.
+
+use proc_macro::*;
+
+use crate::errors::Errors;
+use crate::parser::{unescape, ArgConstraints::*, Parser};
+use crate::provider_info::parse_u64_literal;
+use crate::strings::*;
+use crate::tree::Tree;
+
+const EXPECTED_TAG: &str =
+    "expected NAME = VALUE (VALUE must be an integer literal 0x1..=0x0FFFFFFF), e.g. PII = 0x01000000";
+const MAX_TAG: u64 = 0x0FFFFFFF;
+
+struct FieldTagInfo {
+    name: Ident,
+    value: u32,
+    /// The `#[doc = "..."]` attributes (from `///` comments) for the tag.
+    doc_attributes: Vec<TokenTree>,
+    /// The text of the doc comment, one line per attribute.
+    doc: String,
+}
+
+pub fn define_field_tags(arg_span: Span, arg_tokens: TokenStream) -> TokenStream {
+    let mut errors = Errors::new();
+    let mut parser = Parser::new(&mut errors, arg_span, arg_tokens);
+    let mut tags = Vec::<FieldTagInfo>::new();
+
+    let symbol = parser.next_ident(
+        RequiredNotLast,
+        "expected identifier for tags symbol, e.g. MY_TAGS",
+    );
+
+    loop {
+        let tokens: Vec<TokenTree> = parser
+            .next_tokens(Optional, EXPECTED_TAG)
+            .into_iter()
+            .collect();
+        if tokens.is_empty() {
+            break;
+        }
+
+        // Leading #[doc = "..."] attributes.
+        let mut doc_attributes = Vec::new();
+        let mut doc = String::new();
+        let mut pos = 0;
+        while let [TokenTree::Punct(pound), TokenTree::Group(group), ..] = &tokens[pos..] {
+            if pound.as_char() != '#' || group.delimiter() != Delimiter::Bracket {
+                break;
+            }
+
+            match doc_text(group) {
+                Some(text) => {
+                    if !doc_attributes.is_empty() {
+                        doc.push('\n');
+                    }
+                    doc.push_str(text.strip_prefix(' ').unwrap_or(&text));
+                }
+                None => parser
+                    .errors()
+                    .add(group.span(), "expected doc comment, e.g. /// Description"),
+            }
+            doc_attributes.extend_from_slice(&tokens[pos..pos + 2]);
+            pos += 2;
+        }
+
+        // NAME = VALUE
+        let (name, value) = match &tokens[pos..] {
+            [TokenTree::Ident(name), TokenTree::Punct(eq), TokenTree::Literal(value)]
+                if eq.as_char() == '=' =>
+            {
+                match parse_u64_literal(value) {
+                    Some(0) => {
+                        parser.errors().add(value.span(), "tag value must not be 0");
+                        continue;
+                    }
+                    Some(value_int) if value_int > MAX_TAG => {
+                        parser.errors().add(
+                            value.span(),
+                            "tag value must not be greater than 0x0FFFFFFF",
+                        );
+                        continue;
+                    }
+                    Some(value_int) => (name.clone(), value_int as u32),
+                    None => {
+                        parser.errors().add(value.span(), EXPECTED_TAG);
+                        continue;
+                    }
+                }
+            }
+            [] => {
+                parser.errors().add(tokens[0].span(), EXPECTED_TAG);
+                continue;
+            }
+            rest => {
+                parser.errors().add(rest[0].span(), EXPECTED_TAG);
+                continue;
+            }
+        };
+
+        let name_str = name.to_string();
+        if let Some(other) = tags.iter().find(|t| t.name.to_string() == name_str) {
+            parser
+                .errors()
+                .add(name.span(), &format!("tag {} already defined", other.name));
+        } else if let Some(other) = tags.iter().find(|t| t.value == value) {
+            parser.errors().add(
+                name.span(),
+                &format!("tag {} value is the same as tag {}", name_str, other.name),
+            );
+        } else {
+            tags.push(FieldTagInfo {
+                name,
+                value,
+                doc_attributes,
+                doc,
+            });
+        }
+    }
+
+    let symbol = match symbol {
+        Some(symbol) if errors.is_empty() => symbol,
+        _ => return errors.into_items(),
+    };
+
+    return generate(arg_span, symbol, tags);
+}
+
+/// Returns the text of a `doc = "..."` attribute, or None if the attribute is not a
+/// doc attribute.
+fn doc_text(group: &Group) -> Option<String> {
+    let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+    return match tokens.as_slice() {
+        [TokenTree::Ident(doc), TokenTree::Punct(eq), TokenTree::Literal(lit)]
+            if doc.to_string() == "doc" && eq.as_char() == '=' =>
+        {
+            let lit_str = lit.to_string();
+            if let Some(raw) = lit_str.strip_prefix('r') {
+                // Raw string: r"...", r#"..."#, etc.
+                let hashes = raw.len() - raw.trim_start_matches('#').len();
+                raw.get(hashes + 1..raw.len() - hashes - 1)
+                    .map(|text| text.to_string())
+            } else if lit_str.len() >= 2 && lit_str.starts_with('"') && lit_str.ends_with('"') {
+                unescape(&lit_str[1..lit_str.len() - 1])
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+}
+
+/// Generates:
+///
+/// ```ignore
+/// #[allow(non_snake_case, dead_code)]
+/// mod SYMBOL {
+///     #[doc = "..."]
+///     pub const NAME: u32 = VALUE; // For each tag.
+///     pub const TAGS: ::tracelogging::FieldTags = ::tracelogging::FieldTags::new(&[
+///         ::tracelogging::FieldTag::new("NAME", VALUE, "doc"), ...
+///     ]);
+/// }
+/// ```
+fn generate(arg_span: Span, symbol: Ident, tags: Vec<FieldTagInfo>) -> TokenStream {
+    let mut mod_tree = Tree::new(arg_span);
+    let mut list_tree = Tree::new(arg_span);
+    let mut scratch_tree = Tree::new(arg_span);
+
+    for tag in tags {
+        // #[doc = "..."] pub const NAME: u32 = VALUE;
+        mod_tree
+            .add_tokens(tag.doc_attributes)
+            .push_span(tag.name.span())
+            .add_ident("pub")
+            .add_const_from_tokens(
+                &tag.name.to_string(),
+                U32_PATH,
+                [TokenTree::from(Literal::u32_unsuffixed(tag.value))],
+            )
+            .pop_span();
+
+        // ::tracelogging::FieldTag::new("NAME", VALUE, "doc"),
+        list_tree
+            .add_path_call(
+                FIELD_TAG_NEW_PATH,
+                scratch_tree
+                    .add_literal(Literal::string(&tag.name.to_string()))
+                    .add_punct(",")
+                    .add_literal(Literal::u32_unsuffixed(tag.value))
+                    .add_punct(",")
+                    .add_literal(Literal::string(&tag.doc))
+                    .drain(),
+            )
+            .add_punct(",");
+    }
+
+    // pub const TAGS: ::tracelogging::FieldTags = ::tracelogging::FieldTags::new(&[...]);
+    mod_tree.add_ident("pub").add_const_from_tokens(
+        "TAGS",
+        FIELD_TAGS_PATH,
+        scratch_tree
+            .add_path_call(
+                FIELD_TAGS_NEW_PATH,
+                [
+                    TokenTree::from(Punct::new('&', Spacing::Alone)),
+                    TokenTree::from(Group::new(Delimiter::Bracket, list_tree.drain().collect())),
+                ],
+            )
+            .drain(),
+    );
+
+    // #[allow(non_snake_case, dead_code)] mod SYMBOL { ... }
+    return scratch_tree
+        .add_outer_attribute(
+            "allow",
+            [
+                Ident::new("non_snake_case", symbol.span()).into(),
+                Punct::new(',', Spacing::Alone).into(),
+                Ident::new("dead_code", symbol.span()).into(),
+            ],
+        )
+        .add_ident("mod")
+        .add_token(symbol)
+        .add_group_curly(mod_tree.drain())
+        .drain()
+        .collect();
+}
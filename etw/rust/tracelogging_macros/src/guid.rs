@@ -79,6 +79,20 @@ impl Guid {
         return Guid::from_bytes_le(v[0..16].try_into().unwrap());
     }
 
+    /// Returns a name-based (version 5) UUID as specified by RFC 4122: a SHA-1 hash of
+    /// the namespace id (in RFC byte order) followed by the UTF-8 bytes of the name.
+    /// Unlike [`Guid::from_name`], the name is case-sensitive.
+    pub fn from_name_v5(namespace: &Guid, name: &str) -> Self {
+        let mut hasher = Sha1NonSecret::new();
+        hasher.write(&namespace.to_bytes_be());
+        hasher.write(name.as_bytes());
+
+        let mut v = hasher.finish();
+        v[6] = (v[6] & 0x0F) | 0x50; // Version 5
+        v[8] = (v[8] & 0x3F) | 0x80; // RFC 4122 variant
+        return Guid::from_bytes_be(v[0..16].try_into().unwrap());
+    }
+
     /// Creates a GUID from field values.
     /// ```
     /// # use tracelogging::Guid;
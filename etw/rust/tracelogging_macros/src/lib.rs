@@ -12,6 +12,7 @@ use crate::event_generator::EventGenerator;
 use crate::event_info::EventInfo;
 use crate::provider_generator::ProviderGenerator;
 use crate::provider_info::ProviderInfo;
+use crate::tree::Tree;
 
 #[proc_macro]
 pub fn define_provider(arg_tokens: TokenStream) -> TokenStream {
@@ -31,6 +32,65 @@ pub fn write_event(arg_tokens: TokenStream) -> TokenStream {
     };
 }
 
+#[proc_macro]
+pub fn try_write_event(arg_tokens: TokenStream) -> TokenStream {
+    let call_site = Span::call_site();
+    return match EventInfo::try_from_tokens(call_site, arg_tokens) {
+        Err(error_tokens) => error_tokens,
+        Ok(mut prov) => {
+            prov.require_registered = true;
+            EventGenerator::new(call_site).generate(prov)
+        }
+    };
+}
+
+#[proc_macro]
+pub fn write_event_cpp_style(arg_tokens: TokenStream) -> TokenStream {
+    let call_site = Span::call_site();
+    return match cpp_style::translate(call_site, arg_tokens)
+        .and_then(|event_tokens| EventInfo::try_from_tokens(call_site, event_tokens))
+    {
+        Err(error_tokens) => error_tokens,
+        Ok(prov) => EventGenerator::new(call_site).generate(prov),
+    };
+}
+
+#[proc_macro]
+pub fn define_field_tags(arg_tokens: TokenStream) -> TokenStream {
+    return field_tags::define_field_tags(Span::call_site(), arg_tokens);
+}
+
+#[proc_macro]
+pub fn include_event_catalog(arg_tokens: TokenStream) -> TokenStream {
+    return catalog::include_event_catalog(Span::call_site(), arg_tokens);
+}
+
+#[proc_macro]
+pub fn include_manifest(arg_tokens: TokenStream) -> TokenStream {
+    return manifest::include_manifest(Span::call_site(), arg_tokens);
+}
+
+#[proc_macro_derive(TraceLoggingEvent, attributes(event))]
+pub fn derive_trace_logging_event(input: TokenStream) -> TokenStream {
+    return derive_event::derive_trace_logging_event(Span::call_site(), input);
+}
+
+#[proc_macro]
+pub fn activity_scope(arg_tokens: TokenStream) -> TokenStream {
+    let call_site = Span::call_site();
+    return match EventInfo::try_activity_scope_from_tokens(call_site, arg_tokens) {
+        Err(error_tokens) => error_tokens,
+        Ok(mut start) => {
+            let mut scratch_tree = Tree::new(call_site);
+            let stop = start.split_activity_stop(call_site, &mut scratch_tree);
+            EventGenerator::new(call_site).generate_activity_scope(
+                EventGenerator::new(call_site).generate(start),
+                EventGenerator::new(call_site).generate(stop),
+            )
+        }
+    };
+}
+
 // The tracelogging crate depends on the tracelogging_macros crate so the
 // tracelogging_macros crate can't depend on the tracelogging crate. Instead, pull in
 // the source code for needed modules.
@@ -38,15 +98,21 @@ pub fn write_event(arg_tokens: TokenStream) -> TokenStream {
 #[allow(dead_code)]
 mod guid;
 
+mod catalog;
+mod cpp_style;
+mod derive_event;
 mod enums;
 mod errors;
 mod event_generator;
 mod event_info;
+mod event_ir;
 mod expression;
 mod field_info;
+mod field_tags;
 mod field_option;
 mod field_options;
 mod ident_builder;
+mod manifest;
 mod parser;
 mod provider_generator;
 mod provider_info;
@@ -0,0 +1,720 @@
+// Copyright (c) Microsoft Corporation.
+// Licensed under the MIT license.
+
+//! Implements `include_manifest!`: reads an ETW instrumentation manifest (.man) and
+//! generates a `define_provider!` and a typed event-writing function for each event.
+
+use proc_macro::*;
+use std::fmt::Write;
+use std::fs;
+use std::path;
+use std::str::FromStr;
+
+use crate::errors::Errors;
+use crate::parser::{ArgConstraints::*, Parser};
+
+/// Standard levels (winmeta.xml).
+const WIN_LEVELS: &[(&str, u8)] = &[
+    ("win:LogAlways", 0),
+    ("win:Critical", 1),
+    ("win:Error", 2),
+    ("win:Warning", 3),
+    ("win:Informational", 4),
+    ("win:Verbose", 5),
+];
+
+/// Standard opcodes (winmeta.xml).
+const WIN_OPCODES: &[(&str, u8)] = &[
+    ("win:Info", 0),
+    ("win:Start", 1),
+    ("win:Stop", 2),
+    ("win:DC_Start", 3),
+    ("win:DC_Stop", 4),
+    ("win:Extension", 5),
+    ("win:Reply", 6),
+    ("win:Resume", 7),
+    ("win:Suspend", 8),
+    ("win:Send", 9),
+    ("win:Receive", 240),
+];
+
+/// Standard keywords (winmeta.xml).
+const WIN_KEYWORDS: &[(&str, u64)] = &[
+    ("win:ResponseTime", 0x0001_0000_0000_0000),
+    ("win:WDIContext", 0x0002_0000_0000_0000),
+    ("win:WDIDiag", 0x0004_0000_0000_0000),
+    ("win:SQM", 0x0008_0000_0000_0000),
+    ("win:AuditFailure", 0x0010_0000_0000_0000),
+    ("win:AuditSuccess", 0x0020_0000_0000_0000),
+    ("win:CorrelationHint", 0x0040_0000_0000_0000),
+    ("win:EventlogClassic", 0x0080_0000_0000_0000),
+];
+
+/// Manifest inType (without `win:` prefix) and the corresponding write_event! field
+/// type and Rust parameter type.
+const IN_TYPES: &[(&str, &str, &str)] = &[
+    ("AnsiString", "str8", "&str"),
+    ("Binary", "binary", "&[u8]"),
+    ("Boolean", "bool32", "&i32"),
+    ("CountedAnsiString", "str8", "&str"),
+    ("CountedString", "str8", "&str"),
+    ("Double", "f64", "&f64"),
+    ("FILETIME", "win_filetime", "&i64"),
+    ("Float", "f32", "&f32"),
+    ("GUID", "guid", "&::tracelogging::Guid"),
+    ("HexInt32", "u32_hex", "&u32"),
+    ("HexInt64", "u64_hex", "&u64"),
+    ("Int16", "i16", "&i16"),
+    ("Int32", "i32", "&i32"),
+    ("Int64", "i64", "&i64"),
+    ("Int8", "i8", "&i8"),
+    ("Pointer", "pointer", "&usize"),
+    ("SID", "win_sid", "&[u8]"),
+    ("SYSTEMTIME", "win_systemtime", "&[u16; 8]"),
+    ("UInt16", "u16", "&u16"),
+    ("UInt32", "u32", "&u32"),
+    ("UInt64", "u64", "&u64"),
+    ("UInt8", "u8", "&u8"),
+    ("UnicodeString", "str8", "&str"),
+];
+
+/// (inType, outType) combinations that map to a more specific field type.
+const OUT_TYPES: &[(&str, &str, &str)] = &[
+    ("Int32", "win:HResult", "hresult"),
+    ("UInt16", "win:Port", "port"),
+    ("UInt32", "win:PID", "pid"),
+    ("UInt32", "win:TID", "tid"),
+    ("UInt32", "win:Win32Error", "win_error"),
+    ("HexInt32", "win:HResult", "hresult"),
+    ("HexInt32", "win:Win32Error", "win_error"),
+    ("HexInt32", "win:NTStatus", "win_ntstatus"),
+];
+
+/// Field types that have a `TYPE_slice` variant (used for data with a `count`).
+const SLICE_TYPES: &[&str] = &[
+    "bool32",
+    "f32",
+    "f64",
+    "guid",
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "pointer",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "u32_hex",
+    "u64_hex",
+    "win_filetime",
+    "win_systemtime",
+];
+
+/// An XML element: name (without namespace prefix), attributes, and child elements.
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attr(&self, name: &str) -> Option<&str> {
+        return self
+            .attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str());
+    }
+
+    /// Returns the children of the named child, e.g. `grandchildren("events", "event")`.
+    fn grandchildren<'a>(
+        &'a self,
+        child_name: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Element> {
+        return self
+            .children
+            .iter()
+            .filter(move |c| c.name == child_name)
+            .flat_map(move |c| c.children.iter().filter(move |g| g.name == name));
+    }
+
+    /// Returns all descendants with the specified name (depth-first).
+    fn descendants<'a>(&'a self, name: &str, result: &mut Vec<&'a Element>) {
+        for child in &self.children {
+            if child.name == name {
+                result.push(child);
+            }
+            child.descendants(name, result);
+        }
+    }
+}
+
+/// Minimal XML parser: elements and attributes only. Text, comments, processing
+/// instructions, CDATA, and DOCTYPE are skipped.
+struct XmlParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn parse_document(text: &'a str) -> Result<Element, String> {
+        let mut parser = XmlParser { text, pos: 0 };
+        let mut root = Element {
+            name: String::new(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        };
+        parser.parse_children(&mut root)?;
+        return Ok(root);
+    }
+
+    fn rest(&self) -> &'a str {
+        return &self.text[self.pos..];
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), String> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.pos += i + end.len();
+                return Ok(());
+            }
+            None => return Err(format!("unterminated markup (expected \"{}\")", end)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn read_name(&mut self) -> Result<String, String> {
+        let rest = self.rest();
+        let len = rest
+            .find(|ch: char| ch.is_whitespace() || ch == '=' || ch == '/' || ch == '>')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(format!("expected XML name at offset {}", self.pos));
+        }
+        self.pos += len;
+
+        // Strip namespace prefix (e.g. "win:"), except for attribute values.
+        let name = &rest[..len];
+        return Ok(String::from(match name.rfind(':') {
+            Some(i) if !name.starts_with("xmlns") => &name[i + 1..],
+            _ => name,
+        }));
+    }
+
+    /// Parses child elements until end-of-input or the end tag of `parent`.
+    fn parse_children(&mut self, parent: &mut Element) -> Result<(), String> {
+        loop {
+            match self.rest().find('<') {
+                None => {
+                    return if parent.name.is_empty() {
+                        Ok(())
+                    } else {
+                        Err(format!("missing </{}>", parent.name))
+                    };
+                }
+                Some(i) => self.pos += i,
+            }
+
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.skip_past("]]>")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if rest.starts_with("<!") {
+                self.skip_past(">")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let name = self.read_name()?;
+                self.skip_past(">")?;
+                if name != parent.name {
+                    return Err(format!(
+                        "unexpected </{}> (expected </{}>)",
+                        name, parent.name
+                    ));
+                }
+                return Ok(());
+            } else {
+                self.pos += 1;
+                let mut element = Element {
+                    name: self.read_name()?,
+                    attributes: Vec::new(),
+                    children: Vec::new(),
+                };
+                loop {
+                    self.skip_whitespace();
+                    let rest = self.rest();
+                    if rest.starts_with("/>") {
+                        self.pos += 2;
+                        break;
+                    } else if rest.starts_with('>') {
+                        self.pos += 1;
+                        self.parse_children(&mut element)?;
+                        break;
+                    }
+
+                    let attr_name = self.read_name()?;
+                    self.skip_whitespace();
+                    if !self.rest().starts_with('=') {
+                        return Err(format!("expected '=' after attribute {}", attr_name));
+                    }
+                    self.pos += 1;
+                    self.skip_whitespace();
+                    let quote = match self.rest().chars().next() {
+                        Some(ch) if ch == '"' || ch == '\'' => ch,
+                        _ => return Err(format!("expected quoted value for {}", attr_name)),
+                    };
+                    self.pos += 1;
+                    let len = match self.rest().find(quote) {
+                        Some(len) => len,
+                        None => return Err(format!("unterminated value for {}", attr_name)),
+                    };
+                    let value = decode_entities(&self.rest()[..len]);
+                    self.pos += len + 1;
+                    element.attributes.push((attr_name, value));
+                }
+                parent.children.push(element);
+            }
+        }
+    }
+}
+
+fn decode_entities(value: &str) -> String {
+    return value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
+
+/// Parses a manifest integer attribute (decimal or 0x hex).
+pub fn parse_int(value: &str) -> Option<u64> {
+    let value = value.trim();
+    return if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else {
+        value.parse().ok()
+    };
+}
+
+/// Converts a name like "RequestStart" or "EVT_REQUEST_START" to "request_start" or
+/// "evt_request_start".
+pub fn to_snake_case(name: &str) -> String {
+    let mut result = String::new();
+    let chars: Vec<char> = name.chars().collect();
+    for (i, &ch) in chars.iter().enumerate() {
+        if ch.is_ascii_alphanumeric() {
+            if ch.is_ascii_uppercase()
+                && i != 0
+                && !result.ends_with('_')
+                && (chars[i - 1].is_ascii_lowercase()
+                    || chars[i - 1].is_ascii_digit()
+                    || chars
+                        .get(i + 1)
+                        .map_or(false, |next| next.is_ascii_lowercase())
+                        && chars[i - 1].is_ascii_uppercase())
+            {
+                result.push('_');
+            }
+            result.push(ch.to_ascii_lowercase());
+        } else if !result.is_empty() && !result.ends_with('_') {
+            result.push('_');
+        }
+    }
+
+    while result.ends_with('_') {
+        result.pop();
+    }
+    if result.is_empty() || result.starts_with(|ch: char| ch.is_ascii_digit()) {
+        result.insert(0, '_');
+    }
+    if matches!(
+        result.as_str(),
+        "as" | "break"
+            | "const"
+            | "continue"
+            | "crate"
+            | "else"
+            | "enum"
+            | "extern"
+            | "false"
+            | "fn"
+            | "for"
+            | "if"
+            | "impl"
+            | "in"
+            | "let"
+            | "loop"
+            | "match"
+            | "mod"
+            | "move"
+            | "mut"
+            | "pub"
+            | "ref"
+            | "return"
+            | "self"
+            | "static"
+            | "struct"
+            | "super"
+            | "trait"
+            | "true"
+            | "type"
+            | "unsafe"
+            | "use"
+            | "where"
+            | "while"
+            | "async"
+            | "await"
+            | "dyn"
+    ) {
+        result.push('_');
+    }
+    return result;
+}
+
+pub fn to_upper_snake_case(name: &str) -> String {
+    return to_snake_case(name).to_ascii_uppercase();
+}
+
+pub fn include_manifest(arg_span: Span, arg_tokens: TokenStream) -> TokenStream {
+    return include_generated(
+        arg_span,
+        arg_tokens,
+        "expected manifest path (string literal relative to the crate root), e.g. include_manifest!(\"src/my_provider.man\")",
+        |path, text| {
+            XmlParser::parse_document(text)
+                .map_err(|e| format!("manifest {}: {}", path, e))
+                .and_then(|root| generate(&root))
+        },
+    );
+}
+
+/// Shared implementation of the `include_*!` macros: reads the file named by the
+/// macro's string literal argument (relative to the crate root) and returns the
+/// tokens for the Rust source code produced by `generate(path, text)`.
+pub fn include_generated(
+    arg_span: Span,
+    arg_tokens: TokenStream,
+    expected_path_message: &str,
+    generate: impl FnOnce(&str, &str) -> Result<String, String>,
+) -> TokenStream {
+    let mut errors = Errors::new();
+    let mut parser = Parser::new(&mut errors, arg_span, arg_tokens);
+    let path = parser.next_string_literal(RequiredLast, expected_path_message);
+
+    let result = match path {
+        None => Err(String::new()),
+        Some((path, _)) => {
+            let full_path = match std::env::var_os("CARGO_MANIFEST_DIR") {
+                Some(dir) => path::Path::new(&dir).join(&path),
+                None => path::PathBuf::from(&path),
+            };
+            fs::read_to_string(&full_path)
+                .map_err(|e| format!("unable to read {}: {}", full_path.display(), e))
+                .and_then(|text| generate(&path, &text))
+                .map(|code| {
+                    // Rebuild if the file changes.
+                    format!(
+                        "const _: &[u8] = ::core::include_bytes!({:?});\n{}",
+                        full_path.display().to_string(),
+                        code
+                    )
+                })
+        }
+    };
+
+    match result {
+        Ok(code) => match TokenStream::from_str(&code) {
+            Ok(tokens) => return tokens,
+            Err(e) => errors.add(arg_span, &format!("generated code did not parse: {}", e)),
+        },
+        Err(message) => {
+            if !message.is_empty() {
+                errors.add(arg_span, &message);
+            }
+        }
+    }
+
+    return errors.into_items();
+}
+
+/// Generates Rust source code for all providers in the manifest.
+fn generate(root: &Element) -> Result<String, String> {
+    let mut providers = Vec::new();
+    root.descendants("provider", &mut providers);
+    if providers.is_empty() {
+        return Err(String::from("no <provider> elements found"));
+    }
+
+    let mut code = String::new();
+    let mut function_names = Vec::new();
+    for provider in providers {
+        generate_provider(provider, &mut code, &mut function_names)?;
+    }
+    return Ok(code);
+}
+
+fn generate_provider(
+    provider: &Element,
+    code: &mut String,
+    function_names: &mut Vec<String>,
+) -> Result<(), String> {
+    let prov_name = provider
+        .attr("name")
+        .ok_or("<provider> is missing name attribute")?;
+    let prov_guid = provider
+        .attr("guid")
+        .ok_or_else(|| format!("provider {} is missing guid attribute", prov_name))?
+        .trim_start_matches('{')
+        .trim_end_matches('}');
+    let prov_symbol = to_upper_snake_case(provider.attr("symbol").unwrap_or(prov_name));
+
+    // Keywords
+
+    let mut keywords: Vec<(&str, u64)> = Vec::from(WIN_KEYWORDS);
+    let mut keyword_decls = String::new();
+    for keyword in provider.grandchildren("keywords", "keyword") {
+        let name = keyword.attr("name").unwrap_or_default();
+        let mask = keyword
+            .attr("mask")
+            .and_then(parse_int)
+            .ok_or_else(|| format!("keyword {} has invalid mask", name))?;
+        keywords.push((name, mask));
+        let _ = write!(
+            keyword_decls,
+            ", {} = 0x{:X}",
+            to_upper_snake_case(name),
+            mask
+        );
+    }
+
+    // Levels, tasks, opcodes
+
+    let mut levels: Vec<(&str, u8)> = Vec::from(WIN_LEVELS);
+    for level in provider.grandchildren("levels", "level") {
+        let name = level.attr("name").unwrap_or_default();
+        let value = level
+            .attr("value")
+            .and_then(parse_int)
+            .filter(|v| *v <= 255)
+            .ok_or_else(|| format!("level {} has invalid value", name))?;
+        levels.push((name, value as u8));
+    }
+
+    let mut opcodes: Vec<(&str, &str, u8)> = Vec::new(); // (task, name, value)
+    for (task_name, opcode) in provider
+        .grandchildren("opcodes", "opcode")
+        .map(|o| ("", o))
+        .chain(provider.grandchildren("tasks", "task").flat_map(|t| {
+            t.grandchildren("opcodes", "opcode")
+                .map(move |o| (t.attr("name").unwrap_or_default(), o))
+        }))
+    {
+        let name = opcode.attr("name").unwrap_or_default();
+        let value = opcode
+            .attr("value")
+            .and_then(parse_int)
+            .filter(|v| *v <= 255)
+            .ok_or_else(|| format!("opcode {} has invalid value", name))?;
+        opcodes.push((task_name, name, value as u8));
+    }
+
+    let mut tasks: Vec<(&str, u16)> = Vec::new();
+    for task in provider.grandchildren("tasks", "task") {
+        let name = task.attr("name").unwrap_or_default();
+        let value = task
+            .attr("value")
+            .and_then(parse_int)
+            .filter(|v| *v <= 65535)
+            .ok_or_else(|| format!("task {} has invalid value", name))?;
+        tasks.push((name, value as u16));
+    }
+
+    let _ = writeln!(
+        code,
+        "::tracelogging::define_provider!({}, {:?}, id({:?}){}{}{});",
+        prov_symbol,
+        prov_name,
+        prov_guid,
+        if keyword_decls.is_empty() {
+            ""
+        } else {
+            ", keywords("
+        },
+        keyword_decls.trim_start_matches(", "),
+        if keyword_decls.is_empty() { "" } else { ")" },
+    );
+
+    // Events
+
+    for event in provider.grandchildren("events", "event") {
+        let id = event
+            .attr("value")
+            .and_then(parse_int)
+            .filter(|v| *v <= 65535)
+            .ok_or("<event> has missing or invalid value attribute")?;
+        let version = event
+            .attr("version")
+            .map_or(Some(0), parse_int)
+            .filter(|v| *v <= 255)
+            .ok_or_else(|| format!("event {} has invalid version", id))?;
+
+        let level = match event.attr("level") {
+            None => 0,
+            Some(name) => {
+                levels
+                    .iter()
+                    .find(|l| l.0 == name)
+                    .ok_or_else(|| format!("event {}: unknown level {}", id, name))?
+                    .1
+            }
+        };
+
+        let task_name = event.attr("task").unwrap_or_default();
+        let task = if task_name.is_empty() {
+            0
+        } else {
+            tasks
+                .iter()
+                .find(|t| t.0 == task_name)
+                .ok_or_else(|| format!("event {}: unknown task {}", id, task_name))?
+                .1
+        };
+
+        let opcode = match event.attr("opcode") {
+            None => 0,
+            Some(name) => opcodes
+                .iter()
+                .find(|o| o.1 == name && o.0 == task_name)
+                .or_else(|| opcodes.iter().find(|o| o.1 == name && o.0.is_empty()))
+                .map(|o| o.2)
+                .or_else(|| WIN_OPCODES.iter().find(|o| o.0 == name).map(|o| o.1))
+                .ok_or_else(|| format!("event {}: unknown opcode {}", id, name))?,
+        };
+
+        let mut keyword = 0u64;
+        for name in event
+            .attr("keywords")
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            keyword |= keywords
+                .iter()
+                .find(|k| k.0 == name)
+                .ok_or_else(|| format!("event {}: unknown keyword {}", id, name))?
+                .1;
+        }
+
+        // Event name: symbol, else task + opcode, else id.
+        let event_name = match event.attr("symbol") {
+            Some(symbol) => String::from(symbol),
+            None if !task_name.is_empty() => {
+                let opcode_name = event.attr("opcode").unwrap_or_default();
+                String::from(task_name) + opcode_name.trim_start_matches("win:")
+            }
+            None => format!("Event{}", id),
+        };
+
+        let function_name = to_snake_case(&event_name);
+        if function_names.contains(&function_name) {
+            return Err(format!(
+                "event {}: duplicate function name {}",
+                id, function_name
+            ));
+        }
+        function_names.push(function_name.clone());
+
+        // Fields
+
+        let mut params = String::new();
+        let mut fields = String::new();
+        if let Some(tid) = event.attr("template") {
+            let template = provider
+                .grandchildren("templates", "template")
+                .find(|t| t.attr("tid") == Some(tid))
+                .ok_or_else(|| format!("event {}: unknown template {}", id, tid))?;
+            let mut param_names: Vec<String> = Vec::new();
+            for data in &template.children {
+                let data_name = data.attr("name").unwrap_or_default();
+                if data.name != "data" {
+                    return Err(format!(
+                        "template {}: <{}> {} is not supported",
+                        tid, data.name, data_name
+                    ));
+                }
+
+                let in_type = data.attr("inType").unwrap_or_default();
+                let in_type_short = in_type.trim_start_matches("win:");
+                let (mut field_type, mut param_type) = IN_TYPES
+                    .iter()
+                    .find(|t| t.0 == in_type_short)
+                    .map(|t| (String::from(t.1), String::from(t.2)))
+                    .ok_or_else(|| format!("template {}: unsupported inType {}", tid, in_type))?;
+                if let Some(out_type) = data.attr("outType") {
+                    if let Some(t) = OUT_TYPES
+                        .iter()
+                        .find(|t| t.0 == in_type_short && t.1 == out_type)
+                    {
+                        field_type = String::from(t.2);
+                    }
+                }
+                if data.attr("count").is_some() {
+                    if !SLICE_TYPES.contains(&field_type.as_str()) {
+                        return Err(format!(
+                            "template {}: arrays of {} are not supported",
+                            tid, in_type
+                        ));
+                    }
+                    field_type += "_slice";
+                    param_type = format!("&[{}]", param_type.trim_start_matches('&'));
+                }
+
+                let mut param_name = to_snake_case(data_name);
+                while param_names.contains(&param_name) {
+                    param_name.push('_');
+                }
+
+                let _ = write!(params, "{}: {}, ", param_name, param_type);
+                let _ = write!(fields, ", {}({:?}, {})", field_type, data_name, param_name);
+                param_names.push(param_name);
+            }
+        }
+
+        let _ = writeln!(
+            code,
+            "/// Writes the `{}` event (id {}, version {}) of provider `{}`.\n\
+            #[allow(dead_code, clippy::too_many_arguments)]\n\
+            pub fn {}({}) -> u32 {{\n    \
+            ::tracelogging::write_event!({}, {:?}, id_version({}, {}), level({}), keyword(0x{:X}), opcode({}), task({}){})\n\
+            }}",
+            event_name,
+            id,
+            version,
+            prov_name,
+            function_name,
+            params.trim_end_matches(", "),
+            prov_symbol,
+            event_name,
+            id,
+            version,
+            level,
+            keyword,
+            opcode,
+            task,
+            fields,
+        );
+    }
+
+    return Ok(());
+}
@@ -131,6 +131,96 @@ impl<'a> Parser<'a> {
         return result;
     }
 
+    /// Reads a byte string literal then moves to the next comma or the end-of-stream.
+    /// Emits "expected ..." error for other tokens encountered before comma or end-of-stream.
+    pub fn next_byte_string_literal(
+        &mut self,
+        constraints: ArgConstraints,
+        error_message: &str,
+    ) -> Option<(Vec<u8>, Span)> {
+        let result;
+        match self.move_next() {
+            Some(TokenTree::Literal(literal)) => {
+                let lit_str = literal.to_string();
+                if lit_str.len() < 3 || !lit_str.starts_with("b\"") || !lit_str.ends_with('"') {
+                    self.errors.add(literal.span(), error_message);
+                    if self.skip_to_comma(TokenTree::Literal(literal)) {
+                        self.comma_after_item(constraints);
+                    }
+                    result = None;
+                } else {
+                    match unescape(&lit_str[2..lit_str.len() - 1]) {
+                        Some(unescaped) if unescaped.chars().all(|ch| (ch as u32) < 256) => {
+                            result = Some((
+                                unescaped.chars().map(|ch| ch as u8).collect(),
+                                literal.span(),
+                            ));
+                        }
+                        _ => {
+                            self.errors
+                                .add(literal.span(), "unsupported escape sequence");
+                            result = None;
+                        }
+                    }
+                    self.next_comma(constraints);
+                }
+            }
+            Some(token) => {
+                self.errors.add(token.span(), error_message);
+                if self.skip_to_comma(token) {
+                    self.comma_after_item(constraints);
+                }
+                result = None;
+            }
+            None => {
+                self.eos_before_item(constraints, error_message);
+                result = None;
+            }
+        }
+        return result;
+    }
+
+    /// Reads a u8 integer literal then moves to the next comma or the end-of-stream.
+    /// Emits "expected ..." error for other tokens encountered before comma or end-of-stream.
+    pub fn next_u8_literal(
+        &mut self,
+        constraints: ArgConstraints,
+        error_message: &str,
+    ) -> Option<(u8, Span)> {
+        let result;
+        match self.move_next() {
+            Some(TokenTree::Literal(literal)) => {
+                let lit_str = literal.to_string().replace('_', "");
+                let lit_str = lit_str.strip_suffix("u8").unwrap_or(&lit_str);
+                let value = if let Some(hex) = lit_str.strip_prefix("0x") {
+                    u8::from_str_radix(hex, 16)
+                } else {
+                    lit_str.parse::<u8>()
+                };
+                match value {
+                    Ok(value) => result = Some((value, literal.span())),
+                    Err(_) => {
+                        self.errors.add(literal.span(), error_message);
+                        result = None;
+                    }
+                }
+                self.next_comma(constraints);
+            }
+            Some(token) => {
+                self.errors.add(token.span(), error_message);
+                if self.skip_to_comma(token) {
+                    self.comma_after_item(constraints);
+                }
+                result = None;
+            }
+            None => {
+                self.eos_before_item(constraints, error_message);
+                result = None;
+            }
+        }
+        return result;
+    }
+
     /// Reads tokens to the next comma or the end-of-stream.
     /// Emits an error if no tokens or if ';'.
     pub fn next_tokens(&mut self, constraints: ArgConstraints, error_message: &str) -> TokenStream {
@@ -439,7 +529,7 @@ fn unescape_u(dest: &mut String, it: &mut str::Chars) -> bool {
     return false; // Too many digits
 }
 
-fn unescape(src: &str) -> Option<String> {
+pub fn unescape(src: &str) -> Option<String> {
     let mut dest = String::with_capacity(src.len());
     let mut it = src.chars();
     while let Some(ch) = it.next() {
@@ -452,6 +542,8 @@ fn unescape(src: &str) -> Option<String> {
                 Some('r') => dest.push('\r'),
                 Some('t') => dest.push('\t'),
                 Some('\\') => dest.push('\\'),
+                Some('"') => dest.push('"'),
+                Some('\'') => dest.push('\''),
                 Some('x') => {
                     if !unescape_x(&mut dest, &mut it) {
                         return None;
@@ -24,7 +24,7 @@ impl ProviderGenerator {
         };
     }
 
-    pub fn generate(&mut self, provider: ProviderInfo) -> TokenStream {
+    pub fn generate(&mut self, mut provider: ProviderInfo) -> TokenStream {
         // Reserve space for size.
         let mut meta = Vec::<u8>::new();
         meta.push(0);
@@ -42,51 +42,93 @@ impl ProviderGenerator {
             meta.extend_from_slice(&group_id.to_bytes_le());
         }
 
+        for (trait_type, trait_data) in &provider.traits {
+            // Custom provider trait
+            let trait_len = 3 + trait_data.len(); // sizeof(size) + sizeof(type) + data
+            meta.push(trait_len as u8);
+            meta.push((trait_len >> 8) as u8);
+            meta.push(*trait_type);
+            meta.extend_from_slice(trait_data);
+        }
+
         meta[0] = meta.len() as u8;
         meta[1] = (meta.len() >> 8) as u8;
 
-        let id_fields = provider.id.to_fields();
+        let id_path;
+        let id_args: TokenStream = if let Some(id_expr) = provider.id_expr.take() {
+            // &::tracelogging::Guid::parse_const(id_expr)
+            id_path = GUID_PARSE_CONST_PATH;
+            id_expr
+        } else {
+            // &::tracelogging::Guid::from_fields(d0, d1, d2, *b"d3...")
+            let id_fields = provider.id.to_fields();
+            id_path = GUID_FROM_FIELDS_PATH;
+            self.tree3
+                .add_literal(Literal::u32_unsuffixed(id_fields.0))
+                .add_punct(",")
+                .add_literal(Literal::u16_unsuffixed(id_fields.1))
+                .add_punct(",")
+                .add_literal(Literal::u16_unsuffixed(id_fields.2))
+                .add_punct(",")
+                .add_punct("*")
+                .add_literal(Literal::byte_string(&id_fields.3))
+                .drain()
+                .collect()
+        };
 
-        let prov_tokens = self
-            .prov_tree
-            // static PROVIDER: ::tracelogging::Provider = unsafe { ... };
+        // const _TLG_ID: ::tracelogging::Guid = ::tracelogging::Guid::...(...);
+        // Evaluated outside of the unsafe block so that an id(...) expression cannot
+        // call unsafe functions without an unsafe block of its own.
+        let id_const: TokenStream = self
+            .tree3
+            .add_const_from_tokens(
+                TLG_ID_CONST,
+                GUID_PATH,
+                self.tree1.add_path_call(id_path, id_args).drain(),
+            )
+            .drain()
+            .collect();
+
+        self.prov_tree
+            // static PROVIDER: ::tracelogging::Provider = { const _TLG_ID...; unsafe { ... } };
             .add_ident("static")
             .add_token(provider.symbol.clone())
             .add_punct(":")
             .add_path(PROVIDER_PATH)
             .add_punct("=")
-            .add_ident("unsafe")
             .add_group_curly(
                 self.tree1
-                    .add_path_call(
-                        // ::tracelogging::_internal::provider_new(
-                        PROVIDER_NEW_PATH,
+                    .add_tokens(id_const)
+                    .add_ident("unsafe")
+                    .add_group_curly(
                         self.tree2
-                            // b"EncodedProviderMetadata...",
-                            .add_literal(Literal::byte_string(&meta))
-                            .add_punct(",")
-                            // &::tracelogging::Guid::from_fields(d0, d1, d2, *b"d3...")
-                            .add_punct("&")
                             .add_path_call(
-                                GUID_FROM_FIELDS_PATH,
+                                // ::tracelogging::_internal::provider_new(
+                                PROVIDER_NEW_PATH,
                                 self.tree3
-                                    .add_literal(Literal::u32_unsuffixed(id_fields.0))
-                                    .add_punct(",")
-                                    .add_literal(Literal::u16_unsuffixed(id_fields.1))
-                                    .add_punct(",")
-                                    .add_literal(Literal::u16_unsuffixed(id_fields.2))
+                                    // b"EncodedProviderMetadata...",
+                                    .add_literal(Literal::byte_string(&meta))
                                     .add_punct(",")
-                                    .add_punct("*")
-                                    .add_literal(Literal::byte_string(&id_fields.3))
+                                    .add_punct("&")
+                                    .add_ident(TLG_ID_CONST)
                                     .drain(),
                             )
                             .drain(),
                     )
                     .drain(),
             )
-            .add_punct(";")
-            .drain()
-            .collect();
+            .add_punct(";");
+
+        // ::tracelogging::_tlg_export_provider!(PROVIDER);
+        self.prov_tree
+            .add_path(EXPORT_PROVIDER_PATH)
+            .add_punct("!")
+            .add_group_paren([provider.symbol.clone().into()])
+            .add_punct(";");
+
+        self.add_provider_module(&provider);
+
+        let prov_tokens = self.prov_tree.drain().collect();
 
         if provider.debug {
             println!("{}", prov_tokens);
@@ -94,4 +136,137 @@ impl ProviderGenerator {
 
         return prov_tokens;
     }
+
+    /// Adds a module with the same name as the provider symbol:
+    ///
+    /// ```ignore
+    /// #[allow(non_snake_case, dead_code)]
+    /// mod PROVIDER {
+    ///     #[deprecated(note = "...")] // If warn_default_keyword().
+    ///     pub const DEFAULT_KEYWORD: u64 = VALUE; // Used by events with no keyword.
+    ///     pub const KEYWORD_NAME: u64 = VALUE; // For each keyword.
+    ///     pub const KEYWORDS: &[(&str, u64)] = &[("NAME", VALUE), ...];
+    ///     pub const KEYWORDS_MASK: u64 = MASK | STATE_KEYWORD | MARKER_KEYWORD; // Or u64::MAX.
+    /// }
+    /// ```
+    fn add_provider_module(&mut self, provider: &ProviderInfo) {
+        let symbol = &provider.symbol;
+
+        if provider.warn_default_keyword {
+            // #[deprecated(note = "...")]
+            self.tree1.add_outer_attribute(
+                "deprecated",
+                self.tree2
+                    .add_ident("note")
+                    .add_punct("=")
+                    .add_literal(Literal::string(
+                        "event uses the provider's default keyword; \
+                        specify keyword(...) or keyword_none()",
+                    ))
+                    .drain(),
+            );
+        }
+
+        // pub const DEFAULT_KEYWORD: u64 = VALUE;
+        self.tree1
+            .add_ident("pub")
+            .add_ident("const")
+            .add_ident(DEFAULT_KEYWORD_CONST)
+            .add_punct(":")
+            .add_path(U64_PATH)
+            .add_punct("=")
+            .add_literal(Literal::u64_unsuffixed(
+                provider.default_keyword.unwrap_or(1),
+            ))
+            .add_punct(";");
+
+        for (name, value) in &provider.keywords {
+            // pub const KEYWORD_NAME: u64 = VALUE;
+            self.tree1
+                .push_span(name.span())
+                .add_ident("pub")
+                .add_ident("const")
+                .add_ident(&format!("KEYWORD_{}", name))
+                .add_punct(":")
+                .add_path(U64_PATH)
+                .add_punct("=")
+                .add_literal(Literal::u64_unsuffixed(*value))
+                .add_punct(";")
+                .pop_span();
+
+            // ("NAME", VALUE),
+            self.tree2
+                .add_group_paren(
+                    self.tree3
+                        .add_literal(Literal::string(&name.to_string()))
+                        .add_punct(",")
+                        .add_literal(Literal::u64_unsuffixed(*value))
+                        .drain(),
+                )
+                .add_punct(",");
+        }
+
+        // pub const KEYWORDS: &[(&str, u64)] = &[...];
+        let keywords_type = self
+            .tree3
+            .add_punct("&")
+            .add_path(STR_PATH)
+            .add_punct(",")
+            .add_path(U64_PATH)
+            .drain()
+            .collect();
+        self.tree1
+            .add_ident("pub")
+            .add_ident("const")
+            .add_ident("KEYWORDS")
+            .add_punct(":")
+            .add_punct("&")
+            .add_group_square([TokenTree::from(Group::new(
+                Delimiter::Parenthesis,
+                keywords_type,
+            ))])
+            .add_punct("=")
+            .add_punct("&")
+            .add_group_square(self.tree2.drain())
+            .add_punct(";");
+
+        // pub const KEYWORDS_MASK: u64 = MASK | Provider::STATE_KEYWORD | Provider::MARKER_KEYWORD;
+        self.tree1
+            .add_ident("pub")
+            .add_ident("const")
+            .add_ident(KEYWORDS_MASK_CONST)
+            .add_punct(":")
+            .add_path(U64_PATH)
+            .add_punct("=");
+        match provider.keywords_mask {
+            None => {
+                self.tree1.add_path(U64_PATH).add_path(&["MAX"]);
+            }
+            Some(mask) => {
+                self.tree1
+                    .add_literal(Literal::u64_unsuffixed(mask))
+                    .add_punct("|")
+                    .add_path(PROVIDER_PATH)
+                    .add_path(&["STATE_KEYWORD"])
+                    .add_punct("|")
+                    .add_path(PROVIDER_PATH)
+                    .add_path(&["MARKER_KEYWORD"]);
+            }
+        }
+        self.tree1.add_punct(";");
+
+        // #[allow(non_snake_case, dead_code)] mod PROVIDER { ... }
+        self.prov_tree
+            .add_outer_attribute(
+                "allow",
+                [
+                    Ident::new("non_snake_case", symbol.span()).into(),
+                    Punct::new(',', Spacing::Alone).into(),
+                    Ident::new("dead_code", symbol.span()).into(),
+                ],
+            )
+            .add_ident("mod")
+            .add_token(symbol.clone())
+            .add_group_curly(self.tree1.drain());
+    }
 }
@@ -11,7 +11,21 @@ pub struct ProviderInfo {
     pub symbol: Ident,
     pub name: String,
     pub id: Guid,
+    /// If not None, the provider id is `Guid::parse_const(id_expr)`, e.g. when the id
+    /// is specified as `id(env!("MY_PROVIDER_GUID"))`.
+    pub id_expr: Option<TokenStream>,
     pub group_id: Option<Guid>,
+    /// Additional provider traits: (trait type, trait data).
+    pub traits: Vec<(u8, Vec<u8>)>,
+    /// Named keywords: (name, value).
+    pub keywords: Vec<(Ident, u64)>,
+    /// Keyword used by events that do not specify a `keyword` option.
+    pub default_keyword: Option<u64>,
+    /// If true, events that rely on the default keyword generate a warning.
+    pub warn_default_keyword: bool,
+    /// The keyword bits that events may use (in addition to the bits reserved for
+    /// state and marker events), or None if events may use any bits.
+    pub keywords_mask: Option<u64>,
     pub debug: bool,
 }
 
@@ -22,12 +36,20 @@ impl ProviderInfo {
     ) -> Result<ProviderInfo, TokenStream> {
         let mut prov_id_set = false;
         let mut group_name_set = false;
+        let mut id_namespace: Option<Guid> = None; // Set by id_from_name_v5("NAMESPACE").
+        let mut keywords_mask_span = arg_span;
         let mut errors = Errors::new();
         let mut root_parser = Parser::new(&mut errors, arg_span, arg_tokens);
         let mut prov = ProviderInfo {
             name: String::new(),
             id: Guid::zero(),
+            id_expr: None,
             group_id: None,
+            traits: Vec::new(),
+            keywords: Vec::new(),
+            default_keyword: None,
+            warn_default_keyword: false,
+            keywords_mask: None,
             debug: false,
             symbol: Ident::new("x", arg_span),
         };
@@ -48,6 +70,9 @@ impl ProviderInfo {
             "expected string literal for provider name, e.g. define_provider!(MY_PROVIDER, \"MyCompany.MyComponent\")",
         ) {
             prov.name = prov_name;
+            if prov.name.is_empty() {
+                root_parser.errors().add(span, "provider name must not be empty");
+            }
             if prov.name.len() >= 32768 {
                 root_parser.errors().add(span, "provider name.len() must be less than 32KB");
             }
@@ -56,7 +81,12 @@ impl ProviderInfo {
             }
         }
 
-        // provider options (id or group_id)
+        // provider options (id, group_id, or trait)
+
+        const EXPECTED_GUID: &str =
+            "expected \"GUID\", e.g. \"20cf46dd-3b90-476c-94e9-4e74bbc30e31\"";
+        const EXPECTED_GUID_OR_EXPR: &str =
+            "expected \"GUID\" or a const &str expression, e.g. env!(\"MY_PROVIDER_GUID\")";
 
         while let ArgResult::Option(option_name_ident, mut option_args_parser) =
             root_parser.next_arg(false)
@@ -72,7 +102,41 @@ impl ProviderInfo {
                         errors.add(option_name_ident.span(), "id already set");
                     }
                     prov_id_set = true;
-                    &mut prov.id
+
+                    // id("GUID") is parsed now. id(expression), e.g. id(env!("GUID")),
+                    // is parsed by Guid::parse_const during const evaluation.
+                    let id_tokens: Vec<TokenTree> = option_args_parser
+                        .next_tokens(RequiredLast, EXPECTED_GUID_OR_EXPR)
+                        .into_iter()
+                        .collect();
+                    match id_tokens.as_slice() {
+                        [TokenTree::Literal(lit)] if lit.to_string().starts_with('"') => {
+                            let mut lit_parser = Parser::new(
+                                option_args_parser.errors(),
+                                lit.span(),
+                                TokenStream::from(id_tokens[0].clone()),
+                            );
+                            if let Some((id_str, id_span)) =
+                                lit_parser.next_string_literal(RequiredLast, EXPECTED_GUID)
+                            {
+                                if let Some(id_val) = Guid::try_parse(&id_str) {
+                                    prov.id = id_val;
+                                } else {
+                                    lit_parser.errors().add(id_span, EXPECTED_GUID);
+                                }
+                            }
+                        }
+                        [] => {}
+                        _ => prov.id_expr = Some(id_tokens.into_iter().collect()),
+                    }
+                    continue;
+                }
+                "id_from_name_v5" => {
+                    if prov_id_set {
+                        errors.add(option_name_ident.span(), "id already set");
+                    }
+                    prov_id_set = true;
+                    id_namespace.insert(Guid::zero())
                 }
                 "group_id" | "groupid" => {
                     if prov.group_id.is_some() {
@@ -80,6 +144,80 @@ impl ProviderInfo {
                     }
                     prov.group_id.insert(Guid::zero())
                 }
+                "trait" => {
+                    if let Some((trait_type, type_span)) = option_args_parser.next_u8_literal(
+                        RequiredNotLast,
+                        "expected trait type (integer literal 0..255), e.g. trait(128, b\"data\")",
+                    ) {
+                        if let Some((trait_data, _)) = option_args_parser.next_byte_string_literal(
+                            RequiredLast,
+                            "expected trait data (byte string literal), e.g. trait(128, b\"data\")",
+                        ) {
+                            if trait_type == 1 {
+                                // EtwProviderTraitTypeGroup
+                                option_args_parser.errors().add(
+                                    type_span,
+                                    "trait type 1 is the provider group trait, use group_id(\"GUID\") instead",
+                                );
+                            }
+                            prov.traits.push((trait_type, trait_data));
+                        }
+                    }
+                    continue;
+                }
+                "keywords" => {
+                    prov.parse_keywords(&mut option_args_parser);
+                    continue;
+                }
+                "default_keyword" => {
+                    const EXPECTED_DEFAULT_KEYWORD: &str =
+                        "expected u64 integer literal, e.g. default_keyword(0x0)";
+                    if prov.default_keyword.is_some() {
+                        errors.add(option_name_ident.span(), "default_keyword already set");
+                    }
+                    let tokens: Vec<TokenTree> = option_args_parser
+                        .next_tokens(RequiredLast, EXPECTED_DEFAULT_KEYWORD)
+                        .into_iter()
+                        .collect();
+                    match tokens.as_slice() {
+                        [] => {}
+                        [TokenTree::Literal(value)] if parse_u64_literal(value).is_some() => {
+                            prov.default_keyword = parse_u64_literal(value);
+                        }
+                        _ => option_args_parser
+                            .errors()
+                            .add(tokens[0].span(), EXPECTED_DEFAULT_KEYWORD),
+                    }
+                    continue;
+                }
+                "warn_default_keyword" => {
+                    prov.warn_default_keyword = true;
+                    continue;
+                }
+                "keywords_mask" => {
+                    const EXPECTED_KEYWORDS_MASK: &str =
+                        "expected non-zero u64 integer literal, e.g. keywords_mask(0x00FF)";
+                    if prov.keywords_mask.is_some() {
+                        errors.add(option_name_ident.span(), "keywords_mask already set");
+                    }
+                    let tokens: Vec<TokenTree> = option_args_parser
+                        .next_tokens(RequiredLast, EXPECTED_KEYWORDS_MASK)
+                        .into_iter()
+                        .collect();
+                    match tokens.as_slice() {
+                        [] => {}
+                        [TokenTree::Literal(value)]
+                            if parse_u64_literal(value).map_or(false, |mask| mask != 0) =>
+                        {
+                            prov.keywords_mask = parse_u64_literal(value);
+                            keywords_mask_span = value.span();
+                        }
+                        _ => option_args_parser
+                            .errors()
+                            .add(tokens[0].span(), EXPECTED_KEYWORDS_MASK),
+                    }
+                    continue;
+                }
                 "group_name" | "groupname" => {
                     if group_name_set {
                         errors.add(option_name_ident.span(), "group_name already set");
@@ -100,14 +238,12 @@ impl ProviderInfo {
                 _ => {
                     errors.add(
                         option_name_ident.span(),
-                        "expected id(\"GUID\") or group_id(\"GUID\")",
+                        "expected id(\"GUID\"), id_from_name_v5(\"NAMESPACE_GUID\"), group_id(\"GUID\"), trait(type, b\"data\"), keywords(NAME = 0x1, ...), default_keyword(0x1), warn_default_keyword(), or keywords_mask(0xFF)",
                     );
                     continue;
                 }
             };
 
-            const EXPECTED_GUID: &str =
-                "expected \"GUID\", e.g. \"20cf46dd-3b90-476c-94e9-4e74bbc30e31\"";
             if let Some((id_str, id_span)) =
                 option_args_parser.next_string_literal(RequiredLast, EXPECTED_GUID)
             {
@@ -119,7 +255,39 @@ impl ProviderInfo {
             }
         }
 
-        if !prov_id_set {
+        let traits_len = prov
+            .traits
+            .iter()
+            .fold(0, |len, (_, data)| len + 3 + data.len());
+        if 2 + prov.name.len() + 1 + 19 + traits_len > 65535 {
+            errors.add(arg_span, "provider traits are too large (limit is 64KB)");
+        }
+
+        if let Some(mask) = prov.keywords_mask {
+            for (name, value) in &prov.keywords {
+                if value & !mask != 0 {
+                    errors.add(
+                        name.span(),
+                        &format!(
+                            "keyword {} value 0x{:X} is outside keywords_mask 0x{:X}",
+                            name, value, mask
+                        ),
+                    );
+                }
+            }
+
+            let default_keyword = prov.default_keyword.unwrap_or(1);
+            if default_keyword & !mask != 0 {
+                errors.add(
+                    keywords_mask_span,
+                    &format!("default keyword 0x{:X} is outside keywords_mask 0x{:X}, specify default_keyword(...)", default_keyword, mask),
+                );
+            }
+        }
+
+        if let Some(namespace) = &id_namespace {
+            prov.id = Guid::from_name_v5(namespace, &prov.name);
+        } else if !prov_id_set {
             prov.id = Guid::from_name(&prov.name);
         }
 
@@ -129,4 +297,68 @@ impl ProviderInfo {
             Err(errors.into_items())
         };
     }
+
+    /// Parses `NAME = VALUE, ...` for the keywords option. Reports an error if a name
+    /// is repeated, if a value is 0, or if a value overlaps a previous keyword's value.
+    fn parse_keywords(&mut self, parser: &mut Parser) {
+        const EXPECTED_KEYWORD: &str =
+            "expected NAME = VALUE (VALUE must be a u64 integer literal), e.g. keywords(NETWORK = 0x2)";
+        loop {
+            let tokens: Vec<TokenTree> = parser
+                .next_tokens(Optional, EXPECTED_KEYWORD)
+                .into_iter()
+                .collect();
+            let (name, value, span) = match tokens.as_slice() {
+                [] => break,
+                [TokenTree::Ident(name), TokenTree::Punct(eq), TokenTree::Literal(value)]
+                    if eq.as_char() == '=' =>
+                {
+                    match parse_u64_literal(value) {
+                        Some(value_int) => (name.clone(), value_int, value.span()),
+                        None => {
+                            parser.errors().add(value.span(), EXPECTED_KEYWORD);
+                            continue;
+                        }
+                    }
+                }
+                _ => {
+                    parser.errors().add(tokens[0].span(), EXPECTED_KEYWORD);
+                    continue;
+                }
+            };
+
+            let name_str = name.to_string();
+            if value == 0 {
+                parser.errors().add(span, "keyword value must not be 0");
+            } else if let Some((other, _)) =
+                self.keywords.iter().find(|k| k.0.to_string() == name_str)
+            {
+                parser
+                    .errors()
+                    .add(name.span(), &format!("keyword {} already defined", other));
+            } else if let Some((other, _)) = self.keywords.iter().find(|k| k.1 & value != 0) {
+                parser.errors().add(
+                    span,
+                    &format!("keyword {} value overlaps keyword {}", name_str, other),
+                );
+            } else {
+                self.keywords.push((name, value));
+            }
+        }
+    }
+}
+
+/// Parses an integer literal (decimal, 0x hex, or 0b binary, optionally with `_`
+/// separators and a `u64` suffix) as a u64.
+pub fn parse_u64_literal(literal: &Literal) -> Option<u64> {
+    let lit_str = literal.to_string().replace('_', "");
+    let lit_str = lit_str.strip_suffix("u64").unwrap_or(&lit_str);
+    let value = if let Some(hex) = lit_str.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16)
+    } else if let Some(bin) = lit_str.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2)
+    } else {
+        lit_str.parse::<u64>()
+    };
+    return value.ok();
 }
@@ -96,24 +96,41 @@ pub const OUTTYPE_ENUMS: &[&str] = &[
 
 pub const TLG_LEVEL_CONST: &str = "_TLG_LEVEL";
 pub const TLG_KEYWORD_CONST: &str = "_TLG_KEYWORD";
+pub const TLG_COMPILED_IN_CONST: &str = "_TLG_COMPILED_IN";
+pub const MIN_LEVEL_ENV_VAR: &str = "TRACELOGGING_MIN_LEVEL";
+pub const KEYWORD_MASK_ENV_VAR: &str = "TRACELOGGING_KEYWORD_MASK";
+pub const OPTION_ENV_PATH: &[&str] = &["core", "option_env"];
+pub const DEFAULT_KEYWORD_CONST: &str = "DEFAULT_KEYWORD";
+pub const KEYWORDS_MASK_CONST: &str = "KEYWORDS_MASK";
 pub const TLG_TAG_CONST: &str = "_TLG_TAG";
+pub const TLG_COUNT_CONST: &str = "_TLG_COUNT";
+pub const TLG_PROTOCOL_CONST: &str = "_TLG_PROTOCOL";
+pub const TLG_SCHEMA_CONST: &str = "_TLG_SCHEMA";
+pub const TLG_ID_CONST: &str = "_TLG_ID";
 pub const TLG_PROV_VAR: &str = "_tlg_prov";
 pub const TLG_ARG_VAR: &str = "_tlg_arg";
 pub const TLG_WRITE_FUNC: &str = "_tlg_write";
 pub const TLG_META_TYPE: &str = "_TlgMeta";
 pub const TLG_META_VAR: &str = "_tlg_meta";
 pub const TLG_META_CONST: &str = "_TLG_META";
+pub const TLG_EVENT_IR_CONST: &str = "_TLG_EVENT_IR";
+pub const TLG_SAMPLE_RATE_CONST: &str = "_TLG_SAMPLE_RATE";
+pub const TLG_SAMPLE_STATIC: &str = "_TLG_SAMPLE";
 pub const TLG_LENGTHS_VAR: &str = "_tlg_lengths";
 pub const TLG_DESC_VAR: &str = "_tlg_desc";
 pub const TLG_DESC_CONST: &str = "_TLG_DESC";
 pub const TLG_ACTIVITY_ID_VAR: &str = "_tlg_aid";
 pub const TLG_RELATED_ID_VAR: &str = "_tlg_rid";
 pub const TLG_DUR_VAR: &str = "_tlg_dur";
+pub const TLG_SCOPE_VAR: &str = "_tlg_scope";
+pub const TLG_SCOPE_AID_VAR: &str = "_tlg_scope_aid";
+pub const TLG_NEW_ACTIVITY_ID_VAR: &str = "_tlg_new_aid";
 
 pub const BORROW_BORROW_PATH: &[&str] = &["core", "borrow", "Borrow", "borrow"];
 pub const ASREF_PATH: &[&str] = &["core", "convert", "AsRef"];
 pub const IDENTITY_PATH: &[&str] = &["core", "convert", "identity"];
 pub const BOOL_PATH: &[&str] = &["core", "primitive", "bool"];
+pub const CHAR_PATH: &[&str] = &["core", "primitive", "char"];
 pub const F32_PATH: &[&str] = &["core", "primitive", "f32"];
 pub const F64_PATH: &[&str] = &["core", "primitive", "f64"];
 pub const I8_PATH: &[&str] = &["core", "primitive", "i8"];
@@ -122,7 +139,9 @@ pub const I32_PATH: &[&str] = &["core", "primitive", "i32"];
 pub const I64_PATH: &[&str] = &["core", "primitive", "i64"];
 pub const ISIZE_PATH: &[&str] = &["core", "primitive", "isize"];
 pub const U8_PATH: &[&str] = &["core", "primitive", "u8"];
+pub const STR_PATH: &[&str] = &["core", "primitive", "str"];
 pub const U16_PATH: &[&str] = &["core", "primitive", "u16"];
+pub const U16_TO_LE_BYTES_PATH: &[&str] = &["core", "primitive", "u16", "to_le_bytes"];
 pub const U32_PATH: &[&str] = &["core", "primitive", "u32"];
 pub const U64_PATH: &[&str] = &["core", "primitive", "u64"];
 pub const USIZE_PATH: &[&str] = &["core", "primitive", "usize"];
@@ -130,6 +149,7 @@ pub const ASSERT_PATH: &[&str] = &["core", "assert"];
 pub const OPTION_PATH: &[&str] = &["core", "option", "Option"];
 pub const OPTION_NONE_PATH: &[&str] = &["core", "option", "Option", "None"];
 pub const OPTION_SOME_PATH: &[&str] = &["core", "option", "Option", "Some"];
+pub const OPTION_IS_NONE_PATH: &[&str] = &["core", "option", "Option", "is_none"];
 pub const RESULT_OK_PATH: &[&str] = &["core", "result", "Result", "Ok"];
 pub const RESULT_ERR_PATH: &[&str] = &["core", "result", "Result", "Err"];
 pub const SYSTEMTIME_DURATION_SINCE_PATH: &[&str] =
@@ -141,20 +161,47 @@ pub const INTYPE_PATH: &[&str] = &["tracelogging", "InType"];
 pub const LEVEL_PATH: &[&str] = &["tracelogging", "Level"];
 pub const LEVEL_VERBOSE_PATH: &[&str] = &["tracelogging", "Level", "Verbose"];
 pub const OPCODE_INFO_PATH: &[&str] = &["tracelogging", "Opcode", "Info"];
+pub const OPCODE_ACTIVITY_START_PATH: &[&str] = &["tracelogging", "Opcode", "ActivityStart"];
+pub const OPCODE_ACTIVITY_STOP_PATH: &[&str] = &["tracelogging", "Opcode", "ActivityStop"];
 pub const OUTTYPE_PATH: &[&str] = &["tracelogging", "OutType"];
 pub const OUTTYPE_FROM_INT_PATH: &[&str] = &["tracelogging", "OutType", "from_int"];
 pub const GUID_PATH: &[&str] = &["tracelogging", "Guid"];
 pub const GUID_FROM_FIELDS_PATH: &[&str] = &["tracelogging", "Guid", "from_fields"];
+pub const GUID_PARSE_CONST_PATH: &[&str] = &["tracelogging", "Guid", "parse_const"];
 pub const PROVIDER_PATH: &[&str] = &["tracelogging", "Provider"];
+pub const EMIT_POLICY_PATH: &[&str] = &["tracelogging", "EmitPolicy"];
+pub const EMIT_POLICY_NEW_PATH: &[&str] = &["tracelogging", "EmitPolicy", "new"];
+pub const EMIT_POLICY_CHECK_PATH: &[&str] = &["tracelogging", "EmitPolicy", "check"];
+pub const PROVIDER_CREATE_ACTIVITY_ID_PATH: &[&str] =
+    &["tracelogging", "Provider", "create_activity_id"];
+pub const PROVIDER_EVENT_ID_FROM_NAME_PATH: &[&str] =
+    &["tracelogging", "Provider", "event_id_from_name"];
+pub const ACTIVITY_SCOPE_NEW_PATH: &[&str] = &["tracelogging", "ActivityScope", "new"];
+pub const FIELD_TAG_NEW_PATH: &[&str] = &["tracelogging", "FieldTag", "new"];
+pub const FIELD_TAGS_PATH: &[&str] = &["tracelogging", "FieldTags"];
+pub const FIELD_TAGS_NEW_PATH: &[&str] = &["tracelogging", "FieldTags", "new"];
 
 pub const PROVIDER_NEW_PATH: &[&str] = &["tracelogging", "_internal", "provider_new"];
+pub const PROVIDER_EVENT_FILTER_PATH: &[&str] =
+    &["tracelogging", "_internal", "provider_event_filter"];
+pub const EVENT_TARGET_PATH: &[&str] = &["tracelogging", "_internal", "EventTarget"];
+pub const PROVIDER_NOTE_WRITE_PATH: &[&str] = &["tracelogging", "_internal", "provider_note_write"];
 pub const PROVIDER_WRITE_TRANSFER_PATH: &[&str] =
     &["tracelogging", "_internal", "provider_write_transfer"];
-pub const META_AS_BYTES_PATH: &[&str] = &["tracelogging", "_internal", "meta_as_bytes"];
+pub const META_TO_BYTES_PATH: &[&str] = &["tracelogging", "_internal", "meta_to_bytes"];
 pub const TAG_ENCODE_PATH: &[&str] = &["tracelogging", "_internal", "tag_encode"];
 pub const TAG_SIZE_PATH: &[&str] = &["tracelogging", "_internal", "tag_size"];
+pub const COMPILED_IN_PATH: &[&str] = &["tracelogging", "_internal", "compiled_in"];
+pub const CUSTOM_SCHEMA_PATH: &[&str] = &["tracelogging", "_internal", "custom_schema"];
+pub const EXPORT_EVENT_PATH: &[&str] = &["tracelogging", "_tlg_export_event"];
+pub const EXPORT_PROVIDER_PATH: &[&str] = &["tracelogging", "_tlg_export_provider"];
+pub const ERROR_CHAIN_DATA_PATH: &[&str] = &["tracelogging", "_tlg_error_chain_data"];
+pub const PTR_LEN_SIZE_PATH: &[&str] = &["tracelogging", "_internal", "ptr_len_size"];
 pub const COUNTED_SIZE_PATH: &[&str] = &["tracelogging", "_internal", "counted_size"];
+pub const CAPPED_SLICE_PATH: &[&str] = &["tracelogging", "_internal", "capped_slice"];
+pub const CAPPED_LENGTH_PATH: &[&str] = &["tracelogging", "_internal", "capped_length"];
 pub const SLICE_COUNT_PATH: &[&str] = &["tracelogging", "_internal", "slice_count"];
+pub const OPTION_AS_SLICE_PATH: &[&str] = &["tracelogging", "_internal", "option_as_slice"];
 pub const FILETIME_FROM_DURATION_AFTER_PATH: &[&str] = &[
     "tracelogging",
     "_internal",
@@ -165,6 +212,18 @@ pub const FILETIME_FROM_DURATION_BEFORE_PATH: &[&str] = &[
     "_internal",
     "filetime_from_duration_before_1970",
 ];
+pub const FMT_BUFFER_NEW_PATH: &[&str] = &["tracelogging", "_internal", "FmtBuffer", "new"];
+pub const STR_SLICE_BUFFER_NEW8_PATH: &[&str] =
+    &["tracelogging", "_internal", "StrSliceBuffer", "new8"];
+pub const STR_SLICE_BUFFER_NEW16_PATH: &[&str] =
+    &["tracelogging", "_internal", "StrSliceBuffer", "new16"];
+pub const CALLER_ADDRESS_PATH: &[&str] = &[
+    "tracelogging",
+    "_internal",
+    "ProviderContext",
+    "caller_address",
+];
+pub const CALL_FIELD_CLOSURE_PATH: &[&str] = &["tracelogging", "_internal", "call_field_closure"];
 pub const FILETIME_FROM_TIME32_PATH: &[&str] =
     &["tracelogging", "_internal", "filetime_from_time32"];
 pub const FILETIME_FROM_TIME64_PATH: &[&str] =
@@ -174,6 +233,7 @@ pub const EVENTDESC_PATH: &[&str] = &["tracelogging", "_internal", "EventDescrip
 pub const EVENTDESC_FROM_PARTS_PATH: &[&str] =
     &["tracelogging", "_internal", "EventDescriptor", "from_parts"];
 
+pub const DATADESC_PATH: &[&str] = &["tracelogging", "_internal", "EventDataDescriptor"];
 pub const DATADESC_FROM_RAW_BYTES_PATH: &[&str] = &[
     "tracelogging",
     "_internal",
@@ -210,3 +270,9 @@ pub const DATADESC_FROM_COUNTED_PATH: &[&str] = &[
     "EventDataDescriptor",
     "from_counted",
 ];
+pub const DATADESC_FROM_PTR_LEN_PATH: &[&str] = &[
+    "tracelogging",
+    "_internal",
+    "EventDataDescriptor",
+    "from_ptr_len",
+];